@@ -45,6 +45,9 @@ fn build_cli_args() -> Command {
         .subcommand(g3bench::target::rustls::command())
         .subcommand(g3bench::target::dns::command())
         .subcommand(g3bench::target::keyless::command())
+        .subcommand(g3bench::target::icap::command())
+        .subcommand(g3bench::target::smtp::command())
+        .subcommand(g3bench::target::imap::command())
 }
 
 fn main() -> anyhow::Result<()> {
@@ -111,6 +114,15 @@ fn main() -> anyhow::Result<()> {
             g3bench::target::keyless::COMMAND => {
                 g3bench::target::keyless::run(&proc_args, sub_args).await
             }
+            g3bench::target::icap::COMMAND => {
+                g3bench::target::icap::run(&proc_args, sub_args).await
+            }
+            g3bench::target::smtp::COMMAND => {
+                g3bench::target::smtp::run(&proc_args, sub_args).await
+            }
+            g3bench::target::imap::COMMAND => {
+                g3bench::target::imap::run(&proc_args, sub_args).await
+            }
             cmd => Err(anyhow!("invalid subcommand {}", cmd)),
         }
     })
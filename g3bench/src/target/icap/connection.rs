@@ -0,0 +1,37 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use tokio::io::{AsyncRead, AsyncWrite, BufReader};
+
+use g3_io_ext::{LimitedReader, LimitedWriter};
+
+pub(super) type BoxIcapWriter = Box<dyn AsyncWrite + Send + Unpin>;
+pub(super) type BoxIcapReader = Box<dyn AsyncRead + Send + Unpin>;
+pub(super) type BoxIcapConnection = (BoxIcapReader, BoxIcapWriter);
+
+pub(super) struct SavedIcapConnection {
+    pub(super) reader: BufReader<LimitedReader<BoxIcapReader>>,
+    pub(super) writer: LimitedWriter<BoxIcapWriter>,
+}
+
+impl SavedIcapConnection {
+    pub(super) fn new(
+        reader: BufReader<LimitedReader<BoxIcapReader>>,
+        writer: LimitedWriter<BoxIcapWriter>,
+    ) -> Self {
+        SavedIcapConnection { reader, writer }
+    }
+}
@@ -0,0 +1,506 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::io::{self, Write};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context};
+use clap::{value_parser, Arg, ArgAction, ArgMatches, Command, ValueHint};
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+use url::Url;
+
+use g3_io_ext::AsyncStream;
+use g3_types::collection::{SelectiveVec, WeightedValue};
+use g3_types::net::{OpensslClientConfig, OpensslClientConfigBuilder, UpstreamAddr};
+
+use super::{BoxIcapConnection, IcapRuntimeStats, ProcArgs};
+use crate::module::openssl::{AppendOpensslArgs, OpensslTlsClientArgs};
+use crate::module::proxy_protocol::{AppendProxyProtocolArgs, ProxyProtocolArgs};
+use crate::module::socket::{AppendSocketArgs, SocketArgs};
+
+const ICAP_ARG_URL: &str = "url";
+const ICAP_ARG_METHOD: &str = "method";
+const ICAP_ARG_HTTP_REQUEST_HEADER: &str = "http-request-header";
+const ICAP_ARG_HTTP_RESPONSE_HEADER: &str = "http-response-header";
+const ICAP_ARG_HTTP_BODY: &str = "http-body";
+const ICAP_ARG_HTTP_BODY_SIZE: &str = "http-body-size";
+const ICAP_ARG_PREVIEW: &str = "preview";
+const ICAP_ARG_ALLOW_204: &str = "allow-204";
+const ICAP_ARG_NO_KEEPALIVE: &str = "no-keepalive";
+const ICAP_ARG_OK_STATUS: &str = "ok-status";
+const ICAP_ARG_TIMEOUT: &str = "timeout";
+const ICAP_ARG_HEADER_SIZE: &str = "header-size";
+const ICAP_ARG_CONNECT_TIMEOUT: &str = "connect-timeout";
+
+const ICAP_DEFAULT_PORT: u16 = 1344;
+const ICAPS_DEFAULT_PORT: u16 = 11344;
+const DEFAULT_HTTP_HOST: &str = "www.example.com";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum IcapMethod {
+    Options,
+    Reqmod,
+    Respmod,
+}
+
+impl IcapMethod {
+    fn as_str(&self) -> &'static str {
+        match self {
+            IcapMethod::Options => "OPTIONS",
+            IcapMethod::Reqmod => "REQMOD",
+            IcapMethod::Respmod => "RESPMOD",
+        }
+    }
+}
+
+impl FromStr for IcapMethod {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_str() {
+            "OPTIONS" => Ok(IcapMethod::Options),
+            "REQMOD" => Ok(IcapMethod::Reqmod),
+            "RESPMOD" => Ok(IcapMethod::Respmod),
+            _ => Err(anyhow!("unsupported ICAP method {s}")),
+        }
+    }
+}
+
+pub(super) struct BenchIcapArgs {
+    pub(super) method: IcapMethod,
+    service_url: Url,
+    http_req_header: Vec<u8>,
+    http_rsp_header: Vec<u8>,
+    pub(super) http_body: Vec<u8>,
+    pub(super) preview_size: Option<usize>,
+    allow_204: bool,
+    pub(super) no_keepalive: bool,
+    pub(super) ok_status: Option<u16>,
+    pub(super) timeout: Duration,
+    pub(super) max_header_size: usize,
+    pub(super) connect_timeout: Duration,
+
+    socket: SocketArgs,
+    tls: OpensslTlsClientArgs,
+    proxy_protocol: ProxyProtocolArgs,
+
+    target: UpstreamAddr,
+    target_addrs: Option<SelectiveVec<WeightedValue<SocketAddr>>>,
+}
+
+impl BenchIcapArgs {
+    fn new(method: IcapMethod, mut url: Url) -> anyhow::Result<Self> {
+        let mut tls = OpensslTlsClientArgs::default();
+        let default_port = match url.scheme().to_ascii_lowercase().as_str() {
+            "icap" => ICAP_DEFAULT_PORT,
+            "icaps" => {
+                tls.config = Some(OpensslClientConfigBuilder::with_cache_for_one_site());
+                ICAPS_DEFAULT_PORT
+            }
+            _ => return Err(anyhow!("unsupported ICAP URL scheme: {}", url.scheme())),
+        };
+        if url.port().is_none() {
+            url.set_port(Some(default_port))
+                .map_err(|_| anyhow!("failed to set default port for url {url}"))?;
+        }
+        let target = UpstreamAddr::try_from(&url)?;
+
+        Ok(BenchIcapArgs {
+            method,
+            service_url: url,
+            http_req_header: Vec::new(),
+            http_rsp_header: Vec::new(),
+            http_body: Vec::new(),
+            preview_size: None,
+            allow_204: false,
+            no_keepalive: false,
+            ok_status: None,
+            timeout: Duration::from_secs(30),
+            max_header_size: 8192,
+            connect_timeout: Duration::from_secs(15),
+            socket: SocketArgs::default(),
+            tls,
+            proxy_protocol: ProxyProtocolArgs::default(),
+            target,
+            target_addrs: None,
+        })
+    }
+
+    pub(super) async fn resolve_target_address(
+        &mut self,
+        proc_args: &ProcArgs,
+    ) -> anyhow::Result<()> {
+        let addrs = proc_args.resolve(&self.target).await?;
+        self.target_addrs = Some(addrs);
+        Ok(())
+    }
+
+    pub(super) async fn new_icap_connection(
+        &self,
+        stats: &IcapRuntimeStats,
+        proc_args: &ProcArgs,
+    ) -> anyhow::Result<BoxIcapConnection> {
+        let stream = self
+            .new_tcp_connection(proc_args)
+            .await
+            .context(format!("failed to connect to icap server {}", self.target))?;
+
+        if let Some(tls_client) = &self.tls.client {
+            self.tls_connect_to_target(tls_client, stream, stats).await
+        } else {
+            let (r, w) = stream.into_split();
+            Ok((Box::new(r), Box::new(w)))
+        }
+    }
+
+    async fn new_tcp_connection(&self, proc_args: &ProcArgs) -> anyhow::Result<TcpStream> {
+        let addrs = self
+            .target_addrs
+            .as_ref()
+            .ok_or_else(|| anyhow!("no target addr set"))?;
+        let peer = *proc_args.select_peer(addrs);
+
+        let mut stream = self.socket.tcp_connect_to(peer).await?;
+
+        if let Some(data) = self.proxy_protocol.data() {
+            stream
+                .write_all(data) // no need to flush data
+                .await
+                .map_err(|e| anyhow!("failed to write proxy protocol data: {e:?}"))?;
+        }
+
+        Ok(stream)
+    }
+
+    async fn tls_connect_to_target<S>(
+        &self,
+        tls_client: &OpensslClientConfig,
+        stream: S,
+        stats: &IcapRuntimeStats,
+    ) -> anyhow::Result<BoxIcapConnection>
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        let tls_stream = self
+            .tls
+            .connect_target(tls_client, stream, &self.target)
+            .await?;
+
+        stats.ssl_session.add_total();
+        if tls_stream.ssl().session_reused() {
+            stats.ssl_session.add_reused();
+        }
+
+        let (r, w) = tls_stream.into_split();
+        Ok((Box::new(r), Box::new(w)))
+    }
+
+    /// Get the size of the body data that should be sent in the preview stage,
+    /// and whether it is the whole body
+    pub(super) fn preview_data_size(&self) -> Option<(usize, bool)> {
+        if self.method == IcapMethod::Options || self.http_body.is_empty() {
+            return None;
+        }
+        self.preview_size.map(|size| {
+            if size >= self.http_body.len() {
+                (self.http_body.len(), true)
+            } else {
+                (size, false)
+            }
+        })
+    }
+
+    fn has_body(&self) -> bool {
+        self.method != IcapMethod::Options && !self.http_body.is_empty()
+    }
+
+    pub(super) fn write_request_header<W: Write>(&self, buf: &mut W) -> io::Result<()> {
+        write!(
+            buf,
+            "{} {} ICAP/1.0\r\nHost: {}\r\n",
+            self.method.as_str(),
+            self.service_url,
+            self.target
+        )?;
+
+        let http_req_hdr_len = self.http_req_header.len();
+        let body_entity = if self.has_body() {
+            "req-body"
+        } else {
+            "null-body"
+        };
+        match self.method {
+            IcapMethod::Options => buf.write_all(b"Encapsulated: null-body=0\r\n")?,
+            IcapMethod::Reqmod => write!(
+                buf,
+                "Encapsulated: req-hdr=0, {body_entity}={http_req_hdr_len}\r\n"
+            )?,
+            IcapMethod::Respmod => {
+                let body_entity = if self.has_body() {
+                    "res-body"
+                } else {
+                    "null-body"
+                };
+                write!(
+                    buf,
+                    "Encapsulated: req-hdr=0, res-hdr={http_req_hdr_len}, {body_entity}={}\r\n",
+                    http_req_hdr_len + self.http_rsp_header.len()
+                )?
+            }
+        }
+
+        if self.allow_204 {
+            buf.write_all(b"Allow: 204\r\n")?;
+        }
+        if let Some((size, _)) = self.preview_data_size() {
+            write!(buf, "Preview: {size}\r\n")?;
+        }
+        if self.no_keepalive {
+            buf.write_all(b"Connection: close\r\n")?;
+        }
+        buf.write_all(b"\r\n")?;
+
+        match self.method {
+            IcapMethod::Options => {}
+            IcapMethod::Reqmod => buf.write_all(&self.http_req_header)?,
+            IcapMethod::Respmod => {
+                buf.write_all(&self.http_req_header)?;
+                buf.write_all(&self.http_rsp_header)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn set_default_http_header(&mut self) {
+        let body_len = self.http_body.len();
+        if self.http_req_header.is_empty() {
+            let host = DEFAULT_HTTP_HOST;
+            self.http_req_header = if self.method == IcapMethod::Reqmod && body_len > 0 {
+                format!(
+                    "POST http://{host}/ HTTP/1.1\r\nHost: {host}\r\nContent-Length: {body_len}\r\n\r\n"
+                )
+            } else {
+                format!("GET http://{host}/ HTTP/1.1\r\nHost: {host}\r\n\r\n")
+            }
+            .into_bytes();
+        }
+        if self.http_rsp_header.is_empty() && self.method == IcapMethod::Respmod {
+            self.http_rsp_header = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/octet-stream\r\nContent-Length: {body_len}\r\n\r\n"
+            )
+            .into_bytes();
+        }
+    }
+}
+
+fn load_http_header(path: &Path) -> anyhow::Result<Vec<u8>> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| anyhow!("failed to read file {}: {e}", path.display()))?;
+    let mut header = Vec::with_capacity(content.len() + 4);
+    for line in content.lines() {
+        if line.is_empty() {
+            break;
+        }
+        header.extend_from_slice(line.as_bytes());
+        header.extend_from_slice(b"\r\n");
+    }
+    if header.is_empty() {
+        return Err(anyhow!("no http header found in file {}", path.display()));
+    }
+    header.extend_from_slice(b"\r\n");
+    Ok(header)
+}
+
+pub(super) fn add_icap_args(app: Command) -> Command {
+    app.arg(
+        Arg::new(ICAP_ARG_URL)
+            .help("ICAP service url, scheme should be icap or icaps")
+            .required(true)
+            .num_args(1),
+    )
+    .arg(
+        Arg::new(ICAP_ARG_METHOD)
+            .value_name("METHOD")
+            .short('m')
+            .long(ICAP_ARG_METHOD)
+            .num_args(1)
+            .value_parser(["OPTIONS", "REQMOD", "RESPMOD"])
+            .default_value("OPTIONS"),
+    )
+    .arg(
+        Arg::new(ICAP_ARG_HTTP_REQUEST_HEADER)
+            .help("File that contains the encapsulated HTTP request header")
+            .value_name("HEADER FILE")
+            .long(ICAP_ARG_HTTP_REQUEST_HEADER)
+            .num_args(1)
+            .value_hint(ValueHint::FilePath)
+            .value_parser(value_parser!(PathBuf)),
+    )
+    .arg(
+        Arg::new(ICAP_ARG_HTTP_RESPONSE_HEADER)
+            .help("File that contains the encapsulated HTTP response header")
+            .value_name("HEADER FILE")
+            .long(ICAP_ARG_HTTP_RESPONSE_HEADER)
+            .num_args(1)
+            .value_hint(ValueHint::FilePath)
+            .value_parser(value_parser!(PathBuf)),
+    )
+    .arg(
+        Arg::new(ICAP_ARG_HTTP_BODY)
+            .help("File that contains the encapsulated HTTP body")
+            .value_name("BODY FILE")
+            .long(ICAP_ARG_HTTP_BODY)
+            .num_args(1)
+            .value_hint(ValueHint::FilePath)
+            .value_parser(value_parser!(PathBuf))
+            .conflicts_with(ICAP_ARG_HTTP_BODY_SIZE),
+    )
+    .arg(
+        Arg::new(ICAP_ARG_HTTP_BODY_SIZE)
+            .help("Use a generated encapsulated HTTP body with this size")
+            .value_name("SIZE")
+            .long(ICAP_ARG_HTTP_BODY_SIZE)
+            .num_args(1)
+            .conflicts_with(ICAP_ARG_HTTP_BODY),
+    )
+    .arg(
+        Arg::new(ICAP_ARG_PREVIEW)
+            .help("Send a preview of the encapsulated HTTP body with this size")
+            .value_name("SIZE")
+            .long(ICAP_ARG_PREVIEW)
+            .num_args(1),
+    )
+    .arg(
+        Arg::new(ICAP_ARG_ALLOW_204)
+            .help("Allow the ICAP server to respond 204 if no modification is needed")
+            .action(ArgAction::SetTrue)
+            .long(ICAP_ARG_ALLOW_204),
+    )
+    .arg(
+        Arg::new(ICAP_ARG_NO_KEEPALIVE)
+            .help("Disable ICAP keepalive")
+            .action(ArgAction::SetTrue)
+            .long(ICAP_ARG_NO_KEEPALIVE),
+    )
+    .arg(
+        Arg::new(ICAP_ARG_OK_STATUS)
+            .help("Only treat this ICAP status code as success")
+            .value_name("STATUS CODE")
+            .long(ICAP_ARG_OK_STATUS)
+            .num_args(1)
+            .value_parser(value_parser!(u16).range(100..600)),
+    )
+    .arg(
+        Arg::new(ICAP_ARG_TIMEOUT)
+            .value_name("TIMEOUT DURATION")
+            .help("ICAP response timeout")
+            .default_value("30s")
+            .long(ICAP_ARG_TIMEOUT)
+            .num_args(1),
+    )
+    .arg(
+        Arg::new(ICAP_ARG_HEADER_SIZE)
+            .value_name("SIZE")
+            .help("Set max response header size")
+            .long(ICAP_ARG_HEADER_SIZE)
+            .num_args(1),
+    )
+    .arg(
+        Arg::new(ICAP_ARG_CONNECT_TIMEOUT)
+            .value_name("TIMEOUT DURATION")
+            .help("Timeout for connection to next peer")
+            .default_value("15s")
+            .long(ICAP_ARG_CONNECT_TIMEOUT)
+            .num_args(1),
+    )
+    .append_socket_args()
+    .append_openssl_args()
+    .append_proxy_protocol_args()
+}
+
+pub(super) fn parse_icap_args(args: &ArgMatches) -> anyhow::Result<BenchIcapArgs> {
+    let url = if let Some(v) = args.get_one::<String>(ICAP_ARG_URL) {
+        Url::parse(v).context(format!("invalid {ICAP_ARG_URL} value"))?
+    } else {
+        return Err(anyhow!("no ICAP service url set"));
+    };
+    let method = if let Some(v) = args.get_one::<String>(ICAP_ARG_METHOD) {
+        IcapMethod::from_str(v).context(format!("invalid {ICAP_ARG_METHOD} value"))?
+    } else {
+        IcapMethod::Options
+    };
+
+    let mut icap_args = BenchIcapArgs::new(method, url)?;
+
+    if let Some(file) = args.get_one::<PathBuf>(ICAP_ARG_HTTP_REQUEST_HEADER) {
+        icap_args.http_req_header = load_http_header(file)
+            .context(format!("invalid {ICAP_ARG_HTTP_REQUEST_HEADER} value"))?;
+    }
+    if let Some(file) = args.get_one::<PathBuf>(ICAP_ARG_HTTP_RESPONSE_HEADER) {
+        icap_args.http_rsp_header = load_http_header(file)
+            .context(format!("invalid {ICAP_ARG_HTTP_RESPONSE_HEADER} value"))?;
+    }
+    if let Some(file) = args.get_one::<PathBuf>(ICAP_ARG_HTTP_BODY) {
+        icap_args.http_body = std::fs::read(file)
+            .map_err(|e| anyhow!("failed to read body file {}: {e}", file.display()))?;
+    } else if let Some(size) = g3_clap::humanize::get_usize(args, ICAP_ARG_HTTP_BODY_SIZE)? {
+        icap_args.http_body = vec![b'x'; size];
+    }
+    icap_args.set_default_http_header();
+
+    if let Some(size) = g3_clap::humanize::get_usize(args, ICAP_ARG_PREVIEW)? {
+        icap_args.preview_size = Some(size);
+    }
+    if args.get_flag(ICAP_ARG_ALLOW_204) {
+        icap_args.allow_204 = true;
+    }
+    if args.get_flag(ICAP_ARG_NO_KEEPALIVE) {
+        icap_args.no_keepalive = true;
+    }
+    if let Some(code) = args.get_one::<u16>(ICAP_ARG_OK_STATUS) {
+        icap_args.ok_status = Some(*code);
+    }
+
+    if let Some(timeout) = g3_clap::humanize::get_duration(args, ICAP_ARG_TIMEOUT)? {
+        icap_args.timeout = timeout;
+    }
+    if let Some(header_size) = g3_clap::humanize::get_usize(args, ICAP_ARG_HEADER_SIZE)? {
+        icap_args.max_header_size = header_size;
+    }
+    if let Some(timeout) = g3_clap::humanize::get_duration(args, ICAP_ARG_CONNECT_TIMEOUT)? {
+        icap_args.connect_timeout = timeout;
+    }
+
+    icap_args
+        .socket
+        .parse_args(args)
+        .context("invalid socket config")?;
+    icap_args
+        .tls
+        .parse_tls_args(args)
+        .context("invalid tls config")?;
+    icap_args
+        .proxy_protocol
+        .parse_args(args)
+        .context("invalid proxy protocol config")?;
+
+    Ok(icap_args)
+}
@@ -0,0 +1,224 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use tokio::io::{AsyncBufRead, AsyncReadExt};
+
+use g3_http::{HttpBodyReader, HttpHeaderLine};
+use g3_io_ext::LimitedBufReadExt;
+
+pub(super) struct IcapResponse {
+    pub(super) code: u16,
+    keep_alive: bool,
+    hdr_len: usize,
+    has_body: bool,
+}
+
+impl IcapResponse {
+    pub(super) async fn parse<R>(reader: &mut R, max_header_size: usize) -> anyhow::Result<Self>
+    where
+        R: AsyncBufRead + Unpin,
+    {
+        let mut rsp = IcapResponse {
+            code: 0,
+            keep_alive: true,
+            hdr_len: 0,
+            has_body: false,
+        };
+
+        let mut line_buf = Vec::<u8>::with_capacity(1024);
+        let mut header_size = 0;
+        loop {
+            if header_size >= max_header_size {
+                return Err(anyhow!("too large response header"));
+            }
+            line_buf.clear();
+            let max_len = max_header_size - header_size;
+            let (found, nr) = reader
+                .limited_read_until(b'\n', max_len, &mut line_buf)
+                .await
+                .map_err(|e| anyhow!("read response header failed: {e:?}"))?;
+            if nr == 0 {
+                return Err(anyhow!("connection closed by remote"));
+            }
+            if !found {
+                return if nr < max_len {
+                    Err(anyhow!("connection closed by remote"))
+                } else {
+                    Err(anyhow!("too large response header"))
+                };
+            }
+            header_size += nr;
+
+            if header_size == nr {
+                rsp.parse_status_line(&line_buf)?;
+                continue;
+            }
+            if line_buf.as_slice() == b"\r\n" || line_buf.as_slice() == b"\n" {
+                break;
+            }
+            rsp.parse_header_line(&line_buf)?;
+        }
+
+        Ok(rsp)
+    }
+
+    fn parse_status_line(&mut self, line: &[u8]) -> anyhow::Result<()> {
+        let line = std::str::from_utf8(line)
+            .map_err(|e| anyhow!("invalid status line encoding: {e}"))?
+            .trim_end();
+        let Some(left) = line.strip_prefix("ICAP/1.0 ") else {
+            return Err(anyhow!("invalid status line: {line}"));
+        };
+        let code = match left.split_once(' ') {
+            Some((code, _reason)) => code,
+            None => left,
+        };
+        self.code = u16::from_str(code).map_err(|e| anyhow!("invalid status code {code}: {e}"))?;
+        Ok(())
+    }
+
+    fn parse_header_line(&mut self, line: &[u8]) -> anyhow::Result<()> {
+        let header =
+            HttpHeaderLine::parse(line).map_err(|e| anyhow!("invalid header line: {e}"))?;
+
+        match header.name.to_lowercase().as_str() {
+            "connection" => {
+                for v in header.value.split(',') {
+                    if v.trim().eq_ignore_ascii_case("close") {
+                        self.keep_alive = false;
+                    }
+                }
+            }
+            "encapsulated" => {
+                // the last entity is always the body one, and it's offset is the total length
+                // of all the encapsulated header entities
+                for p in header.value.split(',') {
+                    let Some((name, value)) = p.trim().split_once('=') else {
+                        return Err(anyhow!("invalid Encapsulated value: {}", header.value));
+                    };
+                    let offset = usize::from_str(value.trim())
+                        .map_err(|e| anyhow!("invalid Encapsulated offset for {name}: {e}"))?;
+                    match name.trim().to_lowercase().as_str() {
+                        "req-hdr" | "res-hdr" => {}
+                        "req-body" | "res-body" | "opt-body" => {
+                            self.hdr_len = offset;
+                            self.has_body = true;
+                        }
+                        "null-body" => {
+                            self.hdr_len = offset;
+                            self.has_body = false;
+                        }
+                        _ => return Err(anyhow!("invalid Encapsulated entity {name}")),
+                    }
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    #[inline]
+    pub(super) fn keep_alive(&self) -> bool {
+        self.keep_alive
+    }
+
+    pub(super) async fn recv_payload<R>(&self, reader: &mut R) -> anyhow::Result<()>
+    where
+        R: AsyncBufRead + Unpin,
+    {
+        if self.hdr_len > 0 {
+            let mut hdr_reader = (&mut *reader).take(self.hdr_len as u64);
+            let nr = tokio::io::copy(&mut hdr_reader, &mut tokio::io::sink())
+                .await
+                .map_err(|e| anyhow!("failed to read encapsulated header: {e:?}"))?;
+            if nr != self.hdr_len as u64 {
+                return Err(anyhow!(
+                    "connection closed while reading encapsulated header"
+                ));
+            }
+        }
+
+        if self.has_body {
+            let mut body_reader = HttpBodyReader::new_chunked(reader, 2048);
+            tokio::io::copy(&mut body_reader, &mut tokio::io::sink())
+                .await
+                .map_err(|e| anyhow!("failed to read encapsulated body: {e:?}"))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::BufReader;
+
+    #[tokio::test]
+    async fn parse_modified() {
+        let data: &[u8] = b"ICAP/1.0 200 OK\r\n\
+            ISTag: \"test\"\r\n\
+            Encapsulated: res-hdr=0, res-body=19\r\n\
+            \r\n\
+            HTTP/1.1 200 OK\r\n\r\n\
+            5\r\nhello\r\n0\r\n\r\n\
+            ICAP/1.0 204 No Content\r\n";
+        let mut reader = BufReader::new(data);
+        let rsp = IcapResponse::parse(&mut reader, 4096).await.unwrap();
+        assert_eq!(rsp.code, 200);
+        assert!(rsp.keep_alive());
+        assert_eq!(rsp.hdr_len, 19);
+        assert!(rsp.has_body);
+
+        rsp.recv_payload(&mut reader).await.unwrap();
+        let mut left = String::new();
+        reader.read_to_string(&mut left).await.unwrap();
+        assert_eq!(left, "ICAP/1.0 204 No Content\r\n");
+    }
+
+    #[tokio::test]
+    async fn parse_unmodified() {
+        let data: &[u8] = b"ICAP/1.0 204\r\n\
+            Connection: keep-alive, close\r\n\
+            Encapsulated: null-body=0\r\n\
+            \r\n";
+        let mut reader = BufReader::new(data);
+        let rsp = IcapResponse::parse(&mut reader, 4096).await.unwrap();
+        assert_eq!(rsp.code, 204);
+        assert!(!rsp.keep_alive());
+        assert_eq!(rsp.hdr_len, 0);
+        assert!(!rsp.has_body);
+        rsp.recv_payload(&mut reader).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn parse_invalid() {
+        let mut reader = BufReader::new(&b"HTTP/1.1 200 OK\r\n\r\n"[..]);
+        assert!(IcapResponse::parse(&mut reader, 4096).await.is_err());
+
+        let mut reader = BufReader::new(&b"ICAP/1.0 200 OK\r\nEncapsulated: foo=0\r\n\r\n"[..]);
+        assert!(IcapResponse::parse(&mut reader, 4096).await.is_err());
+
+        let mut reader = BufReader::new(&b"ICAP/1.0 200 OK\r\nISTag: \"0123456789\"\r\n\r\n"[..]);
+        assert!(IcapResponse::parse(&mut reader, 24).await.is_err());
+
+        let mut reader = BufReader::new(&b"ICAP/1.0 200 OK\r\n"[..]);
+        assert!(IcapResponse::parse(&mut reader, 4096).await.is_err());
+    }
+}
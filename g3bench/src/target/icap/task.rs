@@ -0,0 +1,320 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context};
+use futures_util::FutureExt;
+use tokio::io::{AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::time::Instant;
+
+use g3_io_ext::{LimitedReader, LimitedWriter};
+
+use super::{
+    BenchIcapArgs, BenchTaskContext, IcapHistogramRecorder, IcapMethod, IcapResponse,
+    IcapRuntimeStats, ProcArgs, SavedIcapConnection,
+};
+use crate::target::BenchError;
+
+pub(super) struct IcapTaskContext {
+    args: Arc<BenchIcapArgs>,
+    proc_args: Arc<ProcArgs>,
+    saved_connection: Option<SavedIcapConnection>,
+    reuse_conn_count: u64,
+
+    runtime_stats: Arc<IcapRuntimeStats>,
+    histogram_recorder: IcapHistogramRecorder,
+
+    req_header: Vec<u8>,
+    preview_data: Vec<u8>,
+    preview_ieof: bool,
+    body_data: Vec<u8>,
+}
+
+fn encode_chunked(data: &[u8], end: &[u8]) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(data.len() + 32);
+    if !data.is_empty() {
+        buf.extend_from_slice(format!("{:x}\r\n", data.len()).as_bytes());
+        buf.extend_from_slice(data);
+        buf.extend_from_slice(b"\r\n");
+    }
+    buf.extend_from_slice(end);
+    buf
+}
+
+impl IcapTaskContext {
+    pub(super) fn new(
+        args: &Arc<BenchIcapArgs>,
+        proc_args: &Arc<ProcArgs>,
+        runtime_stats: &Arc<IcapRuntimeStats>,
+        histogram_recorder: IcapHistogramRecorder,
+    ) -> anyhow::Result<Self> {
+        let mut req_header = Vec::with_capacity(1024);
+        args.write_request_header(&mut req_header)
+            .map_err(|e| anyhow!("failed to generate request header: {e}"))?;
+
+        let mut preview_data = Vec::new();
+        let mut preview_ieof = false;
+        let mut body_data = Vec::new();
+        if args.method != IcapMethod::Options && !args.http_body.is_empty() {
+            if let Some((size, ieof)) = args.preview_data_size() {
+                let (preview, left) = args.http_body.split_at(size);
+                if ieof {
+                    preview_data = encode_chunked(preview, b"0; ieof\r\n\r\n");
+                } else {
+                    preview_data = encode_chunked(preview, b"0\r\n\r\n");
+                    body_data = encode_chunked(left, b"0\r\n\r\n");
+                }
+                preview_ieof = ieof;
+            } else {
+                body_data = encode_chunked(&args.http_body, b"0\r\n\r\n");
+            }
+        }
+
+        Ok(IcapTaskContext {
+            args: Arc::clone(args),
+            proc_args: Arc::clone(proc_args),
+            saved_connection: None,
+            reuse_conn_count: 0,
+            runtime_stats: Arc::clone(runtime_stats),
+            histogram_recorder,
+            req_header,
+            preview_data,
+            preview_ieof,
+            body_data,
+        })
+    }
+
+    async fn fetch_connection(&mut self) -> anyhow::Result<SavedIcapConnection> {
+        if let Some(mut c) = self.saved_connection.take() {
+            let mut buf = [0u8; 4];
+            if c.reader.read(&mut buf).now_or_never().is_none() {
+                // no eof, reuse the old connection
+                self.reuse_conn_count += 1;
+                return Ok(c);
+            }
+        }
+
+        self.histogram_recorder
+            .record_conn_reuse_count(self.reuse_conn_count);
+        self.reuse_conn_count = 0;
+
+        self.runtime_stats.add_conn_attempt();
+        let (r, w) = match tokio::time::timeout(
+            self.args.connect_timeout,
+            self.args
+                .new_icap_connection(&self.runtime_stats, &self.proc_args),
+        )
+        .await
+        {
+            Ok(Ok(c)) => c,
+            Ok(Err(e)) => return Err(e),
+            Err(_) => return Err(anyhow!("timeout to get new connection")),
+        };
+        self.runtime_stats.add_conn_success();
+
+        let r = LimitedReader::local_limited(
+            r,
+            self.proc_args.tcp_sock_speed_limit.shift_millis,
+            self.proc_args.tcp_sock_speed_limit.max_south,
+            self.runtime_stats.clone(),
+        );
+        let w = LimitedWriter::local_limited(
+            w,
+            self.proc_args.tcp_sock_speed_limit.shift_millis,
+            self.proc_args.tcp_sock_speed_limit.max_north,
+            self.runtime_stats.clone(),
+        );
+        Ok(SavedIcapConnection::new(BufReader::new(r), w))
+    }
+
+    fn save_connection(&mut self, c: SavedIcapConnection) {
+        self.saved_connection = Some(c);
+    }
+
+    async fn recv_response(
+        connection: &mut SavedIcapConnection,
+        timeout: Duration,
+        max_header_size: usize,
+    ) -> anyhow::Result<IcapResponse> {
+        match tokio::time::timeout(
+            timeout,
+            IcapResponse::parse(&mut connection.reader, max_header_size),
+        )
+        .await
+        {
+            Ok(Ok(r)) => Ok(r),
+            Ok(Err(e)) => Err(anyhow!("failed to read response: {e}")),
+            Err(_) => Err(anyhow!("timeout to read response")),
+        }
+    }
+
+    async fn run_with_connection(
+        &mut self,
+        time_started: Instant,
+        connection: &mut SavedIcapConnection,
+    ) -> anyhow::Result<bool> {
+        let keep_alive = !self.args.no_keepalive;
+        let timeout = self.args.timeout;
+        let max_header_size = self.args.max_header_size;
+
+        // send hdr
+        connection
+            .writer
+            .write_all(self.req_header.as_slice())
+            .await
+            .map_err(|e| anyhow!("failed to send request header: {e:?}"))?;
+
+        let rsp = if !self.preview_data.is_empty() {
+            connection
+                .writer
+                .write_all(self.preview_data.as_slice())
+                .await
+                .map_err(|e| anyhow!("failed to send preview data: {e:?}"))?;
+            connection
+                .writer
+                .flush()
+                .await
+                .map_err(|e| anyhow!("failed to flush preview data: {e:?}"))?;
+            let send_hdr_time = time_started.elapsed();
+            self.histogram_recorder.record_send_hdr_time(send_hdr_time);
+
+            let rsp = Self::recv_response(connection, timeout, max_header_size).await?;
+            let preview_time = time_started.elapsed();
+            self.histogram_recorder.record_preview_time(preview_time);
+
+            if rsp.code == 100 {
+                if self.preview_ieof {
+                    return Err(anyhow!("unexpected 100 response after ieof preview"));
+                }
+                self.runtime_stats.add_rsp_continue();
+
+                connection
+                    .writer
+                    .write_all(self.body_data.as_slice())
+                    .await
+                    .map_err(|e| anyhow!("failed to send body data: {e:?}"))?;
+                connection
+                    .writer
+                    .flush()
+                    .await
+                    .map_err(|e| anyhow!("failed to flush body data: {e:?}"))?;
+
+                Self::recv_response(connection, timeout, max_header_size).await?
+            } else {
+                rsp
+            }
+        } else {
+            if !self.body_data.is_empty() {
+                connection
+                    .writer
+                    .write_all(self.body_data.as_slice())
+                    .await
+                    .map_err(|e| anyhow!("failed to send body data: {e:?}"))?;
+            }
+            connection
+                .writer
+                .flush()
+                .await
+                .map_err(|e| anyhow!("failed to flush request data: {e:?}"))?;
+            let send_hdr_time = time_started.elapsed();
+            self.histogram_recorder.record_send_hdr_time(send_hdr_time);
+
+            Self::recv_response(connection, timeout, max_header_size).await?
+        };
+
+        let recv_hdr_time = time_started.elapsed();
+        self.histogram_recorder.record_recv_hdr_time(recv_hdr_time);
+        if let Some(ok_status) = self.args.ok_status {
+            if rsp.code != ok_status {
+                return Err(anyhow!(
+                    "Got rsp code {} while {ok_status} is expected",
+                    rsp.code,
+                ));
+            }
+        }
+        match rsp.code {
+            200 => self.runtime_stats.add_rsp_modified(),
+            204 => self.runtime_stats.add_rsp_unmodified(),
+            code => {
+                if self.args.ok_status.is_none() {
+                    return Err(anyhow!("Got unexpected rsp code {code}"));
+                }
+            }
+        }
+
+        // recv encapsulated data
+        match tokio::time::timeout(self.args.timeout, rsp.recv_payload(&mut connection.reader))
+            .await
+        {
+            Ok(Ok(_)) => {}
+            Ok(Err(e)) => return Err(e),
+            Err(_) => return Err(anyhow!("timeout to read response payload")),
+        }
+
+        Ok(keep_alive & rsp.keep_alive())
+    }
+}
+
+impl BenchTaskContext for IcapTaskContext {
+    fn mark_task_start(&self) {
+        self.runtime_stats.add_task_total();
+        self.runtime_stats.inc_task_alive();
+    }
+
+    fn mark_task_passed(&self) {
+        self.runtime_stats.add_task_passed();
+        self.runtime_stats.dec_task_alive();
+    }
+
+    fn mark_task_failed(&self) {
+        self.runtime_stats.add_task_failed();
+        self.runtime_stats.dec_task_alive();
+    }
+
+    async fn run(&mut self, _task_id: usize, time_started: Instant) -> Result<(), BenchError> {
+        let mut connection = self
+            .fetch_connection()
+            .await
+            .context("connect to icap server failed")
+            .map_err(BenchError::Fatal)?;
+
+        match self
+            .run_with_connection(time_started, &mut connection)
+            .await
+        {
+            Ok(keep_alive) => {
+                let total_time = time_started.elapsed();
+                self.histogram_recorder.record_total_time(total_time);
+
+                if keep_alive {
+                    self.save_connection(connection);
+                } else {
+                    tokio::spawn(async move {
+                        let _ = tokio::time::timeout(
+                            Duration::from_secs(4),
+                            connection.writer.shutdown(),
+                        )
+                        .await;
+                    });
+                }
+                Ok(())
+            }
+            Err(e) => Err(BenchError::Task(e)),
+        }
+    }
+}
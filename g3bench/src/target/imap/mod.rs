@@ -0,0 +1,84 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::sync::Arc;
+
+use clap::{ArgMatches, Command};
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use super::{BenchTarget, BenchTaskContext, ProcArgs};
+
+mod opts;
+use opts::BenchImapArgs;
+
+mod stats;
+use stats::{ImapHistogram, ImapHistogramRecorder, ImapRuntimeStats};
+
+mod task;
+use task::ImapTaskContext;
+
+pub const COMMAND: &str = "imap";
+
+type BoxImapWriter = Box<dyn AsyncWrite + Send + Unpin>;
+type BoxImapReader = Box<dyn AsyncRead + Send + Unpin>;
+type BoxImapConnection = (BoxImapReader, BoxImapWriter);
+
+struct ImapTarget {
+    args: Arc<BenchImapArgs>,
+    proc_args: Arc<ProcArgs>,
+    stats: Arc<ImapRuntimeStats>,
+    histogram: Option<ImapHistogram>,
+    histogram_recorder: ImapHistogramRecorder,
+}
+
+impl BenchTarget<ImapRuntimeStats, ImapHistogram, ImapTaskContext> for ImapTarget {
+    fn new_context(&self) -> anyhow::Result<ImapTaskContext> {
+        ImapTaskContext::new(
+            &self.args,
+            &self.proc_args,
+            &self.stats,
+            self.histogram_recorder.clone(),
+        )
+    }
+
+    fn fetch_runtime_stats(&self) -> Arc<ImapRuntimeStats> {
+        self.stats.clone()
+    }
+
+    fn take_histogram(&mut self) -> Option<ImapHistogram> {
+        self.histogram.take()
+    }
+}
+
+pub fn command() -> Command {
+    opts::add_imap_args(Command::new(COMMAND).about("Run scripted IMAP sessions to fetch mails"))
+}
+
+pub async fn run(proc_args: &Arc<ProcArgs>, cmd_args: &ArgMatches) -> anyhow::Result<()> {
+    let mut imap_args = opts::parse_imap_args(cmd_args)?;
+    imap_args.resolve_target_address(proc_args).await?;
+
+    let (histogram, histogram_recorder) = ImapHistogram::new();
+    let target = ImapTarget {
+        args: Arc::new(imap_args),
+        proc_args: Arc::clone(proc_args),
+        stats: Arc::new(ImapRuntimeStats::default()),
+        histogram: Some(histogram),
+        histogram_recorder,
+    };
+
+    super::run(target, proc_args).await
+}
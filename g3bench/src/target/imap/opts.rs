@@ -0,0 +1,396 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context};
+use clap::{value_parser, Arg, ArgAction, ArgMatches, Command};
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use url::Url;
+
+use g3_io_ext::AsyncStream;
+use g3_openssl::SslStream;
+use g3_types::collection::{SelectiveVec, WeightedValue};
+use g3_types::net::{OpensslClientConfig, OpensslClientConfigBuilder, Proxy, UpstreamAddr};
+
+use super::{BoxImapConnection, ImapRuntimeStats, ProcArgs};
+use crate::module::openssl::{AppendOpensslArgs, OpensslTlsClientArgs};
+use crate::module::proxy_protocol::{AppendProxyProtocolArgs, ProxyProtocolArgs};
+use crate::module::socket::{AppendSocketArgs, SocketArgs};
+
+const IMAP_ARG_TARGET: &str = "target";
+const IMAP_ARG_PROXY: &str = "proxy";
+const IMAP_ARG_TLS: &str = "tls";
+const IMAP_ARG_USERNAME: &str = "username";
+const IMAP_ARG_PASSWORD: &str = "password";
+const IMAP_ARG_MAILBOX: &str = "mailbox";
+const IMAP_ARG_FETCH: &str = "fetch";
+const IMAP_ARG_TIMEOUT: &str = "timeout";
+const IMAP_ARG_CONNECT_TIMEOUT: &str = "connect-timeout";
+
+pub(super) struct BenchImapArgs {
+    target: UpstreamAddr,
+    connect_proxy: Option<Proxy>,
+    pub(super) username: String,
+    pub(super) password: String,
+    pub(super) mailbox: String,
+    pub(super) fetch_count: usize,
+    pub(super) timeout: Duration,
+    pub(super) connect_timeout: Duration,
+
+    socket: SocketArgs,
+    target_tls: OpensslTlsClientArgs,
+    proxy_tls: OpensslTlsClientArgs,
+    proxy_protocol: ProxyProtocolArgs,
+
+    peer_addrs: Option<SelectiveVec<WeightedValue<SocketAddr>>>,
+}
+
+impl BenchImapArgs {
+    fn new(target: UpstreamAddr, tls: bool) -> Self {
+        let mut target_tls = OpensslTlsClientArgs::default();
+        if tls {
+            target_tls.config = Some(OpensslClientConfigBuilder::with_cache_for_one_site());
+        }
+
+        BenchImapArgs {
+            target,
+            connect_proxy: None,
+            username: String::new(),
+            password: String::new(),
+            mailbox: "INBOX".to_string(),
+            fetch_count: 1,
+            timeout: Duration::from_secs(30),
+            connect_timeout: Duration::from_secs(15),
+            socket: SocketArgs::default(),
+            target_tls,
+            proxy_tls: OpensslTlsClientArgs::default(),
+            proxy_protocol: ProxyProtocolArgs::default(),
+            peer_addrs: None,
+        }
+    }
+
+    pub(super) async fn resolve_target_address(
+        &mut self,
+        proc_args: &ProcArgs,
+    ) -> anyhow::Result<()> {
+        let host = if let Some(proxy) = &self.connect_proxy {
+            proxy.peer()
+        } else {
+            &self.target
+        };
+        let addrs = proc_args.resolve(host).await?;
+        self.peer_addrs = Some(addrs);
+        Ok(())
+    }
+
+    async fn new_tcp_connection(&self, proc_args: &ProcArgs) -> anyhow::Result<TcpStream> {
+        let addrs = self
+            .peer_addrs
+            .as_ref()
+            .ok_or_else(|| anyhow!("no peer address set"))?;
+        let peer = *proc_args.select_peer(addrs);
+
+        let mut stream = self.socket.tcp_connect_to(peer).await?;
+
+        if let Some(data) = self.proxy_protocol.data() {
+            stream
+                .write_all(data) // no need to flush data
+                .await
+                .map_err(|e| anyhow!("failed to send proxy protocol data: {e:?}"))?;
+        }
+
+        Ok(stream)
+    }
+
+    pub(super) async fn new_imap_connection(
+        &self,
+        stats: &ImapRuntimeStats,
+        proc_args: &ProcArgs,
+    ) -> anyhow::Result<BoxImapConnection> {
+        if let Some(proxy) = &self.connect_proxy {
+            match proxy {
+                Proxy::Http(http_proxy) => {
+                    let stream = self.new_tcp_connection(proc_args).await.context(format!(
+                        "failed to connect to http proxy {}",
+                        http_proxy.peer()
+                    ))?;
+
+                    if let Some(tls_config) = &self.proxy_tls.client {
+                        let tls_stream = self
+                            .tls_connect_to_proxy(tls_config, http_proxy.peer(), stream, stats)
+                            .await?;
+
+                        let mut buf_stream = BufReader::new(tls_stream);
+
+                        g3_http::connect::client::http_connect_to(
+                            &mut buf_stream,
+                            &http_proxy.auth,
+                            &self.target,
+                        )
+                        .await
+                        .map_err(|e| {
+                            anyhow!("http connect to {} failed: {e}", http_proxy.peer())
+                        })?;
+
+                        self.connect_to_target(buf_stream.into_inner(), stats).await
+                    } else {
+                        let mut buf_stream = BufReader::new(stream);
+
+                        g3_http::connect::client::http_connect_to(
+                            &mut buf_stream,
+                            &http_proxy.auth,
+                            &self.target,
+                        )
+                        .await
+                        .map_err(|e| {
+                            anyhow!("http connect to {} failed: {e}", http_proxy.peer())
+                        })?;
+
+                        self.connect_to_target(buf_stream.into_inner(), stats).await
+                    }
+                }
+                Proxy::Socks4(socks4_proxy) => {
+                    let mut stream = self.new_tcp_connection(proc_args).await.context(format!(
+                        "failed to connect to socks4 proxy {}",
+                        socks4_proxy.peer()
+                    ))?;
+
+                    g3_socks::v4a::client::socks4a_connect_to(&mut stream, &self.target)
+                        .await
+                        .map_err(|e| {
+                            anyhow!("socks4a connect to {} failed: {e}", socks4_proxy.peer())
+                        })?;
+
+                    self.connect_to_target(stream, stats).await
+                }
+                Proxy::Socks5(socks5_proxy) => {
+                    let mut stream = self.new_tcp_connection(proc_args).await.context(format!(
+                        "failed to connect to socks5 proxy {}",
+                        socks5_proxy.peer()
+                    ))?;
+
+                    g3_socks::v5::client::socks5_connect_to(
+                        &mut stream,
+                        &socks5_proxy.auth,
+                        &self.target,
+                    )
+                    .await
+                    .map_err(|e| {
+                        anyhow!("socks5 connect to {} failed: {e}", socks5_proxy.peer())
+                    })?;
+
+                    self.connect_to_target(stream, stats).await
+                }
+            }
+        } else {
+            let stream = self
+                .new_tcp_connection(proc_args)
+                .await
+                .context(format!("failed to connect to target host {}", self.target))?;
+            self.connect_to_target(stream, stats).await
+        }
+    }
+
+    async fn connect_to_target<S>(
+        &self,
+        stream: S,
+        stats: &ImapRuntimeStats,
+    ) -> anyhow::Result<BoxImapConnection>
+    where
+        S: AsyncRead + AsyncWrite + AsyncStream + Unpin + Send + 'static,
+        S::R: AsyncRead + Send + Unpin + 'static,
+        S::W: AsyncWrite + Send + Unpin + 'static,
+    {
+        if let Some(tls_client) = &self.target_tls.client {
+            let tls_stream = self
+                .target_tls
+                .connect_target(tls_client, stream, &self.target)
+                .await?;
+
+            stats.target_ssl_session.add_total();
+            if tls_stream.ssl().session_reused() {
+                stats.target_ssl_session.add_reused();
+            }
+
+            let (r, w) = tls_stream.into_split();
+            Ok((Box::new(r), Box::new(w)))
+        } else {
+            let (r, w) = stream.into_split();
+            Ok((Box::new(r), Box::new(w)))
+        }
+    }
+
+    async fn tls_connect_to_proxy(
+        &self,
+        tls_client: &OpensslClientConfig,
+        peer: &UpstreamAddr,
+        stream: TcpStream,
+        stats: &ImapRuntimeStats,
+    ) -> anyhow::Result<SslStream<TcpStream>> {
+        let tls_stream = self
+            .proxy_tls
+            .connect_target(tls_client, stream, peer)
+            .await?;
+
+        stats.proxy_ssl_session.add_total();
+        if tls_stream.ssl().session_reused() {
+            stats.proxy_ssl_session.add_reused();
+        }
+
+        Ok(tls_stream)
+    }
+}
+
+pub(super) fn add_imap_args(app: Command) -> Command {
+    app.arg(
+        Arg::new(IMAP_ARG_TARGET)
+            .help("Target IMAP server address")
+            .value_name("ADDRESS")
+            .required(true)
+            .num_args(1)
+            .value_parser(value_parser!(UpstreamAddr)),
+    )
+    .arg(
+        Arg::new(IMAP_ARG_PROXY)
+            .value_name("PROXY URL")
+            .short('x')
+            .help("Use a proxy")
+            .long(IMAP_ARG_PROXY)
+            .num_args(1),
+    )
+    .arg(
+        Arg::new(IMAP_ARG_TLS)
+            .help("Use implicit TLS (IMAPS) to the target server")
+            .action(ArgAction::SetTrue)
+            .long(IMAP_ARG_TLS),
+    )
+    .arg(
+        Arg::new(IMAP_ARG_USERNAME)
+            .help("Username to send in LOGIN command")
+            .value_name("USERNAME")
+            .long(IMAP_ARG_USERNAME)
+            .short('u')
+            .required(true)
+            .num_args(1),
+    )
+    .arg(
+        Arg::new(IMAP_ARG_PASSWORD)
+            .help("Password to send in LOGIN command")
+            .value_name("PASSWORD")
+            .long(IMAP_ARG_PASSWORD)
+            .short('p')
+            .required(true)
+            .num_args(1),
+    )
+    .arg(
+        Arg::new(IMAP_ARG_MAILBOX)
+            .help("Mailbox to select")
+            .value_name("MAILBOX")
+            .long(IMAP_ARG_MAILBOX)
+            .num_args(1)
+            .default_value("INBOX"),
+    )
+    .arg(
+        Arg::new(IMAP_ARG_FETCH)
+            .help("Fetch the first N messages in the selected mailbox, 0 to skip")
+            .value_name("COUNT")
+            .long(IMAP_ARG_FETCH)
+            .num_args(1)
+            .value_parser(value_parser!(usize))
+            .default_value("1"),
+    )
+    .arg(
+        Arg::new(IMAP_ARG_TIMEOUT)
+            .value_name("TIMEOUT DURATION")
+            .help("Timeout for each IMAP command")
+            .default_value("30s")
+            .long(IMAP_ARG_TIMEOUT)
+            .num_args(1),
+    )
+    .arg(
+        Arg::new(IMAP_ARG_CONNECT_TIMEOUT)
+            .value_name("TIMEOUT DURATION")
+            .help("Timeout for connection to next peer")
+            .default_value("15s")
+            .long(IMAP_ARG_CONNECT_TIMEOUT)
+            .num_args(1),
+    )
+    .append_socket_args()
+    .append_openssl_args()
+    .append_proxy_openssl_args()
+    .append_proxy_protocol_args()
+}
+
+pub(super) fn parse_imap_args(args: &ArgMatches) -> anyhow::Result<BenchImapArgs> {
+    let target = if let Some(v) = args.get_one::<UpstreamAddr>(IMAP_ARG_TARGET) {
+        v.clone()
+    } else {
+        return Err(anyhow!("no target set"));
+    };
+
+    let mut imap_args = BenchImapArgs::new(target, args.get_flag(IMAP_ARG_TLS));
+
+    if let Some(v) = args.get_one::<String>(IMAP_ARG_PROXY) {
+        let url = Url::parse(v).context(format!("invalid {IMAP_ARG_PROXY} value"))?;
+        let proxy = Proxy::try_from(&url).map_err(|e| anyhow!("invalid proxy: {e}"))?;
+        if let Proxy::Http(http_proxy) = &proxy {
+            imap_args.proxy_tls.config = http_proxy.tls_config.clone();
+        }
+        imap_args.connect_proxy = Some(proxy);
+    }
+
+    if let Some(v) = args.get_one::<String>(IMAP_ARG_USERNAME) {
+        imap_args.username.clone_from(v);
+    }
+    if let Some(v) = args.get_one::<String>(IMAP_ARG_PASSWORD) {
+        imap_args.password.clone_from(v);
+    }
+    if let Some(v) = args.get_one::<String>(IMAP_ARG_MAILBOX) {
+        imap_args.mailbox.clone_from(v);
+    }
+    if let Some(n) = args.get_one::<usize>(IMAP_ARG_FETCH) {
+        imap_args.fetch_count = *n;
+    }
+
+    if let Some(timeout) = g3_clap::humanize::get_duration(args, IMAP_ARG_TIMEOUT)? {
+        imap_args.timeout = timeout;
+    }
+    if let Some(timeout) = g3_clap::humanize::get_duration(args, IMAP_ARG_CONNECT_TIMEOUT)? {
+        imap_args.connect_timeout = timeout;
+    }
+
+    imap_args
+        .socket
+        .parse_args(args)
+        .context("invalid socket config")?;
+    imap_args
+        .target_tls
+        .parse_tls_args(args)
+        .context("invalid target tls config")?;
+    imap_args
+        .proxy_tls
+        .parse_proxy_tls_args(args)
+        .context("invalid proxy tls config")?;
+    imap_args
+        .proxy_protocol
+        .parse_args(args)
+        .context("invalid proxy protocol config")?;
+
+    Ok(imap_args)
+}
@@ -0,0 +1,128 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::time::Duration;
+
+use g3_histogram::{HistogramRecorder, KeepingHistogram};
+use g3_statsd_client::StatsdClient;
+use g3_types::ext::DurationExt;
+
+use crate::target::BenchHistogram;
+
+pub(crate) struct ImapHistogram {
+    connect_time: KeepingHistogram<u64>,
+    greeting_time: KeepingHistogram<u64>,
+    login_time: KeepingHistogram<u64>,
+    select_time: KeepingHistogram<u64>,
+    fetch_time: KeepingHistogram<u64>,
+    total_time: KeepingHistogram<u64>,
+}
+
+impl ImapHistogram {
+    pub(crate) fn new() -> (Self, ImapHistogramRecorder) {
+        let (connect_time_h, connect_time_r) = KeepingHistogram::new();
+        let (greeting_time_h, greeting_time_r) = KeepingHistogram::new();
+        let (login_time_h, login_time_r) = KeepingHistogram::new();
+        let (select_time_h, select_time_r) = KeepingHistogram::new();
+        let (fetch_time_h, fetch_time_r) = KeepingHistogram::new();
+        let (total_time_h, total_time_r) = KeepingHistogram::new();
+        let h = ImapHistogram {
+            connect_time: connect_time_h,
+            greeting_time: greeting_time_h,
+            login_time: login_time_h,
+            select_time: select_time_h,
+            fetch_time: fetch_time_h,
+            total_time: total_time_h,
+        };
+        let r = ImapHistogramRecorder {
+            connect_time: connect_time_r,
+            greeting_time: greeting_time_r,
+            login_time: login_time_r,
+            select_time: select_time_r,
+            fetch_time: fetch_time_r,
+            total_time: total_time_r,
+        };
+        (h, r)
+    }
+}
+
+impl BenchHistogram for ImapHistogram {
+    fn refresh(&mut self) {
+        self.connect_time.refresh().unwrap();
+        self.greeting_time.refresh().unwrap();
+        self.login_time.refresh().unwrap();
+        self.select_time.refresh().unwrap();
+        self.fetch_time.refresh().unwrap();
+        self.total_time.refresh().unwrap();
+    }
+
+    fn emit(&self, client: &mut StatsdClient) {
+        self.emit_histogram(client, self.connect_time.inner(), "imap.time.connect");
+        self.emit_histogram(client, self.greeting_time.inner(), "imap.time.greeting");
+        self.emit_histogram(client, self.login_time.inner(), "imap.time.login");
+        self.emit_histogram(client, self.select_time.inner(), "imap.time.select");
+        self.emit_histogram(client, self.fetch_time.inner(), "imap.time.fetch");
+        self.emit_histogram(client, self.total_time.inner(), "imap.time.total");
+    }
+
+    fn summary(&self) {
+        Self::summary_histogram_title("# Duration Times");
+        Self::summary_duration_line("Connect:", self.connect_time.inner());
+        Self::summary_duration_line("Greeting:", self.greeting_time.inner());
+        Self::summary_duration_line("Login:", self.login_time.inner());
+        Self::summary_duration_line("Select:", self.select_time.inner());
+        Self::summary_duration_line("Fetch:", self.fetch_time.inner());
+        Self::summary_duration_line("Total:", self.total_time.inner());
+        Self::summary_newline();
+        Self::summary_total_percentage(self.total_time.inner());
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct ImapHistogramRecorder {
+    connect_time: HistogramRecorder<u64>,
+    greeting_time: HistogramRecorder<u64>,
+    login_time: HistogramRecorder<u64>,
+    select_time: HistogramRecorder<u64>,
+    fetch_time: HistogramRecorder<u64>,
+    total_time: HistogramRecorder<u64>,
+}
+
+impl ImapHistogramRecorder {
+    pub(crate) fn record_connect_time(&mut self, dur: Duration) {
+        let _ = self.connect_time.record(dur.as_nanos_u64());
+    }
+
+    pub(crate) fn record_greeting_time(&mut self, dur: Duration) {
+        let _ = self.greeting_time.record(dur.as_nanos_u64());
+    }
+
+    pub(crate) fn record_login_time(&mut self, dur: Duration) {
+        let _ = self.login_time.record(dur.as_nanos_u64());
+    }
+
+    pub(crate) fn record_select_time(&mut self, dur: Duration) {
+        let _ = self.select_time.record(dur.as_nanos_u64());
+    }
+
+    pub(crate) fn record_fetch_time(&mut self, dur: Duration) {
+        let _ = self.fetch_time.record(dur.as_nanos_u64());
+    }
+
+    pub(crate) fn record_total_time(&mut self, dur: Duration) {
+        let _ = self.total_time.record(dur.as_nanos_u64());
+    }
+}
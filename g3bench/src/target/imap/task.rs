@@ -0,0 +1,339 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context};
+use tokio::io::{AsyncBufRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::time::Instant;
+
+use g3_io_ext::{LimitedBufReadExt, LimitedReader, LimitedWriter};
+
+use super::{
+    BenchImapArgs, BenchTaskContext, BoxImapReader, BoxImapWriter, ImapHistogramRecorder,
+    ImapRuntimeStats, ProcArgs,
+};
+use crate::target::BenchError;
+
+const MAX_LINE_SIZE: usize = 8192;
+
+type ImapReader = BufReader<LimitedReader<BoxImapReader>>;
+type ImapWriter = LimitedWriter<BoxImapWriter>;
+
+#[derive(Default)]
+struct ImapReply {
+    exists: Option<usize>,
+    fetched: u64,
+}
+
+pub(super) struct ImapTaskContext {
+    args: Arc<BenchImapArgs>,
+    proc_args: Arc<ProcArgs>,
+
+    runtime_stats: Arc<ImapRuntimeStats>,
+    histogram_recorder: ImapHistogramRecorder,
+
+    line_buf: Vec<u8>,
+    login_cmd: String,
+    select_cmd: String,
+}
+
+fn quote_string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        if matches!(c, '"' | '\\') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// get the size of the literal at the end of the line, like `{123}\r\n`
+fn literal_size(line: &[u8]) -> Option<u64> {
+    let line = line.strip_suffix(b"\r\n").unwrap_or(line);
+    let line = line.strip_suffix(b"}")?;
+    let p = line.iter().rposition(|c| *c == b'{')?;
+    let s = std::str::from_utf8(&line[p + 1..]).ok()?;
+    let s = s.strip_suffix('+').unwrap_or(s);
+    u64::from_str(s).ok()
+}
+
+impl ImapTaskContext {
+    pub(super) fn new(
+        args: &Arc<BenchImapArgs>,
+        proc_args: &Arc<ProcArgs>,
+        runtime_stats: &Arc<ImapRuntimeStats>,
+        histogram_recorder: ImapHistogramRecorder,
+    ) -> anyhow::Result<Self> {
+        let login_cmd = format!(
+            "A1 LOGIN {} {}\r\n",
+            quote_string(&args.username),
+            quote_string(&args.password)
+        );
+        let select_cmd = format!("A2 SELECT {}\r\n", quote_string(&args.mailbox));
+        Ok(ImapTaskContext {
+            args: Arc::clone(args),
+            proc_args: Arc::clone(proc_args),
+            runtime_stats: Arc::clone(runtime_stats),
+            histogram_recorder,
+            line_buf: Vec::with_capacity(MAX_LINE_SIZE),
+            login_cmd,
+            select_cmd,
+        })
+    }
+
+    async fn new_connection(&mut self) -> anyhow::Result<(ImapReader, ImapWriter)> {
+        self.runtime_stats.add_conn_attempt();
+        let (r, w) = match tokio::time::timeout(
+            self.args.connect_timeout,
+            self.args
+                .new_imap_connection(&self.runtime_stats, &self.proc_args),
+        )
+        .await
+        {
+            Ok(Ok(c)) => c,
+            Ok(Err(e)) => return Err(e),
+            Err(_) => return Err(anyhow!("timeout to get new connection")),
+        };
+        self.runtime_stats.add_conn_success();
+
+        let r = LimitedReader::local_limited(
+            r,
+            self.proc_args.tcp_sock_speed_limit.shift_millis,
+            self.proc_args.tcp_sock_speed_limit.max_south,
+            self.runtime_stats.clone(),
+        );
+        let w = LimitedWriter::local_limited(
+            w,
+            self.proc_args.tcp_sock_speed_limit.shift_millis,
+            self.proc_args.tcp_sock_speed_limit.max_north,
+            self.runtime_stats.clone(),
+        );
+        Ok((BufReader::new(r), w))
+    }
+
+    async fn send_command<W>(writer: &mut W, cmd: &[u8]) -> anyhow::Result<()>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        writer
+            .write_all(cmd)
+            .await
+            .map_err(|e| anyhow!("failed to send command: {e:?}"))?;
+        writer
+            .flush()
+            .await
+            .map_err(|e| anyhow!("failed to flush command: {e:?}"))
+    }
+
+    async fn read_line<R>(&mut self, reader: &mut R) -> anyhow::Result<()>
+    where
+        R: AsyncBufRead + Unpin,
+    {
+        self.line_buf.clear();
+        let (found, nr) = reader
+            .limited_read_until(b'\n', MAX_LINE_SIZE, &mut self.line_buf)
+            .await
+            .map_err(|e| anyhow!("failed to read response: {e:?}"))?;
+        if nr == 0 {
+            return Err(anyhow!("connection closed by server"));
+        }
+        if !found {
+            return if nr < MAX_LINE_SIZE {
+                Err(anyhow!("connection closed by server"))
+            } else {
+                Err(anyhow!("too long response line"))
+            };
+        }
+        Ok(())
+    }
+
+    async fn read_greeting<R>(&mut self, reader: &mut R) -> anyhow::Result<()>
+    where
+        R: AsyncBufRead + Unpin,
+    {
+        self.read_line(reader).await?;
+        if self.line_buf.starts_with(b"* OK") || self.line_buf.starts_with(b"* PREAUTH") {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "unexpected greeting: {}",
+                String::from_utf8_lossy(&self.line_buf).trim_end()
+            ))
+        }
+    }
+
+    async fn read_tagged_reply<R>(&mut self, reader: &mut R, tag: &str) -> anyhow::Result<ImapReply>
+    where
+        R: AsyncBufRead + Unpin,
+    {
+        let mut reply = ImapReply::default();
+        loop {
+            self.read_line(reader).await?;
+
+            if let Some(left) = self.line_buf.strip_prefix(tag.as_bytes()) {
+                return if left.starts_with(b" OK") {
+                    Ok(reply)
+                } else {
+                    Err(anyhow!(
+                        "command failed: {}",
+                        String::from_utf8_lossy(&self.line_buf).trim_end()
+                    ))
+                };
+            }
+
+            if let Some(left) = self.line_buf.strip_prefix(b"* ") {
+                let mut iter = left.splitn(3, |c| *c == b' ');
+                if let (Some(num), Some(kind)) = (iter.next(), iter.next()) {
+                    let kind = kind.strip_suffix(b"\r\n").unwrap_or(kind);
+                    if kind.eq_ignore_ascii_case(b"EXISTS") {
+                        reply.exists = std::str::from_utf8(num)
+                            .ok()
+                            .and_then(|s| usize::from_str(s).ok());
+                    } else if kind.eq_ignore_ascii_case(b"FETCH") {
+                        reply.fetched += 1;
+                    }
+                }
+            }
+
+            if let Some(size) = literal_size(&self.line_buf) {
+                let nr = tokio::io::copy(&mut (&mut *reader).take(size), &mut tokio::io::sink())
+                    .await
+                    .map_err(|e| anyhow!("failed to read literal data: {e:?}"))?;
+                if nr < size {
+                    return Err(anyhow!("connection closed while reading literal data"));
+                }
+            }
+        }
+    }
+
+    async fn run_command<R, W>(
+        &mut self,
+        reader: &mut R,
+        writer: &mut W,
+        tag: &str,
+        cmd: &[u8],
+    ) -> anyhow::Result<ImapReply>
+    where
+        R: AsyncBufRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        Self::send_command(writer, cmd).await?;
+        match tokio::time::timeout(self.args.timeout, self.read_tagged_reply(reader, tag)).await {
+            Ok(r) => r,
+            Err(_) => Err(anyhow!("timeout to read response")),
+        }
+    }
+
+    async fn run_session(
+        &mut self,
+        reader: &mut ImapReader,
+        writer: &mut ImapWriter,
+    ) -> anyhow::Result<()> {
+        let time_start = Instant::now();
+        match tokio::time::timeout(self.args.timeout, self.read_greeting(reader)).await {
+            Ok(Ok(_)) => {}
+            Ok(Err(e)) => return Err(e.context("failed to read greeting")),
+            Err(_) => return Err(anyhow!("timeout to read greeting")),
+        }
+        self.histogram_recorder
+            .record_greeting_time(time_start.elapsed());
+
+        let time_start = Instant::now();
+        let cmd = std::mem::take(&mut self.login_cmd);
+        let r = self
+            .run_command(reader, writer, "A1 ", cmd.as_bytes())
+            .await;
+        self.login_cmd = cmd;
+        r.context("LOGIN failed")?;
+        self.histogram_recorder
+            .record_login_time(time_start.elapsed());
+
+        let time_start = Instant::now();
+        let cmd = std::mem::take(&mut self.select_cmd);
+        let r = self
+            .run_command(reader, writer, "A2 ", cmd.as_bytes())
+            .await;
+        self.select_cmd = cmd;
+        let reply = r.context("SELECT failed")?;
+        self.histogram_recorder
+            .record_select_time(time_start.elapsed());
+
+        let fetch_count = reply.exists.unwrap_or_default().min(self.args.fetch_count);
+        if fetch_count > 0 {
+            let time_start = Instant::now();
+            let cmd = format!("A3 FETCH 1:{fetch_count} BODY.PEEK[]\r\n");
+            let reply = self
+                .run_command(reader, writer, "A3 ", cmd.as_bytes())
+                .await
+                .context("FETCH failed")?;
+            self.histogram_recorder
+                .record_fetch_time(time_start.elapsed());
+            self.runtime_stats.add_msg_fetched(reply.fetched);
+        }
+
+        self.run_command(reader, writer, "A4 ", b"A4 LOGOUT\r\n")
+            .await
+            .context("LOGOUT failed")?;
+
+        Ok(())
+    }
+}
+
+impl BenchTaskContext for ImapTaskContext {
+    fn mark_task_start(&self) {
+        self.runtime_stats.add_task_total();
+        self.runtime_stats.inc_task_alive();
+    }
+
+    fn mark_task_passed(&self) {
+        self.runtime_stats.add_task_passed();
+        self.runtime_stats.dec_task_alive();
+    }
+
+    fn mark_task_failed(&self) {
+        self.runtime_stats.add_task_failed();
+        self.runtime_stats.dec_task_alive();
+    }
+
+    async fn run(&mut self, _task_id: usize, time_started: Instant) -> Result<(), BenchError> {
+        let (mut reader, mut writer) = self
+            .new_connection()
+            .await
+            .context("connect to imap server failed")
+            .map_err(BenchError::Fatal)?;
+        self.histogram_recorder
+            .record_connect_time(time_started.elapsed());
+
+        match self.run_session(&mut reader, &mut writer).await {
+            Ok(_) => {
+                let total_time = time_started.elapsed();
+                self.histogram_recorder.record_total_time(total_time);
+
+                tokio::spawn(async move {
+                    let _ = tokio::time::timeout(Duration::from_secs(4), writer.shutdown()).await;
+                });
+                Ok(())
+            }
+            Err(e) => Err(BenchError::Task(e)),
+        }
+    }
+}
@@ -0,0 +1,84 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::sync::Arc;
+
+use clap::{ArgMatches, Command};
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use super::{BenchTarget, BenchTaskContext, ProcArgs};
+
+mod opts;
+use opts::BenchSmtpArgs;
+
+mod stats;
+use stats::{SmtpHistogram, SmtpHistogramRecorder, SmtpRuntimeStats};
+
+mod task;
+use task::SmtpTaskContext;
+
+pub const COMMAND: &str = "smtp";
+
+type BoxSmtpWriter = Box<dyn AsyncWrite + Send + Unpin>;
+type BoxSmtpReader = Box<dyn AsyncRead + Send + Unpin>;
+type BoxSmtpConnection = (BoxSmtpReader, BoxSmtpWriter);
+
+struct SmtpTarget {
+    args: Arc<BenchSmtpArgs>,
+    proc_args: Arc<ProcArgs>,
+    stats: Arc<SmtpRuntimeStats>,
+    histogram: Option<SmtpHistogram>,
+    histogram_recorder: SmtpHistogramRecorder,
+}
+
+impl BenchTarget<SmtpRuntimeStats, SmtpHistogram, SmtpTaskContext> for SmtpTarget {
+    fn new_context(&self) -> anyhow::Result<SmtpTaskContext> {
+        SmtpTaskContext::new(
+            &self.args,
+            &self.proc_args,
+            &self.stats,
+            self.histogram_recorder.clone(),
+        )
+    }
+
+    fn fetch_runtime_stats(&self) -> Arc<SmtpRuntimeStats> {
+        self.stats.clone()
+    }
+
+    fn take_histogram(&mut self) -> Option<SmtpHistogram> {
+        self.histogram.take()
+    }
+}
+
+pub fn command() -> Command {
+    opts::add_smtp_args(Command::new(COMMAND).about("Run scripted SMTP sessions to send mails"))
+}
+
+pub async fn run(proc_args: &Arc<ProcArgs>, cmd_args: &ArgMatches) -> anyhow::Result<()> {
+    let mut smtp_args = opts::parse_smtp_args(cmd_args)?;
+    smtp_args.resolve_target_address(proc_args).await?;
+
+    let (histogram, histogram_recorder) = SmtpHistogram::new();
+    let target = SmtpTarget {
+        args: Arc::new(smtp_args),
+        proc_args: Arc::clone(proc_args),
+        stats: Arc::new(SmtpRuntimeStats::default()),
+        histogram: Some(histogram),
+        histogram_recorder,
+    };
+
+    super::run(target, proc_args).await
+}
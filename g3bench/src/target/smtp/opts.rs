@@ -0,0 +1,490 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context};
+use clap::{value_parser, Arg, ArgAction, ArgMatches, Command, ValueHint};
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use url::Url;
+
+use g3_io_ext::AsyncStream;
+use g3_openssl::SslStream;
+use g3_types::collection::{SelectiveVec, WeightedValue};
+use g3_types::net::{OpensslClientConfig, OpensslClientConfigBuilder, Proxy, UpstreamAddr};
+
+use super::{BoxSmtpConnection, ProcArgs, SmtpRuntimeStats};
+use crate::module::openssl::{AppendOpensslArgs, OpensslTlsClientArgs};
+use crate::module::proxy_protocol::{AppendProxyProtocolArgs, ProxyProtocolArgs};
+use crate::module::socket::{AppendSocketArgs, SocketArgs};
+
+const SMTP_ARG_TARGET: &str = "target";
+const SMTP_ARG_PROXY: &str = "proxy";
+const SMTP_ARG_TLS: &str = "tls";
+const SMTP_ARG_EHLO_DOMAIN: &str = "ehlo-domain";
+const SMTP_ARG_MAIL_FROM: &str = "mail-from";
+const SMTP_ARG_RCPT_TO: &str = "rcpt-to";
+const SMTP_ARG_MESSAGE: &str = "message";
+const SMTP_ARG_MESSAGE_SIZE: &str = "message-size";
+const SMTP_ARG_TIMEOUT: &str = "timeout";
+const SMTP_ARG_CONNECT_TIMEOUT: &str = "connect-timeout";
+
+pub(super) struct BenchSmtpArgs {
+    target: UpstreamAddr,
+    connect_proxy: Option<Proxy>,
+    pub(super) ehlo_domain: String,
+    pub(super) mail_from: String,
+    pub(super) rcpt_to: Vec<String>,
+    pub(super) message: Vec<u8>,
+    pub(super) timeout: Duration,
+    pub(super) connect_timeout: Duration,
+
+    socket: SocketArgs,
+    target_tls: OpensslTlsClientArgs,
+    proxy_tls: OpensslTlsClientArgs,
+    proxy_protocol: ProxyProtocolArgs,
+
+    peer_addrs: Option<SelectiveVec<WeightedValue<SocketAddr>>>,
+}
+
+impl BenchSmtpArgs {
+    fn new(target: UpstreamAddr, tls: bool) -> Self {
+        let mut target_tls = OpensslTlsClientArgs::default();
+        if tls {
+            target_tls.config = Some(OpensslClientConfigBuilder::with_cache_for_one_site());
+        }
+
+        BenchSmtpArgs {
+            target,
+            connect_proxy: None,
+            ehlo_domain: "localhost".to_string(),
+            mail_from: String::new(),
+            rcpt_to: Vec::new(),
+            message: Vec::new(),
+            timeout: Duration::from_secs(30),
+            connect_timeout: Duration::from_secs(15),
+            socket: SocketArgs::default(),
+            target_tls,
+            proxy_tls: OpensslTlsClientArgs::default(),
+            proxy_protocol: ProxyProtocolArgs::default(),
+            peer_addrs: None,
+        }
+    }
+
+    pub(super) async fn resolve_target_address(
+        &mut self,
+        proc_args: &ProcArgs,
+    ) -> anyhow::Result<()> {
+        let host = if let Some(proxy) = &self.connect_proxy {
+            proxy.peer()
+        } else {
+            &self.target
+        };
+        let addrs = proc_args.resolve(host).await?;
+        self.peer_addrs = Some(addrs);
+        Ok(())
+    }
+
+    async fn new_tcp_connection(&self, proc_args: &ProcArgs) -> anyhow::Result<TcpStream> {
+        let addrs = self
+            .peer_addrs
+            .as_ref()
+            .ok_or_else(|| anyhow!("no peer address set"))?;
+        let peer = *proc_args.select_peer(addrs);
+
+        let mut stream = self.socket.tcp_connect_to(peer).await?;
+
+        if let Some(data) = self.proxy_protocol.data() {
+            stream
+                .write_all(data) // no need to flush data
+                .await
+                .map_err(|e| anyhow!("failed to send proxy protocol data: {e:?}"))?;
+        }
+
+        Ok(stream)
+    }
+
+    pub(super) async fn new_smtp_connection(
+        &self,
+        stats: &SmtpRuntimeStats,
+        proc_args: &ProcArgs,
+    ) -> anyhow::Result<BoxSmtpConnection> {
+        if let Some(proxy) = &self.connect_proxy {
+            match proxy {
+                Proxy::Http(http_proxy) => {
+                    let stream = self.new_tcp_connection(proc_args).await.context(format!(
+                        "failed to connect to http proxy {}",
+                        http_proxy.peer()
+                    ))?;
+
+                    if let Some(tls_config) = &self.proxy_tls.client {
+                        let tls_stream = self
+                            .tls_connect_to_proxy(tls_config, http_proxy.peer(), stream, stats)
+                            .await?;
+
+                        let mut buf_stream = BufReader::new(tls_stream);
+
+                        g3_http::connect::client::http_connect_to(
+                            &mut buf_stream,
+                            &http_proxy.auth,
+                            &self.target,
+                        )
+                        .await
+                        .map_err(|e| {
+                            anyhow!("http connect to {} failed: {e}", http_proxy.peer())
+                        })?;
+
+                        self.connect_to_target(buf_stream.into_inner(), stats).await
+                    } else {
+                        let mut buf_stream = BufReader::new(stream);
+
+                        g3_http::connect::client::http_connect_to(
+                            &mut buf_stream,
+                            &http_proxy.auth,
+                            &self.target,
+                        )
+                        .await
+                        .map_err(|e| {
+                            anyhow!("http connect to {} failed: {e}", http_proxy.peer())
+                        })?;
+
+                        self.connect_to_target(buf_stream.into_inner(), stats).await
+                    }
+                }
+                Proxy::Socks4(socks4_proxy) => {
+                    let mut stream = self.new_tcp_connection(proc_args).await.context(format!(
+                        "failed to connect to socks4 proxy {}",
+                        socks4_proxy.peer()
+                    ))?;
+
+                    g3_socks::v4a::client::socks4a_connect_to(&mut stream, &self.target)
+                        .await
+                        .map_err(|e| {
+                            anyhow!("socks4a connect to {} failed: {e}", socks4_proxy.peer())
+                        })?;
+
+                    self.connect_to_target(stream, stats).await
+                }
+                Proxy::Socks5(socks5_proxy) => {
+                    let mut stream = self.new_tcp_connection(proc_args).await.context(format!(
+                        "failed to connect to socks5 proxy {}",
+                        socks5_proxy.peer()
+                    ))?;
+
+                    g3_socks::v5::client::socks5_connect_to(
+                        &mut stream,
+                        &socks5_proxy.auth,
+                        &self.target,
+                    )
+                    .await
+                    .map_err(|e| {
+                        anyhow!("socks5 connect to {} failed: {e}", socks5_proxy.peer())
+                    })?;
+
+                    self.connect_to_target(stream, stats).await
+                }
+            }
+        } else {
+            let stream = self
+                .new_tcp_connection(proc_args)
+                .await
+                .context(format!("failed to connect to target host {}", self.target))?;
+            self.connect_to_target(stream, stats).await
+        }
+    }
+
+    async fn connect_to_target<S>(
+        &self,
+        stream: S,
+        stats: &SmtpRuntimeStats,
+    ) -> anyhow::Result<BoxSmtpConnection>
+    where
+        S: AsyncRead + AsyncWrite + AsyncStream + Unpin + Send + 'static,
+        S::R: AsyncRead + Send + Unpin + 'static,
+        S::W: AsyncWrite + Send + Unpin + 'static,
+    {
+        if let Some(tls_client) = &self.target_tls.client {
+            let tls_stream = self
+                .target_tls
+                .connect_target(tls_client, stream, &self.target)
+                .await?;
+
+            stats.target_ssl_session.add_total();
+            if tls_stream.ssl().session_reused() {
+                stats.target_ssl_session.add_reused();
+            }
+
+            let (r, w) = tls_stream.into_split();
+            Ok((Box::new(r), Box::new(w)))
+        } else {
+            let (r, w) = stream.into_split();
+            Ok((Box::new(r), Box::new(w)))
+        }
+    }
+
+    async fn tls_connect_to_proxy(
+        &self,
+        tls_client: &OpensslClientConfig,
+        peer: &UpstreamAddr,
+        stream: TcpStream,
+        stats: &SmtpRuntimeStats,
+    ) -> anyhow::Result<SslStream<TcpStream>> {
+        let tls_stream = self
+            .proxy_tls
+            .connect_target(tls_client, stream, peer)
+            .await?;
+
+        stats.proxy_ssl_session.add_total();
+        if tls_stream.ssl().session_reused() {
+            stats.proxy_ssl_session.add_reused();
+        }
+
+        Ok(tls_stream)
+    }
+
+    fn set_message(&mut self, content: &str) {
+        let mut message = Vec::with_capacity(content.len() + 128);
+        for line in content.lines() {
+            // do dot-stuffing, see RFC 5321 Section 4.5.2
+            if line.starts_with('.') {
+                message.push(b'.');
+            }
+            message.extend_from_slice(line.as_bytes());
+            message.extend_from_slice(b"\r\n");
+        }
+        message.extend_from_slice(b".\r\n");
+        self.message = message;
+    }
+
+    fn set_generated_message(&mut self, size: usize) {
+        const LINE_SIZE: usize = 76;
+
+        let mut content = format!(
+            "From: <{}>\r\nTo: <{}>\r\nSubject: g3bench smtp test\r\n\r\n",
+            self.mail_from,
+            self.rcpt_to.join(">, <")
+        );
+        let mut left = size;
+        while left > 0 {
+            let len = left.min(LINE_SIZE);
+            content.push_str(&"x".repeat(len));
+            content.push_str("\r\n");
+            left -= len;
+        }
+        self.set_message(&content);
+    }
+}
+
+pub(super) fn add_smtp_args(app: Command) -> Command {
+    app.arg(
+        Arg::new(SMTP_ARG_TARGET)
+            .help("Target SMTP server address")
+            .value_name("ADDRESS")
+            .required(true)
+            .num_args(1)
+            .value_parser(value_parser!(UpstreamAddr)),
+    )
+    .arg(
+        Arg::new(SMTP_ARG_PROXY)
+            .value_name("PROXY URL")
+            .short('x')
+            .help("Use a proxy")
+            .long(SMTP_ARG_PROXY)
+            .num_args(1),
+    )
+    .arg(
+        Arg::new(SMTP_ARG_TLS)
+            .help("Use implicit TLS (SMTPS) to the target server")
+            .action(ArgAction::SetTrue)
+            .long(SMTP_ARG_TLS),
+    )
+    .arg(
+        Arg::new(SMTP_ARG_EHLO_DOMAIN)
+            .help("Domain name to send in EHLO command")
+            .value_name("DOMAIN")
+            .long(SMTP_ARG_EHLO_DOMAIN)
+            .num_args(1)
+            .default_value("localhost"),
+    )
+    .arg(
+        Arg::new(SMTP_ARG_MAIL_FROM)
+            .help("Reverse path to send in MAIL command")
+            .value_name("MAILBOX")
+            .long(SMTP_ARG_MAIL_FROM)
+            .required(true)
+            .num_args(1),
+    )
+    .arg(
+        Arg::new(SMTP_ARG_RCPT_TO)
+            .help("Forward path to send in RCPT command")
+            .value_name("MAILBOX")
+            .long(SMTP_ARG_RCPT_TO)
+            .required(true)
+            .action(ArgAction::Append)
+            .num_args(1),
+    )
+    .arg(
+        Arg::new(SMTP_ARG_MESSAGE)
+            .help("File that contains the mail message to send")
+            .value_name("MESSAGE FILE")
+            .long(SMTP_ARG_MESSAGE)
+            .num_args(1)
+            .value_hint(ValueHint::FilePath)
+            .value_parser(value_parser!(PathBuf))
+            .conflicts_with(SMTP_ARG_MESSAGE_SIZE),
+    )
+    .arg(
+        Arg::new(SMTP_ARG_MESSAGE_SIZE)
+            .help("Send a generated mail message with this body size")
+            .value_name("SIZE")
+            .long(SMTP_ARG_MESSAGE_SIZE)
+            .num_args(1)
+            .default_value("1024")
+            .conflicts_with(SMTP_ARG_MESSAGE),
+    )
+    .arg(
+        Arg::new(SMTP_ARG_TIMEOUT)
+            .value_name("TIMEOUT DURATION")
+            .help("Timeout for each SMTP command")
+            .default_value("30s")
+            .long(SMTP_ARG_TIMEOUT)
+            .num_args(1),
+    )
+    .arg(
+        Arg::new(SMTP_ARG_CONNECT_TIMEOUT)
+            .value_name("TIMEOUT DURATION")
+            .help("Timeout for connection to next peer")
+            .default_value("15s")
+            .long(SMTP_ARG_CONNECT_TIMEOUT)
+            .num_args(1),
+    )
+    .append_socket_args()
+    .append_openssl_args()
+    .append_proxy_openssl_args()
+    .append_proxy_protocol_args()
+}
+
+pub(super) fn parse_smtp_args(args: &ArgMatches) -> anyhow::Result<BenchSmtpArgs> {
+    let target = if let Some(v) = args.get_one::<UpstreamAddr>(SMTP_ARG_TARGET) {
+        v.clone()
+    } else {
+        return Err(anyhow!("no target set"));
+    };
+
+    let mut smtp_args = BenchSmtpArgs::new(target, args.get_flag(SMTP_ARG_TLS));
+
+    if let Some(v) = args.get_one::<String>(SMTP_ARG_PROXY) {
+        let url = Url::parse(v).context(format!("invalid {SMTP_ARG_PROXY} value"))?;
+        let proxy = Proxy::try_from(&url).map_err(|e| anyhow!("invalid proxy: {e}"))?;
+        if let Proxy::Http(http_proxy) = &proxy {
+            smtp_args.proxy_tls.config = http_proxy.tls_config.clone();
+        }
+        smtp_args.connect_proxy = Some(proxy);
+    }
+
+    if let Some(v) = args.get_one::<String>(SMTP_ARG_EHLO_DOMAIN) {
+        smtp_args.ehlo_domain.clone_from(v);
+    }
+    if let Some(v) = args.get_one::<String>(SMTP_ARG_MAIL_FROM) {
+        smtp_args.mail_from.clone_from(v);
+    }
+    if let Some(values) = args.get_many::<String>(SMTP_ARG_RCPT_TO) {
+        smtp_args.rcpt_to = values.cloned().collect();
+    }
+
+    if let Some(file) = args.get_one::<PathBuf>(SMTP_ARG_MESSAGE) {
+        let content = std::fs::read_to_string(file)
+            .map_err(|e| anyhow!("failed to read message file {}: {e}", file.display()))?;
+        smtp_args.set_message(&content);
+    } else if let Some(size) = g3_clap::humanize::get_usize(args, SMTP_ARG_MESSAGE_SIZE)? {
+        smtp_args.set_generated_message(size);
+    }
+
+    if let Some(timeout) = g3_clap::humanize::get_duration(args, SMTP_ARG_TIMEOUT)? {
+        smtp_args.timeout = timeout;
+    }
+    if let Some(timeout) = g3_clap::humanize::get_duration(args, SMTP_ARG_CONNECT_TIMEOUT)? {
+        smtp_args.connect_timeout = timeout;
+    }
+
+    smtp_args
+        .socket
+        .parse_args(args)
+        .context("invalid socket config")?;
+    smtp_args
+        .target_tls
+        .parse_tls_args(args)
+        .context("invalid target tls config")?;
+    smtp_args
+        .proxy_tls
+        .parse_proxy_tls_args(args)
+        .context("invalid proxy tls config")?;
+    smtp_args
+        .proxy_protocol
+        .parse_args(args)
+        .context("invalid proxy protocol config")?;
+
+    Ok(smtp_args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn new_args() -> BenchSmtpArgs {
+        let target = UpstreamAddr::from_str("127.0.0.1:25").unwrap();
+        BenchSmtpArgs::new(target, false)
+    }
+
+    #[test]
+    fn message_dot_stuffing() {
+        let mut args = new_args();
+        args.set_message("Subject: test\n\n.hidden\n..two\nend\n");
+        assert_eq!(
+            args.message,
+            b"Subject: test\r\n\r\n..hidden\r\n...two\r\nend\r\n.\r\n"
+        );
+
+        args.set_message("");
+        assert_eq!(args.message, b".\r\n");
+    }
+
+    #[test]
+    fn generated_message() {
+        let mut args = new_args();
+        args.mail_from = "a@example.net".to_string();
+        args.rcpt_to = vec!["b@example.net".to_string(), "c@example.net".to_string()];
+        args.set_generated_message(100);
+
+        let message = std::str::from_utf8(&args.message).unwrap();
+        assert!(message.starts_with(
+            "From: <a@example.net>\r\nTo: <b@example.net>, <c@example.net>\r\n\
+             Subject: g3bench smtp test\r\n\r\n"
+        ));
+        assert!(message.ends_with("\r\n.\r\n"));
+        let body: Vec<&str> = message
+            .split("\r\n\r\n")
+            .nth(1)
+            .unwrap()
+            .split("\r\n")
+            .collect();
+        assert_eq!(body, vec![&"x".repeat(76), &"x".repeat(24), ".", ""]);
+    }
+}
@@ -0,0 +1,128 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::time::Duration;
+
+use g3_histogram::{HistogramRecorder, KeepingHistogram};
+use g3_statsd_client::StatsdClient;
+use g3_types::ext::DurationExt;
+
+use crate::target::BenchHistogram;
+
+pub(crate) struct SmtpHistogram {
+    connect_time: KeepingHistogram<u64>,
+    greeting_time: KeepingHistogram<u64>,
+    ehlo_time: KeepingHistogram<u64>,
+    envelope_time: KeepingHistogram<u64>,
+    data_time: KeepingHistogram<u64>,
+    total_time: KeepingHistogram<u64>,
+}
+
+impl SmtpHistogram {
+    pub(crate) fn new() -> (Self, SmtpHistogramRecorder) {
+        let (connect_time_h, connect_time_r) = KeepingHistogram::new();
+        let (greeting_time_h, greeting_time_r) = KeepingHistogram::new();
+        let (ehlo_time_h, ehlo_time_r) = KeepingHistogram::new();
+        let (envelope_time_h, envelope_time_r) = KeepingHistogram::new();
+        let (data_time_h, data_time_r) = KeepingHistogram::new();
+        let (total_time_h, total_time_r) = KeepingHistogram::new();
+        let h = SmtpHistogram {
+            connect_time: connect_time_h,
+            greeting_time: greeting_time_h,
+            ehlo_time: ehlo_time_h,
+            envelope_time: envelope_time_h,
+            data_time: data_time_h,
+            total_time: total_time_h,
+        };
+        let r = SmtpHistogramRecorder {
+            connect_time: connect_time_r,
+            greeting_time: greeting_time_r,
+            ehlo_time: ehlo_time_r,
+            envelope_time: envelope_time_r,
+            data_time: data_time_r,
+            total_time: total_time_r,
+        };
+        (h, r)
+    }
+}
+
+impl BenchHistogram for SmtpHistogram {
+    fn refresh(&mut self) {
+        self.connect_time.refresh().unwrap();
+        self.greeting_time.refresh().unwrap();
+        self.ehlo_time.refresh().unwrap();
+        self.envelope_time.refresh().unwrap();
+        self.data_time.refresh().unwrap();
+        self.total_time.refresh().unwrap();
+    }
+
+    fn emit(&self, client: &mut StatsdClient) {
+        self.emit_histogram(client, self.connect_time.inner(), "smtp.time.connect");
+        self.emit_histogram(client, self.greeting_time.inner(), "smtp.time.greeting");
+        self.emit_histogram(client, self.ehlo_time.inner(), "smtp.time.ehlo");
+        self.emit_histogram(client, self.envelope_time.inner(), "smtp.time.envelope");
+        self.emit_histogram(client, self.data_time.inner(), "smtp.time.data");
+        self.emit_histogram(client, self.total_time.inner(), "smtp.time.total");
+    }
+
+    fn summary(&self) {
+        Self::summary_histogram_title("# Duration Times");
+        Self::summary_duration_line("Connect:", self.connect_time.inner());
+        Self::summary_duration_line("Greeting:", self.greeting_time.inner());
+        Self::summary_duration_line("Ehlo:", self.ehlo_time.inner());
+        Self::summary_duration_line("Envelope:", self.envelope_time.inner());
+        Self::summary_duration_line("Data:", self.data_time.inner());
+        Self::summary_duration_line("Total:", self.total_time.inner());
+        Self::summary_newline();
+        Self::summary_total_percentage(self.total_time.inner());
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct SmtpHistogramRecorder {
+    connect_time: HistogramRecorder<u64>,
+    greeting_time: HistogramRecorder<u64>,
+    ehlo_time: HistogramRecorder<u64>,
+    envelope_time: HistogramRecorder<u64>,
+    data_time: HistogramRecorder<u64>,
+    total_time: HistogramRecorder<u64>,
+}
+
+impl SmtpHistogramRecorder {
+    pub(crate) fn record_connect_time(&mut self, dur: Duration) {
+        let _ = self.connect_time.record(dur.as_nanos_u64());
+    }
+
+    pub(crate) fn record_greeting_time(&mut self, dur: Duration) {
+        let _ = self.greeting_time.record(dur.as_nanos_u64());
+    }
+
+    pub(crate) fn record_ehlo_time(&mut self, dur: Duration) {
+        let _ = self.ehlo_time.record(dur.as_nanos_u64());
+    }
+
+    pub(crate) fn record_envelope_time(&mut self, dur: Duration) {
+        let _ = self.envelope_time.record(dur.as_nanos_u64());
+    }
+
+    pub(crate) fn record_data_time(&mut self, dur: Duration) {
+        let _ = self.data_time.record(dur.as_nanos_u64());
+    }
+
+    pub(crate) fn record_total_time(&mut self, dur: Duration) {
+        let _ = self.total_time.record(dur.as_nanos_u64());
+    }
+}
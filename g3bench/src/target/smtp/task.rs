@@ -0,0 +1,274 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context};
+use tokio::io::{AsyncBufRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::time::Instant;
+
+use g3_io_ext::{LimitedBufReadExt, LimitedReader, LimitedWriter};
+use g3_smtp_proto::response::{ReplyCode, ResponseParser};
+
+use super::{
+    BenchSmtpArgs, BenchTaskContext, BoxSmtpReader, BoxSmtpWriter, ProcArgs, SmtpHistogramRecorder,
+    SmtpRuntimeStats,
+};
+use crate::target::BenchError;
+
+type SmtpReader = BufReader<LimitedReader<BoxSmtpReader>>;
+type SmtpWriter = LimitedWriter<BoxSmtpWriter>;
+
+pub(super) struct SmtpTaskContext {
+    args: Arc<BenchSmtpArgs>,
+    proc_args: Arc<ProcArgs>,
+
+    runtime_stats: Arc<SmtpRuntimeStats>,
+    histogram_recorder: SmtpHistogramRecorder,
+
+    line_buf: Vec<u8>,
+}
+
+impl SmtpTaskContext {
+    pub(super) fn new(
+        args: &Arc<BenchSmtpArgs>,
+        proc_args: &Arc<ProcArgs>,
+        runtime_stats: &Arc<SmtpRuntimeStats>,
+        histogram_recorder: SmtpHistogramRecorder,
+    ) -> anyhow::Result<Self> {
+        Ok(SmtpTaskContext {
+            args: Arc::clone(args),
+            proc_args: Arc::clone(proc_args),
+            runtime_stats: Arc::clone(runtime_stats),
+            histogram_recorder,
+            line_buf: Vec::with_capacity(ResponseParser::MAX_LINE_SIZE),
+        })
+    }
+
+    async fn new_connection(&mut self) -> anyhow::Result<(SmtpReader, SmtpWriter)> {
+        self.runtime_stats.add_conn_attempt();
+        let (r, w) = match tokio::time::timeout(
+            self.args.connect_timeout,
+            self.args
+                .new_smtp_connection(&self.runtime_stats, &self.proc_args),
+        )
+        .await
+        {
+            Ok(Ok(c)) => c,
+            Ok(Err(e)) => return Err(e),
+            Err(_) => return Err(anyhow!("timeout to get new connection")),
+        };
+        self.runtime_stats.add_conn_success();
+
+        let r = LimitedReader::local_limited(
+            r,
+            self.proc_args.tcp_sock_speed_limit.shift_millis,
+            self.proc_args.tcp_sock_speed_limit.max_south,
+            self.runtime_stats.clone(),
+        );
+        let w = LimitedWriter::local_limited(
+            w,
+            self.proc_args.tcp_sock_speed_limit.shift_millis,
+            self.proc_args.tcp_sock_speed_limit.max_north,
+            self.runtime_stats.clone(),
+        );
+        Ok((BufReader::new(r), w))
+    }
+
+    async fn send_command<W>(writer: &mut W, cmd: &[u8]) -> anyhow::Result<()>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        writer
+            .write_all(cmd)
+            .await
+            .map_err(|e| anyhow!("failed to send command: {e:?}"))?;
+        writer
+            .flush()
+            .await
+            .map_err(|e| anyhow!("failed to flush command: {e:?}"))
+    }
+
+    async fn read_reply<R>(reader: &mut R, line_buf: &mut Vec<u8>) -> anyhow::Result<ReplyCode>
+    where
+        R: AsyncBufRead + Unpin,
+    {
+        let mut parser = ResponseParser::default();
+        loop {
+            line_buf.clear();
+            let (found, nr) = reader
+                .limited_read_until(b'\n', ResponseParser::MAX_LINE_SIZE, line_buf)
+                .await
+                .map_err(|e| anyhow!("failed to read reply: {e:?}"))?;
+            if nr == 0 {
+                return Err(anyhow!("connection closed by server"));
+            }
+            if !found {
+                return if nr < ResponseParser::MAX_LINE_SIZE {
+                    Err(anyhow!("connection closed by server"))
+                } else {
+                    Err(anyhow!("too long reply line"))
+                };
+            }
+
+            parser
+                .feed_line(line_buf)
+                .map_err(|e| anyhow!("invalid reply line: {e}"))?;
+            if parser.finished() {
+                return Ok(parser.code());
+            }
+        }
+    }
+
+    async fn expect_reply<R>(
+        reader: &mut R,
+        line_buf: &mut Vec<u8>,
+        timeout: Duration,
+        stage: &'static str,
+        expected: &[u16],
+    ) -> anyhow::Result<()>
+    where
+        R: AsyncBufRead + Unpin,
+    {
+        let code = match tokio::time::timeout(timeout, Self::read_reply(reader, line_buf)).await {
+            Ok(Ok(code)) => code,
+            Ok(Err(e)) => return Err(e.context(format!("failed to read {stage} reply"))),
+            Err(_) => return Err(anyhow!("timeout to read {stage} reply")),
+        };
+        if expected.contains(&code.as_u16()) {
+            Ok(())
+        } else {
+            Err(anyhow!("unexpected {stage} reply code {code}"))
+        }
+    }
+
+    async fn run_session(
+        &mut self,
+        reader: &mut SmtpReader,
+        writer: &mut SmtpWriter,
+    ) -> anyhow::Result<()> {
+        let args = &*self.args;
+        let line_buf = &mut self.line_buf;
+        let timeout = args.timeout;
+
+        let time_start = Instant::now();
+        Self::expect_reply(
+            reader,
+            line_buf,
+            timeout,
+            "greeting",
+            &[ReplyCode::SERVICE_READY.as_u16()],
+        )
+        .await?;
+        self.histogram_recorder
+            .record_greeting_time(time_start.elapsed());
+
+        let time_start = Instant::now();
+        let cmd = format!("EHLO {}\r\n", args.ehlo_domain);
+        Self::send_command(writer, cmd.as_bytes()).await?;
+        Self::expect_reply(reader, line_buf, timeout, "EHLO", &[ReplyCode::OK.as_u16()]).await?;
+        self.histogram_recorder
+            .record_ehlo_time(time_start.elapsed());
+
+        let time_start = Instant::now();
+        let cmd = format!("MAIL FROM:<{}>\r\n", args.mail_from);
+        Self::send_command(writer, cmd.as_bytes()).await?;
+        Self::expect_reply(reader, line_buf, timeout, "MAIL", &[ReplyCode::OK.as_u16()]).await?;
+        for rcpt in &args.rcpt_to {
+            let cmd = format!("RCPT TO:<{rcpt}>\r\n");
+            Self::send_command(writer, cmd.as_bytes()).await?;
+            Self::expect_reply(reader, line_buf, timeout, "RCPT", &[250, 251]).await?;
+        }
+        self.histogram_recorder
+            .record_envelope_time(time_start.elapsed());
+
+        let time_start = Instant::now();
+        Self::send_command(writer, b"DATA\r\n").await?;
+        Self::expect_reply(
+            reader,
+            line_buf,
+            timeout,
+            "DATA",
+            &[ReplyCode::START_MAIL_INPUT.as_u16()],
+        )
+        .await?;
+        Self::send_command(writer, &args.message).await?;
+        Self::expect_reply(
+            reader,
+            line_buf,
+            timeout,
+            "end of data",
+            &[ReplyCode::OK.as_u16()],
+        )
+        .await?;
+        self.histogram_recorder
+            .record_data_time(time_start.elapsed());
+        self.runtime_stats.add_mail_sent();
+
+        Self::send_command(writer, b"QUIT\r\n").await?;
+        Self::expect_reply(
+            reader,
+            line_buf,
+            timeout,
+            "QUIT",
+            &[ReplyCode::SERVICE_CLOSING.as_u16()],
+        )
+        .await?;
+
+        Ok(())
+    }
+}
+
+impl BenchTaskContext for SmtpTaskContext {
+    fn mark_task_start(&self) {
+        self.runtime_stats.add_task_total();
+        self.runtime_stats.inc_task_alive();
+    }
+
+    fn mark_task_passed(&self) {
+        self.runtime_stats.add_task_passed();
+        self.runtime_stats.dec_task_alive();
+    }
+
+    fn mark_task_failed(&self) {
+        self.runtime_stats.add_task_failed();
+        self.runtime_stats.dec_task_alive();
+    }
+
+    async fn run(&mut self, _task_id: usize, time_started: Instant) -> Result<(), BenchError> {
+        let (mut reader, mut writer) = self
+            .new_connection()
+            .await
+            .context("connect to smtp server failed")
+            .map_err(BenchError::Fatal)?;
+        self.histogram_recorder
+            .record_connect_time(time_started.elapsed());
+
+        match self.run_session(&mut reader, &mut writer).await {
+            Ok(_) => {
+                let total_time = time_started.elapsed();
+                self.histogram_recorder.record_total_time(total_time);
+
+                tokio::spawn(async move {
+                    let _ = tokio::time::timeout(Duration::from_secs(4), writer.shutdown()).await;
+                });
+                Ok(())
+            }
+            Err(e) => Err(BenchError::Task(e)),
+        }
+    }
+}
@@ -0,0 +1,475 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Context};
+use log::{debug, warn};
+use redis::aio::MultiplexedConnection;
+use redis::AsyncCommands;
+use rmpv::ValueRef;
+use tokio::io::AsyncWriteExt;
+
+use g3_redis_client::RedisClientConfig;
+
+use crate::config::{CertCacheConfig, LocalCacheConfig, RedisCacheConfig};
+use crate::frontend::GeneratedData;
+
+const ENTRY_KEY_CERT: &str = "cert";
+const ENTRY_KEY_KEY: &str = "key";
+const ENTRY_KEY_EXPIRE: &str = "expire";
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+fn encode_entry(data: &GeneratedData) -> anyhow::Result<Vec<u8>> {
+    let expire = unix_now() + data.ttl as u64;
+    let map = vec![
+        (
+            ValueRef::String(ENTRY_KEY_CERT.into()),
+            ValueRef::String(data.cert.as_str().into()),
+        ),
+        (
+            ValueRef::String(ENTRY_KEY_KEY.into()),
+            ValueRef::Binary(&data.key),
+        ),
+        (
+            ValueRef::String(ENTRY_KEY_EXPIRE.into()),
+            ValueRef::Integer(expire.into()),
+        ),
+    ];
+    let mut buf = Vec::with_capacity(4096);
+    rmpv::encode::write_value_ref(&mut buf, &ValueRef::Map(map))
+        .map_err(|e| anyhow!("msgpack encode failed: {e}"))?;
+    Ok(buf)
+}
+
+/// Decode a cache entry, returns None if it has already expired
+fn decode_entry(mut buf: &[u8]) -> anyhow::Result<Option<GeneratedData>> {
+    let v =
+        rmpv::decode::read_value_ref(&mut buf).map_err(|e| anyhow!("invalid msgpack data: {e}"))?;
+    let ValueRef::Map(map) = v else {
+        return Err(anyhow!("the cache entry data type should be 'map'"));
+    };
+
+    let mut cert = None;
+    let mut key = None;
+    let mut expire = 0u64;
+    for (k, v) in map {
+        let key_s = g3_msgpack::value::as_string(&k).context("invalid cache entry key")?;
+        match key_s.as_str() {
+            ENTRY_KEY_CERT => {
+                let s = g3_msgpack::value::as_string(&v)
+                    .context(format!("invalid string value for key {key_s}"))?;
+                cert = Some(s);
+            }
+            ENTRY_KEY_KEY => {
+                let ValueRef::Binary(b) = v else {
+                    return Err(anyhow!("invalid binary value for key {key_s}"));
+                };
+                key = Some(b.to_vec());
+            }
+            ENTRY_KEY_EXPIRE => {
+                let ValueRef::Integer(i) = v else {
+                    return Err(anyhow!("invalid integer value for key {key_s}"));
+                };
+                expire = i
+                    .as_u64()
+                    .ok_or_else(|| anyhow!("invalid u64 value for key {key_s}"))?;
+            }
+            _ => {} // ignore unknown keys
+        }
+    }
+
+    let now = unix_now();
+    if expire <= now {
+        return Ok(None);
+    }
+    let cert = cert.ok_or_else(|| anyhow!("no cert set in cache entry"))?;
+    let key = key.ok_or_else(|| anyhow!("no key set in cache entry"))?;
+    Ok(Some(GeneratedData {
+        cert,
+        key,
+        ttl: u32::try_from(expire - now).unwrap_or(u32::MAX),
+    }))
+}
+
+pub(crate) enum CertCache {
+    Local(LocalCertCache),
+    Redis(Box<RedisCertCache>),
+}
+
+impl CertCache {
+    pub(crate) fn new(config: &CertCacheConfig) -> anyhow::Result<Self> {
+        match config {
+            CertCacheConfig::Local(c) => {
+                let cache = LocalCertCache::new(c).context("failed to build local cert cache")?;
+                Ok(CertCache::Local(cache))
+            }
+            CertCacheConfig::Redis(c) => {
+                let cache = RedisCertCache::new(c).context("failed to build redis cert cache")?;
+                Ok(CertCache::Redis(Box::new(cache)))
+            }
+        }
+    }
+
+    pub(crate) async fn get(&self, key: &str) -> anyhow::Result<Option<GeneratedData>> {
+        match self {
+            CertCache::Local(c) => c.get(key).await,
+            CertCache::Redis(c) => c.get(key).await,
+        }
+    }
+
+    /// Store the generated data to the cache.
+    ///
+    /// If another instance has stored data for the same key first, that data
+    /// will be returned, so all instances will use the same certificate.
+    pub(crate) async fn insert(
+        &self,
+        key: &str,
+        data: &GeneratedData,
+    ) -> anyhow::Result<Option<GeneratedData>> {
+        match self {
+            CertCache::Local(c) => c.insert(key, data).await.map(|_| None),
+            CertCache::Redis(c) => c.insert(key, data).await,
+        }
+    }
+
+    pub(crate) async fn purge_expired(&self) -> anyhow::Result<()> {
+        match self {
+            CertCache::Local(c) => c.purge_expired().await,
+            CertCache::Redis(_) => Ok(()), // expired by the redis server
+        }
+    }
+}
+
+pub(crate) struct LocalCertCache {
+    directory: PathBuf,
+    tmp_seq: AtomicU64,
+}
+
+impl LocalCertCache {
+    fn new(config: &LocalCacheConfig) -> anyhow::Result<Self> {
+        prepare_cache_dir(&config.directory)?;
+        Ok(LocalCertCache {
+            directory: config.directory.clone(),
+            tmp_seq: AtomicU64::new(0),
+        })
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.directory.join(format!("{key}.entry"))
+    }
+
+    async fn get(&self, key: &str) -> anyhow::Result<Option<GeneratedData>> {
+        let path = self.entry_path(key);
+        let buf = match tokio::fs::read(&path).await {
+            Ok(buf) => buf,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(anyhow!("failed to read file {}: {e}", path.display())),
+        };
+        match decode_entry(&buf) {
+            Ok(Some(data)) => Ok(Some(data)),
+            Ok(None) => {
+                remove_file(&path).await;
+                Ok(None)
+            }
+            Err(e) => {
+                remove_file(&path).await;
+                Err(e.context(format!("invalid cache file {}", path.display())))
+            }
+        }
+    }
+
+    async fn insert(&self, key: &str, data: &GeneratedData) -> anyhow::Result<()> {
+        let buf = encode_entry(data)?;
+        let path = self.entry_path(key);
+        // write to a temp file first, so readers will never see a partial entry
+        let seq = self.tmp_seq.fetch_add(1, Ordering::Relaxed);
+        let tmp_path = self
+            .directory
+            .join(format!(".{key}.{}.{seq}.tmp", std::process::id()));
+        if let Err(e) = write_private_file(&tmp_path, &buf).await {
+            remove_file(&tmp_path).await;
+            return Err(anyhow!("failed to write file {}: {e}", tmp_path.display()));
+        }
+        if let Err(e) = tokio::fs::rename(&tmp_path, &path).await {
+            remove_file(&tmp_path).await;
+            return Err(anyhow!(
+                "failed to rename {} to {}: {e}",
+                tmp_path.display(),
+                path.display()
+            ));
+        }
+        Ok(())
+    }
+
+    async fn purge_expired(&self) -> anyhow::Result<()> {
+        let mut dir = tokio::fs::read_dir(&self.directory)
+            .await
+            .map_err(|e| anyhow!("failed to read dir {}: {e}", self.directory.display()))?;
+        while let Some(entry) = dir
+            .next_entry()
+            .await
+            .map_err(|e| anyhow!("failed to read dir {}: {e}", self.directory.display()))?
+        {
+            let path = entry.path();
+            if path.extension().map(|ext| ext != "entry").unwrap_or(true) {
+                continue;
+            }
+            let Ok(buf) = tokio::fs::read(&path).await else {
+                continue;
+            };
+            if !matches!(decode_entry(&buf), Ok(Some(_))) {
+                remove_file(&path).await;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Create the cache directory if not existed, and make sure that it's only accessible by the owner,
+/// as the private keys are stored in plaintext in the cache files.
+fn prepare_cache_dir(path: &Path) -> anyhow::Result<()> {
+    let mut builder = std::fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+
+        builder.mode(0o700);
+    }
+    builder
+        .create(path)
+        .map_err(|e| anyhow!("failed to create dir {}: {e}", path.display()))?;
+
+    let meta = std::fs::metadata(path)
+        .map_err(|e| anyhow!("failed to get metadata of {}: {e}", path.display()))?;
+    if !meta.is_dir() {
+        return Err(anyhow!("{} is not a directory", path.display()));
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mode = meta.permissions().mode() & 0o777;
+        if mode & 0o077 != 0 {
+            return Err(anyhow!(
+                "the mode of dir {} is {mode:o}, it should be 700",
+                path.display()
+            ));
+        }
+    }
+    Ok(())
+}
+
+async fn write_private_file(path: &Path, buf: &[u8]) -> io::Result<()> {
+    let mut options = tokio::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    let mut file = options.open(path).await?;
+    file.write_all(buf).await?;
+    file.flush().await
+}
+
+async fn remove_file(path: &Path) {
+    if let Err(e) = tokio::fs::remove_file(path).await {
+        if e.kind() != io::ErrorKind::NotFound {
+            warn!("failed to remove cache file {}: {e}", path.display());
+        }
+    }
+}
+
+pub(crate) struct RedisCertCache {
+    redis: RedisClientConfig,
+    key_prefix: String,
+    conn: Mutex<Option<MultiplexedConnection>>,
+}
+
+impl RedisCertCache {
+    fn new(config: &RedisCacheConfig) -> anyhow::Result<Self> {
+        let redis = config.redis.build()?;
+        Ok(RedisCertCache {
+            redis,
+            key_prefix: config.key_prefix.clone(),
+            conn: Mutex::new(None),
+        })
+    }
+
+    async fn get_conn(&self) -> anyhow::Result<MultiplexedConnection> {
+        if let Some(conn) = self.conn.lock().unwrap().as_ref() {
+            return Ok(conn.clone());
+        }
+
+        let conn = self
+            .redis
+            .connect()
+            .await
+            .context("failed to connect to redis")?;
+        debug!("new redis connection established");
+        *self.conn.lock().unwrap() = Some(conn.clone());
+        Ok(conn)
+    }
+
+    fn reset_conn(&self) {
+        *self.conn.lock().unwrap() = None;
+    }
+
+    fn redis_key(&self, key: &str) -> String {
+        format!("{}{key}", self.key_prefix)
+    }
+
+    async fn get(&self, key: &str) -> anyhow::Result<Option<GeneratedData>> {
+        let redis_key = self.redis_key(key);
+        let mut conn = self.get_conn().await?;
+        let v: redis::Value = conn.get(&redis_key).await.map_err(|e| {
+            self.reset_conn();
+            anyhow!("failed to get redis key {redis_key}: {e}")
+        })?;
+        match v {
+            redis::Value::Nil => Ok(None),
+            redis::Value::BulkString(b) => {
+                decode_entry(&b).context(format!("invalid cache data in redis key {redis_key}"))
+            }
+            _ => Err(anyhow!("invalid data type for redis key {redis_key}")),
+        }
+    }
+
+    async fn insert(
+        &self,
+        key: &str,
+        data: &GeneratedData,
+    ) -> anyhow::Result<Option<GeneratedData>> {
+        if data.ttl == 0 {
+            return Ok(None);
+        }
+        let buf = encode_entry(data)?;
+        let redis_key = self.redis_key(key);
+        let mut conn = self.get_conn().await?;
+        let set: redis::Value = redis::cmd("SET")
+            .arg(&redis_key)
+            .arg(buf)
+            .arg("NX")
+            .arg("EX")
+            .arg(data.ttl)
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| {
+                self.reset_conn();
+                anyhow!("failed to set redis key {redis_key}: {e}")
+            })?;
+        if matches!(set, redis::Value::Nil) {
+            // the key has been set by another instance
+            self.get(key).await
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_data(ttl: u32) -> GeneratedData {
+        GeneratedData {
+            cert: "-----BEGIN CERTIFICATE-----\n-----END CERTIFICATE-----\n".to_string(),
+            key: vec![0x30, 0x82, 0x00, 0xff],
+            ttl,
+        }
+    }
+
+    #[test]
+    fn entry_round_trip() {
+        let data = test_data(3600);
+        let buf = encode_entry(&data).unwrap();
+        let decoded = decode_entry(&buf).unwrap().unwrap();
+        assert_eq!(decoded.cert, data.cert);
+        assert_eq!(decoded.key, data.key);
+        assert!(decoded.ttl <= 3600);
+        assert!(decoded.ttl >= 3590);
+    }
+
+    #[test]
+    fn entry_expired() {
+        let buf = encode_entry(&test_data(0)).unwrap();
+        assert!(decode_entry(&buf).unwrap().is_none());
+    }
+
+    #[test]
+    fn entry_invalid() {
+        assert!(decode_entry(b"").is_err());
+
+        let mut buf = Vec::new();
+        rmpv::encode::write_value_ref(&mut buf, &ValueRef::Integer(1.into())).unwrap();
+        assert!(decode_entry(&buf).is_err());
+
+        let map = vec![
+            (
+                ValueRef::String(ENTRY_KEY_CERT.into()),
+                ValueRef::String("cert".into()),
+            ),
+            (
+                ValueRef::String(ENTRY_KEY_EXPIRE.into()),
+                ValueRef::Integer((unix_now() + 60).into()),
+            ),
+        ];
+        let mut buf = Vec::new();
+        rmpv::encode::write_value_ref(&mut buf, &ValueRef::Map(map)).unwrap();
+        assert!(decode_entry(&buf).is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn local_cache_permission() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let directory =
+            std::env::temp_dir().join(format!("g3fcgen-cache-test-{}/cache", std::process::id()));
+        let config = LocalCacheConfig {
+            directory: directory.clone(),
+        };
+        let cache = LocalCertCache::new(&config).unwrap();
+        let meta = std::fs::metadata(&directory).unwrap();
+        assert_eq!(meta.permissions().mode() & 0o777, 0o700);
+
+        let data = test_data(3600);
+        cache.insert("www.example.net", &data).await.unwrap();
+        let meta = std::fs::metadata(cache.entry_path("www.example.net")).unwrap();
+        assert_eq!(meta.permissions().mode() & 0o777, 0o600);
+        let cached = cache.get("www.example.net").await.unwrap().unwrap();
+        assert_eq!(cached.cert, data.cert);
+        assert_eq!(cached.key, data.key);
+
+        cache.insert("expired", &test_data(0)).await.unwrap();
+        cache.purge_expired().await.unwrap();
+        assert!(!cache.entry_path("expired").exists());
+        assert!(cache.get("expired").await.unwrap().is_none());
+
+        std::fs::set_permissions(&directory, std::fs::Permissions::from_mode(0o755)).unwrap();
+        assert!(LocalCertCache::new(&config).is_err());
+
+        std::fs::remove_dir_all(directory.parent().unwrap()).unwrap();
+    }
+}
@@ -14,67 +14,161 @@
  * limitations under the License.
  */
 
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
 use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Duration;
 
-use anyhow::anyhow;
+use anyhow::{anyhow, Context};
 use flume::{Receiver, Sender};
 use log::{debug, error, warn};
+use openssl::hash::MessageDigest;
 use openssl::pkey::{PKey, Private};
+use openssl::sha::Sha256;
 use openssl::x509::X509;
 use tokio::runtime::Handle;
 
 use g3_cert_agent::Request;
-use g3_tls_cert::builder::{MimicCertBuilder, ServerCertBuilder, TlsServerCertBuilder};
+use g3_tls_cert::builder::{MimicCertBuilder, ServerCertBuilder};
 use g3_types::net::{Host, TlsCertUsage};
 
 mod stats;
 pub(crate) use stats::BackendStats;
 
+mod cache;
+pub(crate) use cache::CertCache;
+
 use super::{BackendRequest, BackendResponse};
-use crate::config::OpensslBackendConfig;
+use crate::config::{CertSigner, KeyType, OpensslBackendConfig};
 use crate::frontend::GeneratedData;
 
 pub(crate) struct OpensslBackend {
     config: Arc<OpensslBackendConfig>,
-    builder: ServerCertBuilder,
+    builders: HashMap<KeyType, ServerCertBuilder>,
+    cache: Option<Arc<CertCache>>,
     stats: Arc<BackendStats>,
 }
 
 impl OpensslBackend {
     pub(crate) fn new(
         config: &Arc<OpensslBackendConfig>,
+        cache: Option<Arc<CertCache>>,
         stats: &Arc<BackendStats>,
     ) -> anyhow::Result<Self> {
-        let builder = TlsServerCertBuilder::new_ec256()?;
+        let mut builders = HashMap::new();
+        let key_type = config.fake_key_type(&config.default_signer);
+        let builder = key_type.new_server_cert_builder()?;
+        builders.insert(key_type, builder);
+
         Ok(OpensslBackend {
             config: Arc::clone(config),
-            builder,
+            builders,
+            cache,
             stats: Arc::clone(stats),
         })
     }
 
     pub(crate) fn refresh(&mut self) -> anyhow::Result<()> {
         self.stats.add_refresh_total();
-        self.builder.refresh_datetime()?;
-        self.builder.refresh_ec256()?;
+        for (key_type, builder) in self.builders.iter_mut() {
+            *builder = key_type.new_server_cert_builder()?;
+        }
         self.stats.add_refresh_ok();
         Ok(())
     }
 
+    /// Get the cache key for the request.
+    ///
+    /// All fields that affect the generated certificate should be included,
+    /// so that instances sharing the same cache will serve the same certificate.
+    fn cache_key(&self, req: &Request) -> anyhow::Result<String> {
+        let host = Host::from_str(req.host_str());
+        let signer = self
+            .config
+            .select_signer(req.requester(), host.as_ref().ok(), req.service());
+
+        let mut hasher = Sha256::new();
+        hasher.update(&signer.ca_fingerprint);
+        hasher.update(req.service().as_str().as_bytes());
+        hasher.update(&[0]);
+        hasher.update(req.cert_usage().as_str().as_bytes());
+        hasher.update(&[0]);
+        hasher.update(req.host_str().as_bytes());
+        hasher.update(&[0]);
+        if let Some(mimic_cert) = req.cert() {
+            let fingerprint = mimic_cert
+                .digest(MessageDigest::sha256())
+                .map_err(|e| anyhow!("failed to get mimic cert fingerprint: {e}"))?;
+            hasher.update(&fingerprint);
+            if self.config.keep_serial {
+                hasher.update(b"keep_serial");
+            }
+        } else {
+            let key_type = self.config.fake_key_type(signer);
+            hasher.update(format!("{key_type:?}").as_bytes());
+        }
+        Ok(hex::encode(hasher.finish()))
+    }
+
+    async fn process(&mut self, req: &Request) -> anyhow::Result<GeneratedData> {
+        let Some(cache) = self.cache.clone() else {
+            return self.generate(req);
+        };
+
+        let host = req.host();
+        let key = self.cache_key(req)?;
+        match cache.get(&key).await {
+            Ok(Some(mut data)) => {
+                self.stats.add_cache_hit();
+                data.ttl = data.ttl.min(self.config.max_ttl as u32);
+                return Ok(data);
+            }
+            Ok(None) => self.stats.add_cache_miss(),
+            Err(e) => {
+                self.stats.add_cache_miss();
+                warn!("{host} - failed to get cert from cache: {e:?}");
+            }
+        }
+
+        let data = self.generate(req)?;
+        match cache.insert(&key, &data).await {
+            Ok(Some(mut cached)) => {
+                cached.ttl = cached.ttl.min(self.config.max_ttl as u32);
+                Ok(cached)
+            }
+            Ok(None) => Ok(data),
+            Err(e) => {
+                warn!("{host} - failed to store cert to cache: {e:?}");
+                Ok(data)
+            }
+        }
+    }
+
     fn generate(&mut self, req: &Request) -> anyhow::Result<GeneratedData> {
         self.stats.add_request_total();
+        let config = Arc::clone(&self.config);
+        let host = Host::from_str(req.host_str());
+        let signer = config.select_signer(req.requester(), host.as_ref().ok(), req.service());
         if let Some(mimic_cert) = req.cert() {
-            self.generate_mimic(mimic_cert, req.cert_usage())
+            self.generate_mimic(mimic_cert, req.cert_usage(), signer)
         } else {
-            let host = Host::from_str(req.host_str())?;
-            self.builder.refresh_serial()?;
-            let cert =
-                self.builder
-                    .build_fake(&host, &self.config.ca_cert, &self.config.ca_key, None)?;
-            let ttl = self.builder.valid_seconds()?;
-            self.pack_data(cert, self.builder.pkey(), ttl)
+            let host = host?;
+            let key_type = config.fake_key_type(signer);
+            let builder = match self.builders.entry(key_type) {
+                Entry::Occupied(o) => o.into_mut(),
+                Entry::Vacant(v) => {
+                    let builder = key_type.new_server_cert_builder().context(format!(
+                        "failed to create cert builder for key type {key_type:?}"
+                    ))?;
+                    v.insert(builder)
+                }
+            };
+            builder.refresh_serial()?;
+            let cert = builder.build_fake(&host, &signer.ca_cert, &signer.ca_key, None)?;
+            let ttl = builder.valid_seconds()?;
+            let pkey = builder.pkey().clone();
+            self.pack_data(cert, &pkey, ttl, signer)
         }
     }
 
@@ -82,34 +176,31 @@ impl OpensslBackend {
         &self,
         mimic_cert: &X509,
         cert_usage: TlsCertUsage,
+        signer: &CertSigner,
     ) -> anyhow::Result<GeneratedData> {
         let mut mimic_builder = MimicCertBuilder::new(mimic_cert)?;
         mimic_builder.set_keep_serial(self.config.keep_serial);
 
         let cert = match cert_usage {
             TlsCertUsage::TlsServer => {
-                mimic_builder.build_tls_cert(&self.config.ca_cert, &self.config.ca_key, None)?
+                mimic_builder.build_tls_cert(&signer.ca_cert, &signer.ca_key, None)?
             }
             TlsCertUsage::TLsServerTongsuo => mimic_builder.build_tls_cert_with_new_usage(
-                &self.config.ca_cert,
-                &self.config.ca_key,
-                None,
-            )?,
-            TlsCertUsage::TlcpServerEncryption => mimic_builder.build_tlcp_enc_cert(
-                &self.config.ca_cert,
-                &self.config.ca_key,
-                None,
-            )?,
-            TlsCertUsage::TlcpServerSignature => mimic_builder.build_tlcp_sign_cert(
-                &self.config.ca_cert,
-                &self.config.ca_key,
+                &signer.ca_cert,
+                &signer.ca_key,
                 None,
             )?,
+            TlsCertUsage::TlcpServerEncryption => {
+                mimic_builder.build_tlcp_enc_cert(&signer.ca_cert, &signer.ca_key, None)?
+            }
+            TlsCertUsage::TlcpServerSignature => {
+                mimic_builder.build_tlcp_sign_cert(&signer.ca_cert, &signer.ca_key, None)?
+            }
         };
 
         let ttl = mimic_builder.valid_seconds()?;
 
-        self.pack_data(cert, mimic_builder.pkey(), ttl)
+        self.pack_data(cert, mimic_builder.pkey(), ttl, signer)
     }
 
     fn pack_data(
@@ -117,13 +208,14 @@ impl OpensslBackend {
         cert: X509,
         pkey: &PKey<Private>,
         ttl: i32,
+        signer: &CertSigner,
     ) -> anyhow::Result<GeneratedData> {
         let ttl = ttl.clamp(0, self.config.max_ttl) as u32;
         let mut cert_pem = cert
             .to_pem()
             .map_err(|e| anyhow!("failed to encode cert to PEM format: {e}"))?;
-        if !self.config.ca_cert_pem.is_empty() {
-            cert_pem.extend_from_slice(&self.config.ca_cert_pem);
+        if !signer.ca_cert_pem.is_empty() {
+            cert_pem.extend_from_slice(&signer.ca_cert_pem);
         }
         let key = pkey
             .private_key_to_der()
@@ -162,7 +254,7 @@ impl OpensslBackend {
 
                         let host = req.user_req.host();
                         debug!("{host} - [#{id}] start cert generation");
-                        match self.generate(&req.user_req) {
+                        match self.process(&req.user_req).await {
                             Ok(data) => {
                                 debug!("{host} - [#{id}] cert generated");
                                 if let Err(e) = rsp_sender.send_async(req.into_response(data)).await {
@@ -22,6 +22,8 @@ pub(crate) struct BackendStats {
     refresh_ok: AtomicU64,
     request_total: AtomicU64,
     request_ok: AtomicU64,
+    cache_hit: AtomicU64,
+    cache_miss: AtomicU64,
 }
 
 macro_rules! impl_for_field {
@@ -41,4 +43,6 @@ impl BackendStats {
     impl_for_field!(add_refresh_ok, take_refresh_ok, refresh_ok);
     impl_for_field!(add_request_total, take_request_total, request_total);
     impl_for_field!(add_request_ok, take_request_ok, request_ok);
+    impl_for_field!(add_cache_hit, take_cache_hit, cache_hit);
+    impl_for_field!(add_cache_miss, take_cache_miss, cache_miss);
 }
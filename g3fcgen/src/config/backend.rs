@@ -1,5 +1,5 @@
 /*
- * Copyright 2023 ByteDance and/or its affiliates.
+ * Copyright 2024 ByteDance and/or its affiliates.
  *
  * Licensed under the Apache License, Version 2.0 (the "License");
  * you may not use this file except in compliance with the License.
@@ -13,16 +13,20 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
-
+use std::collections::HashMap;
+use std::str::FromStr;
 use std::sync::Arc;
 use std::sync::OnceLock;
 
 use anyhow::{anyhow, Context};
-use openssl::pkey::{PKey, Private};
-use openssl::x509::X509;
 use yaml_rust::Yaml;
 
 use g3_histogram::HistogramMetricsConfig;
+use g3_types::net::{Host, TlsServiceType};
+use g3_types::route::HostMatch;
+use g3_yaml::YamlMapCallback;
+
+use super::{CertCacheConfig, CertSigner, CertSignerConfig, KeyType};
 
 static BACKEND_CONFIG_LOCK: OnceLock<Arc<OpensslBackendConfig>> = OnceLock::new();
 
@@ -31,51 +35,129 @@ pub(crate) fn get_config() -> Option<Arc<OpensslBackendConfig>> {
 }
 
 pub(crate) struct OpensslBackendConfig {
-    pub(crate) ca_cert: X509,
-    pub(crate) ca_key: PKey<Private>,
-    pub(crate) ca_cert_pem: Vec<u8>,
+    pub(crate) default_signer: Arc<CertSigner>,
+    pub(crate) requester_signers: HashMap<String, Arc<CertSigner>>,
+    pub(crate) host_signers: HostMatch<Arc<CertSigner>>,
+    pub(crate) service_signers: HashMap<TlsServiceType, Arc<CertSigner>>,
+    pub(crate) key_type: KeyType,
     pub(crate) keep_serial: bool,
     pub(crate) max_ttl: i32,
+    pub(crate) cache: Option<CertCacheConfig>,
     pub(crate) duration_stats: HistogramMetricsConfig,
 }
 
+impl OpensslBackendConfig {
+    /// Select the signer in the order: requester, host, tls service type.
+    pub(crate) fn select_signer(
+        &self,
+        requester: Option<&str>,
+        host: Option<&Host>,
+        service: TlsServiceType,
+    ) -> &Arc<CertSigner> {
+        if let Some(requester) = requester {
+            if let Some(signer) = self.requester_signers.get(requester) {
+                return signer;
+            }
+        }
+        if let Some(host) = host {
+            if let Some(signer) = self.host_signers.get(host) {
+                return signer;
+            }
+        }
+        self.service_signers
+            .get(&service)
+            .unwrap_or(&self.default_signer)
+    }
+
+    pub(crate) fn fake_key_type(&self, signer: &CertSigner) -> KeyType {
+        signer.key_type.unwrap_or(self.key_type)
+    }
+}
+
+fn as_cert_signer(value: &Yaml) -> anyhow::Result<Arc<CertSigner>> {
+    let Yaml::Hash(map) = value else {
+        return Err(anyhow!("yaml value type for 'cert signer' should be 'map'"));
+    };
+    let mut config = CertSignerConfig::default();
+    g3_yaml::foreach_kv(map, |k, v| {
+        config.parse_kv(&g3_yaml::key::normalize(k), v, None)
+    })?;
+    let signer = config.build()?;
+    Ok(Arc::new(signer))
+}
+
+fn as_requester_signers(value: &Yaml) -> anyhow::Result<HashMap<String, Arc<CertSigner>>> {
+    if let Yaml::Hash(map) = value {
+        let mut signers = HashMap::new();
+        g3_yaml::foreach_kv(map, |k, v| {
+            let signer = as_cert_signer(v)
+                .context(format!("invalid cert signer value for requester {k}"))?;
+            signers.insert(k.to_string(), signer);
+            Ok(())
+        })?;
+        Ok(signers)
+    } else {
+        Err(anyhow!(
+            "yaml value type for 'requester cert signers' should be 'map'"
+        ))
+    }
+}
+
+fn as_service_signers(value: &Yaml) -> anyhow::Result<HashMap<TlsServiceType, Arc<CertSigner>>> {
+    if let Yaml::Hash(map) = value {
+        let mut signers = HashMap::new();
+        g3_yaml::foreach_kv(map, |k, v| {
+            let service =
+                TlsServiceType::from_str(k).map_err(|_| anyhow!("invalid tls service type {k}"))?;
+            let signer =
+                as_cert_signer(v).context(format!("invalid cert signer value for service {k}"))?;
+            signers.insert(service, signer);
+            Ok(())
+        })?;
+        Ok(signers)
+    } else {
+        Err(anyhow!(
+            "yaml value type for 'service cert signers' should be 'map'"
+        ))
+    }
+}
+
 pub(super) fn load_config(value: &Yaml) -> anyhow::Result<()> {
     if let Yaml::Hash(map) = value {
-        let mut no_append_ca_cert = false;
-        let mut ca_cert_pem = Vec::new();
-        let mut ca_cert: Option<X509> = None;
-        let mut ca_key: Option<PKey<Private>> = None;
+        let mut default_signer = CertSignerConfig::default();
+        let mut requester_signers = HashMap::new();
+        let mut host_signers = HostMatch::default();
+        let mut service_signers = HashMap::new();
+        let mut key_type = KeyType::Ec256;
         let mut keep_serial = false;
         let mut max_ttl = 24 * 3600; // 1 day
+        let mut cache = None;
         let mut duration_stats = HistogramMetricsConfig::default();
-        let lookup_dir = g3_daemon::config::get_lookup_dir(None)?;
 
         g3_yaml::foreach_kv(map, |k, v| match g3_yaml::key::normalize(k).as_str() {
-            "ca_certificate" => {
-                let certs = g3_yaml::value::as_openssl_certificates(v, Some(lookup_dir))
-                    .context(format!("invalid openssl certificate value for key {k}"))?;
-                for (i, cert) in certs.iter().enumerate() {
-                    let pem = cert.to_pem().map_err(|e| {
-                        anyhow!("failed to convert cert {i} back to pem format: {e}")
-                    })?;
-                    ca_cert_pem.extend(pem);
-                }
-
-                let cert = certs
-                    .into_iter()
-                    .next()
-                    .ok_or_else(|| anyhow!("no valid openssl certificate key found"))?;
-                ca_cert = Some(cert);
+            "key_type" => {
+                let s = g3_yaml::value::as_string(v)?;
+                key_type = KeyType::from_str(&s)?;
                 Ok(())
             }
-            "ca_private_key" => {
-                let key = g3_yaml::value::as_openssl_private_key(v, Some(lookup_dir))
-                    .context(format!("invalid openssl private key value for key {k}"))?;
-                ca_key = Some(key);
+            "requester_ca" | "requester_signer" => {
+                requester_signers = as_requester_signers(v)
+                    .context(format!("invalid requester cert signer value for key {k}"))?;
                 Ok(())
             }
-            "no_append_ca_cert" => {
-                no_append_ca_cert = g3_yaml::value::as_bool(v)?;
+            "host_ca" | "host_signer" => {
+                let signers = g3_yaml::value::as_host_matched_obj::<CertSignerConfig>(v, None)
+                    .context(format!(
+                        "invalid host matched cert signer value for key {k}"
+                    ))?;
+                host_signers = signers
+                    .try_build_arc(|config| config.build())
+                    .context(format!("invalid cert signer value for key {k}"))?;
+                Ok(())
+            }
+            "service_ca" | "service_signer" => {
+                service_signers = as_service_signers(v)
+                    .context(format!("invalid service cert signer value for key {k}"))?;
                 Ok(())
             }
             "keep_serial" => {
@@ -87,32 +169,32 @@ pub(super) fn load_config(value: &Yaml) -> anyhow::Result<()> {
                 max_ttl = v.max(300); // at least for 5 minutes
                 Ok(())
             }
+            "cache" => {
+                let config = CertCacheConfig::parse_yaml(v)
+                    .context(format!("invalid cert cache config value for key {k}"))?;
+                cache = Some(config);
+                Ok(())
+            }
             "duration_stats" | "duration_metrics" => {
                 duration_stats = g3_yaml::value::as_histogram_metrics_config(v).context(
                     format!("invalid histogram metrics config value for key {k}"),
                 )?;
                 Ok(())
             }
-            _ => Err(anyhow!("invalid key {k}")),
+            normalized_key => default_signer.parse_kv(normalized_key, v, None),
         })?;
 
-        let Some(ca_cert) = ca_cert else {
-            return Err(anyhow!("no ca certificate set"));
-        };
-        let Some(ca_key) = ca_key else {
-            return Err(anyhow!("no ca private key set"));
-        };
-
-        if no_append_ca_cert {
-            ca_cert_pem.clear();
-        }
+        let default_signer = default_signer.build()?;
         BACKEND_CONFIG_LOCK
             .set(Arc::new(OpensslBackendConfig {
-                ca_cert,
-                ca_key,
-                ca_cert_pem,
+                default_signer: Arc::new(default_signer),
+                requester_signers,
+                host_signers,
+                service_signers,
+                key_type,
                 keep_serial,
                 max_ttl,
+                cache,
                 duration_stats,
             }))
             .map_err(|_| anyhow!("duplicate backend config"))?;
@@ -123,3 +205,94 @@ pub(super) fn load_config(value: &Yaml) -> anyhow::Result<()> {
         ))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use g3_tls_cert::builder::RootCertBuilder;
+    use openssl::hash::MessageDigest;
+
+    fn new_signer(name: &str, key_type: Option<KeyType>) -> Arc<CertSigner> {
+        let mut builder = RootCertBuilder::new_ec256().unwrap();
+        builder
+            .subject_builder_mut()
+            .set_common_name(name.to_string());
+        let ca_cert = builder.build(None).unwrap();
+        let ca_fingerprint = ca_cert.digest(MessageDigest::sha256()).unwrap().to_vec();
+        Arc::new(CertSigner {
+            ca_cert,
+            ca_key: builder.pkey().clone(),
+            ca_cert_pem: Vec::new(),
+            ca_fingerprint,
+            key_type,
+        })
+    }
+
+    fn signer_name(signer: &CertSigner) -> String {
+        signer
+            .ca_cert
+            .subject_name()
+            .entries()
+            .next()
+            .unwrap()
+            .data()
+            .as_utf8()
+            .unwrap()
+            .to_string()
+    }
+
+    fn new_config() -> OpensslBackendConfig {
+        let mut requester_signers = HashMap::new();
+        requester_signers.insert(
+            "tenant-a".to_string(),
+            new_signer("tenant-a", Some(KeyType::Rsa2048)),
+        );
+        let mut host_signers = HostMatch::default();
+        host_signers.add_child_domain("example.net", new_signer("host", Some(KeyType::Ec384)));
+        let mut service_signers = HashMap::new();
+        service_signers.insert(TlsServiceType::Smtp, new_signer("smtp", None));
+
+        OpensslBackendConfig {
+            default_signer: new_signer("default", None),
+            requester_signers,
+            host_signers,
+            service_signers,
+            key_type: KeyType::Ed25519,
+            keep_serial: false,
+            max_ttl: 3600,
+            cache: None,
+            duration_stats: HistogramMetricsConfig::default(),
+        }
+    }
+
+    #[test]
+    fn select_signer() {
+        let config = new_config();
+        let host = Host::from_str("www.example.net").unwrap();
+        let other_host = Host::from_str("www.example.com").unwrap();
+
+        let s = config.select_signer(Some("tenant-a"), Some(&host), TlsServiceType::Smtp);
+        assert_eq!(signer_name(s), "tenant-a");
+        let s = config.select_signer(Some("tenant-b"), Some(&host), TlsServiceType::Smtp);
+        assert_eq!(signer_name(s), "host");
+        let s = config.select_signer(None, Some(&other_host), TlsServiceType::Smtp);
+        assert_eq!(signer_name(s), "smtp");
+        let s = config.select_signer(None, None, TlsServiceType::Smtp);
+        assert_eq!(signer_name(s), "smtp");
+        let s = config.select_signer(Some("tenant-b"), Some(&other_host), TlsServiceType::Http);
+        assert_eq!(signer_name(s), "default");
+    }
+
+    #[test]
+    fn fake_key_type() {
+        let config = new_config();
+        let host = Host::from_str("www.example.net").unwrap();
+
+        let s = config.select_signer(Some("tenant-a"), None, TlsServiceType::Http);
+        assert_eq!(config.fake_key_type(s), KeyType::Rsa2048);
+        let s = config.select_signer(None, Some(&host), TlsServiceType::Http);
+        assert_eq!(config.fake_key_type(s), KeyType::Ec384);
+        let s = config.select_signer(None, None, TlsServiceType::Http);
+        assert_eq!(config.fake_key_type(s), KeyType::Ed25519);
+    }
+}
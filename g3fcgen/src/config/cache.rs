@@ -0,0 +1,111 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context};
+use yaml_rust::{yaml, Yaml};
+
+use g3_redis_client::RedisClientConfigBuilder;
+
+const CONFIG_KEY_CACHE_TYPE: &str = "type";
+
+pub(crate) enum CertCacheConfig {
+    Local(LocalCacheConfig),
+    Redis(Box<RedisCacheConfig>),
+}
+
+pub(crate) struct LocalCacheConfig {
+    pub(crate) directory: PathBuf,
+}
+
+/// The cached private keys are stored in plaintext, so the redis server should be protected
+/// with ACL and TLS, and the key prefix should not be accessible by any other users.
+pub(crate) struct RedisCacheConfig {
+    pub(crate) redis: RedisClientConfigBuilder,
+    pub(crate) key_prefix: String,
+}
+
+impl CertCacheConfig {
+    pub(super) fn parse_yaml(value: &Yaml) -> anyhow::Result<Self> {
+        if let Yaml::Hash(map) = value {
+            let lookup_dir = g3_daemon::config::get_lookup_dir(None)?;
+            let cache_type = g3_yaml::hash_get_required_str(map, CONFIG_KEY_CACHE_TYPE)?;
+
+            match g3_yaml::key::normalize(cache_type).as_str() {
+                "local" | "file" | "disk" => {
+                    let config = LocalCacheConfig::parse_yaml_map(map, lookup_dir)?;
+                    Ok(CertCacheConfig::Local(config))
+                }
+                "redis" => {
+                    let config = RedisCacheConfig::parse_yaml_map(map, lookup_dir)?;
+                    Ok(CertCacheConfig::Redis(Box::new(config)))
+                }
+                _ => Err(anyhow!("unsupported cert cache type {cache_type}")),
+            }
+        } else {
+            Err(anyhow!(
+                "yaml value type for the cert cache config should be 'map'"
+            ))
+        }
+    }
+}
+
+impl LocalCacheConfig {
+    fn parse_yaml_map(map: &yaml::Hash, lookup_dir: &Path) -> anyhow::Result<Self> {
+        let mut directory: Option<PathBuf> = None;
+
+        g3_yaml::foreach_kv(map, |k, v| match g3_yaml::key::normalize(k).as_str() {
+            CONFIG_KEY_CACHE_TYPE => Ok(()),
+            "directory" | "dir" | "path" => {
+                // the directory will be created with mode 0700 when building the cache
+                let path = g3_yaml::value::as_string(v)
+                    .context(format!("invalid directory path value for key {k}"))?;
+                let path = PathBuf::from(path);
+                if path.is_absolute() {
+                    directory = Some(path);
+                } else {
+                    directory = Some(lookup_dir.join(path));
+                }
+                Ok(())
+            }
+            _ => Err(anyhow!("invalid key {k}")),
+        })?;
+
+        let Some(directory) = directory else {
+            return Err(anyhow!("no cache directory set"));
+        };
+        Ok(LocalCacheConfig { directory })
+    }
+}
+
+impl RedisCacheConfig {
+    fn parse_yaml_map(map: &yaml::Hash, lookup_dir: &Path) -> anyhow::Result<Self> {
+        let mut redis = RedisClientConfigBuilder::default();
+        let mut key_prefix = "g3fcgen:".to_string();
+
+        g3_yaml::foreach_kv(map, |k, v| match g3_yaml::key::normalize(k).as_str() {
+            CONFIG_KEY_CACHE_TYPE => Ok(()),
+            "key_prefix" => {
+                key_prefix = g3_yaml::value::as_string(v)?;
+                Ok(())
+            }
+            normalized_key => redis.set_yaml_kv(normalized_key, v, Some(lookup_dir)),
+        })?;
+
+        redis.build().context("invalid redis client config")?;
+        Ok(RedisCacheConfig { redis, key_prefix })
+    }
+}
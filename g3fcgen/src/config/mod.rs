@@ -19,6 +19,12 @@ use std::path::Path;
 use anyhow::anyhow;
 use yaml_rust::{yaml, Yaml};
 
+mod signer;
+pub(crate) use signer::{CertSigner, CertSignerConfig, KeyType};
+
+mod cache;
+pub(crate) use cache::{CertCacheConfig, LocalCacheConfig, RedisCacheConfig};
+
 mod backend;
 pub(crate) use backend::{get_config as get_backend_config, OpensslBackendConfig};
 
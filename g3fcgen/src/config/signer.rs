@@ -0,0 +1,214 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context};
+use openssl::hash::MessageDigest;
+use openssl::pkey::{PKey, Private};
+use openssl::x509::X509;
+use yaml_rust::Yaml;
+
+use g3_tls_cert::builder::{ServerCertBuilder, TlsServerCertBuilder};
+use g3_yaml::{YamlDocPosition, YamlMapCallback};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum KeyType {
+    Rsa2048,
+    Rsa3072,
+    Rsa4096,
+    Ec256,
+    Ec384,
+    Ec521,
+    Ed25519,
+    Ed448,
+    Sm2,
+}
+
+impl KeyType {
+    pub(crate) fn new_server_cert_builder(&self) -> anyhow::Result<ServerCertBuilder> {
+        match self {
+            KeyType::Rsa2048 => TlsServerCertBuilder::new_rsa(2048),
+            KeyType::Rsa3072 => TlsServerCertBuilder::new_rsa(3072),
+            KeyType::Rsa4096 => TlsServerCertBuilder::new_rsa(4096),
+            KeyType::Ec256 => TlsServerCertBuilder::new_ec256(),
+            KeyType::Ec384 => TlsServerCertBuilder::new_ec384(),
+            KeyType::Ec521 => TlsServerCertBuilder::new_ec521(),
+            KeyType::Ed25519 => TlsServerCertBuilder::new_ed25519(),
+            KeyType::Ed448 => TlsServerCertBuilder::new_ed448(),
+            KeyType::Sm2 => TlsServerCertBuilder::new_sm2(),
+        }
+    }
+}
+
+impl FromStr for KeyType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "rsa2048" | "rsa" => Ok(KeyType::Rsa2048),
+            "rsa3072" => Ok(KeyType::Rsa3072),
+            "rsa4096" => Ok(KeyType::Rsa4096),
+            "ec256" | "p256" | "prime256v1" | "ec" => Ok(KeyType::Ec256),
+            "ec384" | "p384" | "secp384r1" => Ok(KeyType::Ec384),
+            "ec521" | "p521" | "secp521r1" => Ok(KeyType::Ec521),
+            "ed25519" => Ok(KeyType::Ed25519),
+            "ed448" => Ok(KeyType::Ed448),
+            "sm2" => Ok(KeyType::Sm2),
+            _ => Err(anyhow!("unsupported key type {s}")),
+        }
+    }
+}
+
+pub(crate) struct CertSigner {
+    pub(crate) ca_cert: X509,
+    pub(crate) ca_key: PKey<Private>,
+    pub(crate) ca_cert_pem: Vec<u8>,
+    pub(crate) ca_fingerprint: Vec<u8>,
+    pub(crate) key_type: Option<KeyType>,
+}
+
+#[derive(Default)]
+pub(crate) struct CertSignerConfig {
+    ca_cert: Option<X509>,
+    ca_key: Option<PKey<Private>>,
+    ca_cert_pem: Vec<u8>,
+    no_append_ca_cert: bool,
+    key_type: Option<KeyType>,
+}
+
+impl CertSignerConfig {
+    pub(crate) fn build(&self) -> anyhow::Result<CertSigner> {
+        let Some(ca_cert) = self.ca_cert.clone() else {
+            return Err(anyhow!("no ca certificate set"));
+        };
+        let Some(ca_key) = self.ca_key.clone() else {
+            return Err(anyhow!("no ca private key set"));
+        };
+        let ca_cert_pem = if self.no_append_ca_cert {
+            Vec::new()
+        } else {
+            self.ca_cert_pem.clone()
+        };
+        let ca_fingerprint = ca_cert
+            .digest(MessageDigest::sha256())
+            .map_err(|e| anyhow!("failed to get ca certificate fingerprint: {e}"))?
+            .to_vec();
+        Ok(CertSigner {
+            ca_cert,
+            ca_key,
+            ca_cert_pem,
+            ca_fingerprint,
+            key_type: self.key_type,
+        })
+    }
+}
+
+impl YamlMapCallback for CertSignerConfig {
+    fn type_name(&self) -> &'static str {
+        "cert signer"
+    }
+
+    fn parse_kv(
+        &mut self,
+        key: &str,
+        value: &Yaml,
+        _doc: Option<&YamlDocPosition>,
+    ) -> anyhow::Result<()> {
+        match key {
+            "ca_certificate" => {
+                let lookup_dir = g3_daemon::config::get_lookup_dir(None)?;
+                let certs = g3_yaml::value::as_openssl_certificates(value, Some(lookup_dir))
+                    .context(format!("invalid openssl certificate value for key {key}"))?;
+                let mut ca_cert_pem = Vec::new();
+                for (i, cert) in certs.iter().enumerate() {
+                    let pem = cert.to_pem().map_err(|e| {
+                        anyhow!("failed to convert cert {i} back to pem format: {e}")
+                    })?;
+                    ca_cert_pem.extend(pem);
+                }
+
+                let cert = certs
+                    .into_iter()
+                    .next()
+                    .ok_or_else(|| anyhow!("no valid openssl certificate key found"))?;
+                self.ca_cert = Some(cert);
+                self.ca_cert_pem = ca_cert_pem;
+                Ok(())
+            }
+            "ca_private_key" => {
+                let lookup_dir = g3_daemon::config::get_lookup_dir(None)?;
+                let key = g3_yaml::value::as_openssl_private_key(value, Some(lookup_dir))
+                    .context(format!("invalid openssl private key value for key {key}"))?;
+                self.ca_key = Some(key);
+                Ok(())
+            }
+            "no_append_ca_cert" => {
+                self.no_append_ca_cert = g3_yaml::value::as_bool(value)?;
+                Ok(())
+            }
+            "key_type" => {
+                let s = g3_yaml::value::as_string(value)?;
+                let key_type = KeyType::from_str(&s)?;
+                self.key_type = Some(key_type);
+                Ok(())
+            }
+            _ => Err(anyhow!("invalid key {key}")),
+        }
+    }
+
+    fn check(&mut self) -> anyhow::Result<()> {
+        if self.ca_cert.is_none() {
+            return Err(anyhow!("no ca certificate set"));
+        }
+        if self.ca_key.is_none() {
+            return Err(anyhow!("no ca private key set"));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openssl::pkey::Id;
+
+    #[test]
+    fn key_type_from_str() {
+        assert_eq!(KeyType::from_str("rsa").unwrap(), KeyType::Rsa2048);
+        assert_eq!(KeyType::from_str("RSA3072").unwrap(), KeyType::Rsa3072);
+        assert_eq!(KeyType::from_str("prime256v1").unwrap(), KeyType::Ec256);
+        assert_eq!(KeyType::from_str("secp384r1").unwrap(), KeyType::Ec384);
+        assert_eq!(KeyType::from_str("Ed25519").unwrap(), KeyType::Ed25519);
+        assert_eq!(KeyType::from_str("sm2").unwrap(), KeyType::Sm2);
+        assert!(KeyType::from_str("dsa").is_err());
+    }
+
+    #[test]
+    fn key_type_builder() {
+        let builder = KeyType::Rsa3072.new_server_cert_builder().unwrap();
+        let pkey = builder.pkey();
+        assert_eq!(pkey.id(), Id::RSA);
+        assert_eq!(pkey.bits(), 3072);
+
+        let builder = KeyType::Ec384.new_server_cert_builder().unwrap();
+        let pkey = builder.pkey();
+        assert_eq!(pkey.id(), Id::EC);
+        assert_eq!(pkey.bits(), 384);
+
+        let builder = KeyType::Ed25519.new_server_cert_builder().unwrap();
+        assert_eq!(builder.pkey().id(), Id::ED25519);
+    }
+}
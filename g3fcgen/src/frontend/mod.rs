@@ -20,6 +20,11 @@ pub(crate) use stats::FrontendStats;
 mod udp_dgram;
 pub(crate) use udp_dgram::UdpDgramFrontend;
 
+mod stream;
+pub(crate) use stream::TcpStreamFrontend;
+#[cfg(unix)]
+pub(crate) use stream::UnixStreamFrontend;
+
 #[derive(Debug)]
 pub(crate) struct GeneratedData {
     pub(crate) cert: String,
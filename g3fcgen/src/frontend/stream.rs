@@ -0,0 +1,221 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::io;
+use std::net::SocketAddr;
+#[cfg(unix)]
+use std::path::Path;
+use std::sync::Arc;
+
+use log::{debug, warn};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpListener;
+#[cfg(unix)]
+use tokio::net::UnixListener;
+use tokio::time::Instant;
+
+use g3_cert_agent::Request;
+use g3_io_ext::LengthPrefixedMsgStream;
+
+use super::FrontendStats;
+use crate::{BackendRequest, ResponsePeer};
+
+const MAX_MSG_SIZE: usize = 1 << 20;
+const MAX_PENDING_RSP: usize = 1024;
+
+async fn serve_stream<S>(
+    stream: S,
+    req_sender: flume::Sender<BackendRequest>,
+    stats: Arc<FrontendStats>,
+) where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut stream = LengthPrefixedMsgStream::new(stream, MAX_MSG_SIZE);
+    let (rsp_sender, rsp_receiver) = flume::bounded::<Vec<u8>>(MAX_PENDING_RSP);
+    // each pending request holds a clone of the sender, and the receiver will be closed
+    // after all of them have been responded or dropped by the backend
+    let mut rsp_sender = Some(rsp_sender);
+
+    loop {
+        tokio::select! {
+            r = stream.recv_msg(), if rsp_sender.is_some() => {
+                let data = match r {
+                    Ok(Some(data)) => data,
+                    Ok(None) => {
+                        // the peer may half close the connection after sending all requests,
+                        // so we still need to send back the pending responses
+                        rsp_sender = None;
+                        continue;
+                    }
+                    Err(e) => {
+                        warn!("failed to recv req: {e}");
+                        break;
+                    }
+                };
+                let Some(sender) = &rsp_sender else {
+                    break;
+                };
+                stats.add_request_total();
+                let recv_time = Instant::now();
+                match Request::parse_req(&data) {
+                    Ok(user_req) => {
+                        debug!("{} - request received", user_req.host());
+                        let req = BackendRequest {
+                            user_req,
+                            peer: ResponsePeer::Stream(sender.clone()),
+                            recv_time,
+                        };
+                        if req_sender.send_async(req).await.is_err() {
+                            break;
+                        }
+                    }
+                    Err(e) => {
+                        stats.add_request_invalid();
+                        warn!("invalid request: {e:?}");
+                    }
+                }
+            }
+            r = rsp_receiver.recv_async() => {
+                let Ok(buf) = r else {
+                    break;
+                };
+                if let Err(e) = stream.send_msg(&buf).await {
+                    stats.add_response_fail();
+                    debug!("failed to send rsp: {e}");
+                    break;
+                }
+            }
+        }
+    }
+}
+
+pub(crate) struct TcpStreamFrontend {
+    listener: TcpListener,
+}
+
+impl TcpStreamFrontend {
+    pub(crate) async fn new(addr: SocketAddr) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr).await?;
+        Ok(TcpStreamFrontend { listener })
+    }
+
+    pub(crate) async fn into_running(
+        self,
+        req_sender: flume::Sender<BackendRequest>,
+        stats: Arc<FrontendStats>,
+    ) {
+        loop {
+            match self.listener.accept().await {
+                Ok((stream, _peer)) => {
+                    let _ = stream.set_nodelay(true);
+                    tokio::spawn(serve_stream(stream, req_sender.clone(), stats.clone()));
+                }
+                Err(e) => {
+                    warn!("failed to accept tcp connection: {e}")
+                }
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+pub(crate) struct UnixStreamFrontend {
+    listener: UnixListener,
+}
+
+#[cfg(unix)]
+impl UnixStreamFrontend {
+    pub(crate) fn new(path: &Path) -> io::Result<Self> {
+        use std::os::unix::fs::FileTypeExt;
+
+        // remove the stale socket file left by the previous process
+        if let Ok(meta) = std::fs::symlink_metadata(path) {
+            if meta.file_type().is_socket() {
+                std::fs::remove_file(path)?;
+            }
+        }
+        let listener = UnixListener::bind(path)?;
+        Ok(UnixStreamFrontend { listener })
+    }
+
+    pub(crate) async fn into_running(
+        self,
+        req_sender: flume::Sender<BackendRequest>,
+        stats: Arc<FrontendStats>,
+    ) {
+        loop {
+            match self.listener.accept().await {
+                Ok((stream, _peer)) => {
+                    tokio::spawn(serve_stream(stream, req_sender.clone(), stats.clone()));
+                }
+                Err(e) => {
+                    warn!("failed to accept unix connection: {e}")
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmpv::ValueRef;
+    use tokio::io::AsyncWriteExt;
+
+    fn encode_req(host: &str) -> Vec<u8> {
+        let mut buf = Vec::new();
+        rmpv::encode::write_value_ref(&mut buf, &ValueRef::String(host.into())).unwrap();
+        buf
+    }
+
+    #[tokio::test]
+    async fn half_close() {
+        let (client, server) = tokio::io::duplex(1024);
+        let (req_sender, req_receiver) = flume::unbounded::<BackendRequest>();
+        let stats = Arc::new(FrontendStats::default());
+        let server = tokio::spawn(serve_stream(server, req_sender, stats.clone()));
+
+        let backend = tokio::spawn(async move {
+            let req1 = req_receiver.recv_async().await.unwrap();
+            let req2 = req_receiver.recv_async().await.unwrap();
+            // respond after the client has closed its write side
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            for req in [req2, req1] {
+                let ResponsePeer::Stream(sender) = req.peer else {
+                    panic!("unexpected response peer");
+                };
+                sender
+                    .send_async(req.user_req.host_str().as_bytes().to_vec())
+                    .await
+                    .unwrap();
+            }
+        });
+
+        let mut client = LengthPrefixedMsgStream::new(client, MAX_MSG_SIZE);
+        client.send_msg(&encode_req("a.example.net")).await.unwrap();
+        client.send_msg(&encode_req("b.example.net")).await.unwrap();
+        let mut client = client.into_inner();
+        client.shutdown().await.unwrap();
+
+        let mut client = LengthPrefixedMsgStream::new(client, MAX_MSG_SIZE);
+        assert_eq!(client.recv_msg().await.unwrap().unwrap(), b"b.example.net");
+        assert_eq!(client.recv_msg().await.unwrap().unwrap(), b"a.example.net");
+        assert!(client.recv_msg().await.unwrap().is_none());
+
+        backend.await.unwrap();
+        server.await.unwrap();
+        assert_eq!(stats.take_request_total(), 2);
+    }
+}
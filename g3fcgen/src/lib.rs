@@ -14,8 +14,10 @@
  * limitations under the License.
  */
 
+use std::io;
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::{anyhow, Context};
 use log::{debug, warn};
@@ -35,21 +37,26 @@ use opts::ProcArgs;
 mod stat;
 
 mod backend;
-use backend::{BackendStats, OpensslBackend};
+use backend::{BackendStats, CertCache, OpensslBackend};
 
 mod frontend;
-use frontend::{FrontendStats, GeneratedData, UdpDgramFrontend};
+use frontend::{FrontendStats, GeneratedData, TcpStreamFrontend, UdpDgramFrontend};
+
+enum ResponsePeer {
+    Udp(SocketAddr),
+    Stream(flume::Sender<Vec<u8>>),
+}
 
 struct BackendRequest {
     user_req: Request,
-    peer: SocketAddr,
+    peer: ResponsePeer,
     recv_time: Instant,
 }
 
 struct BackendResponse {
     user_req: Request,
     generated: GeneratedData,
-    peer: SocketAddr,
+    peer: ResponsePeer,
     recv_time: Instant,
 }
 
@@ -80,14 +87,32 @@ pub async fn run(proc_args: &ProcArgs) -> anyhow::Result<()> {
 
     let (duration_recorder, duration_stats) = backend_config.duration_stats.build_spawned(None);
 
+    let cert_cache = match &backend_config.cache {
+        Some(config) => {
+            let cache = Arc::new(CertCache::new(config)?);
+            let purge_cache = cache.clone();
+            tokio::spawn(async move {
+                let mut interval = tokio::time::interval(Duration::from_secs(3600));
+                loop {
+                    interval.tick().await;
+                    if let Err(e) = purge_cache.purge_expired().await {
+                        warn!("failed to purge expired cert cache: {e:?}");
+                    }
+                }
+            });
+            Some(cache)
+        }
+        None => None,
+    };
+
     let workers = g3_daemon::runtime::worker::foreach(|h| {
-        let backend = OpensslBackend::new(&backend_config, &backend_stats)
+        let backend = OpensslBackend::new(&backend_config, cert_cache.clone(), &backend_stats)
             .context(format!("failed to build backend for worker {}", h.id))?;
         backend.spawn(&h.handle, h.id, req_receiver.clone(), rsp_sender.clone());
         Ok::<(), anyhow::Error>(())
     })?;
     if workers < 1 {
-        let backend = OpensslBackend::new(&backend_config, &backend_stats)
+        let backend = OpensslBackend::new(&backend_config, cert_cache.clone(), &backend_stats)
             .context("failed to build backend for main runtime")?;
         backend.spawn(&Handle::current(), 0, req_receiver, rsp_sender);
     }
@@ -102,6 +127,19 @@ pub async fn run(proc_args: &ProcArgs) -> anyhow::Result<()> {
         )?;
     }
 
+    if let Some(addr) = proc_args.tcp_listen_addr() {
+        let frontend = TcpStreamFrontend::new(addr)
+            .await
+            .context(format!("failed to listen on tcp address {addr}"))?;
+        tokio::spawn(frontend.into_running(req_sender.clone(), frontend_stats.clone()));
+    }
+    #[cfg(unix)]
+    if let Some(path) = proc_args.unix_listen_path() {
+        let frontend = frontend::UnixStreamFrontend::new(path)
+            .context(format!("failed to listen on unix path {}", path.display()))?;
+        tokio::spawn(frontend.into_running(req_sender.clone(), frontend_stats.clone()));
+    }
+
     let udp_listen_addr = proc_args.udp_listen_addr();
     let frontend = UdpDgramFrontend::new(udp_listen_addr).await?;
 
@@ -115,7 +153,7 @@ pub async fn run(proc_args: &ProcArgs) -> anyhow::Result<()> {
                     Ok((len, peer)) => match Request::parse_req(&rcv_buf[0..len]) {
                         Ok(user_req) => {
                             debug!("{} - request received", user_req.host());
-                            let req = BackendRequest {user_req, peer, recv_time};
+                            let req = BackendRequest {user_req, peer: ResponsePeer::Udp(peer), recv_time};
                             if let Err(e) = req_sender.send_async(req).await {
                                 return Err(anyhow!("failed to send request to backend: {e}"));
                             }
@@ -134,10 +172,20 @@ pub async fn run(proc_args: &ProcArgs) -> anyhow::Result<()> {
                         match rsp.user_req.encode_rsp(&rsp.generated.cert, &rsp.generated.key, rsp.generated.ttl) {
                             Ok(buf) => {
                                 frontend_stats.add_response_total();
-                                match frontend.send_rsp(buf.as_slice(), rsp.peer).await {
+                                let rsp_size = buf.len();
+                                let r = match &rsp.peer {
+                                    ResponsePeer::Udp(addr) => frontend.send_rsp(buf.as_slice(), *addr).await,
+                                    ResponsePeer::Stream(sender) => sender
+                                        .try_send(buf)
+                                        .map_err(|e| match e {
+                                            flume::TrySendError::Full(_) => io::Error::other("too many pending responses in stream connection"),
+                                            flume::TrySendError::Disconnected(_) => io::Error::other("stream connection closed"),
+                                        }),
+                                };
+                                match r {
                                     Ok(_) => {
                                         let duration_nanos = rsp.duration();
-                                        debug!("{} - duration: {}ns, rsp size: {}", rsp.user_req.host(), duration_nanos, buf.len());
+                                        debug!("{} - duration: {}ns, rsp size: {}", rsp.user_req.host(), duration_nanos, rsp_size);
                                         let _ = duration_recorder.record(duration_nanos);
                                     }
                                     Err(e) => {
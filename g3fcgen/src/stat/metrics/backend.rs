@@ -32,6 +32,8 @@ pub(crate) fn emit_stats(client: &mut StatsdClient, s: &BackendStats) {
     emit_count!(take_refresh_ok, "refresh_ok");
     emit_count!(take_request_total, "request_total");
     emit_count!(take_request_ok, "request_ok");
+    emit_count!(take_cache_hit, "cache_hit");
+    emit_count!(take_cache_miss, "cache_miss");
 }
 
 pub(crate) fn emit_duration_stats(client: &mut StatsdClient, s: &HistogramStats) {
@@ -14,30 +14,72 @@
  * limitations under the License.
  */
 
-use std::path::Path;
-use std::sync::Arc;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
 
-use anyhow::anyhow;
+use anyhow::{anyhow, Context};
 use yaml_rust::Yaml;
 
+static GEOIP_DB_CONFIG: OnceLock<GeoIpDbConfig> = OnceLock::new();
+
+#[derive(Default)]
+pub(crate) struct GeoIpDbConfig {
+    pub(crate) country: Option<PathBuf>,
+    pub(crate) city: Option<PathBuf>,
+    pub(crate) asn: Option<PathBuf>,
+    pub(crate) overrides: Option<PathBuf>,
+    pub(crate) check_interval: Option<Duration>,
+}
+
+pub(crate) fn get_config() -> Option<&'static GeoIpDbConfig> {
+    GEOIP_DB_CONFIG.get()
+}
+
 pub(crate) fn load(v: &Yaml, conf_dir: &Path) -> anyhow::Result<()> {
-    if let Yaml::Hash(map) = v {
-        g3_yaml::foreach_kv(map, |k, v| match g3_yaml::key::normalize(k).as_str() {
-            "country" => {
-                let path = g3_yaml::value::as_file_path(v, conf_dir, false)?;
-                let db = g3_geoip_db::vendor::native::load_country(&path)?;
-                g3_geoip_db::store::store_country(Arc::new(db));
-                Ok(())
-            }
-            "asn" => {
-                let path = g3_yaml::value::as_file_path(v, conf_dir, false)?;
-                let db = g3_geoip_db::vendor::native::load_asn(&path)?;
-                g3_geoip_db::store::store_asn(Arc::new(db));
-                Ok(())
+    let Yaml::Hash(map) = v else {
+        return Err(anyhow!("invalid value type"));
+    };
+
+    let mut config = GeoIpDbConfig::default();
+    g3_yaml::foreach_kv(map, |k, v| match g3_yaml::key::normalize(k).as_str() {
+        "country" => {
+            let path = g3_yaml::value::as_file_path(v, conf_dir, false)
+                .context(format!("invalid file path value for key {k}"))?;
+            config.country = Some(path);
+            Ok(())
+        }
+        "city" => {
+            let path = g3_yaml::value::as_file_path(v, conf_dir, false)
+                .context(format!("invalid file path value for key {k}"))?;
+            config.city = Some(path);
+            Ok(())
+        }
+        "asn" => {
+            let path = g3_yaml::value::as_file_path(v, conf_dir, false)
+                .context(format!("invalid file path value for key {k}"))?;
+            config.asn = Some(path);
+            Ok(())
+        }
+        "override" | "overrides" => {
+            let path = g3_yaml::value::as_file_path(v, conf_dir, false)
+                .context(format!("invalid file path value for key {k}"))?;
+            config.overrides = Some(path);
+            Ok(())
+        }
+        "check_interval" => {
+            let interval = g3_yaml::humanize::as_duration(v)
+                .context(format!("invalid humanize duration value for key {k}"))?;
+            if !interval.is_zero() {
+                config.check_interval = Some(interval);
             }
-            _ => Err(anyhow!("invalid key {k}")),
-        })
-    } else {
-        Err(anyhow!("invalid value type"))
-    }
+            Ok(())
+        }
+        _ => Err(anyhow!("invalid key {k}")),
+    })?;
+
+    crate::db::load_all(&config)?;
+    GEOIP_DB_CONFIG
+        .set(config)
+        .map_err(|_| anyhow!("geoip db config has already been set"))
 }
@@ -20,6 +20,7 @@ use anyhow::anyhow;
 use yaml_rust::{yaml, Yaml};
 
 mod geoip;
+pub(crate) use geoip::{get_config as get_geoip_db_config, GeoIpDbConfig};
 
 pub fn load() -> anyhow::Result<&'static Path> {
     let config_file =
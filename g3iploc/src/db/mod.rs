@@ -0,0 +1,335 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use anyhow::Context;
+use log::{info, warn};
+
+use g3_geoip_types::{IpLocation, IpLocationBuilder};
+
+use crate::config::GeoIpDbConfig;
+
+mod overrides;
+
+mod stats;
+pub(crate) use stats::DbLoadStats;
+
+static DB_FILES: Mutex<Vec<DbFile>> = Mutex::new(Vec::new());
+
+#[derive(Clone, Copy)]
+pub(crate) enum DbKind {
+    Country,
+    City,
+    Asn,
+    Override,
+}
+
+impl DbKind {
+    pub(crate) const ALL: [DbKind; 4] =
+        [DbKind::Country, DbKind::City, DbKind::Asn, DbKind::Override];
+
+    pub(crate) const fn as_str(&self) -> &'static str {
+        match self {
+            DbKind::Country => "country",
+            DbKind::City => "city",
+            DbKind::Asn => "asn",
+            DbKind::Override => "override",
+        }
+    }
+
+    /// load the db file and store it, return the number of records
+    fn load_and_store(&self, path: &Path) -> anyhow::Result<usize> {
+        match self {
+            DbKind::Country => {
+                let db = g3_geoip_db::vendor::native::load_country(path)?;
+                let (v4, v6) = db.len();
+                g3_geoip_db::store::store_country(Arc::new(db));
+                Ok(v4 + v6)
+            }
+            DbKind::City => {
+                let db = g3_geoip_db::vendor::native::load_city(path)?;
+                let (v4, v6) = db.len();
+                g3_geoip_db::store::store_city(Arc::new(db));
+                Ok(v4 + v6)
+            }
+            DbKind::Asn => {
+                let db = g3_geoip_db::vendor::native::load_asn(path)?;
+                let (v4, v6) = db.len();
+                g3_geoip_db::store::store_asn(Arc::new(db));
+                Ok(v4 + v6)
+            }
+            DbKind::Override => overrides::load_and_store(path),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct FileStamp {
+    modified: SystemTime,
+    len: u64,
+    #[cfg(unix)]
+    ino: u64,
+}
+
+impl FileStamp {
+    fn get(path: &Path) -> Option<Self> {
+        let meta = std::fs::metadata(path).ok()?;
+        Some(FileStamp {
+            modified: meta.modified().ok()?,
+            len: meta.len(),
+            #[cfg(unix)]
+            ino: std::os::unix::fs::MetadataExt::ino(&meta),
+        })
+    }
+
+    /// a new file has been moved to the path
+    #[cfg(unix)]
+    fn is_replaced(&self, other: &FileStamp) -> bool {
+        self.ino != other.ino
+    }
+
+    #[cfg(not(unix))]
+    fn is_replaced(&self, _other: &FileStamp) -> bool {
+        false
+    }
+}
+
+struct DbFile {
+    kind: DbKind,
+    path: PathBuf,
+    /// the stamp of the file at the last load attempt
+    loaded: Option<FileStamp>,
+    /// the stamp of the changed file found at the last check
+    pending: Option<FileStamp>,
+}
+
+impl DbFile {
+    fn new(kind: DbKind, path: &Path) -> Self {
+        DbFile {
+            kind,
+            path: path.to_path_buf(),
+            loaded: None,
+            pending: None,
+        }
+    }
+
+    /// Check if the file should be reloaded.
+    ///
+    /// A file replaced by rename will be reloaded at once. A file modified in place may be
+    /// still being written, so it will only be reloaded if it keeps unchanged until the next check.
+    fn check_modified(&mut self) -> bool {
+        let Some(stamp) = FileStamp::get(&self.path) else {
+            self.pending = None;
+            return false;
+        };
+        let Some(loaded) = &self.loaded else {
+            return true;
+        };
+        if stamp.eq(loaded) {
+            self.pending = None;
+            return false;
+        }
+        if stamp.is_replaced(loaded) || self.pending == Some(stamp) {
+            return true;
+        }
+        self.pending = Some(stamp);
+        false
+    }
+
+    fn load(&mut self) -> anyhow::Result<()> {
+        // the same file won't be retried if failed
+        self.loaded = FileStamp::get(&self.path);
+        self.pending = None;
+        let records = self.kind.load_and_store(&self.path).context(format!(
+            "failed to load {} db from file {}",
+            self.kind.as_str(),
+            self.path.display()
+        ))?;
+        DbLoadStats::get(self.kind).set_loaded(records);
+        info!(
+            "loaded {records} records to {} db from file {}",
+            self.kind.as_str(),
+            self.path.display()
+        );
+        Ok(())
+    }
+}
+
+pub(crate) fn load_all(config: &GeoIpDbConfig) -> anyhow::Result<()> {
+    let mut files = Vec::with_capacity(DbKind::ALL.len());
+    for (kind, path) in [
+        (DbKind::Country, &config.country),
+        (DbKind::City, &config.city),
+        (DbKind::Asn, &config.asn),
+        (DbKind::Override, &config.overrides),
+    ] {
+        if let Some(path) = path {
+            let mut file = DbFile::new(kind, path);
+            file.load()?;
+            files.push(file);
+        }
+    }
+
+    let mut all_files = DB_FILES.lock().unwrap();
+    *all_files = files;
+    Ok(())
+}
+
+/// reload the db files, the old db will be kept if failed
+fn reload(force: bool) {
+    let mut files = DB_FILES.lock().unwrap();
+    for file in files.iter_mut() {
+        if !force && !file.check_modified() {
+            continue;
+        }
+        if let Err(e) = file.load() {
+            DbLoadStats::get(file.kind).add_reload_failed();
+            warn!("{e:?}");
+        }
+    }
+}
+
+pub(crate) async fn reload_all() {
+    let _ = tokio::task::spawn_blocking(|| reload(true)).await;
+}
+
+pub(crate) fn spawn_watcher(check_interval: Duration) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(check_interval);
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            let _ = tokio::task::spawn_blocking(|| reload(false)).await;
+        }
+    });
+}
+
+pub(crate) fn locate(ip: IpAddr) -> Option<IpLocation> {
+    // the override records always take precedence
+    if let Some(location) = overrides::longest_match(ip) {
+        return Some(location);
+    }
+
+    let mut builder = IpLocationBuilder::default();
+
+    if let Some(db) = g3_geoip_db::store::load_country() {
+        if let Some((net, v)) = db.longest_match(ip) {
+            builder.set_network(net);
+            builder.set_country(v.country);
+            builder.set_continent(v.continent);
+        }
+    }
+
+    if let Some(city_db) = g3_geoip_db::store::load_city() {
+        if let Some((net, v)) = city_db.longest_match(ip) {
+            builder.set_network(net);
+            builder.set_country(v.country);
+            builder.set_continent(v.continent);
+            if let Some(region) = v.region() {
+                builder.set_region(region.to_string());
+            }
+            if let Some(city) = v.city() {
+                builder.set_city(city.to_string());
+            }
+        }
+    }
+
+    if let Some(asn_db) = g3_geoip_db::store::load_asn() {
+        if let Some((net, v)) = asn_db.longest_match(ip) {
+            builder.set_network(net);
+            builder.set_as_number(v.number);
+            if let Some(name) = v.isp_name() {
+                builder.set_isp_name(name.to_string());
+            }
+            if let Some(domain) = v.isp_domain() {
+                builder.set_isp_domain(domain.to_string());
+            }
+            builder.set_hosting(v.is_hosting());
+        }
+    }
+
+    builder.build().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn located_asn(ip: &str) -> Option<u32> {
+        overrides::longest_match(IpAddr::from_str(ip).unwrap())?.network_asn()
+    }
+
+    #[test]
+    fn reload_override() {
+        let _guard = overrides::TEST_LOCK
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+
+        let dir = std::env::temp_dir();
+        let path = dir.join(format!(
+            "g3iploc-{}-override-reload.yaml",
+            std::process::id()
+        ));
+        std::fs::write(&path, "- network: 10.0.0.0/8\n  asn: 64512\n").unwrap();
+
+        let mut file = DbFile::new(DbKind::Override, &path);
+        assert!(file.check_modified());
+        file.load().unwrap();
+        assert!(!file.check_modified());
+        assert_eq!(located_asn("10.0.0.1"), Some(64512));
+
+        // modified in place, only reload if unchanged until the next check
+        std::fs::write(&path, "- network: 10.0.0.0/8\n  asn: 6451").unwrap();
+        assert!(!file.check_modified());
+        std::fs::write(&path, "- network: 10.0.0.0/8\n  asn: 64513\n").unwrap();
+        assert!(!file.check_modified());
+        assert!(file.check_modified());
+        file.load().unwrap();
+        assert!(!file.check_modified());
+        assert_eq!(located_asn("10.0.0.1"), Some(64513));
+
+        // replaced by rename, reload at once
+        #[cfg(unix)]
+        {
+            let tmp_path = dir.join(format!(
+                "g3iploc-{}-override-reload.tmp",
+                std::process::id()
+            ));
+            std::fs::write(&tmp_path, "- network: 10.0.0.0/8\n  asn: 64514\n").unwrap();
+            std::fs::rename(&tmp_path, &path).unwrap();
+            assert!(file.check_modified());
+            file.load().unwrap();
+            assert_eq!(located_asn("10.0.0.1"), Some(64514));
+        }
+
+        // the old db is kept if failed, and the same file won't be retried
+        std::fs::write(&path, "- network: 10.0.0.0/8\n  asn: 645xx\n").unwrap();
+        assert!(!file.check_modified());
+        assert!(file.check_modified());
+        assert!(file.load().is_err());
+        assert!(!file.check_modified());
+        assert!(!file.check_modified());
+        assert!(located_asn("10.0.0.1").is_some());
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(!file.check_modified());
+    }
+}
@@ -0,0 +1,125 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::net::IpAddr;
+use std::path::Path;
+use std::sync::{Arc, LazyLock};
+
+use anyhow::{anyhow, Context};
+use arc_swap::ArcSwapOption;
+use ip_network_table::IpNetworkTable;
+use yaml_rust::{Yaml, YamlLoader};
+
+use g3_geoip_types::IpLocation;
+
+static OVERRIDE_DB: LazyLock<ArcSwapOption<IpNetworkTable<IpLocation>>> =
+    LazyLock::new(|| ArcSwapOption::new(None));
+
+pub(super) fn longest_match(ip: IpAddr) -> Option<IpLocation> {
+    let db = OVERRIDE_DB.load();
+    let (_, location) = db.as_ref()?.longest_match(ip)?;
+    Some(location.clone())
+}
+
+/// The override file should be a yaml file, with each doc to be a sequence of ip locations
+pub(super) fn load_and_store(path: &Path) -> anyhow::Result<usize> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| anyhow!("failed to read file {}: {e}", path.display()))?;
+    let docs = YamlLoader::load_from_str(&content)
+        .map_err(|e| anyhow!("invalid yaml file {}: {e}", path.display()))?;
+
+    let mut table = IpNetworkTable::new();
+    for (i, doc) in docs.iter().enumerate() {
+        match doc {
+            Yaml::Array(seq) => {
+                for (j, v) in seq.iter().enumerate() {
+                    let location = g3_yaml::value::as_ip_location(v).context(format!(
+                        "invalid ip location value for record #{j} in doc #{i}"
+                    ))?;
+                    table.insert(location.network_addr(), location);
+                }
+            }
+            Yaml::Null => {}
+            _ => return Err(anyhow!("yaml doc #{i} should be a sequence")),
+        }
+    }
+
+    let (v4, v6) = table.len();
+    OVERRIDE_DB.store(Some(Arc::new(table)));
+    Ok(v4 + v6)
+}
+
+/// all tests that change the global override db should hold this lock
+#[cfg(test)]
+pub(super) static TEST_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::str::FromStr;
+
+    use g3_geoip_types::IsoCountryCode;
+
+    fn temp_file(name: &str, content: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("g3iploc-{}-{name}", std::process::id()));
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn load_and_store_override() {
+        let _guard = TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+        let path = temp_file(
+            "override-load.yaml",
+            r#"
+---
+- network: 10.0.0.0/8
+  country: CN
+- network: 10.1.0.0/16
+  as_number: 64512
+---
+---
+- network: 2001:db8::/32
+  country: US
+"#,
+        );
+        assert_eq!(load_and_store(&path).unwrap(), 3);
+
+        let location = longest_match(IpAddr::from_str("10.1.2.3").unwrap()).unwrap();
+        assert_eq!(location.network_asn(), Some(64512));
+        let location = longest_match(IpAddr::from_str("10.2.3.4").unwrap()).unwrap();
+        assert_eq!(location.country(), Some(IsoCountryCode::CN));
+        let location = longest_match(IpAddr::from_str("2001:db8::1").unwrap()).unwrap();
+        assert_eq!(location.country(), Some(IsoCountryCode::US));
+        assert!(longest_match(IpAddr::from_str("192.0.2.1").unwrap()).is_none());
+
+        // the old db should be kept if failed
+        std::fs::write(&path, "---\nnetwork: 192.0.2.0/24\n").unwrap();
+        assert!(load_and_store(&path).is_err());
+        std::fs::write(
+            &path,
+            "---\n- network: 192.0.2.0/24\n  country: CN\n  asn: -1\n",
+        )
+        .unwrap();
+        assert!(load_and_store(&path).is_err());
+        assert!(longest_match(IpAddr::from_str("10.2.3.4").unwrap()).is_some());
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(load_and_store(&path).is_err());
+    }
+}
@@ -0,0 +1,73 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::SystemTime;
+
+use super::DbKind;
+
+static DB_LOAD_STATS: [DbLoadStats; DbKind::ALL.len()] = [
+    DbLoadStats::new(),
+    DbLoadStats::new(),
+    DbLoadStats::new(),
+    DbLoadStats::new(),
+];
+
+pub(crate) struct DbLoadStats {
+    records: AtomicU64,
+    load_timestamp: AtomicU64,
+    reload_failed: AtomicU64,
+}
+
+impl DbLoadStats {
+    const fn new() -> Self {
+        DbLoadStats {
+            records: AtomicU64::new(0),
+            load_timestamp: AtomicU64::new(0),
+            reload_failed: AtomicU64::new(0),
+        }
+    }
+
+    pub(crate) fn get(kind: DbKind) -> &'static DbLoadStats {
+        &DB_LOAD_STATS[kind as usize]
+    }
+
+    pub(super) fn set_loaded(&self, records: usize) {
+        let timestamp = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        self.records.store(records as u64, Ordering::Relaxed);
+        self.load_timestamp.store(timestamp, Ordering::Relaxed);
+    }
+
+    pub(super) fn add_reload_failed(&self) {
+        self.reload_failed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn records(&self) -> u64 {
+        self.records.load(Ordering::Relaxed)
+    }
+
+    /// the unix timestamp of the last successful load, 0 if never loaded
+    pub(crate) fn load_timestamp(&self) -> u64 {
+        self.load_timestamp.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn take_reload_failed(&self) -> u64 {
+        self.reload_failed.swap(0, Ordering::Relaxed)
+    }
+}
@@ -13,9 +13,47 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+use log::warn;
+
+use g3_ip_locate::{Request, Response};
 
 mod stats;
 pub(crate) use stats::FrontendStats;
 
 mod udp_dgram;
 pub(crate) use udp_dgram::UdpDgramFrontend;
+
+mod stream;
+pub(crate) use stream::TcpStreamFrontend;
+#[cfg(unix)]
+pub(crate) use stream::UnixStreamFrontend;
+
+fn handle_req(data: &[u8], stats: &FrontendStats) -> Option<Vec<u8>> {
+    stats.add_request_total();
+
+    let req = match Request::parse_req(data) {
+        Ok(req) => req,
+        Err(e) => {
+            stats.add_request_invalid();
+            warn!("invalid request: {e:?}");
+            return None;
+        }
+    };
+    let Some(ip) = req.ip() else {
+        stats.add_request_invalid();
+        return None;
+    };
+
+    let location = crate::db::locate(ip)?;
+
+    match Response::encode_new(ip, location, 300) {
+        Ok(buf) => {
+            stats.add_response_total();
+            Some(buf)
+        }
+        Err(e) => {
+            warn!("failed to encode response for ip {ip}: {e}");
+            None
+        }
+    }
+}
@@ -0,0 +1,121 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::io;
+use std::net::SocketAddr;
+#[cfg(unix)]
+use std::path::Path;
+use std::sync::Arc;
+
+use log::{debug, warn};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpListener;
+#[cfg(unix)]
+use tokio::net::UnixListener;
+
+use g3_io_ext::LengthPrefixedMsgStream;
+
+use super::FrontendStats;
+
+const MAX_MSG_SIZE: usize = 16384;
+
+async fn serve_stream<S>(stream: S, stats: Arc<FrontendStats>)
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut stream = LengthPrefixedMsgStream::new(stream, MAX_MSG_SIZE);
+
+    loop {
+        let req = match stream.recv_msg().await {
+            Ok(Some(req)) => req,
+            // all responses have been sent, as each request is handled inline
+            Ok(None) => break,
+            Err(e) => {
+                warn!("failed to recv req: {e}");
+                break;
+            }
+        };
+
+        let Some(buf) = super::handle_req(&req, &stats) else {
+            continue;
+        };
+        if let Err(e) = stream.send_msg(&buf).await {
+            stats.add_response_fail();
+            debug!("failed to send rsp: {e}");
+            break;
+        }
+    }
+}
+
+pub(crate) struct TcpStreamFrontend {
+    listener: TcpListener,
+    stats: Arc<FrontendStats>,
+}
+
+impl TcpStreamFrontend {
+    pub(crate) async fn new(addr: SocketAddr, stats: Arc<FrontendStats>) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr).await?;
+        Ok(TcpStreamFrontend { listener, stats })
+    }
+
+    pub(crate) async fn into_running(self) {
+        loop {
+            match self.listener.accept().await {
+                Ok((stream, _peer)) => {
+                    let _ = stream.set_nodelay(true);
+                    tokio::spawn(serve_stream(stream, self.stats.clone()));
+                }
+                Err(e) => {
+                    warn!("failed to accept tcp connection: {e}")
+                }
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+pub(crate) struct UnixStreamFrontend {
+    listener: UnixListener,
+    stats: Arc<FrontendStats>,
+}
+
+#[cfg(unix)]
+impl UnixStreamFrontend {
+    pub(crate) fn new(path: &Path, stats: Arc<FrontendStats>) -> io::Result<Self> {
+        use std::os::unix::fs::FileTypeExt;
+
+        // remove the stale socket file left by the previous process
+        if let Ok(meta) = std::fs::symlink_metadata(path) {
+            if meta.file_type().is_socket() {
+                std::fs::remove_file(path)?;
+            }
+        }
+        let listener = UnixListener::bind(path)?;
+        Ok(UnixStreamFrontend { listener, stats })
+    }
+
+    pub(crate) async fn into_running(self) {
+        loop {
+            match self.listener.accept().await {
+                Ok((stream, _peer)) => {
+                    tokio::spawn(serve_stream(stream, self.stats.clone()));
+                }
+                Err(e) => {
+                    warn!("failed to accept unix connection: {e}")
+                }
+            }
+        }
+    }
+}
@@ -21,9 +21,6 @@ use std::sync::Arc;
 use log::warn;
 use tokio::net::UdpSocket;
 
-use g3_geoip_types::IpLocationBuilder;
-use g3_ip_locate::{Request, Response};
-
 use super::FrontendStats;
 
 pub(crate) struct UdpDgramFrontend {
@@ -59,58 +56,11 @@ impl UdpDgramFrontend {
         loop {
             match self.recv_req(&mut recv_buf).await {
                 Ok((len, addr)) => {
-                    self.stats.add_request_total();
-
-                    let req = match Request::parse_req(&recv_buf[..len]) {
-                        Ok(req) => req,
-                        Err(e) => {
-                            self.stats.add_request_invalid();
-                            warn!("invalid request: {e:?}");
-                            continue;
-                        }
-                    };
-                    let Some(ip) = req.ip() else {
-                        self.stats.add_request_invalid();
-                        continue;
-                    };
-
-                    let mut builder = IpLocationBuilder::default();
-
-                    if let Some(db) = g3_geoip_db::store::load_country() {
-                        if let Some((net, v)) = db.longest_match(ip) {
-                            builder.set_network(net);
-                            builder.set_country(v.country);
-                            builder.set_continent(v.continent);
-                        }
-                    }
-
-                    if let Some(asn_db) = g3_geoip_db::store::load_asn() {
-                        if let Some((net, v)) = asn_db.longest_match(ip) {
-                            builder.set_network(net);
-                            builder.set_as_number(v.number);
-                            if let Some(name) = v.isp_name() {
-                                builder.set_isp_name(name.to_string());
-                            }
-                            if let Some(domain) = v.isp_domain() {
-                                builder.set_isp_domain(domain.to_string());
-                            }
-                        }
-                    }
-
-                    let Ok(location) = builder.build() else {
+                    let Some(buf) = super::handle_req(&recv_buf[..len], &self.stats) else {
                         continue;
                     };
-
-                    match Response::encode_new(ip, location, 300) {
-                        Ok(buf) => {
-                            self.stats.add_response_total();
-                            if self.send_rsp(&buf, addr).await.is_err() {
-                                self.stats.add_response_fail();
-                            }
-                        }
-                        Err(e) => {
-                            warn!("failed to encode response for ip {ip}: {e}")
-                        }
+                    if self.send_rsp(&buf, addr).await.is_err() {
+                        self.stats.add_response_fail();
                     }
                 }
                 Err(e) => {
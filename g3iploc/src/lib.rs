@@ -16,6 +16,8 @@
 
 use std::sync::Arc;
 
+use anyhow::Context;
+
 pub mod config;
 
 mod build;
@@ -25,8 +27,12 @@ use opts::ProcArgs;
 
 mod stat;
 
+mod db;
+
+pub mod signal;
+
 mod frontend;
-use frontend::{FrontendStats, UdpDgramFrontend};
+use frontend::{FrontendStats, TcpStreamFrontend, UdpDgramFrontend};
 
 pub async fn run(proc_args: &ProcArgs) -> anyhow::Result<()> {
     let frontend_stats = Arc::new(FrontendStats::default());
@@ -34,6 +40,25 @@ pub async fn run(proc_args: &ProcArgs) -> anyhow::Result<()> {
         stat::spawn_working_thread(stats_config, frontend_stats.clone())?;
     }
 
+    if let Some(check_interval) =
+        config::get_geoip_db_config().and_then(|config| config.check_interval)
+    {
+        db::spawn_watcher(check_interval);
+    }
+
+    if let Some(addr) = proc_args.tcp_listen_addr() {
+        let frontend = TcpStreamFrontend::new(addr, frontend_stats.clone())
+            .await
+            .context(format!("failed to listen on tcp address {addr}"))?;
+        tokio::spawn(frontend.into_running());
+    }
+    #[cfg(unix)]
+    if let Some(path) = proc_args.unix_listen_path() {
+        let frontend = frontend::UnixStreamFrontend::new(path, frontend_stats.clone())
+            .context(format!("failed to listen on unix path {}", path.display()))?;
+        tokio::spawn(frontend.into_running());
+    }
+
     let udp_listen_addr = proc_args.udp_listen_addr();
 
     let frontend = UdpDgramFrontend::new(udp_listen_addr, frontend_stats).await?;
@@ -59,7 +59,7 @@ fn tokio_run(args: &ProcArgs) -> anyhow::Result<()> {
         .start()
         .context("failed to start runtime")?;
     rt.block_on(async {
-        // TODO setup signal handler
+        g3iploc::signal::register().context("failed to setup signal handler")?;
 
         g3iploc::run(args).await
     })
@@ -16,7 +16,7 @@
 
 use std::env;
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::sync::OnceLock;
 
@@ -35,6 +35,8 @@ static DAEMON_GROUP: OnceLock<String> = OnceLock::new();
 pub struct ProcArgs {
     pub daemon_config: DaemonArgs,
     udp_addr: Option<SocketAddr>,
+    tcp_addr: Option<SocketAddr>,
+    unix_path: Option<PathBuf>,
 }
 
 impl Default for ProcArgs {
@@ -42,6 +44,8 @@ impl Default for ProcArgs {
         ProcArgs {
             daemon_config: DaemonArgs::new(crate::build::PKG_NAME),
             udp_addr: None,
+            tcp_addr: None,
+            unix_path: None,
         }
     }
 }
@@ -51,6 +55,14 @@ impl ProcArgs {
         self.udp_addr
             .unwrap_or_else(|| SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 2888))
     }
+
+    pub(crate) fn tcp_listen_addr(&self) -> Option<SocketAddr> {
+        self.tcp_addr
+    }
+
+    pub(crate) fn unix_listen_path(&self) -> Option<&Path> {
+        self.unix_path.as_deref()
+    }
 }
 
 fn build_cli_args() -> Command {
@@ -125,6 +137,16 @@ pub fn parse_clap() -> anyhow::Result<Option<ProcArgs>> {
             proc_args.udp_addr = Some(addr);
         }
     }
+    if let Ok(s) = env::var("TCP_LISTEN_ADDR") {
+        if let Ok(addr) = SocketAddr::from_str(&s) {
+            proc_args.tcp_addr = Some(addr);
+        }
+    }
+    if let Ok(s) = env::var("UNIX_LISTEN_PATH") {
+        if !s.is_empty() {
+            proc_args.unix_path = Some(PathBuf::from(s));
+        }
+    }
 
     Ok(Some(proc_args))
 }
@@ -0,0 +1,39 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+#[cfg(unix)]
+pub fn register() -> anyhow::Result<()> {
+    use std::future::poll_fn;
+
+    use anyhow::anyhow;
+    use log::info;
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hup_sig = signal(SignalKind::hangup())
+        .map_err(|e| anyhow!("failed to create SIGHUP listener: {e}"))?;
+    tokio::spawn(async move {
+        while poll_fn(|cx| hup_sig.poll_recv(cx)).await.is_some() {
+            info!("got reload signal");
+            crate::db::reload_all().await;
+        }
+    });
+    Ok(())
+}
+
+#[cfg(windows)]
+pub fn register() -> anyhow::Result<()> {
+    Ok(())
+}
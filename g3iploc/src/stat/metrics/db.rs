@@ -0,0 +1,44 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use g3_statsd_client::StatsdClient;
+
+use crate::db::{DbKind, DbLoadStats};
+
+const TAG_KEY_DB: &str = "db";
+
+pub(crate) fn emit_stats(client: &mut StatsdClient) {
+    for kind in DbKind::ALL {
+        let s = DbLoadStats::get(kind);
+        let load_timestamp = s.load_timestamp();
+        if load_timestamp == 0 {
+            continue;
+        }
+
+        client
+            .gauge("db.records", s.records())
+            .with_tag(TAG_KEY_DB, kind.as_str())
+            .send();
+        client
+            .gauge("db.load_timestamp", load_timestamp)
+            .with_tag(TAG_KEY_DB, kind.as_str())
+            .send();
+        client
+            .count("db.reload_failed", s.take_reload_failed())
+            .with_tag(TAG_KEY_DB, kind.as_str())
+            .send();
+    }
+}
@@ -14,4 +14,5 @@
  * limitations under the License.
  */
 
+pub(super) mod db;
 pub(super) mod frontend;
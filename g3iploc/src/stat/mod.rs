@@ -40,6 +40,7 @@ pub(crate) fn spawn_working_thread(
             let instant_start = Instant::now();
 
             metrics::frontend::emit_stats(&mut client, &frontend_stats);
+            metrics::db::emit_stats(&mut client);
 
             client.flush_sink();
 
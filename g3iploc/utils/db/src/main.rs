@@ -19,10 +19,11 @@ use std::io::{BufWriter, Write};
 use std::net::IpAddr;
 use std::path::{Path, PathBuf};
 
+use anyhow::anyhow;
 use clap::{value_parser, Arg, ArgAction, ArgMatches, Command, ValueHint};
 use ip_network_table::IpNetworkTable;
 
-use g3_geoip_db::{GeoIpAsnRecord, GeoIpCountryRecord};
+use g3_geoip_db::{GeoIpAsnRecord, GeoIpCityRecord, GeoIpCountryRecord};
 
 const ARG_NATIVE: &str = "native";
 const ARG_IPINFO: &str = "ipinfo";
@@ -30,6 +31,7 @@ const ARG_MAXMIND: &str = "maxmind";
 const ARG_IPFIRE: &str = "ipfire";
 
 const ARG_COUNTRY: &str = "country";
+const ARG_CITY: &str = "city";
 const ARG_ASN: &str = "asn";
 
 const COMMAND_DUMP: &str = "dump";
@@ -73,7 +75,16 @@ fn build_cli_args() -> Command {
                 .help("Set the input country db file")
                 .long(ARG_COUNTRY)
                 .num_args(1)
-                .required_unless_present(ARG_ASN)
+                .required_unless_present_any([ARG_CITY, ARG_ASN])
+                .value_parser(value_parser!(PathBuf))
+                .value_hint(ValueHint::FilePath),
+        )
+        .arg(
+            Arg::new(ARG_CITY)
+                .help("Set the input city db file")
+                .long(ARG_CITY)
+                .num_args(1)
+                .required_unless_present_any([ARG_COUNTRY, ARG_ASN])
                 .value_parser(value_parser!(PathBuf))
                 .value_hint(ValueHint::FilePath),
         )
@@ -82,7 +93,7 @@ fn build_cli_args() -> Command {
                 .help("Set the input asn db file")
                 .long(ARG_ASN)
                 .num_args(1)
-                .required_unless_present(ARG_COUNTRY)
+                .required_unless_present_any([ARG_COUNTRY, ARG_CITY])
                 .value_parser(value_parser!(PathBuf))
                 .value_hint(ValueHint::FilePath),
         )
@@ -137,6 +148,21 @@ fn load_country(
     Ok(table)
 }
 
+fn load_city(args: &ArgMatches, db: &Path) -> anyhow::Result<IpNetworkTable<GeoIpCityRecord>> {
+    let table = if args.get_flag(ARG_NATIVE) {
+        g3_geoip_db::vendor::native::load_city(db)?
+    } else if args.get_flag(ARG_IPINFO) {
+        g3_geoip_db::vendor::ipinfo::load_city(db)?
+    } else if args.get_flag(ARG_MAXMIND) {
+        g3_geoip_db::vendor::maxmind::load_city(db)?
+    } else if args.get_flag(ARG_IPFIRE) {
+        return Err(anyhow!("no city data available in ipfire location dump"));
+    } else {
+        unreachable!()
+    };
+    Ok(table)
+}
+
 fn load_asn(args: &ArgMatches, db: &Path) -> anyhow::Result<IpNetworkTable<GeoIpAsnRecord>> {
     let table = if args.get_flag(ARG_NATIVE) {
         g3_geoip_db::vendor::native::load_asn(db)?
@@ -155,6 +181,8 @@ fn load_asn(args: &ArgMatches, db: &Path) -> anyhow::Result<IpNetworkTable<GeoIp
 fn query(args: &ArgMatches, sub_args: &ArgMatches) -> anyhow::Result<()> {
     if let Some(f) = args.get_one::<PathBuf>(ARG_COUNTRY) {
         query_country(args, sub_args, f)
+    } else if let Some(f) = args.get_one::<PathBuf>(ARG_CITY) {
+        query_city(args, sub_args, f)
     } else if let Some(f) = args.get_one::<PathBuf>(ARG_ASN) {
         query_asn(args, sub_args, f)
     } else {
@@ -188,6 +216,39 @@ fn query_country(args: &ArgMatches, sub_args: &ArgMatches, db: &Path) -> anyhow:
     Ok(())
 }
 
+fn query_city(args: &ArgMatches, sub_args: &ArgMatches, db: &Path) -> anyhow::Result<()> {
+    println!("# loading geoip city data");
+    let geoip_table = load_city(args, db)?;
+    let (v4l, v6l) = geoip_table.len();
+    println!("# loaded {v4l} ipv4 records, {v6l} ipv6 records");
+
+    for ip in sub_args.get_many::<IpAddr>(ARG_IP_LIST).unwrap() {
+        println!("# check for IP {ip}");
+        match geoip_table.longest_match(*ip) {
+            Some((network, r)) => {
+                print!(
+                    "network: {}\ncountry: {}/{}",
+                    network,
+                    r.country.name(),
+                    r.continent.name(),
+                );
+                if let Some(region) = r.region() {
+                    print!("\nregion: {region}");
+                }
+                if let Some(city) = r.city() {
+                    print!("\ncity: {city}");
+                }
+                println!();
+            }
+            None => {
+                println!("no record found");
+            }
+        }
+    }
+
+    Ok(())
+}
+
 fn query_asn(args: &ArgMatches, sub_args: &ArgMatches, db: &Path) -> anyhow::Result<()> {
     println!("# loading geoip asn data");
     let geoip_table = load_asn(args, db)?;
@@ -205,6 +266,9 @@ fn query_asn(args: &ArgMatches, sub_args: &ArgMatches, db: &Path) -> anyhow::Res
                 if let Some(domain) = r.isp_domain() {
                     print!("/{domain}");
                 }
+                if r.is_hosting() {
+                    print!("\nhosting: true");
+                }
                 println!();
             }
             None => {
@@ -219,6 +283,8 @@ fn query_asn(args: &ArgMatches, sub_args: &ArgMatches, db: &Path) -> anyhow::Res
 fn dump(args: &ArgMatches, sub_args: &ArgMatches) -> anyhow::Result<()> {
     if let Some(f) = args.get_one::<PathBuf>(ARG_COUNTRY) {
         dump_country(args, sub_args, f)
+    } else if let Some(f) = args.get_one::<PathBuf>(ARG_CITY) {
+        dump_city(args, sub_args, f)
     } else if let Some(f) = args.get_one::<PathBuf>(ARG_ASN) {
         dump_asn(args, sub_args, f)
     } else {
@@ -247,6 +313,36 @@ fn dump_country(args: &ArgMatches, sub_args: &ArgMatches, db: &Path) -> anyhow::
     Ok(())
 }
 
+fn dump_city(args: &ArgMatches, sub_args: &ArgMatches, db: &Path) -> anyhow::Result<()> {
+    println!("# loading geoip city data");
+    let geoip_table = load_city(args, db)?;
+    let (v4l, v6l) = geoip_table.len();
+    println!("# loaded {v4l} ipv4 records, {v6l} ipv6 records");
+
+    let p = sub_args.get_one::<PathBuf>(ARG_OUTPUT).unwrap();
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(p)?;
+    // use a csv writer as the region and city names may contain comma
+    let mut writer = csv::WriterBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_writer(BufWriter::new(file));
+    for (net, v) in geoip_table.iter() {
+        writer.write_record([
+            net.to_string().as_str(),
+            v.country.alpha2_code(),
+            v.region().unwrap_or_default(),
+            v.city().unwrap_or_default(),
+        ])?;
+    }
+    writer.flush()?;
+
+    Ok(())
+}
+
 fn dump_asn(args: &ArgMatches, sub_args: &ArgMatches, db: &Path) -> anyhow::Result<()> {
     println!("# loading geoip asn data");
     let geoip_table = load_asn(args, db)?;
@@ -262,7 +358,11 @@ fn dump_asn(args: &ArgMatches, sub_args: &ArgMatches, db: &Path) -> anyhow::Resu
         .open(p)?;
     let mut writer = BufWriter::new(file);
     for (net, v) in geoip_table.iter() {
-        writer.write_fmt(format_args!("{net},{}\n", v.number))?;
+        if v.is_hosting() {
+            writer.write_fmt(format_args!("{net},{},hosting\n", v.number))?;
+        } else {
+            writer.write_fmt(format_args!("{net},{}\n", v.number))?;
+        }
     }
     writer.flush()?;
 
@@ -18,6 +18,7 @@ fn main() {
     capnpc::CompilerCommand::new()
         .src_prefix("schema")
         .file("schema/types.capnp")
+        .file("schema/key.capnp")
         .file("schema/proc.capnp")
         .file("schema/server.capnp")
         .run()
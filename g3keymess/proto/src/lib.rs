@@ -21,6 +21,10 @@ pub mod types_capnp {
     include!(concat!(env!("OUT_DIR"), "/types_capnp.rs"));
 }
 
+pub mod key_capnp {
+    include!(concat!(env!("OUT_DIR"), "/key_capnp.rs"));
+}
+
 pub mod proc_capnp {
     include!(concat!(env!("OUT_DIR"), "/proc_capnp.rs"));
 }
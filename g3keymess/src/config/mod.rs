@@ -19,6 +19,8 @@ use std::path::Path;
 use anyhow::anyhow;
 use yaml_rust::{yaml, Yaml};
 
+use g3_daemon::config::{ConfigDump, ConfigDumpFormat, ConfigDumpObject};
+
 pub(crate) mod log;
 pub(crate) mod server;
 pub(crate) mod store;
@@ -36,6 +38,40 @@ pub fn load() -> anyhow::Result<&'static Path> {
     Ok(config_file)
 }
 
+/// Dump the summary of all loaded server and store config.
+///
+/// Only names, types, positions and a few selected server fields are included.
+pub fn dump_summary(format: ConfigDumpFormat) -> anyhow::Result<String> {
+    let mut dump = ConfigDump::default();
+
+    let mut all_server = server::get_all();
+    all_server.sort_by(|a, b| a.name().cmp(b.name()));
+    for c in all_server {
+        let mut o = ConfigDumpObject::new(c.name(), "", c.position().as_ref());
+        o.set_str("listen", c.listen.address());
+        if let Some(logger) = &c.shared_logger {
+            o.set_str("shared_logger", logger);
+        }
+        o.set_duration("request_read_timeout", c.request_read_timeout);
+        o.set_usize("concurrency_limit", c.concurrency_limit);
+        #[cfg(feature = "openssl-async-job")]
+        {
+            o.set_usize("multiplex_queue_depth", c.multiplex_queue_depth);
+            o.set_duration("async_op_timeout", c.async_op_timeout);
+        }
+        dump.add("server", o);
+    }
+
+    let mut all_store = store::get_all();
+    all_store.sort_by(|a, b| a.name().cmp(b.name()));
+    for c in all_store {
+        let o = ConfigDumpObject::new(c.name(), c.store_type(), c.position().as_ref());
+        dump.add("store", o);
+    }
+
+    dump.encode(format)
+}
+
 fn clear_all() {
     server::clear();
     store::clear();
@@ -34,7 +34,6 @@ pub(crate) use registry::{clear, get_all};
 #[derive(Clone)]
 pub(crate) struct KeyServerConfig {
     name: MetricsName,
-    #[allow(unused)]
     position: Option<YamlDocPosition>,
     pub(crate) shared_logger: Option<AsciiString>,
     pub(crate) listen: TcpListenConfig,
@@ -71,6 +70,11 @@ impl KeyServerConfig {
         &self.name
     }
 
+    #[inline]
+    pub(crate) fn position(&self) -> Option<YamlDocPosition> {
+        self.position.clone()
+    }
+
     fn parse(map: &yaml::Hash, position: Option<YamlDocPosition>) -> anyhow::Result<Self> {
         let mut server = KeyServerConfig::new(position);
 
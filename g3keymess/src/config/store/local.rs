@@ -94,6 +94,14 @@ impl KeyStoreConfig for LocalKeyStoreConfig {
         &self.name
     }
 
+    fn position(&self) -> Option<YamlDocPosition> {
+        self.position.clone()
+    }
+
+    fn store_type(&self) -> &'static str {
+        "local"
+    }
+
     async fn load_keys(&self) -> anyhow::Result<()> {
         const BATCH_SIZE: usize = 128;
 
@@ -117,7 +125,7 @@ impl KeyStoreConfig for LocalKeyStoreConfig {
             let path = entry.path();
             match load_key(&path).await {
                 Ok(Some(key)) => {
-                    if let Err(e) = crate::store::add_global(key) {
+                    if let Err(e) = crate::store::add_global(key, Some(self.name())) {
                         warn!("failed to add key from file {}: {e}", path.display());
                     }
                 }
@@ -161,6 +169,7 @@ impl KeyStoreConfig for LocalKeyStoreConfig {
         let mut event_stream = inotify.into_event_stream(buffer)?;
 
         let dir_path = self.dir_path.to_path_buf();
+        let store_name = self.name.clone();
         let async_watch = async move {
             loop {
                 match poll_fn(|cx| event_stream.poll_next_unpin(cx)).await {
@@ -169,7 +178,8 @@ impl KeyStoreConfig for LocalKeyStoreConfig {
                             let path = dir_path.join(p);
                             match load_key(&path).await {
                                 Ok(Some(key)) => {
-                                    if let Err(e) = crate::store::add_global(key) {
+                                    if let Err(e) = crate::store::add_global(key, Some(&store_name))
+                                    {
                                         warn!("failed to add key from file {}: {e}", path.display())
                                     }
                                 }
@@ -33,6 +33,8 @@ const CONFIG_KEY_STORE_TYPE: &str = "type";
 
 pub trait KeyStoreConfig {
     fn name(&self) -> &MetricsName;
+    fn position(&self) -> Option<YamlDocPosition>;
+    fn store_type(&self) -> &'static str;
     async fn load_keys(&self) -> anyhow::Result<()>;
     fn spawn_subscriber(&self) -> anyhow::Result<Option<oneshot::Sender<()>>> {
         Ok(None)
@@ -69,6 +71,8 @@ pub enum AnyKeyStoreConfig {
 
 impl AnyKeyStoreConfig {
     impl_transparent0!(name, &MetricsName);
+    impl_transparent0!(position, Option<YamlDocPosition>);
+    impl_transparent0!(store_type, &'static str);
     impl_async_transparent0!(load_keys, anyhow::Result<()>);
     impl_transparent0!(
         spawn_subscriber,
@@ -84,6 +84,14 @@ impl KeyStoreConfig for RedisKeyStoreConfig {
         &self.name
     }
 
+    fn position(&self) -> Option<YamlDocPosition> {
+        self.position.clone()
+    }
+
+    fn store_type(&self) -> &'static str {
+        "redis"
+    }
+
     async fn load_keys(&self) -> anyhow::Result<()> {
         unimplemented!()
     }
@@ -19,16 +19,34 @@ use std::future::Future;
 use anyhow::anyhow;
 use openssl::pkey::PKey;
 
+use crate::store::GlobalKey;
+
 pub(crate) async fn add_key(pem: &str) -> anyhow::Result<()> {
     let key = PKey::private_key_from_pem(pem.as_bytes())
         .map_err(|e| anyhow!("invalid private key content: {e}"))?;
-    run_in_main_thread(async move { crate::store::add_global(key) }).await
+    run_in_main_thread(async move { crate::store::add_global(key, None) }).await
 }
 
 pub(crate) async fn list_keys() -> anyhow::Result<Vec<Vec<u8>>> {
     run_in_main_thread(async move { Ok(crate::store::get_all_ski()) }).await
 }
 
+pub(crate) async fn del_key(ski: Vec<u8>) -> anyhow::Result<()> {
+    run_in_main_thread(async move {
+        crate::store::del_global(&ski)
+            .map(|_| ())
+            .ok_or_else(|| anyhow!("key not found"))
+    })
+    .await
+}
+
+pub(crate) async fn get_key(ski: Vec<u8>) -> anyhow::Result<GlobalKey> {
+    run_in_main_thread(async move {
+        crate::store::get_by_ski(&ski).ok_or_else(|| anyhow!("key not found"))
+    })
+    .await
+}
+
 pub(crate) async fn check_key(ski: Vec<u8>) -> anyhow::Result<()> {
     run_in_main_thread(async move {
         crate::store::get_by_ski(&ski)
@@ -19,14 +19,17 @@ use std::str::FromStr;
 use anyhow::anyhow;
 use capnp::capability::Promise;
 use capnp_rpc::pry;
+use chrono::SecondsFormat;
 
 use g3_types::metrics::{MetricsTagName, MetricsTagValue};
 
+use g3keymess_proto::key_capnp::key_info;
 use g3keymess_proto::proc_capnp::proc_control;
 use g3keymess_proto::server_capnp::server_control;
 use g3keymess_proto::types_capnp::fetch_result;
 
 use super::set_operation_result;
+use crate::store::GlobalKey;
 
 pub(super) struct ProcControlImpl;
 
@@ -145,6 +148,39 @@ impl proc_control::Server for ProcControlImpl {
         })
     }
 
+    fn unpublish_key(
+        &mut self,
+        params: proc_control::UnpublishKeyParams,
+        mut results: proc_control::UnpublishKeyResults,
+    ) -> Promise<(), capnp::Error> {
+        let ski = pry!(pry!(params.get()).get_ski()).to_vec();
+        Promise::from_future(async move {
+            let r = crate::control::bridge::del_key(ski).await;
+            set_operation_result(results.get().init_result(), r);
+            Ok(())
+        })
+    }
+
+    fn describe_key(
+        &mut self,
+        params: proc_control::DescribeKeyParams,
+        mut results: proc_control::DescribeKeyResults,
+    ) -> Promise<(), capnp::Error> {
+        let ski = pry!(pry!(params.get()).get_ski()).to_vec();
+        Promise::from_future(async move {
+            let mut builder = results.get().init_info();
+            match crate::control::bridge::get_key(ski.clone()).await {
+                Ok(key) => set_key_info(builder.init_data(), &ski, &key),
+                Err(e) => {
+                    let mut ev = builder.init_err();
+                    ev.set_code(-1);
+                    ev.set_reason(format!("{e:?}").as_str());
+                }
+            }
+            Ok(())
+        })
+    }
+
     fn add_metrics_tag(
         &mut self,
         params: proc_control::AddMetricsTagParams,
@@ -173,6 +209,37 @@ fn do_add_metrics_tag(name: &str, value: &str) -> anyhow::Result<()> {
     Ok(())
 }
 
+fn set_key_info(mut builder: key_info::Builder<'_>, ski: &[u8], key: &GlobalKey) {
+    builder.set_ski(ski);
+    builder.set_key_type(key.key_type());
+    builder.set_key_bits(key.key_bits());
+    if let Some(store) = key.stats().store() {
+        builder.set_store(store.as_str());
+    }
+    builder.set_load_time(
+        key.load_time()
+            .to_rfc3339_opts(SecondsFormat::Secs, true)
+            .as_str(),
+    );
+
+    let snap = key.stats().snapshot();
+    builder.set_sign_total(snap.sign_total);
+    builder.set_sign_failed(snap.sign_failed);
+    builder.set_decrypt_total(snap.decrypt_total);
+    builder.set_decrypt_failed(snap.decrypt_failed);
+
+    let mut duration = Vec::new();
+    key.stats().duration.foreach_stat(|_, name, v| {
+        duration.push((name.to_string(), v));
+    });
+    let mut duration_builder = builder.init_duration(duration.len() as u32);
+    for (i, (name, v)) in duration.iter().enumerate() {
+        let mut stat_builder = duration_builder.reborrow().get(i as u32);
+        stat_builder.set_name(name.as_str());
+        stat_builder.set_value(*v);
+    }
+}
+
 fn set_fetch_result<'a, T>(
     mut builder: fetch_result::Builder<'a, T>,
     r: anyhow::Result<<T as capnp::traits::Owned>::Reader<'a>>,
@@ -57,6 +57,16 @@ fn main() -> anyhow::Result<()> {
         info!("the format of the config file is ok");
         return Ok(());
     }
+    if proc_args.daemon_config.check_config {
+        // there is no reference between config objects, all checks are done while loading
+        info!("the config file is valid");
+        return Ok(());
+    }
+    if let Some(format) = proc_args.daemon_config.dump_config_summary {
+        let content = g3keymess::config::dump_summary(format)?;
+        println!("{content}");
+        return Ok(());
+    }
 
     // enter daemon mode after config loaded
     #[cfg(unix)]
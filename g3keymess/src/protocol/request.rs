@@ -30,6 +30,7 @@ use tokio::io::{AsyncRead, AsyncReadExt};
 use g3_types::net::{T1L2BVParse, TlvParse};
 
 use super::{KeylessDataResponse, KeylessErrorResponse, KeylessPongResponse};
+use crate::store::GlobalKey;
 
 #[derive(Clone, Copy)]
 pub(crate) enum KeylessAction {
@@ -281,10 +282,10 @@ impl KeylessRequest {
         }
     }
 
-    pub(crate) fn find_key(&self) -> Result<PKey<Private>, KeylessErrorResponse> {
+    pub(crate) fn find_key(&self) -> Result<GlobalKey, KeylessErrorResponse> {
         if !self.ski.is_empty() {
             if let Some(k) = crate::store::get_by_ski(&self.ski) {
-                self.check_payload_for_key_size(k.size())?;
+                self.check_payload_for_key_size(k.key().size())?;
                 return Ok(k);
             }
         }
@@ -26,6 +26,7 @@ use uuid::Uuid;
 
 use g3_histogram::HistogramRecorder;
 use g3_slog_types::{LtDateTime, LtUuid};
+use g3_types::ext::DurationExt;
 
 use crate::config::server::KeyServerConfig;
 use crate::protocol::{KeylessAction, KeylessErrorResponse, KeylessRequest};
@@ -33,6 +34,7 @@ use crate::serve::{
     KeyServerDurationRecorder, KeyServerRequestStats, KeyServerStats, ServerReloadCommand,
     ServerTaskError,
 };
+use crate::store::GlobalKey;
 
 #[cfg(feature = "openssl-async-job")]
 mod multiplex;
@@ -97,6 +99,19 @@ impl WrappedKeylessRequest {
     fn take_err_rsp(&mut self) -> Option<KeylessErrorResponse> {
         self.err_rsp.take()
     }
+
+    fn add_key_stats(&self, key: &GlobalKey, passed: bool) {
+        match self.inner.action {
+            KeylessAction::RsaDecrypt(_) => key.stats().add_decrypt(passed),
+            _ => key.stats().add_sign(passed),
+        }
+    }
+
+    fn record_duration(&self, key: &GlobalKey) {
+        let elapsed = self.create_time.elapsed().as_nanos_u64();
+        let _ = self.duration_recorder.record(elapsed);
+        let _ = key.duration_recorder().record(elapsed);
+    }
 }
 
 impl Drop for WrappedKeylessRequest {
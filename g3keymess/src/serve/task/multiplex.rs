@@ -14,7 +14,6 @@
  * limitations under the License.
  */
 
-use openssl::pkey::{PKey, Private};
 use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
 use tokio::sync::{broadcast, mpsc};
 
@@ -24,8 +23,9 @@ use g3_types::ext::DurationExt;
 
 use super::{KeylessTask, WrappedKeylessRequest};
 use crate::log::request::RequestErrorLogContext;
-use crate::protocol::{KeylessErrorResponse, KeylessResponse};
+use crate::protocol::{KeylessAction, KeylessErrorResponse, KeylessResponse};
 use crate::serve::{ServerReloadCommand, ServerTaskError};
+use crate::store::GlobalKey;
 
 impl KeylessTask {
     pub(crate) async fn into_multiplex_running<R, W>(mut self, reader: R, mut writer: W)
@@ -177,7 +177,7 @@ impl KeylessTask {
         &self,
         req: WrappedKeylessRequest,
         rsp: KeylessErrorResponse,
-        key: PKey<Private>,
+        key: GlobalKey,
         msg_sender: &mpsc::Sender<KeylessResponse>,
     ) {
         let server_sem = if let Some(sem) = self.ctx.concurrency_limit.clone() {
@@ -189,9 +189,21 @@ impl KeylessTask {
         let create_time = req.create_time;
         let duration_recorder = req.duration_recorder.clone();
         let req_stats = req.stats.clone();
-        let sync_op = OpensslOperation { req, key };
+        let is_decrypt = matches!(req.inner.action, KeylessAction::RsaDecrypt(_));
+        let add_key_stats = move |key: &GlobalKey, passed: bool| {
+            if is_decrypt {
+                key.stats().add_decrypt(passed);
+            } else {
+                key.stats().add_sign(passed);
+            }
+        };
+        let sync_op = OpensslOperation {
+            req,
+            key: key.clone(),
+        };
         let Ok(task) = TokioAsyncOperation::build_async_task(sync_op) else {
             req_stats.add_crypto_fail();
+            add_key_stats(&key, false);
             let _ = msg_sender
                 .send(KeylessResponse::Error(rsp.crypto_fail()))
                 .await;
@@ -204,35 +216,40 @@ impl KeylessTask {
             let rsp = match tokio::time::timeout(async_op_timeout, task).await {
                 Ok(Ok(r)) => {
                     req_stats.add_passed();
+                    add_key_stats(&key, matches!(r, KeylessResponse::Data(_)));
                     r
                 }
                 Ok(Err(_)) => {
                     req_stats.add_crypto_fail();
+                    add_key_stats(&key, false);
                     KeylessResponse::Error(rsp.crypto_fail())
                 }
                 Err(_) => {
                     req_stats.add_crypto_fail();
+                    add_key_stats(&key, false);
                     KeylessResponse::Error(rsp.crypto_fail())
                 }
             };
             drop(server_sem);
             // send to writer
             let _ = msg_sender.send(rsp).await;
-            let _ = duration_recorder.record(create_time.elapsed().as_nanos_u64());
+            let elapsed = create_time.elapsed().as_nanos_u64();
+            let _ = duration_recorder.record(elapsed);
+            let _ = key.duration_recorder().record(elapsed);
         });
     }
 }
 
 struct OpensslOperation {
     req: WrappedKeylessRequest,
-    key: PKey<Private>,
+    key: GlobalKey,
 }
 
 impl SyncOperation for OpensslOperation {
     type Output = KeylessResponse;
 
     fn run(&mut self) -> anyhow::Result<Self::Output> {
-        let rsp = match self.req.inner.process(&self.key) {
+        let rsp = match self.req.inner.process(self.key.key()) {
             Ok(d) => KeylessResponse::Data(d),
             Err(e) => KeylessResponse::Error(e),
         };
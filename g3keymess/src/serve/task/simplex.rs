@@ -14,17 +14,16 @@
  * limitations under the License.
  */
 
-use openssl::pkey::{PKey, Private};
 use tokio::io::{AsyncRead, AsyncWrite, BufReader};
 use tokio::sync::broadcast;
 
 use g3_io_ext::{LimitedBufReadExt, LimitedWriteExt};
-use g3_types::ext::DurationExt;
 
 use super::{KeylessTask, WrappedKeylessRequest};
 use crate::log::request::RequestErrorLogContext;
 use crate::protocol::KeylessResponse;
 use crate::serve::{ServerReloadCommand, ServerTaskError};
+use crate::store::GlobalKey;
 
 impl KeylessTask {
     pub(crate) async fn into_simplex_running<R, W>(mut self, reader: R, mut writer: W)
@@ -127,24 +126,20 @@ impl KeylessTask {
         drop(server_sem);
 
         let r = self.send_response(writer, rsp).await;
-        let _ = req
-            .duration_recorder
-            .record(req.create_time.elapsed().as_nanos_u64());
+        req.record_duration(&key);
         r
     }
 
-    fn process_by_openssl(
-        &self,
-        req: &WrappedKeylessRequest,
-        key: &PKey<Private>,
-    ) -> KeylessResponse {
-        match req.inner.process(key) {
+    fn process_by_openssl(&self, req: &WrappedKeylessRequest, key: &GlobalKey) -> KeylessResponse {
+        match req.inner.process(key.key()) {
             Ok(d) => {
                 req.stats.add_passed();
+                req.add_key_stats(key, true);
                 KeylessResponse::Data(d)
             }
             Err(e) => {
                 req.stats.add_by_error_code(e.error_code());
+                req.add_key_stats(key, false);
                 KeylessResponse::Error(e)
             }
         }
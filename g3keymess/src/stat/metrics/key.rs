@@ -0,0 +1,110 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::sync::{Arc, LazyLock, Mutex};
+
+use ahash::AHashMap;
+
+use g3_daemon::metrics::{TAG_KEY_QUANTILE, TAG_KEY_REQUEST};
+use g3_statsd_client::{StatsdClient, StatsdTagGroup};
+use g3_types::stats::StatId;
+
+use crate::store::{KeySnapshot, KeyStats};
+
+const TAG_KEY_SKI: &str = "ski";
+const TAG_KEY_STORE: &str = "store";
+
+const METRIC_NAME_KEY_REQUEST_TOTAL: &str = "key.request.total";
+const METRIC_NAME_KEY_REQUEST_FAILED: &str = "key.request.failed";
+const METRIC_NAME_KEY_REQUEST_DURATION: &str = "key.request.duration";
+
+const REQUEST_TYPE_SIGN: &str = "sign";
+const REQUEST_TYPE_DECRYPT: &str = "decrypt";
+
+type KeyStatsValue = (Arc<KeyStats>, KeySnapshot);
+
+static STORE_KEY_STATS_MAP: LazyLock<Mutex<AHashMap<StatId, KeyStatsValue>>> =
+    LazyLock::new(|| Mutex::new(AHashMap::new()));
+static KEY_STATS_MAP: LazyLock<Mutex<AHashMap<StatId, KeyStatsValue>>> =
+    LazyLock::new(|| Mutex::new(AHashMap::new()));
+
+pub(crate) fn push_stats(stats: Arc<KeyStats>) {
+    let k = stats.stat_id();
+    let mut ht = STORE_KEY_STATS_MAP.lock().unwrap();
+    ht.insert(k, (stats, KeySnapshot::default()));
+}
+
+pub(in crate::stat) fn sync_stats() {
+    g3_daemon::metrics::helper::move_ht(&STORE_KEY_STATS_MAP, &KEY_STATS_MAP);
+}
+
+pub(in crate::stat) fn emit_stats(client: &mut StatsdClient) {
+    let mut key_stats_map = KEY_STATS_MAP.lock().unwrap();
+    key_stats_map.retain(|_, (stats, snap)| {
+        emit_key_stats(client, stats, snap);
+        // use Arc instead of Weak here, as we should emit the final metrics before drop it
+        Arc::strong_count(stats) > 1
+    });
+}
+
+fn emit_key_stats(client: &mut StatsdClient, stats: &Arc<KeyStats>, snap: &mut KeySnapshot) {
+    let mut common_tags = StatsdTagGroup::default();
+    common_tags.add_tag(TAG_KEY_SKI, stats.ski());
+    if let Some(store) = stats.store() {
+        common_tags.add_tag(TAG_KEY_STORE, store);
+    }
+
+    let new_snap = stats.snapshot();
+
+    macro_rules! emit_count {
+        ($metric:expr, $id:ident, $request:expr) => {
+            let new_value = new_snap.$id;
+            if new_value != 0 || snap.$id != 0 {
+                let diff_value = new_value.wrapping_sub(snap.$id);
+                client
+                    .count_with_tags($metric, diff_value, &common_tags)
+                    .with_tag(TAG_KEY_REQUEST, $request)
+                    .send();
+                snap.$id = new_value;
+            }
+        };
+    }
+    emit_count!(METRIC_NAME_KEY_REQUEST_TOTAL, sign_total, REQUEST_TYPE_SIGN);
+    emit_count!(
+        METRIC_NAME_KEY_REQUEST_FAILED,
+        sign_failed,
+        REQUEST_TYPE_SIGN
+    );
+    emit_count!(
+        METRIC_NAME_KEY_REQUEST_TOTAL,
+        decrypt_total,
+        REQUEST_TYPE_DECRYPT
+    );
+    emit_count!(
+        METRIC_NAME_KEY_REQUEST_FAILED,
+        decrypt_failed,
+        REQUEST_TYPE_DECRYPT
+    );
+
+    stats.duration.foreach_stat(|_, qs, v| {
+        if v > 0_f64 {
+            client
+                .gauge_float_with_tags(METRIC_NAME_KEY_REQUEST_DURATION, v, &common_tags)
+                .with_tag(TAG_KEY_QUANTILE, qs)
+                .send();
+        }
+    });
+}
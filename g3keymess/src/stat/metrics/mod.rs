@@ -14,4 +14,5 @@
  * limitations under the License.
  */
 
+pub(crate) mod key;
 pub(super) mod server;
@@ -15,35 +15,204 @@
  */
 
 use std::cell::RefCell;
+use std::sync::Arc;
 
-use ahash::AHashMap;
+use ahash::{AHashMap, AHashSet};
 use anyhow::anyhow;
-use openssl::pkey::{PKey, Private};
+use chrono::{DateTime, Utc};
+use openssl::pkey::{Id, PKey, Private};
 
+use g3_histogram::{HistogramMetricsConfig, HistogramRecorder};
 use g3_tls_cert::ext::PublicKeyExt;
+use g3_types::metrics::MetricsName;
 
 mod ops;
 pub use ops::{load_all, reload_all};
 
 mod registry;
 
+mod stats;
+pub(crate) use stats::{KeySnapshot, KeyStats};
+
 thread_local! {
-    static GLOBAL_SKI_MAP: RefCell<AHashMap<Vec<u8>, PKey<Private>>> = RefCell::new(AHashMap::new());
+    static GLOBAL_SKI_MAP: RefCell<AHashMap<Vec<u8>, GlobalKey>> = RefCell::new(AHashMap::new());
+    static UNPUBLISHED_SKI_SET: RefCell<AHashSet<Vec<u8>>> = RefCell::new(AHashSet::new());
+}
+
+#[derive(Clone)]
+pub(crate) struct GlobalKey {
+    key: PKey<Private>,
+    load_time: DateTime<Utc>,
+    stats: Arc<KeyStats>,
+    duration_recorder: Arc<HistogramRecorder<u64>>,
+}
+
+impl GlobalKey {
+    fn new(key: PKey<Private>, ski: &[u8], store: Option<&MetricsName>) -> Self {
+        let (duration_recorder, duration_stats) =
+            HistogramMetricsConfig::default().build_spawned(None);
+        let stats = Arc::new(KeyStats::new(ski, store, duration_stats));
+        crate::stat::metrics::key::push_stats(stats.clone());
+        GlobalKey {
+            key,
+            load_time: Utc::now(),
+            stats,
+            duration_recorder: Arc::new(duration_recorder),
+        }
+    }
+
+    fn reload(&self, key: PKey<Private>) -> Self {
+        GlobalKey {
+            key,
+            load_time: Utc::now(),
+            stats: self.stats.clone(),
+            duration_recorder: self.duration_recorder.clone(),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn key(&self) -> &PKey<Private> {
+        &self.key
+    }
+
+    #[inline]
+    pub(crate) fn stats(&self) -> &Arc<KeyStats> {
+        &self.stats
+    }
+
+    #[inline]
+    pub(crate) fn duration_recorder(&self) -> &HistogramRecorder<u64> {
+        &self.duration_recorder
+    }
+
+    #[inline]
+    pub(crate) fn load_time(&self) -> &DateTime<Utc> {
+        &self.load_time
+    }
+
+    pub(crate) fn key_type(&self) -> &'static str {
+        match self.key.id() {
+            Id::RSA => "rsa",
+            Id::RSA_PSS => "rsa-pss",
+            Id::EC => "ec",
+            Id::ED25519 => "ed25519",
+            Id::ED448 => "ed448",
+            _ => "unknown",
+        }
+    }
+
+    #[inline]
+    pub(crate) fn key_bits(&self) -> u32 {
+        self.key.bits()
+    }
 }
 
-pub(crate) fn add_global(key: PKey<Private>) -> anyhow::Result<()> {
+/// Add a key to the global key map, `store` should be set to the name of the key store
+/// if the key is loaded from it, or None if the key is published through the control api.
+///
+/// Keys unpublished through the control api will be skipped if loaded from key stores,
+/// until they are published again through the control api.
+pub(crate) fn add_global(key: PKey<Private>, store: Option<&MetricsName>) -> anyhow::Result<()> {
     let ski = key.ski().map_err(|e| anyhow!("failed to get SKI: {e}"))?;
+    if store.is_some() {
+        if UNPUBLISHED_SKI_SET.with_borrow(|set| set.contains(ski.as_ref())) {
+            return Ok(());
+        }
+    } else {
+        UNPUBLISHED_SKI_SET.with_borrow_mut(|set| set.remove(ski.as_ref()));
+    }
     GLOBAL_SKI_MAP.with_borrow_mut(|map| {
-        map.insert(ski.to_vec(), key);
+        let new_key = match map.get(ski.as_ref()) {
+            Some(old) if old.stats.store() == store => old.reload(key),
+            _ => GlobalKey::new(key, &ski, store),
+        };
+        map.insert(ski.to_vec(), new_key);
     });
 
     Ok(())
 }
 
+/// Remove a key from the global key map, and block it from being loaded from key stores again.
+pub(crate) fn del_global(ski: &[u8]) -> Option<GlobalKey> {
+    let key = GLOBAL_SKI_MAP.with_borrow_mut(|map| map.remove(ski))?;
+    UNPUBLISHED_SKI_SET.with_borrow_mut(|set| set.insert(ski.to_vec()));
+    Some(key)
+}
+
 pub(crate) fn get_all_ski() -> Vec<Vec<u8>> {
     GLOBAL_SKI_MAP.with_borrow(|map| map.keys().map(|v| v.to_vec()).collect())
 }
 
-pub(crate) fn get_by_ski(ski: &[u8]) -> Option<PKey<Private>> {
+pub(crate) fn get_by_ski(ski: &[u8]) -> Option<GlobalKey> {
     GLOBAL_SKI_MAP.with_borrow(|map| map.get(ski).cloned())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    use openssl::ec::{EcGroup, EcKey};
+    use openssl::nid::Nid;
+
+    fn new_key() -> (PKey<Private>, Vec<u8>) {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+        let key = PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap();
+        let ski = key.ski().unwrap().to_vec();
+        (key, ski)
+    }
+
+    #[tokio::test]
+    async fn publish_unpublish() {
+        let (key, ski) = new_key();
+        add_global(key, None).unwrap();
+        assert!(get_all_ski().contains(&ski));
+
+        let key = get_by_ski(&ski).unwrap();
+        assert_eq!(key.key_type(), "ec");
+        assert_eq!(key.key_bits(), 256);
+        assert!(key.stats().store().is_none());
+
+        assert!(del_global(&ski).is_some());
+        assert!(get_by_ski(&ski).is_none());
+        assert!(del_global(&ski).is_none());
+    }
+
+    #[tokio::test]
+    async fn reload_keep_stats() {
+        let store = MetricsName::from_str("local").unwrap();
+        let (key, ski) = new_key();
+        add_global(key.clone(), Some(&store)).unwrap();
+        let old = get_by_ski(&ski).unwrap();
+        assert_eq!(old.stats().store(), Some(&store));
+
+        add_global(key.clone(), Some(&store)).unwrap();
+        let new = get_by_ski(&ski).unwrap();
+        assert!(Arc::ptr_eq(old.stats(), new.stats()));
+
+        add_global(key, None).unwrap();
+        let new = get_by_ski(&ski).unwrap();
+        assert!(!Arc::ptr_eq(old.stats(), new.stats()));
+        assert!(new.stats().store().is_none());
+    }
+
+    #[tokio::test]
+    async fn unpublished_store_key() {
+        let store = MetricsName::from_str("local").unwrap();
+        let (key, ski) = new_key();
+        add_global(key.clone(), Some(&store)).unwrap();
+        assert!(del_global(&ski).is_some());
+
+        // reloaded from the key store, i.e. on inotify event
+        add_global(key.clone(), Some(&store)).unwrap();
+        assert!(get_by_ski(&ski).is_none());
+
+        // published again through the control api
+        add_global(key.clone(), None).unwrap();
+        assert!(get_by_ski(&ski).is_some());
+        assert!(del_global(&ski).is_some());
+        add_global(key.clone(), None).unwrap();
+        add_global(key, Some(&store)).unwrap();
+        assert_eq!(get_by_ski(&ski).unwrap().stats().store(), Some(&store));
+    }
+}
@@ -0,0 +1,100 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use g3_histogram::HistogramStats;
+use g3_types::metrics::MetricsName;
+use g3_types::stats::StatId;
+
+pub(crate) struct KeyStats {
+    id: StatId,
+    ski: String,
+    store: Option<MetricsName>,
+
+    sign_total: AtomicU64,
+    sign_failed: AtomicU64,
+    decrypt_total: AtomicU64,
+    decrypt_failed: AtomicU64,
+
+    pub(crate) duration: Arc<HistogramStats>,
+}
+
+#[derive(Default)]
+pub(crate) struct KeySnapshot {
+    pub(crate) sign_total: u64,
+    pub(crate) sign_failed: u64,
+    pub(crate) decrypt_total: u64,
+    pub(crate) decrypt_failed: u64,
+}
+
+impl KeyStats {
+    pub(super) fn new(
+        ski: &[u8],
+        store: Option<&MetricsName>,
+        duration: Arc<HistogramStats>,
+    ) -> Self {
+        KeyStats {
+            id: StatId::new(),
+            ski: hex::encode(ski),
+            store: store.cloned(),
+            sign_total: AtomicU64::new(0),
+            sign_failed: AtomicU64::new(0),
+            decrypt_total: AtomicU64::new(0),
+            decrypt_failed: AtomicU64::new(0),
+            duration,
+        }
+    }
+
+    #[inline]
+    pub(crate) fn stat_id(&self) -> StatId {
+        self.id
+    }
+
+    #[inline]
+    pub(crate) fn ski(&self) -> &str {
+        &self.ski
+    }
+
+    #[inline]
+    pub(crate) fn store(&self) -> Option<&MetricsName> {
+        self.store.as_ref()
+    }
+
+    pub(crate) fn add_sign(&self, passed: bool) {
+        self.sign_total.fetch_add(1, Ordering::Relaxed);
+        if !passed {
+            self.sign_failed.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub(crate) fn add_decrypt(&self, passed: bool) {
+        self.decrypt_total.fetch_add(1, Ordering::Relaxed);
+        if !passed {
+            self.decrypt_failed.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub(crate) fn snapshot(&self) -> KeySnapshot {
+        KeySnapshot {
+            sign_total: self.sign_total.load(Ordering::Relaxed),
+            sign_failed: self.sign_failed.load(Ordering::Relaxed),
+            decrypt_total: self.decrypt_total.load(Ordering::Relaxed),
+            decrypt_failed: self.decrypt_failed.load(Ordering::Relaxed),
+        }
+    }
+}
@@ -37,6 +37,8 @@ fn build_cli_args() -> Command {
         .subcommand(proc::commands::list())
         .subcommand(proc::commands::publish_key())
         .subcommand(proc::commands::check_key())
+        .subcommand(proc::commands::unpublish_key())
+        .subcommand(proc::commands::describe_key())
         .subcommand(server::command())
         .subcommand(local::commands::check_dup())
 }
@@ -70,6 +72,8 @@ async fn main() -> anyhow::Result<()> {
                 proc::COMMAND_LIST => proc::list(&proc_control, args).await,
                 proc::COMMAND_PUBLISH_KEY => proc::publish_key(&proc_control, args).await,
                 proc::COMMAND_CHECK_KEY => proc::check_key(&proc_control, args).await,
+                proc::COMMAND_UNPUBLISH_KEY => proc::unpublish_key(&proc_control, args).await,
+                proc::COMMAND_DESCRIBE_KEY => proc::describe_key(&proc_control, args).await,
                 server::COMMAND => server::run(&proc_control, args).await,
                 local::COMMAND_CHECK_DUP => local::check_dup(args),
                 _ => Err(CommandError::Cli(anyhow!(
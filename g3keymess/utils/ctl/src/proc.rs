@@ -14,7 +14,7 @@
  * limitations under the License.
  */
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use anyhow::anyhow;
 use clap::ArgMatches;
@@ -23,6 +23,7 @@ use openssl::pkey::PKey;
 use g3_ctl::{CommandError, CommandResult};
 use g3_tls_cert::ext::PublicKeyExt;
 
+use g3keymess_proto::key_capnp::key_info;
 use g3keymess_proto::proc_capnp::proc_control;
 use g3keymess_proto::server_capnp::server_control;
 
@@ -34,16 +35,19 @@ pub const COMMAND_CANCEL_SHUTDOWN: &str = "cancel-shutdown";
 pub const COMMAND_LIST: &str = "list";
 pub const COMMAND_PUBLISH_KEY: &str = "publish-key";
 pub const COMMAND_CHECK_KEY: &str = "check-key";
+pub const COMMAND_UNPUBLISH_KEY: &str = "unpublish-key";
+pub const COMMAND_DESCRIBE_KEY: &str = "describe-key";
 
 const COMMAND_LIST_ARG_RESOURCE: &str = "resource";
 const RESOURCE_VALUE_SERVER: &str = "server";
 const RESOURCE_VALUE_KEY: &str = "key";
 
 const COMMAND_ARG_FILE: &str = "file";
+const COMMAND_ARG_SKI: &str = "ski";
 
 pub mod commands {
     use super::*;
-    use clap::{value_parser, Arg, Command, ValueHint};
+    use clap::{value_parser, Arg, ArgGroup, Command, ValueHint};
 
     pub fn version() -> Command {
         Command::new(COMMAND_VERSION)
@@ -89,6 +93,37 @@ pub mod commands {
                 .value_hint(ValueHint::FilePath),
         )
     }
+
+    fn select_key(cmd: Command) -> Command {
+        cmd.arg(
+            Arg::new(COMMAND_ARG_FILE)
+                .help("Private key file in pem format")
+                .num_args(1)
+                .value_parser(value_parser!(PathBuf))
+                .value_hint(ValueHint::FilePath),
+        )
+        .arg(
+            Arg::new(COMMAND_ARG_SKI)
+                .help("Subject Key Identifier of the key in hex format")
+                .long(COMMAND_ARG_SKI)
+                .num_args(1),
+        )
+        .group(
+            ArgGroup::new("key")
+                .args([COMMAND_ARG_FILE, COMMAND_ARG_SKI])
+                .required(true),
+        )
+    }
+
+    pub fn unpublish_key() -> Command {
+        select_key(Command::new(COMMAND_UNPUBLISH_KEY).about(
+            "Unpublish a key, it won't be loaded from key stores again until published manually",
+        ))
+    }
+
+    pub fn describe_key() -> Command {
+        select_key(Command::new(COMMAND_DESCRIBE_KEY))
+    }
 }
 
 pub async fn version(client: &proc_control::Client) -> CommandResult<()> {
@@ -157,8 +192,7 @@ pub async fn publish_key(client: &proc_control::Client, args: &ArgMatches) -> Co
     parse_operation_result(rsp.get()?.get_result()?)
 }
 
-pub async fn check_key(client: &proc_control::Client, args: &ArgMatches) -> CommandResult<()> {
-    let file = args.get_one::<PathBuf>(COMMAND_ARG_FILE).unwrap();
+fn load_ski_from_file(file: &Path) -> CommandResult<Vec<u8>> {
     let content = std::fs::read_to_string(file).map_err(|e| {
         CommandError::Cli(anyhow!(
             "failed to read content of file {}: {e}",
@@ -172,9 +206,74 @@ pub async fn check_key(client: &proc_control::Client, args: &ArgMatches) -> Comm
     let ski = key.ski().map_err(|e| {
         CommandError::Cli(anyhow!("failed to get SKI for key {}: {e}", file.display()))
     })?;
+    Ok(ski.to_vec())
+}
+
+fn get_ski(args: &ArgMatches) -> CommandResult<Vec<u8>> {
+    if let Some(file) = args.get_one::<PathBuf>(COMMAND_ARG_FILE) {
+        load_ski_from_file(file)
+    } else {
+        let ski = args.get_one::<String>(COMMAND_ARG_SKI).unwrap();
+        hex::decode(ski).map_err(|e| CommandError::Cli(anyhow!("invalid hex SKI {ski}: {e}")))
+    }
+}
+
+pub async fn check_key(client: &proc_control::Client, args: &ArgMatches) -> CommandResult<()> {
+    let file = args.get_one::<PathBuf>(COMMAND_ARG_FILE).unwrap();
+    let ski = load_ski_from_file(file)?;
 
     let mut req = client.check_key_request();
     req.get().set_ski(&ski);
     let rsp = req.send().promise.await?;
     parse_operation_result(rsp.get()?.get_result()?)
 }
+
+pub async fn unpublish_key(client: &proc_control::Client, args: &ArgMatches) -> CommandResult<()> {
+    let ski = get_ski(args)?;
+
+    let mut req = client.unpublish_key_request();
+    req.get().set_ski(&ski);
+    let rsp = req.send().promise.await?;
+    parse_operation_result(rsp.get()?.get_result()?)
+}
+
+pub async fn describe_key(client: &proc_control::Client, args: &ArgMatches) -> CommandResult<()> {
+    let ski = get_ski(args)?;
+
+    let mut req = client.describe_key_request();
+    req.get().set_ski(&ski);
+    let rsp = req.send().promise.await?;
+    let info = parse_fetch_result(rsp.get()?.get_info()?)?;
+    print_key_info(info)
+}
+
+fn print_key_info(info: key_info::Reader<'_>) -> CommandResult<()> {
+    println!("ski: {}", hex::encode(info.get_ski()?));
+    println!("type: {}", to_str("key_type", info.get_key_type()?)?);
+    println!("bits: {}", info.get_key_bits());
+    let store = to_str("store", info.get_store()?)?;
+    if store.is_empty() {
+        println!("store: <control>");
+    } else {
+        println!("store: {store}");
+    }
+    println!("load time: {}", to_str("load_time", info.get_load_time()?)?);
+    println!("sign total: {}", info.get_sign_total());
+    println!("sign failed: {}", info.get_sign_failed());
+    println!("decrypt total: {}", info.get_decrypt_total());
+    println!("decrypt failed: {}", info.get_decrypt_failed());
+    for stat in info.get_duration()?.iter() {
+        println!(
+            "duration {}: {}ns",
+            to_str("duration.name", stat.get_name()?)?,
+            stat.get_value()
+        );
+    }
+    Ok(())
+}
+
+fn to_str<'a>(field: &'static str, reader: capnp::text::Reader<'a>) -> CommandResult<&'a str> {
+    reader
+        .to_str()
+        .map_err(|reason| CommandError::Utf8 { field, reason })
+}
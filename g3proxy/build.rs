@@ -48,4 +48,8 @@ fn main() {
     if env::var("CARGO_FEATURE_QUIC").is_ok() {
         println!("cargo:rustc-env=G3_QUIC_FEATURE=quinn");
     }
+
+    if env::var("CARGO_FEATURE_GSSAPI").is_ok() {
+        println!("cargo:rustc-env=G3_GSSAPI_FEATURE=gssapi");
+    }
 }
@@ -23,6 +23,7 @@ fn main() {
         .file("schema/resolver.capnp")
         .file("schema/escaper.capnp")
         .file("schema/server.capnp")
+        .file("schema/task.capnp")
         .run()
         .unwrap();
 }
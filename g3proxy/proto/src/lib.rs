@@ -40,3 +40,7 @@ pub mod escaper_capnp {
 pub mod server_capnp {
     include!(concat!(env!("OUT_DIR"), "/server_capnp.rs"));
 }
+
+pub mod task_capnp {
+    include!(concat!(env!("OUT_DIR"), "/task_capnp.rs"));
+}
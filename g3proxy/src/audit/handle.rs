@@ -19,8 +19,8 @@ use std::sync::Arc;
 use slog::Logger;
 
 use g3_dpi::{
-    H1InterceptionConfig, H2InterceptionConfig, ImapInterceptionConfig, ProtocolInspectPolicy,
-    ProtocolInspectionConfig, ProtocolPortMap, SmtpInterceptionConfig,
+    GrpcInterceptionConfig, H1InterceptionConfig, H2InterceptionConfig, ImapInterceptionConfig,
+    ProtocolInspectPolicy, ProtocolInspectionConfig, ProtocolPortMap, SmtpInterceptionConfig,
 };
 use g3_icap_client::reqmod::IcapReqmodClient;
 use g3_icap_client::respmod::IcapRespmodClient;
@@ -125,6 +125,11 @@ impl AuditHandle {
         &self.auditor_config.h2_interception
     }
 
+    #[inline]
+    pub(crate) fn grpc_interception(&self) -> &GrpcInterceptionConfig {
+        &self.auditor_config.grpc_interception
+    }
+
     #[inline]
     pub(crate) fn smtp_interception(&self) -> &SmtpInterceptionConfig {
         &self.auditor_config.smtp_interception
@@ -16,12 +16,13 @@
 
 use std::sync::Arc;
 
-use anyhow::Context;
+use anyhow::{anyhow, Context};
 
 use g3_dpi::ProtocolPortMap;
 use g3_icap_client::IcapServiceClient;
 use g3_types::metrics::MetricsName;
 use g3_types::net::{OpensslTicketKey, RollingTicketer};
+use g3_udpdump::PcapDumper;
 
 use crate::config::audit::AuditorConfig;
 use crate::inspect::tls::TlsInterceptionContext;
@@ -48,6 +49,7 @@ pub(crate) struct Auditor {
     server_tcp_portmap: Arc<ProtocolPortMap>,
     client_tcp_portmap: Arc<ProtocolPortMap>,
     tls_rolling_ticketer: Option<Arc<RollingTicketer<OpensslTicketKey>>>,
+    tls_pcap_dumper: Option<Arc<PcapDumper>>,
     icap_reqmod_service: Option<Arc<IcapServiceClient>>,
     icap_respmod_service: Option<Arc<IcapServiceClient>>,
     #[cfg(feature = "quic")]
@@ -64,6 +66,7 @@ impl Auditor {
             server_tcp_portmap,
             client_tcp_portmap,
             tls_rolling_ticketer: None,
+            tls_pcap_dumper: None,
             icap_reqmod_service: None,
             icap_respmod_service: None,
             #[cfg(feature = "quic")]
@@ -83,11 +86,19 @@ impl Auditor {
         } else {
             None
         };
+        let tls_pcap_dumper = if let Some(c) = &config.tls_stream_pcap {
+            let dumper = PcapDumper::new(c.clone())
+                .map_err(|e| anyhow!("failed to create tls stream pcap dumper: {e}"))?;
+            Some(Arc::new(dumper))
+        } else {
+            None
+        };
         let mut auditor = Auditor {
             config: Arc::new(config),
             server_tcp_portmap,
             client_tcp_portmap,
             tls_rolling_ticketer,
+            tls_pcap_dumper,
             icap_reqmod_service: None,
             icap_respmod_service: None,
             #[cfg(feature = "quic")]
@@ -110,11 +121,21 @@ impl Auditor {
         } else {
             None
         };
+        let tls_pcap_dumper = if self.config.tls_stream_pcap.eq(&config.tls_stream_pcap) {
+            self.tls_pcap_dumper.clone()
+        } else if let Some(c) = &config.tls_stream_pcap {
+            let dumper = PcapDumper::new(c.clone())
+                .map_err(|e| anyhow!("failed to create tls stream pcap dumper: {e}"))?;
+            Some(Arc::new(dumper))
+        } else {
+            None
+        };
         let mut auditor = Auditor {
             config: Arc::new(config),
             server_tcp_portmap,
             client_tcp_portmap,
             tls_rolling_ticketer,
+            tls_pcap_dumper,
             icap_reqmod_service: None,
             icap_respmod_service: None,
             #[cfg(feature = "quic")]
@@ -165,6 +186,7 @@ impl Auditor {
                 client_config,
                 server_config,
                 self.config.tls_stream_dump,
+                self.tls_pcap_dumper.clone(),
             )?;
             handle.set_tls_interception(ctx);
         }
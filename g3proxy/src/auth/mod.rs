@@ -25,6 +25,7 @@ use chrono::Utc;
 use log::{info, warn};
 use tokio::sync::{mpsc, oneshot};
 
+use g3_geoip_types::IpLocation;
 use g3_types::metrics::MetricsName;
 
 use crate::config::auth::UserGroupConfig;
@@ -45,9 +46,10 @@ pub(crate) use user::{User, UserContext};
 
 mod stats;
 pub(crate) use stats::{
-    UserForbiddenSnapshot, UserForbiddenStats, UserRequestSnapshot, UserRequestStats,
-    UserSiteDurationRecorder, UserSiteDurationStats, UserSiteStats, UserTrafficSnapshot,
-    UserTrafficStats, UserUpstreamTrafficSnapshot, UserUpstreamTrafficStats,
+    UserForbiddenSnapshot, UserForbiddenStats, UserRateSnapshot, UserRateWindow, UserRates,
+    UserRequestSnapshot, UserRequestStats, UserSiteDurationRecorder, UserSiteDurationStats,
+    UserSiteStats, UserTrafficSnapshot, UserTrafficStats, UserUpstreamTrafficSnapshot,
+    UserUpstreamTrafficStats,
 };
 
 mod source;
@@ -216,11 +218,16 @@ impl UserGroup {
     }
 
     #[inline]
-    pub(crate) fn allow_anonymous(&self, client_addr: SocketAddr) -> bool {
+    pub(crate) fn allow_anonymous(
+        &self,
+        client_addr: SocketAddr,
+        client_location: Option<&IpLocation>,
+    ) -> bool {
         let Some(user) = &self.anonymous_user else {
             return false;
         };
-        user.check_anonymous_client_addr(client_addr).is_ok()
+        user.check_anonymous_client_addr(client_addr, client_location)
+            .is_ok()
     }
 
     pub(crate) fn get_anonymous_user(&self) -> Option<(Arc<User>, UserType)> {
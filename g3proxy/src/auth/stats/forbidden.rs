@@ -14,6 +14,7 @@
  * limitations under the License.
  */
 
+use std::ops;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 
@@ -38,6 +39,7 @@ pub(crate) struct UserForbiddenStats {
     rate_limited: AtomicU64,
     proto_banned: AtomicU64,
     src_blocked: AtomicU64,
+    src_geo_blocked: AtomicU64,
     dest_denied: AtomicU64,
     ip_blocked: AtomicU64,
     ua_blocked: AtomicU64,
@@ -53,12 +55,30 @@ pub(crate) struct UserForbiddenSnapshot {
     pub(crate) rate_limited: u64,
     pub(crate) proto_banned: u64,
     pub(crate) src_blocked: u64,
+    pub(crate) src_geo_blocked: u64,
     pub(crate) dest_denied: u64,
     pub(crate) ip_blocked: u64,
     pub(crate) ua_blocked: u64,
     pub(crate) log_skipped: u64,
 }
 
+impl ops::AddAssign for UserForbiddenSnapshot {
+    fn add_assign(&mut self, other: Self) {
+        self.auth_failed += other.auth_failed;
+        self.user_expired += other.user_expired;
+        self.user_blocked += other.user_blocked;
+        self.fully_loaded += other.fully_loaded;
+        self.rate_limited += other.rate_limited;
+        self.proto_banned += other.proto_banned;
+        self.src_blocked += other.src_blocked;
+        self.src_geo_blocked += other.src_geo_blocked;
+        self.dest_denied += other.dest_denied;
+        self.ip_blocked += other.ip_blocked;
+        self.ua_blocked += other.ua_blocked;
+        self.log_skipped += other.log_skipped;
+    }
+}
+
 impl UserForbiddenStats {
     pub(crate) fn new(
         user_group: &MetricsName,
@@ -81,6 +101,7 @@ impl UserForbiddenStats {
             rate_limited: Default::default(),
             proto_banned: Default::default(),
             src_blocked: Default::default(),
+            src_geo_blocked: Default::default(),
             dest_denied: Default::default(),
             ip_blocked: Default::default(),
             ua_blocked: Default::default(),
@@ -127,6 +148,7 @@ impl UserForbiddenStats {
             rate_limited: self.rate_limited.load(Ordering::Relaxed),
             proto_banned: self.proto_banned.load(Ordering::Relaxed),
             src_blocked: self.src_blocked.load(Ordering::Relaxed),
+            src_geo_blocked: self.src_geo_blocked.load(Ordering::Relaxed),
             dest_denied: self.dest_denied.load(Ordering::Relaxed),
             ip_blocked: self.ip_blocked.load(Ordering::Relaxed),
             ua_blocked: self.ua_blocked.load(Ordering::Relaxed),
@@ -162,6 +184,10 @@ impl UserForbiddenStats {
         self.src_blocked.fetch_add(1, Ordering::Relaxed);
     }
 
+    pub(crate) fn add_src_geo_blocked(&self) {
+        self.src_geo_blocked.fetch_add(1, Ordering::Relaxed);
+    }
+
     pub(crate) fn add_dest_denied(&self) {
         self.dest_denied.fetch_add(1, Ordering::Relaxed);
     }
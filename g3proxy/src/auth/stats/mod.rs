@@ -20,6 +20,9 @@ pub(crate) use forbidden::{UserForbiddenSnapshot, UserForbiddenStats};
 mod request;
 pub(crate) use request::{UserRequestSnapshot, UserRequestStats};
 
+mod rate;
+pub(crate) use rate::{UserRateSnapshot, UserRateWindow, UserRates};
+
 mod traffic;
 pub(crate) use traffic::{
     UserTrafficSnapshot, UserTrafficStats, UserUpstreamTrafficSnapshot, UserUpstreamTrafficStats,
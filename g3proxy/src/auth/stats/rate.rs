@@ -0,0 +1,190 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+/// Counters of a user sampled at one point in time, used to measure rates
+#[derive(Clone, Copy, Default)]
+pub(crate) struct UserRateSnapshot {
+    pub(crate) conn_total: u64,
+    pub(crate) req_total: u64,
+    pub(crate) rate_limited: u64,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) struct UserRates {
+    pub(crate) conn_rate: f64,
+    pub(crate) req_rate: f64,
+    pub(crate) rate_limited: u64,
+}
+
+impl UserRates {
+    #[inline]
+    pub(crate) fn is_rate_limited(&self) -> bool {
+        self.rate_limited > 0
+    }
+}
+
+impl UserRateSnapshot {
+    /// Get the per second rates between `prev` and this snapshot.
+    ///
+    /// The counters are summed over all per server stats, which may be dropped
+    /// when a server goes offline, so a decreasing counter is treated as zero.
+    pub(crate) fn rates_since(&self, prev: &Self, elapsed: Duration) -> UserRates {
+        let secs = elapsed.as_secs_f64();
+        let rate = |cur: u64, old: u64| {
+            if secs > 0.0 {
+                cur.saturating_sub(old) as f64 / secs
+            } else {
+                0.0
+            }
+        };
+        UserRates {
+            conn_rate: rate(self.conn_total, prev.conn_total),
+            req_rate: rate(self.req_total, prev.req_total),
+            rate_limited: self.rate_limited.saturating_sub(prev.rate_limited),
+        }
+    }
+}
+
+/// The rates of a user measured between the latest two stats ticks
+pub(crate) struct UserRateWindow {
+    snapshot: UserRateSnapshot,
+    time: Instant,
+    rates: Option<UserRates>,
+}
+
+impl UserRateWindow {
+    pub(crate) fn new(time: Instant) -> Self {
+        UserRateWindow {
+            snapshot: UserRateSnapshot::default(),
+            time,
+            rates: None,
+        }
+    }
+
+    pub(crate) fn roll(&mut self, snapshot: UserRateSnapshot, time: Instant) {
+        let elapsed = time.saturating_duration_since(self.time);
+        self.rates = Some(snapshot.rates_since(&self.snapshot, elapsed));
+        self.snapshot = snapshot;
+        self.time = time;
+    }
+
+    /// Get the rates of the latest window.
+    ///
+    /// If the window has never been rolled, which is the case when stats is not enabled,
+    /// the average rates since the window start will be returned.
+    pub(crate) fn rates(&self, current: &UserRateSnapshot, now: Instant) -> UserRates {
+        match self.rates {
+            Some(rates) => rates,
+            None => current.rates_since(&self.snapshot, now.saturating_duration_since(self.time)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rates() {
+        let prev = UserRateSnapshot {
+            conn_total: 10,
+            req_total: 100,
+            rate_limited: 3,
+        };
+        let cur = UserRateSnapshot {
+            conn_total: 30,
+            req_total: 400,
+            rate_limited: 3,
+        };
+        let rates = cur.rates_since(&prev, Duration::from_secs(2));
+        assert_eq!(rates.conn_rate, 10.0);
+        assert_eq!(rates.req_rate, 150.0);
+        assert_eq!(rates.rate_limited, 0);
+        assert!(!rates.is_rate_limited());
+
+        let cur = UserRateSnapshot {
+            rate_limited: 5,
+            ..cur
+        };
+        let rates = cur.rates_since(&prev, Duration::from_millis(500));
+        assert_eq!(rates.conn_rate, 40.0);
+        assert_eq!(rates.req_rate, 600.0);
+        assert_eq!(rates.rate_limited, 2);
+        assert!(rates.is_rate_limited());
+    }
+
+    #[test]
+    fn zero_elapsed() {
+        let prev = UserRateSnapshot::default();
+        let cur = UserRateSnapshot {
+            conn_total: 1,
+            req_total: 1,
+            rate_limited: 1,
+        };
+        let rates = cur.rates_since(&prev, Duration::ZERO);
+        assert_eq!(rates.conn_rate, 0.0);
+        assert_eq!(rates.req_rate, 0.0);
+        assert!(rates.is_rate_limited());
+    }
+
+    #[test]
+    fn counter_decreased() {
+        let prev = UserRateSnapshot {
+            conn_total: 20,
+            req_total: 200,
+            rate_limited: 4,
+        };
+        let cur = UserRateSnapshot {
+            conn_total: 5,
+            req_total: 50,
+            rate_limited: 1,
+        };
+        let rates = cur.rates_since(&prev, Duration::from_secs(1));
+        assert_eq!(rates, UserRates::default());
+    }
+
+    #[test]
+    fn window() {
+        let start = Instant::now();
+        let mut window = UserRateWindow::new(start);
+
+        let cur = UserRateSnapshot {
+            conn_total: 4,
+            req_total: 8,
+            rate_limited: 1,
+        };
+        let rates = window.rates(&cur, start + Duration::from_secs(4));
+        assert_eq!(rates.conn_rate, 1.0);
+        assert_eq!(rates.req_rate, 2.0);
+        assert_eq!(rates.rate_limited, 1);
+
+        window.roll(cur, start + Duration::from_secs(2));
+        let cur = UserRateSnapshot {
+            conn_total: 10,
+            req_total: 20,
+            rate_limited: 1,
+        };
+        window.roll(cur, start + Duration::from_secs(4));
+        let rates = window.rates(&UserRateSnapshot::default(), start + Duration::from_secs(5));
+        assert_eq!(rates.conn_rate, 3.0);
+        assert_eq!(rates.req_rate, 6.0);
+        assert!(!rates.is_rate_limited());
+    }
+}
@@ -26,18 +26,20 @@ use chrono::{DateTime, Utc};
 use governor::{clock::DefaultClock, state::InMemoryState, state::NotKeyed, RateLimiter};
 use tokio::time::Instant;
 
+use g3_geoip_types::IpLocation;
 use g3_io_ext::{GlobalDatagramLimiter, GlobalLimitGroup, GlobalStreamLimiter};
 use g3_types::acl::{AclAction, AclNetworkRule};
-use g3_types::acl_set::AclDstHostRuleSet;
+use g3_types::acl_set::{AclClientGeoRuleSet, AclDstHostRuleSet};
 use g3_types::auth::UserAuthError;
-use g3_types::limit::{GaugeSemaphore, GaugeSemaphorePermit};
+use g3_types::limit::{GaugeSemaphore, GaugeSemaphorePermit, RateLimitQuotaConfig};
 use g3_types::metrics::{MetricsName, StaticMetricsTags};
 use g3_types::net::{HttpHeaderMap, ProxyRequestType, UpstreamAddr};
 use g3_types::resolve::{ResolveRedirection, ResolveStrategy};
 
 use super::{
-    UserForbiddenStats, UserRequestStats, UserSite, UserSiteDurationRecorder, UserSiteStats,
-    UserSites, UserTrafficStats, UserType, UserUpstreamTrafficStats,
+    UserForbiddenStats, UserRateSnapshot, UserRateWindow, UserRates, UserRequestStats, UserSite,
+    UserSiteDurationRecorder, UserSiteStats, UserSites, UserTrafficStats, UserType,
+    UserUpstreamTrafficStats,
 };
 use crate::config::auth::{UserAuditConfig, UserConfig};
 
@@ -54,6 +56,7 @@ pub(crate) struct User {
     udp_all_upload_speed_limit: Option<Arc<GlobalDatagramLimiter>>,
     udp_all_download_speed_limit: Option<Arc<GlobalDatagramLimiter>>,
     ingress_net_filter: Option<Arc<AclNetworkRule>>,
+    ingress_geo_filter: Option<Arc<AclClientGeoRuleSet>>,
     dst_host_filter: Option<Arc<AclDstHostRuleSet>>,
     resolve_redirection: Option<ResolveRedirection>,
     log_rate_limit: Option<Arc<RateLimiter<NotKeyed, InMemoryState, DefaultClock>>>,
@@ -61,6 +64,7 @@ pub(crate) struct User {
     req_stats: Arc<Mutex<AHashMap<String, Arc<UserRequestStats>>>>,
     io_stats: Arc<Mutex<AHashMap<String, Arc<UserTrafficStats>>>>,
     upstream_io_stats: Arc<Mutex<AHashMap<String, Arc<UserUpstreamTrafficStats>>>>,
+    rate_window: Arc<Mutex<UserRateWindow>>,
     req_alive_sem: GaugeSemaphore,
     explicit_sites: UserSites,
 }
@@ -79,6 +83,14 @@ impl User {
             .map(|builder| Arc::new(builder.build()));
     }
 
+    fn update_ingress_geo_filter(&mut self) {
+        self.ingress_geo_filter = self
+            .config
+            .ingress_geo_filter
+            .as_ref()
+            .map(|builder| Arc::new(builder.build()));
+    }
+
     fn update_dst_host_filter(&mut self) {
         self.dst_host_filter = self
             .config
@@ -150,10 +162,11 @@ impl User {
         let explicit_sites = UserSites::new(config.explicit_sites.values(), config.name(), group)
             .context("failed to build sites config")?;
 
+        let started = Instant::now();
         let mut user = User {
             config: Arc::clone(config),
             group: group.clone(),
-            started: Instant::now(),
+            started,
             is_expired,
             is_blocked,
             request_rate_limit,
@@ -163,6 +176,7 @@ impl User {
             udp_all_upload_speed_limit,
             udp_all_download_speed_limit,
             ingress_net_filter: None,
+            ingress_geo_filter: None,
             dst_host_filter: None,
             resolve_redirection: None,
             log_rate_limit,
@@ -170,10 +184,12 @@ impl User {
             req_stats: Arc::new(Mutex::new(AHashMap::new())),
             io_stats: Arc::new(Mutex::new(AHashMap::new())),
             upstream_io_stats: Arc::new(Mutex::new(AHashMap::new())),
+            rate_window: Arc::new(Mutex::new(UserRateWindow::new(started))),
             req_alive_sem: GaugeSemaphore::new(config.request_alive_max),
             explicit_sites,
         };
         user.update_ingress_net_filter();
+        user.update_ingress_geo_filter();
         user.update_dst_host_filter();
         user.update_resolve_redirection();
         Ok(user)
@@ -321,6 +337,7 @@ impl User {
             udp_all_upload_speed_limit,
             udp_all_download_speed_limit,
             ingress_net_filter: None,
+            ingress_geo_filter: None,
             dst_host_filter: None,
             resolve_redirection: None,
             log_rate_limit,
@@ -328,6 +345,7 @@ impl User {
             req_stats: Arc::clone(&self.req_stats),
             io_stats: Arc::clone(&self.io_stats),
             upstream_io_stats: Arc::clone(&self.upstream_io_stats),
+            rate_window: Arc::clone(&self.rate_window),
             req_alive_sem: self.req_alive_sem.new_updated(config.request_alive_max),
             explicit_sites,
         };
@@ -340,6 +358,15 @@ impl User {
         } else {
             user.ingress_net_filter.clone_from(&self.ingress_net_filter);
         }
+        if self
+            .config
+            .ingress_geo_filter
+            .ne(&config.ingress_geo_filter)
+        {
+            user.update_ingress_geo_filter();
+        } else {
+            user.ingress_geo_filter.clone_from(&self.ingress_geo_filter);
+        }
         if self.config.dst_host_filter.ne(&config.dst_host_filter) {
             user.update_dst_host_filter();
         } else {
@@ -349,16 +376,41 @@ impl User {
         Ok(user)
     }
 
+    #[inline]
+    pub(crate) fn group(&self) -> &MetricsName {
+        &self.group
+    }
+
     /// for user blocked check in idle checking
     pub(crate) fn is_blocked(&self) -> bool {
         self.is_blocked.load(Ordering::Relaxed)
     }
 
     #[inline]
-    fn is_expired(&self) -> bool {
+    pub(crate) fn is_expired(&self) -> bool {
         self.is_expired.load(Ordering::Relaxed)
     }
 
+    #[inline]
+    pub(crate) fn alive_request_count(&self) -> usize {
+        self.req_alive_sem.gauge()
+    }
+
+    #[inline]
+    pub(crate) fn alive_request_max(&self) -> usize {
+        self.config.request_alive_max
+    }
+
+    #[inline]
+    pub(crate) fn request_rate_limit(&self) -> Option<&RateLimitQuotaConfig> {
+        self.config.request_rate_limit.as_ref()
+    }
+
+    #[inline]
+    pub(crate) fn tcp_conn_rate_limit(&self) -> Option<&RateLimitQuotaConfig> {
+        self.config.tcp_conn_rate_limit.as_ref()
+    }
+
     pub(super) fn check_expired(&self, datetime_now: &DateTime<Utc>) -> bool {
         if self.config.is_expired(datetime_now) {
             // TODO log user expire ?
@@ -373,32 +425,47 @@ impl User {
     fn check_client_addr(
         &self,
         addr: SocketAddr,
+        location: Option<&IpLocation>,
         forbid_stats: &Arc<UserForbiddenStats>,
     ) -> Result<(), UserAuthError> {
-        let Some(filter) = &self.ingress_net_filter else {
-            return Ok(());
-        };
-        let (_, action) = filter.check(addr.ip());
-        if action.forbid_early() {
-            forbid_stats.add_src_blocked();
-            Err(UserAuthError::BlockedSrcIp(addr))
-        } else {
-            Ok(())
+        if let Some(filter) = &self.ingress_net_filter {
+            let (_, action) = filter.check(addr.ip());
+            if action.forbid_early() {
+                forbid_stats.add_src_blocked();
+                return Err(UserAuthError::BlockedSrcIp(addr));
+            }
+        }
+        if self.check_client_location(location).forbid_early() {
+            forbid_stats.add_src_geo_blocked();
+            return Err(UserAuthError::BlockedSrcIp(addr));
         }
+        Ok(())
     }
 
     pub(super) fn check_anonymous_client_addr(
         &self,
         addr: SocketAddr,
+        location: Option<&IpLocation>,
     ) -> Result<(), UserAuthError> {
-        let Some(filter) = &self.ingress_net_filter else {
-            return Ok(());
+        if let Some(filter) = &self.ingress_net_filter {
+            let (_, action) = filter.check(addr.ip());
+            if action.forbid_early() {
+                return Err(UserAuthError::BlockedSrcIp(addr));
+            }
+        }
+        if self.check_client_location(location).forbid_early() {
+            return Err(UserAuthError::BlockedSrcIp(addr));
+        }
+        Ok(())
+    }
+
+    fn check_client_location(&self, location: Option<&IpLocation>) -> AclAction {
+        let Some(filter) = &self.ingress_geo_filter else {
+            return AclAction::Permit;
         };
-        let (_, action) = filter.check(addr.ip());
-        if action.forbid_early() {
-            Err(UserAuthError::BlockedSrcIp(addr))
-        } else {
-            Ok(())
+        match location {
+            Some(location) => filter.check(location).1,
+            None => filter.missed_action(),
         }
     }
 
@@ -411,6 +478,14 @@ impl User {
             forbid_stats.add_auth_failed();
             return Err(UserAuthError::TokenNotMatch);
         }
+        self.check_availability(forbid_stats)
+    }
+
+    /// Check if the user is allowed to be used after it has been authenticated
+    fn check_availability(
+        &self,
+        forbid_stats: &Arc<UserForbiddenStats>,
+    ) -> Result<(), UserAuthError> {
         if self.is_expired() {
             forbid_stats.add_user_expired();
             return Err(UserAuthError::ExpiredUser);
@@ -478,6 +553,31 @@ impl User {
         all_stats
     }
 
+    fn rate_snapshot(&self) -> UserRateSnapshot {
+        let mut snap = UserRateSnapshot::default();
+        for stats in self.all_request_stats() {
+            snap.conn_total += stats.conn_total.get_http() + stats.conn_total.get_socks();
+            snap.req_total += stats.req_total.total();
+        }
+        for stats in self.all_forbidden_stats() {
+            snap.rate_limited += stats.snapshot().rate_limited;
+        }
+        snap
+    }
+
+    /// called in each stats tick to measure the rates
+    pub(crate) fn roll_rate_window(&self) {
+        let snapshot = self.rate_snapshot();
+        let mut window = self.rate_window.lock().unwrap();
+        window.roll(snapshot, Instant::now());
+    }
+
+    pub(crate) fn rates(&self) -> UserRates {
+        let snapshot = self.rate_snapshot();
+        let window = self.rate_window.lock().unwrap();
+        window.rates(&snapshot, Instant::now())
+    }
+
     fn fetch_traffic_stats(
         &self,
         user_type: UserType,
@@ -859,12 +959,17 @@ impl UserContext {
     }
 
     #[inline]
-    pub(crate) fn check_client_addr(&self, addr: SocketAddr) -> Result<(), UserAuthError> {
+    pub(crate) fn check_client_addr(
+        &self,
+        addr: SocketAddr,
+        location: Option<&IpLocation>,
+    ) -> Result<(), UserAuthError> {
         if self.user_type.is_anonymous() {
-            self.user.check_anonymous_client_addr(addr)
+            self.user.check_anonymous_client_addr(addr, location)
         } else {
             // add forbid stats for named user
-            self.user.check_client_addr(addr, &self.forbid_stats)
+            self.user
+                .check_client_addr(addr, location, &self.forbid_stats)
         }
     }
 
@@ -873,6 +978,13 @@ impl UserContext {
         self.user.check_password(password, &self.forbid_stats)
     }
 
+    /// Check the user availability for auth methods that have no password
+    #[cfg(feature = "gssapi")]
+    #[inline]
+    pub(crate) fn check_availability(&self) -> Result<(), UserAuthError> {
+        self.user.check_availability(&self.forbid_stats)
+    }
+
     #[inline]
     pub(crate) fn skip_log(&self) -> bool {
         self.user.skip_log(&self.forbid_stats)
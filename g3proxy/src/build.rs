@@ -36,6 +36,7 @@ const PYTHON_FEATURE: Option<&str> = option_env!("G3_PYTHON_FEATURE");
 const C_ARES_FEATURE: Option<&str> = option_env!("G3_C_ARES_FEATURE");
 const HICKORY_FEATURE: Option<&str> = option_env!("G3_HICKORY_FEATURE");
 const QUIC_FEATURE: Option<&str> = option_env!("G3_QUIC_FEATURE");
+const GSSAPI_FEATURE: Option<&str> = option_env!("G3_GSSAPI_FEATURE");
 
 pub(crate) fn print_version(verbose_level: u8) {
     println!("{PKG_NAME} {VERSION}");
@@ -56,6 +57,9 @@ pub(crate) fn print_version(verbose_level: u8) {
         if let Some(quic) = QUIC_FEATURE {
             print!(" {quic}");
         }
+        if let Some(gssapi) = GSSAPI_FEATURE {
+            print!(" {gssapi}");
+        }
         println!();
         if let Some(variant) = OPENSSL_VARIANT {
             println!("OpenSSL Variant: {variant}");
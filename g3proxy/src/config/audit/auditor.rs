@@ -22,7 +22,7 @@ use yaml_rust::{yaml, Yaml};
 
 use g3_cert_agent::CertAgentConfig;
 use g3_dpi::{
-    H1InterceptionConfig, H2InterceptionConfig, ImapInterceptionConfig,
+    GrpcInterceptionConfig, H1InterceptionConfig, H2InterceptionConfig, ImapInterceptionConfig,
     ProtocolInspectPolicyBuilder, ProtocolInspectionConfig, ProtocolPortMap,
     SmtpInterceptionConfig,
 };
@@ -32,7 +32,7 @@ use g3_types::metrics::MetricsName;
 use g3_types::net::{
     OpensslInterceptionClientConfigBuilder, OpensslInterceptionServerConfigBuilder,
 };
-use g3_udpdump::StreamDumpConfig;
+use g3_udpdump::{PcapDumpConfig, StreamDumpConfig};
 use g3_yaml::YamlDocPosition;
 
 #[cfg(feature = "quic")]
@@ -50,10 +50,12 @@ pub(crate) struct AuditorConfig {
     pub(crate) tls_interception_client: OpensslInterceptionClientConfigBuilder,
     pub(crate) tls_interception_server: OpensslInterceptionServerConfigBuilder,
     pub(crate) tls_stream_dump: Option<StreamDumpConfig>,
+    pub(crate) tls_stream_pcap: Option<PcapDumpConfig>,
     pub(crate) log_uri_max_chars: usize,
     pub(crate) h1_interception: H1InterceptionConfig,
     pub(crate) h2_inspect_policy: ProtocolInspectPolicyBuilder,
     pub(crate) h2_interception: H2InterceptionConfig,
+    pub(crate) grpc_interception: GrpcInterceptionConfig,
     pub(crate) websocket_inspect_policy: ProtocolInspectPolicyBuilder,
     pub(crate) smtp_inspect_policy: ProtocolInspectPolicyBuilder,
     pub(crate) smtp_interception: SmtpInterceptionConfig,
@@ -75,6 +77,19 @@ impl AuditorConfig {
         self.position.clone()
     }
 
+    /// Build the TLS interception configs, which will only be used if cert agent is set
+    pub(crate) fn check_tls(&self) -> anyhow::Result<()> {
+        if self.tls_cert_agent.is_some() {
+            self.tls_interception_client
+                .build()
+                .context("invalid tls interception client config")?;
+            self.tls_interception_server
+                .build()
+                .context("invalid tls interception server config")?;
+        }
+        Ok(())
+    }
+
     fn with_name(name: MetricsName, position: Option<YamlDocPosition>) -> Self {
         AuditorConfig {
             name,
@@ -87,10 +102,12 @@ impl AuditorConfig {
             tls_interception_client: Default::default(),
             tls_interception_server: Default::default(),
             tls_stream_dump: None,
+            tls_stream_pcap: None,
             log_uri_max_chars: 1024,
             h1_interception: Default::default(),
             h2_inspect_policy: Default::default(),
             h2_interception: Default::default(),
+            grpc_interception: Default::default(),
             websocket_inspect_policy: Default::default(),
             smtp_inspect_policy: Default::default(),
             smtp_interception: Default::default(),
@@ -186,6 +203,13 @@ impl AuditorConfig {
                 self.tls_stream_dump = Some(dump);
                 Ok(())
             }
+            "tls_stream_pcap" => {
+                let lookup_dir = g3_daemon::config::get_lookup_dir(self.position.as_ref())?;
+                let pcap = PcapDumpConfig::parse_yaml(v, Some(lookup_dir))
+                    .context(format!("invalid pcap stream dump config value for key {k}"))?;
+                self.tls_stream_pcap = Some(pcap);
+                Ok(())
+            }
             "log_uri_max_chars" | "uri_log_max_chars" => {
                 self.log_uri_max_chars = g3_yaml::value::as_usize(v)
                     .context(format!("invalid usize value for key {k}"))?;
@@ -206,6 +230,11 @@ impl AuditorConfig {
                     .context(format!("invalid h1 interception value for key {k}"))?;
                 Ok(())
             }
+            "grpc_interception" => {
+                self.grpc_interception = g3_yaml::value::as_grpc_interception_config(v)
+                    .context(format!("invalid grpc interception value for key {k}"))?;
+                Ok(())
+            }
             "websocket_inspect_policy" => {
                 self.websocket_inspect_policy =
                     g3_yaml::value::as_protocol_inspect_policy_builder(v)
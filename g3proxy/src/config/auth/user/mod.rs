@@ -24,7 +24,7 @@ use chrono::{DateTime, Utc};
 use g3_types::acl::{
     AclExactPortRule, AclNetworkRuleBuilder, AclProxyRequestRule, AclUserAgentRule,
 };
-use g3_types::acl_set::AclDstHostRuleSetBuilder;
+use g3_types::acl_set::{AclClientGeoRuleSetBuilder, AclDstHostRuleSetBuilder};
 use g3_types::limit::{
     GlobalDatagramSpeedLimitConfig, GlobalStreamSpeedLimitConfig, RateLimitQuotaConfig,
 };
@@ -36,6 +36,7 @@ use g3_types::net::{
 use g3_types::resolve::{ResolveRedirectionBuilder, ResolveStrategy};
 
 use super::{PasswordToken, UserAuditConfig, UserSiteConfig};
+use crate::config::http_rewrite::HttpRewriteRuleSet;
 use crate::escape::EgressPathSelection;
 
 mod json;
@@ -67,7 +68,10 @@ pub(crate) struct UserConfig {
     pub(crate) udp_all_download_speed_limit: Option<GlobalDatagramSpeedLimitConfig>,
     pub(crate) log_rate_limit: Option<RateLimitQuotaConfig>,
     pub(crate) log_uri_max_chars: Option<usize>,
+    pub(crate) http_rewrite_rules: Option<Arc<HttpRewriteRuleSet>>,
+    pub(crate) http_forward_cache: bool,
     pub(crate) ingress_net_filter: Option<AclNetworkRuleBuilder>,
+    pub(crate) ingress_geo_filter: Option<AclClientGeoRuleSetBuilder>,
     pub(crate) proxy_request_filter: Option<AclProxyRequestRule>,
     pub(crate) dst_host_filter: Option<AclDstHostRuleSetBuilder>,
     pub(crate) dst_port_filter: Option<AclExactPortRule>,
@@ -107,7 +111,10 @@ impl Default for UserConfig {
             udp_all_download_speed_limit: None,
             log_rate_limit: None,
             log_uri_max_chars: None,
+            http_rewrite_rules: None,
+            http_forward_cache: false,
             ingress_net_filter: None,
+            ingress_geo_filter: None,
             proxy_request_filter: None,
             dst_host_filter: None,
             dst_port_filter: None,
@@ -15,6 +15,7 @@
  */
 
 use std::str::FromStr;
+use std::sync::Arc;
 
 use ahash::AHashMap;
 use anyhow::{anyhow, Context};
@@ -23,6 +24,7 @@ use yaml_rust::{yaml, Yaml};
 use g3_yaml::YamlDocPosition;
 
 use super::{PasswordToken, UserConfig, UserSiteConfig};
+use crate::config::http_rewrite::HttpRewriteRuleSet;
 use crate::escape::EgressPathSelection;
 
 impl UserConfig {
@@ -174,6 +176,12 @@ impl UserConfig {
                 self.ingress_net_filter = Some(filter);
                 Ok(())
             }
+            "ingress_geo_filter" | "ingress_client_geo_filter" => {
+                let filter = g3_yaml::value::acl_set::as_client_geo_rule_set_builder(v)
+                    .context(format!("invalid client geo acl rule set value for key {k}"))?;
+                self.ingress_geo_filter = Some(filter);
+                Ok(())
+            }
             "proxy_request_filter" => {
                 let filter = g3_yaml::value::acl::as_proxy_request_rule(v)
                     .context(format!("invalid proxy request acl rule for key {k}"))?;
@@ -222,6 +230,17 @@ impl UserConfig {
                 self.log_uri_max_chars = Some(max_chars);
                 Ok(())
             }
+            "http_rewrite_rules" | "rewrite_rules" => {
+                let rules = HttpRewriteRuleSet::parse_yaml(v)
+                    .context(format!("invalid http rewrite rules value for key {k}"))?;
+                self.http_rewrite_rules = Some(Arc::new(rules));
+                Ok(())
+            }
+            "http_forward_cache" => {
+                self.http_forward_cache = g3_yaml::value::as_bool(v)
+                    .context(format!("invalid boolean value for key {k}"))?;
+                Ok(())
+            }
             "task_idle_max_count" => {
                 self.task_idle_max_count =
                     g3_yaml::value::as_i32(v).context(format!("invalid i32 value for key {k}"))?;
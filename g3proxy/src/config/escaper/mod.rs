@@ -23,7 +23,7 @@ use indexmap::IndexSet;
 use slog::Logger;
 use yaml_rust::{yaml, Yaml};
 
-use g3_daemon::config::sort_nodes_in_dependency_graph;
+use g3_daemon::config::{sort_nodes_in_dependency_graph, ConfigChecker};
 use g3_types::metrics::MetricsName;
 use g3_types::net::{TcpConnectConfig, TcpSockSpeedLimitConfig, UdpSockSpeedLimitConfig};
 use g3_yaml::{HybridParser, YamlDocPosition};
@@ -34,10 +34,12 @@ pub(crate) mod direct_float;
 pub(crate) mod divert_tcp;
 pub(crate) mod dummy_deny;
 pub(crate) mod proxy_float;
+pub(crate) mod proxy_h2;
 pub(crate) mod proxy_http;
 pub(crate) mod proxy_https;
 pub(crate) mod proxy_socks5;
 pub(crate) mod proxy_socks5s;
+pub(crate) mod proxy_ssh;
 pub(crate) mod route_client;
 pub(crate) mod route_failover;
 pub(crate) mod route_geoip;
@@ -79,6 +81,10 @@ pub(crate) trait EscaperConfig {
     fn shared_logger(&self) -> Option<&str> {
         None
     }
+    /// Build all TLS configs, so invalid certificates and keys can be found before spawn
+    fn check_tls(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
     fn get_escape_logger(&self) -> Logger {
         if let Some(shared_logger) = self.shared_logger() {
             crate::log::escape::get_shared_logger(shared_logger, self.escaper_type(), self.name())
@@ -103,6 +109,8 @@ pub(crate) enum AnyEscaperConfig {
     DivertTcp(divert_tcp::DivertTcpEscaperConfig),
     DummyDeny(dummy_deny::DummyDenyEscaperConfig),
     ProxyFloat(proxy_float::ProxyFloatEscaperConfig),
+    ProxyH2(Box<proxy_h2::ProxyH2EscaperConfig>),
+    ProxySsh(Box<proxy_ssh::ProxySshEscaperConfig>),
     ProxyHttp(Box<proxy_http::ProxyHttpEscaperConfig>),
     ProxyHttps(Box<proxy_https::ProxyHttpsEscaperConfig>),
     ProxySocks5(proxy_socks5::ProxySocks5EscaperConfig),
@@ -128,6 +136,8 @@ macro_rules! impl_transparent0 {
                 AnyEscaperConfig::DivertTcp(s) => s.$f(),
                 AnyEscaperConfig::DummyDeny(s) => s.$f(),
                 AnyEscaperConfig::ProxyFloat(s) => s.$f(),
+                AnyEscaperConfig::ProxyH2(s) => s.$f(),
+                AnyEscaperConfig::ProxySsh(s) => s.$f(),
                 AnyEscaperConfig::ProxyHttp(s) => s.$f(),
                 AnyEscaperConfig::ProxyHttps(s) => s.$f(),
                 AnyEscaperConfig::ProxySocks5(s) => s.$f(),
@@ -156,6 +166,8 @@ macro_rules! impl_transparent1 {
                 AnyEscaperConfig::DivertTcp(s) => s.$f(p),
                 AnyEscaperConfig::DummyDeny(s) => s.$f(p),
                 AnyEscaperConfig::ProxyFloat(s) => s.$f(p),
+                AnyEscaperConfig::ProxyH2(s) => s.$f(p),
+                AnyEscaperConfig::ProxySsh(s) => s.$f(p),
                 AnyEscaperConfig::ProxyHttp(s) => s.$f(p),
                 AnyEscaperConfig::ProxyHttps(s) => s.$f(p),
                 AnyEscaperConfig::ProxySocks5(s) => s.$f(p),
@@ -179,6 +191,9 @@ impl AnyEscaperConfig {
     impl_transparent0!(position, Option<YamlDocPosition>);
     impl_transparent0!(dependent_escaper, Option<BTreeSet<MetricsName>>);
     impl_transparent0!(resolver, &MetricsName);
+    impl_transparent0!(escaper_type, &str);
+    impl_transparent0!(shared_logger, Option<&str>);
+    impl_transparent0!(check_tls, anyhow::Result<()>);
 
     impl_transparent1!(diff_action, EscaperConfigDiffAction, &Self);
 }
@@ -248,6 +263,10 @@ fn load_escaper(
             let config = dummy_deny::DummyDenyEscaperConfig::parse(map, position, None)?;
             Ok(AnyEscaperConfig::DummyDeny(config))
         }
+        "proxy_h2" | "proxyh2" => {
+            let config = proxy_h2::ProxyH2EscaperConfig::parse(map, position)?;
+            Ok(AnyEscaperConfig::ProxyH2(Box::new(config)))
+        }
         "proxy_http" | "proxyhttp" => {
             let config = proxy_http::ProxyHttpEscaperConfig::parse(map, position)?;
             Ok(AnyEscaperConfig::ProxyHttp(Box::new(config)))
@@ -264,6 +283,10 @@ fn load_escaper(
             let config = proxy_socks5s::ProxySocks5sEscaperConfig::parse(map, position)?;
             Ok(AnyEscaperConfig::ProxySocks5s(config))
         }
+        "proxy_ssh" | "proxyssh" => {
+            let config = proxy_ssh::ProxySshEscaperConfig::parse(map, position)?;
+            Ok(AnyEscaperConfig::ProxySsh(Box::new(config)))
+        }
         "proxy_float" | "proxyfloat" | "proxy_dynamic" | "proxydynamic" => {
             let config = proxy_float::ProxyFloatEscaperConfig::parse(map, position)?;
             Ok(AnyEscaperConfig::ProxyFloat(config))
@@ -378,6 +401,23 @@ fn check_dependency() -> anyhow::Result<()> {
         all_names.insert(conf.name().clone());
     }
 
+    let mut checker = ConfigChecker::default();
+    checker.add_names("escaper", all_names.iter());
+    for conf in all_config.iter() {
+        if let Some(names) = conf.dependent_escaper() {
+            for peer_name in &names {
+                checker.check_reference(
+                    "escaper",
+                    conf.name(),
+                    conf.position(),
+                    "escaper",
+                    peer_name,
+                );
+            }
+        }
+    }
+    checker.finish()?;
+
     let edges = get_edges_for_dependency_graph(&all_config, &all_names)?;
 
     if let Err(node_index) = sort_nodes_in_dependency_graph(edges) {
@@ -235,6 +235,11 @@ impl EscaperConfig for ProxyFloatEscaperConfig {
         self.shared_logger.as_ref().map(|s| s.as_str())
     }
 
+    fn check_tls(&self) -> anyhow::Result<()> {
+        self.tls_config.build().context("invalid tls config")?;
+        Ok(())
+    }
+
     fn diff_action(&self, new: &AnyEscaperConfig) -> EscaperConfigDiffAction {
         let AnyEscaperConfig::ProxyFloat(new) = new else {
             return EscaperConfigDiffAction::SpawnNew;
@@ -100,7 +100,7 @@ impl ProxyHttpEscaperConfig {
         }
     }
 
-    pub(super) fn parse(
+    pub(crate) fn parse(
         map: &yaml::Hash,
         position: Option<YamlDocPosition>,
     ) -> anyhow::Result<Self> {
@@ -104,7 +104,7 @@ impl ProxyHttpsEscaperConfig {
         }
     }
 
-    pub(super) fn parse(
+    pub(crate) fn parse(
         map: &yaml::Hash,
         position: Option<YamlDocPosition>,
     ) -> anyhow::Result<Self> {
@@ -355,6 +355,11 @@ impl EscaperConfig for ProxyHttpsEscaperConfig {
         &self.resolver
     }
 
+    fn check_tls(&self) -> anyhow::Result<()> {
+        self.tls_config.build().context("invalid tls config")?;
+        Ok(())
+    }
+
     fn diff_action(&self, new: &AnyEscaperConfig) -> EscaperConfigDiffAction {
         let AnyEscaperConfig::ProxyHttps(new) = new else {
             return EscaperConfigDiffAction::SpawnNew;
@@ -99,7 +99,7 @@ impl ProxySocks5EscaperConfig {
         }
     }
 
-    pub(super) fn parse(
+    pub(crate) fn parse(
         map: &yaml::Hash,
         position: Option<YamlDocPosition>,
     ) -> anyhow::Result<Self> {
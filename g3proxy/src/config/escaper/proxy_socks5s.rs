@@ -373,6 +373,11 @@ impl EscaperConfig for ProxySocks5sEscaperConfig {
         &self.resolver
     }
 
+    fn check_tls(&self) -> anyhow::Result<()> {
+        self.tls_config.build().context("invalid tls config")?;
+        Ok(())
+    }
+
     fn diff_action(&self, new: &AnyEscaperConfig) -> EscaperConfigDiffAction {
         let AnyEscaperConfig::ProxySocks5s(new) = new else {
             return EscaperConfigDiffAction::SpawnNew;
@@ -0,0 +1,412 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context};
+use ascii::AsciiString;
+use yaml_rust::{yaml, Yaml};
+
+use g3_ssh_client::{SshKnownHosts, SshPrivateKey};
+use g3_types::auth::{Password, Username};
+use g3_types::collection::SelectivePickPolicy;
+use g3_types::metrics::{MetricsName, StaticMetricsTags};
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use g3_types::net::InterfaceName;
+use g3_types::net::{
+    HappyEyeballsConfig, Host, TcpKeepAliveConfig, TcpMiscSockOpts, WeightedUpstreamAddr,
+};
+use g3_types::resolve::{QueryStrategy, ResolveStrategy};
+use g3_yaml::YamlDocPosition;
+
+use super::{AnyEscaperConfig, EscaperConfig, EscaperConfigDiffAction, GeneralEscaperConfig};
+
+const ESCAPER_CONFIG_TYPE: &str = "ProxySsh";
+
+#[derive(Clone, PartialEq)]
+pub(crate) struct ProxySshEscaperConfig {
+    pub(crate) name: MetricsName,
+    position: Option<YamlDocPosition>,
+    pub(crate) shared_logger: Option<AsciiString>,
+    pub(crate) proxy_nodes: Vec<WeightedUpstreamAddr>,
+    pub(crate) proxy_pick_policy: SelectivePickPolicy,
+    pub(crate) ssh_username: Username,
+    pub(crate) ssh_password: Password,
+    pub(crate) ssh_private_key: Option<Arc<SshPrivateKey>>,
+    pub(crate) known_hosts: Option<Arc<SshKnownHosts>>,
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub(crate) bind_interface: Option<InterfaceName>,
+    pub(crate) bind_v4: Option<Ipv4Addr>,
+    pub(crate) bind_v6: Option<Ipv6Addr>,
+    pub(crate) no_ipv4: bool,
+    pub(crate) no_ipv6: bool,
+    pub(crate) resolver: MetricsName,
+    pub(crate) resolve_strategy: ResolveStrategy,
+    pub(crate) general: GeneralEscaperConfig,
+    pub(crate) happy_eyeballs: HappyEyeballsConfig,
+    pub(crate) tcp_keepalive: TcpKeepAliveConfig,
+    pub(crate) tcp_misc_opts: TcpMiscSockOpts,
+    pub(crate) max_sessions_per_peer: usize,
+    pub(crate) max_channels_per_session: usize,
+    pub(crate) session_idle_timeout: Duration,
+    pub(crate) keepalive_interval: Duration,
+    pub(crate) keepalive_count_max: usize,
+    pub(crate) channel_window_size: u32,
+    pub(crate) peer_negotiation_timeout: Duration,
+    pub(crate) extra_metrics_tags: Option<Arc<StaticMetricsTags>>,
+}
+
+impl ProxySshEscaperConfig {
+    fn new(position: Option<YamlDocPosition>) -> Self {
+        ProxySshEscaperConfig {
+            name: MetricsName::default(),
+            position,
+            shared_logger: None,
+            proxy_nodes: Vec::with_capacity(1),
+            proxy_pick_policy: SelectivePickPolicy::Random,
+            ssh_username: Username::empty(),
+            ssh_password: Password::empty(),
+            ssh_private_key: None,
+            known_hosts: None,
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            bind_interface: None,
+            bind_v4: None,
+            bind_v6: None,
+            no_ipv4: false,
+            no_ipv6: false,
+            resolver: MetricsName::default(),
+            resolve_strategy: Default::default(),
+            general: Default::default(),
+            happy_eyeballs: Default::default(),
+            tcp_keepalive: Default::default(),
+            tcp_misc_opts: Default::default(),
+            max_sessions_per_peer: 4,
+            max_channels_per_session: 64,
+            session_idle_timeout: Duration::from_secs(60),
+            keepalive_interval: Duration::from_secs(15),
+            keepalive_count_max: 3,
+            channel_window_size: 2 * 1024 * 1024,
+            peer_negotiation_timeout: Duration::from_secs(10),
+            extra_metrics_tags: None,
+        }
+    }
+
+    pub(super) fn parse(
+        map: &yaml::Hash,
+        position: Option<YamlDocPosition>,
+    ) -> anyhow::Result<Self> {
+        let mut config = Self::new(position);
+
+        g3_yaml::foreach_kv(map, |k, v| config.set(k, v))?;
+
+        config.check()?;
+        Ok(config)
+    }
+
+    fn set(&mut self, k: &str, v: &Yaml) -> anyhow::Result<()> {
+        match g3_yaml::key::normalize(k).as_str() {
+            super::CONFIG_KEY_ESCAPER_TYPE => Ok(()),
+            super::CONFIG_KEY_ESCAPER_NAME => {
+                self.name = g3_yaml::value::as_metrics_name(v)?;
+                Ok(())
+            }
+            "shared_logger" => {
+                let name = g3_yaml::value::as_ascii(v)?;
+                self.shared_logger = Some(name);
+                Ok(())
+            }
+            "extra_metrics_tags" => {
+                let tags = g3_yaml::value::as_static_metrics_tags(v)
+                    .context(format!("invalid static metrics tags value for key {k}"))?;
+                self.extra_metrics_tags = Some(Arc::new(tags));
+                Ok(())
+            }
+            "proxy_addr" => {
+                self.proxy_nodes = g3_yaml::value::as_list(v, |v| {
+                    g3_yaml::value::as_weighted_upstream_addr(v, 22)
+                })
+                .context(format!(
+                    "invalid weighted upstream address list value for key {k}"
+                ))?;
+                Ok(())
+            }
+            "proxy_addr_pick_policy" => {
+                self.proxy_pick_policy = g3_yaml::value::as_selective_pick_policy(v)?;
+                Ok(())
+            }
+            "ssh_username" | "ssh_user" => {
+                self.ssh_username = g3_yaml::value::as_username(v)
+                    .context(format!("invalid username value for key {k}"))?;
+                Ok(())
+            }
+            "ssh_password" | "ssh_passwd" => {
+                self.ssh_password = g3_yaml::value::as_password(v)
+                    .context(format!("invalid password value for key {k}"))?;
+                Ok(())
+            }
+            "ssh_private_key" => {
+                let lookup_dir = g3_daemon::config::get_lookup_dir(self.position.as_ref())?;
+                let key = load_private_key(v, lookup_dir)
+                    .context(format!("invalid ssh private key value for key {k}"))?;
+                self.ssh_private_key = Some(Arc::new(key));
+                Ok(())
+            }
+            "known_hosts" => {
+                let lookup_dir = g3_daemon::config::get_lookup_dir(self.position.as_ref())?;
+                let known_hosts = load_known_hosts(v, lookup_dir)
+                    .context(format!("invalid ssh known hosts value for key {k}"))?;
+                self.known_hosts = Some(Arc::new(known_hosts));
+                Ok(())
+            }
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            "bind_interface" => {
+                let interface = g3_yaml::value::as_interface_name(v)
+                    .context(format!("invalid interface name value for key {k}"))?;
+                self.bind_interface = Some(interface);
+                Ok(())
+            }
+            "bind_ipv4" => {
+                let ip4 = g3_yaml::value::as_ipv4addr(v)?;
+                self.bind_v4 = Some(ip4);
+                Ok(())
+            }
+            "bind_ipv6" => {
+                let ip6 = g3_yaml::value::as_ipv6addr(v)?;
+                self.bind_v6 = Some(ip6);
+                Ok(())
+            }
+            "resolver" => {
+                self.resolver = g3_yaml::value::as_metrics_name(v)?;
+                Ok(())
+            }
+            "resolve_strategy" => {
+                self.resolve_strategy = g3_yaml::value::as_resolve_strategy(v)?;
+                Ok(())
+            }
+            "tcp_sock_speed_limit" | "tcp_conn_speed_limit" | "tcp_conn_limit" | "conn_limit" => {
+                self.general.tcp_sock_speed_limit = g3_yaml::value::as_tcp_sock_speed_limit(v)
+                    .context(format!("invalid tcp socket speed limit value for key {k}"))?;
+                Ok(())
+            }
+            "tcp_keepalive" => {
+                self.tcp_keepalive = g3_yaml::value::as_tcp_keepalive_config(v)
+                    .context(format!("invalid tcp keepalive config value for key {k}"))?;
+                Ok(())
+            }
+            "tcp_misc_opts" => {
+                self.tcp_misc_opts = g3_yaml::value::as_tcp_misc_sock_opts(v)
+                    .context(format!("invalid tcp misc sock opts value for key {k}"))?;
+                Ok(())
+            }
+            "no_ipv4" => {
+                self.no_ipv4 = g3_yaml::value::as_bool(v)?;
+                Ok(())
+            }
+            "no_ipv6" => {
+                self.no_ipv6 = g3_yaml::value::as_bool(v)?;
+                Ok(())
+            }
+            "tcp_connect" => {
+                self.general.tcp_connect = g3_yaml::value::as_tcp_connect_config(v)
+                    .context(format!("invalid tcp connect value for key {k}"))?;
+                Ok(())
+            }
+            "happy_eyeballs" => {
+                self.happy_eyeballs = g3_yaml::value::as_happy_eyeballs_config(v)
+                    .context(format!("invalid happy eyeballs config value for key {k}"))?;
+                Ok(())
+            }
+            "max_sessions_per_peer" => {
+                self.max_sessions_per_peer = g3_yaml::value::as_usize(v)?;
+                Ok(())
+            }
+            "max_channels_per_session" => {
+                self.max_channels_per_session = g3_yaml::value::as_usize(v)?;
+                Ok(())
+            }
+            "session_idle_timeout" => {
+                self.session_idle_timeout = g3_yaml::humanize::as_duration(v)
+                    .context(format!("invalid humanize duration value for key {k}"))?;
+                Ok(())
+            }
+            "keepalive_interval" => {
+                self.keepalive_interval = g3_yaml::humanize::as_duration(v)
+                    .context(format!("invalid humanize duration value for key {k}"))?;
+                Ok(())
+            }
+            "keepalive_count_max" => {
+                self.keepalive_count_max = g3_yaml::value::as_usize(v)?;
+                Ok(())
+            }
+            "channel_window_size" => {
+                self.channel_window_size = g3_yaml::humanize::as_u32(v)
+                    .context(format!("invalid humanize u32 value for key {k}"))?;
+                Ok(())
+            }
+            "peer_negotiation_timeout" => {
+                self.peer_negotiation_timeout = g3_yaml::humanize::as_duration(v)
+                    .context(format!("invalid humanize duration value for key {k}"))?;
+                Ok(())
+            }
+            _ => Err(anyhow!("invalid key {k}")),
+        }
+    }
+
+    fn check(&mut self) -> anyhow::Result<()> {
+        if self.name.is_empty() {
+            return Err(anyhow!("name is not set"));
+        }
+        if self.proxy_nodes.is_empty() {
+            return Err(anyhow!("proxy addr is not set"));
+        }
+        self.proxy_nodes.reverse(); // reverse as we push to the back
+        if self.no_ipv4 && self.no_ipv6 {
+            return Err(anyhow!("both ipv4 and ipv6 are disabled"));
+        }
+
+        let mut disable_ipv4 = true;
+        let mut disable_ipv6 = true;
+        let mut check_resolver = false;
+        for node in &self.proxy_nodes {
+            match node.inner().host() {
+                Host::Domain(_) => {
+                    disable_ipv4 = false;
+                    disable_ipv6 = false;
+                    check_resolver = true;
+                }
+                Host::Ip(IpAddr::V4(_)) => {
+                    if self.no_ipv4 {
+                        return Err(anyhow!("ipv4 is disable but the proxy addr is also ipv4"));
+                    }
+                    disable_ipv4 = false;
+                }
+                Host::Ip(IpAddr::V6(_)) => {
+                    if self.no_ipv6 {
+                        return Err(anyhow!("ipv6 is disable but the proxy addr is also ipv6"));
+                    }
+                    disable_ipv6 = false;
+                }
+            }
+        }
+        if disable_ipv4 {
+            self.no_ipv4 = true;
+        }
+        if disable_ipv6 {
+            self.no_ipv6 = true;
+        }
+        if check_resolver {
+            if self.resolver.is_empty() {
+                return Err(anyhow!("resolver is not set"));
+            }
+            self.resolve_strategy
+                .update_query_strategy(self.no_ipv4, self.no_ipv6)
+                .context("found incompatible resolver strategy")?;
+            if !self.no_ipv4 && !self.no_ipv6 {
+                match self.resolve_strategy.query {
+                    QueryStrategy::Ipv4Only => self.no_ipv6 = true,
+                    QueryStrategy::Ipv6Only => self.no_ipv4 = true,
+                    _ => {}
+                }
+            }
+        }
+
+        if self.ssh_username.is_empty() {
+            return Err(anyhow!("ssh username is not set"));
+        }
+        if self.ssh_private_key.is_none() && self.ssh_password.is_empty() {
+            return Err(anyhow!("neither ssh private key nor ssh password is set"));
+        }
+        match &self.known_hosts {
+            Some(known_hosts) => {
+                if known_hosts.is_empty() {
+                    return Err(anyhow!("no valid entry found in known hosts"));
+                }
+            }
+            None => return Err(anyhow!("known hosts is not set")),
+        }
+        if self.max_sessions_per_peer == 0 {
+            return Err(anyhow!("max_sessions_per_peer should not be 0"));
+        }
+        if self.max_channels_per_session == 0 {
+            return Err(anyhow!("max_channels_per_session should not be 0"));
+        }
+        if self.session_idle_timeout.is_zero() {
+            return Err(anyhow!("session_idle_timeout should not be 0"));
+        }
+        if self.keepalive_interval.is_zero() {
+            return Err(anyhow!("keepalive_interval should not be 0"));
+        }
+        // the window size should be large enough to hold a full data packet
+        if self.channel_window_size < 32 * 1024 {
+            return Err(anyhow!("channel_window_size should not be less than 32KiB"));
+        }
+
+        Ok(())
+    }
+}
+
+fn load_private_key(v: &Yaml, lookup_dir: &Path) -> anyhow::Result<SshPrivateKey> {
+    let path = g3_yaml::value::as_file_path(v, lookup_dir, false)?;
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| anyhow!("failed to read file {}: {e}", path.display()))?;
+    SshPrivateKey::from_pem(&content)
+        .map_err(|e| anyhow!("invalid private key file {}: {e}", path.display()))
+}
+
+fn load_known_hosts(v: &Yaml, lookup_dir: &Path) -> anyhow::Result<SshKnownHosts> {
+    let path = g3_yaml::value::as_file_path(v, lookup_dir, false)?;
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| anyhow!("failed to read file {}: {e}", path.display()))?;
+    SshKnownHosts::parse(&content)
+        .map_err(|e| anyhow!("invalid known hosts file {}: {e}", path.display()))
+}
+
+impl EscaperConfig for ProxySshEscaperConfig {
+    fn name(&self) -> &MetricsName {
+        &self.name
+    }
+
+    fn position(&self) -> Option<YamlDocPosition> {
+        self.position.clone()
+    }
+
+    fn escaper_type(&self) -> &str {
+        ESCAPER_CONFIG_TYPE
+    }
+
+    fn resolver(&self) -> &MetricsName {
+        &self.resolver
+    }
+
+    fn diff_action(&self, new: &AnyEscaperConfig) -> EscaperConfigDiffAction {
+        let AnyEscaperConfig::ProxySsh(new) = new else {
+            return EscaperConfigDiffAction::SpawnNew;
+        };
+
+        if self.eq(new) {
+            return EscaperConfigDiffAction::NoAction;
+        }
+
+        EscaperConfigDiffAction::Reload
+    }
+
+    fn shared_logger(&self) -> Option<&str> {
+        self.shared_logger.as_ref().map(|s| s.as_str())
+    }
+}
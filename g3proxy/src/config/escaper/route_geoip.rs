@@ -15,6 +15,8 @@
  */
 
 use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::str::FromStr;
 use std::time::Duration;
 
 use anyhow::{anyhow, Context};
@@ -31,6 +33,43 @@ use super::{AnyEscaperConfig, EscaperConfig, EscaperConfigDiffAction, EscaperCon
 
 const ESCAPER_CONFIG_TYPE: &str = "RouteGeoIp";
 
+/// A region or city name qualified by the country it belongs to
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub(crate) struct GeoAreaName {
+    pub(crate) country: IsoCountryCode,
+    /// the area name in ascii lowercase
+    pub(crate) name: String,
+}
+
+impl GeoAreaName {
+    fn parse_yaml(v: &Yaml) -> anyhow::Result<Self> {
+        let Yaml::String(s) = v else {
+            return Err(anyhow!(
+                "yaml value type for 'geo area name' should be 'string'"
+            ));
+        };
+        let Some((country, name)) = s.split_once('/') else {
+            return Err(anyhow!("no '/' delimiter found in {s}"));
+        };
+        let country = IsoCountryCode::from_str(country.trim())
+            .map_err(|_| anyhow!("invalid iso country code {country}"))?;
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(anyhow!("empty area name in {s}"));
+        }
+        Ok(GeoAreaName {
+            country,
+            name: name.to_ascii_lowercase(),
+        })
+    }
+}
+
+impl fmt::Display for GeoAreaName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.country.alpha2_code(), self.name)
+    }
+}
+
 #[derive(Clone, Eq, PartialEq)]
 pub(crate) struct RouteGeoIpEscaperConfig {
     pub(crate) name: MetricsName,
@@ -43,6 +82,9 @@ pub(crate) struct RouteGeoIpEscaperConfig {
     pub(crate) asn_rules: BTreeMap<MetricsName, BTreeSet<u32>>,
     pub(crate) country_rules: BTreeMap<MetricsName, BTreeSet<IsoCountryCode>>,
     pub(crate) continent_rules: BTreeMap<MetricsName, BTreeSet<ContinentCode>>,
+    pub(crate) region_rules: BTreeMap<MetricsName, BTreeSet<GeoAreaName>>,
+    pub(crate) city_rules: BTreeMap<MetricsName, BTreeSet<GeoAreaName>>,
+    pub(crate) hosting_next: Option<MetricsName>,
     pub(crate) default_next: MetricsName,
 }
 
@@ -59,6 +101,9 @@ impl RouteGeoIpEscaperConfig {
             asn_rules: BTreeMap::new(),
             country_rules: BTreeMap::new(),
             continent_rules: BTreeMap::new(),
+            region_rules: BTreeMap::new(),
+            city_rules: BTreeMap::new(),
+            hosting_next: None,
             default_next: MetricsName::default(),
         }
     }
@@ -149,6 +194,14 @@ impl RouteGeoIpEscaperConfig {
             EscaperConfigVerifier::check_duplicated_rule(&self.continent_rules)
                 .context("found duplicated continent")?;
         }
+        if !self.region_rules.is_empty() {
+            EscaperConfigVerifier::check_duplicated_rule(&self.region_rules)
+                .context("found duplicated region")?;
+        }
+        if !self.city_rules.is_empty() {
+            EscaperConfigVerifier::check_duplicated_rule(&self.city_rules)
+                .context("found duplicated city")?;
+        }
         Ok(())
     }
 
@@ -158,6 +211,9 @@ impl RouteGeoIpEscaperConfig {
         let mut asn_set = BTreeSet::<u32>::new();
         let mut countries = BTreeSet::<IsoCountryCode>::new();
         let mut continents = BTreeSet::<ContinentCode>::new();
+        let mut regions = BTreeSet::<GeoAreaName>::new();
+        let mut cities = BTreeSet::<GeoAreaName>::new();
+        let mut hosting = false;
         g3_yaml::foreach_kv(map, |k, v| match g3_yaml::key::normalize(k).as_str() {
             "next" | "escaper" => {
                 escaper = g3_yaml::value::as_metrics_name(v)?;
@@ -195,6 +251,26 @@ impl RouteGeoIpEscaperConfig {
                 }
                 Ok(())
             }
+            "region" | "regions" => {
+                let all_regions = g3_yaml::value::as_list(v, GeoAreaName::parse_yaml)
+                    .context(format!("invalid geo area name list value for key {k}"))?;
+                for region in all_regions {
+                    regions.insert(region);
+                }
+                Ok(())
+            }
+            "city" | "cities" => {
+                let all_cities = g3_yaml::value::as_list(v, GeoAreaName::parse_yaml)
+                    .context(format!("invalid geo area name list value for key {k}"))?;
+                for city in all_cities {
+                    cities.insert(city);
+                }
+                Ok(())
+            }
+            "hosting" => {
+                hosting = g3_yaml::value::as_bool(v)?;
+                Ok(())
+            }
             _ => Err(anyhow!("invalid key {k}")),
         })?;
         if escaper.is_empty() {
@@ -230,6 +306,24 @@ impl RouteGeoIpEscaperConfig {
                 "found multiple continent entries for next escaper {escaper}"
             ));
         }
+        if !regions.is_empty() && self.region_rules.insert(escaper.clone(), regions).is_some() {
+            return Err(anyhow!(
+                "found multiple region entries for next escaper {escaper}"
+            ));
+        }
+        if !cities.is_empty() && self.city_rules.insert(escaper.clone(), cities).is_some() {
+            return Err(anyhow!(
+                "found multiple city entries for next escaper {escaper}"
+            ));
+        }
+        if hosting {
+            if let Some(old) = &self.hosting_next {
+                return Err(anyhow!(
+                    "hosting has already been set for next escaper {old}"
+                ));
+            }
+            self.hosting_next = Some(escaper);
+        }
         Ok(())
     }
 }
@@ -271,7 +365,10 @@ impl EscaperConfig for RouteGeoIpEscaperConfig {
             .keys()
             .chain(self.asn_rules.keys())
             .chain(self.country_rules.keys())
-            .chain(self.continent_rules.keys());
+            .chain(self.continent_rules.keys())
+            .chain(self.region_rules.keys())
+            .chain(self.city_rules.keys())
+            .chain(self.hosting_next.iter());
         for key in all_keys {
             set.insert(key.clone());
         }
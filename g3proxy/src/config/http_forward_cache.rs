@@ -0,0 +1,102 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context};
+use yaml_rust::Yaml;
+
+const DEFAULT_MEMORY_SIZE: usize = 64 * 1024 * 1024;
+const DEFAULT_DISK_SIZE: u64 = 1024 * 1024 * 1024;
+const DEFAULT_MAX_OBJECT_SIZE: usize = 16 * 1024 * 1024;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct HttpForwardCacheConfig {
+    pub(crate) memory_size: usize,
+    pub(crate) disk_directory: Option<PathBuf>,
+    pub(crate) disk_size: u64,
+    pub(crate) max_object_size: usize,
+}
+
+impl Default for HttpForwardCacheConfig {
+    fn default() -> Self {
+        HttpForwardCacheConfig {
+            memory_size: DEFAULT_MEMORY_SIZE,
+            disk_directory: None,
+            disk_size: DEFAULT_DISK_SIZE,
+            max_object_size: DEFAULT_MAX_OBJECT_SIZE,
+        }
+    }
+}
+
+impl HttpForwardCacheConfig {
+    pub(crate) fn parse_yaml(value: &Yaml, lookup_dir: Option<&Path>) -> anyhow::Result<Self> {
+        match value {
+            Yaml::Hash(map) => {
+                let mut config = HttpForwardCacheConfig::default();
+                g3_yaml::foreach_kv(map, |k, v| config.set_yaml(k, v, lookup_dir))?;
+                config.check()?;
+                Ok(config)
+            }
+            Yaml::Boolean(true) => Ok(HttpForwardCacheConfig::default()),
+            _ => Err(anyhow!(
+                "yaml value type for 'http forward cache config' should be 'map' or 'bool'"
+            )),
+        }
+    }
+
+    fn set_yaml(&mut self, k: &str, v: &Yaml, lookup_dir: Option<&Path>) -> anyhow::Result<()> {
+        match g3_yaml::key::normalize(k).as_str() {
+            "memory_size" | "memory_cache_size" => {
+                self.memory_size = g3_yaml::humanize::as_usize(v)
+                    .context(format!("invalid humanize usize value for key {k}"))?;
+                Ok(())
+            }
+            "disk_directory" | "disk_dir" => {
+                let lookup_dir =
+                    lookup_dir.ok_or_else(|| anyhow!("no lookup dir set for relative path"))?;
+                let dir = g3_yaml::value::as_dir_path(v, lookup_dir, true)
+                    .context(format!("invalid directory path value for key {k}"))?;
+                self.disk_directory = Some(dir);
+                Ok(())
+            }
+            "disk_size" | "disk_cache_size" => {
+                self.disk_size = g3_yaml::humanize::as_u64(v)
+                    .context(format!("invalid humanize u64 value for key {k}"))?;
+                Ok(())
+            }
+            "max_object_size" => {
+                self.max_object_size = g3_yaml::humanize::as_usize(v)
+                    .context(format!("invalid humanize usize value for key {k}"))?;
+                Ok(())
+            }
+            _ => Err(anyhow!("invalid key {k}")),
+        }
+    }
+
+    fn check(&mut self) -> anyhow::Result<()> {
+        if self.max_object_size == 0 {
+            return Err(anyhow!("max object size should not be zero"));
+        }
+        if self.memory_size == 0 && self.disk_directory.is_none() {
+            return Err(anyhow!("neither memory nor disk cache is enabled"));
+        }
+        if self.disk_directory.is_some() && self.disk_size < self.max_object_size as u64 {
+            return Err(anyhow!("disk size should not be less than max object size"));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,223 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use anyhow::{anyhow, Context};
+use serde_json::Value;
+
+use super::{
+    HttpHeaderRewrite, HttpRewriteMatch, HttpRewriteReply, HttpRewriteRule, HttpRewriteRuleSet,
+};
+
+fn foreach_string<F>(v: &Value, mut f: F) -> anyhow::Result<()>
+where
+    F: FnMut(String) -> anyhow::Result<()>,
+{
+    if let Value::Array(seq) = v {
+        for (i, v) in seq.iter().enumerate() {
+            let s =
+                g3_json::value::as_string(v).context(format!("invalid string value for #{i}"))?;
+            f(s).context(format!("invalid value for #{i}"))?;
+        }
+        Ok(())
+    } else {
+        let s = g3_json::value::as_string(v)?;
+        f(s)
+    }
+}
+
+fn foreach_header<F>(v: &Value, mut f: F) -> anyhow::Result<()>
+where
+    F: FnMut(&str, &str) -> anyhow::Result<()>,
+{
+    if let Value::Object(map) = v {
+        for (k, v) in map {
+            let value = g3_json::value::as_string(v)
+                .context(format!("invalid string value for header {k}"))?;
+            f(k, &value)?;
+        }
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "json value type for 'http headers' should be 'map'"
+        ))
+    }
+}
+
+impl HttpRewriteMatch {
+    fn parse_json(&mut self, v: &Value) -> anyhow::Result<()> {
+        if let Value::Object(map) = v {
+            for (k, v) in map {
+                match g3_json::key::normalize(k).as_str() {
+                    "host" | "hosts" => foreach_string(v, |s| self.add_host(&s))
+                        .context(format!("invalid host value for key {k}"))?,
+                    "path_prefix" | "path" => {
+                        let prefix = g3_json::value::as_string(v)?;
+                        self.set_path_prefix(prefix)
+                            .context(format!("invalid path prefix value for key {k}"))?;
+                    }
+                    "method" | "methods" => foreach_string(v, |s| self.add_method(&s))
+                        .context(format!("invalid http method value for key {k}"))?,
+                    _ => return Err(anyhow!("invalid key {k}")),
+                }
+            }
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "json value type for 'http rewrite match' should be 'map'"
+            ))
+        }
+    }
+}
+
+impl HttpHeaderRewrite {
+    fn parse_json(&mut self, v: &Value) -> anyhow::Result<()> {
+        if let Value::Object(map) = v {
+            for (k, v) in map {
+                match g3_json::key::normalize(k).as_str() {
+                    "remove" | "delete" => foreach_string(v, |s| self.add_remove(&s))
+                        .context(format!("invalid header name value for key {k}"))?,
+                    "set" | "replace" => foreach_header(v, |name, value| self.add_set(name, value))
+                        .context(format!("invalid headers value for key {k}"))?,
+                    "add" | "append" => {
+                        foreach_header(v, |name, value| self.add_append(name, value))
+                            .context(format!("invalid headers value for key {k}"))?
+                    }
+                    _ => return Err(anyhow!("invalid key {k}")),
+                }
+            }
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "json value type for 'http header rewrite' should be 'map'"
+            ))
+        }
+    }
+}
+
+impl HttpRewriteReply {
+    fn parse_json_redirect(v: &Value) -> anyhow::Result<Self> {
+        match v {
+            Value::String(s) => HttpRewriteReply::new_redirect(302, s.to_string()),
+            Value::Object(map) => {
+                let mut status = 302;
+                let mut location = String::new();
+                for (k, v) in map {
+                    match g3_json::key::normalize(k).as_str() {
+                        "status" | "code" => status = g3_json::value::as_u16(v)?,
+                        "location" | "url" => location = g3_json::value::as_string(v)?,
+                        _ => return Err(anyhow!("invalid key {k}")),
+                    }
+                }
+                HttpRewriteReply::new_redirect(status, location)
+            }
+            _ => Err(anyhow!(
+                "json value type for 'http redirect reply' should be 'string' or 'map'"
+            )),
+        }
+    }
+
+    fn parse_json_deny(v: &Value) -> anyhow::Result<Self> {
+        match v {
+            Value::Number(_) => {
+                let status = g3_json::value::as_u16(v)?;
+                HttpRewriteReply::new_deny(status, None)
+            }
+            Value::Object(map) => {
+                let mut status = 403;
+                let mut body = None;
+                for (k, v) in map {
+                    match g3_json::key::normalize(k).as_str() {
+                        "status" | "code" => status = g3_json::value::as_u16(v)?,
+                        "body" => body = Some(g3_json::value::as_string(v)?),
+                        _ => return Err(anyhow!("invalid key {k}")),
+                    }
+                }
+                HttpRewriteReply::new_deny(status, body)
+            }
+            _ => Err(anyhow!(
+                "json value type for 'http deny reply' should be 'number' or 'map'"
+            )),
+        }
+    }
+}
+
+impl HttpRewriteRule {
+    fn parse_json(v: &Value) -> anyhow::Result<Self> {
+        if let Value::Object(map) = v {
+            let mut rule = HttpRewriteRule::default();
+            for (k, v) in map {
+                match g3_json::key::normalize(k).as_str() {
+                    "match" | "condition" => rule
+                        .condition
+                        .parse_json(v)
+                        .context(format!("invalid http rewrite match value for key {k}"))?,
+                    "rewrite_path" => {
+                        if let Value::Object(map) = v {
+                            let from = g3_json::get_required_str(map, "from")?;
+                            let to = g3_json::get_required_str(map, "to")?;
+                            rule.set_path_rewrite(from.to_string(), to.to_string())
+                                .context(format!("invalid rewrite path value for key {k}"))?;
+                        } else {
+                            return Err(anyhow!("invalid map value for key {k}"));
+                        }
+                    }
+                    "request_header" | "req_header" => rule
+                        .request_header
+                        .parse_json(v)
+                        .context(format!("invalid http header rewrite value for key {k}"))?,
+                    "response_header" | "rsp_header" => rule
+                        .response_header
+                        .parse_json(v)
+                        .context(format!("invalid http header rewrite value for key {k}"))?,
+                    "redirect" => {
+                        let reply = HttpRewriteReply::parse_json_redirect(v)
+                            .context(format!("invalid http redirect reply value for key {k}"))?;
+                        rule.set_reply(reply)?;
+                    }
+                    "deny" => {
+                        let reply = HttpRewriteReply::parse_json_deny(v)
+                            .context(format!("invalid http deny reply value for key {k}"))?;
+                        rule.set_reply(reply)?;
+                    }
+                    _ => return Err(anyhow!("invalid key {k}")),
+                }
+            }
+            Ok(rule)
+        } else {
+            Err(anyhow!(
+                "json value type for 'http rewrite rule' should be 'map'"
+            ))
+        }
+    }
+}
+
+impl HttpRewriteRuleSet {
+    pub(crate) fn parse_json(v: &Value) -> anyhow::Result<Self> {
+        let mut set = HttpRewriteRuleSet::default();
+        if let Value::Array(seq) = v {
+            for (i, v) in seq.iter().enumerate() {
+                let rule = HttpRewriteRule::parse_json(v)
+                    .context(format!("invalid http rewrite rule value for #{i}"))?;
+                set.push(rule)
+                    .context(format!("invalid http rewrite rule #{i}"))?;
+            }
+        } else {
+            let rule = HttpRewriteRule::parse_json(v)?;
+            set.push(rule)?;
+        }
+        Ok(set)
+    }
+}
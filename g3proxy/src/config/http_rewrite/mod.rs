@@ -0,0 +1,409 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::str::FromStr;
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use http::uri::PathAndQuery;
+use http::{HeaderName, Method, StatusCode, Uri};
+
+use g3_http::server::HttpProxyClientRequest;
+use g3_types::net::{HttpHeaderMap, HttpHeaderValue, UpstreamAddr};
+
+mod json;
+mod yaml;
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub(crate) struct HttpRewriteMatch {
+    hosts: Vec<String>,
+    path_prefix: Option<String>,
+    methods: Vec<Method>,
+}
+
+impl HttpRewriteMatch {
+    fn add_host(&mut self, host: &str) -> anyhow::Result<()> {
+        if host.is_empty() || host == "." {
+            return Err(anyhow!("empty host"));
+        }
+        self.hosts.push(host.to_ascii_lowercase());
+        Ok(())
+    }
+
+    fn set_path_prefix(&mut self, prefix: String) -> anyhow::Result<()> {
+        if !prefix.starts_with('/') {
+            return Err(anyhow!("path prefix should start with '/'"));
+        }
+        self.path_prefix = Some(prefix);
+        Ok(())
+    }
+
+    fn add_method(&mut self, method: &str) -> anyhow::Result<()> {
+        let method = Method::from_bytes(method.to_ascii_uppercase().as_bytes())
+            .map_err(|e| anyhow!("invalid http method {method}: {e}"))?;
+        self.methods.push(method);
+        Ok(())
+    }
+
+    fn host_match(&self, host: &str) -> bool {
+        if self.hosts.is_empty() {
+            return true;
+        }
+        self.hosts.iter().any(|pattern| {
+            if let Some(suffix) = pattern.strip_prefix('.') {
+                // '.example.net' matches both example.net and all its sub domains
+                let host = host.as_bytes();
+                host.eq_ignore_ascii_case(suffix.as_bytes())
+                    || (host.len() > pattern.len()
+                        && host[host.len() - pattern.len()..]
+                            .eq_ignore_ascii_case(pattern.as_bytes()))
+            } else {
+                host.eq_ignore_ascii_case(pattern)
+            }
+        })
+    }
+
+    fn is_match(&self, method: &Method, upstream: &UpstreamAddr, uri: &Uri) -> bool {
+        if !self.methods.is_empty() && !self.methods.contains(method) {
+            return false;
+        }
+        if let Some(prefix) = &self.path_prefix {
+            if !uri.path().starts_with(prefix.as_str()) {
+                return false;
+            }
+        }
+        self.host_match(&upstream.host_str())
+    }
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub(crate) struct HttpHeaderRewrite {
+    remove: Vec<HeaderName>,
+    set: Vec<(HeaderName, String)>,
+    add: Vec<(HeaderName, String)>,
+}
+
+fn parse_header(name: &str, value: &str) -> anyhow::Result<(HeaderName, String)> {
+    let name =
+        HeaderName::from_str(name).map_err(|e| anyhow!("invalid header name {name}: {e}"))?;
+    HttpHeaderValue::from_str(value)
+        .map_err(|_| anyhow!("invalid value for header {name}: {value}"))?;
+    Ok((name, value.to_string()))
+}
+
+impl HttpHeaderRewrite {
+    fn add_remove(&mut self, name: &str) -> anyhow::Result<()> {
+        let name =
+            HeaderName::from_str(name).map_err(|e| anyhow!("invalid header name {name}: {e}"))?;
+        self.remove.push(name);
+        Ok(())
+    }
+
+    fn add_set(&mut self, name: &str, value: &str) -> anyhow::Result<()> {
+        self.set.push(parse_header(name, value)?);
+        Ok(())
+    }
+
+    fn add_append(&mut self, name: &str, value: &str) -> anyhow::Result<()> {
+        self.add.push(parse_header(name, value)?);
+        Ok(())
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.remove.is_empty() && self.set.is_empty() && self.add.is_empty()
+    }
+
+    pub(crate) fn apply(&self, headers: &mut HttpHeaderMap) {
+        for name in &self.remove {
+            headers.remove(name);
+        }
+        for (name, value) in &self.set {
+            // the value has been checked when parsing config
+            let value = unsafe { HttpHeaderValue::from_string_unchecked(value.clone()) };
+            headers.insert(name.clone(), value);
+        }
+        for (name, value) in &self.add {
+            let value = unsafe { HttpHeaderValue::from_string_unchecked(value.clone()) };
+            headers.append(name.clone(), value);
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) enum HttpRewriteReply {
+    Redirect {
+        status: StatusCode,
+        location: String,
+    },
+    Deny {
+        status: StatusCode,
+        body: Option<String>,
+    },
+}
+
+impl HttpRewriteReply {
+    fn new_redirect(status: u16, location: String) -> anyhow::Result<Self> {
+        let status =
+            StatusCode::from_u16(status).map_err(|e| anyhow!("invalid status code: {e}"))?;
+        if !status.is_redirection() {
+            return Err(anyhow!("status code {status} is not a redirection code"));
+        }
+        if location.is_empty() {
+            return Err(anyhow!("empty redirect location"));
+        }
+        Ok(HttpRewriteReply::Redirect { status, location })
+    }
+
+    fn new_deny(status: u16, body: Option<String>) -> anyhow::Result<Self> {
+        let status =
+            StatusCode::from_u16(status).map_err(|e| anyhow!("invalid status code: {e}"))?;
+        if !status.is_client_error() && !status.is_server_error() {
+            return Err(anyhow!("status code {status} is not an error code"));
+        }
+        Ok(HttpRewriteReply::Deny { status, body })
+    }
+}
+
+/// Variables that can be used in redirect location and deny body templates.
+pub(crate) struct HttpRewriteTemplateVars<'a> {
+    pub(crate) method: &'a Method,
+    pub(crate) upstream: &'a UpstreamAddr,
+    pub(crate) uri: &'a Uri,
+    pub(crate) user: Option<&'a str>,
+}
+
+fn push_html_escaped(output: &mut String, s: &str) {
+    for c in s.chars() {
+        match c {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '"' => output.push_str("&quot;"),
+            '\'' => output.push_str("&#39;"),
+            _ => output.push(c),
+        }
+    }
+}
+
+impl HttpRewriteTemplateVars<'_> {
+    pub(crate) fn render(&self, template: &str) -> String {
+        self.render_with(template, |output, s| output.push_str(s))
+    }
+
+    /// Render the template for use in html body, all variable values will be escaped.
+    pub(crate) fn render_html(&self, template: &str) -> String {
+        self.render_with(template, push_html_escaped)
+    }
+
+    fn render_with<F>(&self, template: &str, push_value: F) -> String
+    where
+        F: Fn(&mut String, &str),
+    {
+        let mut output = String::with_capacity(template.len() + 64);
+        let mut left = template;
+        while let Some(p) = left.find("${") {
+            output.push_str(&left[..p]);
+            let var = &left[p + 2..];
+            let Some(end) = var.find('}') else {
+                left = &left[p..];
+                break;
+            };
+            match &var[..end] {
+                "host" => push_value(&mut output, &self.upstream.host_str()),
+                "port" => push_value(&mut output, &self.upstream.port().to_string()),
+                "method" => push_value(&mut output, self.method.as_str()),
+                "path" => push_value(&mut output, self.uri.path()),
+                "query" => push_value(&mut output, self.uri.query().unwrap_or_default()),
+                "user" => push_value(&mut output, self.user.unwrap_or_default()),
+                _ => output.push_str(&left[p..p + 2 + end + 1]),
+            }
+            left = &var[end + 1..];
+        }
+        output.push_str(left);
+        output
+    }
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub(crate) struct HttpRewriteRule {
+    condition: HttpRewriteMatch,
+    path_rewrite: Option<(String, String)>,
+    pub(crate) request_header: HttpHeaderRewrite,
+    pub(crate) response_header: HttpHeaderRewrite,
+    pub(crate) reply: Option<HttpRewriteReply>,
+}
+
+impl HttpRewriteRule {
+    fn set_path_rewrite(&mut self, from: String, to: String) -> anyhow::Result<()> {
+        if !from.starts_with('/') || !to.starts_with('/') {
+            return Err(anyhow!("rewrite path prefix should start with '/'"));
+        }
+        self.path_rewrite = Some((from, to));
+        Ok(())
+    }
+
+    fn set_reply(&mut self, reply: HttpRewriteReply) -> anyhow::Result<()> {
+        if self.reply.is_some() {
+            return Err(anyhow!("only one of redirect and deny can be set"));
+        }
+        self.reply = Some(reply);
+        Ok(())
+    }
+
+    fn check(&self) -> anyhow::Result<()> {
+        if self.reply.is_some()
+            && (self.path_rewrite.is_some()
+                || !self.request_header.is_empty()
+                || !self.response_header.is_empty())
+        {
+            return Err(anyhow!(
+                "no rewrite action should be set if the rule has a synthetic reply"
+            ));
+        }
+        Ok(())
+    }
+
+    fn rewrite_uri(&self, uri: &Uri) -> Option<Uri> {
+        let (from, to) = self.path_rewrite.as_ref()?;
+        let left = uri.path().strip_prefix(from.as_str())?;
+        let new_pa = match uri.query() {
+            Some(q) => format!("{to}{left}?{q}"),
+            None => format!("{to}{left}"),
+        };
+        let pa = PathAndQuery::from_str(&new_pa).ok()?;
+        let mut parts = uri.clone().into_parts();
+        parts.path_and_query = Some(pa);
+        Uri::from_parts(parts).ok()
+    }
+
+    pub(crate) fn rewrite_request(&self, req: &mut HttpProxyClientRequest) {
+        if let Some(uri) = self.rewrite_uri(&req.uri) {
+            req.uri = uri;
+        }
+        self.request_header.apply(&mut req.end_to_end_headers);
+    }
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub(crate) struct HttpRewriteRuleSet {
+    rules: Vec<Arc<HttpRewriteRule>>,
+}
+
+impl HttpRewriteRuleSet {
+    fn push(&mut self, rule: HttpRewriteRule) -> anyhow::Result<()> {
+        rule.check()?;
+        self.rules.push(Arc::new(rule));
+        Ok(())
+    }
+
+    /// find the first matched rule
+    pub(crate) fn find(
+        &self,
+        method: &Method,
+        upstream: &UpstreamAddr,
+        uri: &Uri,
+    ) -> Option<&Arc<HttpRewriteRule>> {
+        self.rules
+            .iter()
+            .find(|r| r.condition.is_match(method, upstream, uri))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn match_host() {
+        let mut condition = HttpRewriteMatch::default();
+        condition.add_host(".example.net").unwrap();
+        condition.add_host("www.example.org").unwrap();
+
+        assert!(condition.host_match("example.net"));
+        assert!(condition.host_match("www.Example.net"));
+        assert!(!condition.host_match("badexample.net"));
+        assert!(condition.host_match("www.example.org"));
+        assert!(!condition.host_match("example.org"));
+    }
+
+    #[test]
+    fn rewrite_uri() {
+        let mut rule = HttpRewriteRule::default();
+        rule.set_path_rewrite("/api/v1/".to_string(), "/v2/".to_string())
+            .unwrap();
+
+        let uri = Uri::from_static("http://www.example.net/api/v1/a?b=c");
+        let new_uri = rule.rewrite_uri(&uri).unwrap();
+        assert_eq!(new_uri, Uri::from_static("http://www.example.net/v2/a?b=c"));
+
+        let uri = Uri::from_static("http://www.example.net/api/v2/a");
+        assert!(rule.rewrite_uri(&uri).is_none());
+    }
+
+    #[test]
+    fn render_template() {
+        let upstream = UpstreamAddr::from_host_str_and_port("www.example.net", 80).unwrap();
+        let uri = Uri::from_static("http://www.example.net/a?b=c");
+        let vars = HttpRewriteTemplateVars {
+            method: &Method::GET,
+            upstream: &upstream,
+            uri: &uri,
+            user: Some("foo"),
+        };
+        assert_eq!(
+            vars.render("https://${host}${path}?${query}&u=${user}"),
+            "https://www.example.net/a?b=c&u=foo"
+        );
+        assert_eq!(vars.render("${unknown}:${port}"), "${unknown}:80");
+        assert_eq!(vars.render("${method} ${host"), "GET ${host");
+    }
+
+    #[test]
+    fn render_html_template() {
+        let upstream = UpstreamAddr::from_host_str_and_port("www.example.net", 80).unwrap();
+        let uri = Uri::from_static("http://www.example.net/a?b=1&c='d'");
+        let vars = HttpRewriteTemplateVars {
+            method: &Method::GET,
+            upstream: &upstream,
+            uri: &uri,
+            user: Some("<i>\"foo\"</i>"),
+        };
+        assert_eq!(
+            vars.render_html("<p>${query} by ${user}</p>"),
+            "<p>b=1&amp;c=&#39;d&#39; by &lt;i&gt;&quot;foo&quot;&lt;/i&gt;</p>"
+        );
+        assert_eq!(
+            vars.render("${query} by ${user}"),
+            "b=1&c='d' by <i>\"foo\"</i>"
+        );
+    }
+
+    #[test]
+    fn reject_multiple_reply() {
+        let mut rule = HttpRewriteRule::default();
+        rule.set_reply(HttpRewriteReply::new_deny(403, None).unwrap())
+            .unwrap();
+        let redirect =
+            HttpRewriteReply::new_redirect(302, "https://www.example.net/".to_string()).unwrap();
+        assert!(rule.set_reply(redirect).is_err());
+
+        let v = yaml_rust::YamlLoader::load_from_str(
+            "{deny: 403, redirect: 'https://www.example.net/'}",
+        )
+        .unwrap();
+        assert!(HttpRewriteRuleSet::parse_yaml(&v[0]).is_err());
+    }
+}
@@ -16,9 +16,11 @@
 
 use std::path::Path;
 
-use anyhow::anyhow;
+use anyhow::{anyhow, Context};
 use yaml_rust::{yaml, Yaml};
 
+use g3_daemon::config::{ConfigChecker, ConfigDump, ConfigDumpFormat, ConfigDumpObject};
+
 mod graphviz;
 pub use graphviz::graphviz_graph;
 
@@ -31,6 +33,8 @@ pub use plantuml::plantuml_graph;
 pub(crate) mod audit;
 pub(crate) mod auth;
 pub(crate) mod escaper;
+pub(crate) mod http_forward_cache;
+pub(crate) mod http_rewrite;
 pub(crate) mod log;
 pub(crate) mod resolver;
 pub(crate) mod server;
@@ -48,6 +52,145 @@ pub fn load() -> anyhow::Result<&'static Path> {
     Ok(config_file)
 }
 
+/// Check the references between all loaded config objects, and build all TLS configs.
+///
+/// Dependency cycles and missing dependencies of the same kind are detected when loading,
+/// and all other errors will be reported together.
+pub fn check() -> anyhow::Result<()> {
+    let all_resolver = resolver::get_all_sorted().context("failed to get all resolver config")?;
+    let all_escaper = escaper::get_all_sorted().context("failed to get all escaper config")?;
+    let all_server = server::get_all_sorted().context("failed to get all server config")?;
+    let all_user_group = auth::get_all();
+    let all_auditor = audit::get_all();
+
+    let mut checker = ConfigChecker::default();
+    checker.add_names("resolver", all_resolver.iter().map(|c| c.name()));
+    checker.add_names("escaper", all_escaper.iter().map(|c| c.name()));
+    checker.add_names("user_group", all_user_group.iter().map(|c| c.name()));
+    checker.add_names("auditor", all_auditor.iter().map(|c| c.name()));
+
+    for c in &all_escaper {
+        checker.check_reference("escaper", c.name(), c.position(), "resolver", c.resolver());
+        if let Err(e) = c.check_tls() {
+            checker.add_error("escaper", c.name(), c.position(), format_args!("{e:#}"));
+        }
+    }
+
+    for c in &all_server {
+        checker.check_reference("server", c.name(), c.position(), "escaper", c.escaper());
+        checker.check_reference(
+            "server",
+            c.name(),
+            c.position(),
+            "user_group",
+            c.user_group(),
+        );
+        checker.check_reference("server", c.name(), c.position(), "auditor", c.auditor());
+        if let Err(e) = c.check_tls() {
+            checker.add_error("server", c.name(), c.position(), format_args!("{e:#}"));
+        }
+        if !c.user_group().is_empty() && !c.has_ip_locate_service() {
+            if let Some(group) = all_user_group.iter().find(|g| g.name() == c.user_group()) {
+                for user in group
+                    .static_users
+                    .values()
+                    .chain(group.anonymous_user.iter())
+                    .filter(|u| u.ingress_geo_filter.is_some())
+                {
+                    checker.add_error(
+                        "server",
+                        c.name(),
+                        c.position(),
+                        format_args!(
+                            "ip_locate_service is required as user {} in user group {} has ingress_geo_filter set",
+                            user.name(),
+                            group.name()
+                        ),
+                    );
+                }
+            }
+        }
+    }
+
+    for c in &all_auditor {
+        if let Err(e) = c.check_tls() {
+            checker.add_error("auditor", c.name(), c.position(), format_args!("{e:#}"));
+        }
+    }
+
+    checker.finish()
+}
+
+/// Dump the summary of all loaded resolver, escaper, server, user group and auditor config.
+///
+/// Only names, types, positions, references and a few selected fields are included.
+pub fn dump_summary(format: ConfigDumpFormat) -> anyhow::Result<String> {
+    let mut dump = ConfigDump::default();
+
+    for c in resolver::get_all_sorted()? {
+        let mut o = ConfigDumpObject::new(c.name(), c.resolver_type(), c.position().as_ref());
+        o.set_names(
+            "dependent_resolver",
+            c.dependent_resolver().unwrap_or_default(),
+        );
+        dump.add("resolver", o);
+    }
+
+    for c in escaper::get_all_sorted()? {
+        let mut o = ConfigDumpObject::new(c.name(), c.escaper_type(), c.position().as_ref());
+        o.set_name("resolver", c.resolver());
+        o.set_names(
+            "dependent_escaper",
+            c.dependent_escaper().unwrap_or_default(),
+        );
+        if let Some(logger) = c.shared_logger() {
+            o.set_str("shared_logger", logger);
+        }
+        dump.add("escaper", o);
+    }
+
+    for c in server::get_all_sorted()? {
+        let mut o = ConfigDumpObject::new(c.name(), c.server_type(), c.position().as_ref());
+        o.set_name("escaper", c.escaper());
+        o.set_name("user_group", c.user_group());
+        o.set_name("auditor", c.auditor());
+        o.set_names("dependent_server", c.dependent_server().unwrap_or_default());
+        if let Some(logger) = c.shared_logger() {
+            o.set_str("shared_logger", logger);
+        }
+        o.set_duration("task_idle_check_duration", c.task_idle_check_duration());
+        o.set_int("task_idle_max_count", c.task_max_idle_count());
+        dump.add("server", o);
+    }
+
+    let mut all_auth = auth::get_all();
+    all_auth.sort_by(|a, b| a.name().cmp(b.name()));
+    for c in all_auth {
+        let mut o = ConfigDumpObject::new(c.name(), "", c.position().as_ref());
+        let mut static_users: Vec<&str> = c.static_users.keys().map(|k| k.as_ref()).collect();
+        static_users.sort_unstable();
+        o.set_names("static_users", static_users);
+        o.set_bool("dynamic_source", c.dynamic_source.is_some());
+        o.set_duration("refresh_interval", c.refresh_interval);
+        o.set_bool("anonymous_user", c.anonymous_user.is_some());
+        dump.add("user_group", o);
+    }
+
+    let mut all_audit = audit::get_all();
+    all_audit.sort_by(|a, b| a.name().cmp(b.name()));
+    for c in all_audit {
+        let mut o = ConfigDumpObject::new(c.name(), "", c.position().as_ref());
+        o.set_bool("tls_cert_agent", c.tls_cert_agent.is_some());
+        o.set_bool("tls_stream_dump", c.tls_stream_dump.is_some());
+        o.set_bool("icap_reqmod_service", c.icap_reqmod_service.is_some());
+        o.set_bool("icap_respmod_service", c.icap_respmod_service.is_some());
+        o.set_usize("log_uri_max_chars", c.log_uri_max_chars);
+        dump.add("auditor", o);
+    }
+
+    dump.encode(format)
+}
+
 fn clear_all() {
     escaper::clear();
     audit::clear();
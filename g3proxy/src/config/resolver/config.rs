@@ -88,6 +88,7 @@ macro_rules! impl_transparent1 {
 impl AnyResolverConfig {
     impl_transparent0!(name, &MetricsName);
     impl_transparent0!(position, Option<YamlDocPosition>);
+    impl_transparent0!(resolver_type, &'static str);
     impl_transparent0!(dependent_resolver, Option<BTreeSet<MetricsName>>);
 
     impl_transparent1!(diff_action, ResolverConfigDiffAction, &Self);
@@ -25,13 +25,14 @@ use yaml_rust::{yaml, Yaml};
 
 use g3_ftp_client::FtpClientConfig;
 use g3_io_ext::LimitedCopyConfig;
+use g3_ip_locate::IpLocateServiceConfig;
 use g3_tls_ticket::TlsTicketConfig;
 use g3_types::acl::{AclExactPortRule, AclNetworkRuleBuilder};
-use g3_types::acl_set::AclDstHostRuleSetBuilder;
+use g3_types::acl_set::{AclClientGeoRuleSetBuilder, AclDstHostRuleSetBuilder};
 use g3_types::metrics::{MetricsName, StaticMetricsTags};
 use g3_types::net::{
     HttpKeepAliveConfig, HttpServerId, OpensslClientConfigBuilder, RustlsServerConfigBuilder,
-    TcpListenConfig, TcpMiscSockOpts, TcpSockSpeedLimitConfig,
+    SocketBufferConfig, TcpListenConfig, TcpMiscSockOpts, TcpSockSpeedLimitConfig,
 };
 use g3_yaml::YamlDocPosition;
 
@@ -39,6 +40,8 @@ use super::{
     AnyServerConfig, ServerConfig, ServerConfigDiffAction, IDLE_CHECK_DEFAULT_DURATION,
     IDLE_CHECK_MAXIMUM_DURATION,
 };
+use crate::config::http_forward_cache::HttpForwardCacheConfig;
+use crate::config::http_rewrite::HttpRewriteRuleSet;
 
 const SERVER_CONFIG_TYPE: &str = "HttpProxy";
 
@@ -60,6 +63,171 @@ impl Default for HttpProxyServerTimeoutConfig {
     }
 }
 
+/// config for HTTP/2 clients, which is negotiated by TLS ALPN or with prior knowledge
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct HttpProxyServerH2Config {
+    pub(crate) max_header_list_size: u32,
+    pub(crate) max_concurrent_streams: u32,
+    pub(crate) max_frame_size: u32,
+    pub(crate) max_send_buffer_size: usize,
+    pub(crate) handshake_timeout: Duration,
+    pub(crate) enable_connect_udp: bool,
+    pub(crate) udp_socket_buffer: SocketBufferConfig,
+}
+
+impl Default for HttpProxyServerH2Config {
+    fn default() -> Self {
+        HttpProxyServerH2Config {
+            max_header_list_size: 64 * 1024, // 64KB
+            max_concurrent_streams: 128,
+            max_frame_size: 1024 * 1024,            // 1MB
+            max_send_buffer_size: 16 * 1024 * 1024, // 16MB
+            handshake_timeout: Duration::from_secs(10),
+            enable_connect_udp: true,
+            udp_socket_buffer: SocketBufferConfig::default(),
+        }
+    }
+}
+
+impl HttpProxyServerH2Config {
+    fn parse_yaml(v: &Yaml) -> anyhow::Result<Option<Self>> {
+        match v {
+            Yaml::Boolean(enable) => {
+                if *enable {
+                    Ok(Some(HttpProxyServerH2Config::default()))
+                } else {
+                    Ok(None)
+                }
+            }
+            Yaml::Hash(map) => {
+                let mut config = HttpProxyServerH2Config::default();
+                g3_yaml::foreach_kv(map, |k, v| config.set(k, v))?;
+                Ok(Some(config))
+            }
+            Yaml::Null => Ok(None),
+            _ => Err(anyhow!("invalid yaml value type")),
+        }
+    }
+
+    fn set(&mut self, k: &str, v: &Yaml) -> anyhow::Result<()> {
+        match g3_yaml::key::normalize(k).as_str() {
+            "max_header_list_size" => {
+                self.max_header_list_size = g3_yaml::humanize::as_u32(v)
+                    .context(format!("invalid humanize u32 value for key {k}"))?;
+                Ok(())
+            }
+            "max_concurrent_streams" => {
+                self.max_concurrent_streams =
+                    g3_yaml::value::as_u32(v).context(format!("invalid u32 value for key {k}"))?;
+                Ok(())
+            }
+            "max_frame_size" => {
+                let max_frame_size = g3_yaml::humanize::as_u32(v)
+                    .context(format!("invalid humanize u32 value for key {k}"))?;
+                // see h2::frame::{DEFAULT_MAX_FRAME_SIZE, MAX_MAX_FRAME_SIZE}
+                self.max_frame_size = max_frame_size.clamp(1 << 14, (1 << 24) - 1);
+                Ok(())
+            }
+            "max_send_buffer_size" => {
+                self.max_send_buffer_size = g3_yaml::humanize::as_usize(v)
+                    .context(format!("invalid humanize usize value for key {k}"))?;
+                Ok(())
+            }
+            "handshake_timeout" => {
+                self.handshake_timeout = g3_yaml::humanize::as_duration(v)
+                    .context(format!("invalid humanize duration value for key {k}"))?;
+                Ok(())
+            }
+            "enable_connect_udp" => {
+                self.enable_connect_udp = g3_yaml::value::as_bool(v)
+                    .context(format!("invalid bool value for key {k}"))?;
+                Ok(())
+            }
+            "udp_socket_buffer" => {
+                self.udp_socket_buffer = g3_yaml::value::as_socket_buffer_config(v)
+                    .context(format!("invalid socket buffer config value for key {k}"))?;
+                Ok(())
+            }
+            _ => Err(anyhow!("invalid key {k}")),
+        }
+    }
+}
+
+/// config for HTTP/3 clients, which is negotiated by QUIC ALPN
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct HttpProxyServerH3Config {
+    pub(crate) max_field_section_size: u32,
+    pub(crate) handshake_timeout: Duration,
+    pub(crate) enable_connect_udp: bool,
+    pub(crate) udp_socket_buffer: SocketBufferConfig,
+    pub(crate) udp_datagram_queue_size: usize,
+}
+
+impl Default for HttpProxyServerH3Config {
+    fn default() -> Self {
+        HttpProxyServerH3Config {
+            max_field_section_size: 64 * 1024, // 64KB
+            handshake_timeout: Duration::from_secs(10),
+            enable_connect_udp: true,
+            udp_socket_buffer: SocketBufferConfig::default(),
+            udp_datagram_queue_size: 256,
+        }
+    }
+}
+
+impl HttpProxyServerH3Config {
+    fn parse_yaml(v: &Yaml) -> anyhow::Result<Option<Self>> {
+        match v {
+            Yaml::Boolean(enable) => {
+                if *enable {
+                    Ok(Some(HttpProxyServerH3Config::default()))
+                } else {
+                    Ok(None)
+                }
+            }
+            Yaml::Hash(map) => {
+                let mut config = HttpProxyServerH3Config::default();
+                g3_yaml::foreach_kv(map, |k, v| config.set(k, v))?;
+                Ok(Some(config))
+            }
+            Yaml::Null => Ok(None),
+            _ => Err(anyhow!("invalid yaml value type")),
+        }
+    }
+
+    fn set(&mut self, k: &str, v: &Yaml) -> anyhow::Result<()> {
+        match g3_yaml::key::normalize(k).as_str() {
+            "max_field_section_size" => {
+                self.max_field_section_size = g3_yaml::humanize::as_u32(v)
+                    .context(format!("invalid humanize u32 value for key {k}"))?;
+                Ok(())
+            }
+            "handshake_timeout" => {
+                self.handshake_timeout = g3_yaml::humanize::as_duration(v)
+                    .context(format!("invalid humanize duration value for key {k}"))?;
+                Ok(())
+            }
+            "enable_connect_udp" => {
+                self.enable_connect_udp = g3_yaml::value::as_bool(v)
+                    .context(format!("invalid bool value for key {k}"))?;
+                Ok(())
+            }
+            "udp_socket_buffer" => {
+                self.udp_socket_buffer = g3_yaml::value::as_socket_buffer_config(v)
+                    .context(format!("invalid socket buffer config value for key {k}"))?;
+                Ok(())
+            }
+            "udp_datagram_queue_size" => {
+                let size = g3_yaml::value::as_usize(v)
+                    .context(format!("invalid usize value for key {k}"))?;
+                self.udp_datagram_queue_size = size.max(1);
+                Ok(())
+            }
+            _ => Err(anyhow!("invalid key {k}")),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub(crate) struct HttpProxyServerConfig {
     name: MetricsName,
@@ -75,6 +243,8 @@ pub(crate) struct HttpProxyServerConfig {
     pub(crate) client_tls_config: OpensslClientConfigBuilder,
     pub(crate) ftp_client_config: Arc<FtpClientConfig>,
     pub(crate) ingress_net_filter: Option<AclNetworkRuleBuilder>,
+    pub(crate) ingress_geo_filter: Option<AclClientGeoRuleSetBuilder>,
+    pub(crate) ip_locate_service: Option<IpLocateServiceConfig>,
     pub(crate) dst_host_filter: Option<AclDstHostRuleSetBuilder>,
     pub(crate) dst_port_filter: Option<AclExactPortRule>,
     pub(crate) server_id: Option<HttpServerId>,
@@ -99,6 +269,10 @@ pub(crate) struct HttpProxyServerConfig {
     pub(crate) untrusted_read_limit: Option<TcpSockSpeedLimitConfig>,
     pub(crate) egress_path_selection_header: Option<HeaderName>,
     pub(crate) steal_forwarded_for: bool,
+    pub(crate) http_rewrite_rules: Option<Arc<HttpRewriteRuleSet>>,
+    pub(crate) http_forward_cache: Option<HttpForwardCacheConfig>,
+    pub(crate) h2_config: Option<HttpProxyServerH2Config>,
+    pub(crate) h3_config: Option<HttpProxyServerH3Config>,
     pub(crate) extra_metrics_tags: Option<Arc<StaticMetricsTags>>,
 }
 
@@ -118,6 +292,8 @@ impl HttpProxyServerConfig {
             client_tls_config: OpensslClientConfigBuilder::with_cache_for_many_sites(),
             ftp_client_config: Arc::new(Default::default()),
             ingress_net_filter: None,
+            ingress_geo_filter: None,
+            ip_locate_service: None,
             dst_host_filter: None,
             dst_port_filter: None,
             server_id: None,
@@ -142,6 +318,10 @@ impl HttpProxyServerConfig {
             untrusted_read_limit: None,
             egress_path_selection_header: None,
             steal_forwarded_for: false,
+            http_rewrite_rules: None,
+            http_forward_cache: None,
+            h2_config: None,
+            h3_config: None,
             extra_metrics_tags: None,
         }
     }
@@ -237,6 +417,19 @@ impl HttpProxyServerConfig {
                 self.ingress_net_filter = Some(filter);
                 Ok(())
             }
+            "ingress_geo_filter" | "ingress_client_geo_filter" => {
+                let filter = g3_yaml::value::acl_set::as_client_geo_rule_set_builder(v)
+                    .context(format!("invalid client geo acl rule set value for key {k}"))?;
+                self.ingress_geo_filter = Some(filter);
+                Ok(())
+            }
+            "ip_locate_service" => {
+                let service = IpLocateServiceConfig::parse_yaml(v).context(format!(
+                    "invalid ip locate service config value for key {k}"
+                ))?;
+                self.ip_locate_service = Some(service);
+                Ok(())
+            }
             "dst_host_filter_set" => {
                 let filter_set = g3_yaml::value::acl_set::as_dst_host_rule_set_builder(v)
                     .context(format!("invalid dst host acl rule set value for key {k}"))?;
@@ -376,6 +569,30 @@ impl HttpProxyServerConfig {
                     .context(format!("invalid boolean value for key {k}"))?;
                 Ok(())
             }
+            "http_rewrite_rules" | "rewrite_rules" => {
+                let rules = HttpRewriteRuleSet::parse_yaml(v)
+                    .context(format!("invalid http rewrite rules value for key {k}"))?;
+                self.http_rewrite_rules = Some(Arc::new(rules));
+                Ok(())
+            }
+            "http_forward_cache" => {
+                let lookup_dir = g3_daemon::config::get_lookup_dir(self.position.as_ref())?;
+                let cache = HttpForwardCacheConfig::parse_yaml(v, Some(lookup_dir)).context(
+                    format!("invalid http forward cache config value for key {k}"),
+                )?;
+                self.http_forward_cache = Some(cache);
+                Ok(())
+            }
+            "http2" | "h2" => {
+                self.h2_config = HttpProxyServerH2Config::parse_yaml(v)
+                    .context(format!("invalid http2 config value for key {k}"))?;
+                Ok(())
+            }
+            "http3" | "h3" => {
+                self.h3_config = HttpProxyServerH3Config::parse_yaml(v)
+                    .context(format!("invalid http3 config value for key {k}"))?;
+                Ok(())
+            }
             _ => Err(anyhow!("invalid key {k}")),
         }
     }
@@ -387,6 +604,11 @@ impl HttpProxyServerConfig {
         if self.escaper.is_empty() {
             return Err(anyhow!("escaper is not set"));
         }
+        if self.ingress_geo_filter.is_some() && self.ip_locate_service.is_none() {
+            return Err(anyhow!(
+                "ip_locate_service is required as ingress_geo_filter is set"
+            ));
+        }
         if !self.user_group.is_empty() && self.auth_realm.is_empty() {
             // not really necessary as we have set default realm value
             return Err(anyhow!("auth_realm is required is auth is enabled"));
@@ -429,6 +651,20 @@ impl ServerConfig for HttpProxyServerConfig {
         &self.auditor
     }
 
+    fn has_ip_locate_service(&self) -> bool {
+        self.ip_locate_service.is_some()
+    }
+
+    fn check_tls(&self) -> anyhow::Result<()> {
+        if let Some(builder) = &self.server_tls_config {
+            builder.build().context("invalid tls server config")?;
+        }
+        self.client_tls_config
+            .build()
+            .context("invalid tls client config")?;
+        Ok(())
+    }
+
     fn diff_action(&self, new: &AnyServerConfig) -> ServerConfigDiffAction {
         let AnyServerConfig::HttpProxy(new) = new else {
             return ServerConfigDiffAction::SpawnNew;
@@ -22,8 +22,10 @@ use ascii::AsciiString;
 use yaml_rust::{yaml, Yaml};
 
 use g3_io_ext::LimitedCopyConfig;
+use g3_ip_locate::IpLocateServiceConfig;
 use g3_tls_ticket::TlsTicketConfig;
 use g3_types::acl::AclNetworkRuleBuilder;
+use g3_types::acl_set::AclClientGeoRuleSetBuilder;
 use g3_types::metrics::{MetricsName, StaticMetricsTags};
 use g3_types::net::{
     HttpForwardedHeaderType, HttpKeepAliveConfig, HttpServerId, RustlsServerConfigBuilder,
@@ -70,6 +72,8 @@ pub(crate) struct HttpRProxyServerConfig {
     pub(crate) listen: Option<TcpListenConfig>,
     pub(crate) listen_in_worker: bool,
     pub(crate) ingress_net_filter: Option<AclNetworkRuleBuilder>,
+    pub(crate) ingress_geo_filter: Option<AclClientGeoRuleSetBuilder>,
+    pub(crate) ip_locate_service: Option<IpLocateServiceConfig>,
     pub(crate) server_id: Option<HttpServerId>,
     pub(crate) auth_realm: AsciiString,
     pub(crate) tcp_sock_speed_limit: TcpSockSpeedLimitConfig,
@@ -107,6 +111,8 @@ impl HttpRProxyServerConfig {
             listen: None,
             listen_in_worker: false,
             ingress_net_filter: None,
+            ingress_geo_filter: None,
+            ip_locate_service: None,
             server_id: None,
             auth_realm: AsciiString::from_ascii("g3proxy").unwrap(),
             tcp_sock_speed_limit: TcpSockSpeedLimitConfig::default(),
@@ -189,6 +195,19 @@ impl HttpRProxyServerConfig {
                 self.ingress_net_filter = Some(filter);
                 Ok(())
             }
+            "ingress_geo_filter" | "ingress_client_geo_filter" => {
+                let filter = g3_yaml::value::acl_set::as_client_geo_rule_set_builder(v)
+                    .context(format!("invalid client geo acl rule set value for key {k}"))?;
+                self.ingress_geo_filter = Some(filter);
+                Ok(())
+            }
+            "ip_locate_service" => {
+                let service = IpLocateServiceConfig::parse_yaml(v).context(format!(
+                    "invalid ip locate service config value for key {k}"
+                ))?;
+                self.ip_locate_service = Some(service);
+                Ok(())
+            }
             "server_id" => {
                 let server_id = g3_yaml::value::as_http_server_id(v)
                     .context(format!("invalid http server id value for key {k}"))?;
@@ -339,6 +358,11 @@ impl HttpRProxyServerConfig {
         if self.escaper.is_empty() {
             return Err(anyhow!("escaper is not set"));
         }
+        if self.ingress_geo_filter.is_some() && self.ip_locate_service.is_none() {
+            return Err(anyhow!(
+                "ip_locate_service is required as ingress_geo_filter is set"
+            ));
+        }
         if !self.user_group.is_empty() && self.auth_realm.is_empty() {
             // not really necessary as we have set default realm value
             return Err(anyhow!("auth_realm is required is auth is enabled"));
@@ -376,6 +400,34 @@ impl ServerConfig for HttpRProxyServerConfig {
         Default::default()
     }
 
+    fn has_ip_locate_service(&self) -> bool {
+        self.ip_locate_service.is_some()
+    }
+
+    fn check_tls(&self) -> anyhow::Result<()> {
+        if let Some(builder) = &self.global_tls_server {
+            builder
+                .build()
+                .context("invalid global tls server config")?;
+        }
+        self.hosts.try_build_arc(|host| {
+            if let Some(builder) = &host.tls_server_builder {
+                builder.build().context(format!(
+                    "invalid tls server config for upstream {}",
+                    host.upstream()
+                ))?;
+            }
+            if let Some(builder) = &host.tls_client_builder {
+                builder.build().context(format!(
+                    "invalid tls client config for upstream {}",
+                    host.upstream()
+                ))?;
+            }
+            Ok::<(), anyhow::Error>(())
+        })?;
+        Ok(())
+    }
+
     fn diff_action(&self, new: &AnyServerConfig) -> ServerConfigDiffAction {
         let AnyServerConfig::HttpRProxy(new) = new else {
             return ServerConfigDiffAction::SpawnNew;
@@ -39,6 +39,8 @@ pub(crate) struct IntelliProxyConfig {
     pub(crate) ingress_net_filter: Option<AclNetworkRuleBuilder>,
     pub(crate) http_server: MetricsName,
     pub(crate) socks_server: MetricsName,
+    pub(crate) tls_server: MetricsName,
+    pub(crate) h2_server: MetricsName,
     pub(crate) protocol_detection_timeout: Duration,
     pub(crate) proxy_protocol: Option<ProxyProtocolVersion>,
     pub(crate) proxy_protocol_read_timeout: Duration,
@@ -54,6 +56,8 @@ impl IntelliProxyConfig {
             ingress_net_filter: None,
             http_server: MetricsName::default(),
             socks_server: MetricsName::default(),
+            tls_server: MetricsName::default(),
+            h2_server: MetricsName::default(),
             protocol_detection_timeout: Duration::from_secs(4),
             proxy_protocol: None,
             proxy_protocol_read_timeout: Duration::from_secs(5),
@@ -103,6 +107,14 @@ impl IntelliProxyConfig {
                 self.socks_server = g3_yaml::value::as_metrics_name(v)?;
                 Ok(())
             }
+            "tls_server" => {
+                self.tls_server = g3_yaml::value::as_metrics_name(v)?;
+                Ok(())
+            }
+            "h2_server" | "http2_server" => {
+                self.h2_server = g3_yaml::value::as_metrics_name(v)?;
+                Ok(())
+            }
             "protocol_detection_channel_size" => Ok(()),
             "protocol_detection_timeout" => {
                 self.protocol_detection_timeout = g3_yaml::humanize::as_duration(v)
@@ -188,6 +200,12 @@ impl ServerConfig for IntelliProxyConfig {
         let mut set = BTreeSet::new();
         set.insert(self.http_server.clone());
         set.insert(self.socks_server.clone());
+        if !self.tls_server.is_empty() {
+            set.insert(self.tls_server.clone());
+        }
+        if !self.h2_server.is_empty() {
+            set.insert(self.h2_server.clone());
+        }
         Some(set)
     }
 }
@@ -24,7 +24,7 @@ use indexmap::IndexSet;
 use slog::Logger;
 use yaml_rust::{yaml, Yaml};
 
-use g3_daemon::config::sort_nodes_in_dependency_graph;
+use g3_daemon::config::{sort_nodes_in_dependency_graph, ConfigChecker};
 use g3_io_ext::LimitedCopyConfig;
 use g3_types::metrics::MetricsName;
 use g3_yaml::{HybridParser, YamlDocPosition};
@@ -53,6 +53,8 @@ pub(crate) mod tcp_stream;
 ))]
 pub(crate) mod tcp_tproxy;
 pub(crate) mod tls_stream;
+#[cfg(target_os = "linux")]
+pub(crate) mod udp_tproxy;
 
 mod registry;
 pub(crate) use registry::clear;
@@ -107,6 +109,16 @@ pub(crate) trait ServerConfig {
         1
     }
 
+    /// Build all TLS configs, so invalid certificates and keys can be found before spawn
+    fn check_tls(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// Whether the client location will be fetched, which is needed by user level geo filters
+    fn has_ip_locate_service(&self) -> bool {
+        false
+    }
+
     fn get_user_group(&self) -> Option<Arc<UserGroup>> {
         if self.user_group().is_empty() {
             None
@@ -145,6 +157,8 @@ pub(crate) enum AnyServerConfig {
         target_os = "openbsd"
     ))]
     TcpTProxy(tcp_tproxy::TcpTProxyServerConfig),
+    #[cfg(target_os = "linux")]
+    UdpTProxy(udp_tproxy::UdpTProxyServerConfig),
     TlsStream(Box<tls_stream::TlsStreamServerConfig>),
     SniProxy(Box<sni_proxy::SniProxyServerConfig>),
     SocksProxy(Box<socks_proxy::SocksProxyServerConfig>),
@@ -171,6 +185,8 @@ macro_rules! impl_transparent0 {
                     target_os = "openbsd"
                 ))]
                 AnyServerConfig::TcpTProxy(s) => s.$f(),
+                #[cfg(target_os = "linux")]
+                AnyServerConfig::UdpTProxy(s) => s.$f(),
                 AnyServerConfig::TlsStream(s) => s.$f(),
                 AnyServerConfig::SniProxy(s) => s.$f(),
                 AnyServerConfig::SocksProxy(s) => s.$f(),
@@ -200,6 +216,8 @@ macro_rules! impl_transparent1 {
                     target_os = "openbsd"
                 ))]
                 AnyServerConfig::TcpTProxy(s) => s.$f(p),
+                #[cfg(target_os = "linux")]
+                AnyServerConfig::UdpTProxy(s) => s.$f(p),
                 AnyServerConfig::TlsStream(s) => s.$f(p),
                 AnyServerConfig::SniProxy(s) => s.$f(p),
                 AnyServerConfig::SocksProxy(s) => s.$f(p),
@@ -218,6 +236,11 @@ impl AnyServerConfig {
     impl_transparent0!(escaper, &MetricsName);
     impl_transparent0!(user_group, &MetricsName);
     impl_transparent0!(auditor, &MetricsName);
+    impl_transparent0!(shared_logger, Option<&str>);
+    impl_transparent0!(task_idle_check_duration, Duration);
+    impl_transparent0!(task_max_idle_count, i32);
+    impl_transparent0!(check_tls, anyhow::Result<()>);
+    impl_transparent0!(has_ip_locate_service, bool);
 
     impl_transparent1!(diff_action, ServerConfigDiffAction, &Self);
 }
@@ -298,6 +321,12 @@ fn load_server(
                 .context("failed to load this TcpTProxy server")?;
             Ok(AnyServerConfig::TcpTProxy(server))
         }
+        #[cfg(target_os = "linux")]
+        "udp_tproxy" | "udptproxy" => {
+            let server = udp_tproxy::UdpTProxyServerConfig::parse(map, position)
+                .context("failed to load this UdpTProxy server")?;
+            Ok(AnyServerConfig::UdpTProxy(server))
+        }
         "tls_stream" | "tlsstream" => {
             let server = tls_stream::TlsStreamServerConfig::parse(map, position)
                 .context("failed to load this TLsStream server")?;
@@ -398,6 +427,23 @@ fn check_dependency() -> anyhow::Result<()> {
         all_names.insert(conf.name().clone());
     }
 
+    let mut checker = ConfigChecker::default();
+    checker.add_names("server", all_names.iter());
+    for conf in all_config.iter() {
+        if let Some(names) = conf.dependent_server() {
+            for peer_name in &names {
+                checker.check_reference(
+                    "server",
+                    conf.name(),
+                    conf.position(),
+                    "server",
+                    peer_name,
+                );
+            }
+        }
+    }
+    checker.finish()?;
+
     let edges = get_edges_for_dependency_graph(&all_config, &all_names)?;
 
     if let Err(node_index) = sort_nodes_in_dependency_graph(edges) {
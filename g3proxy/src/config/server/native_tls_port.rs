@@ -173,6 +173,13 @@ impl ServerConfig for NativeTlsPortConfig {
         Default::default()
     }
 
+    fn check_tls(&self) -> anyhow::Result<()> {
+        if let Some(builder) = &self.server_tls_config {
+            builder.build().context("invalid tls server config")?;
+        }
+        Ok(())
+    }
+
     fn diff_action(&self, new: &AnyServerConfig) -> ServerConfigDiffAction {
         let AnyServerConfig::NativeTlsPort(new) = new else {
             return ServerConfigDiffAction::SpawnNew;
@@ -23,7 +23,7 @@ use yaml_rust::{yaml, Yaml};
 use g3_tls_ticket::TlsTicketConfig;
 use g3_types::acl::AclNetworkRuleBuilder;
 use g3_types::metrics::MetricsName;
-use g3_types::net::{RustlsServerConfigBuilder, UdpListenConfig};
+use g3_types::net::{AlpnProtocol, RustlsServerConfigBuilder, UdpListenConfig};
 use g3_yaml::YamlDocPosition;
 
 use super::ServerConfig;
@@ -46,6 +46,7 @@ pub(crate) struct PlainQuicPortConfig {
     pub(crate) listen: UdpListenConfig,
     pub(crate) listen_in_worker: bool,
     pub(crate) tls_server: RustlsServerConfigBuilder,
+    pub(crate) alpn_protocols: Option<Vec<AlpnProtocol>>,
     pub(crate) tls_ticketer: Option<TlsTicketConfig>,
     pub(crate) ingress_net_filter: Option<AclNetworkRuleBuilder>,
     pub(crate) server: MetricsName,
@@ -60,6 +61,7 @@ impl PlainQuicPortConfig {
             listen: UdpListenConfig::default(),
             listen_in_worker: false,
             tls_server: RustlsServerConfigBuilder::empty(),
+            alpn_protocols: None,
             tls_ticketer: None,
             ingress_net_filter: None,
             server: MetricsName::default(),
@@ -106,6 +108,16 @@ impl PlainQuicPortConfig {
                     g3_yaml::value::as_rustls_server_config_builder(v, Some(lookup_dir))?;
                 Ok(())
             }
+            "alpn_protocols" | "alpn_protocol" => {
+                let protocols = g3_yaml::value::as_list(v, |v| {
+                    let s = g3_yaml::value::as_string(v)?;
+                    AlpnProtocol::from_buf(s.as_bytes())
+                        .ok_or_else(|| anyhow!("unsupported alpn protocol {s}"))
+                })
+                .context(format!("invalid alpn protocol list value for key {k}"))?;
+                self.alpn_protocols = Some(protocols);
+                Ok(())
+            }
             "tls_ticketer" => {
                 let lookup_dir = g3_daemon::config::get_lookup_dir(self.position.as_ref())?;
                 let ticketer = TlsTicketConfig::parse_yaml(v, Some(lookup_dir))
@@ -168,6 +180,13 @@ impl ServerConfig for PlainQuicPortConfig {
         Default::default()
     }
 
+    fn check_tls(&self) -> anyhow::Result<()> {
+        self.tls_server
+            .build_quic()
+            .context("invalid tls server config")?;
+        Ok(())
+    }
+
     fn diff_action(&self, new: &AnyServerConfig) -> ServerConfigDiffAction {
         let AnyServerConfig::PlainQuicPort(new) = new else {
             return ServerConfigDiffAction::SpawnNew;
@@ -185,7 +204,7 @@ impl ServerConfig for PlainQuicPortConfig {
         if self.listen != new.listen {
             flags.set(PlainQuicPortUpdateFlags::LISTEN, true);
         }
-        if self.tls_server != new.tls_server {
+        if self.tls_server != new.tls_server || self.alpn_protocols != new.alpn_protocols {
             flags.set(PlainQuicPortUpdateFlags::QUINN, true);
         }
         if self.server != new.server {
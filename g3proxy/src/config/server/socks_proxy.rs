@@ -15,6 +15,8 @@
  */
 
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+#[cfg(feature = "gssapi")]
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -24,8 +26,9 @@ use ascii::AsciiString;
 use yaml_rust::{yaml, Yaml};
 
 use g3_io_ext::{LimitedCopyConfig, LimitedUdpRelayConfig};
+use g3_ip_locate::IpLocateServiceConfig;
 use g3_types::acl::{AclExactPortRule, AclNetworkRuleBuilder};
-use g3_types::acl_set::AclDstHostRuleSetBuilder;
+use g3_types::acl_set::{AclClientGeoRuleSetBuilder, AclDstHostRuleSetBuilder};
 use g3_types::metrics::{MetricsName, StaticMetricsTags};
 use g3_types::net::{
     PortRange, SocketBufferConfig, TcpListenConfig, TcpMiscSockOpts, TcpSockSpeedLimitConfig,
@@ -47,6 +50,8 @@ pub(crate) struct SocksProxyServerTimeoutConfig {
     pub(crate) negotiation: Duration,
     /// only for udp associate: client must send first udp packet before this timeout
     pub(crate) udp_client_initial: Duration,
+    /// only for tcp bind: remote peer must connect in before this timeout
+    pub(crate) tcp_bind_accept: Duration,
 }
 
 impl Default for SocksProxyServerTimeoutConfig {
@@ -55,10 +60,54 @@ impl Default for SocksProxyServerTimeoutConfig {
         SocksProxyServerTimeoutConfig {
             negotiation: Duration::from_secs(4),
             udp_client_initial: Duration::from_secs(30),
+            tcp_bind_accept: Duration::from_secs(60),
         }
     }
 }
 
+/// GSSAPI (RFC 1961) authentication config
+#[cfg(feature = "gssapi")]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub(crate) struct SocksProxyGssApiConfig {
+    /// host based service name, like socks@proxy.example.net
+    pub(crate) service: Option<String>,
+    /// the keytab file, the default keytab will be used if not set
+    pub(crate) keytab: Option<PathBuf>,
+    /// strip the realm part of the client principal before user lookup
+    pub(crate) strip_realm: bool,
+}
+
+#[cfg(feature = "gssapi")]
+impl SocksProxyGssApiConfig {
+    fn parse(v: &Yaml, position: Option<&YamlDocPosition>) -> anyhow::Result<Self> {
+        let Yaml::Hash(map) = v else {
+            return Err(anyhow!("yaml value type for gssapi config should be 'map'"));
+        };
+
+        let mut config = SocksProxyGssApiConfig {
+            strip_realm: true,
+            ..Default::default()
+        };
+        g3_yaml::foreach_kv(map, |k, v| match g3_yaml::key::normalize(k).as_str() {
+            "service" | "service_name" => {
+                config.service = Some(g3_yaml::value::as_string(v)?);
+                Ok(())
+            }
+            "keytab" => {
+                let lookup_dir = g3_daemon::config::get_lookup_dir(position)?;
+                config.keytab = Some(g3_yaml::value::as_file_path(v, lookup_dir, false)?);
+                Ok(())
+            }
+            "strip_realm" => {
+                config.strip_realm = g3_yaml::value::as_bool(v)?;
+                Ok(())
+            }
+            _ => Err(anyhow!("invalid key {k}")),
+        })?;
+        Ok(config)
+    }
+}
+
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub(crate) struct SocksProxyServerConfig {
     name: MetricsName,
@@ -66,6 +115,8 @@ pub(crate) struct SocksProxyServerConfig {
     pub(crate) escaper: MetricsName,
     pub(crate) auditor: MetricsName,
     pub(crate) user_group: MetricsName,
+    #[cfg(feature = "gssapi")]
+    pub(crate) gssapi: Option<SocksProxyGssApiConfig>,
     pub(crate) shared_logger: Option<AsciiString>,
     pub(crate) listen: Option<TcpListenConfig>,
     pub(crate) listen_in_worker: bool,
@@ -75,6 +126,8 @@ pub(crate) struct SocksProxyServerConfig {
     pub(crate) udp_bind_port_range: Option<PortRange>,
     pub(crate) udp_socket_buffer: SocketBufferConfig,
     pub(crate) ingress_net_filter: Option<AclNetworkRuleBuilder>,
+    pub(crate) ingress_geo_filter: Option<AclClientGeoRuleSetBuilder>,
+    pub(crate) ip_locate_service: Option<IpLocateServiceConfig>,
     pub(crate) dst_host_filter: Option<AclDstHostRuleSetBuilder>,
     pub(crate) dst_port_filter: Option<AclExactPortRule>,
     pub(crate) tcp_sock_speed_limit: TcpSockSpeedLimitConfig,
@@ -98,6 +151,8 @@ impl SocksProxyServerConfig {
             escaper: MetricsName::default(),
             auditor: MetricsName::default(),
             user_group: MetricsName::default(),
+            #[cfg(feature = "gssapi")]
+            gssapi: None,
             shared_logger: None,
             listen: None,
             listen_in_worker: false,
@@ -107,6 +162,8 @@ impl SocksProxyServerConfig {
             udp_bind_port_range: None,
             udp_socket_buffer: SocketBufferConfig::default(),
             ingress_net_filter: None,
+            ingress_geo_filter: None,
+            ip_locate_service: None,
             dst_host_filter: None,
             dst_port_filter: None,
             tcp_sock_speed_limit: TcpSockSpeedLimitConfig::default(),
@@ -154,6 +211,13 @@ impl SocksProxyServerConfig {
                 self.user_group = g3_yaml::value::as_metrics_name(v)?;
                 Ok(())
             }
+            #[cfg(feature = "gssapi")]
+            "gssapi" => {
+                let config = SocksProxyGssApiConfig::parse(v, self.position.as_ref())
+                    .context(format!("invalid gssapi config value for key {k}"))?;
+                self.gssapi = Some(config);
+                Ok(())
+            }
             "shared_logger" => {
                 let name = g3_yaml::value::as_ascii(v)?;
                 self.shared_logger = Some(name);
@@ -211,6 +275,19 @@ impl SocksProxyServerConfig {
                 self.ingress_net_filter = Some(filter);
                 Ok(())
             }
+            "ingress_geo_filter" | "ingress_client_geo_filter" => {
+                let filter = g3_yaml::value::acl_set::as_client_geo_rule_set_builder(v)
+                    .context(format!("invalid client geo acl rule set value for key {k}"))?;
+                self.ingress_geo_filter = Some(filter);
+                Ok(())
+            }
+            "ip_locate_service" => {
+                let service = IpLocateServiceConfig::parse_yaml(v).context(format!(
+                    "invalid ip locate service config value for key {k}"
+                ))?;
+                self.ip_locate_service = Some(service);
+                Ok(())
+            }
             "dst_host_filter_set" => {
                 let filter_set = g3_yaml::value::acl_set::as_dst_host_rule_set_builder(v)
                     .context(format!("invalid dst host acl rule set value for key {k}"))?;
@@ -285,6 +362,11 @@ impl SocksProxyServerConfig {
                     .context(format!("invalid humanize duration value for key {k}"))?;
                 Ok(())
             }
+            "tcp_bind_accept_timeout" => {
+                self.timeout.tcp_bind_accept = g3_yaml::humanize::as_duration(v)
+                    .context(format!("invalid humanize duration value for key {k}"))?;
+                Ok(())
+            }
             "task_idle_check_duration" => {
                 self.task_idle_check_duration = g3_yaml::humanize::as_duration(v)
                     .context(format!("invalid humanize duration value for key {k}"))?;
@@ -322,6 +404,15 @@ impl SocksProxyServerConfig {
         if self.escaper.is_empty() {
             return Err(anyhow!("escaper is not set"));
         }
+        if self.ingress_geo_filter.is_some() && self.ip_locate_service.is_none() {
+            return Err(anyhow!(
+                "ip_locate_service is required as ingress_geo_filter is set"
+            ));
+        }
+        #[cfg(feature = "gssapi")]
+        if self.gssapi.is_some() && self.user_group.is_empty() {
+            return Err(anyhow!("user group is required to enable gssapi auth"));
+        }
         if self.task_idle_check_duration > IDLE_CHECK_MAXIMUM_DURATION {
             self.task_idle_check_duration = IDLE_CHECK_MAXIMUM_DURATION;
         }
@@ -370,6 +461,10 @@ impl ServerConfig for SocksProxyServerConfig {
         &self.auditor
     }
 
+    fn has_ip_locate_service(&self) -> bool {
+        self.ip_locate_service.is_some()
+    }
+
     fn diff_action(&self, new: &AnyServerConfig) -> ServerConfigDiffAction {
         let AnyServerConfig::SocksProxy(new) = new else {
             return ServerConfigDiffAction::SpawnNew;
@@ -257,6 +257,13 @@ impl ServerConfig for TcpStreamServerConfig {
         &self.auditor
     }
 
+    fn check_tls(&self) -> anyhow::Result<()> {
+        if let Some(builder) = &self.client_tls_config {
+            builder.build().context("invalid tls client config")?;
+        }
+        Ok(())
+    }
+
     fn diff_action(&self, new: &AnyServerConfig) -> ServerConfigDiffAction {
         let AnyServerConfig::TcpStream(new) = new else {
             return ServerConfigDiffAction::SpawnNew;
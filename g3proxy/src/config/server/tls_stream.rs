@@ -281,6 +281,16 @@ impl ServerConfig for TlsStreamServerConfig {
         &self.auditor
     }
 
+    fn check_tls(&self) -> anyhow::Result<()> {
+        self.server_tls_config
+            .build()
+            .context("invalid tls server config")?;
+        if let Some(builder) = &self.client_tls_config {
+            builder.build().context("invalid tls client config")?;
+        }
+        Ok(())
+    }
+
     fn diff_action(&self, new: &AnyServerConfig) -> ServerConfigDiffAction {
         let AnyServerConfig::TlsStream(new) = new else {
             return ServerConfigDiffAction::SpawnNew;
@@ -0,0 +1,259 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context};
+use ascii::AsciiString;
+use yaml_rust::{yaml, Yaml};
+
+use g3_io_ext::LimitedUdpRelayConfig;
+use g3_types::acl::AclNetworkRuleBuilder;
+use g3_types::metrics::{MetricsName, StaticMetricsTags};
+use g3_types::net::{
+    SocketBufferConfig, UdpListenConfig, UdpMiscSockOpts, UdpSockSpeedLimitConfig,
+};
+use g3_yaml::YamlDocPosition;
+
+use super::{AnyServerConfig, ServerConfig, ServerConfigDiffAction, IDLE_CHECK_MAXIMUM_DURATION};
+
+const SERVER_CONFIG_TYPE: &str = "UdpTProxy";
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct UdpTProxyServerConfig {
+    name: MetricsName,
+    position: Option<YamlDocPosition>,
+    pub(crate) escaper: MetricsName,
+    pub(crate) shared_logger: Option<AsciiString>,
+    pub(crate) listen: UdpListenConfig,
+    pub(crate) listen_in_worker: bool,
+    pub(crate) ingress_net_filter: Option<AclNetworkRuleBuilder>,
+    pub(crate) udp_sock_speed_limit: UdpSockSpeedLimitConfig,
+    pub(crate) udp_relay: LimitedUdpRelayConfig,
+    pub(crate) udp_socket_buffer: SocketBufferConfig,
+    pub(crate) udp_misc_opts: UdpMiscSockOpts,
+    pub(crate) flow_queue_size: usize,
+    pub(crate) max_flows: usize,
+    pub(crate) task_idle_check_duration: Duration,
+    pub(crate) task_idle_max_count: i32,
+    pub(crate) extra_metrics_tags: Option<Arc<StaticMetricsTags>>,
+}
+
+impl UdpTProxyServerConfig {
+    fn new(position: Option<YamlDocPosition>) -> Self {
+        UdpTProxyServerConfig {
+            name: MetricsName::default(),
+            position,
+            escaper: MetricsName::default(),
+            shared_logger: None,
+            listen: UdpListenConfig::default(),
+            listen_in_worker: false,
+            ingress_net_filter: None,
+            udp_sock_speed_limit: UdpSockSpeedLimitConfig::default(),
+            udp_relay: Default::default(),
+            udp_socket_buffer: SocketBufferConfig::default(),
+            udp_misc_opts: Default::default(),
+            flow_queue_size: 128,
+            max_flows: 65536,
+            task_idle_check_duration: Duration::from_secs(60),
+            task_idle_max_count: 1,
+            extra_metrics_tags: None,
+        }
+    }
+
+    pub(crate) fn parse(
+        map: &yaml::Hash,
+        position: Option<YamlDocPosition>,
+    ) -> anyhow::Result<Self> {
+        let mut server = UdpTProxyServerConfig::new(position);
+
+        g3_yaml::foreach_kv(map, |k, v| server.set(k, v))?;
+
+        server.check()?;
+        Ok(server)
+    }
+
+    fn set(&mut self, k: &str, v: &Yaml) -> anyhow::Result<()> {
+        match g3_yaml::key::normalize(k).as_str() {
+            super::CONFIG_KEY_SERVER_TYPE => Ok(()),
+            super::CONFIG_KEY_SERVER_NAME => {
+                self.name = g3_yaml::value::as_metrics_name(v)?;
+                Ok(())
+            }
+            "escaper" => {
+                self.escaper = g3_yaml::value::as_metrics_name(v)?;
+                Ok(())
+            }
+            "shared_logger" => {
+                let name = g3_yaml::value::as_ascii(v)?;
+                self.shared_logger = Some(name);
+                Ok(())
+            }
+            "extra_metrics_tags" => {
+                let tags = g3_yaml::value::as_static_metrics_tags(v)
+                    .context(format!("invalid static metrics tags value for key {k}"))?;
+                self.extra_metrics_tags = Some(Arc::new(tags));
+                Ok(())
+            }
+            "listen" => {
+                self.listen = g3_yaml::value::as_udp_listen_config(v)
+                    .context(format!("invalid udp listen config value for key {k}"))?;
+                Ok(())
+            }
+            "listen_in_worker" => {
+                self.listen_in_worker = g3_yaml::value::as_bool(v)?;
+                Ok(())
+            }
+            "ingress_network_filter" | "ingress_net_filter" => {
+                let filter = g3_yaml::value::acl::as_ingress_network_rule_builder(v).context(
+                    format!("invalid ingress network acl rule value for key {k}"),
+                )?;
+                self.ingress_net_filter = Some(filter);
+                Ok(())
+            }
+            "udp_sock_speed_limit"
+            | "udp_relay_speed_limit"
+            | "udp_relay_limit"
+            | "relay_limit" => {
+                self.udp_sock_speed_limit = g3_yaml::value::as_udp_sock_speed_limit(v)
+                    .context(format!("invalid udp socket speed limit value for key {k}"))?;
+                Ok(())
+            }
+            "udp_relay_packet_size" => {
+                let packet_size = g3_yaml::humanize::as_usize(v)
+                    .context(format!("invalid humanize usize value for key {k}"))?;
+                self.udp_relay.set_packet_size(packet_size);
+                Ok(())
+            }
+            "udp_relay_yield_size" => {
+                let yield_size = g3_yaml::humanize::as_usize(v)
+                    .context(format!("invalid humanize usize value for key {k}"))?;
+                self.udp_relay.set_yield_size(yield_size);
+                Ok(())
+            }
+            "udp_relay_batch_size" => {
+                let batch_size = g3_yaml::value::as_usize(v)?;
+                self.udp_relay.set_batch_size(batch_size);
+                Ok(())
+            }
+            "udp_socket_buffer" => {
+                self.udp_socket_buffer = g3_yaml::value::as_socket_buffer_config(v)
+                    .context(format!("invalid socket buffer config value for key {k}"))?;
+                Ok(())
+            }
+            "udp_misc_opts" => {
+                self.udp_misc_opts = g3_yaml::value::as_udp_misc_sock_opts(v)
+                    .context(format!("invalid udp misc sock opts value for key {k}"))?;
+                Ok(())
+            }
+            "flow_queue_size" => {
+                self.flow_queue_size = g3_yaml::value::as_usize(v)?;
+                Ok(())
+            }
+            "max_flows" => {
+                self.max_flows = g3_yaml::value::as_usize(v)?;
+                Ok(())
+            }
+            "task_idle_check_duration" => {
+                self.task_idle_check_duration = g3_yaml::humanize::as_duration(v)
+                    .context(format!("invalid humanize duration value for key {k}"))?;
+                Ok(())
+            }
+            "task_idle_max_count" => {
+                self.task_idle_max_count =
+                    g3_yaml::value::as_i32(v).context(format!("invalid i32 value for key {k}"))?;
+                Ok(())
+            }
+            _ => Err(anyhow!("invalid key {k}")),
+        }
+    }
+
+    fn check(&mut self) -> anyhow::Result<()> {
+        if self.name.is_empty() {
+            return Err(anyhow!("name is not set"));
+        }
+        if self.escaper.is_empty() {
+            return Err(anyhow!("escaper is not set"));
+        }
+        if self.flow_queue_size == 0 {
+            return Err(anyhow!("flow queue size should not be zero"));
+        }
+        if self.task_idle_check_duration > IDLE_CHECK_MAXIMUM_DURATION {
+            self.task_idle_check_duration = IDLE_CHECK_MAXIMUM_DURATION;
+        }
+
+        self.listen.set_transparent();
+        self.listen.check()?;
+
+        Ok(())
+    }
+}
+
+impl ServerConfig for UdpTProxyServerConfig {
+    fn name(&self) -> &MetricsName {
+        &self.name
+    }
+
+    fn position(&self) -> Option<YamlDocPosition> {
+        self.position.clone()
+    }
+
+    fn server_type(&self) -> &'static str {
+        SERVER_CONFIG_TYPE
+    }
+
+    fn escaper(&self) -> &MetricsName {
+        &self.escaper
+    }
+
+    fn user_group(&self) -> &MetricsName {
+        Default::default()
+    }
+
+    fn auditor(&self) -> &MetricsName {
+        Default::default()
+    }
+
+    fn diff_action(&self, new: &AnyServerConfig) -> ServerConfigDiffAction {
+        let AnyServerConfig::UdpTProxy(new) = new else {
+            return ServerConfigDiffAction::SpawnNew;
+        };
+
+        if self.eq(new) {
+            return ServerConfigDiffAction::NoAction;
+        }
+
+        if self.listen != new.listen {
+            return ServerConfigDiffAction::ReloadAndRespawn;
+        }
+
+        ServerConfigDiffAction::ReloadOnlyConfig
+    }
+
+    fn shared_logger(&self) -> Option<&str> {
+        self.shared_logger.as_ref().map(|s| s.as_str())
+    }
+
+    #[inline]
+    fn task_idle_check_duration(&self) -> Duration {
+        self.task_idle_check_duration
+    }
+    #[inline]
+    fn task_max_idle_count(&self) -> i32 {
+        self.task_idle_max_count
+    }
+}
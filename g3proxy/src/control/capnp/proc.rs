@@ -16,6 +16,7 @@
 
 use capnp::capability::Promise;
 use capnp_rpc::pry;
+use uuid::Uuid;
 
 use g3_types::metrics::MetricsName;
 
@@ -23,10 +24,12 @@ use g3proxy_proto::escaper_capnp::escaper_control;
 use g3proxy_proto::proc_capnp::proc_control;
 use g3proxy_proto::resolver_capnp::resolver_control;
 use g3proxy_proto::server_capnp::server_control;
+use g3proxy_proto::task_capnp::task_info;
 use g3proxy_proto::types_capnp::fetch_result;
 use g3proxy_proto::user_group_capnp::user_group_control;
 
 use super::set_operation_result;
+use crate::serve::task_registry::AliveTask;
 
 pub(super) struct ProcControlImpl;
 
@@ -280,6 +283,105 @@ impl proc_control::Server for ProcControlImpl {
         results.get().init_result().set_ok("success");
         Promise::ok(())
     }
+
+    fn list_task(
+        &mut self,
+        params: proc_control::ListTaskParams,
+        mut results: proc_control::ListTaskResults,
+    ) -> Promise<(), capnp::Error> {
+        let filter = pry!(pry!(params.get()).get_filter());
+        let server = pry!(pry!(filter.get_server()).to_str());
+        let user_group = pry!(pry!(filter.get_user_group()).to_str());
+        let user = pry!(pry!(filter.get_user()).to_str());
+        let escaper = pry!(pry!(filter.get_escaper()).to_str());
+        let min_alive_secs = filter.get_min_alive_seconds();
+
+        let mut tasks = Vec::new();
+        crate::serve::task_registry::foreach(|task| {
+            if !server.is_empty() && task.server.as_str() != server {
+                return;
+            }
+            if !user_group.is_empty()
+                && task.user_group.as_ref().map(|g| g.as_str()) != Some(user_group)
+            {
+                return;
+            }
+            if !user.is_empty() && task.user.as_deref() != Some(user) {
+                return;
+            }
+            if !escaper.is_empty() && task.escaper.as_str() != escaper {
+                return;
+            }
+            if task.alive_time().as_secs() < min_alive_secs {
+                return;
+            }
+            tasks.push(task.clone());
+        });
+
+        let mut builder = results.get().init_result(tasks.len() as u32);
+        for (i, task) in tasks.iter().enumerate() {
+            set_task_info(builder.reborrow().get(i as u32), task);
+        }
+        Promise::ok(())
+    }
+
+    fn kill_task(
+        &mut self,
+        params: proc_control::KillTaskParams,
+        mut results: proc_control::KillTaskResults,
+    ) -> Promise<(), capnp::Error> {
+        let id = pry!(pry!(pry!(params.get()).get_id()).to_str());
+        let r = match Uuid::parse_str(id) {
+            Ok(id) => {
+                if crate::serve::task_registry::kill(&id) {
+                    Ok(())
+                } else {
+                    Err(anyhow::anyhow!("no alive task with id {id} found"))
+                }
+            }
+            Err(e) => Err(anyhow::anyhow!("invalid task id {id}: {e}")),
+        };
+        set_operation_result(results.get().init_result(), r);
+        Promise::ok(())
+    }
+
+    fn kill_user_task(
+        &mut self,
+        params: proc_control::KillUserTaskParams,
+        mut results: proc_control::KillUserTaskResults,
+    ) -> Promise<(), capnp::Error> {
+        let params = pry!(params.get());
+        let user_group = pry!(pry!(params.get_user_group()).to_str());
+        let user = pry!(pry!(params.get_user()).to_str());
+        let count = crate::serve::task_registry::kill_user(user_group, user);
+        results
+            .get()
+            .init_result()
+            .set_ok(format!("{count} tasks killed").as_str());
+        Promise::ok(())
+    }
+}
+
+fn set_task_info(mut builder: task_info::Builder<'_>, task: &AliveTask) {
+    builder.set_id(task.id.to_string().as_str());
+    builder.set_server(task.server.as_str());
+    builder.set_task_type(task.task_type);
+    builder.set_client_addr(task.client_addr.to_string().as_str());
+    builder.set_server_addr(task.server_addr.to_string().as_str());
+    if let Some(user_group) = &task.user_group {
+        builder.set_user_group(user_group.as_str());
+    }
+    if let Some(user) = &task.user {
+        builder.set_user(user.as_ref());
+    }
+    builder.set_upstream(task.upstream.to_string().as_str());
+    builder.set_escaper(task.escaper.as_str());
+    builder.set_start_at(task.start_at.to_rfc3339().as_str());
+    builder.set_alive_seconds(task.alive_time().as_secs());
+    builder.set_clt_read_bytes(task.stats.clt_read_bytes());
+    builder.set_clt_write_bytes(task.stats.clt_write_bytes());
+    builder.set_ups_read_bytes(task.stats.ups_read_bytes());
+    builder.set_ups_write_bytes(task.stats.ups_write_bytes());
 }
 
 fn set_fetch_result<'a, T>(
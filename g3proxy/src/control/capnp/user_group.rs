@@ -19,12 +19,14 @@ use std::sync::Arc;
 use capnp::capability::Promise;
 use capnp_rpc::pry;
 
+use g3_types::limit::RateLimitQuotaConfig;
 use g3_types::metrics::MetricsName;
+use g3_types::stats::{TcpIoSnapshot, UdpIoSnapshot};
 
-use g3proxy_proto::user_group_capnp::user_group_control;
+use g3proxy_proto::user_group_capnp::{rate_limit_quota, user_group_control, user_stats};
 
 use super::set_operation_result;
-use crate::auth::UserGroup;
+use crate::auth::{User, UserForbiddenSnapshot, UserGroup, UserRates, UserType};
 
 pub(super) struct UserGroupControlImpl {
     user_group: Arc<UserGroup>,
@@ -78,4 +80,112 @@ impl user_group_control::Server for UserGroupControlImpl {
             Ok(())
         })
     }
+
+    fn get_user(
+        &mut self,
+        params: user_group_control::GetUserParams,
+        mut results: user_group_control::GetUserResults,
+    ) -> Promise<(), capnp::Error> {
+        let name = pry!(pry!(pry!(params.get()).get_name()).to_str());
+        let (user, user_type) = match self.user_group.get_user(name) {
+            Some((user, user_type)) if user_type != UserType::Anonymous => (user, user_type),
+            _ => {
+                let mut ev = results.get().init_stats().init_err();
+                ev.set_code(-1);
+                ev.set_reason(format!("no user named {name} found").as_str());
+                return Promise::ok(());
+            }
+        };
+        let builder = results.get().init_stats();
+        set_user_stats(builder.init_data(), &user, user_type, user.rates());
+        Promise::ok(())
+    }
+}
+
+fn set_rate_limit_quota(mut builder: rate_limit_quota::Builder<'_>, config: &RateLimitQuotaConfig) {
+    let quota = config.get_inner();
+    builder.set_burst(quota.burst_size().get());
+    builder.set_replenish_interval_millis(quota.replenish_interval().as_millis() as u64);
+}
+
+fn set_user_stats(
+    mut builder: user_stats::Builder<'_>,
+    user: &User,
+    user_type: UserType,
+    rates: UserRates,
+) {
+    builder.set_user_type(user_type.as_str());
+    builder.set_blocked(user.is_blocked());
+    builder.set_expired(user.is_expired());
+
+    builder.set_alive_request_count(user.alive_request_count() as u64);
+    builder.set_alive_request_max(user.alive_request_max() as u64);
+    if let Some(quota) = user.request_rate_limit() {
+        set_rate_limit_quota(builder.reborrow().init_request_rate_limit(), quota);
+    }
+    if let Some(quota) = user.tcp_conn_rate_limit() {
+        set_rate_limit_quota(builder.reborrow().init_tcp_conn_rate_limit(), quota);
+    }
+
+    let mut http_conn_total = 0u64;
+    let mut socks_conn_total = 0u64;
+    let mut l7_conn_alive = 0i32;
+    let mut req_total = 0u64;
+    let mut req_alive = 0i32;
+    let mut req_ready = 0u64;
+    for stats in user.all_request_stats() {
+        http_conn_total += stats.conn_total.get_http();
+        socks_conn_total += stats.conn_total.get_socks();
+        l7_conn_alive += stats.l7_conn_alive.get_http();
+        req_total += stats.req_total.total();
+        req_alive += stats.req_alive.total();
+        req_ready += stats.req_ready.total();
+    }
+    builder.set_http_conn_total(http_conn_total);
+    builder.set_socks_conn_total(socks_conn_total);
+    builder.set_l7_conn_alive(l7_conn_alive);
+    builder.set_req_total(req_total);
+    builder.set_req_alive(req_alive);
+    builder.set_req_ready(req_ready);
+
+    let mut tcp_io = TcpIoSnapshot::default();
+    let mut udp_io = UdpIoSnapshot::default();
+    for stats in user.all_traffic_stats() {
+        tcp_io = tcp_io + stats.io.tcp_total();
+        udp_io = udp_io + stats.io.udp_total();
+    }
+    builder.set_tcp_in_bytes(tcp_io.in_bytes);
+    builder.set_tcp_out_bytes(tcp_io.out_bytes);
+    builder.set_udp_in_bytes(udp_io.in_bytes);
+    builder.set_udp_out_bytes(udp_io.out_bytes);
+
+    let mut ups_io = TcpIoSnapshot::default();
+    for stats in user.all_upstream_traffic_stats() {
+        let tcp = stats.io.tcp.snapshot();
+        let udp = stats.io.udp.snapshot();
+        ups_io.in_bytes += tcp.in_bytes + udp.in_bytes;
+        ups_io.out_bytes += tcp.out_bytes + udp.out_bytes;
+    }
+    builder.set_upstream_in_bytes(ups_io.in_bytes);
+    builder.set_upstream_out_bytes(ups_io.out_bytes);
+
+    let mut forbid = UserForbiddenSnapshot::default();
+    for stats in user.all_forbidden_stats() {
+        forbid += stats.snapshot();
+    }
+    builder.set_forbid_auth_failed(forbid.auth_failed);
+    builder.set_forbid_user_expired(forbid.user_expired);
+    builder.set_forbid_user_blocked(forbid.user_blocked);
+    builder.set_forbid_fully_loaded(forbid.fully_loaded);
+    builder.set_forbid_rate_limited(forbid.rate_limited);
+    builder.set_forbid_proto_banned(forbid.proto_banned);
+    builder.set_forbid_src_blocked(forbid.src_blocked);
+    builder.set_forbid_dest_denied(forbid.dest_denied);
+    builder.set_forbid_ip_blocked(forbid.ip_blocked);
+    builder.set_forbid_ua_blocked(forbid.ua_blocked);
+
+    builder.set_conn_rate(rates.conn_rate);
+    builder.set_req_rate(rates.req_rate);
+    builder.set_rate_limited(rates.is_rate_limited());
+    builder.set_rate_limited_count(rates.rate_limited);
 }
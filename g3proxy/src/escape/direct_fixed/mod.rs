@@ -46,6 +46,7 @@ use crate::module::http_forward::{
     ArcHttpForwardTaskRemoteStats, BoxHttpForwardConnection, BoxHttpForwardContext,
     DirectHttpForwardContext,
 };
+use crate::module::tcp_bind::{TcpBindSetupResult, TcpBindTaskConf};
 use crate::module::tcp_connect::{
     TcpConnectError, TcpConnectResult, TcpConnectTaskConf, TcpConnectTaskNotes, TlsConnectTaskConf,
 };
@@ -63,6 +64,7 @@ pub(crate) use stats::DirectFixedEscaperStats;
 
 mod ftp_connect;
 pub(crate) mod http_forward;
+mod tcp_bind;
 pub(crate) mod tcp_connect;
 mod tls_connect;
 pub(crate) mod udp_connect;
@@ -338,6 +340,18 @@ impl Escaper for DirectFixedEscaper {
             .await
     }
 
+    async fn tcp_setup_bind(
+        &self,
+        task_conf: &TcpBindTaskConf<'_>,
+        tcp_notes: &mut TcpConnectTaskNotes,
+        task_notes: &ServerTaskNotes,
+        task_stats: ArcTcpConnectionTaskRemoteStats,
+    ) -> TcpBindSetupResult {
+        tcp_notes.escaper.clone_from(&self.config.name);
+        self.tcp_bind_listen(task_conf, tcp_notes, task_notes, task_stats)
+            .await
+    }
+
     fn new_http_forward_context(&self, escaper: ArcEscaper) -> BoxHttpForwardContext {
         let ctx = DirectHttpForwardContext::new(self.stats.clone(), escaper);
         Box::new(ctx)
@@ -0,0 +1,172 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use tokio::net::TcpListener;
+use tokio::time::Instant;
+
+use g3_daemon::stat::remote::ArcTcpConnectionTaskRemoteStats;
+use g3_io_ext::{LimitedReader, LimitedWriter};
+use g3_socket::util::AddressFamily;
+use g3_types::acl::{AclAction, AclNetworkRule};
+use g3_types::net::{Host, TcpListenConfig, TcpSockSpeedLimitConfig};
+
+use super::{DirectFixedEscaper, DirectFixedEscaperStats};
+use crate::module::tcp_bind::{TcpBindError, TcpBindListener, TcpBindSetupResult, TcpBindTaskConf};
+use crate::module::tcp_connect::{
+    TcpConnectRemoteWrapperStats, TcpConnectTaskNotes, TcpConnection,
+};
+use crate::serve::ServerTaskNotes;
+
+pub(crate) struct DirectTcpBindListener {
+    listener: TcpListener,
+    listen_addr: SocketAddr,
+    escaper_stats: Arc<DirectFixedEscaperStats>,
+    egress_net_filter: Arc<AclNetworkRule>,
+    wrapper_stats: Arc<TcpConnectRemoteWrapperStats<DirectFixedEscaperStats>>,
+    limit_config: TcpSockSpeedLimitConfig,
+}
+
+impl DirectTcpBindListener {
+    fn check_peer_ip(
+        &self,
+        peer_ip: IpAddr,
+        task_notes: &ServerTaskNotes,
+    ) -> Result<(), TcpBindError> {
+        let (_, action) = self.egress_net_filter.check(peer_ip);
+        let forbid = match action {
+            AclAction::Permit | AclAction::PermitAndLog => false,
+            AclAction::Forbid | AclAction::ForbidAndLog => true,
+        };
+        if forbid {
+            self.escaper_stats.forbidden.add_ip_blocked();
+            if let Some(user_ctx) = task_notes.user_ctx() {
+                user_ctx.add_ip_blocked();
+            }
+            Err(TcpBindError::ForbiddenRemoteAddress)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[async_trait]
+impl TcpBindListener for DirectTcpBindListener {
+    fn listen_addr(&self) -> SocketAddr {
+        self.listen_addr
+    }
+
+    async fn accept(
+        &mut self,
+        tcp_notes: &mut TcpConnectTaskNotes,
+        task_notes: &ServerTaskNotes,
+    ) -> Result<TcpConnection, TcpBindError> {
+        let instant_now = Instant::now();
+        let (stream, peer) = self
+            .listener
+            .accept()
+            .await
+            .map_err(TcpBindError::AcceptFailed)?;
+        tcp_notes.duration = instant_now.elapsed();
+        tcp_notes.next = Some(peer);
+        tcp_notes.tries = 1;
+        self.check_peer_ip(peer.ip(), task_notes)?;
+
+        self.escaper_stats.tcp.add_connection_established();
+        tcp_notes.chained.target_addr = Some(peer);
+        tcp_notes.chained.outgoing_addr = Some(self.listen_addr);
+
+        let (r, w) = stream.into_split();
+        let r = LimitedReader::local_limited(
+            r,
+            self.limit_config.shift_millis,
+            self.limit_config.max_south,
+            self.wrapper_stats.clone(),
+        );
+        let w = LimitedWriter::local_limited(
+            w,
+            self.limit_config.shift_millis,
+            self.limit_config.max_north,
+            self.wrapper_stats.clone(),
+        );
+
+        Ok((Box::new(r), Box::new(w)))
+    }
+}
+
+impl DirectFixedEscaper {
+    fn select_bind_family(
+        &self,
+        task_conf: &TcpBindTaskConf<'_>,
+    ) -> Result<AddressFamily, TcpBindError> {
+        let family = match task_conf.peer.host() {
+            Host::Ip(ip) => AddressFamily::from(ip),
+            Host::Domain(_) => {
+                if self.config.no_ipv4 {
+                    AddressFamily::Ipv6
+                } else {
+                    AddressFamily::Ipv4
+                }
+            }
+        };
+        match family {
+            AddressFamily::Ipv4 if self.config.no_ipv4 => Err(TcpBindError::ForbiddenAddressFamily),
+            AddressFamily::Ipv6 if self.config.no_ipv6 => Err(TcpBindError::ForbiddenAddressFamily),
+            _ => Ok(family),
+        }
+    }
+
+    pub(super) async fn tcp_bind_listen(
+        &self,
+        task_conf: &TcpBindTaskConf<'_>,
+        tcp_notes: &mut TcpConnectTaskNotes,
+        task_notes: &ServerTaskNotes,
+        task_stats: ArcTcpConnectionTaskRemoteStats,
+    ) -> TcpBindSetupResult {
+        let family = self.select_bind_family(task_conf)?;
+        let bind = self.get_bind_random(family, task_notes.egress_path());
+        let bind_ip = bind.ip().unwrap_or(match family {
+            AddressFamily::Ipv4 => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            AddressFamily::Ipv6 => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+        });
+
+        let mut listen_config = TcpListenConfig::new(SocketAddr::new(bind_ip, 0));
+        listen_config.set_backlog(1);
+        let listener = g3_socket::tcp::new_listen_to(&listen_config)
+            .map_err(TcpBindError::SetupSocketFailed)?;
+        let listen_addr = listener
+            .local_addr()
+            .map_err(TcpBindError::SetupSocketFailed)?;
+        tcp_notes.bind = bind;
+        tcp_notes.local = Some(listen_addr);
+        self.stats.tcp.add_connection_attempted();
+
+        let mut wrapper_stats = TcpConnectRemoteWrapperStats::new(&self.stats, task_stats);
+        wrapper_stats.push_user_io_stats(self.fetch_user_upstream_io_stats(task_notes));
+
+        Ok(Box::new(DirectTcpBindListener {
+            listener,
+            listen_addr,
+            escaper_stats: self.stats.clone(),
+            egress_net_filter: self.egress_net_filter.clone(),
+            wrapper_stats: Arc::new(wrapper_stats),
+            limit_config: self.config.general.tcp_sock_speed_limit,
+        }))
+    }
+}
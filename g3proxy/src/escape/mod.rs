@@ -34,6 +34,7 @@ use crate::module::ftp_over_http::{
 use crate::module::http_forward::{
     ArcHttpForwardTaskRemoteStats, BoxHttpForwardConnection, BoxHttpForwardContext,
 };
+use crate::module::tcp_bind::{TcpBindError, TcpBindSetupResult, TcpBindTaskConf};
 use crate::module::tcp_connect::{
     TcpConnectError, TcpConnectResult, TcpConnectTaskConf, TcpConnectTaskNotes, TlsConnectTaskConf,
 };
@@ -63,11 +64,14 @@ mod direct_fixed;
 mod direct_float;
 mod divert_tcp;
 mod dummy_deny;
+mod pooled_tunnel;
 mod proxy_float;
+mod proxy_h2;
 mod proxy_http;
 mod proxy_https;
 mod proxy_socks5;
 mod proxy_socks5s;
+mod proxy_ssh;
 mod route_client;
 mod route_failover;
 mod route_geoip;
@@ -203,6 +207,27 @@ pub(crate) trait Escaper: EscaperInternal {
         task_stats: ArcUdpRelayTaskRemoteStats,
     ) -> UdpRelaySetupResult;
 
+    /// Setup a listen socket for the SOCKS BIND command.
+    ///
+    /// Only the direct escapers provide a local implementation, the route escapers
+    /// will forward the request to the selected next escaper if possible.
+    async fn tcp_setup_bind(
+        &self,
+        task_conf: &TcpBindTaskConf<'_>,
+        tcp_notes: &mut TcpConnectTaskNotes,
+        task_notes: &ServerTaskNotes,
+        task_stats: ArcTcpConnectionTaskRemoteStats,
+    ) -> TcpBindSetupResult {
+        match self._check_out_next_escaper(task_notes, task_conf.peer).await {
+            Some(escaper) => {
+                escaper
+                    .tcp_setup_bind(task_conf, tcp_notes, task_notes, task_stats)
+                    .await
+            }
+            None => Err(TcpBindError::MethodUnavailable),
+        }
+    }
+
     fn new_http_forward_context(&self, escaper: ArcEscaper) -> BoxHttpForwardContext;
 
     async fn new_ftp_connect_context(
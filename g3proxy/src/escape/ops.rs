@@ -34,10 +34,12 @@ use super::direct_float::DirectFloatEscaper;
 use super::divert_tcp::DivertTcpEscaper;
 use super::dummy_deny::DummyDenyEscaper;
 use super::proxy_float::ProxyFloatEscaper;
+use super::proxy_h2::ProxyH2Escaper;
 use super::proxy_http::ProxyHttpEscaper;
 use super::proxy_https::ProxyHttpsEscaper;
 use super::proxy_socks5::ProxySocks5Escaper;
 use super::proxy_socks5s::ProxySocks5sEscaper;
+use super::proxy_ssh::ProxySshEscaper;
 use super::route_client::RouteClientEscaper;
 use super::route_failover::RouteFailoverEscaper;
 use super::route_geoip::RouteGeoIpEscaper;
@@ -264,10 +266,12 @@ async fn spawn_new_unlocked(config: AnyEscaperConfig) -> anyhow::Result<()> {
         AnyEscaperConfig::DivertTcp(c) => DivertTcpEscaper::prepare_initial(c)?,
         AnyEscaperConfig::DummyDeny(c) => DummyDenyEscaper::prepare_initial(c)?,
         AnyEscaperConfig::ProxyFloat(c) => ProxyFloatEscaper::prepare_initial(c).await?,
+        AnyEscaperConfig::ProxyH2(c) => ProxyH2Escaper::prepare_initial(*c)?,
         AnyEscaperConfig::ProxyHttp(c) => ProxyHttpEscaper::prepare_initial(*c)?,
         AnyEscaperConfig::ProxyHttps(c) => ProxyHttpsEscaper::prepare_initial(*c)?,
         AnyEscaperConfig::ProxySocks5(c) => ProxySocks5Escaper::prepare_initial(c)?,
         AnyEscaperConfig::ProxySocks5s(c) => ProxySocks5sEscaper::prepare_initial(c)?,
+        AnyEscaperConfig::ProxySsh(c) => ProxySshEscaper::prepare_initial(*c)?,
         AnyEscaperConfig::RouteFailover(c) => RouteFailoverEscaper::prepare_initial(c)?,
         AnyEscaperConfig::RouteResolved(c) => RouteResolvedEscaper::prepare_initial(c)?,
         AnyEscaperConfig::RouteGeoIp(c) => RouteGeoIpEscaper::prepare_initial(c)?,
@@ -0,0 +1,230 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::future::Future;
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use slog::Logger;
+use tokio::io::{AsyncRead, AsyncWrite, Join};
+
+use g3_daemon::stat::remote::{
+    ArcTcpConnectionTaskRemoteStats, TcpConnectionTaskRemoteStatsWrapper,
+};
+use g3_io_ext::{
+    AsyncStream, LimitedBufReader, LimitedReader, LimitedWriter, NilLimitedReaderStats,
+};
+use g3_openssl::{SslConnector, SslStream};
+
+use crate::auth::UserUpstreamTrafficStats;
+use crate::escape::direct_fixed::http_forward::{DirectHttpForwardReader, DirectHttpForwardWriter};
+use crate::log::escape::tls_handshake::{EscapeLogForTlsHandshake, TlsApplication};
+use crate::module::http_forward::{
+    ArcHttpForwardTaskRemoteStats, BoxHttpForwardConnection, HttpForwardTaskRemoteWrapperStats,
+};
+use crate::module::tcp_connect::{
+    TcpConnectError, TcpConnectResult, TcpConnectTaskConf, TcpConnectTaskNotes, TlsConnectTaskConf,
+};
+use crate::serve::ServerTaskNotes;
+
+mod pool;
+pub(crate) use pool::{
+    PooledTunnel, PooledTunnelEscaperStats, PooledTunnelFetch, PooledTunnelGuard,
+    PooledTunnelHandle, PooledTunnelPool, PooledTunnelStats,
+};
+
+mod stream;
+pub(crate) use stream::{PooledTunnelReader, PooledTunnelWriter};
+
+mod tcp_connect;
+pub(crate) use tcp_connect::PooledTunnelTcpConnector;
+
+/// The shared logic for escapers that multiplex tasks on pooled tunnels to the remote peer,
+/// such as ssh sessions or h2 connections.
+pub(crate) trait PooledTunnelEscaper: Sync {
+    type StreamReader: AsyncRead + Unpin + Send + Sync + 'static;
+    type StreamWriter: AsyncWrite + Unpin + Send + Sync + 'static;
+
+    fn escape_logger(&self) -> &Logger;
+
+    fn fetch_user_upstream_io_stats(
+        &self,
+        task_notes: &ServerTaskNotes,
+    ) -> Vec<Arc<UserUpstreamTrafficStats>>;
+
+    /// Open a new stream to the upstream on a pooled tunnel, with the peer negotiation timeout
+    fn timed_tunnel_connect_to(
+        &self,
+        task_conf: &TcpConnectTaskConf<'_>,
+        tcp_notes: &mut TcpConnectTaskNotes,
+        task_notes: &ServerTaskNotes,
+    ) -> impl Future<Output = Result<(Self::StreamReader, Self::StreamWriter), TcpConnectError>> + Send;
+
+    async fn tunnel_new_tcp_connection(
+        &self,
+        task_conf: &TcpConnectTaskConf<'_>,
+        tcp_notes: &mut TcpConnectTaskNotes,
+        task_notes: &ServerTaskNotes,
+        task_stats: ArcTcpConnectionTaskRemoteStats,
+    ) -> TcpConnectResult {
+        let (r, w) = self
+            .timed_tunnel_connect_to(task_conf, tcp_notes, task_notes)
+            .await?;
+
+        // add task and user stats
+        let mut wrapper_stats = TcpConnectionTaskRemoteStatsWrapper::new(task_stats);
+        wrapper_stats.push_other_stats(self.fetch_user_upstream_io_stats(task_notes));
+        let wrapper_stats = Arc::new(wrapper_stats);
+
+        let r = LimitedReader::new(r, wrapper_stats.clone());
+        let w = LimitedWriter::new(w, wrapper_stats);
+
+        Ok((Box::new(r), Box::new(w)))
+    }
+
+    async fn tunnel_tls_connect_to(
+        &self,
+        task_conf: &TlsConnectTaskConf<'_>,
+        tcp_notes: &mut TcpConnectTaskNotes,
+        task_notes: &ServerTaskNotes,
+        tls_application: TlsApplication,
+    ) -> Result<SslStream<Join<Self::StreamReader, Self::StreamWriter>>, TcpConnectError> {
+        let (r, w) = self
+            .timed_tunnel_connect_to(&task_conf.tcp, tcp_notes, task_notes)
+            .await?;
+
+        let ssl = task_conf.build_ssl()?;
+        let connector = SslConnector::new(ssl, tokio::io::join(r, w))
+            .map_err(|e| TcpConnectError::InternalTlsClientError(anyhow::Error::new(e)))?;
+
+        match tokio::time::timeout(task_conf.handshake_timeout(), connector.connect()).await {
+            Ok(Ok(stream)) => Ok(stream),
+            Ok(Err(e)) => {
+                let e = anyhow::Error::new(e);
+                EscapeLogForTlsHandshake {
+                    upstream: task_conf.tcp.upstream,
+                    tcp_notes,
+                    task_id: &task_notes.id,
+                    tls_name: task_conf.tls_name,
+                    tls_peer: task_conf.tcp.upstream,
+                    tls_application,
+                }
+                .log(self.escape_logger(), &e);
+                Err(TcpConnectError::UpstreamTlsHandshakeFailed(e))
+            }
+            Err(_) => {
+                let e = anyhow!("upstream tls handshake timed out");
+                EscapeLogForTlsHandshake {
+                    upstream: task_conf.tcp.upstream,
+                    tcp_notes,
+                    task_id: &task_notes.id,
+                    tls_name: task_conf.tls_name,
+                    tls_peer: task_conf.tcp.upstream,
+                    tls_application,
+                }
+                .log(self.escape_logger(), &e);
+                Err(TcpConnectError::UpstreamTlsHandshakeTimeout)
+            }
+        }
+    }
+
+    async fn tunnel_new_tls_connection(
+        &self,
+        task_conf: &TlsConnectTaskConf<'_>,
+        tcp_notes: &mut TcpConnectTaskNotes,
+        task_notes: &ServerTaskNotes,
+        task_stats: ArcTcpConnectionTaskRemoteStats,
+    ) -> TcpConnectResult {
+        let tls_stream = self
+            .tunnel_tls_connect_to(task_conf, tcp_notes, task_notes, TlsApplication::TcpStream)
+            .await?;
+
+        let (ups_r, ups_w) = tls_stream.into_split();
+
+        // add task and user stats
+        let mut wrapper_stats = TcpConnectionTaskRemoteStatsWrapper::new(task_stats);
+        wrapper_stats.push_other_stats(self.fetch_user_upstream_io_stats(task_notes));
+        let wrapper_stats = Arc::new(wrapper_stats);
+
+        let ups_r = LimitedReader::new(ups_r, wrapper_stats.clone());
+        let ups_w = LimitedWriter::new(ups_w, wrapper_stats);
+
+        Ok((Box::new(ups_r), Box::new(ups_w)))
+    }
+
+    async fn tunnel_http_forward_new_connection(
+        &self,
+        task_conf: &TcpConnectTaskConf<'_>,
+        tcp_notes: &mut TcpConnectTaskNotes,
+        task_notes: &ServerTaskNotes,
+        task_stats: ArcHttpForwardTaskRemoteStats,
+    ) -> Result<BoxHttpForwardConnection, TcpConnectError> {
+        let (ups_r, ups_w) = self
+            .timed_tunnel_connect_to(task_conf, tcp_notes, task_notes)
+            .await?;
+
+        // add task and user stats, the escaper stats is counted on the pooled tunnel
+        let mut wrapper_stats = HttpForwardTaskRemoteWrapperStats::new(task_stats);
+        wrapper_stats.push_user_io_stats(self.fetch_user_upstream_io_stats(task_notes));
+        let wrapper_stats = Arc::new(wrapper_stats);
+
+        let ups_r = LimitedBufReader::new_unlimited(
+            ups_r,
+            Arc::new(NilLimitedReaderStats::default()),
+            wrapper_stats.clone(),
+        );
+        let ups_w = LimitedWriter::new(ups_w, wrapper_stats);
+
+        let writer = DirectHttpForwardWriter::<_, UserUpstreamTrafficStats>::new(ups_w, None);
+        let reader = DirectHttpForwardReader::new(ups_r);
+        Ok((Box::new(writer), Box::new(reader)))
+    }
+
+    async fn tunnel_https_forward_new_connection(
+        &self,
+        task_conf: &TlsConnectTaskConf<'_>,
+        tcp_notes: &mut TcpConnectTaskNotes,
+        task_notes: &ServerTaskNotes,
+        task_stats: ArcHttpForwardTaskRemoteStats,
+    ) -> Result<BoxHttpForwardConnection, TcpConnectError> {
+        let tls_stream = self
+            .tunnel_tls_connect_to(
+                task_conf,
+                tcp_notes,
+                task_notes,
+                TlsApplication::HttpForward,
+            )
+            .await?;
+
+        let (ups_r, ups_w) = tls_stream.into_split();
+
+        // add task and user stats
+        let mut wrapper_stats = HttpForwardTaskRemoteWrapperStats::new(task_stats);
+        wrapper_stats.push_user_io_stats(self.fetch_user_upstream_io_stats(task_notes));
+        let wrapper_stats = Arc::new(wrapper_stats);
+
+        let ups_r = LimitedBufReader::new_unlimited(
+            ups_r,
+            Arc::new(NilLimitedReaderStats::default()),
+            wrapper_stats.clone(),
+        );
+        let ups_w = LimitedWriter::new(ups_w, wrapper_stats);
+
+        let writer = DirectHttpForwardWriter::<_, UserUpstreamTrafficStats>::new(ups_w, None);
+        let reader = DirectHttpForwardReader::new(ups_r);
+        Ok((Box::new(writer), Box::new(reader)))
+    }
+}
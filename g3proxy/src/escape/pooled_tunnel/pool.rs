@@ -0,0 +1,417 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use ahash::AHashMap;
+use tokio::time::Instant;
+
+use g3_io_ext::{LimitedReaderStats, LimitedWriterStats};
+use g3_socket::BindAddr;
+use g3_types::net::UpstreamAddr;
+
+use crate::escape::EscaperTcpStats;
+use crate::module::tcp_connect::TcpConnectTaskNotes;
+
+/// The escaper stats that the io of pooled tunnels should be counted on
+pub(crate) trait PooledTunnelEscaperStats: Send + Sync {
+    fn tcp(&self) -> &EscaperTcpStats;
+}
+
+/// The handle to open new streams on the tunnel
+pub(crate) trait PooledTunnelHandle {
+    /// Whether the tunnel has been closed by the underlying protocol driver
+    fn is_closed(&self) -> bool {
+        false
+    }
+}
+
+pub(crate) struct PooledTunnelStats {
+    escaper: Arc<dyn PooledTunnelEscaperStats>,
+    created: Instant,
+    alive_streams: AtomicUsize,
+    total_streams: AtomicU64,
+    in_bytes: AtomicU64,
+    out_bytes: AtomicU64,
+}
+
+impl PooledTunnelStats {
+    pub(crate) fn new(escaper: Arc<dyn PooledTunnelEscaperStats>) -> Self {
+        PooledTunnelStats {
+            escaper,
+            created: Instant::now(),
+            alive_streams: AtomicUsize::new(0),
+            total_streams: AtomicU64::new(0),
+            in_bytes: AtomicU64::new(0),
+            out_bytes: AtomicU64::new(0),
+        }
+    }
+
+    pub(crate) fn alive_time(&self) -> Duration {
+        self.created.elapsed()
+    }
+
+    pub(crate) fn alive_streams(&self) -> usize {
+        self.alive_streams.load(Ordering::Acquire)
+    }
+
+    pub(crate) fn total_streams(&self) -> u64 {
+        self.total_streams.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn in_bytes(&self) -> u64 {
+        self.in_bytes.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn out_bytes(&self) -> u64 {
+        self.out_bytes.load(Ordering::Relaxed)
+    }
+}
+
+impl LimitedReaderStats for PooledTunnelStats {
+    fn add_read_bytes(&self, size: usize) {
+        let size = size as u64;
+        self.in_bytes.fetch_add(size, Ordering::Relaxed);
+        self.escaper.tcp().io.add_in_bytes(size);
+    }
+}
+
+impl LimitedWriterStats for PooledTunnelStats {
+    fn add_write_bytes(&self, size: usize) {
+        let size = size as u64;
+        self.out_bytes.fetch_add(size, Ordering::Relaxed);
+        self.escaper.tcp().io.add_out_bytes(size);
+    }
+}
+
+/// Hold a stream slot in the pooled tunnel until dropped
+pub(crate) struct PooledTunnelGuard {
+    stats: Arc<PooledTunnelStats>,
+}
+
+impl Drop for PooledTunnelGuard {
+    fn drop(&mut self) {
+        self.stats.alive_streams.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// A multiplexed tunnel to the remote peer, such as a ssh session or a h2 connection
+pub(crate) struct PooledTunnel<T> {
+    handle: T,
+    bind: BindAddr,
+    local: Option<SocketAddr>,
+    next: Option<SocketAddr>,
+    closed: AtomicBool,
+    stats: Arc<PooledTunnelStats>,
+}
+
+impl<T: PooledTunnelHandle> PooledTunnel<T> {
+    pub(crate) fn new(
+        handle: T,
+        tcp_notes: &TcpConnectTaskNotes,
+        stats: Arc<PooledTunnelStats>,
+    ) -> Self {
+        PooledTunnel {
+            handle,
+            bind: tcp_notes.bind,
+            local: tcp_notes.local,
+            next: tcp_notes.next,
+            closed: AtomicBool::new(false),
+            stats,
+        }
+    }
+
+    #[inline]
+    pub(crate) fn stats(&self) -> &Arc<PooledTunnelStats> {
+        &self.stats
+    }
+
+    #[inline]
+    pub(crate) fn handle(&self) -> &T {
+        &self.handle
+    }
+
+    pub(crate) fn fill_tcp_notes(&self, tcp_notes: &mut TcpConnectTaskNotes) {
+        tcp_notes.bind = self.bind;
+        tcp_notes.local = self.local;
+        tcp_notes.next = self.next;
+    }
+
+    pub(crate) fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::Acquire) || self.handle.is_closed()
+    }
+
+    pub(crate) fn mark_closed(&self) {
+        self.closed.store(true, Ordering::Release);
+    }
+
+    /// Reserve a stream slot, will fail if the tunnel has been closed
+    pub(crate) fn try_acquire(&self) -> Option<PooledTunnelGuard> {
+        self.stats.alive_streams.fetch_add(1, Ordering::SeqCst);
+        if self.closed.load(Ordering::SeqCst) {
+            self.stats.alive_streams.fetch_sub(1, Ordering::SeqCst);
+            return None;
+        }
+        self.stats.total_streams.fetch_add(1, Ordering::Relaxed);
+        Some(PooledTunnelGuard {
+            stats: self.stats.clone(),
+        })
+    }
+
+    /// Mark the tunnel as closed if there is no alive streams
+    pub(crate) fn try_close_idle(&self) -> bool {
+        self.closed.store(true, Ordering::SeqCst);
+        if self.stats.alive_streams.load(Ordering::SeqCst) > 0 {
+            // a new stream has been acquired concurrently
+            self.closed.store(false, Ordering::SeqCst);
+            false
+        } else {
+            true
+        }
+    }
+}
+
+struct PeerTunnels<T> {
+    tunnels: Vec<Arc<PooledTunnel<T>>>,
+    /// the number of new tunnels that are being created
+    pending: usize,
+}
+
+impl<T> Default for PeerTunnels<T> {
+    fn default() -> Self {
+        PeerTunnels {
+            tunnels: Vec::new(),
+            pending: 0,
+        }
+    }
+}
+
+pub(crate) enum PooledTunnelFetch<'a, T> {
+    /// An existed tunnel with a stream slot reserved
+    Reuse(Arc<PooledTunnel<T>>, PooledTunnelGuard),
+    /// A new tunnel should be created and then added to the pool by using the permit
+    New(PooledTunnelPermit<'a, T>),
+    /// All tunnels to the peer have reached the max streams limit, and no more tunnels can be created
+    Full,
+}
+
+/// Hold a tunnel slot in the pool while the new tunnel is being created
+pub(crate) struct PooledTunnelPermit<'a, T> {
+    pool: &'a PooledTunnelPool<T>,
+    peer: Option<UpstreamAddr>,
+}
+
+impl<T> PooledTunnelPermit<'_, T> {
+    /// Add the new tunnel to the pool, and release the permit
+    pub(crate) fn add(mut self, tunnel: Arc<PooledTunnel<T>>) {
+        let Some(peer) = self.peer.take() else {
+            return;
+        };
+        let mut tunnels = self.pool.tunnels.lock().unwrap();
+        let peer_tunnels = tunnels.entry(peer).or_default();
+        peer_tunnels.pending = peer_tunnels.pending.saturating_sub(1);
+        peer_tunnels.tunnels.push(tunnel);
+    }
+}
+
+impl<T> Drop for PooledTunnelPermit<'_, T> {
+    fn drop(&mut self) {
+        let Some(peer) = self.peer.take() else {
+            return;
+        };
+        let mut tunnels = self.pool.tunnels.lock().unwrap();
+        if let Some(peer_tunnels) = tunnels.get_mut(&peer) {
+            peer_tunnels.pending = peer_tunnels.pending.saturating_sub(1);
+            if peer_tunnels.pending == 0 && peer_tunnels.tunnels.is_empty() {
+                tunnels.remove(&peer);
+            }
+        }
+    }
+}
+
+/// The pool of tunnels to each peer.
+///
+/// Both the max tunnels per peer and the max streams per tunnel limits are hard limits,
+/// tunnels that are still being created are also counted in the max tunnels limit.
+pub(crate) struct PooledTunnelPool<T> {
+    max_tunnels: usize,
+    max_streams: usize,
+    tunnels: Mutex<AHashMap<UpstreamAddr, PeerTunnels<T>>>,
+}
+
+impl<T: PooledTunnelHandle> PooledTunnelPool<T> {
+    pub(crate) fn new(max_tunnels: usize, max_streams: usize) -> Self {
+        PooledTunnelPool {
+            max_tunnels,
+            max_streams,
+            tunnels: Mutex::new(AHashMap::new()),
+        }
+    }
+
+    /// Select the least loaded tunnel to the peer, or get a permit to create a new one
+    pub(crate) fn fetch(&self, peer: &UpstreamAddr) -> PooledTunnelFetch<'_, T> {
+        let mut tunnels = self.tunnels.lock().unwrap();
+        let peer_tunnels = tunnels.entry(peer.clone()).or_default();
+        peer_tunnels.tunnels.retain(|c| !c.is_closed());
+
+        let mut selected: Option<&Arc<PooledTunnel<T>>> = None;
+        let mut min_alive_streams = self.max_streams;
+        for c in peer_tunnels.tunnels.iter() {
+            let alive_streams = c.stats().alive_streams();
+            if alive_streams < min_alive_streams {
+                min_alive_streams = alive_streams;
+                selected = Some(c);
+            }
+        }
+        // the alive streams count will only be increased with the lock held
+        if let Some(c) = selected {
+            if let Some(guard) = c.try_acquire() {
+                return PooledTunnelFetch::Reuse(Arc::clone(c), guard);
+            }
+        }
+
+        if peer_tunnels.tunnels.len() + peer_tunnels.pending < self.max_tunnels {
+            peer_tunnels.pending += 1;
+            PooledTunnelFetch::New(PooledTunnelPermit {
+                pool: self,
+                peer: Some(peer.clone()),
+            })
+        } else {
+            PooledTunnelFetch::Full
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    struct MockEscaperStats {
+        tcp: EscaperTcpStats,
+    }
+
+    impl PooledTunnelEscaperStats for MockEscaperStats {
+        fn tcp(&self) -> &EscaperTcpStats {
+            &self.tcp
+        }
+    }
+
+    struct MockHandle;
+
+    impl PooledTunnelHandle for MockHandle {}
+
+    fn new_tunnel() -> Arc<PooledTunnel<MockHandle>> {
+        let escaper_stats = Arc::new(MockEscaperStats {
+            tcp: EscaperTcpStats::default(),
+        });
+        let stats = Arc::new(PooledTunnelStats::new(escaper_stats));
+        Arc::new(PooledTunnel::new(
+            MockHandle,
+            &TcpConnectTaskNotes::default(),
+            stats,
+        ))
+    }
+
+    fn expect_new<'a, T>(r: PooledTunnelFetch<'a, T>) -> PooledTunnelPermit<'a, T> {
+        match r {
+            PooledTunnelFetch::New(permit) => permit,
+            PooledTunnelFetch::Reuse(..) => panic!("unexpected reuse"),
+            PooledTunnelFetch::Full => panic!("unexpected full"),
+        }
+    }
+
+    fn expect_reuse<T>(r: PooledTunnelFetch<'_, T>) -> (Arc<PooledTunnel<T>>, PooledTunnelGuard) {
+        match r {
+            PooledTunnelFetch::Reuse(tunnel, guard) => (tunnel, guard),
+            PooledTunnelFetch::New(_) => panic!("unexpected new"),
+            PooledTunnelFetch::Full => panic!("unexpected full"),
+        }
+    }
+
+    #[test]
+    fn max_streams() {
+        let pool = PooledTunnelPool::new(2, 2);
+        let peer = UpstreamAddr::from_str("proxy.example.net:443").unwrap();
+
+        let permit = expect_new(pool.fetch(&peer));
+        let t1 = new_tunnel();
+        let _g1 = t1.try_acquire().unwrap();
+        permit.add(t1.clone());
+
+        let (t, _g2) = expect_reuse(pool.fetch(&peer));
+        assert!(Arc::ptr_eq(&t, &t1));
+        assert_eq!(t1.stats().alive_streams(), 2);
+
+        let permit = expect_new(pool.fetch(&peer));
+        let t2 = new_tunnel();
+        let _g3 = t2.try_acquire().unwrap();
+        permit.add(t2.clone());
+
+        let (t, g4) = expect_reuse(pool.fetch(&peer));
+        assert!(Arc::ptr_eq(&t, &t2));
+
+        // the stream limit is not ignored after reaching the tunnel limit
+        assert!(matches!(pool.fetch(&peer), PooledTunnelFetch::Full));
+
+        drop(g4);
+        let (t, _g4) = expect_reuse(pool.fetch(&peer));
+        assert!(Arc::ptr_eq(&t, &t2));
+    }
+
+    #[test]
+    fn max_tunnels_pending() {
+        let pool = PooledTunnelPool::new(2, 1);
+        let peer = UpstreamAddr::from_str("proxy.example.net:443").unwrap();
+
+        // concurrent creation should be limited by pending permits
+        let p1 = expect_new(pool.fetch(&peer));
+        let p2 = expect_new(pool.fetch(&peer));
+        assert!(matches!(pool.fetch(&peer), PooledTunnelFetch::Full));
+
+        // a failed creation should release the slot
+        drop(p1);
+        let p3 = expect_new(pool.fetch(&peer));
+
+        let t2 = new_tunnel();
+        let _g2 = t2.try_acquire().unwrap();
+        p2.add(t2);
+        let t3 = new_tunnel();
+        let _g3 = t3.try_acquire().unwrap();
+        p3.add(t3);
+        assert!(matches!(pool.fetch(&peer), PooledTunnelFetch::Full));
+    }
+
+    #[test]
+    fn closed_tunnel() {
+        let pool = PooledTunnelPool::new(1, 8);
+        let peer = UpstreamAddr::from_str("proxy.example.net:443").unwrap();
+
+        let permit = expect_new(pool.fetch(&peer));
+        let t1 = new_tunnel();
+        permit.add(t1.clone());
+
+        assert!(t1.try_close_idle());
+        assert!(t1.try_acquire().is_none());
+
+        let permit = expect_new(pool.fetch(&peer));
+        drop(permit);
+        assert!(pool.tunnels.lock().unwrap().is_empty());
+    }
+}
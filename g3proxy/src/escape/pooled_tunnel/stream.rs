@@ -0,0 +1,80 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::io;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use super::PooledTunnelGuard;
+
+pub(crate) struct PooledTunnelReader<R> {
+    inner: R,
+    _guard: Arc<PooledTunnelGuard>,
+}
+
+impl<R> PooledTunnelReader<R> {
+    pub(crate) fn new(inner: R, guard: Arc<PooledTunnelGuard>) -> Self {
+        PooledTunnelReader {
+            inner,
+            _guard: guard,
+        }
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for PooledTunnelReader<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+pub(crate) struct PooledTunnelWriter<W> {
+    inner: W,
+    _guard: Arc<PooledTunnelGuard>,
+}
+
+impl<W> PooledTunnelWriter<W> {
+    pub(crate) fn new(inner: W, guard: Arc<PooledTunnelGuard>) -> Self {
+        PooledTunnelWriter {
+            inner,
+            _guard: guard,
+        }
+    }
+}
+
+impl<W: AsyncWrite + Unpin> AsyncWrite for PooledTunnelWriter<W> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
@@ -0,0 +1,283 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use bytes::Bytes;
+use h2::client::SendRequest;
+use h2::ext::Protocol;
+use h2::{RecvStream, SendStream};
+use http::{header, HeaderValue, Method, Request, StatusCode, Uri, Version};
+use slog::Logger;
+
+use g3_h2::{H2StreamReader, H2StreamWriter};
+use g3_http::connect::HttpConnectError;
+use g3_types::net::{Host, UpstreamAddr};
+
+use super::{h2_error_to_io, ProxyH2Escaper};
+use crate::auth::UserUpstreamTrafficStats;
+use crate::escape::pooled_tunnel::{
+    PooledTunnelEscaper, PooledTunnelFetch, PooledTunnelGuard, PooledTunnelHandle,
+    PooledTunnelReader, PooledTunnelWriter,
+};
+use crate::escape::{Escaper, EscaperStats};
+use crate::module::tcp_connect::{TcpConnectError, TcpConnectTaskConf, TcpConnectTaskNotes};
+use crate::serve::ServerTaskNotes;
+
+impl PooledTunnelHandle for SendRequest<Bytes> {}
+
+fn check_response_status(status: StatusCode) -> Result<(), TcpConnectError> {
+    if status.is_success() {
+        return Ok(());
+    }
+
+    let code = status.as_u16();
+    let e = if code == 504 || code == 522 || code == 524 {
+        // Peer tells us it timeout
+        HttpConnectError::PeerTimeout(code)
+    } else {
+        HttpConnectError::UnexpectedStatusCode(
+            code,
+            status.canonical_reason().unwrap_or_default().to_string(),
+        )
+    };
+    Err(e.into())
+}
+
+impl ProxyH2Escaper {
+    /// Get a ready h2 stream sender from the pool, a new connection will be created if needed.
+    async fn h2_get_stream(
+        &self,
+        peer: &UpstreamAddr,
+        task_conf: &TcpConnectTaskConf<'_>,
+        tcp_notes: &mut TcpConnectTaskNotes,
+        task_notes: &ServerTaskNotes,
+    ) -> Result<(SendRequest<Bytes>, PooledTunnelGuard), TcpConnectError> {
+        let permit = loop {
+            match self.pool.fetch(peer) {
+                PooledTunnelFetch::Reuse(connection, guard) => {
+                    match connection.handle().clone().ready().await {
+                        Ok(send_request) => {
+                            connection.fill_tcp_notes(tcp_notes);
+                            return Ok((send_request, guard));
+                        }
+                        Err(_) => {
+                            // the connection may be closed by the peer, try the next one
+                            connection.mark_closed();
+                        }
+                    }
+                }
+                PooledTunnelFetch::New(permit) => break permit,
+                PooledTunnelFetch::Full => {
+                    return Err(TcpConnectError::EscaperNotUsable(anyhow!(
+                        "all h2 connections to {peer} have reached the max streams limit"
+                    )));
+                }
+            }
+        };
+
+        let connection = self
+            .h2_new_connection(peer, task_conf, tcp_notes, task_notes)
+            .await?;
+        let guard = connection
+            .try_acquire()
+            .ok_or(TcpConnectError::InternalServerError(
+                "the new h2 connection closed unexpectedly",
+            ))?;
+        permit.add(connection.clone());
+        let send_request = connection
+            .handle()
+            .clone()
+            .ready()
+            .await
+            .map_err(|e| TcpConnectError::NegotiationWriteFailed(h2_error_to_io(e)))?;
+        Ok((send_request, guard))
+    }
+
+    fn h2_set_proxy_auth(
+        &self,
+        req: &mut Request<()>,
+        task_notes: &ServerTaskNotes,
+    ) -> Result<(), TcpConnectError> {
+        let value = if let Some(value) = &self.proxy_auth_value {
+            value.clone()
+        } else if self.config.pass_proxy_userid {
+            let Some(name) = task_notes.raw_user_name() else {
+                return Ok(());
+            };
+            let value = crate::module::http_header::proxy_authorization_basic_pass_value(name);
+            HeaderValue::from_str(&value).map_err(|_| {
+                TcpConnectError::InternalServerError("invalid proxy authorization header value")
+            })?
+        } else {
+            return Ok(());
+        };
+        req.headers_mut().insert(header::PROXY_AUTHORIZATION, value);
+        Ok(())
+    }
+
+    async fn h2_connect_to(
+        &self,
+        task_conf: &TcpConnectTaskConf<'_>,
+        tcp_notes: &mut TcpConnectTaskNotes,
+        task_notes: &ServerTaskNotes,
+    ) -> Result<
+        (
+            PooledTunnelReader<H2StreamReader>,
+            PooledTunnelWriter<H2StreamWriter>,
+        ),
+        TcpConnectError,
+    > {
+        let peer = self
+            .get_next_proxy(task_notes, task_conf.upstream.host())
+            .clone();
+        let (mut send_request, guard) = self
+            .h2_get_stream(&peer, task_conf, tcp_notes, task_notes)
+            .await?;
+
+        let uri = Uri::builder()
+            .authority(task_conf.upstream.to_string())
+            .build()
+            .map_err(|_| TcpConnectError::InternalServerError("failed to build CONNECT uri"))?;
+        let mut req = Request::builder()
+            .method(Method::CONNECT)
+            .version(Version::HTTP_2)
+            .uri(uri)
+            .body(())
+            .map_err(|_| TcpConnectError::InternalServerError("failed to build CONNECT request"))?;
+        self.h2_set_proxy_auth(&mut req, task_notes)?;
+
+        let (rsp_fut, send_stream) = send_request
+            .send_request(req, false)
+            .map_err(|e| TcpConnectError::NegotiationWriteFailed(h2_error_to_io(e)))?;
+        let rsp = rsp_fut
+            .await
+            .map_err(|e| TcpConnectError::NegotiationReadFailed(h2_error_to_io(e)))?;
+        check_response_status(rsp.status())?;
+        let recv_stream = rsp.into_body();
+
+        // TODO detect and set outgoing_addr and target_addr for supported remote proxies
+
+        let guard = Arc::new(guard);
+        let r = PooledTunnelReader::new(H2StreamReader::new(recv_stream), guard.clone());
+        let w = PooledTunnelWriter::new(H2StreamWriter::new(send_stream), guard);
+        Ok((r, w))
+    }
+
+    async fn h2_connect_udp_to(
+        &self,
+        task_conf: &TcpConnectTaskConf<'_>,
+        tcp_notes: &mut TcpConnectTaskNotes,
+        task_notes: &ServerTaskNotes,
+    ) -> Result<(SendStream<Bytes>, RecvStream, PooledTunnelGuard), TcpConnectError> {
+        let peer = self
+            .get_next_proxy(task_notes, task_conf.upstream.host())
+            .clone();
+        let (mut send_request, guard) = self
+            .h2_get_stream(&peer, task_conf, tcp_notes, task_notes)
+            .await?;
+        if !send_request.is_extended_connect_protocol_enabled() {
+            return Err(TcpConnectError::NegotiationRejected(
+                "extended CONNECT protocol is not enabled by remote proxy".to_string(),
+            ));
+        }
+
+        // see RFC 9298 Section 3
+        let target_host = match task_conf.upstream.host() {
+            Host::Ip(IpAddr::V6(ip6)) => ip6.to_string().replace(':', "%3A"),
+            host => host.to_string(),
+        };
+        let scheme = if self.tls_config.is_some() {
+            "https"
+        } else {
+            "http"
+        };
+        let uri = format!(
+            "{scheme}://{peer}/.well-known/masque/udp/{target_host}/{}/",
+            task_conf.upstream.port()
+        );
+        let mut req = Request::builder()
+            .method(Method::CONNECT)
+            .version(Version::HTTP_2)
+            .uri(uri)
+            .header("capsule-protocol", "?1")
+            .extension(Protocol::from_static("connect-udp"))
+            .body(())
+            .map_err(|_| {
+                TcpConnectError::InternalServerError("failed to build connect-udp request")
+            })?;
+        self.h2_set_proxy_auth(&mut req, task_notes)?;
+
+        let (rsp_fut, send_stream) = send_request
+            .send_request(req, false)
+            .map_err(|e| TcpConnectError::NegotiationWriteFailed(h2_error_to_io(e)))?;
+        let rsp = rsp_fut
+            .await
+            .map_err(|e| TcpConnectError::NegotiationReadFailed(h2_error_to_io(e)))?;
+        check_response_status(rsp.status())?;
+
+        Ok((send_stream, rsp.into_body(), guard))
+    }
+
+    pub(super) async fn timed_h2_connect_udp_to(
+        &self,
+        task_conf: &TcpConnectTaskConf<'_>,
+        tcp_notes: &mut TcpConnectTaskNotes,
+        task_notes: &ServerTaskNotes,
+    ) -> Result<(SendStream<Bytes>, RecvStream, PooledTunnelGuard), TcpConnectError> {
+        tokio::time::timeout(
+            self.config.peer_negotiation_timeout,
+            self.h2_connect_udp_to(task_conf, tcp_notes, task_notes),
+        )
+        .await
+        .map_err(|_| TcpConnectError::NegotiationPeerTimeout)?
+    }
+}
+
+impl PooledTunnelEscaper for ProxyH2Escaper {
+    type StreamReader = PooledTunnelReader<H2StreamReader>;
+    type StreamWriter = PooledTunnelWriter<H2StreamWriter>;
+
+    fn escape_logger(&self) -> &Logger {
+        &self.escape_logger
+    }
+
+    fn fetch_user_upstream_io_stats(
+        &self,
+        task_notes: &ServerTaskNotes,
+    ) -> Vec<Arc<UserUpstreamTrafficStats>> {
+        task_notes
+            .user_ctx()
+            .map(|ctx| ctx.fetch_upstream_traffic_stats(self.name(), self.stats.share_extra_tags()))
+            .unwrap_or_default()
+    }
+
+    async fn timed_tunnel_connect_to(
+        &self,
+        task_conf: &TcpConnectTaskConf<'_>,
+        tcp_notes: &mut TcpConnectTaskNotes,
+        task_notes: &ServerTaskNotes,
+    ) -> Result<(Self::StreamReader, Self::StreamWriter), TcpConnectError> {
+        tokio::time::timeout(
+            self.config.peer_negotiation_timeout,
+            self.h2_connect_to(task_conf, tcp_notes, task_notes),
+        )
+        .await
+        .map_err(|_| TcpConnectError::NegotiationPeerTimeout)?
+    }
+}
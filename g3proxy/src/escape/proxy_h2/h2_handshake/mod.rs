@@ -0,0 +1,230 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use bytes::Bytes;
+use h2::client::{Connection, SendRequest};
+use h2::Ping;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::time::Instant;
+
+use g3_io_ext::LimitedStream;
+use g3_openssl::{SslConnector, SslStream};
+use g3_types::net::{AlpnProtocol, OpensslClientConfig, UpstreamAddr};
+
+use super::{h2_error_to_io, ProxyH2Escaper};
+use crate::escape::pooled_tunnel::{PooledTunnel, PooledTunnelStats};
+use crate::log::escape::h2_connection::EscapeLogForH2Connection;
+use crate::log::escape::tls_handshake::{EscapeLogForTlsHandshake, TlsApplication};
+use crate::module::tcp_connect::{TcpConnectError, TcpConnectTaskConf, TcpConnectTaskNotes};
+use crate::serve::ServerTaskNotes;
+
+impl ProxyH2Escaper {
+    async fn tls_handshake_to_peer<S>(
+        &self,
+        tls_config: &OpensslClientConfig,
+        peer: &UpstreamAddr,
+        stream: S,
+        task_conf: &TcpConnectTaskConf<'_>,
+        tcp_notes: &TcpConnectTaskNotes,
+        task_notes: &ServerTaskNotes,
+    ) -> Result<SslStream<S>, TcpConnectError>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        let tls_name = self.config.tls_name.as_ref().unwrap_or_else(|| peer.host());
+        let ssl = tls_config
+            .build_ssl(tls_name, peer.port())
+            .map_err(TcpConnectError::InternalTlsClientError)?;
+        let connector = SslConnector::new(ssl, stream)
+            .map_err(|e| TcpConnectError::InternalTlsClientError(anyhow::Error::new(e)))?;
+
+        match tokio::time::timeout(tls_config.handshake_timeout, connector.connect()).await {
+            Ok(Ok(stream)) => {
+                if let Some(alpn) = stream.ssl().selected_alpn_protocol() {
+                    if AlpnProtocol::from_buf(alpn) != Some(AlpnProtocol::Http2) {
+                        return Err(TcpConnectError::NegotiationProtocolErr);
+                    }
+                }
+                Ok(stream)
+            }
+            Ok(Err(e)) => {
+                let e = anyhow::Error::new(e);
+                EscapeLogForTlsHandshake {
+                    upstream: task_conf.upstream,
+                    tcp_notes,
+                    task_id: &task_notes.id,
+                    tls_name,
+                    tls_peer: peer,
+                    tls_application: TlsApplication::HttpProxy,
+                }
+                .log(&self.escape_logger, &e);
+                Err(TcpConnectError::PeerTlsHandshakeFailed(e))
+            }
+            Err(_) => {
+                let e = anyhow!("peer tls handshake timed out");
+                EscapeLogForTlsHandshake {
+                    upstream: task_conf.upstream,
+                    tcp_notes,
+                    task_id: &task_notes.id,
+                    tls_name,
+                    tls_peer: peer,
+                    tls_application: TlsApplication::HttpProxy,
+                }
+                .log(&self.escape_logger, &e);
+                Err(TcpConnectError::PeerTlsHandshakeTimeout)
+            }
+        }
+    }
+
+    pub(super) async fn h2_new_connection(
+        &self,
+        peer: &UpstreamAddr,
+        task_conf: &TcpConnectTaskConf<'_>,
+        tcp_notes: &mut TcpConnectTaskNotes,
+        task_notes: &ServerTaskNotes,
+    ) -> Result<Arc<PooledTunnel<SendRequest<Bytes>>>, TcpConnectError> {
+        let stream = self
+            .tcp_connector()
+            .connect(peer, task_conf, tcp_notes, task_notes)
+            .await?;
+
+        let conn_stats = Arc::new(PooledTunnelStats::new(self.stats.clone()));
+        let limit_config = &self.config.general.tcp_sock_speed_limit;
+        let stream = LimitedStream::local_limited(
+            stream,
+            limit_config.shift_millis,
+            limit_config.max_south,
+            limit_config.max_north,
+            conn_stats.clone(),
+        );
+
+        if let Some(tls_config) = &self.tls_config {
+            let tls_stream = self
+                .tls_handshake_to_peer(tls_config, peer, stream, task_conf, tcp_notes, task_notes)
+                .await?;
+            self.h2_handshake(
+                tls_stream, peer, conn_stats, task_conf, tcp_notes, task_notes,
+            )
+            .await
+        } else {
+            self.h2_handshake(stream, peer, conn_stats, task_conf, tcp_notes, task_notes)
+                .await
+        }
+    }
+
+    async fn h2_handshake<S>(
+        &self,
+        stream: S,
+        peer: &UpstreamAddr,
+        conn_stats: Arc<PooledTunnelStats>,
+        task_conf: &TcpConnectTaskConf<'_>,
+        tcp_notes: &TcpConnectTaskNotes,
+        task_notes: &ServerTaskNotes,
+    ) -> Result<Arc<PooledTunnel<SendRequest<Bytes>>>, TcpConnectError>
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        let mut client_builder = h2::client::Builder::new();
+        client_builder
+            .enable_push(false)
+            .max_frame_size(self.config.h2_max_frame_size)
+            .initial_window_size(self.config.h2_stream_window_size)
+            .initial_connection_window_size(self.config.h2_connection_window_size);
+        let (send_request, mut h2_conn) = client_builder
+            .handshake::<_, Bytes>(stream)
+            .await
+            .map_err(|e| TcpConnectError::NegotiationWriteFailed(h2_error_to_io(e)))?;
+        let mut ping_pong = h2_conn
+            .ping_pong()
+            .ok_or(TcpConnectError::InternalServerError(
+                "failed to get h2 ping pong handle",
+            ))?;
+
+        let connection = Arc::new(PooledTunnel::new(send_request, tcp_notes, conn_stats));
+        self.spawn_h2_connection_driver(
+            h2_conn,
+            connection.clone(),
+            peer,
+            task_conf,
+            tcp_notes,
+            task_notes,
+        );
+
+        // the peer SETTINGS frame should have been received when we get the PONG
+        if let Err(e) = ping_pong.ping(Ping::opaque()).await {
+            connection.mark_closed();
+            return Err(TcpConnectError::NegotiationReadFailed(h2_error_to_io(e)));
+        }
+        Ok(connection)
+    }
+
+    fn spawn_h2_connection_driver<S>(
+        &self,
+        mut h2_conn: Connection<S, Bytes>,
+        connection: Arc<PooledTunnel<SendRequest<Bytes>>>,
+        peer: &UpstreamAddr,
+        task_conf: &TcpConnectTaskConf<'_>,
+        tcp_notes: &TcpConnectTaskNotes,
+        task_notes: &ServerTaskNotes,
+    ) where
+        S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        let idle_timeout = self.config.connection_idle_timeout;
+        let logger = self.escape_logger.clone();
+        let peer = peer.clone();
+        let upstream = task_conf.upstream.clone();
+        let tcp_notes = tcp_notes.clone();
+        let task_id = task_notes.id;
+
+        tokio::spawn(async move {
+            let mut idle_interval =
+                tokio::time::interval_at(Instant::now() + idle_timeout, idle_timeout);
+            let mut last_total_streams = connection.stats().total_streams();
+
+            let r = loop {
+                tokio::select! {
+                    r = &mut h2_conn => break r,
+                    _ = idle_interval.tick() => {
+                        let total_streams = connection.stats().total_streams();
+                        if total_streams == last_total_streams && connection.try_close_idle() {
+                            break Ok(());
+                        }
+                        last_total_streams = total_streams;
+                    }
+                }
+            };
+            connection.mark_closed();
+
+            if let Err(e) = r {
+                let stats = connection.stats();
+                EscapeLogForH2Connection {
+                    upstream: &upstream,
+                    tcp_notes: &tcp_notes,
+                    task_id: &task_id,
+                    h2_peer: &peer,
+                    alive_time: stats.alive_time(),
+                    total_streams: stats.total_streams(),
+                    in_bytes: stats.in_bytes(),
+                    out_bytes: stats.out_bytes(),
+                }
+                .log(&logger, &anyhow::Error::new(e));
+            }
+        });
+    }
+}
@@ -0,0 +1,377 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::BTreeSet;
+use std::io;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context};
+use async_trait::async_trait;
+use base64::prelude::*;
+use bytes::Bytes;
+use h2::client::SendRequest;
+use http::HeaderValue;
+use slog::Logger;
+
+use g3_daemon::stat::remote::ArcTcpConnectionTaskRemoteStats;
+use g3_types::collection::{SelectiveVec, SelectiveVecBuilder};
+use g3_types::metrics::MetricsName;
+use g3_types::net::{AlpnProtocol, Host, OpensslClientConfig, UpstreamAddr, WeightedUpstreamAddr};
+
+use super::pooled_tunnel::{PooledTunnelEscaper, PooledTunnelPool, PooledTunnelTcpConnector};
+use super::{
+    ArcEscaper, ArcEscaperInternalStats, ArcEscaperStats, Escaper, EscaperExt, EscaperInternal,
+};
+use crate::audit::AuditContext;
+use crate::config::escaper::proxy_h2::ProxyH2EscaperConfig;
+use crate::config::escaper::{AnyEscaperConfig, EscaperConfig};
+use crate::module::ftp_over_http::{
+    ArcFtpTaskRemoteControlStats, ArcFtpTaskRemoteTransferStats, BoxFtpConnectContext,
+    BoxFtpRemoteConnection, DirectFtpConnectContext,
+};
+use crate::module::http_forward::{
+    ArcHttpForwardTaskRemoteStats, BoxHttpForwardConnection, BoxHttpForwardContext,
+    DirectHttpForwardContext,
+};
+use crate::module::tcp_connect::{
+    TcpConnectError, TcpConnectResult, TcpConnectTaskConf, TcpConnectTaskNotes, TlsConnectTaskConf,
+};
+use crate::module::udp_connect::{
+    ArcUdpConnectTaskRemoteStats, UdpConnectResult, UdpConnectTaskConf, UdpConnectTaskNotes,
+};
+use crate::module::udp_relay::{
+    ArcUdpRelayTaskRemoteStats, UdpRelaySetupError, UdpRelaySetupResult, UdpRelayTaskConf,
+    UdpRelayTaskNotes,
+};
+use crate::resolve::ArcIntegratedResolverHandle;
+use crate::serve::ServerTaskNotes;
+
+mod stats;
+use stats::ProxyH2EscaperStats;
+
+mod h2_connect;
+mod h2_handshake;
+mod udp_connect;
+
+fn h2_error_to_io(e: h2::Error) -> io::Error {
+    if e.is_io() {
+        e.into_io().unwrap()
+    } else {
+        io::Error::other(e)
+    }
+}
+
+pub(super) struct ProxyH2Escaper {
+    config: Arc<ProxyH2EscaperConfig>,
+    stats: Arc<ProxyH2EscaperStats>,
+    proxy_nodes: SelectiveVec<WeightedUpstreamAddr>,
+    tls_config: Option<OpensslClientConfig>,
+    proxy_auth_value: Option<HeaderValue>,
+    pool: PooledTunnelPool<SendRequest<Bytes>>,
+    resolver_handle: Option<ArcIntegratedResolverHandle>,
+    escape_logger: Logger,
+}
+
+impl ProxyH2Escaper {
+    fn new_obj(
+        config: ProxyH2EscaperConfig,
+        stats: Arc<ProxyH2EscaperStats>,
+    ) -> anyhow::Result<ArcEscaper> {
+        let mut nodes_builder = SelectiveVecBuilder::new();
+        for node in &config.proxy_nodes {
+            nodes_builder.insert(node.clone());
+        }
+        let proxy_nodes = nodes_builder
+            .build()
+            .ok_or_else(|| anyhow!("no next proxy node set"))?;
+
+        let tls_config = if let Some(builder) = &config.tls_config {
+            let tls_config = builder
+                .build_with_alpn_protocols(Some(vec![AlpnProtocol::Http2]))
+                .context("failed to build tls config")?;
+            Some(tls_config)
+        } else {
+            None
+        };
+
+        let proxy_auth_value = if config.proxy_username.is_empty() {
+            None
+        } else {
+            let value = format!(
+                "Basic {}",
+                BASE64_STANDARD.encode(format!(
+                    "{}:{}",
+                    config.proxy_username.as_original(),
+                    config.proxy_password.as_original()
+                ))
+            );
+            let value = HeaderValue::from_str(&value)
+                .map_err(|e| anyhow!("invalid proxy authorization header value: {e}"))?;
+            Some(value)
+        };
+
+        let pool = PooledTunnelPool::new(
+            config.max_connections_per_peer,
+            config.max_streams_per_connection,
+        );
+
+        let escape_logger = config.get_escape_logger();
+
+        let resolver = config.resolver();
+        let resolver_handle = if resolver.is_empty() {
+            None
+        } else {
+            Some(crate::resolve::get_handle(resolver)?)
+        };
+
+        stats.set_extra_tags(config.extra_metrics_tags.clone());
+
+        let escaper = ProxyH2Escaper {
+            config: Arc::new(config),
+            stats,
+            proxy_nodes,
+            tls_config,
+            proxy_auth_value,
+            pool,
+            resolver_handle,
+            escape_logger,
+        };
+        Ok(Arc::new(escaper))
+    }
+
+    pub(super) fn prepare_initial(config: ProxyH2EscaperConfig) -> anyhow::Result<ArcEscaper> {
+        let stats = Arc::new(ProxyH2EscaperStats::new(config.name()));
+        ProxyH2Escaper::new_obj(config, stats)
+    }
+
+    fn prepare_reload(
+        config: AnyEscaperConfig,
+        stats: Arc<ProxyH2EscaperStats>,
+    ) -> anyhow::Result<ArcEscaper> {
+        if let AnyEscaperConfig::ProxyH2(config) = config {
+            ProxyH2Escaper::new_obj(*config, stats)
+        } else {
+            Err(anyhow!("invalid escaper config type"))
+        }
+    }
+
+    fn get_next_proxy(&self, task_notes: &ServerTaskNotes, target_host: &Host) -> &UpstreamAddr {
+        self.select_consistent(
+            &self.proxy_nodes,
+            self.config.proxy_pick_policy,
+            task_notes,
+            target_host,
+        )
+        .inner()
+    }
+
+    fn tcp_connector(&self) -> PooledTunnelTcpConnector<'_> {
+        PooledTunnelTcpConnector {
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            bind_interface: self.config.bind_interface,
+            bind_v4: self.config.bind_v4,
+            bind_v6: self.config.bind_v6,
+            no_ipv4: self.config.no_ipv4,
+            no_ipv6: self.config.no_ipv6,
+            tcp_connect: &self.config.general.tcp_connect,
+            happy_eyeballs: &self.config.happy_eyeballs,
+            tcp_keepalive: &self.config.tcp_keepalive,
+            tcp_misc_opts: &self.config.tcp_misc_opts,
+            resolve_strategy: self.config.resolve_strategy,
+            resolver_handle: self.resolver_handle.as_ref(),
+            stats: &self.stats.tcp,
+            escape_logger: &self.escape_logger,
+        }
+    }
+}
+
+impl EscaperExt for ProxyH2Escaper {}
+
+#[async_trait]
+impl Escaper for ProxyH2Escaper {
+    fn name(&self) -> &MetricsName {
+        self.config.name()
+    }
+
+    fn escaper_type(&self) -> &str {
+        self.config.escaper_type()
+    }
+
+    fn get_escape_stats(&self) -> Option<ArcEscaperStats> {
+        Some(self.stats.clone())
+    }
+
+    async fn publish(&self, _data: String) -> anyhow::Result<()> {
+        Err(anyhow!("not implemented"))
+    }
+
+    async fn tcp_setup_connection(
+        &self,
+        task_conf: &TcpConnectTaskConf<'_>,
+        tcp_notes: &mut TcpConnectTaskNotes,
+        task_notes: &ServerTaskNotes,
+        task_stats: ArcTcpConnectionTaskRemoteStats,
+        _audit_ctx: &mut AuditContext,
+    ) -> TcpConnectResult {
+        self.stats.interface.add_tcp_connect_attempted();
+        tcp_notes.escaper.clone_from(&self.config.name);
+        self.tunnel_new_tcp_connection(task_conf, tcp_notes, task_notes, task_stats)
+            .await
+    }
+
+    async fn tls_setup_connection(
+        &self,
+        task_conf: &TlsConnectTaskConf<'_>,
+        tcp_notes: &mut TcpConnectTaskNotes,
+        task_notes: &ServerTaskNotes,
+        task_stats: ArcTcpConnectionTaskRemoteStats,
+        _audit_ctx: &mut AuditContext,
+    ) -> TcpConnectResult {
+        self.stats.interface.add_tls_connect_attempted();
+        tcp_notes.escaper.clone_from(&self.config.name);
+        self.tunnel_new_tls_connection(task_conf, tcp_notes, task_notes, task_stats)
+            .await
+    }
+
+    async fn udp_setup_connection(
+        &self,
+        task_conf: &UdpConnectTaskConf<'_>,
+        udp_notes: &mut UdpConnectTaskNotes,
+        task_notes: &ServerTaskNotes,
+        task_stats: ArcUdpConnectTaskRemoteStats,
+    ) -> UdpConnectResult {
+        self.stats.interface.add_udp_connect_attempted();
+        udp_notes.escaper.clone_from(&self.config.name);
+        self.udp_connect_to(task_conf, udp_notes, task_notes, task_stats)
+            .await
+    }
+
+    async fn udp_setup_relay(
+        &self,
+        _task_conf: &UdpRelayTaskConf<'_>,
+        udp_notes: &mut UdpRelayTaskNotes,
+        _task_notes: &ServerTaskNotes,
+        _task_stats: ArcUdpRelayTaskRemoteStats,
+    ) -> UdpRelaySetupResult {
+        self.stats.interface.add_udp_relay_session_attempted();
+        udp_notes.escaper.clone_from(&self.config.name);
+        Err(UdpRelaySetupError::MethodUnavailable)
+    }
+
+    fn new_http_forward_context(&self, escaper: ArcEscaper) -> BoxHttpForwardContext {
+        let ctx = DirectHttpForwardContext::new(
+            Arc::clone(&self.stats) as ArcEscaperInternalStats,
+            escaper,
+        );
+        Box::new(ctx)
+    }
+
+    async fn new_ftp_connect_context(
+        &self,
+        escaper: ArcEscaper,
+        task_conf: &TcpConnectTaskConf<'_>,
+        _task_notes: &ServerTaskNotes,
+    ) -> BoxFtpConnectContext {
+        Box::new(DirectFtpConnectContext::new(
+            escaper,
+            task_conf.upstream.clone(),
+        ))
+    }
+}
+
+#[async_trait]
+impl EscaperInternal for ProxyH2Escaper {
+    fn _resolver(&self) -> &MetricsName {
+        self.config.resolver()
+    }
+
+    fn _dependent_escaper(&self) -> Option<BTreeSet<MetricsName>> {
+        None
+    }
+
+    fn _clone_config(&self) -> AnyEscaperConfig {
+        let config = &*self.config;
+        AnyEscaperConfig::ProxyH2(Box::new(config.clone()))
+    }
+
+    fn _update_config_in_place(
+        &self,
+        _flags: u64,
+        _config: AnyEscaperConfig,
+    ) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn _lock_safe_reload(&self, config: AnyEscaperConfig) -> anyhow::Result<ArcEscaper> {
+        let stats = Arc::clone(&self.stats);
+        ProxyH2Escaper::prepare_reload(config, stats)
+    }
+
+    async fn _new_http_forward_connection(
+        &self,
+        task_conf: &TcpConnectTaskConf<'_>,
+        tcp_notes: &mut TcpConnectTaskNotes,
+        task_notes: &ServerTaskNotes,
+        task_stats: ArcHttpForwardTaskRemoteStats,
+    ) -> Result<BoxHttpForwardConnection, TcpConnectError> {
+        self.stats.interface.add_http_forward_connection_attempted();
+        tcp_notes.escaper.clone_from(&self.config.name);
+        self.tunnel_http_forward_new_connection(task_conf, tcp_notes, task_notes, task_stats)
+            .await
+    }
+
+    async fn _new_https_forward_connection(
+        &self,
+        task_conf: &TlsConnectTaskConf<'_>,
+        tcp_notes: &mut TcpConnectTaskNotes,
+        task_notes: &ServerTaskNotes,
+        task_stats: ArcHttpForwardTaskRemoteStats,
+    ) -> Result<BoxHttpForwardConnection, TcpConnectError> {
+        self.stats
+            .interface
+            .add_https_forward_connection_attempted();
+        tcp_notes.escaper.clone_from(&self.config.name);
+        self.tunnel_https_forward_new_connection(task_conf, tcp_notes, task_notes, task_stats)
+            .await
+    }
+
+    async fn _new_ftp_control_connection(
+        &self,
+        _task_conf: &TcpConnectTaskConf<'_>,
+        tcp_notes: &mut TcpConnectTaskNotes,
+        _task_notes: &ServerTaskNotes,
+        _task_stats: ArcFtpTaskRemoteControlStats,
+    ) -> Result<BoxFtpRemoteConnection, TcpConnectError> {
+        self.stats.interface.add_ftp_over_http_request_attempted();
+        self.stats.interface.add_ftp_control_connection_attempted();
+        tcp_notes.escaper.clone_from(&self.config.name);
+        Err(TcpConnectError::MethodUnavailable)
+    }
+
+    async fn _new_ftp_transfer_connection(
+        &self,
+        _task_conf: &TcpConnectTaskConf<'_>,
+        transfer_tcp_notes: &mut TcpConnectTaskNotes,
+        _control_tcp_notes: &TcpConnectTaskNotes,
+        _task_notes: &ServerTaskNotes,
+        _task_stats: ArcFtpTaskRemoteTransferStats,
+        _ftp_server: &UpstreamAddr,
+    ) -> Result<BoxFtpRemoteConnection, TcpConnectError> {
+        self.stats.interface.add_ftp_transfer_connection_attempted();
+        transfer_tcp_notes.escaper.clone_from(&self.config.name);
+        Err(TcpConnectError::MethodUnavailable)
+    }
+}
@@ -0,0 +1,67 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::io;
+use std::sync::Arc;
+
+use super::{h2_error_to_io, ProxyH2Escaper};
+use crate::escape::pooled_tunnel::PooledTunnelEscaper;
+use crate::module::tcp_connect::{TcpConnectTaskConf, TcpConnectTaskNotes};
+use crate::module::udp_connect::{
+    ArcUdpConnectTaskRemoteStats, UdpConnectError, UdpConnectRemoteWrapperStats, UdpConnectResult,
+    UdpConnectTaskConf, UdpConnectTaskNotes,
+};
+use crate::serve::ServerTaskNotes;
+
+mod recv;
+mod send;
+
+use recv::ProxyH2UdpConnectRemoteRecv;
+use send::ProxyH2UdpConnectRemoteSend;
+
+impl ProxyH2Escaper {
+    pub(super) async fn udp_connect_to(
+        &self,
+        task_conf: &UdpConnectTaskConf<'_>,
+        udp_notes: &mut UdpConnectTaskNotes,
+        task_notes: &ServerTaskNotes,
+        task_stats: ArcUdpConnectTaskRemoteStats,
+    ) -> UdpConnectResult {
+        let tcp_task_conf = TcpConnectTaskConf {
+            upstream: task_conf.upstream,
+        };
+        let mut tcp_notes = TcpConnectTaskNotes::default();
+        let (send_stream, recv_stream, guard) = self
+            .timed_h2_connect_udp_to(&tcp_task_conf, &mut tcp_notes, task_notes)
+            .await
+            .map_err(|e| UdpConnectError::SetupSocketFailed(io::Error::other(e)))?;
+
+        udp_notes.bind = tcp_notes.bind;
+        udp_notes.local = tcp_notes.local;
+        udp_notes.next = tcp_notes.next;
+
+        let mut wrapper_stats = UdpConnectRemoteWrapperStats::new(&self.stats, task_stats);
+        wrapper_stats.push_user_io_stats(self.fetch_user_upstream_io_stats(task_notes));
+        let wrapper_stats = Arc::new(wrapper_stats);
+
+        let guard = Arc::new(guard);
+        let recv =
+            ProxyH2UdpConnectRemoteRecv::new(recv_stream, wrapper_stats.clone(), guard.clone());
+        let send = ProxyH2UdpConnectRemoteSend::new(send_stream, wrapper_stats, guard);
+
+        Ok((Box::new(recv), Box::new(send), self.escape_logger.clone()))
+    }
+}
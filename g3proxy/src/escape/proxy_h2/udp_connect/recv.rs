@@ -0,0 +1,167 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::io;
+use std::sync::Arc;
+use std::task::{ready, Context, Poll};
+
+use bytes::{Buf, BytesMut};
+use h2::RecvStream;
+
+use g3_http::capsule::{self, CapsuleHeader};
+use g3_io_ext::{LimitedRecvStats, UdpCopyRemoteError, UdpCopyRemoteRecv};
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    target_os = "macos",
+))]
+use g3_io_ext::{UdpCopyPacket, UdpCopyPacketMeta};
+
+use super::h2_error_to_io;
+use crate::escape::pooled_tunnel::PooledTunnelGuard;
+
+pub(super) struct ProxyH2UdpConnectRemoteRecv<S> {
+    recv_stream: RecvStream,
+    buf: BytesMut,
+    stats: Arc<S>,
+    _guard: Arc<PooledTunnelGuard>,
+}
+
+impl<S> ProxyH2UdpConnectRemoteRecv<S>
+where
+    S: LimitedRecvStats,
+{
+    pub(super) fn new(
+        recv_stream: RecvStream,
+        stats: Arc<S>,
+        guard: Arc<PooledTunnelGuard>,
+    ) -> Self {
+        ProxyH2UdpConnectRemoteRecv {
+            recv_stream,
+            buf: BytesMut::new(),
+            stats,
+            _guard: guard,
+        }
+    }
+
+    /// Copy the next UDP payload into `buf`, return the payload length.
+    fn take_datagram(&mut self, buf: &mut [u8]) -> Result<Option<usize>, UdpCopyRemoteError> {
+        loop {
+            let Some(header) = CapsuleHeader::try_parse(&self.buf) else {
+                return Ok(None);
+            };
+            let total_length = header.total_length();
+            if self.buf.len() < total_length {
+                return Ok(None);
+            }
+
+            let capsule = self.buf.split_to(total_length);
+            if header.capsule_type != capsule::CAPSULE_TYPE_DATAGRAM {
+                // unknown capsules should be skipped
+                continue;
+            }
+            let Some(payload) = capsule::udp_payload_in_datagram(&capsule[header.header_length..])
+            else {
+                continue;
+            };
+            if payload.len() > buf.len() {
+                return Err(UdpCopyRemoteError::InvalidPacket(format!(
+                    "too large udp payload size {}",
+                    payload.len()
+                )));
+            }
+            buf[..payload.len()].copy_from_slice(payload);
+            self.stats.add_recv_bytes(payload.len());
+            self.stats.add_recv_packet();
+            return Ok(Some(payload.len()));
+        }
+    }
+
+    fn poll_recv(
+        &mut self,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize, UdpCopyRemoteError>> {
+        loop {
+            if let Some(len) = self.take_datagram(buf)? {
+                return Poll::Ready(Ok(len));
+            }
+
+            match ready!(self.recv_stream.poll_data(cx)) {
+                Some(Ok(data)) => {
+                    let _ = self.recv_stream.flow_control().release_capacity(data.len());
+                    self.buf.extend_from_slice(data.chunk());
+                }
+                Some(Err(e)) => {
+                    return Poll::Ready(Err(UdpCopyRemoteError::RecvFailed(h2_error_to_io(e))))
+                }
+                None => return Poll::Ready(Err(UdpCopyRemoteError::RemoteSessionClosed)),
+            }
+        }
+    }
+}
+
+impl<S> UdpCopyRemoteRecv for ProxyH2UdpConnectRemoteRecv<S>
+where
+    S: LimitedRecvStats,
+{
+    fn max_hdr_len(&self) -> usize {
+        0
+    }
+
+    fn poll_recv_packet(
+        &mut self,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<(usize, usize), UdpCopyRemoteError>> {
+        let len = ready!(self.poll_recv(cx, buf))?;
+        Poll::Ready(Ok((0, len)))
+    }
+
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd",
+        target_os = "macos",
+    ))]
+    fn poll_recv_packets(
+        &mut self,
+        cx: &mut Context<'_>,
+        packets: &mut [UdpCopyPacket],
+    ) -> Poll<Result<usize, UdpCopyRemoteError>> {
+        let mut count = 0;
+        for p in packets.iter_mut() {
+            let r = if count == 0 {
+                ready!(self.poll_recv(cx, p.buf_mut()))?
+            } else {
+                // only return already received datagrams for the following packets
+                match self.take_datagram(p.buf_mut())? {
+                    Some(len) => len,
+                    None => break,
+                }
+            };
+            let iov = io::IoSliceMut::new(p.buf_mut());
+            UdpCopyPacketMeta::new(&iov, 0, r).set_packet(p);
+            count += 1;
+        }
+        Poll::Ready(Ok(count))
+    }
+}
@@ -0,0 +1,245 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::sync::Arc;
+
+use super::ProxyHttpEscaper;
+use crate::module::ftp_over_http::{
+    ArcFtpTaskRemoteControlStats, ArcFtpTaskRemoteTransferStats, BoxFtpRemoteConnection,
+    FtpControlRemoteWrapperStats, FtpTransferRemoteWrapperStats,
+};
+use crate::module::tcp_connect::{TcpConnectError, TcpConnectTaskConf, TcpConnectTaskNotes};
+use crate::serve::ServerTaskNotes;
+
+impl ProxyHttpEscaper {
+    pub(super) async fn http_connect_new_ftp_control_connection(
+        &self,
+        task_conf: &TcpConnectTaskConf<'_>,
+        tcp_notes: &mut TcpConnectTaskNotes,
+        task_notes: &ServerTaskNotes,
+        task_stats: ArcFtpTaskRemoteControlStats,
+    ) -> Result<BoxFtpRemoteConnection, TcpConnectError> {
+        let mut buf_stream = self
+            .timed_http_connect_tcp_connect_to(task_conf, tcp_notes, task_notes)
+            .await?;
+
+        // add in read buffered data, the ftp greeting may be received along with the connect response
+        let r_buffer_size = buf_stream.buffer().len() as u64;
+        task_stats.add_read_bytes(r_buffer_size);
+        let mut wrapper_stats = FtpControlRemoteWrapperStats::new(&self.stats, task_stats);
+        let user_stats = self.fetch_user_upstream_io_stats(task_notes);
+        for s in &user_stats {
+            s.io.tcp.add_in_bytes(r_buffer_size);
+        }
+        wrapper_stats.push_user_io_stats(user_stats);
+        let wrapper_stats = Arc::new(wrapper_stats);
+
+        // reset underlying io stats
+        buf_stream.get_mut().reset_stats(wrapper_stats);
+
+        Ok(Box::new(buf_stream))
+    }
+
+    pub(super) async fn http_connect_new_ftp_transfer_connection(
+        &self,
+        task_conf: &TcpConnectTaskConf<'_>,
+        tcp_notes: &mut TcpConnectTaskNotes,
+        task_notes: &ServerTaskNotes,
+        task_stats: ArcFtpTaskRemoteTransferStats,
+    ) -> Result<BoxFtpRemoteConnection, TcpConnectError> {
+        let mut buf_stream = self
+            .timed_http_connect_tcp_connect_to(task_conf, tcp_notes, task_notes)
+            .await?;
+
+        // add in read buffered data
+        let r_buffer_size = buf_stream.buffer().len() as u64;
+        task_stats.add_read_bytes(r_buffer_size);
+        let mut wrapper_stats = FtpTransferRemoteWrapperStats::new(&self.stats, task_stats);
+        let user_stats = self.fetch_user_upstream_io_stats(task_notes);
+        for s in &user_stats {
+            s.io.tcp.add_in_bytes(r_buffer_size);
+        }
+        wrapper_stats.push_user_io_stats(user_stats);
+        let wrapper_stats = Arc::new(wrapper_stats);
+
+        // reset underlying io stats
+        buf_stream.get_mut().reset_stats(wrapper_stats);
+
+        Ok(Box::new(buf_stream))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::SocketAddr;
+    use std::str::FromStr;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::time::Duration;
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+    use yaml_rust::YamlLoader;
+
+    use g3_daemon::server::ClientConnectionInfo;
+    use g3_types::net::UpstreamAddr;
+
+    use crate::config::escaper::proxy_http::ProxyHttpEscaperConfig;
+    use crate::escape::ArcEscaper;
+    use crate::module::ftp_over_http::{FtpTaskRemoteControlStats, FtpTaskRemoteTransferStats};
+
+    #[derive(Default)]
+    struct TaskStats {
+        read: AtomicU64,
+        write: AtomicU64,
+    }
+
+    impl FtpTaskRemoteControlStats for TaskStats {
+        fn add_read_bytes(&self, size: u64) {
+            self.read.fetch_add(size, Ordering::Relaxed);
+        }
+
+        fn add_write_bytes(&self, size: u64) {
+            self.write.fetch_add(size, Ordering::Relaxed);
+        }
+    }
+
+    impl FtpTaskRemoteTransferStats for TaskStats {
+        fn add_read_bytes(&self, size: u64) {
+            self.read.fetch_add(size, Ordering::Relaxed);
+        }
+
+        fn add_write_bytes(&self, size: u64) {
+            self.write.fetch_add(size, Ordering::Relaxed);
+        }
+    }
+
+    fn new_escaper(proxy_addr: SocketAddr) -> ArcEscaper {
+        let yaml = format!("name: test\ntype: proxy_http\nproxy_addr: {proxy_addr}\n");
+        let docs = YamlLoader::load_from_str(&yaml).unwrap();
+        let config = ProxyHttpEscaperConfig::parse(docs[0].as_hash().unwrap(), None).unwrap();
+        ProxyHttpEscaper::prepare_initial(config).unwrap()
+    }
+
+    /// accept a new connection and read the CONNECT request
+    async fn accept_connect(listener: &TcpListener) -> TcpStream {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut buf = Vec::new();
+        while !buf.ends_with(b"\r\n\r\n") {
+            buf.push(stream.read_u8().await.unwrap());
+        }
+        assert!(buf.starts_with(b"CONNECT ftp.example.net:21 HTTP/1.1\r\n"));
+        stream
+    }
+
+    #[tokio::test]
+    async fn control_connection() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy_addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let mut stream = accept_connect(&listener).await;
+            // send the greeting along with the connect response
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\n\r\n220 ready\r\n")
+                .await
+                .unwrap();
+            let mut buf = [0u8; 6];
+            stream.read_exact(&mut buf).await.unwrap();
+            assert_eq!(&buf, b"QUIT\r\n");
+        });
+
+        let escaper = new_escaper(proxy_addr);
+        let upstream = UpstreamAddr::from_str("ftp.example.net:21").unwrap();
+        let task_conf = TcpConnectTaskConf {
+            upstream: &upstream,
+        };
+        let mut tcp_notes = TcpConnectTaskNotes::default();
+        let task_notes = ServerTaskNotes::new(
+            ClientConnectionInfo::new(proxy_addr, proxy_addr),
+            None,
+            Duration::ZERO,
+        );
+        let task_stats = Arc::new(TaskStats::default());
+
+        let mut conn = escaper
+            ._new_ftp_control_connection(
+                &task_conf,
+                &mut tcp_notes,
+                &task_notes,
+                task_stats.clone(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(tcp_notes.escaper.as_str(), "test");
+
+        let mut buf = [0u8; 11];
+        conn.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"220 ready\r\n");
+        conn.write_all(b"QUIT\r\n").await.unwrap();
+        conn.flush().await.unwrap();
+        server.await.unwrap();
+
+        // the connect response should not be counted
+        assert_eq!(task_stats.read.load(Ordering::Relaxed), 11);
+        assert_eq!(task_stats.write.load(Ordering::Relaxed), 6);
+    }
+
+    #[tokio::test]
+    async fn transfer_connection() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy_addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let mut stream = accept_connect(&listener).await;
+            stream.write_all(b"HTTP/1.1 200 OK\r\n\r\n").await.unwrap();
+            stream.write_all(b"file data").await.unwrap();
+            stream.shutdown().await.unwrap();
+        });
+
+        let escaper = new_escaper(proxy_addr);
+        let upstream = UpstreamAddr::from_str("ftp.example.net:21").unwrap();
+        let task_conf = TcpConnectTaskConf {
+            upstream: &upstream,
+        };
+        let control_tcp_notes = TcpConnectTaskNotes::default();
+        let mut transfer_tcp_notes = TcpConnectTaskNotes::default();
+        let task_notes = ServerTaskNotes::new(
+            ClientConnectionInfo::new(proxy_addr, proxy_addr),
+            None,
+            Duration::ZERO,
+        );
+        let task_stats = Arc::new(TaskStats::default());
+
+        let mut conn = escaper
+            ._new_ftp_transfer_connection(
+                &task_conf,
+                &mut transfer_tcp_notes,
+                &control_tcp_notes,
+                &task_notes,
+                task_stats.clone(),
+                &upstream,
+            )
+            .await
+            .unwrap();
+
+        let mut data = Vec::new();
+        conn.read_to_end(&mut data).await.unwrap();
+        assert_eq!(data, b"file data");
+        server.await.unwrap();
+
+        assert_eq!(task_stats.read.load(Ordering::Relaxed), 9);
+        assert_eq!(task_stats.write.load(Ordering::Relaxed), 0);
+    }
+}
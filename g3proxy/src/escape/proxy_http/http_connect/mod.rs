@@ -71,7 +71,7 @@ impl ProxyHttpEscaper {
         Ok(buf_stream)
     }
 
-    async fn timed_http_connect_tcp_connect_to(
+    pub(super) async fn timed_http_connect_tcp_connect_to(
         &self,
         task_conf: &TcpConnectTaskConf<'_>,
         tcp_notes: &mut TcpConnectTaskNotes,
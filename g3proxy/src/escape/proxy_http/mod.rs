@@ -57,6 +57,7 @@ use crate::serve::ServerTaskNotes;
 mod stats;
 pub(crate) use stats::ProxyHttpEscaperStats;
 
+mod ftp_connect;
 mod http_connect;
 mod http_forward;
 mod tcp_connect;
@@ -302,28 +303,35 @@ impl EscaperInternal for ProxyHttpEscaper {
 
     async fn _new_ftp_control_connection(
         &self,
-        _task_conf: &TcpConnectTaskConf<'_>,
+        task_conf: &TcpConnectTaskConf<'_>,
         tcp_notes: &mut TcpConnectTaskNotes,
-        _task_notes: &ServerTaskNotes,
-        _task_stats: ArcFtpTaskRemoteControlStats,
+        task_notes: &ServerTaskNotes,
+        task_stats: ArcFtpTaskRemoteControlStats,
     ) -> Result<BoxFtpRemoteConnection, TcpConnectError> {
         self.stats.interface.add_ftp_over_http_request_attempted();
         self.stats.interface.add_ftp_control_connection_attempted();
         tcp_notes.escaper.clone_from(&self.config.name);
-        Err(TcpConnectError::MethodUnavailable)
+        self.http_connect_new_ftp_control_connection(task_conf, tcp_notes, task_notes, task_stats)
+            .await
     }
 
     async fn _new_ftp_transfer_connection(
         &self,
-        _task_conf: &TcpConnectTaskConf<'_>,
+        task_conf: &TcpConnectTaskConf<'_>,
         transfer_tcp_notes: &mut TcpConnectTaskNotes,
         _control_tcp_notes: &TcpConnectTaskNotes,
-        _task_notes: &ServerTaskNotes,
-        _task_stats: ArcFtpTaskRemoteTransferStats,
+        task_notes: &ServerTaskNotes,
+        task_stats: ArcFtpTaskRemoteTransferStats,
         _ftp_server: &UpstreamAddr,
     ) -> Result<BoxFtpRemoteConnection, TcpConnectError> {
         self.stats.interface.add_ftp_transfer_connection_attempted();
         transfer_tcp_notes.escaper.clone_from(&self.config.name);
-        Err(TcpConnectError::MethodUnavailable)
+        self.http_connect_new_ftp_transfer_connection(
+            task_conf,
+            transfer_tcp_notes,
+            task_notes,
+            task_stats,
+        )
+        .await
     }
 }
@@ -24,6 +24,7 @@ use g3_types::metrics::{MetricsName, StaticMetricsTags};
 use g3_types::stats::{StatId, TcpIoSnapshot};
 
 use crate::escape::{EscaperInterfaceStats, EscaperInternalStats, EscaperStats, EscaperTcpStats};
+use crate::module::ftp_over_http::{FtpTaskRemoteControlStats, FtpTaskRemoteTransferStats};
 use crate::module::http_forward::HttpForwardTaskRemoteStats;
 
 pub(crate) struct ProxyHttpEscaperStats {
@@ -129,3 +130,23 @@ impl HttpForwardTaskRemoteStats for ProxyHttpEscaperStats {
         self.tcp.io.add_out_bytes(size);
     }
 }
+
+impl FtpTaskRemoteControlStats for ProxyHttpEscaperStats {
+    fn add_read_bytes(&self, size: u64) {
+        self.tcp.io.add_in_bytes(size);
+    }
+
+    fn add_write_bytes(&self, size: u64) {
+        self.tcp.io.add_out_bytes(size);
+    }
+}
+
+impl FtpTaskRemoteTransferStats for ProxyHttpEscaperStats {
+    fn add_read_bytes(&self, size: u64) {
+        self.tcp.io.add_in_bytes(size);
+    }
+
+    fn add_write_bytes(&self, size: u64) {
+        self.tcp.io.add_out_bytes(size);
+    }
+}
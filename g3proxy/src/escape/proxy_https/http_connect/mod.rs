@@ -18,12 +18,15 @@ use std::sync::Arc;
 
 use anyhow::anyhow;
 use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpStream;
 
 use g3_daemon::stat::remote::{
     ArcTcpConnectionTaskRemoteStats, TcpConnectionTaskRemoteStatsWrapper,
 };
 use g3_http::connect::{HttpConnectRequest, HttpConnectResponse};
-use g3_io_ext::{AsyncStream, FlexBufReader, LimitedReader, LimitedWriter, OnceBufReader};
+use g3_io_ext::{
+    AsyncStream, FlexBufReader, LimitedReader, LimitedStream, LimitedWriter, OnceBufReader,
+};
 use g3_openssl::{SslConnector, SslStream};
 
 use super::ProxyHttpsEscaper;
@@ -39,7 +42,7 @@ impl ProxyHttpsEscaper {
         task_conf: &TcpConnectTaskConf<'_>,
         tcp_notes: &mut TcpConnectTaskNotes,
         task_notes: &ServerTaskNotes,
-    ) -> Result<FlexBufReader<SslStream<impl AsyncRead + AsyncWrite>>, TcpConnectError> {
+    ) -> Result<FlexBufReader<SslStream<LimitedStream<TcpStream>>>, TcpConnectError> {
         let mut stream = self
             .tls_handshake_to_remote(task_conf, tcp_notes, task_notes)
             .await?;
@@ -67,12 +70,12 @@ impl ProxyHttpsEscaper {
         Ok(buf_stream)
     }
 
-    async fn timed_http_connect_tcp_connect_to(
+    pub(super) async fn timed_http_connect_tcp_connect_to(
         &self,
         task_conf: &TcpConnectTaskConf<'_>,
         tcp_notes: &mut TcpConnectTaskNotes,
         task_notes: &ServerTaskNotes,
-    ) -> Result<FlexBufReader<SslStream<impl AsyncRead + AsyncWrite>>, TcpConnectError> {
+    ) -> Result<FlexBufReader<SslStream<LimitedStream<TcpStream>>>, TcpConnectError> {
         tokio::time::timeout(
             self.config.peer_negotiation_timeout,
             self.http_connect_tcp_connect_to(task_conf, tcp_notes, task_notes),
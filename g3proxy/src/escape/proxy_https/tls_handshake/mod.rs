@@ -15,8 +15,9 @@
  */
 
 use anyhow::anyhow;
-use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpStream;
 
+use g3_io_ext::LimitedStream;
 use g3_openssl::{SslConnector, SslStream};
 
 use super::ProxyHttpsEscaper;
@@ -30,7 +31,7 @@ impl ProxyHttpsEscaper {
         task_conf: &TcpConnectTaskConf<'_>,
         tcp_notes: &mut TcpConnectTaskNotes,
         task_notes: &ServerTaskNotes,
-    ) -> Result<SslStream<impl AsyncRead + AsyncWrite>, TcpConnectError> {
+    ) -> Result<SslStream<LimitedStream<TcpStream>>, TcpConnectError> {
         let (peer, ups_s) = self
             .tcp_new_connection(task_conf, tcp_notes, task_notes)
             .await?;
@@ -26,6 +26,7 @@ use g3_types::stats::{StatId, TcpIoSnapshot, UdpIoSnapshot};
 use crate::escape::{
     EscaperInterfaceStats, EscaperInternalStats, EscaperStats, EscaperTcpStats, EscaperUdpStats,
 };
+use crate::module::ftp_over_http::{FtpTaskRemoteControlStats, FtpTaskRemoteTransferStats};
 use crate::module::http_forward::HttpForwardTaskRemoteStats;
 use crate::module::udp_connect::UdpConnectTaskRemoteStats;
 use crate::module::udp_relay::UdpRelayTaskRemoteStats;
@@ -140,6 +141,26 @@ impl HttpForwardTaskRemoteStats for ProxySocks5EscaperStats {
     }
 }
 
+impl FtpTaskRemoteControlStats for ProxySocks5EscaperStats {
+    fn add_read_bytes(&self, size: u64) {
+        self.tcp.io.add_in_bytes(size);
+    }
+
+    fn add_write_bytes(&self, size: u64) {
+        self.tcp.io.add_out_bytes(size);
+    }
+}
+
+impl FtpTaskRemoteTransferStats for ProxySocks5EscaperStats {
+    fn add_read_bytes(&self, size: u64) {
+        self.tcp.io.add_in_bytes(size);
+    }
+
+    fn add_write_bytes(&self, size: u64) {
+        self.tcp.io.add_out_bytes(size);
+    }
+}
+
 impl UdpRelayTaskRemoteStats for ProxySocks5EscaperStats {
     fn add_recv_bytes(&self, size: u64) {
         self.udp.io.add_in_bytes(size);
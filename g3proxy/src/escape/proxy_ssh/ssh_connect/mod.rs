@@ -0,0 +1,141 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use slog::Logger;
+
+use g3_ssh_client::{SshChannelReader, SshChannelWriter, SshSession};
+use g3_types::net::UpstreamAddr;
+
+use super::ProxySshEscaper;
+use crate::auth::UserUpstreamTrafficStats;
+use crate::escape::pooled_tunnel::{
+    PooledTunnelEscaper, PooledTunnelFetch, PooledTunnelGuard, PooledTunnelHandle,
+    PooledTunnelReader, PooledTunnelWriter,
+};
+use crate::escape::{Escaper, EscaperStats};
+use crate::module::tcp_connect::{TcpConnectError, TcpConnectTaskConf, TcpConnectTaskNotes};
+use crate::serve::ServerTaskNotes;
+
+impl PooledTunnelHandle for SshSession {
+    fn is_closed(&self) -> bool {
+        SshSession::is_closed(self)
+    }
+}
+
+impl ProxySshEscaper {
+    /// Get a usable ssh session from the pool, a new session will be created if needed.
+    async fn ssh_get_session(
+        &self,
+        peer: &UpstreamAddr,
+        task_conf: &TcpConnectTaskConf<'_>,
+        tcp_notes: &mut TcpConnectTaskNotes,
+        task_notes: &ServerTaskNotes,
+    ) -> Result<(SshSession, PooledTunnelGuard), TcpConnectError> {
+        let permit = match self.pool.fetch(peer) {
+            PooledTunnelFetch::Reuse(session, guard) => {
+                session.fill_tcp_notes(tcp_notes);
+                return Ok((session.handle().clone(), guard));
+            }
+            PooledTunnelFetch::New(permit) => permit,
+            PooledTunnelFetch::Full => {
+                return Err(TcpConnectError::EscaperNotUsable(anyhow!(
+                    "all ssh sessions to {peer} have reached the max channels limit"
+                )));
+            }
+        };
+
+        let session = self
+            .ssh_new_session(peer, task_conf, tcp_notes, task_notes)
+            .await?;
+        let guard = session
+            .try_acquire()
+            .ok_or(TcpConnectError::InternalServerError(
+                "the new ssh session closed unexpectedly",
+            ))?;
+        permit.add(session.clone());
+        Ok((session.handle().clone(), guard))
+    }
+
+    async fn ssh_connect_to(
+        &self,
+        task_conf: &TcpConnectTaskConf<'_>,
+        tcp_notes: &mut TcpConnectTaskNotes,
+        task_notes: &ServerTaskNotes,
+    ) -> Result<
+        (
+            PooledTunnelReader<SshChannelReader>,
+            PooledTunnelWriter<SshChannelWriter>,
+        ),
+        TcpConnectError,
+    > {
+        let peer = self
+            .get_next_proxy(task_notes, task_conf.upstream.host())
+            .clone();
+        let (session, guard) = self
+            .ssh_get_session(&peer, task_conf, tcp_notes, task_notes)
+            .await?;
+
+        let target_host = task_conf.upstream.host().to_string();
+        let (r, w) = session
+            .open_direct_tcpip(
+                &target_host,
+                task_conf.upstream.port(),
+                task_notes.client_addr(),
+            )
+            .await?;
+
+        let guard = Arc::new(guard);
+        let r = PooledTunnelReader::new(r, guard.clone());
+        let w = PooledTunnelWriter::new(w, guard);
+        Ok((r, w))
+    }
+}
+
+impl PooledTunnelEscaper for ProxySshEscaper {
+    type StreamReader = PooledTunnelReader<SshChannelReader>;
+    type StreamWriter = PooledTunnelWriter<SshChannelWriter>;
+
+    fn escape_logger(&self) -> &Logger {
+        &self.escape_logger
+    }
+
+    fn fetch_user_upstream_io_stats(
+        &self,
+        task_notes: &ServerTaskNotes,
+    ) -> Vec<Arc<UserUpstreamTrafficStats>> {
+        task_notes
+            .user_ctx()
+            .map(|ctx| ctx.fetch_upstream_traffic_stats(self.name(), self.stats.share_extra_tags()))
+            .unwrap_or_default()
+    }
+
+    async fn timed_tunnel_connect_to(
+        &self,
+        task_conf: &TcpConnectTaskConf<'_>,
+        tcp_notes: &mut TcpConnectTaskNotes,
+        task_notes: &ServerTaskNotes,
+    ) -> Result<(Self::StreamReader, Self::StreamWriter), TcpConnectError> {
+        tokio::time::timeout(
+            self.config.peer_negotiation_timeout,
+            self.ssh_connect_to(task_conf, tcp_notes, task_notes),
+        )
+        .await
+        .map_err(|_| TcpConnectError::NegotiationPeerTimeout)?
+    }
+}
@@ -0,0 +1,142 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use tokio::time::Instant;
+
+use g3_io_ext::LimitedStream;
+use g3_ssh_client::{SshConnection, SshSession};
+use g3_types::net::UpstreamAddr;
+
+use super::ProxySshEscaper;
+use crate::escape::pooled_tunnel::{PooledTunnel, PooledTunnelStats};
+use crate::log::escape::ssh_session::EscapeLogForSshSession;
+use crate::module::tcp_connect::{TcpConnectError, TcpConnectTaskConf, TcpConnectTaskNotes};
+use crate::serve::ServerTaskNotes;
+
+impl ProxySshEscaper {
+    pub(super) async fn ssh_new_session(
+        &self,
+        peer: &UpstreamAddr,
+        task_conf: &TcpConnectTaskConf<'_>,
+        tcp_notes: &mut TcpConnectTaskNotes,
+        task_notes: &ServerTaskNotes,
+    ) -> Result<Arc<PooledTunnel<SshSession>>, TcpConnectError> {
+        let stream = self
+            .tcp_connector()
+            .connect(peer, task_conf, tcp_notes, task_notes)
+            .await?;
+
+        let session_stats = Arc::new(PooledTunnelStats::new(self.stats.clone()));
+        let limit_config = &self.config.general.tcp_sock_speed_limit;
+        let stream = LimitedStream::local_limited(
+            stream,
+            limit_config.shift_millis,
+            limit_config.max_south,
+            limit_config.max_north,
+            session_stats.clone(),
+        );
+
+        let host = peer.host().to_string();
+        match g3_ssh_client::handshake(stream, &host, peer.port(), &self.ssh_config).await {
+            Ok((ssh_session, ssh_conn)) => {
+                let session = Arc::new(PooledTunnel::new(ssh_session, tcp_notes, session_stats));
+                self.spawn_ssh_session_driver(
+                    ssh_conn,
+                    session.clone(),
+                    peer,
+                    task_conf,
+                    tcp_notes,
+                    task_notes,
+                );
+                Ok(session)
+            }
+            Err(e) => {
+                EscapeLogForSshSession {
+                    upstream: task_conf.upstream,
+                    tcp_notes,
+                    task_id: &task_notes.id,
+                    ssh_peer: peer,
+                    alive_time: session_stats.alive_time(),
+                    total_channels: 0,
+                    in_bytes: session_stats.in_bytes(),
+                    out_bytes: session_stats.out_bytes(),
+                }
+                .log(&self.escape_logger, &anyhow!("ssh handshake failed: {e}"));
+                Err(e.into())
+            }
+        }
+    }
+
+    fn spawn_ssh_session_driver(
+        &self,
+        ssh_conn: SshConnection,
+        session: Arc<PooledTunnel<SshSession>>,
+        peer: &UpstreamAddr,
+        task_conf: &TcpConnectTaskConf<'_>,
+        tcp_notes: &TcpConnectTaskNotes,
+        task_notes: &ServerTaskNotes,
+    ) {
+        let idle_timeout = self.config.session_idle_timeout;
+        let logger = self.escape_logger.clone();
+        let peer = peer.clone();
+        let upstream = task_conf.upstream.clone();
+        let tcp_notes = tcp_notes.clone();
+        let task_id = task_notes.id;
+
+        tokio::spawn(async move {
+            let mut idle_interval =
+                tokio::time::interval_at(Instant::now() + idle_timeout, idle_timeout);
+            let mut last_total_channels = session.stats().total_streams();
+            let mut idle_closed = false;
+
+            let run = ssh_conn.run();
+            tokio::pin!(run);
+            let r = loop {
+                tokio::select! {
+                    r = &mut run => break r,
+                    _ = idle_interval.tick(), if !idle_closed => {
+                        let total_channels = session.stats().total_streams();
+                        if total_channels == last_total_channels && session.try_close_idle() {
+                            // wait for the disconnect message to be sent out
+                            session.handle().disconnect().await;
+                            idle_closed = true;
+                        }
+                        last_total_channels = total_channels;
+                    }
+                }
+            };
+            session.mark_closed();
+
+            if let Err(e) = r {
+                let stats = session.stats();
+                EscapeLogForSshSession {
+                    upstream: &upstream,
+                    tcp_notes: &tcp_notes,
+                    task_id: &task_id,
+                    ssh_peer: &peer,
+                    alive_time: stats.alive_time(),
+                    total_channels: stats.total_streams(),
+                    in_bytes: stats.in_bytes(),
+                    out_bytes: stats.out_bytes(),
+                }
+                .log(&logger, &anyhow::Error::new(e));
+            }
+        });
+    }
+}
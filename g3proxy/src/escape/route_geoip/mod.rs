@@ -34,7 +34,7 @@ use g3_types::net::{Host, UpstreamAddr};
 
 use super::{ArcEscaper, Escaper, EscaperInternal, RouteEscaperStats};
 use crate::audit::AuditContext;
-use crate::config::escaper::route_geoip::RouteGeoIpEscaperConfig;
+use crate::config::escaper::route_geoip::{GeoAreaName, RouteGeoIpEscaperConfig};
 use crate::config::escaper::{AnyEscaperConfig, EscaperConfig};
 use crate::module::ftp_over_http::{
     ArcFtpTaskRemoteControlStats, ArcFtpTaskRemoteTransferStats, BoxFtpConnectContext,
@@ -68,6 +68,9 @@ pub(super) struct RouteGeoIpEscaper {
     country_table: FnvHashMap<u16, ArcEscaper>,
     continent_bitset: FixedBitSet,
     continent_table: FnvHashMap<u8, ArcEscaper>,
+    region_table: FnvHashMap<u16, Vec<(String, ArcEscaper)>>,
+    city_table: FnvHashMap<u16, Vec<(String, ArcEscaper)>>,
+    hosting_next: Option<ArcEscaper>,
     default_next: ArcEscaper,
     check_ip_location: bool,
 }
@@ -126,9 +129,17 @@ impl RouteGeoIpEscaper {
             }
         }
 
-        let check_asn_db = !asn_table.is_empty();
-        let check_country_db = !(country_bitset.is_empty() && country_bitset.is_empty());
-        let check_ip_location = check_asn_db || check_country_db;
+        let region_table = build_area_table(&config.region_rules, &next_table);
+        let city_table = build_area_table(&config.city_rules, &next_table);
+        let hosting_next = config
+            .hosting_next
+            .as_ref()
+            .map(|escaper| Arc::clone(next_table.get(escaper).unwrap()));
+
+        let check_asn_db = !asn_table.is_empty() || hosting_next.is_some();
+        let check_country_db = !(country_bitset.is_empty() && continent_bitset.is_empty());
+        let check_city_db = !(region_table.is_empty() && city_table.is_empty());
+        let check_ip_location = check_asn_db || check_country_db || check_city_db;
         let escaper = RouteGeoIpEscaper {
             config,
             stats,
@@ -141,6 +152,9 @@ impl RouteGeoIpEscaper {
             country_table,
             continent_bitset,
             continent_table,
+            region_table,
+            city_table,
+            hosting_next,
             default_next,
             check_ip_location,
         };
@@ -195,7 +209,25 @@ impl RouteGeoIpEscaper {
             }
         }
 
+        if let Some(escaper) = &self.hosting_next {
+            if location.is_hosting() {
+                return Some(Arc::clone(escaper));
+            }
+        }
+
         if let Some(country) = location.country() {
+            if let Some(city) = location.city() {
+                if let Some(escaper) = match_area_table(&self.city_table, country as u16, city) {
+                    return Some(escaper);
+                }
+            }
+            if let Some(region) = location.region() {
+                if let Some(escaper) = match_area_table(&self.region_table, country as u16, region)
+                {
+                    return Some(escaper);
+                }
+            }
+
             if self.country_bitset.contains(country as usize) {
                 if let Some(escaper) = self.country_table.get(&(country as u16)) {
                     return Some(Arc::clone(escaper));
@@ -240,6 +272,35 @@ impl RouteGeoIpEscaper {
     }
 }
 
+fn build_area_table(
+    rules: &BTreeMap<MetricsName, BTreeSet<GeoAreaName>>,
+    next_table: &BTreeMap<MetricsName, ArcEscaper>,
+) -> FnvHashMap<u16, Vec<(String, ArcEscaper)>> {
+    let mut table: FnvHashMap<u16, Vec<(String, ArcEscaper)>> = FnvHashMap::default();
+    for (escaper, areas) in rules {
+        let next = next_table.get(escaper).unwrap();
+        for area in areas {
+            table
+                .entry(area.country as u16)
+                .or_default()
+                .push((area.name.clone(), Arc::clone(next)));
+        }
+    }
+    table
+}
+
+fn match_area_table(
+    table: &FnvHashMap<u16, Vec<(String, ArcEscaper)>>,
+    country: u16,
+    name: &str,
+) -> Option<ArcEscaper> {
+    let areas = table.get(&country)?;
+    areas
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|(_, escaper)| Arc::clone(escaper))
+}
+
 #[async_trait]
 impl Escaper for RouteGeoIpEscaper {
     fn name(&self) -> &MetricsName {
@@ -26,6 +26,8 @@ use g3_icap_client::reqmod::h2::H2ReqmodAdaptationError;
 use g3_icap_client::respmod::h2::H2RespmodAdaptationError;
 use g3_io_ext::IdleForceQuitReason;
 
+use super::forward::GrpcMessageAuditError;
+
 #[derive(Debug, Error)]
 pub(crate) enum H2InterceptionError {
     #[error("upstream io error during handshake: {0:?}")]
@@ -114,6 +116,10 @@ pub(crate) enum H2StreamTransferError {
     HttpUpstreamWriteIdle,
     #[error("idle after {0:?} x {1}")]
     Idle(Duration, i32),
+    #[error("grpc call forbidden by method acl")]
+    GrpcCallForbidden,
+    #[error("grpc message audit: {0}")]
+    GrpcMessageAuditFailed(GrpcMessageAuditError),
 }
 
 impl H2StreamTransferError {
@@ -0,0 +1,582 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::future::poll_fn;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use h2::{Reason, RecvStream, SendStream};
+use http::{HeaderMap, HeaderValue, Response, StatusCode, Version};
+use thiserror::Error;
+
+use g3_dpi::parser::grpc::{is_grpc_content_type, GrpcCallPath, GrpcMessageParser};
+use g3_dpi::GrpcInterceptionConfig;
+use g3_h2::{H2BodyInspector, H2StreamBodyTransferError};
+use g3_icap_client::reqmod::grpc::{
+    GrpcAdaptationError, GrpcMessageAuditResult, GrpcMessageAuditor, GrpcMessageDirection,
+};
+
+const GRPC_STATUS_PERMISSION_DENIED: u32 = 7;
+const GRPC_MESSAGE_HEADER_LEN: usize = 5;
+
+#[derive(Debug, Error)]
+pub(crate) enum GrpcMessageAuditError {
+    #[error("malformed message prefix")]
+    MalformedMessage,
+    #[error("message too large: {0}")]
+    MessageTooLarge(usize),
+    #[error("audit of {0} message {1} failed: {2}")]
+    AuditFailed(&'static str, usize, GrpcAdaptationError),
+    #[error("audit of {0} message {1} timeout")]
+    AuditTimeout(&'static str, usize),
+    #[error("{0} message {1} rejected with status {2}")]
+    Rejected(&'static str, usize, u16),
+}
+
+#[derive(Debug, Error)]
+pub(crate) enum GrpcMessageTransferError {
+    #[error("{0}")]
+    Transfer(#[from] H2StreamBodyTransferError),
+    #[error("{0}")]
+    Audit(#[from] GrpcMessageAuditError),
+}
+
+#[derive(Default)]
+pub(super) struct GrpcStreamInspector {
+    parser: GrpcMessageParser,
+    status: Option<u32>,
+}
+
+impl GrpcStreamInspector {
+    pub(super) fn inspect_headers(&mut self, headers: &HeaderMap) {
+        if let Some(status) = headers
+            .get("grpc-status")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<u32>().ok())
+        {
+            self.status = Some(status);
+        }
+    }
+
+    #[inline]
+    pub(super) fn message_count(&self) -> u64 {
+        self.parser.message_count()
+    }
+
+    #[inline]
+    pub(super) fn message_bytes(&self) -> u64 {
+        self.parser.message_bytes()
+    }
+
+    #[inline]
+    pub(super) fn status(&self) -> Option<u32> {
+        self.status
+    }
+}
+
+impl H2BodyInspector for GrpcStreamInspector {
+    fn inspect_data(&mut self, data: &[u8]) {
+        self.parser.feed(data);
+    }
+
+    fn inspect_trailers(&mut self, trailers: &HeaderMap) {
+        self.inspect_headers(trailers);
+    }
+}
+
+pub(super) struct GrpcCallNotes {
+    pub(super) service: String,
+    pub(super) method: String,
+    pub(super) req: GrpcStreamInspector,
+    pub(super) rsp: GrpcStreamInspector,
+    pub(super) icap_forward: bool,
+}
+
+impl GrpcCallNotes {
+    pub(super) fn detect(
+        path: &str,
+        headers: &HeaderMap,
+        config: &GrpcInterceptionConfig,
+        icap_forward: bool,
+    ) -> Option<Self> {
+        let content_type = headers.get(http::header::CONTENT_TYPE)?.to_str().ok()?;
+        if !is_grpc_content_type(content_type) {
+            return None;
+        }
+        let call_path = GrpcCallPath::parse(path)?;
+        Some(GrpcCallNotes {
+            service: call_path.service.to_string(),
+            method: call_path.method.to_string(),
+            req: GrpcStreamInspector::default(),
+            rsp: GrpcStreamInspector::default(),
+            icap_forward: icap_forward && config.icap_forward_messages,
+        })
+    }
+
+    pub(super) fn check_forbidden(&self, config: &GrpcInterceptionConfig) -> bool {
+        let Some(acl) = &config.method_acl else {
+            return false;
+        };
+        let (_, action) = acl.check(&self.service, &self.method);
+        action.forbid_early()
+    }
+
+    pub(super) fn build_forbidden_response(&mut self) -> Option<Response<()>> {
+        self.rsp.status = Some(GRPC_STATUS_PERMISSION_DENIED);
+        Response::builder()
+            .status(StatusCode::OK)
+            .version(Version::HTTP_2)
+            .header(
+                http::header::CONTENT_TYPE,
+                HeaderValue::from_static("application/grpc"),
+            )
+            .header(
+                "grpc-status",
+                HeaderValue::from(GRPC_STATUS_PERMISSION_DENIED),
+            )
+            .header(
+                "grpc-message",
+                HeaderValue::from_static("forbidden by proxy policy"),
+            )
+            .body(())
+            .ok()
+    }
+}
+
+fn build_forbidden_trailers() -> HeaderMap {
+    let mut trailers = HeaderMap::new();
+    trailers.insert(
+        "grpc-status",
+        HeaderValue::from(GRPC_STATUS_PERMISSION_DENIED),
+    );
+    trailers.insert(
+        "grpc-message",
+        HeaderValue::from_static("forbidden by proxy policy"),
+    );
+    trailers
+}
+
+/// Split Length-Prefixed-Messages out of the gRPC body
+#[derive(Default)]
+struct GrpcMessageFramer {
+    buf: BytesMut,
+}
+
+impl GrpcMessageFramer {
+    fn feed(&mut self, data: &[u8]) {
+        self.buf.extend_from_slice(data);
+    }
+
+    fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    fn take_all(&mut self) -> Bytes {
+        self.buf.split().freeze()
+    }
+
+    /// Get the next complete message, with the compressed flag and the message payload
+    fn next_message(
+        &mut self,
+        max_size: usize,
+    ) -> Result<Option<(bool, Bytes)>, GrpcMessageAuditError> {
+        if self.buf.len() < GRPC_MESSAGE_HEADER_LEN {
+            return Ok(None);
+        }
+        let compressed = match self.buf[0] {
+            0 => false,
+            1 => true,
+            _ => return Err(GrpcMessageAuditError::MalformedMessage),
+        };
+        let size =
+            u32::from_be_bytes([self.buf[1], self.buf[2], self.buf[3], self.buf[4]]) as usize;
+        if size > max_size {
+            return Err(GrpcMessageAuditError::MessageTooLarge(size));
+        }
+        if self.buf.len() < GRPC_MESSAGE_HEADER_LEN + size {
+            return Ok(None);
+        }
+        self.buf.advance(GRPC_MESSAGE_HEADER_LEN);
+        let data = self.buf.split_to(size).freeze();
+        Ok(Some((compressed, data)))
+    }
+}
+
+enum GrpcNextMessage {
+    /// a complete message which should be audited
+    Audit(bool, Bytes),
+    /// more data is needed for the next message
+    Pending,
+    /// no more audit, the remaining data should be forwarded directly
+    Forward,
+}
+
+fn encode_message(compressed: bool, data: &[u8]) -> Bytes {
+    let mut buf = BytesMut::with_capacity(GRPC_MESSAGE_HEADER_LEN + data.len());
+    buf.put_u8(if compressed { 1 } else { 0 });
+    buf.put_u32(data.len() as u32);
+    buf.put_slice(data);
+    buf.freeze()
+}
+
+async fn send_data(
+    send_stream: &mut SendStream<Bytes>,
+    mut data: Bytes,
+) -> Result<(), H2StreamBodyTransferError> {
+    while !data.is_empty() {
+        send_stream.reserve_capacity(data.len());
+        match poll_fn(|cx| send_stream.poll_capacity(cx)).await {
+            Some(Ok(n)) => {
+                let chunk = data.split_to(n.min(data.len()));
+                send_stream
+                    .send_data(chunk, false)
+                    .map_err(H2StreamBodyTransferError::SendDataFailed)?;
+            }
+            Some(Err(e)) => return Err(H2StreamBodyTransferError::WaitSendCapacityFailed(e)),
+            None => return Err(H2StreamBodyTransferError::SenderNotInSendState),
+        }
+    }
+    Ok(())
+}
+
+/// Transfer the gRPC body message by message, and each message will be audited
+/// by the ICAP server before it's forwarded.
+pub(super) struct GrpcMessageAuditTransfer {
+    auditor: GrpcMessageAuditor,
+    direction: GrpcMessageDirection,
+    timeout: Duration,
+    max_size: usize,
+    max_count: usize,
+    bypass: bool,
+    active: AtomicBool,
+}
+
+impl GrpcMessageAuditTransfer {
+    pub(super) fn new(
+        auditor: GrpcMessageAuditor,
+        direction: GrpcMessageDirection,
+        config: &GrpcInterceptionConfig,
+        bypass: bool,
+    ) -> Self {
+        GrpcMessageAuditTransfer {
+            auditor,
+            direction,
+            timeout: config.icap_audit_timeout,
+            max_size: config.icap_message_max_size,
+            max_count: config.icap_message_max_count,
+            bypass,
+            active: AtomicBool::new(false),
+        }
+    }
+
+    pub(super) fn is_idle(&self) -> bool {
+        !self.active.load(Ordering::Relaxed)
+    }
+
+    pub(super) fn reset_active(&self) {
+        self.active.store(false, Ordering::Relaxed);
+    }
+
+    /// Run the transfer.
+    ///
+    /// If a message is rejected, the send stream will be reset for request direction,
+    /// or be closed with PERMISSION_DENIED grpc-status trailers for response direction.
+    pub(super) async fn run(
+        &self,
+        mut recv_stream: RecvStream,
+        mut send_stream: SendStream<Bytes>,
+        inspector: &mut GrpcStreamInspector,
+    ) -> Result<(), GrpcMessageTransferError> {
+        let r = self
+            .transfer(&mut recv_stream, &mut send_stream, inspector)
+            .await;
+        if let Err(GrpcMessageTransferError::Audit(e)) = &r {
+            match (self.direction, e) {
+                (GrpcMessageDirection::Response, GrpcMessageAuditError::Rejected(..)) => {
+                    inspector.status = Some(GRPC_STATUS_PERMISSION_DENIED);
+                    let _ = send_stream.send_trailers(build_forbidden_trailers());
+                }
+                _ => send_stream.send_reset(Reason::CANCEL),
+            }
+        }
+        r
+    }
+
+    async fn transfer(
+        &self,
+        recv_stream: &mut RecvStream,
+        send_stream: &mut SendStream<Bytes>,
+        inspector: &mut GrpcStreamInspector,
+    ) -> Result<(), GrpcMessageTransferError> {
+        let mut framer = GrpcMessageFramer::default();
+        let mut index = 0usize;
+        let mut forward_all = false;
+
+        while let Some(r) = recv_stream.data().await {
+            let chunk = r.map_err(H2StreamBodyTransferError::RecvDataFailed)?;
+            self.active.store(true, Ordering::Relaxed);
+            if chunk.is_empty() {
+                continue;
+            }
+            inspector.inspect_data(&chunk);
+            let chunk_len = chunk.len();
+
+            if forward_all {
+                send_data(send_stream, chunk).await?;
+            } else {
+                framer.feed(&chunk);
+                loop {
+                    match self.next_message(&mut framer, index)? {
+                        GrpcNextMessage::Audit(compressed, data) => {
+                            let data = self.audit_message(index, compressed, data).await?;
+                            send_data(send_stream, encode_message(compressed, &data)).await?;
+                            self.active.store(true, Ordering::Relaxed);
+                            index += 1;
+                        }
+                        GrpcNextMessage::Pending => break,
+                        GrpcNextMessage::Forward => {
+                            forward_all = true;
+                            if !framer.is_empty() {
+                                send_data(send_stream, framer.take_all()).await?;
+                            }
+                            break;
+                        }
+                    }
+                }
+            }
+
+            recv_stream
+                .flow_control()
+                .release_capacity(chunk_len)
+                .map_err(H2StreamBodyTransferError::ReleaseRecvCapacityFailed)?;
+        }
+
+        if !framer.is_empty() {
+            // truncated message at the end of stream
+            return Err(GrpcMessageAuditError::MalformedMessage.into());
+        }
+
+        match recv_stream.trailers().await {
+            Ok(Some(trailers)) => {
+                inspector.inspect_trailers(&trailers);
+                send_stream
+                    .send_trailers(trailers)
+                    .map_err(H2StreamBodyTransferError::SendTrailersFailed)?;
+            }
+            Ok(None) => {
+                send_stream
+                    .send_data(Bytes::new(), true)
+                    .map_err(H2StreamBodyTransferError::GracefulCloseError)?;
+            }
+            Err(e) => return Err(H2StreamBodyTransferError::RecvTrailersFailed(e).into()),
+        }
+        Ok(())
+    }
+
+    /// Get the next message to audit.
+    ///
+    /// Oversized messages will be forwarded without audit if bypass is enabled,
+    /// the same as audit failures.
+    fn next_message(
+        &self,
+        framer: &mut GrpcMessageFramer,
+        index: usize,
+    ) -> Result<GrpcNextMessage, GrpcMessageAuditError> {
+        if index >= self.max_count {
+            return Ok(GrpcNextMessage::Forward);
+        }
+        match framer.next_message(self.max_size) {
+            Ok(Some((compressed, data))) => Ok(GrpcNextMessage::Audit(compressed, data)),
+            Ok(None) => Ok(GrpcNextMessage::Pending),
+            Err(GrpcMessageAuditError::MessageTooLarge(_)) if self.bypass => {
+                Ok(GrpcNextMessage::Forward)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn audit_message(
+        &self,
+        index: usize,
+        compressed: bool,
+        data: Bytes,
+    ) -> Result<Bytes, GrpcMessageAuditError> {
+        let direction = self.direction.as_str();
+        match tokio::time::timeout(
+            self.timeout,
+            self.auditor
+                .audit_message(self.direction, index, compressed, &data),
+        )
+        .await
+        {
+            Ok(Ok(GrpcMessageAuditResult::Accepted)) => Ok(data),
+            Ok(Ok(GrpcMessageAuditResult::Modified(new_data))) => Ok(new_data),
+            Ok(Ok(GrpcMessageAuditResult::Rejected(rsp))) => Err(GrpcMessageAuditError::Rejected(
+                direction,
+                index,
+                rsp.status.as_u16(),
+            )),
+            Ok(Err(e)) => {
+                if self.bypass {
+                    Ok(data)
+                } else {
+                    Err(GrpcMessageAuditError::AuditFailed(direction, index, e))
+                }
+            }
+            Err(_) => {
+                if self.bypass {
+                    Ok(data)
+                } else {
+                    Err(GrpcMessageAuditError::AuditTimeout(direction, index))
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    use g3_icap_client::reqmod::IcapReqmodClient;
+    use g3_icap_client::{IcapMethod, IcapServiceClient, IcapServiceConfig};
+
+    #[test]
+    fn framer_split() {
+        let mut framer = GrpcMessageFramer::default();
+        let msg1 = encode_message(false, b"hello");
+        let msg2 = encode_message(true, b"world!");
+
+        framer.feed(&msg1[..3]);
+        assert!(framer.next_message(16).unwrap().is_none());
+        framer.feed(&msg1[3..]);
+        framer.feed(&msg2[..7]);
+        let (compressed, data) = framer.next_message(16).unwrap().unwrap();
+        assert!(!compressed);
+        assert_eq!(data.as_ref(), b"hello");
+        assert!(framer.next_message(16).unwrap().is_none());
+        assert!(!framer.is_empty());
+
+        framer.feed(&msg2[7..]);
+        let (compressed, data) = framer.next_message(16).unwrap().unwrap();
+        assert!(compressed);
+        assert_eq!(data.as_ref(), b"world!");
+        assert!(framer.is_empty());
+    }
+
+    #[test]
+    fn framer_empty_message() {
+        let mut framer = GrpcMessageFramer::default();
+        framer.feed(&encode_message(false, b""));
+        let (compressed, data) = framer.next_message(0).unwrap().unwrap();
+        assert!(!compressed);
+        assert!(data.is_empty());
+        assert!(framer.is_empty());
+    }
+
+    #[test]
+    fn framer_invalid() {
+        let mut framer = GrpcMessageFramer::default();
+        framer.feed(&[2, 0, 0, 0, 1, 0]);
+        assert!(matches!(
+            framer.next_message(16),
+            Err(GrpcMessageAuditError::MalformedMessage)
+        ));
+
+        let mut framer = GrpcMessageFramer::default();
+        framer.feed(&encode_message(false, b"0123456789"));
+        assert!(matches!(
+            framer.next_message(8),
+            Err(GrpcMessageAuditError::MessageTooLarge(10))
+        ));
+    }
+
+    fn new_audit_transfer(bypass: bool) -> GrpcMessageAuditTransfer {
+        // the icap server won't be connected as no message will be audited
+        let url = url::Url::parse("icap://127.0.0.1:1344/reqmod").unwrap();
+        let config = IcapServiceConfig::new(IcapMethod::Reqmod, url).unwrap();
+        let client = IcapServiceClient::new(Arc::new(config)).unwrap();
+        let reqmod = IcapReqmodClient::new(Arc::new(client));
+        let config = GrpcInterceptionConfig {
+            icap_message_max_size: 8,
+            icap_message_max_count: 2,
+            ..Default::default()
+        };
+        GrpcMessageAuditTransfer::new(
+            reqmod.grpc_message_auditor("test.Echo", "Say"),
+            GrpcMessageDirection::Request,
+            &config,
+            bypass,
+        )
+    }
+
+    #[tokio::test]
+    async fn oversized_message() {
+        let transfer = new_audit_transfer(false);
+        let mut framer = GrpcMessageFramer::default();
+        framer.feed(&encode_message(false, b"0123456789"));
+        assert!(matches!(
+            transfer.next_message(&mut framer, 0),
+            Err(GrpcMessageAuditError::MessageTooLarge(10))
+        ));
+
+        let transfer = new_audit_transfer(true);
+        let mut framer = GrpcMessageFramer::default();
+        framer.feed(&encode_message(false, b"hello"));
+        framer.feed(&encode_message(false, b"0123456789"));
+        let GrpcNextMessage::Audit(compressed, data) =
+            transfer.next_message(&mut framer, 0).unwrap()
+        else {
+            panic!("the first message should be audited");
+        };
+        assert!(!compressed);
+        assert_eq!(data.as_ref(), b"hello");
+        assert!(matches!(
+            transfer.next_message(&mut framer, 1),
+            Ok(GrpcNextMessage::Forward)
+        ));
+        assert_eq!(framer.take_all().len(), GRPC_MESSAGE_HEADER_LEN + 10);
+    }
+
+    #[tokio::test]
+    async fn max_message_count() {
+        let transfer = new_audit_transfer(false);
+        let mut framer = GrpcMessageFramer::default();
+        framer.feed(&encode_message(false, b"hello"));
+        assert!(matches!(
+            transfer.next_message(&mut framer, 1),
+            Ok(GrpcNextMessage::Audit(..))
+        ));
+        assert!(matches!(
+            transfer.next_message(&mut framer, 1),
+            Ok(GrpcNextMessage::Pending)
+        ));
+        framer.feed(&encode_message(false, b"0123456789"));
+        assert!(matches!(
+            transfer.next_message(&mut framer, 2),
+            Ok(GrpcNextMessage::Forward)
+        ));
+    }
+
+    #[test]
+    fn forbidden_trailers() {
+        let trailers = build_forbidden_trailers();
+        assert_eq!(trailers.get("grpc-status").unwrap(), "7");
+        assert!(trailers.contains_key("grpc-message"));
+    }
+}
@@ -19,20 +19,22 @@ use std::time::Duration;
 use anyhow::anyhow;
 use bytes::Bytes;
 use chrono::{DateTime, Utc};
-use h2::client::SendRequest;
+use h2::client::{ResponseFuture, SendRequest};
 use h2::server::SendResponse;
-use h2::{Reason, RecvStream, StreamId};
+use h2::{Reason, RecvStream, SendStream, StreamId};
 use http::{HeaderValue, Method, Request, Response, StatusCode, Uri, Version};
 use slog::slog_info;
 use tokio::time::Instant;
 
 use g3_h2::{H2StreamBodyTransferError, H2StreamFromChunkedTransferError, RequestExt};
+use g3_icap_client::reqmod::grpc::GrpcMessageDirection;
 use g3_icap_client::reqmod::h2::{
-    H2RequestAdapter, HttpAdapterErrorResponse, ReqmodAdaptationEndState, ReqmodAdaptationRunState,
-    ReqmodRecvHttpResponseBody,
+    H2RequestAdapter, HttpAdapterErrorResponse, ReqmodAdaptationEndState, ReqmodAdaptationMidState,
+    ReqmodAdaptationRunState, ReqmodRecvHttpResponseBody,
 };
 use g3_icap_client::respmod::h2::{
-    H2ResponseAdapter, RespmodAdaptationEndState, RespmodAdaptationRunState,
+    H2ResponseAdapter, RespmodAdaptationEndState, RespmodAdaptationMidState,
+    RespmodAdaptationRunState,
 };
 use g3_slog_types::{
     LtDateTime, LtDuration, LtH2StreamId, LtHttpHeaderValue, LtHttpMethod, LtHttpUri, LtUuid,
@@ -44,6 +46,10 @@ use crate::config::server::ServerConfig;
 use crate::inspect::StreamInspectContext;
 use crate::serve::ServerIdleChecker;
 
+mod grpc;
+pub(super) use grpc::GrpcMessageAuditError;
+use grpc::{GrpcCallNotes, GrpcMessageAuditTransfer, GrpcMessageTransferError};
+
 macro_rules! intercept_log {
     ($obj:tt, $($args:tt)+) => {
         slog_info!($obj.ctx.intercept_logger(), $($args)+;
@@ -63,6 +69,13 @@ macro_rules! intercept_log {
             "dur_req_send_all" => LtDuration($obj.http_notes.dur_req_send_all),
             "dur_rsp_recv_hdr" => LtDuration($obj.http_notes.dur_rsp_recv_hdr),
             "dur_rsp_recv_all" => LtDuration($obj.http_notes.dur_rsp_recv_all),
+            "grpc_service" => $obj.grpc.as_ref().map(|g| g.service.as_str()),
+            "grpc_method" => $obj.grpc.as_ref().map(|g| g.method.as_str()),
+            "grpc_status" => $obj.grpc.as_ref().and_then(|g| g.rsp.status()),
+            "grpc_req_msg_count" => $obj.grpc.as_ref().map(|g| g.req.message_count()),
+            "grpc_req_msg_bytes" => $obj.grpc.as_ref().map(|g| g.req.message_bytes()),
+            "grpc_rsp_msg_count" => $obj.grpc.as_ref().map(|g| g.rsp.message_count()),
+            "grpc_rsp_msg_bytes" => $obj.grpc.as_ref().map(|g| g.rsp.message_bytes()),
         )
     };
 }
@@ -135,6 +148,7 @@ pub(crate) struct H2ForwardTask<SC: ServerConfig> {
     ups_stream_id: Option<StreamId>,
     send_error_response: bool,
     http_notes: HttpForwardTaskNotes,
+    grpc: Option<GrpcCallNotes>,
 }
 
 impl<SC> H2ForwardTask<SC>
@@ -151,12 +165,19 @@ where
             req.uri().clone(),
             req.headers().get(http::header::HOST).cloned(),
         );
+        let grpc = GrpcCallNotes::detect(
+            req.uri().path(),
+            req.headers(),
+            ctx.grpc_interception(),
+            ctx.audit_handle.icap_reqmod_client().is_some(),
+        );
         H2ForwardTask {
             ctx,
             clt_stream_id,
             ups_stream_id: None,
             send_error_response: false,
             http_notes,
+            grpc,
         }
     }
 
@@ -190,6 +211,64 @@ where
         Ok(())
     }
 
+    fn reply_grpc_forbidden(
+        &mut self,
+        clt_send_rsp: &mut SendResponse<Bytes>,
+        e: H2StreamTransferError,
+    ) -> Result<(), H2StreamTransferError> {
+        let Some(response) = self
+            .grpc
+            .as_mut()
+            .and_then(|grpc| grpc.build_forbidden_response())
+        else {
+            return Err(H2StreamTransferError::InternalServerError(
+                "failed to build grpc forbidden response",
+            ));
+        };
+        if clt_send_rsp.send_response(response, true).is_ok() {
+            self.http_notes.rsp_status = StatusCode::OK.as_u16();
+        }
+        self.send_error_response = false;
+        Err(e)
+    }
+
+    fn grpc_icap_forward(&self) -> bool {
+        self.grpc.as_ref().map(|g| g.icap_forward).unwrap_or(false)
+    }
+
+    fn grpc_message_audit_transfer(
+        &self,
+        direction: GrpcMessageDirection,
+    ) -> Option<GrpcMessageAuditTransfer> {
+        let grpc = self.grpc.as_ref().filter(|g| g.icap_forward)?;
+        let reqmod = self.ctx.audit_handle.icap_reqmod_client()?;
+        let config = self.ctx.grpc_interception();
+
+        let mut auditor = reqmod.grpc_message_auditor(&grpc.service, &grpc.method);
+        auditor.set_message_max_size(config.icap_message_max_size);
+        auditor.set_client_addr(self.ctx.task_notes.client_addr);
+        if let Some(username) = self.ctx.raw_user_name() {
+            auditor.set_client_username(username.clone());
+        }
+        if let Some(authority) = self.http_notes.uri.authority() {
+            auditor.set_authority(authority.to_string());
+        } else if let Some(host) = self
+            .http_notes
+            .host_header
+            .as_ref()
+            .and_then(|v| v.to_str().ok())
+        {
+            auditor.set_authority(host.to_string());
+        }
+
+        Some(GrpcMessageAuditTransfer::new(
+            auditor,
+            direction,
+            config,
+            reqmod.bypass(),
+        ))
+    }
+
     pub(crate) async fn forward(
         mut self,
         clt_req: Request<RecvStream>,
@@ -219,6 +298,12 @@ where
         } else if parts.headers.contains_key(http::header::EXPECT) {
             return self.reply_expectation_failed(clt_send_rsp);
         }
+        if let Some(grpc) = &self.grpc {
+            if grpc.check_forbidden(self.ctx.grpc_interception()) {
+                return self
+                    .reply_grpc_forbidden(clt_send_rsp, H2StreamTransferError::GrpcCallForbidden);
+            }
+        }
 
         let ups_send_req = match tokio::time::timeout(
             self.ctx.h2_interception().upstream_stream_open_timeout,
@@ -244,6 +329,7 @@ where
         self.send_error_response = true;
         let ups_req = Request::from_parts(parts, ());
 
+        let grpc_icap_forward = self.grpc_icap_forward();
         if let Some(reqmod) = self.ctx.audit_handle.icap_reqmod_client() {
             match reqmod
                 .h2_adapter(
@@ -263,8 +349,9 @@ where
                     if let Some(username) = self.ctx.raw_user_name() {
                         adapter.set_client_username(username.clone());
                     }
-                    let r = self
-                        .forward_with_adaptation(
+                    let r = if grpc_icap_forward {
+                        // only adapt the header here, the decoded messages will be audited inline
+                        self.forward_with_header_adaptation(
                             ups_send_req,
                             ups_req,
                             clt_body,
@@ -272,7 +359,18 @@ where
                             adapter,
                             &mut adaptation_state,
                         )
-                        .await;
+                        .await
+                    } else {
+                        self.forward_with_adaptation(
+                            ups_send_req,
+                            ups_req,
+                            clt_body,
+                            clt_send_rsp,
+                            adapter,
+                            &mut adaptation_state,
+                        )
+                        .await
+                    };
                     if let Some(dur) = adaptation_state.dur_ups_send_header {
                         self.http_notes.dur_req_send_hdr = dur;
                     }
@@ -292,10 +390,42 @@ where
             }
         }
 
-        self.forward_without_adaptation(ups_send_req, ups_req, clt_body, clt_send_rsp)
+        self.forward_without_adaptation(ups_send_req, ups_req, clt_body, clt_send_rsp, None)
             .await
     }
 
+    async fn forward_with_header_adaptation(
+        &mut self,
+        ups_send_req: SendRequest<Bytes>,
+        ups_req: Request<()>,
+        clt_body: RecvStream,
+        clt_send_rsp: &mut SendResponse<Bytes>,
+        icap_adapter: H2RequestAdapter<ServerIdleChecker>,
+        adaptation_state: &mut ReqmodAdaptationRunState,
+    ) -> Result<(), H2StreamTransferError> {
+        match icap_adapter
+            .xfer_header_only(adaptation_state, ups_req)
+            .await
+        {
+            Ok(ReqmodAdaptationMidState::OriginalRequest(ups_req))
+            | Ok(ReqmodAdaptationMidState::AdaptedRequest(_, ups_req)) => {
+                self.forward_without_adaptation(
+                    ups_send_req,
+                    ups_req,
+                    clt_body,
+                    clt_send_rsp,
+                    adaptation_state.take_respond_shared_headers(),
+                )
+                .await
+            }
+            Ok(ReqmodAdaptationMidState::HttpErrResponse(err_rsp, recv_body)) => {
+                self.send_adaptation_error_response(clt_send_rsp, err_rsp, recv_body)
+                    .await
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
     async fn forward_with_adaptation(
         &mut self,
         ups_send_req: SendRequest<Bytes>,
@@ -394,13 +524,37 @@ where
         ups_req: Request<()>,
         clt_body: RecvStream,
         clt_send_rsp: &mut SendResponse<Bytes>,
+        adaptation_respond_shared_headers: Option<HttpHeaderMap>,
     ) -> Result<(), H2StreamTransferError> {
         if clt_body.is_end_stream() {
-            self.forward_without_body(ups_send_req, ups_req, clt_send_rsp)
-                .await
+            self.forward_without_body(
+                ups_send_req,
+                ups_req,
+                clt_send_rsp,
+                adaptation_respond_shared_headers,
+            )
+            .await
+        } else if let Some(audit_transfer) =
+            self.grpc_message_audit_transfer(GrpcMessageDirection::Request)
+        {
+            self.forward_grpc_with_audit(
+                ups_send_req,
+                ups_req,
+                clt_body,
+                clt_send_rsp,
+                audit_transfer,
+                adaptation_respond_shared_headers,
+            )
+            .await
         } else {
-            self.forward_with_body(ups_send_req, ups_req, clt_body, clt_send_rsp)
-                .await
+            self.forward_with_body(
+                ups_send_req,
+                ups_req,
+                clt_body,
+                clt_send_rsp,
+                adaptation_respond_shared_headers,
+            )
+            .await
         }
     }
 
@@ -409,6 +563,7 @@ where
         mut ups_send_req: SendRequest<Bytes>,
         ups_req: Request<()>,
         clt_send_rsp: &mut SendResponse<Bytes>,
+        adaptation_respond_shared_headers: Option<HttpHeaderMap>,
     ) -> Result<(), H2StreamTransferError> {
         let orig_req = ups_req.clone_header();
 
@@ -430,8 +585,13 @@ where
                 Err(_) => return Err(H2StreamTransferError::ResponseHeadRecvTimeout),
             };
 
-        self.send_response(orig_req, ups_rsp, clt_send_rsp, None)
-            .await
+        self.send_response(
+            orig_req,
+            ups_rsp,
+            clt_send_rsp,
+            adaptation_respond_shared_headers,
+        )
+        .await
     }
 
     async fn forward_with_body(
@@ -440,6 +600,7 @@ where
         ups_req: Request<()>,
         clt_body: RecvStream,
         clt_send_rsp: &mut SendResponse<Bytes>,
+        adaptation_respond_shared_headers: Option<HttpHeaderMap>,
     ) -> Result<(), H2StreamTransferError> {
         let orig_req = ups_req.clone_header();
 
@@ -449,10 +610,11 @@ where
         self.ups_stream_id = Some(ups_rsp_fut.stream_id());
         self.http_notes.mark_req_send_hdr();
 
-        let mut req_body_transfer = H2BodyTransfer::new(
+        let mut req_body_transfer = H2BodyTransfer::with_inspector(
             clt_body,
             ups_send_stream,
             self.ctx.server_config.limited_copy_config().yield_size(),
+            self.grpc.as_mut().map(|g| &mut g.req),
         );
 
         let idle_duration = self.ctx.server_config.task_idle_check_duration();
@@ -514,11 +676,135 @@ where
             }
         }
 
-        if let Some(ups_rsp) = ups_rsp {
-            self.send_response(orig_req, ups_rsp, clt_send_rsp, None)
-                .await
-        } else {
-            let ups_rsp =
+        self.send_response_after_body(
+            orig_req,
+            ups_rsp,
+            ups_rsp_fut,
+            clt_send_rsp,
+            adaptation_respond_shared_headers,
+        )
+        .await
+    }
+
+    async fn forward_grpc_with_audit(
+        &mut self,
+        mut ups_send_req: SendRequest<Bytes>,
+        ups_req: Request<()>,
+        clt_body: RecvStream,
+        clt_send_rsp: &mut SendResponse<Bytes>,
+        audit_transfer: GrpcMessageAuditTransfer,
+        adaptation_respond_shared_headers: Option<HttpHeaderMap>,
+    ) -> Result<(), H2StreamTransferError> {
+        let orig_req = ups_req.clone_header();
+
+        let (mut ups_rsp_fut, ups_send_stream) = ups_send_req
+            .send_request(ups_req, false)
+            .map_err(H2StreamTransferError::RequestHeadSendFailed)?; // do not send REFUSED_STREAM, use the default rst in h2
+        self.ups_stream_id = Some(ups_rsp_fut.stream_id());
+        self.http_notes.mark_req_send_hdr();
+
+        let Some(grpc) = &mut self.grpc else {
+            return Err(H2StreamTransferError::InternalServerError(
+                "no grpc call notes found",
+            ));
+        };
+
+        let idle_duration = self.ctx.server_config.task_idle_check_duration();
+        let mut idle_interval =
+            tokio::time::interval_at(Instant::now() + idle_duration, idle_duration);
+        let mut idle_count = 0;
+        let max_idle_count = self.ctx.task_max_idle_count();
+
+        let r = {
+            let req_body_transfer = audit_transfer.run(clt_body, ups_send_stream, &mut grpc.req);
+            tokio::pin!(req_body_transfer);
+
+            loop {
+                tokio::select! {
+                    biased;
+
+                    r = &mut req_body_transfer => {
+                        break r.map(|_| None);
+                    }
+                    r = &mut ups_rsp_fut => {
+                        match r {
+                            Ok(rsp) => break Ok(Some(rsp)),
+                            Err(e) => {
+                                return Err(H2StreamTransferError::ResponseHeadRecvFailed(e));
+                            }
+                        }
+                    }
+                    _ = idle_interval.tick() => {
+                        if audit_transfer.is_idle() {
+                            idle_count += 1;
+
+                            if idle_count > max_idle_count {
+                                return Err(H2StreamTransferError::Idle(idle_duration, idle_count));
+                            }
+                        } else {
+                            idle_count = 0;
+
+                            audit_transfer.reset_active();
+                        }
+
+                        if self.ctx.belongs_to_blocked_user() {
+                            return Err(H2StreamTransferError::CanceledAsUserBlocked);
+                        }
+
+                        if self.ctx.server_force_quit() {
+                            return Err(H2StreamTransferError::CanceledAsServerQuit)
+                        }
+                    }
+                }
+            }
+        };
+
+        let ups_rsp = match r {
+            Ok(Some(rsp)) => {
+                self.http_notes.mark_rsp_recv_hdr();
+                Some(rsp)
+            }
+            Ok(None) => {
+                self.http_notes.mark_req_send_all();
+                None
+            }
+            Err(GrpcMessageTransferError::Transfer(e)) => {
+                return Err(H2StreamTransferError::RequestBodyTransferFailed(e));
+            }
+            Err(GrpcMessageTransferError::Audit(e)) => {
+                return if matches!(e, GrpcMessageAuditError::Rejected(..)) {
+                    // the upstream stream has been reset
+                    self.reply_grpc_forbidden(
+                        clt_send_rsp,
+                        H2StreamTransferError::GrpcMessageAuditFailed(e),
+                    )
+                } else {
+                    Err(H2StreamTransferError::GrpcMessageAuditFailed(e))
+                };
+            }
+        };
+
+        self.send_response_after_body(
+            orig_req,
+            ups_rsp,
+            ups_rsp_fut,
+            clt_send_rsp,
+            adaptation_respond_shared_headers,
+        )
+        .await
+    }
+
+    async fn send_response_after_body(
+        &mut self,
+        orig_req: Request<()>,
+        ups_rsp: Option<Response<RecvStream>>,
+        ups_rsp_fut: ResponseFuture,
+        clt_send_rsp: &mut SendResponse<Bytes>,
+        adaptation_respond_shared_headers: Option<HttpHeaderMap>,
+    ) -> Result<(), H2StreamTransferError> {
+        let ups_rsp = match ups_rsp {
+            Some(rsp) => rsp,
+            None => {
                 match tokio::time::timeout(self.ctx.h2_rsp_hdr_recv_timeout(), ups_rsp_fut).await {
                     Ok(Ok(d)) => {
                         self.http_notes.mark_rsp_recv_hdr();
@@ -526,11 +812,17 @@ where
                     }
                     Ok(Err(e)) => return Err(H2StreamTransferError::ResponseHeadRecvFailed(e)),
                     Err(_) => return Err(H2StreamTransferError::ResponseHeadRecvTimeout),
-                };
+                }
+            }
+        };
 
-            self.send_response(orig_req, ups_rsp, clt_send_rsp, None)
-                .await
-        }
+        self.send_response(
+            orig_req,
+            ups_rsp,
+            clt_send_rsp,
+            adaptation_respond_shared_headers,
+        )
+        .await
     }
 
     async fn send_response(
@@ -544,7 +836,12 @@ where
         let clt_rsp = Response::from_parts(parts, ());
 
         self.http_notes.origin_status = clt_rsp.status().as_u16();
+        if let Some(grpc) = &mut self.grpc {
+            // there may be trailers only response
+            grpc.rsp.inspect_headers(clt_rsp.headers());
+        }
 
+        let grpc_icap_forward = self.grpc_icap_forward();
         if let Some(respmod) = self.ctx.audit_handle.icap_respmod_client() {
             match respmod
                 .h2_adapter(
@@ -565,6 +862,18 @@ where
                         adapter.set_client_username(username);
                     }
                     adapter.set_respond_shared_headers(adaptation_respond_shared_headers);
+                    if grpc_icap_forward && !ups_body.is_end_stream() {
+                        // only adapt the header here, the decoded messages will be audited inline
+                        return self
+                            .send_response_with_header_adaptation(
+                                &ups_req,
+                                clt_rsp,
+                                ups_body,
+                                clt_send_rsp,
+                                adapter,
+                            )
+                            .await;
+                    }
                     let r = self
                         .send_response_with_adaptation(
                             &ups_req,
@@ -595,6 +904,24 @@ where
             .await
     }
 
+    async fn send_response_with_header_adaptation(
+        &mut self,
+        ups_req: &Request<()>,
+        clt_rsp: Response<()>,
+        ups_body: RecvStream,
+        clt_send_rsp: &mut SendResponse<Bytes>,
+        icap_adapter: H2ResponseAdapter<ServerIdleChecker>,
+    ) -> Result<(), H2StreamTransferError> {
+        match icap_adapter.xfer_header_only(ups_req, clt_rsp).await {
+            Ok(RespmodAdaptationMidState::OriginalResponse(clt_rsp))
+            | Ok(RespmodAdaptationMidState::AdaptedResponse(_, clt_rsp)) => {
+                self.send_response_without_adaptation(clt_rsp, ups_body, clt_send_rsp)
+                    .await
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
     async fn send_response_with_adaptation(
         &mut self,
         ups_req: &Request<()>,
@@ -628,23 +955,35 @@ where
         clt_send_rsp: &mut SendResponse<Bytes>,
     ) -> Result<(), H2StreamTransferError> {
         self.send_error_response = false;
+        let rsp_status = clt_rsp.status().as_u16();
 
         if ups_body.is_end_stream() {
             self.http_notes.mark_rsp_no_body();
             let _ = clt_send_rsp
                 .send_response(clt_rsp, true)
                 .map_err(H2StreamTransferError::ResponseHeadSendFailed)?;
-            self.http_notes.rsp_status = self.http_notes.origin_status;
+            self.http_notes.rsp_status = rsp_status;
+        } else if let Some(audit_transfer) =
+            self.grpc_message_audit_transfer(GrpcMessageDirection::Response)
+        {
+            let clt_send_stream = clt_send_rsp
+                .send_response(clt_rsp, false)
+                .map_err(H2StreamTransferError::ResponseHeadSendFailed)?;
+            self.http_notes.rsp_status = rsp_status;
+
+            self.transfer_grpc_response_with_audit(ups_body, clt_send_stream, audit_transfer)
+                .await?;
         } else {
             let clt_send_stream = clt_send_rsp
                 .send_response(clt_rsp, false)
                 .map_err(H2StreamTransferError::ResponseHeadSendFailed)?;
-            self.http_notes.rsp_status = self.http_notes.origin_status;
+            self.http_notes.rsp_status = rsp_status;
 
-            let mut rsp_body_transfer = H2BodyTransfer::new(
+            let mut rsp_body_transfer = H2BodyTransfer::with_inspector(
                 ups_body,
                 clt_send_stream,
                 self.ctx.server_config.limited_copy_config().yield_size(),
+                self.grpc.as_mut().map(|g| &mut g.rsp),
             );
 
             let idle_duration = self.ctx.server_config.task_idle_check_duration();
@@ -693,4 +1032,68 @@ where
 
         Ok(())
     }
+
+    async fn transfer_grpc_response_with_audit(
+        &mut self,
+        ups_body: RecvStream,
+        clt_send_stream: SendStream<Bytes>,
+        audit_transfer: GrpcMessageAuditTransfer,
+    ) -> Result<(), H2StreamTransferError> {
+        let Some(grpc) = &mut self.grpc else {
+            return Err(H2StreamTransferError::InternalServerError(
+                "no grpc call notes found",
+            ));
+        };
+
+        let idle_duration = self.ctx.server_config.task_idle_check_duration();
+        let mut idle_interval =
+            tokio::time::interval_at(Instant::now() + idle_duration, idle_duration);
+        let mut idle_count = 0;
+        let max_idle_count = self.ctx.task_max_idle_count();
+
+        let rsp_body_transfer = audit_transfer.run(ups_body, clt_send_stream, &mut grpc.rsp);
+        tokio::pin!(rsp_body_transfer);
+
+        loop {
+            tokio::select! {
+                biased;
+
+                r = &mut rsp_body_transfer => {
+                    return match r {
+                        Ok(_) => {
+                            self.http_notes.mark_rsp_recv_all();
+                            Ok(())
+                        }
+                        Err(GrpcMessageTransferError::Transfer(e)) => {
+                            Err(H2StreamTransferError::ResponseBodyTransferFailed(e))
+                        }
+                        Err(GrpcMessageTransferError::Audit(e)) => {
+                            Err(H2StreamTransferError::GrpcMessageAuditFailed(e))
+                        }
+                    };
+                }
+                _ = idle_interval.tick() => {
+                    if audit_transfer.is_idle() {
+                        idle_count += 1;
+
+                        if idle_count > max_idle_count {
+                            return Err(H2StreamTransferError::Idle(idle_duration, idle_count));
+                        }
+                    } else {
+                        idle_count = 0;
+
+                        audit_transfer.reset_active();
+                    }
+
+                    if self.ctx.belongs_to_blocked_user() {
+                        return Err(H2StreamTransferError::CanceledAsUserBlocked);
+                    }
+
+                    if self.ctx.server_force_quit() {
+                        return Err(H2StreamTransferError::CanceledAsServerQuit)
+                    }
+                }
+            }
+        }
+    }
 }
@@ -24,8 +24,8 @@ use uuid::Uuid;
 
 use g3_daemon::server::ServerQuitPolicy;
 use g3_dpi::{
-    H1InterceptionConfig, H2InterceptionConfig, ImapInterceptionConfig, MaybeProtocol,
-    ProtocolInspectAction, ProtocolInspector, SmtpInterceptionConfig,
+    GrpcInterceptionConfig, H1InterceptionConfig, H2InterceptionConfig, ImapInterceptionConfig,
+    MaybeProtocol, ProtocolInspectAction, ProtocolInspector, SmtpInterceptionConfig,
 };
 use g3_types::net::{Host, OpensslClientConfig};
 
@@ -275,6 +275,11 @@ impl<SC: ServerConfig> StreamInspectContext<SC> {
         self.audit_handle.h2_interception()
     }
 
+    #[inline]
+    fn grpc_interception(&self) -> &GrpcInterceptionConfig {
+        self.audit_handle.grpc_interception()
+    }
+
     fn h2_rsp_hdr_recv_timeout(&self) -> Duration {
         self.task_notes
             .user_ctx
@@ -29,6 +29,7 @@ use g3_slog_types::{LtUpstreamAddr, LtUuid, LtX509VerifyResult};
 use g3_types::net::{Host, TlsCertUsage, TlsServiceType, UpstreamAddr};
 use g3_udpdump::ExportedPduDissectorHint;
 
+use super::tls::{pcap_comment, pcap_remote_addr};
 use super::{
     BoxAsyncRead, BoxAsyncWrite, InterceptionError, StreamInspectContext, StreamInspection,
     TlsInterceptionContext,
@@ -96,6 +97,7 @@ pub(crate) struct StartTlsInterceptObject<SC: ServerConfig> {
     tls_interception: TlsInterceptionContext,
     protocol: StartTlsProtocol,
     server_verify_result: Option<X509VerifyResult>,
+    tls_key_log: Vec<String>,
 }
 
 impl<SC> StartTlsInterceptObject<SC>
@@ -115,6 +117,7 @@ where
             tls_interception: tls,
             protocol,
             server_verify_result: None,
+            tls_key_log: Vec::new(),
         }
     }
 
@@ -155,11 +158,12 @@ where
             ups_w,
         } = self.io.take().unwrap();
 
-        let ssl = Ssl::new(&self.tls_interception.server_config.ssl_context).map_err(|e| {
+        let mut ssl = Ssl::new(&self.tls_interception.server_config.ssl_context).map_err(|e| {
             TlsInterceptionError::InternalOpensslServerError(anyhow!(
                 "failed to get new SSL state: {e}"
             ))
         })?;
+        self.tls_interception.enable_client_key_log(&mut ssl);
         let mut lazy_acceptor =
             SslLazyAcceptor::new(ssl, tokio::io::join(clt_r, clt_w)).map_err(|e| {
                 TlsInterceptionError::InternalOpensslServerError(anyhow!(
@@ -192,7 +196,7 @@ where
             .tls_interception
             .server_config
             .fetch_alpn_extension(lazy_acceptor.ssl());
-        let ups_ssl = match self.ctx.user_site_tls_client() {
+        let mut ups_ssl = match self.ctx.user_site_tls_client() {
             Some(c) => c
                 .build_mimic_ssl(sni_hostname, &self.upstream, alpn_ext)
                 .map_err(|e| {
@@ -211,6 +215,8 @@ where
                 })?,
         };
 
+        self.tls_interception.enable_upstream_key_log(&mut ups_ssl);
+
         // handshake with upstream server
         let ups_tls_connector =
             SslConnector::new(ups_ssl, tokio::io::join(ups_r, ups_w)).map_err(|e| {
@@ -271,6 +277,10 @@ where
             TlsInterceptionError::ClientHandshakeFailed(anyhow!("client handshake error: {e:?}"))
         })?;
 
+        self.tls_key_log = self
+            .tls_interception
+            .take_key_log(clt_tls_stream.ssl(), ups_tls_stream.ssl());
+
         let (clt_r, clt_w) = clt_tls_stream.into_split();
         let (ups_r, ups_w) = ups_tls_stream.into_split();
 
@@ -292,7 +302,7 @@ where
                     clt_r,
                     clt_w,
                 );
-                Ok(self.inspect_inner(protocol, clt_r, clt_w, ups_r, ups_w))
+                Ok(self.pcap_and_inspect(protocol, clt_r, clt_w, ups_r, ups_w))
             } else {
                 let (ups_r, ups_w) = stream_dumper.wrap_remote_io(
                     self.ctx.task_notes.client_addr,
@@ -301,10 +311,49 @@ where
                     ups_r,
                     ups_w,
                 );
-                Ok(self.inspect_inner(protocol, clt_r, clt_w, ups_r, ups_w))
+                Ok(self.pcap_and_inspect(protocol, clt_r, clt_w, ups_r, ups_w))
             }
         } else {
-            Ok(self.inspect_inner(protocol, clt_r, clt_w, ups_r, ups_w))
+            Ok(self.pcap_and_inspect(protocol, clt_r, clt_w, ups_r, ups_w))
+        }
+    }
+
+    fn pcap_and_inspect<CR, CW, UR, UW>(
+        &self,
+        protocol: Protocol,
+        clt_r: CR,
+        clt_w: CW,
+        ups_r: UR,
+        ups_w: UW,
+    ) -> StreamInspection<SC>
+    where
+        CR: AsyncRead + Send + Unpin + 'static,
+        CW: AsyncWrite + Send + Unpin + 'static,
+        UR: AsyncRead + Send + Unpin + 'static,
+        UW: AsyncWrite + Send + Unpin + 'static,
+    {
+        let Some(pcap_dumper) = self.tls_interception.get_pcap_dumper(
+            self.ctx.raw_user_name().map(|v| v.as_ref()),
+            self.upstream.host(),
+        ) else {
+            return self.inspect_inner(protocol, clt_r, clt_w, ups_r, ups_w);
+        };
+
+        let client_addr = self.ctx.task_notes.client_addr;
+        let remote_addr = pcap_remote_addr(
+            self.ctx.task_notes.server_addr,
+            protocol,
+            self.upstream.port(),
+        );
+        let comment = pcap_comment(&self.ctx, &self.upstream, protocol, &self.tls_key_log);
+        if pcap_dumper.client_side() {
+            let (clt_r, clt_w) =
+                pcap_dumper.wrap_client_io(client_addr, remote_addr, Some(comment), clt_r, clt_w);
+            self.inspect_inner(protocol, clt_r, clt_w, ups_r, ups_w)
+        } else {
+            let (ups_r, ups_w) =
+                pcap_dumper.wrap_remote_io(client_addr, remote_addr, Some(comment), ups_r, ups_w);
+            self.inspect_inner(protocol, clt_r, clt_w, ups_r, ups_w)
         }
     }
 
@@ -14,9 +14,11 @@
  * limitations under the License.
  */
 
+use std::net::SocketAddr;
 use std::sync::Arc;
 
 use anyhow::anyhow;
+use openssl::ssl::SslRef;
 use openssl::x509::X509VerifyResult;
 use slog::slog_info;
 use tokio::io::{AsyncRead, AsyncWrite};
@@ -27,9 +29,10 @@ use g3_dpi::Protocol;
 use g3_io_ext::{AsyncStream, FlexBufReader, OnceBufReader};
 use g3_slog_types::{LtUpstreamAddr, LtUuid, LtX509VerifyResult};
 use g3_types::net::{
-    AlpnProtocol, OpensslInterceptionClientConfig, OpensslInterceptionServerConfig, UpstreamAddr,
+    AlpnProtocol, Host, OpensslInterceptionClientConfig, OpensslInterceptionServerConfig,
+    UpstreamAddr,
 };
-use g3_udpdump::{ExportedPduDissectorHint, StreamDumpConfig, StreamDumper};
+use g3_udpdump::{ExportedPduDissectorHint, PcapDumper, StreamDumpConfig, StreamDumper};
 
 use super::{BoxAsyncRead, BoxAsyncWrite, StreamInspectContext, StreamInspection};
 use crate::config::server::ServerConfig;
@@ -48,6 +51,7 @@ pub(crate) struct TlsInterceptionContext {
     pub(super) client_config: Arc<OpensslInterceptionClientConfig>,
     pub(super) server_config: Arc<OpensslInterceptionServerConfig>,
     stream_dumper: Arc<Vec<StreamDumper>>,
+    pcap_dumper: Option<Arc<PcapDumper>>,
 }
 
 impl TlsInterceptionContext {
@@ -56,6 +60,7 @@ impl TlsInterceptionContext {
         client_config: OpensslInterceptionClientConfig,
         server_config: OpensslInterceptionServerConfig,
         dump_config: Option<StreamDumpConfig>,
+        pcap_dumper: Option<Arc<PcapDumper>>,
     ) -> anyhow::Result<Self> {
         let mut stream_dumper = Vec::new();
         if let Some(dump) = dump_config {
@@ -85,6 +90,7 @@ impl TlsInterceptionContext {
             client_config: Arc::new(client_config),
             server_config: Arc::new(server_config),
             stream_dumper: Arc::new(stream_dumper),
+            pcap_dumper,
         })
     }
 
@@ -101,6 +107,79 @@ impl TlsInterceptionContext {
 
         fastrand::choice(self.stream_dumper.iter())
     }
+
+    pub(super) fn get_pcap_dumper(&self, user: Option<&str>, host: &Host) -> Option<&PcapDumper> {
+        self.pcap_dumper
+            .as_deref()
+            .filter(|d| d.check_filter(user, host))
+    }
+
+    fn pcap_key_log_enabled(&self) -> bool {
+        self.pcap_dumper
+            .as_ref()
+            .map(|d| d.tls_key_log())
+            .unwrap_or(false)
+    }
+
+    pub(super) fn enable_client_key_log(&self, ssl: &mut SslRef) {
+        if self.pcap_key_log_enabled() {
+            self.server_config.enable_key_log(ssl);
+        }
+    }
+
+    pub(super) fn enable_upstream_key_log(&self, ssl: &mut SslRef) {
+        if self.pcap_key_log_enabled() {
+            self.client_config.enable_key_log(ssl);
+        }
+    }
+
+    pub(super) fn take_key_log(&self, clt_ssl: &SslRef, ups_ssl: &SslRef) -> Vec<String> {
+        if !self.pcap_key_log_enabled() {
+            return Vec::new();
+        }
+        let mut lines = Vec::new();
+        for line in self.server_config.take_key_log(clt_ssl) {
+            lines.push(format!("client-side: {line}"));
+        }
+        for line in self.client_config.take_key_log(ups_ssl) {
+            lines.push(format!("upstream-side: {line}"));
+        }
+        lines
+    }
+}
+
+/// Use the well known clear text port as the remote port in the pcap files,
+/// so the decrypted application protocol can be detected by wireshark
+pub(super) fn pcap_remote_addr(
+    server_addr: SocketAddr,
+    protocol: Protocol,
+    port: u16,
+) -> SocketAddr {
+    let port = match protocol {
+        Protocol::Http1 | Protocol::Http2 => 80,
+        Protocol::Smtp => 25,
+        Protocol::Imap => 143,
+        _ => port,
+    };
+    SocketAddr::new(server_addr.ip(), port)
+}
+
+pub(super) fn pcap_comment<SC: ServerConfig>(
+    ctx: &StreamInspectContext<SC>,
+    upstream: &UpstreamAddr,
+    protocol: Protocol,
+    key_log: &[String],
+) -> String {
+    let mut comment = format!("task_id: {}\n", ctx.server_task_id());
+    if let Some(user) = ctx.raw_user_name() {
+        comment.push_str(&format!("user: {user}\n"));
+    }
+    comment.push_str(&format!("upstream: {upstream}\nprotocol: {protocol}\n"));
+    for line in key_log {
+        comment.push_str(line);
+        comment.push('\n');
+    }
+    comment
 }
 
 struct TlsInterceptIo {
@@ -116,6 +195,7 @@ pub(crate) struct TlsInterceptObject<SC: ServerConfig> {
     upstream: UpstreamAddr,
     tls_interception: TlsInterceptionContext,
     server_verify_result: Option<X509VerifyResult>,
+    tls_key_log: Vec<String>,
 }
 
 macro_rules! intercept_log {
@@ -142,6 +222,7 @@ impl<SC: ServerConfig> TlsInterceptObject<SC> {
             upstream,
             tls_interception: tls,
             server_verify_result: None,
+            tls_key_log: Vec::new(),
         }
     }
 
@@ -226,7 +307,7 @@ where
                     clt_r,
                     clt_w,
                 );
-                self.inspect_inner(protocol, has_alpn, clt_r, clt_w, ups_r, ups_w)
+                self.pcap_and_inspect(protocol, has_alpn, clt_r, clt_w, ups_r, ups_w)
             } else {
                 let (ups_r, ups_w) = stream_dumper.wrap_remote_io(
                     self.ctx.task_notes.client_addr,
@@ -235,9 +316,49 @@ where
                     ups_r,
                     ups_w,
                 );
-                self.inspect_inner(protocol, has_alpn, clt_r, clt_w, ups_r, ups_w)
+                self.pcap_and_inspect(protocol, has_alpn, clt_r, clt_w, ups_r, ups_w)
             }
         } else {
+            self.pcap_and_inspect(protocol, has_alpn, clt_r, clt_w, ups_r, ups_w)
+        }
+    }
+
+    fn pcap_and_inspect<CR, CW, UR, UW>(
+        &self,
+        protocol: Protocol,
+        has_alpn: bool,
+        clt_r: CR,
+        clt_w: CW,
+        ups_r: UR,
+        ups_w: UW,
+    ) -> StreamInspection<SC>
+    where
+        CR: AsyncRead + Send + Unpin + 'static,
+        CW: AsyncWrite + Send + Unpin + 'static,
+        UR: AsyncRead + Send + Unpin + 'static,
+        UW: AsyncWrite + Send + Unpin + 'static,
+    {
+        let Some(pcap_dumper) = self.tls_interception.get_pcap_dumper(
+            self.ctx.raw_user_name().map(|v| v.as_ref()),
+            self.upstream.host(),
+        ) else {
+            return self.inspect_inner(protocol, has_alpn, clt_r, clt_w, ups_r, ups_w);
+        };
+
+        let client_addr = self.ctx.task_notes.client_addr;
+        let remote_addr = pcap_remote_addr(
+            self.ctx.task_notes.server_addr,
+            protocol,
+            self.upstream.port(),
+        );
+        let comment = pcap_comment(&self.ctx, &self.upstream, protocol, &self.tls_key_log);
+        if pcap_dumper.client_side() {
+            let (clt_r, clt_w) =
+                pcap_dumper.wrap_client_io(client_addr, remote_addr, Some(comment), clt_r, clt_w);
+            self.inspect_inner(protocol, has_alpn, clt_r, clt_w, ups_r, ups_w)
+        } else {
+            let (ups_r, ups_w) =
+                pcap_dumper.wrap_remote_io(client_addr, remote_addr, Some(comment), ups_r, ups_w);
             self.inspect_inner(protocol, has_alpn, clt_r, clt_w, ups_r, ups_w)
         }
     }
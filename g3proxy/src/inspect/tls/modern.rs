@@ -64,11 +64,12 @@ where
             ups_w,
         } = self.io.take().unwrap();
 
-        let ssl = Ssl::new(&self.tls_interception.server_config.ssl_context).map_err(|e| {
+        let mut ssl = Ssl::new(&self.tls_interception.server_config.ssl_context).map_err(|e| {
             TlsInterceptionError::InternalOpensslServerError(anyhow!(
                 "failed to get new SSL state: {e}"
             ))
         })?;
+        self.tls_interception.enable_client_key_log(&mut ssl);
         let mut lazy_acceptor =
             SslLazyAcceptor::new(ssl, tokio::io::join(clt_r, clt_w)).map_err(|e| {
                 TlsInterceptionError::InternalOpensslServerError(anyhow!(
@@ -111,7 +112,7 @@ where
                     new_ext
                 }
             });
-        let ups_ssl = match self.ctx.user_site_tls_client() {
+        let mut ups_ssl = match self.ctx.user_site_tls_client() {
             Some(c) => c
                 .build_mimic_ssl(sni_hostname, &self.upstream, alpn_ext.as_ref())
                 .map_err(|e| {
@@ -130,6 +131,8 @@ where
                 })?,
         };
 
+        self.tls_interception.enable_upstream_key_log(&mut ups_ssl);
+
         // fetch fake server cert early in the background
         let cert_domain = sni_hostname
             .map(|v| v.to_string())
@@ -218,6 +221,10 @@ where
             false
         };
 
+        self.tls_key_log = self
+            .tls_interception
+            .take_key_log(clt_tls_stream.ssl(), ups_tls_stream.ssl());
+
         Ok(self.transfer_connected(protocol, has_alpn, clt_tls_stream, ups_tls_stream))
     }
 }
@@ -18,6 +18,8 @@ use slog::{slog_o, Logger};
 
 use g3_types::metrics::MetricsName;
 
+pub(crate) mod h2_connection;
+pub(crate) mod ssh_session;
 pub(crate) mod tcp_connect;
 pub(crate) mod tls_handshake;
 pub(crate) mod udp_sendto;
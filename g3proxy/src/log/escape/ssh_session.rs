@@ -0,0 +1,54 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::time::Duration;
+
+use slog::{slog_info, Logger};
+use uuid::Uuid;
+
+use g3_slog_types::{LtDuration, LtIpAddr, LtUpstreamAddr, LtUuid};
+use g3_types::net::UpstreamAddr;
+
+use crate::module::tcp_connect::TcpConnectTaskNotes;
+
+pub(crate) struct EscapeLogForSshSession<'a> {
+    pub(crate) upstream: &'a UpstreamAddr,
+    pub(crate) tcp_notes: &'a TcpConnectTaskNotes,
+    pub(crate) task_id: &'a Uuid,
+    pub(crate) ssh_peer: &'a UpstreamAddr,
+    pub(crate) alive_time: Duration,
+    pub(crate) total_channels: u64,
+    pub(crate) in_bytes: u64,
+    pub(crate) out_bytes: u64,
+}
+
+impl EscapeLogForSshSession<'_> {
+    pub(crate) fn log(&self, logger: &Logger, e: &anyhow::Error) {
+        slog_info!(logger, "{:?}", e;
+            "escape_type" => "SshSession",
+            "task_id" => LtUuid(self.task_id),
+            "upstream" => LtUpstreamAddr(self.upstream),
+            "next_bind_ip" => self.tcp_notes.bind.ip().map(LtIpAddr),
+            "next_bound_addr" => self.tcp_notes.local,
+            "next_peer_addr" => self.tcp_notes.next,
+            "ssh_peer" => LtUpstreamAddr(self.ssh_peer),
+            "alive_time" => LtDuration(self.alive_time),
+            "total_channels" => self.total_channels,
+            "in_bytes" => self.in_bytes,
+            "out_bytes" => self.out_bytes,
+        )
+    }
+}
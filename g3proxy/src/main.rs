@@ -60,6 +60,16 @@ fn main() -> anyhow::Result<()> {
         info!("the format of the config file is ok");
         return Ok(());
     }
+    if proc_args.daemon_config.check_config {
+        g3proxy::config::check()?;
+        info!("the config file is valid");
+        return Ok(());
+    }
+    if let Some(format) = proc_args.daemon_config.dump_config_summary {
+        let content = g3proxy::config::dump_summary(format)?;
+        println!("{content}");
+        return Ok(());
+    }
     if proc_args.output_graphviz_graph {
         let content = g3proxy::config::graphviz_graph()?;
         println!("{content}");
@@ -17,7 +17,8 @@
 use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::net::TcpStream;
 
-use g3_io_ext::LimitedStream;
+use g3_io_ext::{FlexBufReader, LimitedStream};
+use g3_openssl::SslStream;
 
 pub(crate) trait FtpRemoteConnection: AsyncRead + AsyncWrite {}
 
@@ -25,4 +26,8 @@ impl FtpRemoteConnection for TcpStream {}
 
 impl<S> FtpRemoteConnection for LimitedStream<S> where S: AsyncRead + AsyncWrite {}
 
+impl<S> FtpRemoteConnection for FlexBufReader<S> where S: AsyncRead + AsyncWrite {}
+
+impl<S> FtpRemoteConnection for SslStream<S> where S: AsyncRead + AsyncWrite + Unpin {}
+
 pub(crate) type BoxFtpRemoteConnection = Box<dyn FtpRemoteConnection + Send + Unpin>;
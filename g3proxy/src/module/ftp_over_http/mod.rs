@@ -20,7 +20,7 @@ mod path;
 mod stats;
 mod task;
 
-pub(crate) use connection::BoxFtpRemoteConnection;
+pub(crate) use connection::{BoxFtpRemoteConnection, FtpRemoteConnection};
 pub(crate) use context::{
     BoxFtpConnectContext, DenyFtpConnectContext, DirectFtpConnectContext, FtpConnectContext,
 };
@@ -16,6 +16,7 @@
 
 use http::{Method, Uri};
 
+use g3_ftp_client::FtpTlsMode;
 use g3_http::server::HttpProxyClientRequest;
 use g3_types::auth::{Password, Username};
 use g3_types::net::{HttpAuth, HttpBasicAuth, UpstreamAddr};
@@ -25,6 +26,7 @@ use crate::module::tcp_connect::TcpConnectTaskNotes;
 
 pub(crate) struct FtpOverHttpTaskNotes {
     upstream: UpstreamAddr,
+    tls_mode: Option<FtpTlsMode>,
     pub(crate) method: Method,
     pub(crate) uri: Uri,
     pub(crate) uri_log_max_chars: usize,
@@ -70,8 +72,21 @@ impl FtpOverHttpTaskNotes {
             }
         }
 
+        let tls_mode = req.uri.scheme_str().and_then(|scheme| {
+            if scheme.eq_ignore_ascii_case("ftps") {
+                Some(FtpTlsMode::Implicit)
+            } else if scheme.eq_ignore_ascii_case("ftpes") {
+                // not registered, but used by FileZilla and WinSCP for explicit FTPS,
+                // as there is no way to require AUTH TLS with the plain ftp scheme
+                Some(FtpTlsMode::Explicit)
+            } else {
+                None
+            }
+        });
+
         FtpOverHttpTaskNotes {
             upstream: upstream.clone(),
+            tls_mode,
             method: req.method.clone(),
             uri: req.uri.clone(),
             uri_log_max_chars,
@@ -98,4 +113,9 @@ impl FtpOverHttpTaskNotes {
     pub(crate) fn upstream(&self) -> &UpstreamAddr {
         &self.upstream
     }
+
+    #[inline]
+    pub(crate) fn tls_mode(&self) -> Option<FtpTlsMode> {
+        self.tls_mode
+    }
 }
@@ -0,0 +1,266 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::sync::Arc;
+
+use bytes::Bytes;
+use http::{header, Method};
+
+use g3_http::client::HttpForwardRemoteResponse;
+use g3_http::server::HttpProxyClientRequest;
+use g3_http::HttpBodyType;
+use g3_types::net::UpstreamAddr;
+
+use super::{entry, HttpForwardCache, HttpForwardCacheEntry};
+
+pub(crate) struct HttpForwardCacheContext {
+    cache: Arc<HttpForwardCache>,
+    key: String,
+    stored: Option<Arc<HttpForwardCacheEntry>>,
+    fresh: bool,
+}
+
+impl HttpForwardCacheContext {
+    fn cache_key(is_https: bool, upstream: &UpstreamAddr, req: &HttpProxyClientRequest) -> String {
+        let scheme = if is_https { "https" } else { "http" };
+        let path = req.uri.path_and_query().map(|v| v.as_str()).unwrap_or("/");
+        let authority = upstream.to_string().to_lowercase();
+        format!("{scheme}://{authority}{path}")
+    }
+
+    fn lookup_allowed(req: &HttpProxyClientRequest) -> bool {
+        if req.method != Method::GET || req.body_type().is_some() {
+            return false;
+        }
+        // leave conditional and range requests to the origin server
+        const SKIPPED_HEADERS: [header::HeaderName; 6] = [
+            header::IF_MATCH,
+            header::IF_NONE_MATCH,
+            header::IF_MODIFIED_SINCE,
+            header::IF_UNMODIFIED_SINCE,
+            header::IF_RANGE,
+            header::RANGE,
+        ];
+        !SKIPPED_HEADERS
+            .iter()
+            .any(|name| req.end_to_end_headers.contains_key(name))
+    }
+
+    /// lookup the cache for the request, conditional headers will be added if validation is needed
+    pub(crate) async fn lookup(
+        cache: &Arc<HttpForwardCache>,
+        is_https: bool,
+        upstream: &UpstreamAddr,
+        req: &mut HttpProxyClientRequest,
+    ) -> Option<Self> {
+        if !HttpForwardCacheContext::lookup_allowed(req) {
+            if !req.method.is_safe() {
+                // invalidate the stored response, see rfc9111 Section 4.4
+                cache.remove(&HttpForwardCacheContext::cache_key(is_https, upstream, req));
+            }
+            return None;
+        }
+
+        let key = HttpForwardCacheContext::cache_key(is_https, upstream, req);
+        let mut stored = cache.get(&key).await.filter(|e| e.match_vary(req));
+        let mut fresh = false;
+        if let Some(entry) = &stored {
+            if entry.is_fresh_for(req, entry::now_secs()) {
+                fresh = true;
+            } else if !entry.set_validators(&mut req.end_to_end_headers) {
+                stored = None;
+            }
+        }
+
+        Some(HttpForwardCacheContext {
+            cache: Arc::clone(cache),
+            key,
+            stored,
+            fresh,
+        })
+    }
+
+    pub(crate) fn fresh_entry(&self) -> Option<&Arc<HttpForwardCacheEntry>> {
+        if self.fresh {
+            self.stored.as_ref()
+        } else {
+            None
+        }
+    }
+
+    /// update the stale entry if the origin server responds with 304
+    pub(crate) fn revalidate(
+        &self,
+        rsp: &HttpForwardRemoteResponse,
+    ) -> Option<Arc<HttpForwardCacheEntry>> {
+        if self.fresh || rsp.code != 304 {
+            return None;
+        }
+        let stale = self.stored.as_ref()?;
+        let entry = Arc::new(stale.refresh(rsp));
+        self.cache.insert(entry.clone());
+        Some(entry)
+    }
+
+    /// create a new entry with empty body if the response should be stored
+    pub(crate) fn new_entry(
+        &self,
+        req: &HttpProxyClientRequest,
+        rsp: &HttpForwardRemoteResponse,
+    ) -> Option<HttpForwardCacheEntry> {
+        match rsp.body_type(&req.method) {
+            Some(HttpBodyType::ContentLength(size)) if size <= self.max_object_size() as u64 => {}
+            Some(_) => return None,
+            None => {}
+        }
+        if !HttpForwardCacheEntry::storable(req, rsp) {
+            return None;
+        }
+        Some(HttpForwardCacheEntry::new(self.key.clone(), req, rsp))
+    }
+
+    #[inline]
+    pub(crate) fn max_object_size(&self) -> usize {
+        self.cache.max_object_size()
+    }
+
+    pub(crate) fn store(&self, mut entry: HttpForwardCacheEntry, body: Bytes) {
+        entry.set_body(body);
+        self.cache.insert(Arc::new(entry));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::http_forward_cache::HttpForwardCacheConfig;
+    use tokio::io::BufReader;
+
+    async fn parse_req(data: &'static [u8]) -> HttpProxyClientRequest {
+        let mut version = http::Version::HTTP_11;
+        let mut buf = BufReader::new(data);
+        HttpProxyClientRequest::parse_basic(&mut buf, 4096, &mut version)
+            .await
+            .unwrap()
+    }
+
+    async fn parse_rsp(data: &'static [u8]) -> HttpForwardRemoteResponse {
+        let mut buf = BufReader::new(data);
+        HttpForwardRemoteResponse::parse(&mut buf, &Method::GET, true, 4096)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn store_and_revalidate() {
+        let cache = Arc::new(HttpForwardCache::new(&HttpForwardCacheConfig::default()).unwrap());
+        let upstream = UpstreamAddr::from_host_str_and_port("example.net", 80).unwrap();
+        const REQ: &[u8] = b"GET /a HTTP/1.1\r\nHost: example.net\r\n\r\n";
+
+        // miss, and store a stale response with validator
+        let mut req = parse_req(REQ).await;
+        let ctx = HttpForwardCacheContext::lookup(&cache, false, &upstream, &mut req)
+            .await
+            .unwrap();
+        assert!(ctx.fresh_entry().is_none());
+        let rsp = parse_rsp(
+            b"HTTP/1.1 200 OK\r\nCache-Control: max-age=0\r\nETag: \"v1\"\r\nContent-Length: 5\r\n\r\n",
+        )
+        .await;
+        let entry = ctx.new_entry(&req, &rsp).unwrap();
+        ctx.store(entry, Bytes::from_static(b"hello"));
+
+        // stale, conditional headers should be added
+        let mut req = parse_req(REQ).await;
+        let ctx = HttpForwardCacheContext::lookup(&cache, false, &upstream, &mut req)
+            .await
+            .unwrap();
+        assert!(ctx.fresh_entry().is_none());
+        assert_eq!(
+            req.end_to_end_headers
+                .get(header::IF_NONE_MATCH)
+                .unwrap()
+                .to_str(),
+            "\"v1\""
+        );
+
+        // the origin server responds with 304
+        let rsp = parse_rsp(
+            b"HTTP/1.1 304 Not Modified\r\nCache-Control: max-age=600\r\nETag: \"v1\"\r\n\r\n",
+        )
+        .await;
+        let entry = ctx.revalidate(&rsp).unwrap();
+        assert_eq!(entry.code(), 200);
+        assert_eq!(entry.body().as_ref(), b"hello");
+
+        // now fresh
+        let mut req = parse_req(REQ).await;
+        let ctx = HttpForwardCacheContext::lookup(&cache, false, &upstream, &mut req)
+            .await
+            .unwrap();
+        let entry = ctx.fresh_entry().unwrap();
+        assert_eq!(entry.body().as_ref(), b"hello");
+        assert!(!req.end_to_end_headers.contains_key(header::IF_NONE_MATCH));
+        // no revalidate for fresh entry
+        assert!(ctx.revalidate(&rsp).is_none());
+    }
+
+    #[tokio::test]
+    async fn skip_and_invalidate() {
+        let cache = Arc::new(HttpForwardCache::new(&HttpForwardCacheConfig::default()).unwrap());
+        let upstream = UpstreamAddr::from_host_str_and_port("example.net", 80).unwrap();
+
+        let mut req = parse_req(b"GET /a HTTP/1.1\r\nHost: example.net\r\n\r\n").await;
+        let ctx = HttpForwardCacheContext::lookup(&cache, false, &upstream, &mut req)
+            .await
+            .unwrap();
+        let rsp = parse_rsp(
+            b"HTTP/1.1 200 OK\r\nCache-Control: max-age=600\r\nContent-Length: 5\r\n\r\n",
+        )
+        .await;
+        let entry = ctx.new_entry(&req, &rsp).unwrap();
+        ctx.store(entry, Bytes::from_static(b"hello"));
+
+        // range requests are left to the origin server
+        let mut req =
+            parse_req(b"GET /a HTTP/1.1\r\nHost: example.net\r\nRange: bytes=0-1\r\n\r\n").await;
+        assert!(
+            HttpForwardCacheContext::lookup(&cache, false, &upstream, &mut req)
+                .await
+                .is_none()
+        );
+
+        // unsafe methods invalidate the stored response
+        let mut req = parse_req(b"DELETE /a HTTP/1.1\r\nHost: example.net\r\n\r\n").await;
+        assert!(
+            HttpForwardCacheContext::lookup(&cache, false, &upstream, &mut req)
+                .await
+                .is_none()
+        );
+        let mut req = parse_req(b"GET /a HTTP/1.1\r\nHost: example.net\r\n\r\n").await;
+        let ctx = HttpForwardCacheContext::lookup(&cache, false, &upstream, &mut req)
+            .await
+            .unwrap();
+        assert!(ctx.fresh_entry().is_none());
+
+        // too large object should not be stored
+        let rsp = parse_rsp(
+            b"HTTP/1.1 200 OK\r\nCache-Control: max-age=600\r\nContent-Length: 999999999\r\n\r\n",
+        )
+        .await;
+        assert!(ctx.new_entry(&req, &rsp).is_none());
+    }
+}
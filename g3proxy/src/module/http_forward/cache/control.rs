@@ -0,0 +1,209 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::str::FromStr;
+
+use chrono::DateTime;
+
+use g3_types::net::HttpHeaderMap;
+
+/// max heuristic freshness lifetime, see rfc9111 Section 4.2.2
+const HEURISTIC_FRESHNESS_MAX_SECS: u64 = 24 * 60 * 60;
+
+#[derive(Default)]
+pub(super) struct CacheControl {
+    pub(super) no_store: bool,
+    pub(super) no_cache: bool,
+    pub(super) private: bool,
+    pub(super) public: bool,
+    pub(super) must_revalidate: bool,
+    pub(super) max_age: Option<u64>,
+    pub(super) s_maxage: Option<u64>,
+}
+
+impl CacheControl {
+    pub(super) fn parse(headers: &HttpHeaderMap) -> Self {
+        let mut cc = CacheControl::default();
+        for value in headers.get_all(http::header::CACHE_CONTROL) {
+            for directive in value.to_str().split(',') {
+                cc.add_directive(directive.trim());
+            }
+        }
+        if !headers.contains_key(http::header::CACHE_CONTROL) {
+            // see rfc9111 Section 5.4, Pragma is only used if Cache-Control is absent
+            for value in headers.get_all(http::header::PRAGMA) {
+                if value.to_str().eq_ignore_ascii_case("no-cache") {
+                    cc.no_cache = true;
+                }
+            }
+        }
+        cc
+    }
+
+    fn add_directive(&mut self, directive: &str) {
+        let (name, arg) = match directive.split_once('=') {
+            Some((name, arg)) => (name.trim(), Some(arg.trim().trim_matches('"'))),
+            None => (directive, None),
+        };
+        let parse_seconds = |arg: Option<&str>| arg.and_then(|s| u64::from_str(s).ok());
+
+        match name.to_ascii_lowercase().as_str() {
+            "no-store" => self.no_store = true,
+            // a no-cache directive with field names is treated as unqualified
+            "no-cache" => self.no_cache = true,
+            // a private directive with field names is treated as unqualified
+            "private" => self.private = true,
+            "public" => self.public = true,
+            "must-revalidate" | "proxy-revalidate" => self.must_revalidate = true,
+            "max-age" => {
+                // invalid value should be treated as stale, see rfc9111 Section 4.2.1
+                self.max_age = Some(parse_seconds(arg).unwrap_or(0));
+            }
+            "s-maxage" => self.s_maxage = Some(parse_seconds(arg).unwrap_or(0)),
+            _ => {}
+        }
+    }
+}
+
+pub(super) fn parse_http_date(headers: &HttpHeaderMap, name: http::HeaderName) -> Option<u64> {
+    let value = headers.get(name)?;
+    let date = DateTime::parse_from_rfc2822(value.to_str()).ok()?;
+    u64::try_from(date.timestamp()).ok()
+}
+
+/// get the freshness lifetime of the response, see rfc9111 Section 4.2.1
+pub(super) fn freshness_lifetime(
+    code: u16,
+    cc: &CacheControl,
+    headers: &HttpHeaderMap,
+    response_time: u64,
+) -> u64 {
+    if let Some(secs) = cc.s_maxage {
+        return secs;
+    }
+    if let Some(secs) = cc.max_age {
+        return secs;
+    }
+
+    let date = parse_http_date(headers, http::header::DATE).unwrap_or(response_time);
+    if headers.contains_key(http::header::EXPIRES) {
+        // invalid Expires value should be treated as already expired
+        return parse_http_date(headers, http::header::EXPIRES)
+            .map(|expires| expires.saturating_sub(date))
+            .unwrap_or(0);
+    }
+
+    if heuristically_cacheable(code) {
+        if let Some(last_modified) = parse_http_date(headers, http::header::LAST_MODIFIED) {
+            let secs = date.saturating_sub(last_modified) / 10;
+            return secs.min(HEURISTIC_FRESHNESS_MAX_SECS);
+        }
+    }
+    0
+}
+
+/// see rfc9110 Section 15.1
+pub(super) fn heuristically_cacheable(code: u16) -> bool {
+    matches!(
+        code,
+        200 | 203 | 204 | 206 | 300 | 301 | 308 | 404 | 405 | 410 | 414 | 501
+    )
+}
+
+/// get the initial age of the response, see rfc9111 Section 4.2.3
+pub(super) fn initial_age(headers: &HttpHeaderMap, response_time: u64) -> u64 {
+    let age_value = headers
+        .get(http::header::AGE)
+        .and_then(|v| u64::from_str(v.to_str()).ok())
+        .unwrap_or(0);
+    let apparent_age = parse_http_date(headers, http::header::DATE)
+        .map(|date| response_time.saturating_sub(date))
+        .unwrap_or(0);
+    apparent_age.max(age_value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use g3_types::net::HttpHeaderValue;
+
+    fn build_headers(headers: &[(&'static str, &'static str)]) -> HttpHeaderMap {
+        let mut map = HttpHeaderMap::default();
+        for (name, value) in headers {
+            map.append(
+                http::HeaderName::from_static(name),
+                HttpHeaderValue::from_static(value),
+            );
+        }
+        map
+    }
+
+    #[test]
+    fn parse_cache_control() {
+        let headers = build_headers(&[
+            ("cache-control", "public, max-age=600"),
+            ("cache-control", "s-maxage=\"60\", no-cache=\"set-cookie\""),
+        ]);
+        let cc = CacheControl::parse(&headers);
+        assert!(cc.public);
+        assert!(cc.no_cache);
+        assert!(!cc.no_store);
+        assert_eq!(cc.max_age, Some(600));
+        assert_eq!(cc.s_maxage, Some(60));
+
+        let headers = build_headers(&[("pragma", "no-cache")]);
+        let cc = CacheControl::parse(&headers);
+        assert!(cc.no_cache);
+
+        let headers = build_headers(&[("cache-control", "max-age=abc")]);
+        let cc = CacheControl::parse(&headers);
+        assert_eq!(cc.max_age, Some(0));
+    }
+
+    #[test]
+    fn calc_freshness() {
+        let headers = build_headers(&[("cache-control", "max-age=300, s-maxage=30")]);
+        let cc = CacheControl::parse(&headers);
+        assert_eq!(freshness_lifetime(200, &cc, &headers, 0), 30);
+
+        let headers = build_headers(&[
+            ("date", "Sun, 06 Nov 1994 08:49:37 GMT"),
+            ("expires", "Sun, 06 Nov 1994 09:49:37 GMT"),
+        ]);
+        let cc = CacheControl::parse(&headers);
+        assert_eq!(freshness_lifetime(200, &cc, &headers, 0), 3600);
+
+        let headers = build_headers(&[("date", "Sun, 06 Nov 1994 08:49:37 GMT"), ("expires", "0")]);
+        let cc = CacheControl::parse(&headers);
+        assert_eq!(freshness_lifetime(200, &cc, &headers, 0), 0);
+
+        let headers = build_headers(&[
+            ("date", "Sun, 06 Nov 1994 08:49:37 GMT"),
+            ("last-modified", "Sun, 06 Nov 1994 07:49:37 GMT"),
+        ]);
+        let cc = CacheControl::parse(&headers);
+        assert_eq!(freshness_lifetime(200, &cc, &headers, 0), 360);
+        assert_eq!(freshness_lifetime(302, &cc, &headers, 0), 0);
+    }
+
+    #[test]
+    fn calc_initial_age() {
+        let headers = build_headers(&[("date", "Sun, 06 Nov 1994 08:49:37 GMT"), ("age", "5")]);
+        let date = parse_http_date(&headers, http::header::DATE).unwrap();
+        assert_eq!(initial_age(&headers, date + 2), 5);
+        assert_eq!(initial_age(&headers, date + 10), 10);
+    }
+}
@@ -0,0 +1,485 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::io::Write;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bytes::{BufMut, Bytes};
+use http::{header, HeaderName, Version};
+use serde_json::{json, Value};
+
+use g3_http::client::HttpForwardRemoteResponse;
+use g3_http::server::HttpProxyClientRequest;
+use g3_types::net::{HttpHeaderMap, HttpHeaderValue};
+
+use super::control::{self, CacheControl};
+use crate::config::http_rewrite::HttpHeaderRewrite;
+
+pub(super) fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+fn merged_header_value(headers: &HttpHeaderMap, name: &HeaderName) -> Option<String> {
+    let mut iter = headers.get_all(name).into_iter();
+    let mut merged = iter.next()?.to_str().to_string();
+    for value in iter {
+        merged.push_str(", ");
+        merged.push_str(value.to_str());
+    }
+    Some(merged)
+}
+
+fn collect_vary(
+    rsp_headers: &HttpHeaderMap,
+    req_headers: &HttpHeaderMap,
+) -> Vec<(HeaderName, Option<String>)> {
+    let mut vary = Vec::new();
+    for value in rsp_headers.get_all(header::VARY) {
+        for name in value.to_str().split(',') {
+            let Ok(name) = HeaderName::from_str(name.trim()) else {
+                continue;
+            };
+            let value = merged_header_value(req_headers, &name);
+            vary.push((name, value));
+        }
+    }
+    vary
+}
+
+pub(crate) struct HttpForwardCacheEntry {
+    key: String,
+    code: u16,
+    reason: String,
+    headers: HttpHeaderMap,
+    body: Bytes,
+    /// the unix timestamp when the response is received
+    response_time: u64,
+    initial_age: u64,
+    freshness_lifetime: u64,
+    no_cache: bool,
+    vary: Vec<(HeaderName, Option<String>)>,
+}
+
+impl HttpForwardCacheEntry {
+    /// check if the response is allowed to be stored in a shared cache, see rfc9111 Section 3
+    pub(crate) fn storable(req: &HttpProxyClientRequest, rsp: &HttpForwardRemoteResponse) -> bool {
+        if rsp.code < 200 || rsp.code == 206 || rsp.code == 304 {
+            return false;
+        }
+
+        let req_cc = CacheControl::parse(&req.end_to_end_headers);
+        if req_cc.no_store {
+            return false;
+        }
+        let rsp_cc = CacheControl::parse(&rsp.end_to_end_headers);
+        if rsp_cc.no_store || rsp_cc.private {
+            return false;
+        }
+        // see rfc9111 Section 3.5
+        if req.end_to_end_headers.contains_key(header::AUTHORIZATION)
+            && !(rsp_cc.public || rsp_cc.must_revalidate || rsp_cc.s_maxage.is_some())
+        {
+            return false;
+        }
+        // never share cookies between clients
+        if rsp.end_to_end_headers.contains_key(header::SET_COOKIE) {
+            return false;
+        }
+        let vary_all = rsp
+            .end_to_end_headers
+            .get_all(header::VARY)
+            .into_iter()
+            .any(|v| v.to_str().split(',').any(|s| s.trim() == "*"));
+        if vary_all {
+            return false;
+        }
+
+        rsp_cc.public
+            || rsp_cc.max_age.is_some()
+            || rsp_cc.s_maxage.is_some()
+            || rsp.end_to_end_headers.contains_key(header::EXPIRES)
+            || control::heuristically_cacheable(rsp.code)
+    }
+
+    pub(crate) fn new(
+        key: String,
+        req: &HttpProxyClientRequest,
+        rsp: &HttpForwardRemoteResponse,
+    ) -> Self {
+        let response_time = now_secs();
+        let cc = CacheControl::parse(&rsp.end_to_end_headers);
+        let initial_age = control::initial_age(&rsp.end_to_end_headers, response_time);
+        let freshness_lifetime =
+            control::freshness_lifetime(rsp.code, &cc, &rsp.end_to_end_headers, response_time);
+        let mut headers = rsp.end_to_end_headers.clone();
+        headers.remove(header::AGE);
+        let vary = collect_vary(&headers, &req.end_to_end_headers);
+        HttpForwardCacheEntry {
+            key,
+            code: rsp.code,
+            reason: rsp.reason.clone(),
+            headers,
+            body: Bytes::new(),
+            response_time,
+            initial_age,
+            freshness_lifetime,
+            no_cache: cc.no_cache,
+            vary,
+        }
+    }
+
+    pub(super) fn set_body(&mut self, body: Bytes) {
+        self.body = body;
+    }
+
+    /// update the stored entry by a 304 response, see rfc9111 Section 4.3.4
+    pub(crate) fn refresh(&self, rsp: &HttpForwardRemoteResponse) -> Self {
+        let response_time = now_secs();
+        let mut headers = self.headers.clone();
+        rsp.end_to_end_headers.for_each(|name, _| {
+            if name != header::CONTENT_LENGTH {
+                headers.remove(name);
+            }
+        });
+        rsp.end_to_end_headers.for_each(|name, value| {
+            if name != header::CONTENT_LENGTH {
+                headers.append(name.clone(), value.clone());
+            }
+        });
+        let initial_age = control::initial_age(&headers, response_time);
+        headers.remove(header::AGE);
+        let cc = CacheControl::parse(&headers);
+        let freshness_lifetime =
+            control::freshness_lifetime(self.code, &cc, &headers, response_time);
+        HttpForwardCacheEntry {
+            key: self.key.clone(),
+            code: self.code,
+            reason: self.reason.clone(),
+            headers,
+            body: self.body.clone(),
+            response_time,
+            initial_age,
+            freshness_lifetime,
+            no_cache: cc.no_cache,
+            vary: self.vary.clone(),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn key(&self) -> &str {
+        &self.key
+    }
+
+    #[inline]
+    pub(crate) fn code(&self) -> u16 {
+        self.code
+    }
+
+    #[inline]
+    pub(crate) fn body(&self) -> &Bytes {
+        &self.body
+    }
+
+    pub(crate) fn size(&self) -> usize {
+        let mut size = self.key.len() + self.reason.len() + self.body.len();
+        self.headers
+            .for_each(|name, value| size += name.as_str().len() + value.to_str().len() + 4);
+        size
+    }
+
+    fn current_age(&self, now: u64) -> u64 {
+        self.initial_age + now.saturating_sub(self.response_time)
+    }
+
+    /// check if the stored response can be used without validation, see rfc9111 Section 4.2
+    pub(crate) fn is_fresh_for(&self, req: &HttpProxyClientRequest, now: u64) -> bool {
+        if self.no_cache {
+            return false;
+        }
+        let age = self.current_age(now);
+        if age >= self.freshness_lifetime {
+            return false;
+        }
+        let req_cc = CacheControl::parse(&req.end_to_end_headers);
+        if req_cc.no_cache {
+            return false;
+        }
+        match req_cc.max_age {
+            Some(max_age) => age <= max_age,
+            None => true,
+        }
+    }
+
+    /// check if the request headers nominated by Vary match, see rfc9111 Section 4.1
+    pub(crate) fn match_vary(&self, req: &HttpProxyClientRequest) -> bool {
+        self.vary
+            .iter()
+            .all(|(name, value)| merged_header_value(&req.end_to_end_headers, name).eq(value))
+    }
+
+    /// set conditional headers to the request, return false if there is no validator
+    pub(crate) fn set_validators(&self, req_headers: &mut HttpHeaderMap) -> bool {
+        let mut has_validator = false;
+        if let Some(etag) = self.headers.get(header::ETAG) {
+            req_headers.insert(header::IF_NONE_MATCH, etag.clone());
+            has_validator = true;
+        }
+        if let Some(last_modified) = self.headers.get(header::LAST_MODIFIED) {
+            req_headers.insert(header::IF_MODIFIED_SINCE, last_modified.clone());
+            has_validator = true;
+        }
+        has_validator
+    }
+
+    pub(crate) fn serialize_header(
+        &self,
+        version: Version,
+        close: bool,
+        header_rewrite: Option<&HttpHeaderRewrite>,
+    ) -> Vec<u8> {
+        let mut buf = Vec::<u8>::with_capacity(self.size() - self.body.len() + 128);
+        let _ = write!(buf, "{:?} {} {}\r\n", version, self.code, self.reason);
+        if let Some(rewrite) = header_rewrite {
+            let mut headers = self.headers.clone();
+            rewrite.apply(&mut headers);
+            headers.for_each(|name, value| value.write_to_buf(name, &mut buf));
+        } else {
+            self.headers
+                .for_each(|name, value| value.write_to_buf(name, &mut buf));
+        }
+        let _ = write!(buf, "Age: {}\r\n", self.current_age(now_secs()));
+        buf.put_slice(g3_http::header::connection_as_bytes(close));
+        buf.put_slice(b"\r\n");
+        buf
+    }
+
+    /// encode as a json meta line followed by the raw body
+    pub(super) fn encode(&self) -> Vec<u8> {
+        let mut headers = Vec::new();
+        self.headers.for_each(|name, value| {
+            headers.push(json!([name.as_str(), value.to_str()]));
+        });
+        let vary = self
+            .vary
+            .iter()
+            .map(|(name, value)| json!([name.as_str(), value]))
+            .collect::<Vec<_>>();
+        let meta = json!({
+            "key": self.key,
+            "code": self.code,
+            "reason": self.reason,
+            "headers": headers,
+            "response_time": self.response_time,
+            "initial_age": self.initial_age,
+            "freshness_lifetime": self.freshness_lifetime,
+            "no_cache": self.no_cache,
+            "vary": vary,
+        });
+
+        let mut buf = meta.to_string().into_bytes();
+        buf.reserve(self.body.len() + 1);
+        buf.push(b'\n');
+        buf.extend_from_slice(&self.body);
+        buf
+    }
+
+    pub(super) fn decode(data: Bytes) -> Option<Self> {
+        let meta_len = memchr::memchr(b'\n', &data)?;
+        let meta: Value = serde_json::from_slice(&data[..meta_len]).ok()?;
+        let body = data.slice(meta_len + 1..);
+
+        let mut headers = HttpHeaderMap::default();
+        for pair in meta.get("headers")?.as_array()? {
+            let name = HeaderName::from_str(pair.get(0)?.as_str()?).ok()?;
+            let value = HttpHeaderValue::from_str(pair.get(1)?.as_str()?).ok()?;
+            headers.append(name, value);
+        }
+        let mut vary = Vec::new();
+        for pair in meta.get("vary")?.as_array()? {
+            let name = HeaderName::from_str(pair.get(0)?.as_str()?).ok()?;
+            let value = pair.get(1)?.as_str().map(|s| s.to_string());
+            vary.push((name, value));
+        }
+
+        Some(HttpForwardCacheEntry {
+            key: meta.get("key")?.as_str()?.to_string(),
+            code: u16::try_from(meta.get("code")?.as_u64()?).ok()?,
+            reason: meta.get("reason")?.as_str()?.to_string(),
+            headers,
+            body,
+            response_time: meta.get("response_time")?.as_u64()?,
+            initial_age: meta.get("initial_age")?.as_u64()?,
+            freshness_lifetime: meta.get("freshness_lifetime")?.as_u64()?,
+            no_cache: meta.get("no_cache")?.as_bool()?,
+            vary,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::Method;
+    use tokio::io::BufReader;
+
+    async fn parse_req(data: &'static [u8]) -> HttpProxyClientRequest {
+        let mut version = Version::HTTP_11;
+        let mut buf = BufReader::new(data);
+        HttpProxyClientRequest::parse_basic(&mut buf, 4096, &mut version)
+            .await
+            .unwrap()
+    }
+
+    async fn parse_rsp(data: &'static [u8]) -> HttpForwardRemoteResponse {
+        let mut buf = BufReader::new(data);
+        HttpForwardRemoteResponse::parse(&mut buf, &Method::GET, true, 4096)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn storable() {
+        let req = parse_req(b"GET / HTTP/1.1\r\nHost: example.net\r\n\r\n").await;
+
+        let rsp = parse_rsp(b"HTTP/1.1 200 OK\r\nCache-Control: max-age=60\r\n\r\n").await;
+        assert!(HttpForwardCacheEntry::storable(&req, &rsp));
+        let rsp = parse_rsp(b"HTTP/1.1 200 OK\r\nCache-Control: private, max-age=60\r\n\r\n").await;
+        assert!(!HttpForwardCacheEntry::storable(&req, &rsp));
+        let rsp =
+            parse_rsp(b"HTTP/1.1 200 OK\r\nCache-Control: max-age=60\r\nSet-Cookie: a=b\r\n\r\n")
+                .await;
+        assert!(!HttpForwardCacheEntry::storable(&req, &rsp));
+        let rsp =
+            parse_rsp(b"HTTP/1.1 200 OK\r\nCache-Control: max-age=60\r\nVary: *\r\n\r\n").await;
+        assert!(!HttpForwardCacheEntry::storable(&req, &rsp));
+        let rsp =
+            parse_rsp(b"HTTP/1.1 206 Partial Content\r\nCache-Control: max-age=60\r\n\r\n").await;
+        assert!(!HttpForwardCacheEntry::storable(&req, &rsp));
+
+        let req =
+            parse_req(b"GET / HTTP/1.1\r\nHost: example.net\r\nAuthorization: Basic YTpi\r\n\r\n")
+                .await;
+        let rsp = parse_rsp(b"HTTP/1.1 200 OK\r\nCache-Control: max-age=60\r\n\r\n").await;
+        assert!(!HttpForwardCacheEntry::storable(&req, &rsp));
+        let rsp = parse_rsp(b"HTTP/1.1 200 OK\r\nCache-Control: public, max-age=60\r\n\r\n").await;
+        assert!(HttpForwardCacheEntry::storable(&req, &rsp));
+    }
+
+    #[tokio::test]
+    async fn encode_decode() {
+        let req =
+            parse_req(b"GET / HTTP/1.1\r\nHost: example.net\r\nAccept-Encoding: gzip\r\n\r\n")
+                .await;
+        let rsp = parse_rsp(
+            b"HTTP/1.1 200 OK\r\nCache-Control: max-age=60\r\nVary: Accept-Encoding, Accept-Language\r\nETag: \"x\"\r\n\r\n",
+        )
+        .await;
+        let mut entry = HttpForwardCacheEntry::new("http://example.net/".to_string(), &req, &rsp);
+        entry.set_body(Bytes::from_static(b"\nbody\n"));
+
+        let decoded = HttpForwardCacheEntry::decode(Bytes::from(entry.encode())).unwrap();
+        assert_eq!(decoded.key(), entry.key());
+        assert_eq!(decoded.code(), 200);
+        assert_eq!(decoded.reason, "OK");
+        assert_eq!(decoded.body().as_ref(), b"\nbody\n");
+        assert_eq!(decoded.response_time, entry.response_time);
+        assert_eq!(decoded.freshness_lifetime, 60);
+        assert_eq!(decoded.vary, entry.vary);
+        assert_eq!(decoded.headers.get(header::ETAG).unwrap().to_str(), "\"x\"");
+
+        assert!(HttpForwardCacheEntry::decode(Bytes::from_static(b"{}\n")).is_none());
+        assert!(HttpForwardCacheEntry::decode(Bytes::from_static(b"no meta line")).is_none());
+    }
+
+    #[tokio::test]
+    async fn freshness_and_vary() {
+        let req =
+            parse_req(b"GET / HTTP/1.1\r\nHost: example.net\r\nAccept-Encoding: gzip\r\n\r\n")
+                .await;
+        let rsp = parse_rsp(
+            b"HTTP/1.1 200 OK\r\nCache-Control: max-age=60\r\nAge: 10\r\nVary: Accept-Encoding\r\n\r\n",
+        )
+        .await;
+        let entry = HttpForwardCacheEntry::new("http://example.net/".to_string(), &req, &rsp);
+        let now = entry.response_time;
+        assert!(entry.is_fresh_for(&req, now));
+        assert!(entry.is_fresh_for(&req, now + 49));
+        assert!(!entry.is_fresh_for(&req, now + 50));
+        assert!(entry.match_vary(&req));
+
+        let req2 =
+            parse_req(b"GET / HTTP/1.1\r\nHost: example.net\r\nCache-Control: max-age=5\r\n\r\n")
+                .await;
+        assert!(!entry.match_vary(&req2));
+        assert!(!entry.is_fresh_for(&req2, now));
+
+        let req3 = parse_req(
+            b"GET / HTTP/1.1\r\nHost: example.net\r\nAccept-Encoding: gzip\r\nCache-Control: no-cache\r\n\r\n",
+        )
+        .await;
+        assert!(entry.match_vary(&req3));
+        assert!(!entry.is_fresh_for(&req3, now));
+    }
+
+    #[tokio::test]
+    async fn refresh() {
+        let req = parse_req(b"GET / HTTP/1.1\r\nHost: example.net\r\n\r\n").await;
+        let rsp = parse_rsp(
+            b"HTTP/1.1 200 OK\r\nCache-Control: no-cache\r\nETag: \"v1\"\r\nLast-Modified: Mon, 01 Jan 2024 00:00:00 GMT\r\nX-Old: 1\r\nContent-Length: 2\r\n\r\n",
+        )
+        .await;
+        let mut entry = HttpForwardCacheEntry::new("http://example.net/".to_string(), &req, &rsp);
+        entry.set_body(Bytes::from_static(b"ok"));
+        assert!(!entry.is_fresh_for(&req, entry.response_time));
+
+        let mut headers = HttpHeaderMap::default();
+        assert!(entry.set_validators(&mut headers));
+        assert_eq!(
+            headers.get(header::IF_NONE_MATCH).unwrap().to_str(),
+            "\"v1\""
+        );
+        assert!(headers.contains_key(header::IF_MODIFIED_SINCE));
+
+        let rsp = parse_rsp(
+            b"HTTP/1.1 304 Not Modified\r\nCache-Control: max-age=300\r\nETag: \"v1\"\r\n\r\n",
+        )
+        .await;
+        let refreshed = entry.refresh(&rsp);
+        assert!(refreshed.is_fresh_for(&req, refreshed.response_time));
+        assert_eq!(refreshed.body().as_ref(), b"ok");
+        assert_eq!(
+            refreshed
+                .headers
+                .get(header::CONTENT_LENGTH)
+                .unwrap()
+                .to_str(),
+            "2"
+        );
+        assert!(refreshed.headers.contains_key("x-old"));
+        assert_eq!(
+            refreshed
+                .headers
+                .get(header::CACHE_CONTROL)
+                .unwrap()
+                .to_str(),
+            "max-age=300"
+        );
+    }
+}
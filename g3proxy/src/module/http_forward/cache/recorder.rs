@@ -0,0 +1,69 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::io;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use bytes::Bytes;
+use tokio::io::{AsyncRead, ReadBuf};
+
+/// record the response body while it's been sent to the client
+pub(crate) struct HttpForwardCacheRecorder<'a, R> {
+    inner: &'a mut R,
+    data: Option<Vec<u8>>,
+    max_size: usize,
+}
+
+impl<'a, R> HttpForwardCacheRecorder<'a, R> {
+    pub(crate) fn new(inner: &'a mut R, record: bool, max_size: usize) -> Self {
+        HttpForwardCacheRecorder {
+            inner,
+            data: record.then(Vec::new),
+            max_size,
+        }
+    }
+
+    pub(crate) fn into_body(self) -> Option<Bytes> {
+        self.data.map(Bytes::from)
+    }
+}
+
+impl<R> AsyncRead for HttpForwardCacheRecorder<'_, R>
+where
+    R: AsyncRead + Unpin,
+{
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let offset = buf.filled().len();
+        ready!(Pin::new(&mut *self.inner).poll_read(cx, buf))?;
+
+        let max_size = self.max_size;
+        if let Some(data) = &mut self.data {
+            let read = &buf.filled()[offset..];
+            if data.len() + read.len() > max_size {
+                // too large to be cached
+                self.data = None;
+            } else {
+                data.extend_from_slice(read);
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+}
@@ -0,0 +1,416 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::hash::Hasher;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use anyhow::anyhow;
+use bytes::Bytes;
+use fnv::FnvHasher;
+use log::warn;
+use lru::LruCache;
+
+use super::HttpForwardCacheEntry;
+use crate::config::http_forward_cache::HttpForwardCacheConfig;
+
+struct MemoryStore {
+    lru: LruCache<String, Arc<HttpForwardCacheEntry>>,
+    size: usize,
+    max_size: usize,
+}
+
+impl MemoryStore {
+    fn new(max_size: usize) -> Self {
+        MemoryStore {
+            lru: LruCache::unbounded(),
+            size: 0,
+            max_size,
+        }
+    }
+
+    fn get(&mut self, key: &str) -> Option<Arc<HttpForwardCacheEntry>> {
+        self.lru.get(key).cloned()
+    }
+
+    fn insert(&mut self, entry: Arc<HttpForwardCacheEntry>) {
+        let entry_size = entry.size();
+        if entry_size > self.max_size {
+            self.remove(entry.key());
+            return;
+        }
+
+        if let Some((_, old)) = self.lru.push(entry.key().to_string(), entry) {
+            self.size -= old.size();
+        }
+        self.size += entry_size;
+        while self.size > self.max_size {
+            let Some((_, evicted)) = self.lru.pop_lru() else {
+                break;
+            };
+            self.size -= evicted.size();
+        }
+    }
+
+    fn remove(&mut self, key: &str) {
+        if let Some(old) = self.lru.pop(key) {
+            self.size -= old.size();
+        }
+    }
+}
+
+struct DiskIndex {
+    lru: LruCache<u64, u64>,
+    size: u64,
+}
+
+struct DiskStore {
+    dir: PathBuf,
+    max_size: u64,
+    index: Mutex<DiskIndex>,
+    tmp_seq: AtomicU64,
+}
+
+impl DiskStore {
+    fn new(dir: &Path, max_size: u64) -> anyhow::Result<Self> {
+        let mut index = DiskIndex {
+            lru: LruCache::unbounded(),
+            size: 0,
+        };
+        let read_dir = std::fs::read_dir(dir)
+            .map_err(|e| anyhow!("failed to read cache dir {}: {e}", dir.display()))?;
+        for entry in read_dir.flatten() {
+            let file_name = entry.file_name();
+            if file_name.to_str().is_some_and(|s| s.ends_with(".tmp")) {
+                // left by unfinished writes
+                let _ = std::fs::remove_file(entry.path());
+                continue;
+            }
+            let Some(hash) = file_name
+                .to_str()
+                .and_then(|s| u64::from_str_radix(s, 16).ok())
+            else {
+                continue;
+            };
+            let Ok(meta) = entry.metadata() else {
+                continue;
+            };
+            if meta.is_file() {
+                index.lru.push(hash, meta.len());
+                index.size += meta.len();
+            }
+        }
+
+        Ok(DiskStore {
+            dir: dir.to_path_buf(),
+            max_size,
+            index: Mutex::new(index),
+            tmp_seq: AtomicU64::new(0),
+        })
+    }
+
+    fn key_hash(key: &str) -> u64 {
+        let mut hasher = FnvHasher::default();
+        hasher.write(key.as_bytes());
+        hasher.finish()
+    }
+
+    fn file_path(&self, hash: u64) -> PathBuf {
+        self.dir.join(format!("{hash:016x}"))
+    }
+
+    async fn get(&self, key: &str) -> Option<HttpForwardCacheEntry> {
+        let hash = DiskStore::key_hash(key);
+        self.index.lock().unwrap().lru.get(&hash)?;
+
+        let path = self.file_path(hash);
+        match tokio::fs::read(&path).await {
+            Ok(data) => match HttpForwardCacheEntry::decode(Bytes::from(data)) {
+                // the key is checked as the hash value may conflict
+                Some(entry) if entry.key() == key => Some(entry),
+                Some(_) => None,
+                None => {
+                    warn!("invalid http forward cache file {}", path.display());
+                    self.remove(hash).await;
+                    None
+                }
+            },
+            Err(e) => {
+                warn!(
+                    "failed to read http forward cache file {}: {e}",
+                    path.display()
+                );
+                self.remove(hash).await;
+                None
+            }
+        }
+    }
+
+    async fn insert(&self, entry: Arc<HttpForwardCacheEntry>) {
+        let hash = DiskStore::key_hash(entry.key());
+        let data = entry.encode();
+        drop(entry);
+
+        let path = self.file_path(hash);
+        // concurrent writes for the same key should use different temp files
+        let seq = self.tmp_seq.fetch_add(1, Ordering::Relaxed);
+        let tmp_path = self.dir.join(format!("{hash:016x}.{seq}.tmp"));
+        if let Err(e) = tokio::fs::write(&tmp_path, &data).await {
+            warn!(
+                "failed to write http forward cache file {}: {e}",
+                tmp_path.display()
+            );
+            let _ = tokio::fs::remove_file(&tmp_path).await;
+            return;
+        }
+        if let Err(e) = tokio::fs::rename(&tmp_path, &path).await {
+            warn!(
+                "failed to rename http forward cache file {}: {e}",
+                path.display()
+            );
+            let _ = tokio::fs::remove_file(&tmp_path).await;
+            return;
+        }
+
+        let mut evicted = Vec::new();
+        {
+            let mut index = self.index.lock().unwrap();
+            if let Some((_, old_size)) = index.lru.push(hash, data.len() as u64) {
+                index.size -= old_size;
+            }
+            index.size += data.len() as u64;
+            while index.size > self.max_size {
+                let Some((hash, size)) = index.lru.pop_lru() else {
+                    break;
+                };
+                index.size -= size;
+                evicted.push(hash);
+            }
+        }
+
+        for hash in evicted {
+            let _ = tokio::fs::remove_file(self.file_path(hash)).await;
+        }
+    }
+
+    async fn remove(&self, hash: u64) {
+        {
+            let mut index = self.index.lock().unwrap();
+            if let Some(size) = index.lru.pop(&hash) {
+                index.size -= size;
+            }
+        }
+        let _ = tokio::fs::remove_file(self.file_path(hash)).await;
+    }
+}
+
+pub(crate) struct HttpForwardCache {
+    config: HttpForwardCacheConfig,
+    memory: Mutex<MemoryStore>,
+    disk: Option<Arc<DiskStore>>,
+}
+
+impl HttpForwardCache {
+    pub(crate) fn new(config: &HttpForwardCacheConfig) -> anyhow::Result<Self> {
+        let disk = match &config.disk_directory {
+            Some(dir) => Some(Arc::new(DiskStore::new(dir, config.disk_size)?)),
+            None => None,
+        };
+        Ok(HttpForwardCache {
+            config: config.clone(),
+            memory: Mutex::new(MemoryStore::new(config.memory_size)),
+            disk,
+        })
+    }
+
+    #[inline]
+    pub(crate) fn config(&self) -> &HttpForwardCacheConfig {
+        &self.config
+    }
+
+    #[inline]
+    pub(crate) fn max_object_size(&self) -> usize {
+        self.config.max_object_size
+    }
+
+    pub(crate) async fn get(&self, key: &str) -> Option<Arc<HttpForwardCacheEntry>> {
+        if let Some(entry) = self.memory.lock().unwrap().get(key) {
+            return Some(entry);
+        }
+
+        let disk = self.disk.as_ref()?;
+        let entry = Arc::new(disk.get(key).await?);
+        // promote to the memory tier
+        self.memory.lock().unwrap().insert(entry.clone());
+        Some(entry)
+    }
+
+    pub(crate) fn insert(&self, entry: Arc<HttpForwardCacheEntry>) {
+        self.memory.lock().unwrap().insert(entry.clone());
+        if let Some(disk) = &self.disk {
+            let disk = disk.clone();
+            tokio::spawn(async move { disk.insert(entry).await });
+        }
+    }
+
+    pub(crate) fn remove(&self, key: &str) {
+        self.memory.lock().unwrap().remove(key);
+        if let Some(disk) = &self.disk {
+            let disk = disk.clone();
+            let hash = DiskStore::key_hash(key);
+            tokio::spawn(async move { disk.remove(hash).await });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::Method;
+    use tokio::io::BufReader;
+
+    use g3_http::client::HttpForwardRemoteResponse;
+    use g3_http::server::HttpProxyClientRequest;
+
+    async fn new_entry(key: &str, body: &'static [u8]) -> HttpForwardCacheEntry {
+        let mut version = http::Version::HTTP_11;
+        let mut req_buf = BufReader::new(&b"GET / HTTP/1.1\r\nHost: example.net\r\n\r\n"[..]);
+        let req = HttpProxyClientRequest::parse_basic(&mut req_buf, 4096, &mut version)
+            .await
+            .unwrap();
+        let mut rsp_buf = BufReader::new(
+            &b"HTTP/1.1 200 OK\r\nCache-Control: max-age=60\r\nContent-Length: 0\r\n\r\n"[..],
+        );
+        let rsp = HttpForwardRemoteResponse::parse(&mut rsp_buf, &Method::GET, true, 4096)
+            .await
+            .unwrap();
+        let mut entry = HttpForwardCacheEntry::new(key.to_string(), &req, &rsp);
+        entry.set_body(Bytes::from_static(body));
+        entry
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("g3proxy-cache-test-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[tokio::test]
+    async fn memory_evict() {
+        let e1 = Arc::new(new_entry("http://a/1", b"0123456789").await);
+        let e2 = Arc::new(new_entry("http://a/2", b"0123456789").await);
+        let mut store = MemoryStore::new(e1.size() + e2.size() - 1);
+
+        store.insert(e1.clone());
+        assert!(store.get("http://a/1").is_some());
+        store.insert(e2.clone());
+        assert!(store.get("http://a/1").is_none());
+        assert!(store.get("http://a/2").is_some());
+        assert_eq!(store.size, e2.size());
+
+        store.remove("http://a/2");
+        assert_eq!(store.size, 0);
+
+        let big = Arc::new(new_entry("http://a/3", &[0u8; 256]).await);
+        store.insert(big);
+        assert!(store.get("http://a/3").is_none());
+    }
+
+    #[tokio::test]
+    async fn disk_store() {
+        let dir = temp_dir("disk");
+        let store = DiskStore::new(&dir, 1 << 20).unwrap();
+
+        let entry = Arc::new(new_entry("http://a/1", b"hello").await);
+        store.insert(entry).await;
+        let got = store.get("http://a/1").await.unwrap();
+        assert_eq!(got.body().as_ref(), b"hello");
+        assert!(store.get("http://a/2").await.is_none());
+
+        // the index is rebuilt from the directory
+        let store = DiskStore::new(&dir, 1 << 20).unwrap();
+        assert!(store.get("http://a/1").await.is_some());
+
+        store.remove(DiskStore::key_hash("http://a/1")).await;
+        assert!(store.get("http://a/1").await.is_none());
+        assert_eq!(store.index.lock().unwrap().size, 0);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn disk_concurrent_insert() {
+        let dir = temp_dir("concurrent");
+        let store = Arc::new(DiskStore::new(&dir, 1 << 20).unwrap());
+
+        let mut tasks = Vec::new();
+        for _ in 0..8 {
+            let store = store.clone();
+            let entry = Arc::new(new_entry("http://a/1", b"same body").await);
+            tasks.push(tokio::spawn(async move { store.insert(entry).await }));
+        }
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        let got = store.get("http://a/1").await.unwrap();
+        assert_eq!(got.body().as_ref(), b"same body");
+        let files = std::fs::read_dir(&dir).unwrap().count();
+        assert_eq!(files, 1);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn disk_evict() {
+        let dir = temp_dir("evict");
+        let e1 = Arc::new(new_entry("http://a/1", b"0123456789").await);
+        let size = e1.encode().len() as u64;
+        let store = DiskStore::new(&dir, size * 2 - 1).unwrap();
+
+        store.insert(e1).await;
+        store
+            .insert(Arc::new(new_entry("http://a/2", b"0123456789").await))
+            .await;
+        assert!(store.get("http://a/1").await.is_none());
+        assert!(store.get("http://a/2").await.is_some());
+        assert!(!store.file_path(DiskStore::key_hash("http://a/1")).exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn promote_from_disk() {
+        let dir = temp_dir("promote");
+        let config = HttpForwardCacheConfig {
+            disk_directory: Some(dir.clone()),
+            ..Default::default()
+        };
+        let disk = DiskStore::new(&dir, config.disk_size).unwrap();
+        disk.insert(Arc::new(new_entry("http://a/1", b"hello").await))
+            .await;
+
+        let cache = HttpForwardCache::new(&config).unwrap();
+        assert!(cache.memory.lock().unwrap().get("http://a/1").is_none());
+        assert!(cache.get("http://a/1").await.is_some());
+        assert!(cache.memory.lock().unwrap().get("http://a/1").is_some());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -14,12 +14,17 @@
  * limitations under the License.
  */
 
+mod cache;
 mod connection;
 mod context;
 mod response;
 mod stats;
 mod task;
 
+pub(crate) use cache::{
+    HttpForwardCache, HttpForwardCacheContext, HttpForwardCacheEntry, HttpForwardCacheRecorder,
+    HttpForwardCacheSnapshot, HttpForwardCacheStats,
+};
 pub(crate) use connection::{
     send_req_header_to_origin, send_req_header_via_proxy, BoxHttpForwardConnection,
     BoxHttpForwardReader, BoxHttpForwardWriter, HttpConnectionEofPoller, HttpForwardRead,
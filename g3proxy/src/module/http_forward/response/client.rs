@@ -225,10 +225,12 @@ impl HttpProxyClientResponse {
         should_close: bool,
     ) -> Self {
         match e {
-            FtpConnectError::ConnectIoError(e) => {
+            FtpConnectError::ConnectIoError(e) | FtpConnectError::TlsHandshakeFailed(e) => {
                 HttpProxyClientResponse::from_tcp_connect_error(e, version, should_close)
             }
-            FtpConnectError::ConnectTimedOut | FtpConnectError::GreetingTimedOut => {
+            FtpConnectError::ConnectTimedOut
+            | FtpConnectError::TlsHandshakeTimedOut
+            | FtpConnectError::GreetingTimedOut => {
                 HttpProxyClientResponse::from_standard(StatusCode::GATEWAY_TIMEOUT, version, true)
             }
             FtpConnectError::GreetingFailed(_)
@@ -405,11 +407,13 @@ impl HttpProxyClientResponse {
             ServerTaskError::CanceledAsUserBlocked => {
                 HttpProxyClientResponse::from_standard(StatusCode::FORBIDDEN, version, true)
             }
-            ServerTaskError::CanceledAsServerQuit => HttpProxyClientResponse::from_standard(
-                StatusCode::INTERNAL_SERVER_ERROR,
-                version,
-                true,
-            ),
+            ServerTaskError::CanceledAsServerQuit | ServerTaskError::CanceledAsTaskKilled => {
+                HttpProxyClientResponse::from_standard(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    version,
+                    true,
+                )
+            }
             ServerTaskError::ClientTcpReadFailed(_)
             | ServerTaskError::ClientTcpWriteFailed(_)
             | ServerTaskError::ClientUdpRecvFailed(_)
@@ -528,22 +532,36 @@ impl HttpProxyClientResponse {
              </html>\n"
         );
 
-        let mut header = Vec::<u8>::with_capacity(Self::RESPONSE_BUFFER_SIZE);
+        self.reply_with_body(writer, &mime::TEXT_HTML, body.as_bytes())
+            .await
+    }
+
+    pub(crate) async fn reply_with_body<W>(
+        &self,
+        writer: &mut W,
+        content_type: &Mime,
+        body: &[u8],
+    ) -> io::Result<()>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        let mut header = Vec::<u8>::with_capacity(Self::RESPONSE_BUFFER_SIZE + body.len());
         write!(
             header,
-            "{:?} {} {reason}\r\n",
+            "{:?} {} {}\r\n",
             self.version,
             self.status.as_str(),
+            self.canonical_reason(),
         )?;
         for line in &self.extra_headers {
             header.extend_from_slice(line.as_bytes());
         }
-        header.extend_from_slice(g3_http::header::content_type(&mime::TEXT_HTML).as_bytes());
+        header.extend_from_slice(g3_http::header::content_type(content_type).as_bytes());
         header.extend_from_slice(g3_http::header::content_length(body.len() as u64).as_bytes());
         header.extend_from_slice(g3_http::header::connection_as_bytes(self.close));
         header.extend_from_slice(b"\r\n");
         // append body
-        header.extend_from_slice(body.as_bytes());
+        header.extend_from_slice(body);
 
         writer.write_all_flush(header.as_ref()).await?;
         Ok(())
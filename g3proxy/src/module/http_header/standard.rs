@@ -18,7 +18,14 @@ use base64::prelude::*;
 
 pub(crate) fn proxy_authorization_basic_pass(userid: &str) -> String {
     format!(
-        "Proxy-Authorization: Basic {}\r\n",
+        "Proxy-Authorization: {}\r\n",
+        proxy_authorization_basic_pass_value(userid)
+    )
+}
+
+pub(crate) fn proxy_authorization_basic_pass_value(userid: &str) -> String {
+    format!(
+        "Basic {}",
         BASE64_STANDARD.encode(format!("{userid}:{}", crate::build::PKG_NAME))
     )
 }
@@ -17,6 +17,7 @@
 pub(crate) mod ftp_over_http;
 pub(crate) mod http_forward;
 pub(crate) mod http_header;
+pub(crate) mod tcp_bind;
 pub(crate) mod tcp_connect;
 pub(crate) mod udp_connect;
 pub(crate) mod udp_relay;
@@ -0,0 +1,67 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::io;
+
+use thiserror::Error;
+
+use g3_socks::v5::Socks5Reply;
+
+use crate::serve::{ServerTaskError, ServerTaskForbiddenError};
+
+#[derive(Error, Debug)]
+pub(crate) enum TcpBindError {
+    #[error("method is not available")]
+    MethodUnavailable,
+    #[error("forbidden address family")]
+    ForbiddenAddressFamily,
+    #[error("setup socket failed: {0:?}")]
+    SetupSocketFailed(io::Error),
+    #[error("accept failed: {0:?}")]
+    AcceptFailed(io::Error),
+    #[error("forbidden remote address")]
+    ForbiddenRemoteAddress,
+}
+
+impl From<TcpBindError> for ServerTaskError {
+    fn from(e: TcpBindError) -> Self {
+        match e {
+            TcpBindError::MethodUnavailable => {
+                ServerTaskError::ForbiddenByRule(ServerTaskForbiddenError::MethodUnavailable)
+            }
+            TcpBindError::ForbiddenAddressFamily | TcpBindError::ForbiddenRemoteAddress => {
+                ServerTaskError::ForbiddenByRule(ServerTaskForbiddenError::IpBlocked)
+            }
+            TcpBindError::SetupSocketFailed(_) => {
+                ServerTaskError::InternalServerError("failed to setup local listen socket")
+            }
+            TcpBindError::AcceptFailed(e) => ServerTaskError::UpstreamReadFailed(e),
+        }
+    }
+}
+
+impl From<&TcpBindError> for Socks5Reply {
+    fn from(e: &TcpBindError) -> Self {
+        match e {
+            TcpBindError::MethodUnavailable
+            | TcpBindError::ForbiddenAddressFamily
+            | TcpBindError::ForbiddenRemoteAddress => Socks5Reply::ForbiddenByRule,
+            TcpBindError::SetupSocketFailed(_) | TcpBindError::AcceptFailed(_) => {
+                Socks5Reply::GeneralServerFailure
+            }
+        }
+    }
+}
@@ -0,0 +1,47 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::net::SocketAddr;
+
+use async_trait::async_trait;
+
+use g3_types::net::UpstreamAddr;
+
+use crate::module::tcp_connect::{TcpConnectTaskNotes, TcpConnection};
+use crate::serve::ServerTaskNotes;
+
+mod error;
+pub(crate) use error::TcpBindError;
+
+pub(crate) struct TcpBindTaskConf<'a> {
+    /// the expected peer address sent in the BIND request
+    pub(crate) peer: &'a UpstreamAddr,
+}
+
+#[async_trait]
+pub(crate) trait TcpBindListener {
+    fn listen_addr(&self) -> SocketAddr;
+
+    /// wait for the remote peer, the `next` field of `tcp_notes` will be set to the peer address
+    async fn accept(
+        &mut self,
+        tcp_notes: &mut TcpConnectTaskNotes,
+        task_notes: &ServerTaskNotes,
+    ) -> Result<TcpConnection, TcpBindError>;
+}
+
+pub(crate) type BoxTcpBindListener = Box<dyn TcpBindListener + Send + Sync>;
+pub(crate) type TcpBindSetupResult = Result<BoxTcpBindListener, TcpBindError>;
@@ -22,6 +22,7 @@ use g3_http::connect::HttpConnectError;
 use g3_resolver::ResolveError;
 use g3_socks::v5::Socks5Reply;
 use g3_socks::SocksConnectError;
+use g3_ssh_client::{SshChannelOpenError, SshError};
 use g3_types::net::{ConnectError, ProxyProtocolEncodeError};
 
 use crate::serve::{ServerTaskError, ServerTaskForbiddenError};
@@ -230,3 +231,34 @@ impl From<HttpConnectError> for TcpConnectError {
         }
     }
 }
+
+impl From<SshError> for TcpConnectError {
+    fn from(e: SshError) -> Self {
+        match e {
+            SshError::IoFailed(e) => TcpConnectError::NegotiationReadFailed(e),
+            SshError::NoCommonAlgorithm(_)
+            | SshError::InvalidHostKeySignature
+            | SshError::HostKeyNotTrusted(_)
+            | SshError::HostKeyRevoked(_)
+            | SshError::AuthFailed
+            | SshError::Disconnected(_, _) => {
+                TcpConnectError::NegotiationRejected(format!("ssh negotiation failed: {e}"))
+            }
+            SshError::KeepaliveTimeout => TcpConnectError::NegotiationPeerTimeout,
+            SshError::ProtocolError(_) => TcpConnectError::NegotiationProtocolErr,
+        }
+    }
+}
+
+impl From<SshChannelOpenError> for TcpConnectError {
+    fn from(e: SshChannelOpenError) -> Self {
+        match e {
+            SshChannelOpenError::SessionClosed => TcpConnectError::NegotiationReadFailed(
+                io::Error::new(io::ErrorKind::ConnectionReset, "ssh session closed"),
+            ),
+            SshChannelOpenError::Rejected(code, reason) => TcpConnectError::NegotiationRejected(
+                format!("rejected by ssh server with reason {code}: {reason}"),
+            ),
+        }
+    }
+}
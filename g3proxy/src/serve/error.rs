@@ -127,6 +127,8 @@ pub(crate) enum ServerTaskError {
     CanceledAsUserBlocked,
     #[error("canceled as server quit")]
     CanceledAsServerQuit,
+    #[error("canceled as killed by control")]
+    CanceledAsTaskKilled,
     #[error("idle after {0:?} x {1}")]
     Idle(Duration, i32),
     #[error("{0} interception error: {1}")]
@@ -172,6 +174,7 @@ impl ServerTaskError {
             ServerTaskError::ClosedEarlyByClient => "ClosedEarlyByClient",
             ServerTaskError::CanceledAsUserBlocked => "CanceledAsUserBlocked",
             ServerTaskError::CanceledAsServerQuit => "CanceledAsServerQuit",
+            ServerTaskError::CanceledAsTaskKilled => "CanceledAsTaskKilled",
             ServerTaskError::Idle(_, _) => "Idle",
             ServerTaskError::InterceptionError(_, _) => "InterceptionError",
             ServerTaskError::Finished => "Finished",
@@ -325,7 +328,10 @@ impl From<HttpResponseParseError> for ServerTaskError {
 impl From<FtpConnectError<TcpConnectError>> for ServerTaskError {
     fn from(e: FtpConnectError<TcpConnectError>) -> Self {
         match e {
-            FtpConnectError::ConnectIoError(e) => ServerTaskError::from(e),
+            FtpConnectError::ConnectIoError(e) | FtpConnectError::TlsHandshakeFailed(e) => {
+                ServerTaskError::from(e)
+            }
+            FtpConnectError::TlsHandshakeTimedOut => ServerTaskError::UpstreamTlsHandshakeTimeout,
             FtpConnectError::ConnectTimedOut => {
                 ServerTaskError::UpstreamAppTimeout("ftp connect timed out")
             }
@@ -21,6 +21,7 @@ use std::time::Duration;
 use anyhow::{anyhow, Context};
 use arc_swap::{ArcSwap, ArcSwapOption};
 use async_trait::async_trait;
+use bytes::BytesMut;
 use log::debug;
 #[cfg(feature = "quic")]
 use quinn::Connection;
@@ -32,17 +33,21 @@ use tokio_rustls::{server::TlsStream, TlsAcceptor};
 
 use g3_daemon::listen::{AcceptQuicServer, AcceptTcpServer, ListenStats, ListenTcpRuntime};
 use g3_daemon::server::{BaseServer, ClientConnectionInfo, ServerReloadCommand};
-use g3_io_ext::AsyncStream;
+use g3_geoip_types::IpLocation;
+use g3_io_ext::{AsyncStream, OnceBufReader};
+use g3_ip_locate::IpLocationServiceHandle;
 use g3_openssl::SslStream;
 use g3_types::acl::{AclAction, AclNetworkRule};
-use g3_types::acl_set::AclDstHostRuleSet;
+use g3_types::acl_set::{AclClientGeoRuleSet, AclDstHostRuleSet};
 use g3_types::metrics::MetricsName;
 use g3_types::net::{
     AlpnProtocol, OpensslClientConfig, OpensslTicketKey, RollingTicketer, RustlsServerConnectionExt,
 };
 
+#[cfg(feature = "quic")]
+use super::task::H3ProxyConnectionTask;
 use super::task::{
-    CommonTaskContext, HttpProxyPipelineReaderTask, HttpProxyPipelineStats,
+    CommonTaskContext, H2ProxyConnectionTask, HttpProxyPipelineReaderTask, HttpProxyPipelineStats,
     HttpProxyPipelineWriterTask,
 };
 use super::HttpProxyServerStats;
@@ -51,6 +56,7 @@ use crate::auth::UserGroup;
 use crate::config::server::http_proxy::HttpProxyServerConfig;
 use crate::config::server::{AnyServerConfig, ServerConfig};
 use crate::escape::ArcEscaper;
+use crate::module::http_forward::HttpForwardCache;
 use crate::serve::{
     ArcServer, ArcServerStats, Server, ServerInternal, ServerQuitPolicy, ServerStats, WrapArcServer,
 };
@@ -63,7 +69,10 @@ pub(crate) struct HttpProxyServer {
     tls_acceptor: Option<TlsAcceptor>,
     tls_accept_timeout: Duration,
     tls_client_config: Arc<OpensslClientConfig>,
+    http_forward_cache: Option<Arc<HttpForwardCache>>,
     ingress_net_filter: Option<AclNetworkRule>,
+    ingress_geo_filter: Option<AclClientGeoRuleSet>,
+    ip_locate_handle: Option<Arc<IpLocationServiceHandle>>,
     dst_host_filter: Option<Arc<AclDstHostRuleSet>>,
     reload_sender: broadcast::Sender<ServerReloadCommand>,
     task_logger: Logger,
@@ -81,17 +90,22 @@ impl HttpProxyServer {
         server_stats: Arc<HttpProxyServerStats>,
         listen_stats: Arc<ListenStats>,
         tls_rolling_ticketer: Option<Arc<RollingTicketer<OpensslTicketKey>>>,
+        http_forward_cache: Option<Arc<HttpForwardCache>>,
+        ip_locate_handle: Option<Arc<IpLocationServiceHandle>>,
         version: usize,
     ) -> anyhow::Result<HttpProxyServer> {
         let reload_sender = crate::serve::new_reload_notify_channel();
 
         let mut tls_accept_timeout = Duration::from_secs(10);
         let tls_acceptor = if let Some(tls_config_builder) = &config.server_tls_config {
+            let mut alpn_protocols = Vec::with_capacity(3);
+            if config.h2_config.is_some() {
+                alpn_protocols.push(AlpnProtocol::Http2);
+            }
+            alpn_protocols.push(AlpnProtocol::Http11);
+            alpn_protocols.push(AlpnProtocol::Http10);
             let tls_server_config = tls_config_builder
-                .build_with_alpn_protocols(
-                    Some(vec![AlpnProtocol::Http11, AlpnProtocol::Http10]),
-                    tls_rolling_ticketer.clone(),
-                )
+                .build_with_alpn_protocols(Some(alpn_protocols), tls_rolling_ticketer.clone())
                 .context("failed to build tls server config")?;
             tls_accept_timeout = tls_server_config.accept_timeout;
             Some(TlsAcceptor::from(tls_server_config.driver))
@@ -108,6 +122,10 @@ impl HttpProxyServer {
             .ingress_net_filter
             .as_ref()
             .map(|builder| builder.build());
+        let ingress_geo_filter = config
+            .ingress_geo_filter
+            .as_ref()
+            .map(|builder| builder.build());
 
         let dst_host_filter = config
             .dst_host_filter
@@ -131,7 +149,10 @@ impl HttpProxyServer {
             tls_acceptor,
             tls_accept_timeout,
             tls_client_config: Arc::new(tls_client_config),
+            http_forward_cache,
             ingress_net_filter,
+            ingress_geo_filter,
+            ip_locate_handle,
             dst_host_filter,
             reload_sender,
             task_logger,
@@ -159,8 +180,31 @@ impl HttpProxyServer {
             None
         };
 
-        let server =
-            HttpProxyServer::new(config, server_stats, listen_stats, tls_rolling_ticketer, 1)?;
+        let http_forward_cache = if let Some(c) = &config.http_forward_cache {
+            let cache = HttpForwardCache::new(c).context("failed to create http forward cache")?;
+            Some(Arc::new(cache))
+        } else {
+            None
+        };
+
+        let ip_locate_handle = if let Some(c) = &config.ip_locate_service {
+            let handle = c
+                .spawn_ip_locate_agent()
+                .context("failed to spawn ip locate agent")?;
+            Some(Arc::new(handle))
+        } else {
+            None
+        };
+
+        let server = HttpProxyServer::new(
+            config,
+            server_stats,
+            listen_stats,
+            tls_rolling_ticketer,
+            http_forward_cache,
+            ip_locate_handle,
+            1,
+        )?;
         Ok(Arc::new(server))
     }
 
@@ -181,11 +225,35 @@ impl HttpProxyServer {
                 None
             };
 
+            // keep the cached objects if the cache config is not changed
+            let http_forward_cache = match (&self.http_forward_cache, &config.http_forward_cache) {
+                (Some(cache), Some(c)) if cache.config().eq(c) => Some(cache.clone()),
+                (_, Some(c)) => {
+                    let cache =
+                        HttpForwardCache::new(c).context("failed to create http forward cache")?;
+                    Some(Arc::new(cache))
+                }
+                (_, None) => None,
+            };
+
+            let ip_locate_handle = if self.config.ip_locate_service.eq(&config.ip_locate_service) {
+                self.ip_locate_handle.clone()
+            } else if let Some(c) = &config.ip_locate_service {
+                let handle = c
+                    .spawn_ip_locate_agent()
+                    .context("failed to spawn ip locate agent")?;
+                Some(Arc::new(handle))
+            } else {
+                None
+            };
+
             let server = HttpProxyServer::new(
                 config,
                 server_stats,
                 listen_stats,
                 tls_rolling_ticketer,
+                http_forward_cache,
+                ip_locate_handle,
                 self.reload_version + 1,
             )?;
             Ok(server)
@@ -198,14 +266,20 @@ impl HttpProxyServer {
         }
     }
 
-    fn get_common_task_context(&self, cc_info: ClientConnectionInfo) -> Arc<CommonTaskContext> {
+    fn get_common_task_context(
+        &self,
+        cc_info: ClientConnectionInfo,
+        client_location: Option<Arc<IpLocation>>,
+    ) -> Arc<CommonTaskContext> {
         Arc::new(CommonTaskContext {
             server_config: Arc::clone(&self.config),
             server_stats: Arc::clone(&self.server_stats),
             server_quit_policy: Arc::clone(&self.quit_policy),
             escaper: self.escaper.load().as_ref().clone(),
             cc_info,
+            client_location,
             tls_client_config: self.tls_client_config.clone(),
+            http_forward_cache: self.http_forward_cache.clone(),
             task_logger: self.task_logger.clone(),
             dst_host_filter: self.dst_host_filter.clone(),
         })
@@ -228,17 +302,41 @@ impl HttpProxyServer {
         false
     }
 
+    async fn locate_client(&self, client_addr: SocketAddr) -> Option<Arc<IpLocation>> {
+        let handle = self.ip_locate_handle.as_ref()?;
+        handle.fetch(client_addr.ip()).await
+    }
+
+    fn drop_by_location(&self, location: Option<&IpLocation>) -> bool {
+        let Some(filter) = &self.ingress_geo_filter else {
+            return false;
+        };
+        let action = match location {
+            Some(location) => filter.check(location).1,
+            None => filter.missed_action(),
+        };
+        if action.forbid_early() {
+            self.server_stats.forbidden.add_src_geo_blocked();
+            return true;
+        }
+        false
+    }
+
     fn audit_context(&self) -> AuditContext {
         AuditContext::new(self.audit_handle.load_full())
     }
 
-    async fn spawn_stream_task<T>(&self, stream: T, cc_info: ClientConnectionInfo)
-    where
+    async fn spawn_stream_task<T>(
+        &self,
+        stream: T,
+        cc_info: ClientConnectionInfo,
+        client_location: Option<Arc<IpLocation>>,
+    ) where
         T: AsyncStream,
         T::R: AsyncRead + Send + Sync + Unpin + 'static,
         T::W: AsyncWrite + Send + Sync + Unpin + 'static,
     {
-        let ctx = self.get_common_task_context(cc_info);
+        let ctx = self.get_common_task_context(cc_info, client_location);
         let pipeline_stats = Arc::new(HttpProxyPipelineStats::default());
         let (task_sender, task_receiver) = mpsc::channel(ctx.server_config.pipeline_size);
 
@@ -259,14 +357,73 @@ impl HttpProxyServer {
         w_task.into_running().await
     }
 
+    async fn spawn_h2_task<T>(
+        &self,
+        stream: T,
+        cc_info: ClientConnectionInfo,
+        client_location: Option<Arc<IpLocation>>,
+    ) where
+        T: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+    {
+        let ctx = self.get_common_task_context(cc_info, client_location);
+        let task =
+            H2ProxyConnectionTask::new(ctx, self.audit_context(), self.user_group.load_full());
+        task.into_running(stream).await
+    }
+
+    async fn spawn_plain_stream_task(
+        &self,
+        mut stream: TcpStream,
+        cc_info: ClientConnectionInfo,
+        client_location: Option<Arc<IpLocation>>,
+    ) {
+        if self.config.h2_config.is_none() {
+            self.spawn_stream_task(stream, cc_info, client_location)
+                .await;
+            return;
+        }
+
+        let mut buf = BytesMut::with_capacity(64);
+        match tokio::time::timeout(
+            self.config.timeout.recv_req_header,
+            super::task::check_prior_knowledge(&mut stream, &mut buf),
+        )
+        .await
+        {
+            Ok(Ok(true)) => {
+                self.spawn_h2_task(OnceBufReader::new(stream, buf), cc_info, client_location)
+                    .await
+            }
+            Ok(Ok(false)) => {
+                self.spawn_stream_task(OnceBufReader::new(stream, buf), cc_info, client_location)
+                    .await
+            }
+            Ok(Err(e)) => {
+                debug!(
+                    "{} - {} read failed: {e:?}",
+                    cc_info.sock_local_addr(),
+                    cc_info.sock_peer_addr()
+                );
+            }
+            Err(_) => {
+                debug!(
+                    "{} - {} timeout to read the first request",
+                    cc_info.sock_local_addr(),
+                    cc_info.sock_peer_addr()
+                );
+            }
+        }
+    }
+
     #[cfg(feature = "quic")]
     fn spawn_quic_stream_task(
         &self,
         send_stream: quinn::SendStream,
         recv_stream: quinn::RecvStream,
         cc_info: ClientConnectionInfo,
+        client_location: Option<Arc<IpLocation>>,
     ) {
-        let ctx = self.get_common_task_context(cc_info);
+        let ctx = self.get_common_task_context(cc_info, client_location);
         let pipeline_stats = Arc::new(HttpProxyPipelineStats::default());
         let (task_sender, task_receiver) = mpsc::channel(ctx.server_config.pipeline_size);
 
@@ -378,6 +535,10 @@ impl AcceptTcpServer for HttpProxyServer {
         if self.drop_early(client_addr) {
             return;
         }
+        let client_location = self.locate_client(client_addr).await;
+        if self.drop_by_location(client_location.as_deref()) {
+            return;
+        }
 
         if let Some(tls_acceptor) = &self.tls_acceptor {
             match tokio::time::timeout(self.tls_accept_timeout, tls_acceptor.accept(stream)).await {
@@ -386,7 +547,15 @@ impl AcceptTcpServer for HttpProxyServer {
                         // Quick ACK is needed with session resumption
                         cc_info.tcp_sock_try_quick_ack();
                     }
-                    self.spawn_stream_task(tls_stream, cc_info).await
+                    if self.config.h2_config.is_some()
+                        && tls_stream.get_ref().1.alpn_protocol() == Some(b"h2")
+                    {
+                        self.spawn_h2_task(tls_stream, cc_info, client_location)
+                            .await
+                    } else {
+                        self.spawn_stream_task(tls_stream, cc_info, client_location)
+                            .await
+                    }
                 }
                 Ok(Err(e)) => {
                     self.listen_stats.add_failed();
@@ -408,11 +577,22 @@ impl AcceptTcpServer for HttpProxyServer {
                 }
             }
         } else {
-            self.spawn_stream_task(stream, cc_info).await;
+            self.spawn_plain_stream_task(stream, cc_info, client_location)
+                .await;
         }
     }
 }
 
+#[cfg(feature = "quic")]
+fn quic_alpn_is_h3(connection: &Connection) -> bool {
+    connection
+        .handshake_data()
+        .and_then(|data| data.downcast::<quinn::crypto::rustls::HandshakeData>().ok())
+        .and_then(|data| data.protocol)
+        .map(|protocol| protocol == AlpnProtocol::Http3.identification_sequence())
+        .unwrap_or(false)
+}
+
 #[async_trait]
 impl AcceptQuicServer for HttpProxyServer {
     #[cfg(feature = "quic")]
@@ -422,13 +602,28 @@ impl AcceptQuicServer for HttpProxyServer {
         if self.drop_early(client_addr) {
             return;
         }
+        let client_location = self.locate_client(client_addr).await;
+        if self.drop_by_location(client_location.as_deref()) {
+            return;
+        }
+
+        if self.config.h3_config.is_some() && quic_alpn_is_h3(&connection) {
+            let ctx = self.get_common_task_context(cc_info, client_location);
+            let task =
+                H3ProxyConnectionTask::new(ctx, self.audit_context(), self.user_group.load_full());
+            task.into_running(connection).await;
+            return;
+        }
 
         loop {
             // TODO update ctx and quit gracefully
             match connection.accept_bi().await {
-                Ok((send_stream, recv_stream)) => {
-                    self.spawn_quic_stream_task(send_stream, recv_stream, cc_info.clone())
-                }
+                Ok((send_stream, recv_stream)) => self.spawn_quic_stream_task(
+                    send_stream,
+                    recv_stream,
+                    cc_info.clone(),
+                    client_location.clone(),
+                ),
                 Err(e) => {
                     debug!(
                         "{} - {} quic connection error: {e:?}",
@@ -479,8 +674,17 @@ impl Server for HttpProxyServer {
         if self.drop_early(client_addr) {
             return;
         }
+        let client_location = self.locate_client(client_addr).await;
+        if self.drop_by_location(client_location.as_deref()) {
+            return;
+        }
 
-        self.spawn_stream_task(stream, cc_info).await;
+        if self.config.h2_config.is_some() && stream.get_ref().1.alpn_protocol() == Some(b"h2") {
+            self.spawn_h2_task(stream, cc_info, client_location).await;
+        } else {
+            self.spawn_stream_task(stream, cc_info, client_location)
+                .await;
+        }
     }
 
     async fn run_openssl_task(&self, stream: SslStream<TcpStream>, cc_info: ClientConnectionInfo) {
@@ -489,7 +693,16 @@ impl Server for HttpProxyServer {
         if self.drop_early(client_addr) {
             return;
         }
+        let client_location = self.locate_client(client_addr).await;
+        if self.drop_by_location(client_location.as_deref()) {
+            return;
+        }
 
-        self.spawn_stream_task(stream, cc_info).await;
+        if self.config.h2_config.is_some() && stream.ssl().selected_alpn_protocol() == Some(b"h2") {
+            self.spawn_h2_task(stream, cc_info, client_location).await;
+        } else {
+            self.spawn_stream_task(stream, cc_info, client_location)
+                .await;
+        }
     }
 }
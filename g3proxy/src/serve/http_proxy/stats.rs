@@ -21,8 +21,9 @@ use std::sync::Arc;
 use arc_swap::ArcSwapOption;
 
 use g3_types::metrics::{MetricsName, StaticMetricsTags};
-use g3_types::stats::{StatId, TcpIoSnapshot, TcpIoStats};
+use g3_types::stats::{StatId, TcpIoSnapshot, TcpIoStats, UdpIoSnapshot, UdpIoStats};
 
+use crate::module::http_forward::{HttpForwardCacheSnapshot, HttpForwardCacheStats};
 use crate::serve::{
     ServerForbiddenSnapshot, ServerForbiddenStats, ServerPerTaskStats, ServerStats,
 };
@@ -43,10 +44,14 @@ pub(crate) struct HttpProxyServerStats {
     pub task_http_connect: ServerPerTaskStats,
     pub task_http_forward: ServerPerTaskStats,
     pub task_ftp_over_http: ServerPerTaskStats,
+    pub task_http_connect_udp: ServerPerTaskStats,
 
     pub io_http: TcpIoStats,
     pub io_connect: TcpIoStats,
     pub io_untrusted: TcpIoStats,
+    pub io_udp: UdpIoStats,
+
+    pub http_forward_cache: HttpForwardCacheStats,
 }
 
 impl HttpProxyServerStats {
@@ -62,9 +67,12 @@ impl HttpProxyServerStats {
             task_http_connect: Default::default(),
             task_http_forward: Default::default(),
             task_ftp_over_http: Default::default(),
+            task_http_connect_udp: Default::default(),
             io_http: Default::default(),
             io_connect: Default::default(),
             io_untrusted: Default::default(),
+            io_udp: Default::default(),
+            http_forward_cache: Default::default(),
         }
     }
 
@@ -119,6 +127,7 @@ impl ServerStats for HttpProxyServerStats {
         self.task_http_connect.get_task_total()
             + self.task_http_forward.get_task_total()
             + self.task_ftp_over_http.get_task_total()
+            + self.task_http_connect_udp.get_task_total()
     }
 
     fn get_alive_count(&self) -> i32 {
@@ -126,6 +135,7 @@ impl ServerStats for HttpProxyServerStats {
         self.task_http_connect.get_alive_count()
             + self.task_http_forward.get_alive_count()
             + self.task_ftp_over_http.get_alive_count()
+            + self.task_http_connect_udp.get_alive_count()
     }
 
     fn tcp_io_snapshot(&self) -> Option<TcpIoSnapshot> {
@@ -134,6 +144,10 @@ impl ServerStats for HttpProxyServerStats {
         Some(self.io_http.snapshot() + self.io_connect.snapshot())
     }
 
+    fn udp_io_snapshot(&self) -> Option<UdpIoSnapshot> {
+        Some(self.io_udp.snapshot())
+    }
+
     #[inline]
     fn forbidden_stats(&self) -> ServerForbiddenSnapshot {
         self.forbidden.snapshot()
@@ -146,4 +160,8 @@ impl ServerStats for HttpProxyServerStats {
             in_bytes: self.io_untrusted.get_in_bytes(),
         })
     }
+
+    fn http_forward_cache_snapshot(&self) -> Option<HttpForwardCacheSnapshot> {
+        Some(self.http_forward_cache.snapshot())
+    }
 }
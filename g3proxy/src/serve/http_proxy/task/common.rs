@@ -14,23 +14,34 @@
  * limitations under the License.
  */
 
+use std::io;
 use std::net::SocketAddr;
 use std::sync::Arc;
 
 use slog::Logger;
+use tokio::io::AsyncWrite;
 
 use g3_daemon::server::ClientConnectionInfo;
+use g3_geoip_types::IpLocation;
 use g3_icap_client::reqmod::h1::HttpAdapterErrorResponse;
 use g3_types::acl::AclAction;
 use g3_types::acl_set::AclDstHostRuleSet;
 use g3_types::net::{OpensslClientConfig, UpstreamAddr};
 
+use super::protocol::HttpProxyRequest;
 use super::{HttpProxyServerConfig, HttpProxyServerStats};
+use crate::audit::AuditContext;
+use crate::config::http_rewrite::{HttpRewriteReply, HttpRewriteRule, HttpRewriteTemplateVars};
 use crate::escape::ArcEscaper;
-use crate::module::http_forward::HttpProxyClientResponse;
+use crate::log::task::http_forward::TaskLogForHttpForward;
+use crate::module::http_forward::{
+    HttpForwardCache, HttpForwardTaskNotes, HttpProxyClientResponse,
+};
 use crate::module::http_header;
 use crate::module::tcp_connect::TcpConnectTaskNotes;
-use crate::serve::{ServerIdleChecker, ServerQuitPolicy, ServerTaskNotes};
+use crate::serve::{
+    ServerIdleChecker, ServerQuitPolicy, ServerTaskError, ServerTaskForbiddenError, ServerTaskNotes,
+};
 
 #[derive(Clone)]
 pub(crate) struct CommonTaskContext {
@@ -39,7 +50,9 @@ pub(crate) struct CommonTaskContext {
     pub(crate) server_quit_policy: Arc<ServerQuitPolicy>,
     pub(crate) escaper: ArcEscaper,
     pub(crate) cc_info: ClientConnectionInfo,
+    pub(crate) client_location: Option<Arc<IpLocation>>,
     pub(crate) tls_client_config: Arc<OpensslClientConfig>,
+    pub(crate) http_forward_cache: Option<Arc<HttpForwardCache>>,
     pub(crate) task_logger: Logger,
 
     pub(crate) dst_host_filter: Option<Arc<AclDstHostRuleSet>>,
@@ -87,6 +100,123 @@ impl CommonTaskContext {
         default_action
     }
 
+    pub(crate) fn find_rewrite_rule<CDR>(
+        &self,
+        req: &HttpProxyRequest<CDR>,
+        task_notes: &ServerTaskNotes,
+    ) -> Option<Arc<HttpRewriteRule>> {
+        // user level rules take precedence over server level rules
+        let user_rules = task_notes
+            .user_ctx()
+            .and_then(|ctx| ctx.user_config().http_rewrite_rules.as_ref());
+        user_rules
+            .into_iter()
+            .chain(self.server_config.http_rewrite_rules.as_ref())
+            .find_map(|rules| rules.find(&req.inner.method, &req.upstream, &req.inner.uri))
+            .cloned()
+    }
+
+    /// Reply the request with the rewrite reply and log the task.
+    pub(crate) async fn reply_rewrite<CDR, W>(
+        &self,
+        req: &HttpProxyRequest<CDR>,
+        task_notes: &ServerTaskNotes,
+        reply: &HttpRewriteReply,
+        close: bool,
+        clt_w: &mut W,
+    ) -> io::Result<()>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        let vars = HttpRewriteTemplateVars {
+            method: &req.inner.method,
+            upstream: &req.upstream,
+            uri: &req.inner.uri,
+            user: task_notes.user_ctx().map(|ctx| ctx.user_name().as_ref()),
+        };
+        let (write_result, task_result, rsp_status) = match reply {
+            HttpRewriteReply::Redirect { status, location } => {
+                let mut rsp =
+                    HttpProxyClientResponse::from_standard(*status, req.inner.version, close);
+                rsp.add_extra_header(format!("Location: {}\r\n", vars.render(location)));
+                let r = rsp.reply_with_body(clt_w, &mime::TEXT_HTML, b"").await;
+                (r, ServerTaskError::Finished, *status)
+            }
+            HttpRewriteReply::Deny { status, body } => {
+                self.server_stats.forbidden.add_dest_denied();
+                let rsp = HttpProxyClientResponse::from_standard(*status, req.inner.version, close);
+                let r = match body {
+                    Some(template) => {
+                        let body = vars.render_html(template);
+                        rsp.reply_with_body(clt_w, &mime::TEXT_HTML, body.as_bytes())
+                            .await
+                    }
+                    None => rsp.reply_err_to_request(clt_w).await,
+                };
+                let e = ServerTaskError::ForbiddenByRule(ServerTaskForbiddenError::DestDenied);
+                (r, e, *status)
+            }
+        };
+
+        let uri_log_max_chars = task_notes
+            .user_ctx()
+            .and_then(|c| c.user_config().log_uri_max_chars)
+            .unwrap_or(self.server_config.log_uri_max_chars);
+        let mut http_notes = HttpForwardTaskNotes::new(
+            req.time_received,
+            task_notes.task_created_instant(),
+            req.inner.method.clone(),
+            req.inner.uri.clone(),
+            uri_log_max_chars,
+        );
+        if write_result.is_ok() {
+            http_notes.rsp_status = rsp_status.as_u16();
+        }
+        let tcp_notes = TcpConnectTaskNotes::default();
+        TaskLogForHttpForward {
+            upstream: &req.upstream,
+            task_notes,
+            http_notes: &http_notes,
+            http_user_agent: req
+                .inner
+                .end_to_end_headers
+                .get(http::header::USER_AGENT)
+                .map(|v| v.to_str()),
+            tcp_notes: &tcp_notes,
+            total_time: task_notes.time_elapsed(),
+            client_rd_bytes: 0,
+            client_wr_bytes: 0,
+            remote_rd_bytes: 0,
+            remote_wr_bytes: 0,
+        }
+        .log(&self.task_logger, &task_result);
+
+        write_result
+    }
+
+    pub(crate) fn get_forward_cache(
+        &self,
+        task_notes: &ServerTaskNotes,
+        audit_ctx: &AuditContext,
+    ) -> Option<Arc<HttpForwardCache>> {
+        let cache = self.http_forward_cache.as_ref()?;
+        if let Some(user_ctx) = task_notes.user_ctx() {
+            if !user_ctx.user_config().http_forward_cache {
+                return None;
+            }
+        }
+        if let Some(audit_handle) = audit_ctx.handle() {
+            // cached responses should not bypass icap adaptation
+            if audit_handle.icap_reqmod_client().is_some()
+                || audit_handle.icap_respmod_client().is_some()
+            {
+                return None;
+            }
+        }
+
+        Some(cache.clone())
+    }
+
     pub(crate) fn set_custom_header_for_local_reply(
         &self,
         tcp_notes: &TcpConnectTaskNotes,
@@ -18,4 +18,4 @@ use super::HttpProxyServerStats;
 
 mod wrapper;
 
-pub(super) use wrapper::TcpConnectTaskCltWrapperStats;
+pub(crate) use wrapper::TcpConnectTaskCltWrapperStats;
@@ -35,6 +35,7 @@ use crate::module::http_forward::HttpProxyClientResponse;
 use crate::module::tcp_connect::{
     TcpConnectError, TcpConnectTaskConf, TcpConnectTaskNotes, TcpConnection,
 };
+use crate::serve::task_registry;
 use crate::serve::{
     ServerStats, ServerTaskError, ServerTaskForbiddenError, ServerTaskNotes, ServerTaskResult,
     ServerTaskStage,
@@ -421,7 +422,18 @@ impl HttpProxyConnectTask {
             });
         }
         let clt_w = clt_w.into_inner();
-        self.relay(clt_r, clt_w, ups_r, ups_w).await
+        let alive_task = task_registry::register(
+            self.ctx.server_config.name(),
+            "HttpProxy/CONNECT",
+            &self.task_notes,
+            &self.upstream,
+            &self.tcp_notes.escaper,
+            &self.task_stats,
+        );
+        tokio::select! {
+            r = self.relay(clt_r, clt_w, ups_r, ups_w) => r,
+            _ = alive_task.killed() => Err(ServerTaskError::CanceledAsTaskKilled),
+        }
     }
 
     async fn relay<CDR, CDW, UR, UW>(
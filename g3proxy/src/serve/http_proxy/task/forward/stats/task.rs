@@ -17,6 +17,7 @@
 use g3_daemon::stat::task::TcpStreamConnectionStats;
 
 use crate::module::http_forward::HttpForwardTaskRemoteStats;
+use crate::serve::task_registry::AliveTaskStats;
 
 #[derive(Default)]
 pub(crate) struct HttpForwardTaskStats {
@@ -33,3 +34,21 @@ impl HttpForwardTaskRemoteStats for HttpForwardTaskStats {
         self.ups.write.add_bytes(size);
     }
 }
+
+impl AliveTaskStats for HttpForwardTaskStats {
+    fn clt_read_bytes(&self) -> u64 {
+        self.clt.read.get_bytes()
+    }
+
+    fn clt_write_bytes(&self) -> u64 {
+        self.clt.write.get_bytes()
+    }
+
+    fn ups_read_bytes(&self) -> u64 {
+        self.ups.read.get_bytes()
+    }
+
+    fn ups_write_bytes(&self) -> u64 {
+        self.ups.write.get_bytes()
+    }
+}
@@ -18,6 +18,7 @@ use std::sync::Arc;
 use std::time::Duration;
 
 use anyhow::anyhow;
+use bytes::Bytes;
 use futures_util::FutureExt;
 use log::debug;
 use tokio::io::{AsyncBufRead, AsyncRead, AsyncWrite, AsyncWriteExt};
@@ -45,16 +46,19 @@ use super::{
     HttpsForwardTaskCltWrapperStats,
 };
 use crate::audit::AuditContext;
+use crate::config::http_rewrite::HttpRewriteRule;
 use crate::config::server::ServerConfig;
 use crate::log::task::http_forward::TaskLogForHttpForward;
 use crate::module::http_forward::{
     BoxHttpForwardConnection, BoxHttpForwardContext, BoxHttpForwardReader, BoxHttpForwardWriter,
-    HttpForwardTaskNotes, HttpProxyClientResponse,
+    HttpForwardCacheContext, HttpForwardCacheEntry, HttpForwardCacheRecorder, HttpForwardTaskNotes,
+    HttpProxyClientResponse,
 };
 use crate::module::http_header;
 use crate::module::tcp_connect::{
     TcpConnectError, TcpConnectTaskConf, TcpConnectTaskNotes, TlsConnectTaskConf,
 };
+use crate::serve::task_registry;
 use crate::serve::{
     ServerIdleChecker, ServerStats, ServerTaskError, ServerTaskForbiddenError, ServerTaskNotes,
     ServerTaskResult, ServerTaskStage,
@@ -72,6 +76,8 @@ pub(crate) struct HttpProxyForwardTask<'a> {
     http_notes: HttpForwardTaskNotes,
     tcp_notes: TcpConnectTaskNotes,
     task_stats: Arc<HttpForwardTaskStats>,
+    rewrite_rule: Option<Arc<HttpRewriteRule>>,
+    cache_ctx: Option<HttpForwardCacheContext>,
 }
 
 impl<'a> HttpProxyForwardTask<'a> {
@@ -81,6 +87,8 @@ impl<'a> HttpProxyForwardTask<'a> {
         req: &'a HttpProxyRequest<impl AsyncRead>,
         is_https: bool,
         task_notes: ServerTaskNotes,
+        rewrite_rule: Option<Arc<HttpRewriteRule>>,
+        cache_ctx: Option<HttpForwardCacheContext>,
     ) -> Self {
         let uri_log_max_chars = task_notes
             .user_ctx()
@@ -105,6 +113,8 @@ impl<'a> HttpProxyForwardTask<'a> {
             http_notes,
             tcp_notes: TcpConnectTaskNotes::default(),
             task_stats: Arc::new(HttpForwardTaskStats::default()),
+            rewrite_rule,
+            cache_ctx,
         }
     }
 
@@ -592,6 +602,23 @@ impl<'a> HttpProxyForwardTask<'a> {
 
         self.setup_clt_limit_and_stats(clt_r, clt_w);
 
+        if let Some(entry) = self
+            .cache_ctx
+            .as_ref()
+            .and_then(|c| c.fresh_entry())
+            .cloned()
+        {
+            self.ctx
+                .server_stats
+                .http_forward_cache
+                .add_hit(entry.body().len() as u64);
+            let r = self.send_cached_response(clt_w, &entry).await;
+            if r.is_err() {
+                self.should_close = true;
+            }
+            return r;
+        }
+
         fwd_ctx.prepare_connection(&self.upstream, self.is_https);
 
         if let Some(connection) = fwd_ctx
@@ -737,6 +764,31 @@ impl<'a> HttpProxyForwardTask<'a> {
         }
         ups_c.0.prepare_new(&self.task_notes, &self.upstream);
 
+        let alive_task = task_registry::register(
+            self.ctx.server_config.name(),
+            "HttpProxy/FORWARD",
+            &self.task_notes,
+            &self.upstream,
+            &self.tcp_notes.escaper,
+            &self.task_stats,
+        );
+        tokio::select! {
+            r = self.relay_with_connection(clt_r, clt_w, ups_c, audit_task) => r,
+            _ = alive_task.killed() => Err(ServerTaskError::CanceledAsTaskKilled),
+        }
+    }
+
+    async fn relay_with_connection<'f, CDR, CDW>(
+        &'f mut self,
+        clt_r: &'f mut Option<HttpClientReader<CDR>>,
+        clt_w: &'f mut HttpClientWriter<CDW>,
+        ups_c: BoxHttpForwardConnection,
+        audit_task: bool,
+    ) -> ServerTaskResult<Option<BoxHttpForwardConnection>>
+    where
+        CDR: AsyncRead + Send + Unpin,
+        CDW: AsyncWrite + Send + Unpin,
+    {
         if audit_task {
             if let Some(audit_handle) = self.audit_ctx.handle() {
                 if let Some(reqmod) = audit_handle.icap_reqmod_client() {
@@ -1276,6 +1328,20 @@ impl<'a> HttpProxyForwardTask<'a> {
         }
         self.http_notes.origin_status = rsp_header.code;
         self.http_notes.rsp_status = 0;
+
+        let mut cache_entry = None;
+        if let Some(cache_ctx) = &self.cache_ctx {
+            if let Some(entry) = cache_ctx.revalidate(rsp_header) {
+                self.ctx
+                    .server_stats
+                    .http_forward_cache
+                    .add_revalidated(entry.body().len() as u64);
+                return self.send_cached_response(clt_w, &entry).await;
+            }
+            self.ctx.server_stats.http_forward_cache.add_miss();
+            cache_entry = cache_ctx.new_entry(self.req, rsp_header);
+        }
+
         self.update_response_header(rsp_header);
 
         if audit_task {
@@ -1329,7 +1395,7 @@ impl<'a> HttpProxyForwardTask<'a> {
             }
         }
 
-        self.send_response_without_adaptation(clt_w, ups_r, rsp_header)
+        self.send_response_without_adaptation(clt_w, ups_r, rsp_header, cache_entry)
             .await
     }
 
@@ -1366,6 +1432,7 @@ impl<'a> HttpProxyForwardTask<'a> {
         clt_w: &mut W,
         ups_r: &mut R,
         rsp_header: &HttpForwardRemoteResponse,
+        cache_entry: Option<HttpForwardCacheEntry>,
     ) -> ServerTaskResult<()>
     where
         R: AsyncBufRead + Unpin,
@@ -1377,21 +1444,60 @@ impl<'a> HttpProxyForwardTask<'a> {
             let mut buf = Vec::with_capacity(self.ctx.server_config.tcp_copy.buffer_size());
             rsp_header.serialize_to(&mut buf);
             self.http_notes.rsp_status = rsp_header.code; // the following function must send rsp header out
-            self.send_response_body(buf, clt_w, ups_r, body_type).await
+            self.send_response_body(buf, clt_w, ups_r, body_type, cache_entry)
+                .await
         } else {
             self.send_response_header(clt_w, rsp_header).await?;
             self.http_notes.rsp_status = rsp_header.code;
             self.http_notes.mark_rsp_no_body();
+            if let (Some(cache_ctx), Some(entry)) = (&self.cache_ctx, cache_entry) {
+                cache_ctx.store(entry, Bytes::new());
+                self.ctx.server_stats.http_forward_cache.add_stored();
+            }
             Ok(())
         }
     }
 
+    async fn send_cached_response<W>(
+        &mut self,
+        clt_w: &mut W,
+        entry: &HttpForwardCacheEntry,
+    ) -> ServerTaskResult<()>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        self.send_error_response = false;
+
+        let header = entry.serialize_header(
+            self.req.version,
+            self.should_close,
+            self.rewrite_rule.as_ref().map(|r| &r.response_header),
+        );
+        clt_w
+            .write_all(&header)
+            .await
+            .map_err(ServerTaskError::ClientTcpWriteFailed)?;
+        self.http_notes.rsp_status = entry.code();
+        clt_w
+            .write_all(entry.body())
+            .await
+            .map_err(ServerTaskError::ClientTcpWriteFailed)?;
+        clt_w
+            .flush()
+            .await
+            .map_err(ServerTaskError::ClientTcpWriteFailed)?;
+
+        self.task_notes.stage = ServerTaskStage::Finished;
+        Ok(())
+    }
+
     async fn send_response_body<R, W>(
         &mut self,
         header: Vec<u8>,
         clt_w: &mut W,
         ups_r: &mut R,
         body_type: HttpBodyType,
+        cache_entry: Option<HttpForwardCacheEntry>,
     ) -> ServerTaskResult<()>
     where
         R: AsyncBufRead + Unpin,
@@ -1400,9 +1506,16 @@ impl<'a> HttpProxyForwardTask<'a> {
         let header_len = header.len() as u64;
         let mut body_reader =
             HttpBodyReader::new(ups_r, body_type, self.ctx.server_config.body_line_max_len);
+        let max_object_size = self
+            .cache_ctx
+            .as_ref()
+            .map(|c| c.max_object_size())
+            .unwrap_or_default();
+        let mut body_recorder =
+            HttpForwardCacheRecorder::new(&mut body_reader, cache_entry.is_some(), max_object_size);
 
         let mut ups_to_clt = LimitedCopy::with_data(
-            &mut body_reader,
+            &mut body_recorder,
             clt_w,
             &self.ctx.server_config.tcp_copy,
             header,
@@ -1420,6 +1533,12 @@ impl<'a> HttpProxyForwardTask<'a> {
                     return match r {
                         Ok(_) => {
                             self.http_notes.mark_rsp_recv_all();
+                            if let (Some(cache_ctx), Some(entry)) = (&self.cache_ctx, cache_entry) {
+                                if let Some(body) = body_recorder.into_body() {
+                                    cache_ctx.store(entry, body);
+                                    self.ctx.server_stats.http_forward_cache.add_stored();
+                                }
+                            }
                             // clt_w is already flushed
                             Ok(())
                         }
@@ -1483,6 +1602,10 @@ impl<'a> HttpProxyForwardTask<'a> {
     }
 
     fn update_response_header(&self, rsp: &mut HttpForwardRemoteResponse) {
+        if let Some(rule) = &self.rewrite_rule {
+            rule.response_header.apply(&mut rsp.end_to_end_headers);
+        }
+
         // append headers to hop-by-hop headers, so they will pass to client without adaptation
         if let Some(server_id) = &self.ctx.server_config.server_id {
             if self.ctx.server_config.http_forward_mark_upstream {
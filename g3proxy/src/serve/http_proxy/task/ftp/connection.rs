@@ -14,31 +14,110 @@
  * limitations under the License.
  */
 
-use std::sync::Arc;
+use std::io;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{ready, Context, Poll};
 
+use anyhow::anyhow;
 use async_trait::async_trait;
+use openssl::ssl::SslSession;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
 
 use g3_ftp_client::FtpConnectionProvider;
-use g3_types::net::UpstreamAddr;
+use g3_openssl::{SslConnector, SslStream};
+use g3_types::net::{OpensslClientConfig, UpstreamAddr};
 
 use super::FtpOverHttpTaskStats;
-use crate::module::ftp_over_http::{BoxFtpConnectContext, BoxFtpRemoteConnection};
+use crate::module::ftp_over_http::{
+    BoxFtpConnectContext, BoxFtpRemoteConnection, FtpRemoteConnection,
+};
 use crate::module::tcp_connect::{TcpConnectError, TcpConnectTaskConf};
 use crate::serve::ServerTaskNotes;
 
+type SharedSslSession = Arc<Mutex<Option<SslSession>>>;
+
+/// TLS stream of the ftp control channel.
+///
+/// With TLS 1.3 the session tickets are sent by the server after the handshake,
+/// and they will only be processed when we read from the stream, so the session
+/// is refreshed after each read, to be reused by the data channels.
+struct FtpControlTlsStream<S> {
+    inner: SslStream<S>,
+    session: SharedSslSession,
+}
+
+impl<S> FtpControlTlsStream<S> {
+    fn new(inner: SslStream<S>, session: SharedSslSession) -> Self {
+        let stream = FtpControlTlsStream { inner, session };
+        stream.update_session();
+        stream
+    }
+
+    fn update_session(&self) {
+        if let Some(session) = self.inner.ssl().session() {
+            let mut guard = self.session.lock().unwrap();
+            *guard = Some(session.to_owned());
+        }
+    }
+}
+
+impl<S> AsyncRead for FtpControlTlsStream<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        ready!(Pin::new(&mut self.inner).poll_read(cx, buf))?;
+        self.update_session();
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<S> AsyncWrite for FtpControlTlsStream<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+impl<S> FtpRemoteConnection for FtpControlTlsStream<S> where S: AsyncRead + AsyncWrite + Unpin {}
+
 pub(super) struct HttpProxyFtpConnectionProvider {
     task_stats: Arc<FtpOverHttpTaskStats>,
     connect_context: BoxFtpConnectContext,
+    tls_config: Arc<OpensslClientConfig>,
+    tls_session: SharedSslSession,
 }
 
 impl HttpProxyFtpConnectionProvider {
     pub(super) fn new(
         task_stats: &Arc<FtpOverHttpTaskStats>,
         connect_context: BoxFtpConnectContext,
+        tls_config: &Arc<OpensslClientConfig>,
     ) -> Self {
         HttpProxyFtpConnectionProvider {
             task_stats: Arc::clone(task_stats),
             connect_context,
+            tls_config: Arc::clone(tls_config),
+            tls_session: Arc::new(Mutex::new(None)),
         }
     }
 
@@ -75,4 +154,189 @@ impl FtpConnectionProvider<BoxFtpRemoteConnection, TcpConnectError, ServerTaskNo
             .new_transfer_connection(&task_conf, task_notes, self.task_stats.clone())
             .await
     }
+
+    async fn tls_handshake_control(
+        &mut self,
+        stream: BoxFtpRemoteConnection,
+        upstream: &UpstreamAddr,
+        _task_notes: &ServerTaskNotes,
+    ) -> Result<BoxFtpRemoteConnection, TcpConnectError> {
+        let ssl = self
+            .tls_config
+            .build_ssl(upstream.host(), upstream.port())
+            .map_err(TcpConnectError::InternalTlsClientError)?;
+        let connector = SslConnector::new(ssl, stream)
+            .map_err(|e| TcpConnectError::InternalTlsClientError(anyhow::Error::new(e)))?;
+
+        match tokio::time::timeout(self.tls_config.handshake_timeout, connector.connect()).await {
+            Ok(Ok(stream)) => Ok(Box::new(FtpControlTlsStream::new(
+                stream,
+                self.tls_session.clone(),
+            ))),
+            Ok(Err(e)) => Err(TcpConnectError::UpstreamTlsHandshakeFailed(
+                anyhow::Error::new(e).context("ftp control channel tls handshake failed"),
+            )),
+            Err(_) => Err(TcpConnectError::UpstreamTlsHandshakeTimeout),
+        }
+    }
+
+    async fn tls_handshake_data(
+        &mut self,
+        stream: BoxFtpRemoteConnection,
+        upstream: &UpstreamAddr,
+        _task_notes: &ServerTaskNotes,
+    ) -> Result<BoxFtpRemoteConnection, TcpConnectError> {
+        // the session of the control connection may already be set if session cache is enabled
+        let mut ssl = self
+            .tls_config
+            .build_ssl(upstream.host(), upstream.port())
+            .map_err(TcpConnectError::InternalTlsClientError)?;
+        if ssl.session().is_none() {
+            let session = self.tls_session.lock().unwrap().clone();
+            if let Some(session) = &session {
+                // the session is got from a connection which is using the same SslContext
+                unsafe { ssl.set_session(session) }.map_err(|e| {
+                    TcpConnectError::InternalTlsClientError(anyhow!(
+                        "failed to reuse control channel tls session: {e}"
+                    ))
+                })?;
+            }
+        }
+        let connector = SslConnector::new(ssl, stream)
+            .map_err(|e| TcpConnectError::InternalTlsClientError(anyhow::Error::new(e)))?;
+
+        match tokio::time::timeout(self.tls_config.handshake_timeout, connector.connect()).await {
+            Ok(Ok(stream)) => Ok(Box::new(stream)),
+            Ok(Err(e)) => Err(TcpConnectError::UpstreamTlsHandshakeFailed(
+                anyhow::Error::new(e).context("ftp data channel tls handshake failed"),
+            )),
+            Err(_) => Err(TcpConnectError::UpstreamTlsHandshakeTimeout),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    use openssl::asn1::Asn1Time;
+    use openssl::ec::{EcGroup, EcKey};
+    use openssl::hash::MessageDigest;
+    use openssl::nid::Nid;
+    use openssl::pkey::PKey;
+    use openssl::ssl::{Ssl, SslContext, SslMethod, SslVerifyMode, SslVersion};
+    use openssl::x509::{X509NameBuilder, X509};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use g3_openssl::SslAcceptor;
+
+    fn server_context() -> SslContext {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+        let key = PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap();
+
+        let mut name = X509NameBuilder::new().unwrap();
+        name.append_entry_by_text("CN", "ftp.example.net").unwrap();
+        let name = name.build();
+
+        let mut cert = X509::builder().unwrap();
+        cert.set_version(2).unwrap();
+        cert.set_subject_name(&name).unwrap();
+        cert.set_issuer_name(&name).unwrap();
+        cert.set_pubkey(&key).unwrap();
+        cert.set_not_before(&Asn1Time::days_from_now(0).unwrap())
+            .unwrap();
+        cert.set_not_after(&Asn1Time::days_from_now(1).unwrap())
+            .unwrap();
+        cert.sign(&key, MessageDigest::sha256()).unwrap();
+        let cert = cert.build();
+
+        let mut builder = SslContext::builder(SslMethod::tls_server()).unwrap();
+        builder
+            .set_min_proto_version(Some(SslVersion::TLS1_3))
+            .unwrap();
+        builder.set_certificate(&cert).unwrap();
+        builder.set_private_key(&key).unwrap();
+        builder.build()
+    }
+
+    fn client_context() -> SslContext {
+        let mut builder = SslContext::builder(SslMethod::tls_client()).unwrap();
+        builder
+            .set_min_proto_version(Some(SslVersion::TLS1_3))
+            .unwrap();
+        builder.set_verify(SslVerifyMode::NONE);
+        builder.build()
+    }
+
+    /// connect to a new server connection and return if the session is reused
+    async fn connect(
+        server_ctx: &SslContext,
+        client_ctx: &SslContext,
+        session: Option<&SslSession>,
+        shared_session: SharedSslSession,
+    ) -> (bool, Option<SslSession>) {
+        let (clt, svr) = tokio::io::duplex(4096);
+
+        let server_ssl = Ssl::new(server_ctx).unwrap();
+        let acceptor = SslAcceptor::new(server_ssl, svr, Duration::from_secs(5)).unwrap();
+
+        let mut client_ssl = Ssl::new(client_ctx).unwrap();
+        if let Some(session) = session {
+            unsafe { client_ssl.set_session(session) }.unwrap();
+        }
+        let connector = SslConnector::new(client_ssl, clt).unwrap();
+
+        let (svr_r, clt_r) = tokio::join!(acceptor.accept(), connector.connect());
+        let mut svr_stream = svr_r.unwrap();
+        let clt_stream = clt_r.unwrap();
+        let reused = clt_stream.ssl().session_reused();
+        let handshake_session = clt_stream.ssl().session().map(|s| s.to_owned());
+
+        let mut stream = FtpControlTlsStream::new(clt_stream, shared_session);
+        svr_stream.write_all(b"220 ready\r\n").await.unwrap();
+        svr_stream.flush().await.unwrap();
+        let mut buf = [0u8; 16];
+        let len = stream.read(&mut buf).await.unwrap();
+        assert_eq!(&buf[..len], b"220 ready\r\n");
+
+        // the session will be marked as not resumable if dropped without shutdown
+        stream.shutdown().await.unwrap();
+        svr_stream.shutdown().await.unwrap();
+
+        (reused, handshake_session)
+    }
+
+    #[tokio::test]
+    async fn tls13_session_after_read() {
+        let server_ctx = server_context();
+        let client_ctx = client_context();
+
+        let shared_session: SharedSslSession = Arc::new(Mutex::new(None));
+        let (reused, handshake_session) =
+            connect(&server_ctx, &client_ctx, None, shared_session.clone()).await;
+        assert!(!reused);
+        let handshake_session = handshake_session.unwrap();
+        let read_session = shared_session.lock().unwrap().clone().unwrap();
+
+        // the session got right after the handshake has no ticket
+        let (reused, _) = connect(
+            &server_ctx,
+            &client_ctx,
+            Some(&handshake_session),
+            Arc::new(Mutex::new(None)),
+        )
+        .await;
+        assert!(!reused);
+
+        // the session refreshed after read contains the ticket
+        let (reused, _) = connect(
+            &server_ctx,
+            &client_ctx,
+            Some(&read_session),
+            Arc::new(Mutex::new(None)),
+        )
+        .await;
+        assert!(reused);
+    }
 }
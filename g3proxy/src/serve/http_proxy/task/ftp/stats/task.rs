@@ -17,6 +17,7 @@
 use g3_daemon::stat::task::{TcpStreamConnectionStats, TcpStreamHalfConnectionStats};
 
 use crate::module::ftp_over_http::{FtpTaskRemoteControlStats, FtpTaskRemoteTransferStats};
+use crate::serve::task_registry::AliveTaskStats;
 
 #[derive(Default)]
 pub(crate) struct FtpOverHttpServerStats {
@@ -51,3 +52,21 @@ impl FtpTaskRemoteTransferStats for FtpOverHttpTaskStats {
         self.ftp_server.transfer_write.add_bytes(size);
     }
 }
+
+impl AliveTaskStats for FtpOverHttpTaskStats {
+    fn clt_read_bytes(&self) -> u64 {
+        self.http_client.read.get_bytes()
+    }
+
+    fn clt_write_bytes(&self) -> u64 {
+        self.http_client.write.get_bytes()
+    }
+
+    fn ups_read_bytes(&self) -> u64 {
+        self.ftp_server.control_read.get_bytes() + self.ftp_server.transfer_read.get_bytes()
+    }
+
+    fn ups_write_bytes(&self) -> u64 {
+        self.ftp_server.control_write.get_bytes() + self.ftp_server.transfer_write.get_bytes()
+    }
+}
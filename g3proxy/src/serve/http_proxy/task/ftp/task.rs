@@ -43,6 +43,7 @@ use crate::log::task::ftp_over_http::TaskLogForFtpOverHttp;
 use crate::module::ftp_over_http::{BoxFtpRemoteConnection, FtpOverHttpTaskNotes, FtpRequestPath};
 use crate::module::http_forward::HttpProxyClientResponse;
 use crate::module::tcp_connect::{TcpConnectError, TcpConnectTaskConf};
+use crate::serve::task_registry::{self, AliveTaskGuard};
 use crate::serve::{
     ServerStats, ServerTaskError, ServerTaskForbiddenError, ServerTaskNotes, ServerTaskResult,
     ServerTaskStage,
@@ -362,6 +363,17 @@ impl<'a> FtpOverHttpTask<'a> {
         }
     }
 
+    fn register_alive_task(&self) -> AliveTaskGuard {
+        task_registry::register(
+            self.ctx.server_config.name(),
+            "HttpProxy/FtpOverHttp",
+            &self.task_notes,
+            self.ftp_notes.upstream(),
+            &self.ftp_notes.control_tcp_notes.escaper,
+            &self.task_stats,
+        )
+    }
+
     async fn run_ftp<CDR, CDW>(
         &mut self,
         clt_r: &mut HttpClientReader<CDR>,
@@ -430,7 +442,12 @@ impl<'a> FtpOverHttpTask<'a> {
                 }
                 let mut ftp_client = self.setup_ftp_client(clt_w, false).await?;
                 self.login(&mut ftp_client, clt_w).await?;
-                self.delete_path(&mut ftp_client, clt_w).await
+
+                let alive_task = self.register_alive_task();
+                tokio::select! {
+                    r = self.delete_path(&mut ftp_client, clt_w) => r,
+                    _ = alive_task.killed() => Err(ServerTaskError::CanceledAsTaskKilled),
+                }
             }
             Method::GET => {
                 if self.req.body_type().is_some() {
@@ -440,7 +457,12 @@ impl<'a> FtpOverHttpTask<'a> {
                 }
                 let mut ftp_client = self.setup_ftp_client(clt_w, false).await?;
                 self.login(&mut ftp_client, clt_w).await?;
-                self.list_or_download(&mut ftp_client, clt_w).await
+
+                let alive_task = self.register_alive_task();
+                tokio::select! {
+                    r = self.list_or_download(&mut ftp_client, clt_w) => r,
+                    _ = alive_task.killed() => Err(ServerTaskError::CanceledAsTaskKilled),
+                }
             }
             Method::PUT => {
                 if self
@@ -458,7 +480,11 @@ impl<'a> FtpOverHttpTask<'a> {
                     self.login(&mut ftp_client, clt_w).await?;
 
                     let body_reader = HttpBodyReader::new_fixed_length(clt_r, size);
-                    self.upload(&mut ftp_client, clt_w, body_reader, size).await
+                    let alive_task = self.register_alive_task();
+                    tokio::select! {
+                        r = self.upload(&mut ftp_client, clt_w, body_reader, size) => r,
+                        _ = alive_task.killed() => Err(ServerTaskError::CanceledAsTaskKilled),
+                    }
                 } else {
                     self.reply_bad_request(clt_w, "allow body with fixed content-length only")
                         .await
@@ -614,18 +640,37 @@ impl<'a> FtpOverHttpTask<'a> {
             .escaper
             .new_ftp_connect_context(Arc::clone(&self.ctx.escaper), &task_conf, &self.task_notes)
             .await;
-        let ftp_connection_provider =
-            HttpProxyFtpConnectionProvider::new(&self.task_stats, escaper_connect_context);
+        let ftp_connection_provider = HttpProxyFtpConnectionProvider::new(
+            &self.task_stats,
+            escaper_connect_context,
+            &self.ctx.tls_client_config,
+        );
 
         self.task_notes.stage = ServerTaskStage::Connecting;
-        match FtpClient::connect_to(
-            self.ftp_notes.upstream().clone(),
-            ftp_connection_provider,
-            &self.task_notes,
-            &self.ctx.server_config.ftp_client_config,
-        )
-        .await
-        {
+        let upstream = self.ftp_notes.upstream().clone();
+        let ftp_client_config = &self.ctx.server_config.ftp_client_config;
+        let r = match self.ftp_notes.tls_mode() {
+            Some(tls_mode) => {
+                FtpClient::connect_to_tls(
+                    upstream,
+                    tls_mode,
+                    ftp_connection_provider,
+                    &self.task_notes,
+                    ftp_client_config,
+                )
+                .await
+            }
+            None => {
+                FtpClient::connect_to(
+                    upstream,
+                    ftp_connection_provider,
+                    &self.task_notes,
+                    ftp_client_config,
+                )
+                .await
+            }
+        };
+        match r {
             Ok(client) => {
                 self.task_notes.stage = ServerTaskStage::Connected;
                 client
@@ -0,0 +1,125 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::io;
+use std::sync::Arc;
+use std::task::{ready, Context, Poll};
+
+use bytes::{Bytes, BytesMut};
+use h2::SendStream;
+
+use g3_http::capsule;
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    target_os = "macos",
+))]
+use g3_io_ext::UdpCopyPacket;
+use g3_io_ext::{LimitedSendStats, UdpCopyClientError, UdpCopyClientSend};
+
+use super::{h2_error_to_io, UdpConnectTaskCltWrapperStats};
+
+pub(super) struct H2ConnectUdpClientSend {
+    send_stream: SendStream<Bytes>,
+    stats: Arc<UdpConnectTaskCltWrapperStats>,
+}
+
+impl H2ConnectUdpClientSend {
+    pub(super) fn new(
+        send_stream: SendStream<Bytes>,
+        stats: Arc<UdpConnectTaskCltWrapperStats>,
+    ) -> Self {
+        H2ConnectUdpClientSend { send_stream, stats }
+    }
+
+    fn poll_send(
+        &mut self,
+        cx: &mut Context<'_>,
+        payload: &[u8],
+    ) -> Poll<Result<usize, UdpCopyClientError>> {
+        let capsule_len = capsule::udp_datagram_capsule_len(payload.len());
+        self.send_stream.reserve_capacity(capsule_len);
+        // wait for some capacity, the data exceed it will be buffered in the h2 send buffer
+        match ready!(self.send_stream.poll_capacity(cx)) {
+            Some(Ok(_)) => {}
+            Some(Err(e)) => {
+                return Poll::Ready(Err(UdpCopyClientError::SendFailed(h2_error_to_io(e))))
+            }
+            None => {
+                return Poll::Ready(Err(UdpCopyClientError::SendFailed(io::Error::new(
+                    io::ErrorKind::BrokenPipe,
+                    "client stream closed",
+                ))))
+            }
+        }
+
+        let mut buf = BytesMut::with_capacity(capsule_len);
+        capsule::encode_udp_datagram_capsule(payload, &mut buf);
+        self.send_stream
+            .send_data(buf.freeze(), false)
+            .map_err(|e| UdpCopyClientError::SendFailed(h2_error_to_io(e)))?;
+        self.stats.add_send_bytes(payload.len());
+        self.stats.add_send_packet();
+        Poll::Ready(Ok(payload.len().max(1)))
+    }
+}
+
+impl UdpCopyClientSend for H2ConnectUdpClientSend {
+    fn poll_send_packet(
+        &mut self,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, UdpCopyClientError>> {
+        self.poll_send(cx, buf)
+    }
+
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd",
+        target_os = "macos",
+    ))]
+    fn poll_send_packets(
+        &mut self,
+        cx: &mut Context<'_>,
+        packets: &[UdpCopyPacket],
+    ) -> Poll<Result<usize, UdpCopyClientError>> {
+        let mut count = 0;
+        for p in packets {
+            match self.poll_send(cx, p.payload()) {
+                Poll::Ready(Ok(_)) => count += 1,
+                Poll::Ready(Err(e)) => {
+                    if count == 0 {
+                        return Poll::Ready(Err(e));
+                    }
+                    break;
+                }
+                Poll::Pending => {
+                    if count == 0 {
+                        return Poll::Pending;
+                    }
+                    break;
+                }
+            }
+        }
+        Poll::Ready(Ok(count))
+    }
+}
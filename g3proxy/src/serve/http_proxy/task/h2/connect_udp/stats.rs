@@ -0,0 +1,125 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::sync::Arc;
+
+use g3_daemon::stat::task::UdpConnectConnectionStats;
+use g3_io_ext::{LimitedRecvStats, LimitedSendStats};
+
+use super::HttpProxyServerStats;
+use crate::auth::UserTrafficStats;
+use crate::module::udp_connect::UdpConnectTaskRemoteStats;
+use crate::serve::task_registry::AliveTaskStats;
+
+#[derive(Default)]
+pub(crate) struct UdpConnectTaskStats {
+    pub(crate) clt: UdpConnectConnectionStats,
+    pub(crate) ups: UdpConnectConnectionStats,
+}
+
+impl UdpConnectTaskRemoteStats for UdpConnectTaskStats {
+    fn add_recv_bytes(&self, size: u64) {
+        self.ups.recv.add_bytes(size);
+    }
+
+    fn add_recv_packets(&self, n: usize) {
+        self.ups.recv.add_packets(n);
+    }
+
+    fn add_send_bytes(&self, size: u64) {
+        self.ups.send.add_bytes(size);
+    }
+
+    fn add_send_packets(&self, n: usize) {
+        self.ups.send.add_packets(n);
+    }
+}
+
+impl AliveTaskStats for UdpConnectTaskStats {
+    fn clt_read_bytes(&self) -> u64 {
+        self.clt.recv.get_bytes()
+    }
+
+    fn clt_write_bytes(&self) -> u64 {
+        self.clt.send.get_bytes()
+    }
+
+    fn ups_read_bytes(&self) -> u64 {
+        self.ups.recv.get_bytes()
+    }
+
+    fn ups_write_bytes(&self) -> u64 {
+        self.ups.send.get_bytes()
+    }
+}
+
+pub(crate) struct UdpConnectTaskCltWrapperStats {
+    server: Arc<HttpProxyServerStats>,
+    task: Arc<UdpConnectTaskStats>,
+    others: Vec<Arc<UserTrafficStats>>,
+}
+
+impl UdpConnectTaskCltWrapperStats {
+    pub(crate) fn new(server: &Arc<HttpProxyServerStats>, task: &Arc<UdpConnectTaskStats>) -> Self {
+        UdpConnectTaskCltWrapperStats {
+            server: Arc::clone(server),
+            task: Arc::clone(task),
+            others: Vec::with_capacity(2),
+        }
+    }
+
+    pub(crate) fn push_user_io_stats(&mut self, all: Vec<Arc<UserTrafficStats>>) {
+        self.others.extend(all);
+    }
+}
+
+impl LimitedRecvStats for UdpConnectTaskCltWrapperStats {
+    fn add_recv_bytes(&self, size: usize) {
+        let size = size as u64;
+        self.server.io_udp.add_in_bytes(size);
+        self.task.clt.recv.add_bytes(size);
+        self.others
+            .iter()
+            .for_each(|s| s.io.http_connect_udp.add_in_bytes(size));
+    }
+
+    fn add_recv_packets(&self, n: usize) {
+        self.server.io_udp.add_in_packets(n);
+        self.task.clt.recv.add_packets(n);
+        self.others
+            .iter()
+            .for_each(|s| s.io.http_connect_udp.add_in_packets(n));
+    }
+}
+
+impl LimitedSendStats for UdpConnectTaskCltWrapperStats {
+    fn add_send_bytes(&self, size: usize) {
+        let size = size as u64;
+        self.server.io_udp.add_out_bytes(size);
+        self.task.clt.send.add_bytes(size);
+        self.others
+            .iter()
+            .for_each(|s| s.io.http_connect_udp.add_out_bytes(size));
+    }
+
+    fn add_send_packets(&self, n: usize) {
+        self.server.io_udp.add_out_packets(n);
+        self.task.clt.send.add_packets(n);
+        self.others
+            .iter()
+            .for_each(|s| s.io.http_connect_udp.add_out_packets(n));
+    }
+}
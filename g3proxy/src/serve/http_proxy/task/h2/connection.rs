@@ -0,0 +1,548 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::str::FromStr;
+use std::sync::Arc;
+
+use ahash::AHashMap;
+use bytes::Bytes;
+use h2::ext::Protocol;
+use h2::server::{Connection, SendResponse};
+use h2::{Reason, RecvStream};
+use http::{header, HeaderMap, Method, Request, StatusCode};
+use log::debug;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::time::Instant;
+
+use g3_http::server::{HttpProxyClientRequest, HttpRequestParseError, UriExt};
+use g3_types::auth::UserAuthError;
+use g3_types::net::{HttpAuth, HttpBasicAuth, HttpUpgradeToken, UpstreamAddr};
+
+use super::{
+    CommonTaskContext, H2ProxyConnectTask, H2ProxyConnectUdpTask, H2ProxyForwardTask,
+    HttpProxyPipelineStats,
+};
+use crate::audit::AuditContext;
+use crate::auth::{UserContext, UserGroup, UserRequestStats};
+use crate::config::server::ServerConfig;
+use crate::escape::EgressPathSelection;
+use crate::serve::{ServerStats, ServerTaskNotes};
+
+struct UserData {
+    req_stats: Arc<UserRequestStats>,
+    site_req_stats: Option<Arc<UserRequestStats>>,
+}
+
+impl Drop for UserData {
+    fn drop(&mut self) {
+        self.req_stats.l7_conn_alive.dec_http();
+        if let Some(site_req_stats) = &self.site_req_stats {
+            site_req_stats.l7_conn_alive.dec_http();
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum H2ProxyStreamType {
+    TcpConnect,
+    UdpConnect,
+    HttpForward(bool),
+}
+
+/// Get the stream type and the upstream address of the h2 proxy request.
+fn get_stream_type<T>(
+    clt_req: &Request<T>,
+) -> Result<(H2ProxyStreamType, UpstreamAddr), HttpRequestParseError> {
+    let uri = clt_req.uri();
+    if clt_req.method().eq(&Method::CONNECT) {
+        if let Some(protocol) = clt_req.extensions().get::<Protocol>() {
+            return match HttpUpgradeToken::from_str(protocol.as_str()) {
+                Ok(HttpUpgradeToken::ConnectUdp) => {
+                    let upstream = uri.get_connect_udp_upstream()?;
+                    Ok((H2ProxyStreamType::UdpConnect, upstream))
+                }
+                _ => Err(HttpRequestParseError::UpgradeIsNotSupported),
+            };
+        }
+        let upstream = uri.get_upstream_with_default_port(443)?;
+        return Ok((H2ProxyStreamType::TcpConnect, upstream));
+    }
+
+    match uri.scheme() {
+        Some(scheme) => {
+            if scheme.eq(&http::uri::Scheme::HTTP) {
+                let upstream = uri.get_upstream_with_default_port(80)?;
+                Ok((H2ProxyStreamType::HttpForward(false), upstream))
+            } else if scheme.eq(&http::uri::Scheme::HTTPS) {
+                let upstream = uri.get_upstream_with_default_port(443)?;
+                Ok((H2ProxyStreamType::HttpForward(true), upstream))
+            } else {
+                Err(HttpRequestParseError::UnsupportedScheme)
+            }
+        }
+        None => Err(HttpRequestParseError::InvalidRequestTarget),
+    }
+}
+
+pub(crate) struct H2ProxyConnectionTask {
+    ctx: Arc<CommonTaskContext>,
+    audit_ctx: AuditContext,
+    user_group: Option<Arc<UserGroup>>,
+    stream_stats: Arc<HttpProxyPipelineStats>,
+    passed_users: AHashMap<Arc<str>, UserData>,
+}
+
+impl H2ProxyConnectionTask {
+    pub(crate) fn new(
+        ctx: Arc<CommonTaskContext>,
+        audit_ctx: AuditContext,
+        user_group: Option<Arc<UserGroup>>,
+    ) -> Self {
+        H2ProxyConnectionTask {
+            ctx,
+            audit_ctx,
+            user_group,
+            stream_stats: Arc::new(HttpProxyPipelineStats::default()),
+            passed_users: AHashMap::new(),
+        }
+    }
+
+    pub(crate) async fn into_running<IO>(mut self, io: IO)
+    where
+        IO: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+    {
+        let Some(h2_config) = &self.ctx.server_config.h2_config else {
+            return;
+        };
+
+        let mut server_builder = h2::server::Builder::new();
+        server_builder
+            .max_header_list_size(h2_config.max_header_list_size)
+            .max_concurrent_streams(h2_config.max_concurrent_streams)
+            .max_frame_size(h2_config.max_frame_size)
+            .max_send_buffer_size(h2_config.max_send_buffer_size);
+        if h2_config.enable_connect_udp {
+            server_builder.enable_connect_protocol();
+        }
+
+        let mut h2c = match tokio::time::timeout(
+            h2_config.handshake_timeout,
+            server_builder.handshake::<_, Bytes>(io),
+        )
+        .await
+        {
+            Ok(Ok(h2c)) => h2c,
+            Ok(Err(e)) => {
+                debug!(
+                    "{} - {} h2 handshake error: {e}",
+                    self.ctx.cc_info.sock_local_addr(),
+                    self.ctx.cc_info.sock_peer_addr()
+                );
+                return;
+            }
+            Err(_) => {
+                debug!(
+                    "{} - {} h2 handshake timeout",
+                    self.ctx.cc_info.sock_local_addr(),
+                    self.ctx.cc_info.sock_peer_addr()
+                );
+                return;
+            }
+        };
+
+        let idle_duration = self.ctx.server_config.task_idle_check_duration;
+        let mut idle_interval =
+            tokio::time::interval_at(Instant::now() + idle_duration, idle_duration);
+        let mut idle_count = 0;
+
+        loop {
+            tokio::select! {
+                biased;
+
+                r = h2c.accept() => {
+                    match r {
+                        Some(Ok((clt_req, clt_send_rsp))) => {
+                            self.handle_stream(clt_req, clt_send_rsp);
+                        }
+                        Some(Err(e)) => {
+                            debug!(
+                                "{} - {} h2 connection error: {e}",
+                                self.ctx.cc_info.sock_local_addr(),
+                                self.ctx.cc_info.sock_peer_addr()
+                            );
+                            break;
+                        }
+                        None => break,
+                    }
+                }
+                _ = idle_interval.tick() => {
+                    if self.stream_stats.get_alive_task() <= 0 {
+                        idle_count += 1;
+
+                        if idle_count > self.ctx.server_config.task_idle_max_count {
+                            server_graceful_shutdown(h2c).await;
+                            break;
+                        }
+                    } else {
+                        idle_count = 0;
+                    }
+
+                    if self.ctx.server_quit_policy.force_quit() {
+                        server_abrupt_shutdown(h2c, Reason::CANCEL).await;
+                        break;
+                    }
+
+                    if !self.ctx.server_stats.is_online() {
+                        h2c.graceful_shutdown();
+                    }
+                }
+            }
+        }
+    }
+
+    fn get_egress_path_selection(&self, headers: &mut HeaderMap) -> Option<EgressPathSelection> {
+        if let Some(header) = &self.ctx.server_config.egress_path_selection_header {
+            // check and remove the custom header
+            if let Some(value) = headers.remove(header) {
+                if let Ok(value) = value.to_str() {
+                    if let Ok(egress) = EgressPathSelection::from_str(value) {
+                        return Some(egress);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    fn reply_invalid_request(
+        &self,
+        mut clt_send_rsp: SendResponse<Bytes>,
+        e: HttpRequestParseError,
+    ) {
+        debug!(
+            "{} - {} invalid h2 proxy request: {e}",
+            self.ctx.cc_info.sock_local_addr(),
+            self.ctx.cc_info.sock_peer_addr()
+        );
+        if self.ctx.server_config.no_early_error_reply {
+            clt_send_rsp.send_reset(Reason::REFUSED_STREAM);
+        } else if let Some(status) = e.status_code() {
+            let _ = super::reply_status(&mut clt_send_rsp, status);
+        }
+    }
+
+    fn handle_stream(
+        &mut self,
+        mut clt_req: Request<RecvStream>,
+        clt_send_rsp: SendResponse<Bytes>,
+    ) {
+        let time_accepted = Instant::now();
+
+        if self.ctx.server_config.steal_forwarded_for {
+            let headers = clt_req.headers_mut();
+            headers.remove(header::FORWARDED);
+            headers.remove("x-forwarded-for");
+        }
+        // the expect header is handled by the h2 layer
+        clt_req.headers_mut().remove(header::EXPECT);
+        let path_selection = self.get_egress_path_selection(clt_req.headers_mut());
+
+        let (stream_type, upstream) = match get_stream_type(&clt_req) {
+            Ok(v) => v,
+            Err(e) => {
+                self.reply_invalid_request(clt_send_rsp, e);
+                return;
+            }
+        };
+
+        let (parts, clt_body) = clt_req.into_parts();
+        let req = match HttpProxyClientRequest::from_h2_parts(&parts, !clt_body.is_end_stream()) {
+            Ok(req) => req,
+            Err(e) => {
+                self.reply_invalid_request(clt_send_rsp, e);
+                return;
+            }
+        };
+        if matches!(stream_type, H2ProxyStreamType::HttpForward(_))
+            && !self.ctx.server_config.allow_custom_host
+        {
+            if let Some(host) = &req.host {
+                if !host.host_eq(&upstream) {
+                    self.reply_invalid_request(
+                        clt_send_rsp,
+                        HttpRequestParseError::UnmatchedHostAndAuthority,
+                    );
+                    return;
+                }
+            }
+        }
+
+        let user_ctx = match self.do_auth(&req.auth_info, &upstream) {
+            Ok(user_ctx) => user_ctx,
+            Err(e) => {
+                self.reply_auth_err(clt_send_rsp, e);
+                return;
+            }
+        };
+
+        let task_notes = ServerTaskNotes::with_path_selection(
+            self.ctx.cc_info.clone(),
+            user_ctx,
+            time_accepted.elapsed(),
+            path_selection,
+        );
+
+        let stream_stats = self.stream_stats.clone();
+        stream_stats.add_task();
+        match stream_type {
+            H2ProxyStreamType::TcpConnect => {
+                let task = H2ProxyConnectTask::new(
+                    &self.ctx,
+                    self.audit_ctx.clone(),
+                    upstream,
+                    task_notes,
+                );
+                tokio::spawn(async move {
+                    task.into_running(clt_body, clt_send_rsp).await;
+                    stream_stats.del_task();
+                });
+            }
+            H2ProxyStreamType::UdpConnect => {
+                let task = H2ProxyConnectUdpTask::new(&self.ctx, upstream, task_notes);
+                tokio::spawn(async move {
+                    task.into_running(clt_body, clt_send_rsp).await;
+                    stream_stats.del_task();
+                });
+            }
+            H2ProxyStreamType::HttpForward(is_https) => {
+                let task = H2ProxyForwardTask::new(
+                    &self.ctx,
+                    self.audit_ctx.clone(),
+                    req,
+                    upstream,
+                    is_https,
+                    time_accepted,
+                    task_notes,
+                );
+                tokio::spawn(async move {
+                    task.into_running(clt_body, clt_send_rsp).await;
+                    stream_stats.del_task();
+                });
+            }
+        }
+    }
+
+    fn do_auth(
+        &mut self,
+        auth_info: &HttpAuth,
+        upstream: &UpstreamAddr,
+    ) -> Result<Option<UserContext>, UserAuthError> {
+        let Some(user_group) = &self.user_group else {
+            return Ok(None);
+        };
+
+        let user_ctx = match auth_info {
+            HttpAuth::None => {
+                if let Some((user, user_type)) = user_group.get_anonymous_user() {
+                    let user_ctx = UserContext::new(
+                        None,
+                        user,
+                        user_type,
+                        self.ctx.server_config.name(),
+                        self.ctx.server_stats.share_extra_tags(),
+                    );
+                    user_ctx.check_client_addr(
+                        self.ctx.client_addr(),
+                        self.ctx.client_location.as_deref(),
+                    )?;
+                    user_ctx
+                } else {
+                    return Err(UserAuthError::NoUserSupplied);
+                }
+            }
+            HttpAuth::Basic(HttpBasicAuth {
+                username, password, ..
+            }) => match user_group.get_user(username.as_original()) {
+                Some((user, user_type)) => {
+                    let user_ctx = UserContext::new(
+                        Some(Arc::from(username.as_original())),
+                        user,
+                        user_type,
+                        self.ctx.server_config.name(),
+                        self.ctx.server_stats.share_extra_tags(),
+                    );
+                    user_ctx.check_client_addr(
+                        self.ctx.client_addr(),
+                        self.ctx.client_location.as_deref(),
+                    )?;
+                    user_ctx.check_password(password.as_original())?;
+                    user_ctx
+                }
+                None => return Err(UserAuthError::NoSuchUser),
+            },
+        };
+
+        let mut user_ctx = user_ctx;
+        user_ctx.check_in_site(
+            self.ctx.server_config.name(),
+            self.ctx.server_stats.share_extra_tags(),
+            upstream,
+        );
+        if self.passed_users.contains_key(user_ctx.user_name()) {
+            user_ctx.mark_reused_client_connection();
+        } else {
+            let req_stats = user_ctx.req_stats().clone();
+            req_stats.conn_total.add_http();
+            req_stats.l7_conn_alive.inc_http();
+            let site_req_stats = if let Some(site_req_stats) = user_ctx.site_req_stats() {
+                site_req_stats.conn_total.add_http();
+                site_req_stats.l7_conn_alive.inc_http();
+                Some(Arc::clone(site_req_stats))
+            } else {
+                None
+            };
+            self.passed_users.insert(
+                user_ctx.user_name().clone(),
+                UserData {
+                    req_stats,
+                    site_req_stats,
+                },
+            );
+        }
+        Ok(Some(user_ctx))
+    }
+
+    fn reply_auth_err(&self, mut clt_send_rsp: SendResponse<Bytes>, e: UserAuthError) {
+        match e.blocked_delay() {
+            Some(duration) => {
+                self.ctx.server_stats.forbidden.add_user_blocked();
+
+                let no_early_error_reply = self.ctx.server_config.no_early_error_reply;
+                tokio::spawn(async move {
+                    // delay some time before reply
+                    tokio::time::sleep(duration).await;
+                    if no_early_error_reply {
+                        clt_send_rsp.send_reset(Reason::REFUSED_STREAM);
+                    } else {
+                        let _ = super::reply_status(&mut clt_send_rsp, StatusCode::FORBIDDEN);
+                    }
+                });
+            }
+            None => {
+                self.ctx.server_stats.forbidden.add_auth_failed();
+
+                if self.ctx.server_config.no_early_error_reply {
+                    clt_send_rsp.send_reset(Reason::REFUSED_STREAM);
+                } else {
+                    super::reply_proxy_auth_required(
+                        &mut clt_send_rsp,
+                        self.ctx.server_config.auth_realm.as_str(),
+                    );
+                }
+            }
+        }
+    }
+}
+
+async fn server_graceful_shutdown<T>(mut h2c: Connection<T, Bytes>)
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    h2c.graceful_shutdown();
+
+    while let Some(r) = h2c.accept().await {
+        match r {
+            Ok((_req, mut send_rsp)) => {
+                send_rsp.send_reset(Reason::REFUSED_STREAM);
+            }
+            Err(_) => break,
+        }
+    }
+}
+
+async fn server_abrupt_shutdown<T>(mut h2c: Connection<T, Bytes>, reason: Reason)
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    h2c.abrupt_shutdown(reason);
+
+    while let Some(r) = h2c.accept().await {
+        match r {
+            Ok((_req, mut send_rsp)) => {
+                send_rsp.send_reset(reason);
+            }
+            Err(_) => break,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build_request(method: Method, uri: &str, protocol: Option<&str>) -> Request<()> {
+        let mut req = Request::builder().method(method).uri(uri).body(()).unwrap();
+        if let Some(protocol) = protocol {
+            req.extensions_mut().insert(Protocol::from(protocol));
+        }
+        req
+    }
+
+    #[test]
+    fn tcp_connect() {
+        let req = build_request(Method::CONNECT, "www.example.net:8443", None);
+        let (stream_type, upstream) = get_stream_type(&req).unwrap();
+        assert_eq!(stream_type, H2ProxyStreamType::TcpConnect);
+        assert_eq!(upstream.to_string(), "www.example.net:8443");
+    }
+
+    #[test]
+    fn udp_connect() {
+        let req = build_request(
+            Method::CONNECT,
+            "https://proxy.example.net/.well-known/masque/udp/192.0.2.6/443/",
+            Some("connect-udp"),
+        );
+        let (stream_type, upstream) = get_stream_type(&req).unwrap();
+        assert_eq!(stream_type, H2ProxyStreamType::UdpConnect);
+        assert_eq!(upstream.to_string(), "192.0.2.6:443");
+
+        let req = build_request(
+            Method::CONNECT,
+            "https://proxy.example.net/chat",
+            Some("websocket"),
+        );
+        let e = get_stream_type(&req).unwrap_err();
+        assert!(matches!(e, HttpRequestParseError::UpgradeIsNotSupported));
+    }
+
+    #[test]
+    fn http_forward() {
+        let req = build_request(Method::GET, "http://www.example.net/", None);
+        let (stream_type, upstream) = get_stream_type(&req).unwrap();
+        assert_eq!(stream_type, H2ProxyStreamType::HttpForward(false));
+        assert_eq!(upstream.to_string(), "www.example.net:80");
+
+        let req = build_request(Method::POST, "https://www.example.net:8443/upload", None);
+        let (stream_type, upstream) = get_stream_type(&req).unwrap();
+        assert_eq!(stream_type, H2ProxyStreamType::HttpForward(true));
+        assert_eq!(upstream.to_string(), "www.example.net:8443");
+
+        let req = build_request(Method::GET, "ftp://www.example.net/a.txt", None);
+        let e = get_stream_type(&req).unwrap_err();
+        assert!(matches!(e, HttpRequestParseError::UnsupportedScheme));
+    }
+}
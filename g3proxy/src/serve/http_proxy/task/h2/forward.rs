@@ -0,0 +1,418 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use bytes::Bytes;
+use h2::server::SendResponse;
+use h2::{Reason, RecvStream};
+use http::{HeaderMap, Method, Response, StatusCode, Version};
+use log::debug;
+use tokio::io::{AsyncBufRead, AsyncRead, BufReader, DuplexStream};
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+
+use g3_h2::{H2BodyEncodeTransfer, H2StreamFromChunkedTransfer, H2StreamToChunkedTransfer};
+use g3_http::client::HttpForwardRemoteResponse;
+use g3_http::server::HttpProxyClientRequest;
+use g3_http::{HttpBodyDecodeReader, HttpBodyType};
+use g3_io_ext::{
+    LimitedBufReader, LimitedCopyConfig, LimitedWriter, NilLimitedReaderStats,
+    NilLimitedWriterStats,
+};
+use g3_types::net::UpstreamAddr;
+
+use super::{
+    CommonTaskContext, HttpClientWriter, HttpProxyForwardTask, HttpProxyRequest,
+    HttpProxySubProtocol,
+};
+use crate::audit::AuditContext;
+use crate::module::http_forward::HttpForwardCacheContext;
+use crate::serve::ServerTaskNotes;
+
+/// Forward task for plain http or https requests in h2 streams.
+///
+/// The h2 stream is bridged to the HTTP/1.x forward task, so auth, audit, ICAP adaptation,
+/// forward cache and http rewrite rules will be applied in the same way. The upstream connection
+/// will not be shared between streams.
+pub(crate) struct H2ProxyForwardTask {
+    ctx: Arc<CommonTaskContext>,
+    audit_ctx: AuditContext,
+    req: HttpProxyRequest<DuplexStream>,
+    task_notes: ServerTaskNotes,
+}
+
+impl H2ProxyForwardTask {
+    pub(crate) fn new(
+        ctx: &Arc<CommonTaskContext>,
+        audit_ctx: AuditContext,
+        req: HttpProxyClientRequest,
+        upstream: UpstreamAddr,
+        is_https: bool,
+        time_accepted: Instant,
+        task_notes: ServerTaskNotes,
+    ) -> Self {
+        let client_protocol = if is_https {
+            HttpProxySubProtocol::HttpsForward
+        } else {
+            HttpProxySubProtocol::HttpForward
+        };
+        // there will be no following requests in the same h2 stream
+        let (stream_sender, _) = mpsc::channel(1);
+        H2ProxyForwardTask {
+            ctx: Arc::clone(ctx),
+            audit_ctx,
+            req: HttpProxyRequest {
+                client_protocol,
+                inner: req,
+                upstream,
+                time_accepted,
+                time_received: time_accepted,
+                body_reader: None,
+                stream_sender,
+            },
+            task_notes,
+        }
+    }
+
+    pub(crate) async fn into_running(
+        mut self,
+        clt_body: RecvStream,
+        mut clt_send_rsp: SendResponse<Bytes>,
+    ) {
+        let server_config = &self.ctx.server_config;
+        let limit_config = &server_config.tcp_sock_speed_limit;
+        let buffer_size = server_config.tcp_copy.buffer_size();
+
+        let mut req_body_w = None;
+        if self.req.inner.body_type().is_some() {
+            let (r, w) = tokio::io::duplex(buffer_size);
+            // stats and limit will be set up in the forward task
+            self.req.body_reader = Some(LimitedBufReader::new(
+                r,
+                limit_config.shift_millis,
+                limit_config.max_north,
+                Arc::new(NilLimitedReaderStats::default()),
+                Arc::new(NilLimitedReaderStats::default()),
+            ));
+            req_body_w = Some(w);
+        }
+        let (rsp_w, rsp_r) = tokio::io::duplex(buffer_size);
+        let clt_w = LimitedWriter::local_limited(
+            rsp_w,
+            limit_config.shift_millis,
+            limit_config.max_south,
+            Arc::new(NilLimitedWriterStats::default()),
+        );
+
+        let ctx = self.ctx.clone();
+        let method = self.req.inner.method.clone();
+        let send_rsp = async {
+            let mut rsp_r = BufReader::with_capacity(buffer_size, rsp_r);
+            if let Err(e) = send_response(
+                &mut rsp_r,
+                &mut clt_send_rsp,
+                &method,
+                &ctx.server_config.tcp_copy,
+                ctx.server_config.rsp_hdr_max_size,
+                ctx.server_config.body_line_max_len,
+            )
+            .await
+            {
+                debug!(
+                    "{} - {} failed to send h2 forward response: {e:?}",
+                    ctx.cc_info.sock_local_addr(),
+                    ctx.cc_info.sock_peer_addr()
+                );
+            }
+        };
+
+        let yield_size = self.ctx.server_config.tcp_copy.yield_size();
+        let recv_req_body = async move {
+            if let Some(mut w) = req_body_w {
+                let mut clt_body = clt_body;
+                // the forward task will get an unexpected eof if the transfer failed
+                let _ = H2StreamToChunkedTransfer::new(&mut clt_body, &mut w, yield_size).await;
+            }
+            // keep the h2 stream until the forward task finished
+            std::future::pending::<()>().await
+        };
+
+        tokio::select! {
+            biased;
+
+            _ = async { tokio::join!(self.run(clt_w), send_rsp) } => {}
+            _ = recv_req_body => {}
+        }
+    }
+
+    async fn run(mut self, mut clt_w: HttpClientWriter<DuplexStream>) {
+        let rewrite_rule = self.ctx.find_rewrite_rule(&self.req, &self.task_notes);
+        if let Some(rule) = &rewrite_rule {
+            if let Some(reply) = &rule.reply {
+                let _ = self
+                    .ctx
+                    .reply_rewrite(&self.req, &self.task_notes, reply, true, &mut clt_w)
+                    .await;
+                return;
+            }
+            rule.rewrite_request(&mut self.req.inner);
+        }
+
+        let mut forward_context = self
+            .ctx
+            .escaper
+            .new_http_forward_context(Arc::clone(&self.ctx.escaper));
+        let mut audit_ctx = self.audit_ctx;
+        let forward_capability = forward_context
+            .check_in_final_escaper(&self.task_notes, &self.req.upstream, &mut audit_ctx)
+            .await;
+        let https_url = matches!(self.req.client_protocol, HttpProxySubProtocol::HttpsForward);
+        let is_https = https_url && !forward_capability.forward_https();
+
+        let cache_ctx = match self.ctx.get_forward_cache(&self.task_notes, &audit_ctx) {
+            Some(cache) => {
+                HttpForwardCacheContext::lookup(
+                    &cache,
+                    https_url,
+                    &self.req.upstream,
+                    &mut self.req.inner,
+                )
+                .await
+            }
+            None => None,
+        };
+
+        let mut clt_r = self.req.body_reader.take();
+        let mut forward_task = HttpProxyForwardTask::new(
+            &self.ctx,
+            audit_ctx,
+            &self.req,
+            is_https,
+            self.task_notes,
+            rewrite_rule,
+            cache_ctx,
+        );
+        forward_task
+            .run(&mut clt_r, &mut clt_w, &mut forward_context)
+            .await;
+    }
+}
+
+/// Convert the HTTP/1.x response written by the forward task to the h2 response.
+async fn send_response<R>(
+    rsp_r: &mut R,
+    clt_send_rsp: &mut SendResponse<Bytes>,
+    method: &Method,
+    copy_config: &LimitedCopyConfig,
+    rsp_hdr_max_size: usize,
+    body_line_max_len: usize,
+) -> anyhow::Result<()>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let rsp = loop {
+        match HttpForwardRemoteResponse::parse(rsp_r, method, true, rsp_hdr_max_size).await {
+            // informational responses are not forwarded to h2 clients
+            Ok(rsp) if rsp.code < 200 => {}
+            Ok(rsp) => break rsp,
+            Err(e) => {
+                clt_send_rsp.send_reset(Reason::INTERNAL_ERROR);
+                return Err(anyhow!("no valid response: {e}"));
+            }
+        }
+    };
+
+    let body_type = rsp.body_type(method);
+    let mut headers = HeaderMap::from(&rsp.end_to_end_headers);
+    headers.remove(http::header::CONTENT_LENGTH);
+    if let Some(HttpBodyType::ContentLength(len)) = body_type {
+        headers.insert(http::header::CONTENT_LENGTH, len.into());
+    }
+    let mut h2_rsp = Response::new(());
+    *h2_rsp.status_mut() =
+        StatusCode::from_u16(rsp.code).map_err(|_| anyhow!("invalid status code {}", rsp.code))?;
+    *h2_rsp.version_mut() = Version::HTTP_2;
+    *h2_rsp.headers_mut() = headers;
+
+    let mut send_stream = clt_send_rsp
+        .send_response(h2_rsp, body_type.is_none())
+        .map_err(|e| anyhow!("failed to send response header: {e}"))?;
+    let r = match body_type {
+        None => return Ok(()),
+        Some(HttpBodyType::Chunked) => H2StreamFromChunkedTransfer::new(
+            rsp_r,
+            &mut send_stream,
+            copy_config,
+            body_line_max_len,
+            rsp_hdr_max_size,
+        )
+        .await
+        .map_err(|e| anyhow!("failed to send chunked body: {e}")),
+        Some(HttpBodyType::ContentLength(len)) => {
+            let mut body_reader = HttpBodyDecodeReader::new_fixed_length(rsp_r, len);
+            send_body_to_end(&mut body_reader, &mut send_stream, copy_config).await
+        }
+        Some(HttpBodyType::ReadUntilEnd) => {
+            let mut body_reader = HttpBodyDecodeReader::new_read_until_end(rsp_r);
+            send_body_to_end(&mut body_reader, &mut send_stream, copy_config).await
+        }
+    };
+    if r.is_err() {
+        send_stream.send_reset(Reason::INTERNAL_ERROR);
+    }
+    r
+}
+
+async fn send_body_to_end<R>(
+    body_reader: &mut R,
+    send_stream: &mut h2::SendStream<Bytes>,
+    copy_config: &LimitedCopyConfig,
+) -> anyhow::Result<()>
+where
+    R: AsyncRead + Unpin,
+{
+    H2BodyEncodeTransfer::new(body_reader, send_stream, copy_config)
+        .await
+        .map_err(|e| anyhow!("failed to send body: {e}"))?;
+    send_stream
+        .send_data(Bytes::new(), true)
+        .map_err(|e| anyhow!("failed to send end of stream: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use h2::client;
+    use http::Request;
+
+    async fn forward_response(h1_rsp: &'static [u8]) -> (Response<()>, Bytes, Option<HeaderMap>) {
+        let (clt_io, svr_io) = tokio::io::duplex(4096);
+
+        let server = tokio::spawn(async move {
+            let mut h2c = h2::server::handshake(svr_io).await.unwrap();
+            let (req, mut send_rsp) = h2c.accept().await.unwrap().unwrap();
+            assert_eq!(req.uri().scheme_str(), Some("http"));
+            // drive the connection until the client closed
+            let h2c_task = tokio::spawn(async move { while h2c.accept().await.is_some() {} });
+            let mut rsp_r = BufReader::new(h1_rsp);
+            send_response(
+                &mut rsp_r,
+                &mut send_rsp,
+                req.method(),
+                &LimitedCopyConfig::default(),
+                4096,
+                1024,
+            )
+            .await
+            .unwrap();
+            h2c_task.await.unwrap();
+        });
+
+        let (mut send_req, h2c) = client::handshake(clt_io).await.unwrap();
+        tokio::spawn(async move {
+            let _ = h2c.await;
+        });
+        let req = Request::builder()
+            .method(Method::GET)
+            .uri("http://www.example.net/index.html")
+            .body(())
+            .unwrap();
+        let (rsp_fut, _) = send_req.send_request(req, true).unwrap();
+        let rsp = rsp_fut.await.unwrap();
+        let (parts, mut body) = rsp.into_parts();
+        let mut data = Vec::new();
+        while let Some(chunk) = body.data().await {
+            let chunk = chunk.unwrap();
+            body.flow_control().release_capacity(chunk.len()).unwrap();
+            data.extend_from_slice(&chunk);
+        }
+        let trailers = body.trailers().await.unwrap();
+        // close the client connection to let the server side finish
+        drop(body);
+        drop(send_req);
+        server.await.unwrap();
+        (Response::from_parts(parts, ()), Bytes::from(data), trailers)
+    }
+
+    #[tokio::test]
+    async fn http_forward_fixed_length() {
+        let (rsp, body, _) = forward_response(
+            b"HTTP/1.1 100 Continue\r\n\r\n\
+              HTTP/1.1 200 OK\r\nContent-Length: 5\r\nConnection: keep-alive\r\n\r\nhello",
+        )
+        .await;
+        assert_eq!(rsp.status(), StatusCode::OK);
+        assert_eq!(
+            rsp.headers().get(http::header::CONTENT_LENGTH).unwrap(),
+            "5"
+        );
+        assert!(rsp.headers().get(http::header::CONNECTION).is_none());
+        assert_eq!(body.as_ref(), b"hello");
+    }
+
+    #[tokio::test]
+    async fn http_forward_chunked() {
+        let (rsp, body, trailers) = forward_response(
+            b"HTTP/1.1 404 Not Found\r\nTransfer-Encoding: chunked\r\n\r\n\
+              3\r\nabc\r\n2\r\nde\r\n0\r\nx-checksum: 1\r\n\r\n",
+        )
+        .await;
+        assert_eq!(rsp.status(), StatusCode::NOT_FOUND);
+        assert!(rsp.headers().get(http::header::TRANSFER_ENCODING).is_none());
+        assert_eq!(body.as_ref(), b"abcde");
+        assert_eq!(trailers.unwrap().get("x-checksum").unwrap(), "1");
+    }
+
+    #[tokio::test]
+    async fn http_forward_no_response() {
+        let (clt_io, svr_io) = tokio::io::duplex(4096);
+
+        let server = tokio::spawn(async move {
+            let mut h2c = h2::server::handshake(svr_io).await.unwrap();
+            let (req, mut send_rsp) = h2c.accept().await.unwrap().unwrap();
+            let h2c_task = tokio::spawn(async move { while h2c.accept().await.is_some() {} });
+            let mut rsp_r = BufReader::new(&b""[..]);
+            let r = send_response(
+                &mut rsp_r,
+                &mut send_rsp,
+                req.method(),
+                &LimitedCopyConfig::default(),
+                4096,
+                1024,
+            )
+            .await;
+            assert!(r.is_err());
+            h2c_task.await.unwrap();
+        });
+
+        let (mut send_req, h2c) = client::handshake(clt_io).await.unwrap();
+        tokio::spawn(async move {
+            let _ = h2c.await;
+        });
+        let req = Request::builder()
+            .method(Method::GET)
+            .uri("http://www.example.net/")
+            .body(())
+            .unwrap();
+        let (rsp_fut, _) = send_req.send_request(req, true).unwrap();
+        let e = rsp_fut.await.unwrap_err();
+        assert_eq!(e.reason(), Some(Reason::INTERNAL_ERROR));
+        drop(send_req);
+        server.await.unwrap();
+    }
+}
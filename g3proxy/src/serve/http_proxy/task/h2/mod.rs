@@ -0,0 +1,120 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::io;
+
+use bytes::{Bytes, BytesMut};
+use h2::server::SendResponse;
+use http::{header, HeaderValue, Response, StatusCode, Version};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use super::protocol::{HttpClientWriter, HttpProxyRequest, HttpProxySubProtocol};
+use super::{
+    CommonTaskContext, HttpProxyForwardTask, HttpProxyPipelineStats, HttpProxyServerStats,
+    TcpConnectTaskCltWrapperStats,
+};
+
+mod connection;
+pub(crate) use connection::H2ProxyConnectionTask;
+
+mod connect;
+use connect::H2ProxyConnectTask;
+
+mod connect_udp;
+use connect_udp::H2ProxyConnectUdpTask;
+#[cfg(feature = "quic")]
+pub(super) use connect_udp::{UdpConnectTaskCltWrapperStats, UdpConnectTaskStats};
+
+mod forward;
+use forward::H2ProxyForwardTask;
+
+const H2_CONNECTION_PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
+/// Check if the client is speaking HTTP/2 with prior knowledge.
+///
+/// All data read from the client will be kept in `buf`.
+pub(crate) async fn check_prior_knowledge<R>(reader: &mut R, buf: &mut BytesMut) -> io::Result<bool>
+where
+    R: AsyncRead + Unpin,
+{
+    while buf.len() < H2_CONNECTION_PREFACE.len() {
+        let nr = reader.read_buf(buf).await?;
+        if nr == 0 {
+            // let the HTTP/1.x task handle the closed connection
+            return Ok(false);
+        }
+
+        let len = buf.len().min(H2_CONNECTION_PREFACE.len());
+        if buf[..len] != H2_CONNECTION_PREFACE[..len] {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+fn h2_error_to_io(e: h2::Error) -> io::Error {
+    if e.is_io() {
+        e.into_io().unwrap()
+    } else {
+        io::Error::other(e)
+    }
+}
+
+fn reply_status(clt_send_rsp: &mut SendResponse<Bytes>, status: StatusCode) -> Option<u16> {
+    let mut rsp = Response::new(());
+    *rsp.status_mut() = status;
+    *rsp.version_mut() = Version::HTTP_2;
+    clt_send_rsp
+        .send_response(rsp, true)
+        .ok()
+        .map(|_| status.as_u16())
+}
+
+fn reply_proxy_auth_required(clt_send_rsp: &mut SendResponse<Bytes>, realm: &str) {
+    let mut rsp = Response::new(());
+    *rsp.status_mut() = StatusCode::PROXY_AUTHENTICATION_REQUIRED;
+    *rsp.version_mut() = Version::HTTP_2;
+    if let Ok(value) = HeaderValue::from_str(&format!("Basic realm=\"{realm}\"")) {
+        rsp.headers_mut().insert(header::PROXY_AUTHENTICATE, value);
+    }
+    let _ = clt_send_rsp.send_response(rsp, true);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio_util::io::StreamReader;
+
+    #[tokio::test]
+    async fn prior_knowledge() {
+        let stream = tokio_stream::iter(vec![
+            io::Result::Ok(Bytes::from_static(b"PRI * HTTP/2.0\r\n")),
+            io::Result::Ok(Bytes::from_static(b"\r\nSM\r\n\r\n\x00\x00")),
+        ]);
+        let mut reader = StreamReader::new(stream);
+        let mut buf = BytesMut::with_capacity(64);
+        assert!(check_prior_knowledge(&mut reader, &mut buf).await.unwrap());
+        assert_eq!(buf.len(), H2_CONNECTION_PREFACE.len() + 2);
+
+        let stream = tokio_stream::iter(vec![io::Result::Ok(Bytes::from_static(
+            b"GET http://example.net/ HTTP/1.1\r\n",
+        ))]);
+        let mut reader = StreamReader::new(stream);
+        let mut buf = BytesMut::with_capacity(64);
+        assert!(!check_prior_knowledge(&mut reader, &mut buf).await.unwrap());
+        assert_eq!(&buf[..], b"GET http://example.net/ HTTP/1.1\r\n");
+    }
+}
@@ -0,0 +1,370 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use bytes::Bytes;
+use h3::server::RequestStream;
+use http::{Response, StatusCode, Version};
+use log::debug;
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use g3_daemon::stat::task::TcpStreamTaskStats;
+use g3_io_ext::{LimitedReader, LimitedWriter};
+use g3_types::acl::AclAction;
+use g3_types::net::{ProxyRequestType, UpstreamAddr};
+
+use super::{
+    CommonTaskContext, H3BidiStream, H3StreamReader, H3StreamWriter, TcpConnectTaskCltWrapperStats,
+};
+use crate::audit::AuditContext;
+use crate::config::server::ServerConfig;
+use crate::inspect::StreamInspectContext;
+use crate::log::task::tcp_connect::TaskLogForTcpConnect;
+use crate::module::http_forward::HttpProxyClientResponse;
+use crate::module::tcp_connect::{TcpConnectTaskConf, TcpConnectTaskNotes};
+use crate::serve::task_registry;
+use crate::serve::{
+    ServerStats, ServerTaskError, ServerTaskForbiddenError, ServerTaskNotes, ServerTaskResult,
+    ServerTaskStage,
+};
+
+pub(crate) struct H3ProxyConnectTask {
+    ctx: Arc<CommonTaskContext>,
+    audit_ctx: AuditContext,
+    upstream: UpstreamAddr,
+    task_notes: ServerTaskNotes,
+    tcp_notes: TcpConnectTaskNotes,
+    task_stats: Arc<TcpStreamTaskStats>,
+}
+
+impl H3ProxyConnectTask {
+    pub(crate) fn new(
+        ctx: &Arc<CommonTaskContext>,
+        audit_ctx: AuditContext,
+        upstream: UpstreamAddr,
+        task_notes: ServerTaskNotes,
+    ) -> Self {
+        H3ProxyConnectTask {
+            ctx: Arc::clone(ctx),
+            audit_ctx,
+            upstream,
+            task_notes,
+            tcp_notes: TcpConnectTaskNotes::default(),
+            task_stats: Arc::new(TcpStreamTaskStats::default()),
+        }
+    }
+
+    fn get_log_context(&self) -> TaskLogForTcpConnect {
+        TaskLogForTcpConnect {
+            upstream: &self.upstream,
+            task_notes: &self.task_notes,
+            tcp_notes: &self.tcp_notes,
+            total_time: self.task_notes.time_elapsed(),
+            client_rd_bytes: self.task_stats.clt.read.get_bytes(),
+            client_wr_bytes: self.task_stats.clt.write.get_bytes(),
+            remote_rd_bytes: self.task_stats.ups.read.get_bytes(),
+            remote_wr_bytes: self.task_stats.ups.write.get_bytes(),
+        }
+    }
+
+    pub(crate) async fn into_running(mut self, clt_stream: RequestStream<H3BidiStream, Bytes>) {
+        self.pre_start();
+        match self.run(clt_stream).await {
+            Ok(_) => self
+                .get_log_context()
+                .log(&self.ctx.task_logger, &ServerTaskError::Finished),
+            Err(e) => self.get_log_context().log(&self.ctx.task_logger, &e),
+        }
+        self.pre_stop();
+    }
+
+    fn pre_start(&self) {
+        debug!(
+            "HttpProxy/H3/CONNECT: new client from {} to {} server {}, using escaper {}",
+            self.ctx.client_addr(),
+            self.ctx.server_config.server_type(),
+            self.ctx.server_config.name(),
+            self.ctx.server_config.escaper
+        );
+        self.ctx.server_stats.task_http_connect.add_task();
+        self.ctx.server_stats.task_http_connect.inc_alive_task();
+
+        if let Some(user_ctx) = self.task_notes.user_ctx() {
+            user_ctx.foreach_req_stats(|s| {
+                s.req_total.add_http_connect();
+                s.req_alive.add_http_connect();
+            });
+        }
+    }
+
+    fn pre_stop(&mut self) {
+        self.ctx.server_stats.task_http_connect.dec_alive_task();
+
+        if let Some(user_ctx) = self.task_notes.user_ctx() {
+            user_ctx.foreach_req_stats(|s| {
+                s.req_alive.del_http_connect();
+            });
+
+            if let Some(user_req_alive_permit) = self.task_notes.user_req_alive_permit.take() {
+                drop(user_req_alive_permit);
+            }
+        }
+    }
+
+    async fn handle_server_upstream_acl_action(
+        &self,
+        action: AclAction,
+        clt_stream: &mut RequestStream<H3BidiStream, Bytes>,
+    ) -> ServerTaskResult<()> {
+        if action.forbid_early() {
+            self.ctx.server_stats.forbidden.add_dest_denied();
+            if let Some(user_ctx) = self.task_notes.user_ctx() {
+                // also add to user level forbidden stats
+                user_ctx.add_dest_denied();
+            }
+
+            let _ = super::reply_status(clt_stream, StatusCode::FORBIDDEN).await;
+            Err(ServerTaskError::ForbiddenByRule(
+                ServerTaskForbiddenError::DestDenied,
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    async fn run(
+        &mut self,
+        mut clt_stream: RequestStream<H3BidiStream, Bytes>,
+    ) -> ServerTaskResult<()> {
+        if let Some(user_ctx) = self.task_notes.user_ctx() {
+            let user_ctx = user_ctx.clone();
+
+            if user_ctx.check_rate_limit().is_err() {
+                let _ = super::reply_status(&mut clt_stream, StatusCode::TOO_MANY_REQUESTS).await;
+                return Err(ServerTaskError::ForbiddenByRule(
+                    ServerTaskForbiddenError::RateLimited,
+                ));
+            }
+
+            match user_ctx.acquire_request_semaphore() {
+                Ok(permit) => self.task_notes.user_req_alive_permit = Some(permit),
+                Err(_) => {
+                    let _ =
+                        super::reply_status(&mut clt_stream, StatusCode::TOO_MANY_REQUESTS).await;
+                    return Err(ServerTaskError::ForbiddenByRule(
+                        ServerTaskForbiddenError::FullyLoaded,
+                    ));
+                }
+            }
+
+            let action = user_ctx.check_proxy_request(ProxyRequestType::HttpConnect);
+            if action.forbid_early() {
+                let _ = super::reply_status(&mut clt_stream, StatusCode::METHOD_NOT_ALLOWED).await;
+                return Err(ServerTaskError::ForbiddenByRule(
+                    ServerTaskForbiddenError::ProtoBanned,
+                ));
+            }
+
+            let action = user_ctx.check_upstream(&self.upstream);
+            if action.forbid_early() {
+                let _ = super::reply_status(&mut clt_stream, StatusCode::FORBIDDEN).await;
+                return Err(ServerTaskError::ForbiddenByRule(
+                    ServerTaskForbiddenError::DestDenied,
+                ));
+            }
+        }
+
+        // server level dst host/port acl rules
+        let action = self.ctx.check_upstream(&self.upstream);
+        self.handle_server_upstream_acl_action(action, &mut clt_stream)
+            .await?;
+
+        self.task_notes.stage = ServerTaskStage::Connecting;
+        let task_conf = TcpConnectTaskConf {
+            upstream: &self.upstream,
+        };
+        let (ups_r, ups_w) = match self
+            .ctx
+            .escaper
+            .tcp_setup_connection(
+                &task_conf,
+                &mut self.tcp_notes,
+                &self.task_notes,
+                self.task_stats.clone(),
+                &mut self.audit_ctx,
+            )
+            .await
+        {
+            Ok(connection) => connection,
+            Err(e) => {
+                let rsp =
+                    HttpProxyClientResponse::from_tcp_connect_error(&e, Version::HTTP_11, false);
+                if let Ok(status) = StatusCode::from_u16(rsp.status()) {
+                    let _ = super::reply_status(&mut clt_stream, status).await;
+                }
+                return Err(e.into());
+            }
+        };
+        self.task_notes.stage = ServerTaskStage::Connected;
+
+        self.task_notes.stage = ServerTaskStage::Replying;
+        let mut rsp = Response::new(());
+        *rsp.version_mut() = Version::HTTP_3;
+        clt_stream
+            .send_response(rsp)
+            .await
+            .map_err(|e| ServerTaskError::ClientAppError(anyhow!("send response failed: {e}")))?;
+
+        self.task_notes.mark_relaying();
+        if let Some(user_ctx) = self.task_notes.user_ctx() {
+            user_ctx.foreach_req_stats(|s| {
+                s.req_ready.add_http_connect();
+            });
+        }
+
+        let (clt_send_stream, clt_recv_stream) = clt_stream.split();
+        let clt_r = H3StreamReader::new(clt_recv_stream);
+        let clt_w = H3StreamWriter::new(clt_send_stream);
+        let alive_task = task_registry::register(
+            self.ctx.server_config.name(),
+            "HttpProxy/H3/CONNECT",
+            &self.task_notes,
+            &self.upstream,
+            &self.tcp_notes.escaper,
+            &self.task_stats,
+        );
+        tokio::select! {
+            r = self.relay(clt_r, clt_w, ups_r, ups_w) => r,
+            _ = alive_task.killed() => Err(ServerTaskError::CanceledAsTaskKilled),
+        }
+    }
+
+    async fn relay<CDR, CDW, UR, UW>(
+        &mut self,
+        clt_r: CDR,
+        clt_w: CDW,
+        ups_r: UR,
+        ups_w: UW,
+    ) -> ServerTaskResult<()>
+    where
+        CDR: AsyncRead + Send + Sync + Unpin + 'static,
+        CDW: AsyncWrite + Send + Sync + Unpin + 'static,
+        UR: AsyncRead + Send + Sync + Unpin + 'static,
+        UW: AsyncWrite + Send + Sync + Unpin + 'static,
+    {
+        let (clt_r, clt_w) = self.update_clt(clt_r, clt_w);
+
+        if let Some(audit_handle) = self.audit_ctx.handle() {
+            let audit_task = self
+                .task_notes
+                .user_ctx()
+                .map(|ctx| {
+                    let user_config = &ctx.user_config().audit;
+                    user_config.enable_protocol_inspection
+                        && user_config
+                            .do_task_audit()
+                            .unwrap_or_else(|| audit_handle.do_task_audit())
+                })
+                .unwrap_or_else(|| audit_handle.do_task_audit());
+
+            if audit_task {
+                let ctx = StreamInspectContext::new(
+                    audit_handle.clone(),
+                    self.ctx.server_config.clone(),
+                    self.ctx.server_stats.clone(),
+                    self.ctx.server_quit_policy.clone(),
+                    &self.task_notes,
+                );
+                return crate::inspect::stream::transit_with_inspection(
+                    clt_r,
+                    clt_w,
+                    ups_r,
+                    ups_w,
+                    ctx,
+                    self.upstream.clone(),
+                    None,
+                )
+                .await;
+            }
+        }
+
+        crate::inspect::stream::transit_transparent(
+            clt_r,
+            clt_w,
+            ups_r,
+            ups_w,
+            &self.ctx.server_config,
+            &self.ctx.server_quit_policy,
+            self.task_notes.user_ctx().map(|ctx| ctx.user()),
+        )
+        .await
+    }
+
+    fn update_clt<CDR, CDW>(
+        &self,
+        clt_r: CDR,
+        clt_w: CDW,
+    ) -> (LimitedReader<CDR>, LimitedWriter<CDW>)
+    where
+        CDR: AsyncRead + Unpin,
+        CDW: AsyncWrite + Unpin,
+    {
+        let mut wrapper_stats =
+            TcpConnectTaskCltWrapperStats::new(&self.ctx.server_stats, &self.task_stats);
+
+        let limit_config = if let Some(user_ctx) = self.task_notes.user_ctx() {
+            wrapper_stats.push_user_io_stats(user_ctx.fetch_traffic_stats(
+                self.ctx.server_config.name(),
+                self.ctx.server_stats.share_extra_tags(),
+            ));
+
+            user_ctx
+                .user_config()
+                .tcp_sock_speed_limit
+                .shrink_as_smaller(&self.ctx.server_config.tcp_sock_speed_limit)
+        } else {
+            self.ctx.server_config.tcp_sock_speed_limit
+        };
+
+        let wrapper_stats = Arc::new(wrapper_stats);
+        let mut clt_r = LimitedReader::local_limited(
+            clt_r,
+            limit_config.shift_millis,
+            limit_config.max_north,
+            wrapper_stats.clone(),
+        );
+        let mut clt_w = LimitedWriter::local_limited(
+            clt_w,
+            limit_config.shift_millis,
+            limit_config.max_south,
+            wrapper_stats,
+        );
+
+        if let Some(user_ctx) = self.task_notes.user_ctx() {
+            let user = user_ctx.user();
+            if let Some(limiter) = user.tcp_all_upload_speed_limit() {
+                clt_r.add_global_limiter(limiter.clone());
+            }
+            if let Some(limiter) = user.tcp_all_download_speed_limit() {
+                clt_w.add_global_limiter(limiter.clone());
+            }
+        }
+
+        (clt_r, clt_w)
+    }
+}
@@ -0,0 +1,48 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use super::{
+    datagram, h3_error_to_io, reply_status, CommonTaskContext, H3BidiStream, H3RecvStream,
+    H3StreamWriter, UdpConnectTaskCltWrapperStats, UdpConnectTaskStats,
+};
+
+mod task;
+pub(super) use task::H3ProxyConnectUdpTask;
+
+mod recv;
+mod send;
+
+use recv::H3ConnectUdpClientRecv;
+use send::H3ConnectUdpClientSend;
+
+/// Whether to send UDP payloads to the client in HTTP datagrams.
+///
+/// We only switch to HTTP datagrams after the client has sent one to us, as HTTP datagrams
+/// need to be negotiated by both sides in HTTP/3 SETTINGS.
+#[derive(Default)]
+struct DatagramFlag(AtomicBool);
+
+impl DatagramFlag {
+    fn mark_used(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    fn is_used(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
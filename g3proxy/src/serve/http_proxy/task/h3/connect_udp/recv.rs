@@ -0,0 +1,282 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::io;
+use std::sync::Arc;
+use std::task::{ready, Context, Poll};
+
+use bytes::{Buf, Bytes, BytesMut};
+use tokio::sync::mpsc;
+
+use g3_http::capsule::{self, CapsuleHeader};
+use g3_io_ext::{LimitedRecvStats, UdpCopyClientError, UdpCopyClientRecv};
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    target_os = "macos",
+))]
+use g3_io_ext::{UdpCopyPacket, UdpCopyPacketMeta};
+
+use super::{h3_error_to_io, DatagramFlag, H3RecvStream, UdpConnectTaskCltWrapperStats};
+
+pub(super) struct H3ConnectUdpClientRecv {
+    recv_stream: H3RecvStream,
+    datagram_receiver: mpsc::Receiver<Bytes>,
+    datagram_flag: Arc<DatagramFlag>,
+    buf: BytesMut,
+    stats: Arc<UdpConnectTaskCltWrapperStats>,
+}
+
+impl H3ConnectUdpClientRecv {
+    pub(super) fn new(
+        recv_stream: H3RecvStream,
+        datagram_receiver: mpsc::Receiver<Bytes>,
+        datagram_flag: Arc<DatagramFlag>,
+        stats: Arc<UdpConnectTaskCltWrapperStats>,
+    ) -> Self {
+        H3ConnectUdpClientRecv {
+            recv_stream,
+            datagram_receiver,
+            datagram_flag,
+            buf: BytesMut::new(),
+            stats,
+        }
+    }
+
+    fn copy_payload(&self, payload: &[u8], buf: &mut [u8]) -> Result<usize, UdpCopyClientError> {
+        if payload.len() > buf.len() {
+            return Err(UdpCopyClientError::InvalidPacket(format!(
+                "too large udp payload size {}",
+                payload.len()
+            )));
+        }
+        buf[..payload.len()].copy_from_slice(payload);
+        self.stats.add_recv_bytes(payload.len());
+        self.stats.add_recv_packet();
+        Ok(payload.len())
+    }
+
+    /// Copy the next UDP payload in DATAGRAM capsules into `buf`, return the payload length.
+    fn take_capsule(&mut self, buf: &mut [u8]) -> Result<Option<usize>, UdpCopyClientError> {
+        match take_capsule_udp_payload(&mut self.buf) {
+            Some(payload) => self.copy_payload(&payload, buf).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Copy the next UDP payload in HTTP datagrams into `buf`, return the payload length.
+    fn take_datagram(&mut self, buf: &mut [u8]) -> Result<Option<usize>, UdpCopyClientError> {
+        while let Ok(data) = self.datagram_receiver.try_recv() {
+            if let Some(payload) = capsule::udp_payload_in_datagram(&data) {
+                self.datagram_flag.mark_used();
+                return self.copy_payload(payload, buf).map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    fn poll_recv(
+        &mut self,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize, UdpCopyClientError>> {
+        loop {
+            if let Some(len) = self.take_capsule(buf)? {
+                return Poll::Ready(Ok(len));
+            }
+
+            if let Poll::Ready(Some(data)) = self.datagram_receiver.poll_recv(cx) {
+                if let Some(payload) = capsule::udp_payload_in_datagram(&data) {
+                    self.datagram_flag.mark_used();
+                    return Poll::Ready(self.copy_payload(payload, buf));
+                }
+                continue;
+            }
+
+            match ready!(self.recv_stream.poll_recv_data(cx)) {
+                Ok(Some(mut data)) => {
+                    while data.has_remaining() {
+                        let chunk = data.chunk();
+                        let len = chunk.len();
+                        self.buf.extend_from_slice(chunk);
+                        data.advance(len);
+                    }
+                }
+                Ok(None) => {
+                    return Poll::Ready(Err(UdpCopyClientError::RecvFailed(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "client stream closed",
+                    ))))
+                }
+                Err(e) => {
+                    return Poll::Ready(Err(UdpCopyClientError::RecvFailed(h3_error_to_io(e))))
+                }
+            }
+        }
+    }
+
+    fn take_next(&mut self, buf: &mut [u8]) -> Result<Option<usize>, UdpCopyClientError> {
+        if let Some(len) = self.take_capsule(buf)? {
+            return Ok(Some(len));
+        }
+        self.take_datagram(buf)
+    }
+}
+
+/// Take the UDP payload in the next complete DATAGRAM capsule out of `buf`.
+///
+/// Unknown capsules and DATAGRAM capsules with non-zero context id will be skipped.
+fn take_capsule_udp_payload(buf: &mut BytesMut) -> Option<BytesMut> {
+    loop {
+        let header = CapsuleHeader::try_parse(buf)?;
+        let total_length = header.total_length();
+        if buf.len() < total_length {
+            return None;
+        }
+
+        let mut capsule = buf.split_to(total_length);
+        if header.capsule_type != capsule::CAPSULE_TYPE_DATAGRAM {
+            // unknown capsules should be skipped
+            continue;
+        }
+        let Some(payload) = capsule::udp_payload_in_datagram(&capsule[header.header_length..])
+        else {
+            continue;
+        };
+        let offset = total_length - payload.len();
+        return Some(capsule.split_off(offset));
+    }
+}
+
+impl UdpCopyClientRecv for H3ConnectUdpClientRecv {
+    fn max_hdr_len(&self) -> usize {
+        0
+    }
+
+    fn poll_recv_packet(
+        &mut self,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<(usize, usize), UdpCopyClientError>> {
+        let len = ready!(self.poll_recv(cx, buf))?;
+        Poll::Ready(Ok((0, len)))
+    }
+
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd",
+        target_os = "macos",
+    ))]
+    fn poll_recv_packets(
+        &mut self,
+        cx: &mut Context<'_>,
+        packets: &mut [UdpCopyPacket],
+    ) -> Poll<Result<usize, UdpCopyClientError>> {
+        let mut count = 0;
+        for p in packets.iter_mut() {
+            let r = if count == 0 {
+                ready!(self.poll_recv(cx, p.buf_mut()))?
+            } else {
+                // only return already received payloads for the following packets
+                match self.take_next(p.buf_mut())? {
+                    Some(len) => len,
+                    None => break,
+                }
+            };
+            let iov = std::io::IoSliceMut::new(p.buf_mut());
+            UdpCopyPacketMeta::new(&iov, 0, r).set_packet(p);
+            count += 1;
+        }
+        Poll::Ready(Ok(count))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capsule_split() {
+        let mut data = Vec::new();
+        capsule::encode_udp_datagram_capsule(b"hello", &mut data);
+        capsule::encode_udp_datagram_capsule(b"world!", &mut data);
+
+        let mut buf = BytesMut::new();
+        let mut payloads = Vec::new();
+        // feed one byte at a time, like a fragmented request stream
+        for b in &data {
+            buf.extend_from_slice(&[*b]);
+            if let Some(payload) = take_capsule_udp_payload(&mut buf) {
+                payloads.push(payload);
+            }
+        }
+        assert_eq!(payloads, [&b"hello"[..], &b"world!"[..]]);
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn capsule_batch() {
+        let mut buf = BytesMut::new();
+        capsule::encode_udp_datagram_capsule(b"a", &mut buf);
+        capsule::encode_udp_datagram_capsule(b"", &mut buf);
+        capsule::encode_udp_datagram_capsule(&[0xffu8; 300], &mut buf);
+        capsule::encode_udp_datagram_capsule(b"b", &mut buf);
+
+        assert_eq!(take_capsule_udp_payload(&mut buf).unwrap(), &b"a"[..]);
+        assert_eq!(take_capsule_udp_payload(&mut buf).unwrap(), &b""[..]);
+        assert_eq!(
+            take_capsule_udp_payload(&mut buf).unwrap(),
+            &[0xffu8; 300][..]
+        );
+        assert_eq!(take_capsule_udp_payload(&mut buf).unwrap(), &b"b"[..]);
+        assert!(take_capsule_udp_payload(&mut buf).is_none());
+    }
+
+    #[test]
+    fn capsule_skipped() {
+        let mut buf = BytesMut::new();
+        // unknown capsule type 0x29 with 3 bytes value
+        buf.extend_from_slice(&[0x29, 0x03, 0x01, 0x02, 0x03]);
+        // DATAGRAM capsule with context id 2
+        buf.extend_from_slice(&[0x00, 0x03, 0x02, 0x61, 0x62]);
+        capsule::encode_udp_datagram_capsule(b"udp", &mut buf);
+
+        assert_eq!(take_capsule_udp_payload(&mut buf).unwrap(), &b"udp"[..]);
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn capsule_incomplete() {
+        let mut buf = BytesMut::new();
+        capsule::encode_udp_datagram_capsule(b"payload", &mut buf);
+        let mut partial = buf.split_to(buf.len() - 1);
+        assert!(take_capsule_udp_payload(&mut partial).is_none());
+        // nothing should be consumed
+        assert_eq!(partial.len(), capsule::udp_datagram_capsule_len(7) - 1);
+
+        partial.unsplit(buf);
+        assert_eq!(
+            take_capsule_udp_payload(&mut partial).unwrap(),
+            &b"payload"[..]
+        );
+    }
+}
@@ -0,0 +1,148 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::io;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{ready, Context, Poll};
+
+use bytes::BytesMut;
+use quinn::{Connection, SendDatagramError};
+use tokio::io::AsyncWrite;
+
+use g3_http::capsule;
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    target_os = "macos",
+))]
+use g3_io_ext::UdpCopyPacket;
+use g3_io_ext::{LimitedSendStats, UdpCopyClientError, UdpCopyClientSend};
+
+use super::{datagram, DatagramFlag, H3StreamWriter, UdpConnectTaskCltWrapperStats};
+
+pub(super) struct H3ConnectUdpClientSend {
+    send_stream: H3StreamWriter,
+    connection: Connection,
+    quarter_stream_id: u64,
+    datagram_flag: Arc<DatagramFlag>,
+    stats: Arc<UdpConnectTaskCltWrapperStats>,
+}
+
+impl H3ConnectUdpClientSend {
+    pub(super) fn new(
+        send_stream: H3StreamWriter,
+        connection: Connection,
+        quarter_stream_id: u64,
+        datagram_flag: Arc<DatagramFlag>,
+        stats: Arc<UdpConnectTaskCltWrapperStats>,
+    ) -> Self {
+        H3ConnectUdpClientSend {
+            send_stream,
+            connection,
+            quarter_stream_id,
+            datagram_flag,
+            stats,
+        }
+    }
+
+    /// Try to send the payload in a HTTP datagram, return false if we should use capsules.
+    fn try_send_datagram(&self, payload: &[u8]) -> Result<bool, UdpCopyClientError> {
+        if !self.datagram_flag.is_used() {
+            return Ok(false);
+        }
+
+        let datagram_len = datagram::udp_datagram_len(self.quarter_stream_id, payload.len());
+        match self.connection.max_datagram_size() {
+            Some(max_size) if datagram_len <= max_size => {}
+            _ => return Ok(false),
+        }
+
+        let data = datagram::encode_udp_datagram(self.quarter_stream_id, payload);
+        match self.connection.send_datagram(data) {
+            Ok(_) => Ok(true),
+            Err(SendDatagramError::ConnectionLost(e)) => Err(UdpCopyClientError::SendFailed(
+                io::Error::new(io::ErrorKind::BrokenPipe, e),
+            )),
+            Err(_) => Ok(false),
+        }
+    }
+
+    fn poll_send(
+        &mut self,
+        cx: &mut Context<'_>,
+        payload: &[u8],
+    ) -> Poll<Result<usize, UdpCopyClientError>> {
+        if !self.try_send_datagram(payload)? {
+            let capsule_len = capsule::udp_datagram_capsule_len(payload.len());
+            let mut buf = BytesMut::with_capacity(capsule_len);
+            capsule::encode_udp_datagram_capsule(payload, &mut buf);
+            // the whole capsule will be accepted in a single write
+            ready!(Pin::new(&mut self.send_stream).poll_write(cx, &buf))
+                .map_err(UdpCopyClientError::SendFailed)?;
+        }
+        self.stats.add_send_bytes(payload.len());
+        self.stats.add_send_packet();
+        Poll::Ready(Ok(payload.len().max(1)))
+    }
+}
+
+impl UdpCopyClientSend for H3ConnectUdpClientSend {
+    fn poll_send_packet(
+        &mut self,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, UdpCopyClientError>> {
+        self.poll_send(cx, buf)
+    }
+
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd",
+        target_os = "macos",
+    ))]
+    fn poll_send_packets(
+        &mut self,
+        cx: &mut Context<'_>,
+        packets: &[UdpCopyPacket],
+    ) -> Poll<Result<usize, UdpCopyClientError>> {
+        let mut count = 0;
+        for p in packets {
+            match self.poll_send(cx, p.payload()) {
+                Poll::Ready(Ok(_)) => count += 1,
+                Poll::Ready(Err(e)) => {
+                    if count == 0 {
+                        return Poll::Ready(Err(e));
+                    }
+                    break;
+                }
+                Poll::Pending => {
+                    if count == 0 {
+                        return Poll::Pending;
+                    }
+                    break;
+                }
+            }
+        }
+        Poll::Ready(Ok(count))
+    }
+}
@@ -0,0 +1,394 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::io;
+use std::sync::Arc;
+
+use bytes::Bytes;
+use h3::server::RequestStream;
+use http::{HeaderName, HeaderValue, Response, StatusCode, Version};
+use log::debug;
+use quinn::Connection;
+use slog::Logger;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+
+use g3_io_ext::{
+    LimitedUdpRelayConfig, UdpCopyClientError, UdpCopyClientRecv, UdpCopyClientSend,
+    UdpCopyClientToRemote, UdpCopyError, UdpCopyRemoteRecv, UdpCopyRemoteSend,
+    UdpCopyRemoteToClient,
+};
+use g3_types::acl::AclAction;
+use g3_types::net::{ProxyRequestType, UpstreamAddr};
+
+use super::{
+    h3_error_to_io, reply_status, CommonTaskContext, DatagramFlag, H3BidiStream,
+    H3ConnectUdpClientRecv, H3ConnectUdpClientSend, H3StreamWriter, UdpConnectTaskCltWrapperStats,
+    UdpConnectTaskStats,
+};
+use crate::config::server::ServerConfig;
+use crate::log::escape::udp_sendto::EscapeLogForUdpConnectSendTo;
+use crate::log::task::udp_connect::TaskLogForUdpConnect;
+use crate::module::udp_connect::{UdpConnectTaskConf, UdpConnectTaskNotes};
+use crate::serve::task_registry;
+use crate::serve::{
+    ServerStats, ServerTaskError, ServerTaskForbiddenError, ServerTaskNotes, ServerTaskResult,
+    ServerTaskStage,
+};
+
+const CAPSULE_PROTOCOL: HeaderName = HeaderName::from_static("capsule-protocol");
+
+pub(crate) struct H3ProxyConnectUdpTask {
+    ctx: Arc<CommonTaskContext>,
+    upstream: UpstreamAddr,
+    udp_notes: UdpConnectTaskNotes,
+    task_notes: ServerTaskNotes,
+    task_stats: Arc<UdpConnectTaskStats>,
+}
+
+impl H3ProxyConnectUdpTask {
+    pub(crate) fn new(
+        ctx: &Arc<CommonTaskContext>,
+        upstream: UpstreamAddr,
+        task_notes: ServerTaskNotes,
+    ) -> Self {
+        H3ProxyConnectUdpTask {
+            ctx: Arc::clone(ctx),
+            upstream,
+            udp_notes: UdpConnectTaskNotes::default(),
+            task_notes,
+            task_stats: Arc::new(UdpConnectTaskStats::default()),
+        }
+    }
+
+    fn get_log_context(&self) -> TaskLogForUdpConnect {
+        TaskLogForUdpConnect {
+            task_notes: &self.task_notes,
+            tcp_server_addr: self.ctx.cc_info.server_addr(),
+            tcp_client_addr: self.ctx.client_addr(),
+            udp_listen_addr: None,
+            udp_client_addr: None,
+            upstream: Some(&self.upstream),
+            udp_notes: &self.udp_notes,
+            total_time: self.task_notes.time_elapsed(),
+            client_rd_bytes: self.task_stats.clt.recv.get_bytes(),
+            client_rd_packets: self.task_stats.clt.recv.get_packets(),
+            client_wr_bytes: self.task_stats.clt.send.get_bytes(),
+            client_wr_packets: self.task_stats.clt.send.get_packets(),
+            remote_rd_bytes: self.task_stats.ups.recv.get_bytes(),
+            remote_rd_packets: self.task_stats.ups.recv.get_packets(),
+            remote_wr_bytes: self.task_stats.ups.send.get_bytes(),
+            remote_wr_packets: self.task_stats.ups.send.get_packets(),
+        }
+    }
+
+    pub(crate) async fn into_running(
+        mut self,
+        clt_stream: RequestStream<H3BidiStream, Bytes>,
+        connection: Connection,
+        datagram_receiver: mpsc::Receiver<Bytes>,
+    ) {
+        self.pre_start();
+        match self.run(clt_stream, connection, datagram_receiver).await {
+            Ok(_) => self
+                .get_log_context()
+                .log(&self.ctx.task_logger, &ServerTaskError::ClosedByClient),
+            Err(e) => self.get_log_context().log(&self.ctx.task_logger, &e),
+        }
+        self.pre_stop();
+    }
+
+    fn pre_start(&self) {
+        debug!(
+            "HttpProxy/H3/CONNECT-UDP: new client from {} to {} server {}, using escaper {}",
+            self.ctx.client_addr(),
+            self.ctx.server_config.server_type(),
+            self.ctx.server_config.name(),
+            self.ctx.server_config.escaper
+        );
+        self.ctx.server_stats.task_http_connect_udp.add_task();
+        self.ctx.server_stats.task_http_connect_udp.inc_alive_task();
+
+        if let Some(user_ctx) = self.task_notes.user_ctx() {
+            user_ctx.foreach_req_stats(|s| {
+                s.req_total.add_http_connect_udp();
+                s.req_alive.add_http_connect_udp();
+            });
+        }
+    }
+
+    fn pre_stop(&mut self) {
+        self.ctx.server_stats.task_http_connect_udp.dec_alive_task();
+
+        if let Some(user_ctx) = self.task_notes.user_ctx() {
+            user_ctx.foreach_req_stats(|s| s.req_alive.del_http_connect_udp());
+
+            if let Some(user_req_alive_permit) = self.task_notes.user_req_alive_permit.take() {
+                drop(user_req_alive_permit);
+            }
+        }
+    }
+
+    async fn handle_server_upstream_acl_action(
+        &self,
+        action: AclAction,
+        clt_stream: &mut RequestStream<H3BidiStream, Bytes>,
+    ) -> ServerTaskResult<()> {
+        if action.forbid_early() {
+            self.ctx.server_stats.forbidden.add_dest_denied();
+            if let Some(user_ctx) = self.task_notes.user_ctx() {
+                // also add to user level forbidden stats
+                user_ctx.add_dest_denied();
+            }
+
+            let _ = reply_status(clt_stream, StatusCode::FORBIDDEN).await;
+            Err(ServerTaskError::ForbiddenByRule(
+                ServerTaskForbiddenError::DestDenied,
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    async fn run(
+        &mut self,
+        mut clt_stream: RequestStream<H3BidiStream, Bytes>,
+        connection: Connection,
+        datagram_receiver: mpsc::Receiver<Bytes>,
+    ) -> ServerTaskResult<()> {
+        if let Some(user_ctx) = self.task_notes.user_ctx() {
+            let user_ctx = user_ctx.clone();
+
+            if user_ctx.check_rate_limit().is_err() {
+                let _ = reply_status(&mut clt_stream, StatusCode::TOO_MANY_REQUESTS).await;
+                return Err(ServerTaskError::ForbiddenByRule(
+                    ServerTaskForbiddenError::RateLimited,
+                ));
+            }
+
+            match user_ctx.acquire_request_semaphore() {
+                Ok(permit) => self.task_notes.user_req_alive_permit = Some(permit),
+                Err(_) => {
+                    let _ = reply_status(&mut clt_stream, StatusCode::TOO_MANY_REQUESTS).await;
+                    return Err(ServerTaskError::ForbiddenByRule(
+                        ServerTaskForbiddenError::FullyLoaded,
+                    ));
+                }
+            }
+
+            let action = user_ctx.check_proxy_request(ProxyRequestType::HttpConnectUdp);
+            if action.forbid_early() {
+                let _ = reply_status(&mut clt_stream, StatusCode::METHOD_NOT_ALLOWED).await;
+                return Err(ServerTaskError::ForbiddenByRule(
+                    ServerTaskForbiddenError::ProtoBanned,
+                ));
+            }
+
+            let action = user_ctx.check_upstream(&self.upstream);
+            if action.forbid_early() {
+                let _ = reply_status(&mut clt_stream, StatusCode::FORBIDDEN).await;
+                return Err(ServerTaskError::ForbiddenByRule(
+                    ServerTaskForbiddenError::DestDenied,
+                ));
+            }
+        }
+
+        // server level dst host/port acl rules
+        let action = self.ctx.check_upstream(&self.upstream);
+        self.handle_server_upstream_acl_action(action, &mut clt_stream)
+            .await?;
+
+        self.task_notes.stage = ServerTaskStage::Connecting;
+        let sock_buf = self
+            .ctx
+            .server_config
+            .h3_config
+            .as_ref()
+            .map(|c| c.udp_socket_buffer)
+            .unwrap_or_default();
+        let task_conf = UdpConnectTaskConf {
+            upstream: &self.upstream,
+            sock_buf,
+        };
+        let (ups_r, ups_w, escape_logger) = match self
+            .ctx
+            .escaper
+            .udp_setup_connection(
+                &task_conf,
+                &mut self.udp_notes,
+                &self.task_notes,
+                self.task_stats.clone(),
+            )
+            .await
+        {
+            Ok(connection) => connection,
+            Err(e) => {
+                let _ = reply_status(&mut clt_stream, StatusCode::BAD_GATEWAY).await;
+                return Err(e.into());
+            }
+        };
+        self.task_notes.stage = ServerTaskStage::Connected;
+
+        self.task_notes.stage = ServerTaskStage::Replying;
+        let mut rsp = Response::new(());
+        *rsp.version_mut() = Version::HTTP_3;
+        rsp.headers_mut()
+            .insert(CAPSULE_PROTOCOL, HeaderValue::from_static("?1"));
+        clt_stream
+            .send_response(rsp)
+            .await
+            .map_err(|e| ServerTaskError::ClientTcpWriteFailed(h3_error_to_io(e)))?;
+
+        let mut wrapper_stats =
+            UdpConnectTaskCltWrapperStats::new(&self.ctx.server_stats, &self.task_stats);
+        if let Some(user_ctx) = self.task_notes.user_ctx() {
+            wrapper_stats.push_user_io_stats(user_ctx.fetch_traffic_stats(
+                self.ctx.server_config.name(),
+                self.ctx.server_stats.share_extra_tags(),
+            ));
+        }
+        let wrapper_stats = Arc::new(wrapper_stats);
+        let quarter_stream_id = clt_stream.id().index();
+        let (clt_send_stream, clt_recv_stream) = clt_stream.split();
+        let datagram_flag = Arc::new(DatagramFlag::default());
+        let clt_r = H3ConnectUdpClientRecv::new(
+            clt_recv_stream,
+            datagram_receiver,
+            datagram_flag.clone(),
+            wrapper_stats.clone(),
+        );
+        let clt_w = H3ConnectUdpClientSend::new(
+            H3StreamWriter::new(clt_send_stream),
+            connection,
+            quarter_stream_id,
+            datagram_flag,
+            wrapper_stats,
+        );
+
+        self.task_notes.mark_relaying();
+        if let Some(user_ctx) = self.task_notes.user_ctx() {
+            user_ctx.foreach_req_stats(|s| s.req_ready.add_http_connect_udp());
+        }
+        let alive_task = task_registry::register(
+            self.ctx.server_config.name(),
+            "HttpProxy/H3/CONNECT-UDP",
+            &self.task_notes,
+            &self.upstream,
+            &self.udp_notes.escaper,
+            &self.task_stats,
+        );
+        tokio::select! {
+            r = self.run_relay(
+                Box::new(clt_r),
+                Box::new(clt_w),
+                ups_r,
+                ups_w,
+                &escape_logger,
+            ) => r,
+            _ = alive_task.killed() => Err(ServerTaskError::CanceledAsTaskKilled),
+        }
+    }
+
+    async fn run_relay<'a>(
+        &'a mut self,
+        mut clt_r: Box<dyn UdpCopyClientRecv + Unpin + Send>,
+        mut clt_w: Box<dyn UdpCopyClientSend + Unpin + Send>,
+        mut ups_r: Box<dyn UdpCopyRemoteRecv + Unpin + Send>,
+        mut ups_w: Box<dyn UdpCopyRemoteSend + Unpin + Send>,
+        escape_logger: &'a Logger,
+    ) -> ServerTaskResult<()> {
+        let task_id = &self.task_notes.id;
+
+        let relay_config = LimitedUdpRelayConfig::default();
+        let mut c_to_r = UdpCopyClientToRemote::new(&mut *clt_r, &mut *ups_w, relay_config);
+        let mut r_to_c = UdpCopyRemoteToClient::new(&mut *clt_w, &mut *ups_r, relay_config);
+
+        let idle_duration = self.ctx.server_config.task_idle_check_duration;
+        let mut idle_interval =
+            tokio::time::interval_at(Instant::now() + idle_duration, idle_duration);
+        let mut idle_count = 0;
+        loop {
+            tokio::select! {
+                r = &mut c_to_r => {
+                    return match r {
+                        Ok(_) => Ok(()),
+                        Err(UdpCopyError::RemoteError(e)) => {
+                            EscapeLogForUdpConnectSendTo {
+                                task_id,
+                                upstream: Some(&self.upstream),
+                                udp_notes: &self.udp_notes,
+                            }
+                            .log(escape_logger, &e);
+                            Err(e.into())
+                        }
+                        Err(UdpCopyError::ClientError(UdpCopyClientError::RecvFailed(e)))
+                            if e.kind() == io::ErrorKind::UnexpectedEof => Ok(()),
+                        Err(UdpCopyError::ClientError(e)) => Err(e.into()),
+                    };
+                }
+                r = &mut r_to_c => {
+                    return match r {
+                        Ok(_) => Ok(()),
+                        Err(UdpCopyError::RemoteError(e)) => {
+                            EscapeLogForUdpConnectSendTo {
+                                task_id,
+                                upstream: Some(&self.upstream),
+                                udp_notes: &self.udp_notes,
+                            }
+                            .log(escape_logger, &e);
+                            Err(e.into())
+                        }
+                        Err(UdpCopyError::ClientError(e)) => Err(e.into()),
+                    };
+                }
+                _ = idle_interval.tick() => {
+                    if c_to_r.is_idle() && r_to_c.is_idle() {
+                        idle_count += 1;
+
+                        let quit = if let Some(user_ctx) = self.task_notes.user_ctx() {
+                            let user = user_ctx.user();
+                            if user.is_blocked() {
+                                return Err(ServerTaskError::CanceledAsUserBlocked);
+                            }
+                            idle_count >= user.task_max_idle_count()
+                        } else {
+                            idle_count >= self.ctx.server_config.task_idle_max_count
+                        };
+
+                        if quit {
+                            return Err(ServerTaskError::Idle(idle_duration, idle_count));
+                        }
+                    } else {
+                        idle_count = 0;
+
+                        c_to_r.reset_active();
+                        r_to_c.reset_active();
+                    }
+
+                    if let Some(user_ctx) = self.task_notes.user_ctx() {
+                        if user_ctx.user().is_blocked() {
+                            return Err(ServerTaskError::CanceledAsUserBlocked);
+                        }
+                    }
+
+                    if self.ctx.server_quit_policy.force_quit() {
+                        return Err(ServerTaskError::CanceledAsServerQuit)
+                    }
+                }
+            }
+        }
+    }
+}
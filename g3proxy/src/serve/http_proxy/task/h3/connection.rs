@@ -0,0 +1,472 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::str::FromStr;
+use std::sync::Arc;
+
+use ahash::AHashMap;
+use bytes::Bytes;
+use h3::error::{Code, ErrorLevel};
+use h3::ext::Protocol;
+use h3::server::RequestStream;
+use http::{header, HeaderMap, Method, Request, StatusCode};
+use log::debug;
+use quinn::{Connection, VarInt};
+use tokio::time::Instant;
+
+use g3_http::server::{HttpProxyClientRequest, HttpRequestParseError, UriExt};
+use g3_types::auth::UserAuthError;
+use g3_types::net::{HttpAuth, HttpBasicAuth, HttpUpgradeToken, UpstreamAddr};
+
+use super::{
+    CommonTaskContext, H3BidiStream, H3DatagramDispatcher, H3ProxyConnectTask,
+    H3ProxyConnectUdpTask, HttpProxyPipelineStats,
+};
+use crate::audit::AuditContext;
+use crate::auth::{UserContext, UserGroup, UserRequestStats};
+use crate::config::server::ServerConfig;
+use crate::escape::EgressPathSelection;
+use crate::serve::{ServerStats, ServerTaskNotes};
+
+struct UserData {
+    req_stats: Arc<UserRequestStats>,
+    site_req_stats: Option<Arc<UserRequestStats>>,
+}
+
+impl Drop for UserData {
+    fn drop(&mut self) {
+        self.req_stats.l7_conn_alive.dec_http();
+        if let Some(site_req_stats) = &self.site_req_stats {
+            site_req_stats.l7_conn_alive.dec_http();
+        }
+    }
+}
+
+enum H3ProxyStreamType {
+    TcpConnect,
+    UdpConnect,
+}
+
+pub(crate) struct H3ProxyConnectionTask {
+    ctx: Arc<CommonTaskContext>,
+    audit_ctx: AuditContext,
+    user_group: Option<Arc<UserGroup>>,
+    stream_stats: Arc<HttpProxyPipelineStats>,
+    passed_users: AHashMap<Arc<str>, UserData>,
+    datagram_dispatcher: H3DatagramDispatcher,
+}
+
+impl H3ProxyConnectionTask {
+    pub(crate) fn new(
+        ctx: Arc<CommonTaskContext>,
+        audit_ctx: AuditContext,
+        user_group: Option<Arc<UserGroup>>,
+    ) -> Self {
+        H3ProxyConnectionTask {
+            ctx,
+            audit_ctx,
+            user_group,
+            stream_stats: Arc::new(HttpProxyPipelineStats::default()),
+            passed_users: AHashMap::new(),
+            datagram_dispatcher: H3DatagramDispatcher::default(),
+        }
+    }
+
+    pub(crate) async fn into_running(mut self, connection: Connection) {
+        let Some(h3_config) = &self.ctx.server_config.h3_config else {
+            return;
+        };
+
+        let mut server_builder = h3::server::builder();
+        server_builder
+            .max_field_section_size(h3_config.max_field_section_size as u64)
+            .enable_connect(true)
+            .enable_datagram(h3_config.enable_connect_udp);
+
+        let mut h3c = match tokio::time::timeout(
+            h3_config.handshake_timeout,
+            server_builder.build::<_, Bytes>(h3_quinn::Connection::new(connection.clone())),
+        )
+        .await
+        {
+            Ok(Ok(h3c)) => h3c,
+            Ok(Err(e)) => {
+                debug!(
+                    "{} - {} h3 handshake error: {e}",
+                    self.ctx.cc_info.sock_local_addr(),
+                    self.ctx.cc_info.sock_peer_addr()
+                );
+                return;
+            }
+            Err(_) => {
+                debug!(
+                    "{} - {} h3 handshake timeout",
+                    self.ctx.cc_info.sock_local_addr(),
+                    self.ctx.cc_info.sock_peer_addr()
+                );
+                return;
+            }
+        };
+
+        let idle_duration = self.ctx.server_config.task_idle_check_duration;
+        let mut idle_interval =
+            tokio::time::interval_at(Instant::now() + idle_duration, idle_duration);
+        let mut idle_count = 0;
+        let mut goaway_sent = false;
+
+        loop {
+            let r = {
+                // the accept future is not cancel safe, so keep it alive until it's ready
+                let accept = h3c.accept();
+                tokio::pin!(accept);
+
+                loop {
+                    tokio::select! {
+                        biased;
+
+                        r = &mut accept => break Ok(r),
+                        r = connection.read_datagram() => {
+                            match r {
+                                Ok(data) => self.datagram_dispatcher.dispatch(data),
+                                Err(_) => break Err(false),
+                            }
+                        }
+                        _ = idle_interval.tick() => {
+                            self.datagram_dispatcher.clean_closed();
+
+                            if self.stream_stats.get_alive_task() <= 0 {
+                                idle_count += 1;
+
+                                if idle_count > self.ctx.server_config.task_idle_max_count {
+                                    close_connection(&connection, Code::H3_NO_ERROR, b"idle");
+                                    break Err(false);
+                                }
+                            } else {
+                                idle_count = 0;
+                            }
+
+                            if self.ctx.server_quit_policy.force_quit() {
+                                close_connection(
+                                    &connection,
+                                    Code::H3_REQUEST_CANCELLED,
+                                    b"server quit",
+                                );
+                                break Err(false);
+                            }
+
+                            if !goaway_sent && !self.ctx.server_stats.is_online() {
+                                break Err(true);
+                            }
+                        }
+                    }
+                }
+            };
+
+            match r {
+                Ok(Ok(Some((clt_req, clt_stream)))) => {
+                    self.handle_stream(clt_req, clt_stream, &connection);
+                }
+                Ok(Ok(None)) => break,
+                Ok(Err(e)) => {
+                    debug!(
+                        "{} - {} h3 connection error: {e}",
+                        self.ctx.cc_info.sock_local_addr(),
+                        self.ctx.cc_info.sock_peer_addr()
+                    );
+                    if matches!(e.get_error_level(), ErrorLevel::ConnectionError) {
+                        break;
+                    }
+                }
+                Err(true) => {
+                    // send GOAWAY, the client should open new connections for new requests
+                    let _ = h3c.shutdown(0).await;
+                    goaway_sent = true;
+                }
+                Err(false) => break,
+            }
+        }
+    }
+
+    fn get_egress_path_selection(&self, headers: &mut HeaderMap) -> Option<EgressPathSelection> {
+        if let Some(header) = &self.ctx.server_config.egress_path_selection_header {
+            // check and remove the custom header
+            if let Some(value) = headers.remove(header) {
+                if let Ok(value) = value.to_str() {
+                    if let Ok(egress) = EgressPathSelection::from_str(value) {
+                        return Some(egress);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    fn get_stream_type(
+        &self,
+        clt_req: &Request<()>,
+    ) -> Result<(H3ProxyStreamType, UpstreamAddr), HttpRequestParseError> {
+        if clt_req.method().ne(&Method::CONNECT) {
+            return Err(HttpRequestParseError::UnsupportedMethod(
+                clt_req.method().to_string(),
+            ));
+        }
+
+        let uri = clt_req.uri();
+        if let Some(protocol) = clt_req.extensions().get::<Protocol>() {
+            return match HttpUpgradeToken::from_str(protocol.as_str()) {
+                Ok(HttpUpgradeToken::ConnectUdp) => {
+                    let upstream = uri.get_connect_udp_upstream()?;
+                    Ok((H3ProxyStreamType::UdpConnect, upstream))
+                }
+                _ => Err(HttpRequestParseError::UpgradeIsNotSupported),
+            };
+        }
+        let upstream = uri.get_upstream_with_default_port(443)?;
+        Ok((H3ProxyStreamType::TcpConnect, upstream))
+    }
+
+    fn reply_invalid_request(
+        &self,
+        mut clt_stream: RequestStream<H3BidiStream, Bytes>,
+        e: HttpRequestParseError,
+    ) {
+        debug!(
+            "{} - {} invalid h3 proxy request: {e}",
+            self.ctx.cc_info.sock_local_addr(),
+            self.ctx.cc_info.sock_peer_addr()
+        );
+        if self.ctx.server_config.no_early_error_reply {
+            clt_stream.stop_stream(Code::H3_REQUEST_REJECTED);
+        } else if let Some(status) = e.status_code() {
+            tokio::spawn(async move {
+                let _ = super::reply_status(&mut clt_stream, status).await;
+            });
+        }
+    }
+
+    fn handle_stream(
+        &mut self,
+        mut clt_req: Request<()>,
+        clt_stream: RequestStream<H3BidiStream, Bytes>,
+        connection: &Connection,
+    ) {
+        let time_accepted = Instant::now();
+
+        if self.ctx.server_config.steal_forwarded_for {
+            let headers = clt_req.headers_mut();
+            headers.remove(header::FORWARDED);
+            headers.remove("x-forwarded-for");
+        }
+        let path_selection = self.get_egress_path_selection(clt_req.headers_mut());
+
+        let (stream_type, upstream) = match self.get_stream_type(&clt_req) {
+            Ok(v) => v,
+            Err(e) => {
+                self.reply_invalid_request(clt_stream, e);
+                return;
+            }
+        };
+        if matches!(stream_type, H3ProxyStreamType::UdpConnect)
+            && !self
+                .ctx
+                .server_config
+                .h3_config
+                .as_ref()
+                .map(|c| c.enable_connect_udp)
+                .unwrap_or_default()
+        {
+            self.reply_invalid_request(clt_stream, HttpRequestParseError::UpgradeIsNotSupported);
+            return;
+        }
+
+        let (parts, _) = clt_req.into_parts();
+        let req = match HttpProxyClientRequest::from_h2_parts(&parts, false) {
+            Ok(req) => req,
+            Err(e) => {
+                self.reply_invalid_request(clt_stream, e);
+                return;
+            }
+        };
+
+        let user_ctx = match self.do_auth(&req.auth_info, &upstream) {
+            Ok(user_ctx) => user_ctx,
+            Err(e) => {
+                self.reply_auth_err(clt_stream, e);
+                return;
+            }
+        };
+
+        let task_notes = ServerTaskNotes::with_path_selection(
+            self.ctx.cc_info.clone(),
+            user_ctx,
+            time_accepted.elapsed(),
+            path_selection,
+        );
+
+        let stream_stats = self.stream_stats.clone();
+        stream_stats.add_task();
+        match stream_type {
+            H3ProxyStreamType::TcpConnect => {
+                let task = H3ProxyConnectTask::new(
+                    &self.ctx,
+                    self.audit_ctx.clone(),
+                    upstream,
+                    task_notes,
+                );
+                tokio::spawn(async move {
+                    task.into_running(clt_stream).await;
+                    stream_stats.del_task();
+                });
+            }
+            H3ProxyStreamType::UdpConnect => {
+                let queue_size = self
+                    .ctx
+                    .server_config
+                    .h3_config
+                    .as_ref()
+                    .map(|c| c.udp_datagram_queue_size)
+                    .unwrap_or(1);
+                let datagram_receiver = self
+                    .datagram_dispatcher
+                    .register(clt_stream.id().index(), queue_size);
+
+                let task = H3ProxyConnectUdpTask::new(&self.ctx, upstream, task_notes);
+                let connection = connection.clone();
+                tokio::spawn(async move {
+                    task.into_running(clt_stream, connection, datagram_receiver)
+                        .await;
+                    stream_stats.del_task();
+                });
+            }
+        }
+    }
+
+    fn do_auth(
+        &mut self,
+        auth_info: &HttpAuth,
+        upstream: &UpstreamAddr,
+    ) -> Result<Option<UserContext>, UserAuthError> {
+        let Some(user_group) = &self.user_group else {
+            return Ok(None);
+        };
+
+        let user_ctx = match auth_info {
+            HttpAuth::None => {
+                if let Some((user, user_type)) = user_group.get_anonymous_user() {
+                    let user_ctx = UserContext::new(
+                        None,
+                        user,
+                        user_type,
+                        self.ctx.server_config.name(),
+                        self.ctx.server_stats.share_extra_tags(),
+                    );
+                    user_ctx.check_client_addr(
+                        self.ctx.client_addr(),
+                        self.ctx.client_location.as_deref(),
+                    )?;
+                    user_ctx
+                } else {
+                    return Err(UserAuthError::NoUserSupplied);
+                }
+            }
+            HttpAuth::Basic(HttpBasicAuth {
+                username, password, ..
+            }) => match user_group.get_user(username.as_original()) {
+                Some((user, user_type)) => {
+                    let user_ctx = UserContext::new(
+                        Some(Arc::from(username.as_original())),
+                        user,
+                        user_type,
+                        self.ctx.server_config.name(),
+                        self.ctx.server_stats.share_extra_tags(),
+                    );
+                    user_ctx.check_client_addr(
+                        self.ctx.client_addr(),
+                        self.ctx.client_location.as_deref(),
+                    )?;
+                    user_ctx.check_password(password.as_original())?;
+                    user_ctx
+                }
+                None => return Err(UserAuthError::NoSuchUser),
+            },
+        };
+
+        let mut user_ctx = user_ctx;
+        user_ctx.check_in_site(
+            self.ctx.server_config.name(),
+            self.ctx.server_stats.share_extra_tags(),
+            upstream,
+        );
+        if self.passed_users.contains_key(user_ctx.user_name()) {
+            user_ctx.mark_reused_client_connection();
+        } else {
+            let req_stats = user_ctx.req_stats().clone();
+            req_stats.conn_total.add_http();
+            req_stats.l7_conn_alive.inc_http();
+            let site_req_stats = if let Some(site_req_stats) = user_ctx.site_req_stats() {
+                site_req_stats.conn_total.add_http();
+                site_req_stats.l7_conn_alive.inc_http();
+                Some(Arc::clone(site_req_stats))
+            } else {
+                None
+            };
+            self.passed_users.insert(
+                user_ctx.user_name().clone(),
+                UserData {
+                    req_stats,
+                    site_req_stats,
+                },
+            );
+        }
+        Ok(Some(user_ctx))
+    }
+
+    fn reply_auth_err(&self, mut clt_stream: RequestStream<H3BidiStream, Bytes>, e: UserAuthError) {
+        let no_early_error_reply = self.ctx.server_config.no_early_error_reply;
+        match e.blocked_delay() {
+            Some(duration) => {
+                self.ctx.server_stats.forbidden.add_user_blocked();
+
+                tokio::spawn(async move {
+                    // delay some time before reply
+                    tokio::time::sleep(duration).await;
+                    if no_early_error_reply {
+                        clt_stream.stop_stream(Code::H3_REQUEST_REJECTED);
+                    } else {
+                        let _ = super::reply_status(&mut clt_stream, StatusCode::FORBIDDEN).await;
+                    }
+                });
+            }
+            None => {
+                self.ctx.server_stats.forbidden.add_auth_failed();
+
+                if no_early_error_reply {
+                    clt_stream.stop_stream(Code::H3_REQUEST_REJECTED);
+                } else {
+                    let realm = self.ctx.server_config.auth_realm.clone();
+                    tokio::spawn(async move {
+                        super::reply_proxy_auth_required(&mut clt_stream, realm.as_str()).await;
+                    });
+                }
+            }
+        }
+    }
+}
+
+fn close_connection(connection: &Connection, code: Code, reason: &[u8]) {
+    let code = VarInt::from_u64(code.value()).unwrap_or_default();
+    connection.close(code, reason);
+}
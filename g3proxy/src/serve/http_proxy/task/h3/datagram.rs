@@ -0,0 +1,154 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use ahash::AHashMap;
+use bytes::{BufMut, Bytes, BytesMut};
+use tokio::sync::mpsc;
+
+use g3_http::capsule;
+
+/// Dispatch HTTP datagrams to the CONNECT-UDP tasks by the quarter stream id,
+/// see [RFC 9297](https://datatracker.ietf.org/doc/html/rfc9297#section-2.1).
+#[derive(Default)]
+pub(super) struct H3DatagramDispatcher {
+    senders: AHashMap<u64, mpsc::Sender<Bytes>>,
+}
+
+impl H3DatagramDispatcher {
+    /// Register a new request stream, the received datagrams will have the quarter stream id
+    /// stripped, so only the context id and the payload will be left.
+    pub(super) fn register(
+        &mut self,
+        quarter_stream_id: u64,
+        queue_size: usize,
+    ) -> mpsc::Receiver<Bytes> {
+        let (sender, receiver) = mpsc::channel(queue_size);
+        self.senders.insert(quarter_stream_id, sender);
+        receiver
+    }
+
+    pub(super) fn dispatch(&mut self, data: Bytes) {
+        let Some((quarter_stream_id, len)) = capsule::decode_var_int(&data) else {
+            return;
+        };
+        if let Some(sender) = self.senders.get(&quarter_stream_id) {
+            match sender.try_send(data.slice(len..)) {
+                Ok(_) => {}
+                Err(mpsc::error::TrySendError::Full(_)) => {
+                    // drop it just like the UDP socket recv buffer is full
+                }
+                Err(mpsc::error::TrySendError::Closed(_)) => {
+                    self.senders.remove(&quarter_stream_id);
+                }
+            }
+        }
+    }
+
+    pub(super) fn clean_closed(&mut self) {
+        self.senders.retain(|_, sender| !sender.is_closed());
+    }
+}
+
+pub(super) fn udp_datagram_len(quarter_stream_id: u64, payload_len: usize) -> usize {
+    capsule::var_int_encoded_len(quarter_stream_id) + 1 + payload_len
+}
+
+/// Encode the UDP payload as a HTTP datagram of the request stream
+pub(super) fn encode_udp_datagram(quarter_stream_id: u64, payload: &[u8]) -> Bytes {
+    let mut buf = BytesMut::with_capacity(udp_datagram_len(quarter_stream_id, payload.len()));
+    capsule::encode_var_int(quarter_stream_id, &mut buf);
+    capsule::encode_var_int(0, &mut buf); // context id
+    buf.put_slice(payload);
+    buf.freeze()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dispatch_by_quarter_stream_id() {
+        let mut dispatcher = H3DatagramDispatcher::default();
+        // client initiated bidirectional streams 0, 4 and 400
+        let mut r0 = dispatcher.register(0, 4);
+        let mut r1 = dispatcher.register(1, 4);
+        let mut r100 = dispatcher.register(100, 4);
+
+        let data = encode_udp_datagram(1, b"one");
+        assert_eq!(data.len(), udp_datagram_len(1, 3));
+        dispatcher.dispatch(data);
+        dispatcher.dispatch(encode_udp_datagram(100, b"hundred"));
+        dispatcher.dispatch(encode_udp_datagram(0, b"zero"));
+        // unknown stream
+        dispatcher.dispatch(encode_udp_datagram(2, b"two"));
+
+        let data = r1.try_recv().unwrap();
+        assert_eq!(capsule::udp_payload_in_datagram(&data), Some(&b"one"[..]));
+        assert!(r1.try_recv().is_err());
+        let data = r100.try_recv().unwrap();
+        assert_eq!(data[0], 0);
+        assert_eq!(&data[1..], b"hundred");
+        assert!(r100.try_recv().is_err());
+        let data = r0.try_recv().unwrap();
+        assert_eq!(capsule::udp_payload_in_datagram(&data), Some(&b"zero"[..]));
+        assert!(r0.try_recv().is_err());
+    }
+
+    #[test]
+    fn dispatch_large_stream_id() {
+        let mut dispatcher = H3DatagramDispatcher::default();
+        let quarter_stream_id = 20000;
+        let mut r = dispatcher.register(quarter_stream_id, 1);
+
+        let data = encode_udp_datagram(quarter_stream_id, b"data");
+        assert_eq!(&data[..3], &[0x80, 0x00, 0x4e]);
+        dispatcher.dispatch(data);
+        let data = r.try_recv().unwrap();
+        assert_eq!(capsule::udp_payload_in_datagram(&data), Some(&b"data"[..]));
+    }
+
+    #[test]
+    fn dispatch_full_and_closed() {
+        let mut dispatcher = H3DatagramDispatcher::default();
+        let mut r = dispatcher.register(1, 1);
+
+        dispatcher.dispatch(encode_udp_datagram(1, b"first"));
+        dispatcher.dispatch(encode_udp_datagram(1, b"second"));
+        let data = r.try_recv().unwrap();
+        assert_eq!(capsule::udp_payload_in_datagram(&data), Some(&b"first"[..]));
+        assert!(r.try_recv().is_err());
+
+        drop(r);
+        dispatcher.dispatch(encode_udp_datagram(1, b"third"));
+        assert!(dispatcher.senders.is_empty());
+
+        let r = dispatcher.register(2, 1);
+        drop(r);
+        dispatcher.clean_closed();
+        assert!(dispatcher.senders.is_empty());
+    }
+
+    #[test]
+    fn dispatch_invalid() {
+        let mut dispatcher = H3DatagramDispatcher::default();
+        let mut r = dispatcher.register(1, 4);
+
+        dispatcher.dispatch(Bytes::new());
+        // truncated 2 bytes var int
+        dispatcher.dispatch(Bytes::from_static(&[0x40]));
+        assert!(r.try_recv().is_err());
+    }
+}
@@ -0,0 +1,79 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::io;
+
+use bytes::Bytes;
+use h3::server::RequestStream;
+use http::{header, HeaderValue, Response, StatusCode, Version};
+
+use super::{
+    CommonTaskContext, HttpProxyPipelineStats, TcpConnectTaskCltWrapperStats,
+    UdpConnectTaskCltWrapperStats, UdpConnectTaskStats,
+};
+
+mod connection;
+pub(crate) use connection::H3ProxyConnectionTask;
+
+mod stream;
+use stream::{H3StreamReader, H3StreamWriter};
+
+mod connect;
+use connect::H3ProxyConnectTask;
+
+mod connect_udp;
+use connect_udp::H3ProxyConnectUdpTask;
+
+mod datagram;
+use datagram::H3DatagramDispatcher;
+
+type H3BidiStream = h3_quinn::BidiStream<Bytes>;
+type H3SendStream = RequestStream<h3_quinn::SendStream<Bytes>, Bytes>;
+type H3RecvStream = RequestStream<h3_quinn::RecvStream, Bytes>;
+
+fn h3_error_to_io(e: h3::Error) -> io::Error {
+    io::Error::other(e)
+}
+
+async fn reply_status<S>(
+    clt_stream: &mut RequestStream<S, Bytes>,
+    status: StatusCode,
+) -> Option<u16>
+where
+    S: h3::quic::SendStream<Bytes>,
+{
+    let mut rsp = Response::new(());
+    *rsp.status_mut() = status;
+    *rsp.version_mut() = Version::HTTP_3;
+    clt_stream.send_response(rsp).await.ok()?;
+    let _ = clt_stream.finish().await;
+    Some(status.as_u16())
+}
+
+async fn reply_proxy_auth_required<S>(clt_stream: &mut RequestStream<S, Bytes>, realm: &str)
+where
+    S: h3::quic::SendStream<Bytes>,
+{
+    let mut rsp = Response::new(());
+    *rsp.status_mut() = StatusCode::PROXY_AUTHENTICATION_REQUIRED;
+    *rsp.version_mut() = Version::HTTP_3;
+    if let Ok(value) = HeaderValue::from_str(&format!("Basic realm=\"{realm}\"")) {
+        rsp.headers_mut().insert(header::PROXY_AUTHENTICATE, value);
+    }
+    if clt_stream.send_response(rsp).await.is_ok() {
+        let _ = clt_stream.finish().await;
+    }
+}
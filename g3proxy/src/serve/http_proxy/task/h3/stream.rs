@@ -0,0 +1,149 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use bytes::{Buf, Bytes};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use super::{h3_error_to_io, H3RecvStream, H3SendStream};
+
+pub(super) struct H3StreamReader {
+    recv_stream: H3RecvStream,
+    chunk: Bytes,
+    finished: bool,
+}
+
+impl H3StreamReader {
+    pub(super) fn new(recv_stream: H3RecvStream) -> Self {
+        H3StreamReader {
+            recv_stream,
+            chunk: Bytes::new(),
+            finished: false,
+        }
+    }
+}
+
+impl AsyncRead for H3StreamReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        loop {
+            if !self.chunk.is_empty() {
+                let to_copy = self.chunk.len().min(buf.remaining());
+                buf.put_slice(&self.chunk[..to_copy]);
+                self.chunk.advance(to_copy);
+                return Poll::Ready(Ok(()));
+            }
+            if self.finished {
+                return Poll::Ready(Ok(()));
+            }
+
+            match ready!(self.recv_stream.poll_recv_data(cx)) {
+                Ok(Some(mut data)) => {
+                    let len = data.remaining();
+                    self.chunk = data.copy_to_bytes(len);
+                }
+                Ok(None) => self.finished = true,
+                Err(e) => return Poll::Ready(Err(h3_error_to_io(e))),
+            }
+        }
+    }
+}
+
+type SendFuture =
+    Pin<Box<dyn Future<Output = (H3SendStream, Result<(), h3::Error>)> + Send + Sync>>;
+
+/// AsyncWrite adapter for the send half of a HTTP/3 request stream.
+///
+/// Each write will be sent as a single DATA frame, and it will be awaited on the next
+/// write / flush / shutdown call.
+pub(super) struct H3StreamWriter {
+    send_stream: Option<H3SendStream>,
+    send_future: Option<SendFuture>,
+    shutdown: bool,
+}
+
+impl H3StreamWriter {
+    pub(super) fn new(send_stream: H3SendStream) -> Self {
+        H3StreamWriter {
+            send_stream: Some(send_stream),
+            send_future: None,
+            shutdown: false,
+        }
+    }
+
+    fn poll_pending(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        if let Some(fut) = &mut self.send_future {
+            let (send_stream, r) = ready!(fut.as_mut().poll(cx));
+            self.send_future = None;
+            self.send_stream = Some(send_stream);
+            r.map_err(h3_error_to_io)?;
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn take_send_stream(&mut self) -> io::Result<H3SendStream> {
+        self.send_stream
+            .take()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::BrokenPipe, "stream already closed"))
+    }
+}
+
+impl AsyncWrite for H3StreamWriter {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        ready!(self.poll_pending(cx))?;
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+
+        let mut send_stream = self.take_send_stream()?;
+        let data = Bytes::copy_from_slice(buf);
+        self.send_future = Some(Box::pin(async move {
+            let r = send_stream.send_data(data).await;
+            (send_stream, r)
+        }));
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.poll_pending(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.poll_pending(cx))?;
+        if self.shutdown {
+            return Poll::Ready(Ok(()));
+        }
+
+        let mut send_stream = self.take_send_stream()?;
+        self.send_future = Some(Box::pin(async move {
+            let r = send_stream.finish().await;
+            (send_stream, r)
+        }));
+        self.shutdown = true;
+        self.poll_pending(cx)
+    }
+}
@@ -25,12 +25,20 @@ mod protocol;
 mod connect;
 mod forward;
 mod ftp;
+mod h2;
+#[cfg(feature = "quic")]
+mod h3;
 mod pipeline;
 mod untrusted;
 
-use connect::HttpProxyConnectTask;
+use connect::{HttpProxyConnectTask, TcpConnectTaskCltWrapperStats};
 use forward::HttpProxyForwardTask;
 use ftp::FtpOverHttpTask;
+pub(super) use h2::{check_prior_knowledge, H2ProxyConnectionTask};
+#[cfg(feature = "quic")]
+use h2::{UdpConnectTaskCltWrapperStats, UdpConnectTaskStats};
+#[cfg(feature = "quic")]
+pub(super) use h3::H3ProxyConnectionTask;
 pub(super) use pipeline::{
     HttpProxyPipelineReaderTask, HttpProxyPipelineStats, HttpProxyPipelineWriterTask,
 };
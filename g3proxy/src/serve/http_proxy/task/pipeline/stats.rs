@@ -38,16 +38,16 @@ impl Default for HttpProxyPipelineStats {
 }
 
 impl HttpProxyPipelineStats {
-    pub(super) fn add_task(&self) {
+    pub(crate) fn add_task(&self) {
         self.total_task.fetch_add(1, Ordering::Relaxed);
         self.alive_task.fetch_add(1, Ordering::Relaxed);
     }
 
-    pub(super) fn del_task(&self) {
+    pub(crate) fn del_task(&self) {
         self.alive_task.fetch_sub(1, Ordering::Relaxed);
     }
 
-    pub(super) fn get_alive_task(&self) -> i32 {
+    pub(crate) fn get_alive_task(&self) -> i32 {
         self.alive_task.load(Ordering::Relaxed)
     }
 }
@@ -33,9 +33,12 @@ use super::{
 };
 use crate::audit::AuditContext;
 use crate::auth::{UserContext, UserGroup, UserRequestStats};
+use crate::config::http_rewrite::{HttpRewriteReply, HttpRewriteRule};
 use crate::config::server::ServerConfig;
 use crate::escape::EgressPathSelection;
-use crate::module::http_forward::{BoxHttpForwardContext, HttpProxyClientResponse};
+use crate::module::http_forward::{
+    BoxHttpForwardContext, HttpForwardCacheContext, HttpProxyClientResponse,
+};
 use crate::serve::{ServerStats, ServerTaskNotes};
 
 struct UserData {
@@ -142,7 +145,10 @@ where
                             self.ctx.server_config.name(),
                             self.ctx.server_stats.share_extra_tags(),
                         );
-                        user_ctx.check_client_addr(self.ctx.client_addr())?;
+                        user_ctx.check_client_addr(
+                            self.ctx.client_addr(),
+                            self.ctx.client_location.as_deref(),
+                        )?;
                         user_ctx
                     } else {
                         return Err(UserAuthError::NoUserSupplied);
@@ -159,7 +165,10 @@ where
                             self.ctx.server_config.name(),
                             self.ctx.server_stats.share_extra_tags(),
                         );
-                        user_ctx.check_client_addr(self.ctx.client_addr())?;
+                        user_ctx.check_client_addr(
+                            self.ctx.client_addr(),
+                            self.ctx.client_location.as_deref(),
+                        )?;
                         user_ctx.check_password(password.as_original())?;
                         user_ctx
                     }
@@ -272,6 +281,18 @@ where
             path_selection,
         );
 
+        let rewrite_rule = if matches!(req.client_protocol, HttpProxySubProtocol::TcpConnect) {
+            None
+        } else {
+            self.ctx.find_rewrite_rule(&req, &task_notes)
+        };
+        if let Some(rule) = &rewrite_rule {
+            if let Some(reply) = &rule.reply {
+                return self.run_rewrite_reply(req, &task_notes, reply).await;
+            }
+            rule.rewrite_request(&mut req.inner);
+        }
+
         let mut audit_ctx = self.audit_ctx.clone();
         let remote_protocol = match req.client_protocol {
             HttpProxySubProtocol::TcpConnect => HttpProxySubProtocol::TcpConnect,
@@ -337,7 +358,14 @@ where
             HttpProxySubProtocol::HttpForward | HttpProxySubProtocol::HttpsForward => {
                 if let Some(mut stream_w) = self.stream_writer.take() {
                     match self
-                        .run_forward(&mut stream_w, req, task_notes, audit_ctx, remote_protocol)
+                        .run_forward(
+                            &mut stream_w,
+                            req,
+                            task_notes,
+                            audit_ctx,
+                            remote_protocol,
+                            rewrite_rule,
+                        )
                         .await
                     {
                         LoopAction::Continue => {
@@ -371,6 +399,47 @@ where
         }
     }
 
+    async fn run_rewrite_reply(
+        &mut self,
+        mut req: HttpProxyRequest<CDR>,
+        task_notes: &ServerTaskNotes,
+        reply: &HttpRewriteReply,
+    ) -> LoopAction {
+        let Some(clt_w) = &mut self.stream_writer else {
+            self.notify_reader_to_close();
+            return LoopAction::Break;
+        };
+
+        let clt_r = req.body_reader.take();
+        // the request body is not consumed, so we need to close the connection if there is one
+        let close = !req.inner.keep_alive() || (clt_r.is_some() && req.inner.body_type().is_some());
+
+        let write_result = self
+            .ctx
+            .reply_rewrite(&req, task_notes, reply, close, clt_w)
+            .await;
+
+        if close || write_result.is_err() {
+            if clt_r.is_some() {
+                // close read end
+                let _ = req.stream_sender.send(None).await;
+            } else {
+                self.notify_reader_to_close();
+            }
+            LoopAction::Break
+        } else if let Some(clt_r) = clt_r {
+            // reopen read end
+            if req.stream_sender.send(Some(clt_r)).await.is_err() {
+                // read end has closed, impossible as reader should be waiting this channel
+                LoopAction::Break
+            } else {
+                LoopAction::Continue
+            }
+        } else {
+            LoopAction::Continue
+        }
+    }
+
     fn reset_client_writer(&mut self, mut stream_w: HttpClientWriter<CDW>) {
         stream_w.reset_stats(Arc::clone(&self.wrapper_stats));
         let limit_config = &self.ctx.server_config.tcp_sock_speed_limit;
@@ -478,6 +547,7 @@ where
         task_notes: ServerTaskNotes,
         audit_ctx: AuditContext,
         remote_protocol: HttpProxySubProtocol,
+        rewrite_rule: Option<Arc<HttpRewriteRule>>,
     ) -> LoopAction {
         let is_https = match remote_protocol {
             HttpProxySubProtocol::HttpForward => false,
@@ -485,12 +555,29 @@ where
             _ => unreachable!(),
         };
 
+        let cache_ctx = match self.ctx.get_forward_cache(&task_notes, &audit_ctx) {
+            Some(cache) => {
+                // use the client side scheme, as https may be forwarded by the next proxy
+                let https_url = matches!(req.client_protocol, HttpProxySubProtocol::HttpsForward);
+                HttpForwardCacheContext::lookup(&cache, https_url, &req.upstream, &mut req.inner)
+                    .await
+            }
+            None => None,
+        };
+
         match req.body_reader.take() {
             Some(stream_r) => {
                 // we have a body, or we need to close the connection
                 // we may need to send stream_r back if we have a body
-                let mut forward_task =
-                    HttpProxyForwardTask::new(&self.ctx, audit_ctx, &req, is_https, task_notes);
+                let mut forward_task = HttpProxyForwardTask::new(
+                    &self.ctx,
+                    audit_ctx,
+                    &req,
+                    is_https,
+                    task_notes,
+                    rewrite_rule,
+                    cache_ctx,
+                );
                 let mut clt_r = Some(stream_r);
                 forward_task
                     .run(&mut clt_r, clt_w, &mut self.forward_context)
@@ -511,8 +598,15 @@ where
             }
             None => {
                 // no body, and the connection is expected to keep alive from the client side
-                let mut forward_task =
-                    HttpProxyForwardTask::new(&self.ctx, audit_ctx, &req, is_https, task_notes);
+                let mut forward_task = HttpProxyForwardTask::new(
+                    &self.ctx,
+                    audit_ctx,
+                    &req,
+                    is_https,
+                    task_notes,
+                    rewrite_rule,
+                    cache_ctx,
+                );
                 let mut clt_r = None;
                 forward_task
                     .run::<CDR, CDW>(&mut clt_r, clt_w, &mut self.forward_context)
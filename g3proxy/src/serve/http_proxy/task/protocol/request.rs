@@ -131,9 +131,14 @@ fn get_forward_upstream_and_protocol(
             } else if scheme.eq(&http::uri::Scheme::HTTPS) {
                 let upstream = uri.get_upstream_with_default_port(443)?;
                 Ok((upstream, HttpProxySubProtocol::HttpsForward))
-            } else if scheme.as_str().eq_ignore_ascii_case("ftp") {
+            } else if scheme.as_str().eq_ignore_ascii_case("ftp")
+                || scheme.as_str().eq_ignore_ascii_case("ftpes")
+            {
                 let upstream = uri.get_upstream_with_default_port(21)?;
                 Ok((upstream, HttpProxySubProtocol::FtpOverHttp))
+            } else if scheme.as_str().eq_ignore_ascii_case("ftps") {
+                let upstream = uri.get_upstream_with_default_port(990)?;
+                Ok((upstream, HttpProxySubProtocol::FtpOverHttp))
             } else {
                 Err(HttpRequestParseError::UnsupportedScheme)
             }
@@ -141,3 +146,27 @@ fn get_forward_upstream_and_protocol(
         None => Err(HttpRequestParseError::InvalidRequestTarget),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn forward_port(uri: &str) -> u16 {
+        let uri = http::Uri::try_from(uri).unwrap();
+        let (upstream, protocol) = get_forward_upstream_and_protocol(&uri).unwrap();
+        assert!(matches!(protocol, HttpProxySubProtocol::FtpOverHttp));
+        upstream.port()
+    }
+
+    #[test]
+    fn ftp_schemes() {
+        assert_eq!(forward_port("ftp://example.net/a.txt"), 21);
+        assert_eq!(forward_port("ftpes://example.net/a.txt"), 21);
+        assert_eq!(forward_port("FTPES://example.net/a.txt"), 21);
+        assert_eq!(forward_port("ftps://example.net/a.txt"), 990);
+        assert_eq!(forward_port("ftpes://example.net:2121/a.txt"), 2121);
+
+        let uri = http::Uri::try_from("sftp://example.net/a.txt").unwrap();
+        assert!(get_forward_upstream_and_protocol(&uri).is_err());
+    }
+}
@@ -33,9 +33,12 @@ use tokio_rustls::LazyConfigAcceptor;
 
 use g3_daemon::listen::{AcceptQuicServer, AcceptTcpServer, ListenStats, ListenTcpRuntime};
 use g3_daemon::server::{BaseServer, ClientConnectionInfo, ServerReloadCommand};
+use g3_geoip_types::IpLocation;
 use g3_io_ext::AsyncStream;
+use g3_ip_locate::IpLocationServiceHandle;
 use g3_openssl::SslStream;
 use g3_types::acl::{AclAction, AclNetworkRule};
+use g3_types::acl_set::AclClientGeoRuleSet;
 use g3_types::metrics::MetricsName;
 use g3_types::net::{
     AlpnProtocol, OpensslTicketKey, RollingTicketer, RustlsServerConfig, RustlsServerConnectionExt,
@@ -63,6 +66,8 @@ pub(crate) struct HttpRProxyServer {
     tls_rolling_ticketer: Option<Arc<RollingTicketer<OpensslTicketKey>>>,
     global_tls_server: Option<RustlsServerConfig>,
     ingress_net_filter: Option<AclNetworkRule>,
+    ingress_geo_filter: Option<AclClientGeoRuleSet>,
+    ip_locate_handle: Option<Arc<IpLocationServiceHandle>>,
     reload_sender: broadcast::Sender<ServerReloadCommand>,
     task_logger: Logger,
     hosts: HostMatch<Arc<HttpHost>>,
@@ -80,6 +85,7 @@ impl HttpRProxyServer {
         listen_stats: Arc<ListenStats>,
         hosts: HostMatch<Arc<HttpHost>>,
         tls_rolling_ticketer: Option<Arc<RollingTicketer<OpensslTicketKey>>>,
+        ip_locate_handle: Option<Arc<IpLocationServiceHandle>>,
         version: usize,
     ) -> anyhow::Result<Self> {
         let reload_sender = crate::serve::new_reload_notify_channel();
@@ -101,6 +107,10 @@ impl HttpRProxyServer {
             .ingress_net_filter
             .as_ref()
             .map(|builder| builder.build());
+        let ingress_geo_filter = config
+            .ingress_geo_filter
+            .as_ref()
+            .map(|builder| builder.build());
 
         let task_logger = config.get_task_logger();
 
@@ -117,6 +127,8 @@ impl HttpRProxyServer {
             tls_rolling_ticketer,
             global_tls_server,
             ingress_net_filter,
+            ingress_geo_filter,
+            ip_locate_handle,
             reload_sender,
             task_logger,
             hosts,
@@ -146,12 +158,22 @@ impl HttpRProxyServer {
             .hosts
             .try_build_arc(|c| HttpHost::try_build(c, tls_rolling_ticketer.clone()))?;
 
+        let ip_locate_handle = if let Some(c) = &config.ip_locate_service {
+            let handle = c
+                .spawn_ip_locate_agent()
+                .context("failed to spawn ip locate agent")?;
+            Some(Arc::new(handle))
+        } else {
+            None
+        };
+
         let server = HttpRProxyServer::new(
             config,
             server_stats,
             listen_stats,
             hosts,
             tls_rolling_ticketer,
+            ip_locate_handle,
             1,
         )?;
         Ok(Arc::new(server))
@@ -178,12 +200,24 @@ impl HttpRProxyServer {
                 .hosts
                 .try_build_arc(|c| HttpHost::try_build(c, tls_rolling_ticketer.clone()))?;
 
+            let ip_locate_handle = if self.config.ip_locate_service.eq(&config.ip_locate_service) {
+                self.ip_locate_handle.clone()
+            } else if let Some(c) = &config.ip_locate_service {
+                let handle = c
+                    .spawn_ip_locate_agent()
+                    .context("failed to spawn ip locate agent")?;
+                Some(Arc::new(handle))
+            } else {
+                None
+            };
+
             let server = HttpRProxyServer::new(
                 config,
                 server_stats,
                 listen_stats,
                 hosts,
                 tls_rolling_ticketer,
+                ip_locate_handle,
                 self.reload_version + 1,
             )?;
             Ok(server)
@@ -196,13 +230,18 @@ impl HttpRProxyServer {
         }
     }
 
-    fn get_common_task_context(&self, cc_info: ClientConnectionInfo) -> Arc<CommonTaskContext> {
+    fn get_common_task_context(
+        &self,
+        cc_info: ClientConnectionInfo,
+        client_location: Option<Arc<IpLocation>>,
+    ) -> Arc<CommonTaskContext> {
         Arc::new(CommonTaskContext {
             server_config: Arc::clone(&self.config),
             server_stats: Arc::clone(&self.server_stats),
             server_quit_policy: Arc::clone(&self.quit_policy),
             escaper: self.escaper.load().as_ref().clone(),
             cc_info,
+            client_location,
             task_logger: self.task_logger.clone(),
         })
     }
@@ -224,13 +263,37 @@ impl HttpRProxyServer {
         false
     }
 
-    async fn spawn_stream_task<T>(&self, stream: T, cc_info: ClientConnectionInfo)
-    where
+    async fn locate_client(&self, client_addr: SocketAddr) -> Option<Arc<IpLocation>> {
+        let handle = self.ip_locate_handle.as_ref()?;
+        handle.fetch(client_addr.ip()).await
+    }
+
+    fn drop_by_location(&self, location: Option<&IpLocation>) -> bool {
+        let Some(filter) = &self.ingress_geo_filter else {
+            return false;
+        };
+        let action = match location {
+            Some(location) => filter.check(location).1,
+            None => filter.missed_action(),
+        };
+        if action.forbid_early() {
+            self.server_stats.forbidden.add_src_geo_blocked();
+            return true;
+        }
+        false
+    }
+
+    async fn spawn_stream_task<T>(
+        &self,
+        stream: T,
+        cc_info: ClientConnectionInfo,
+        client_location: Option<Arc<IpLocation>>,
+    ) where
         T: AsyncStream,
         T::R: AsyncRead + Send + Sync + Unpin + 'static,
         T::W: AsyncWrite + Send + Sync + Unpin + 'static,
     {
-        let ctx = self.get_common_task_context(cc_info);
+        let ctx = self.get_common_task_context(cc_info, client_location);
         let pipeline_stats = Arc::new(HttpRProxyPipelineStats::default());
         let (task_sender, task_receiver) = mpsc::channel(ctx.server_config.pipeline_size);
 
@@ -341,6 +404,10 @@ impl AcceptTcpServer for HttpRProxyServer {
         if self.drop_early(client_addr) {
             return;
         }
+        let client_location = self.locate_client(client_addr).await;
+        if self.drop_by_location(client_location.as_deref()) {
+            return;
+        }
 
         if self.config.enable_tls_server {
             let tls_acceptor = LazyConfigAcceptor::new(rustls::server::Acceptor::default(), stream);
@@ -371,7 +438,8 @@ impl AcceptTcpServer for HttpRProxyServer {
                                         // Quick ACK is needed with session resumption
                                         cc_info.tcp_sock_try_quick_ack();
                                     }
-                                    self.spawn_stream_task(stream, cc_info).await
+                                    self.spawn_stream_task(stream, cc_info, client_location)
+                                        .await
                                 }
                                 Ok(Err(e)) => {
                                     self.listen_stats.add_failed();
@@ -424,7 +492,8 @@ impl AcceptTcpServer for HttpRProxyServer {
                 }
             }
         } else {
-            self.spawn_stream_task(stream, cc_info).await;
+            self.spawn_stream_task(stream, cc_info, client_location)
+                .await;
         }
     }
 }
@@ -472,8 +541,13 @@ impl Server for HttpRProxyServer {
         if self.drop_early(client_addr) {
             return;
         }
+        let client_location = self.locate_client(client_addr).await;
+        if self.drop_by_location(client_location.as_deref()) {
+            return;
+        }
 
-        self.spawn_stream_task(stream, cc_info).await;
+        self.spawn_stream_task(stream, cc_info, client_location)
+            .await;
     }
 
     async fn run_openssl_task(&self, stream: SslStream<TcpStream>, cc_info: ClientConnectionInfo) {
@@ -482,7 +556,12 @@ impl Server for HttpRProxyServer {
         if self.drop_early(client_addr) {
             return;
         }
+        let client_location = self.locate_client(client_addr).await;
+        if self.drop_by_location(client_location.as_deref()) {
+            return;
+        }
 
-        self.spawn_stream_task(stream, cc_info).await;
+        self.spawn_stream_task(stream, cc_info, client_location)
+            .await;
     }
 }
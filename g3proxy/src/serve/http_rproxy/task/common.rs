@@ -20,6 +20,7 @@ use std::sync::Arc;
 use slog::Logger;
 
 use g3_daemon::server::ClientConnectionInfo;
+use g3_geoip_types::IpLocation;
 
 use super::{HttpRProxyServerConfig, HttpRProxyServerStats};
 use crate::escape::ArcEscaper;
@@ -32,6 +33,7 @@ pub(crate) struct CommonTaskContext {
     pub(crate) server_quit_policy: Arc<ServerQuitPolicy>,
     pub(crate) escaper: ArcEscaper,
     pub(crate) cc_info: ClientConnectionInfo,
+    pub(crate) client_location: Option<Arc<IpLocation>>,
     pub(crate) task_logger: Logger,
 }
 
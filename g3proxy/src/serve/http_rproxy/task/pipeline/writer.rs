@@ -139,7 +139,10 @@ where
                             self.ctx.server_config.name(),
                             self.ctx.server_stats.share_extra_tags(),
                         );
-                        user_ctx.check_client_addr(self.ctx.client_addr())?;
+                        user_ctx.check_client_addr(
+                            self.ctx.client_addr(),
+                            self.ctx.client_location.as_deref(),
+                        )?;
                         user_ctx
                     } else {
                         return Err(UserAuthError::NoUserSupplied);
@@ -156,7 +159,10 @@ where
                             self.ctx.server_config.name(),
                             self.ctx.server_stats.share_extra_tags(),
                         );
-                        user_ctx.check_client_addr(self.ctx.client_addr())?;
+                        user_ctx.check_client_addr(
+                            self.ctx.client_addr(),
+                            self.ctx.client_location.as_deref(),
+                        )?;
                         user_ctx.check_password(password.as_original())?;
                         user_ctx
                     }
@@ -16,28 +16,196 @@
 
 use std::io;
 
+use tokio::io::Interest;
 use tokio::net::TcpStream;
 
+const DETECTION_PEEK_SIZE: usize = 24;
+
+const HTTP2_PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+const PROXY_PROTOCOL_V1_PREFIX: &[u8] = b"PROXY ";
+const PROXY_PROTOCOL_V2_SIGNATURE: &[u8] = b"\r\n\r\n\0\r\nQUIT\n";
+
+#[derive(Debug, PartialEq, Eq)]
 pub(super) enum DetectedProxyProtocol {
     Unknown,
     Http,
+    Http2,
     Socks,
+    Tls,
+    /// an unexpected PROXY protocol header
+    ProxyProtocol,
 }
 
 pub(super) async fn detect_tcp_proxy_protocol(
     stream: &TcpStream,
 ) -> io::Result<DetectedProxyProtocol> {
-    let mut buf = [0u8; 1];
-    let len = stream.peek(&mut buf).await?;
-    if len == 0 {
-        return Ok(DetectedProxyProtocol::Unknown);
+    let mut buf = [0u8; DETECTION_PEEK_SIZE];
+    let mut len = stream.peek(&mut buf).await?;
+    loop {
+        if len == 0 {
+            return Ok(DetectedProxyProtocol::Unknown);
+        }
+
+        if let Some(p) = detect_by_leading_bytes(&buf[..len]) {
+            return Ok(p);
+        }
+
+        // peek won't clear the read readiness, so clear it here before waiting for more data,
+        // the caller should set a timeout
+        let _ = stream.try_io(Interest::READABLE, || {
+            Err::<(), _>(io::Error::from(io::ErrorKind::WouldBlock))
+        });
+        let ready = stream.ready(Interest::READABLE).await?;
+
+        let new_len = stream.peek(&mut buf).await?;
+        if new_len == len && ready.is_read_closed() {
+            // no more data, the client has shutdown the write side
+            return Ok(DetectedProxyProtocol::Unknown);
+        }
+        len = new_len;
+    }
+}
+
+/// Return `None` if more data is needed
+fn match_prefix(buf: &[u8], expected: &[u8]) -> Option<bool> {
+    let len = buf.len().min(expected.len());
+    if buf[..len] != expected[..len] {
+        Some(false)
+    } else if len == expected.len() {
+        Some(true)
+    } else {
+        None
     }
+}
 
+/// Return `None` if more data is needed
+fn detect_by_leading_bytes(buf: &[u8]) -> Option<DetectedProxyProtocol> {
     match buf[0] {
-        b'\x04' | b'\x05' => return Ok(DetectedProxyProtocol::Socks),
-        b'G' | b'H' | b'P' | b'D' | b'C' | b'O' | b'T' => return Ok(DetectedProxyProtocol::Http),
-        _ => {}
+        b'\x04' | b'\x05' => Some(DetectedProxyProtocol::Socks),
+        b'\x16' => {
+            // TLS handshake record with ClientHello message
+            if buf.len() < 6 {
+                return None;
+            }
+            if buf[1] == 0x03 && buf[5] == 0x01 {
+                Some(DetectedProxyProtocol::Tls)
+            } else {
+                Some(DetectedProxyProtocol::Unknown)
+            }
+        }
+        b'\r' => match match_prefix(buf, PROXY_PROTOCOL_V2_SIGNATURE)? {
+            true => Some(DetectedProxyProtocol::ProxyProtocol),
+            false => Some(DetectedProxyProtocol::Unknown),
+        },
+        b'P' => {
+            if match_prefix(buf, HTTP2_PREFACE)? {
+                return Some(DetectedProxyProtocol::Http2);
+            }
+            if match_prefix(buf, PROXY_PROTOCOL_V1_PREFIX)? {
+                return Some(DetectedProxyProtocol::ProxyProtocol);
+            }
+            Some(DetectedProxyProtocol::Http)
+        }
+        b'G' | b'H' | b'D' | b'C' | b'O' | b'T' => Some(DetectedProxyProtocol::Http),
+        _ => Some(DetectedProxyProtocol::Unknown),
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    use tokio::io::AsyncWriteExt;
+    use tokio::net::TcpListener;
 
-    Ok(DetectedProxyProtocol::Unknown)
+    #[test]
+    fn socks_and_http() {
+        assert_eq!(
+            detect_by_leading_bytes(b"\x05"),
+            Some(DetectedProxyProtocol::Socks)
+        );
+        assert_eq!(
+            detect_by_leading_bytes(b"CONNECT "),
+            Some(DetectedProxyProtocol::Http)
+        );
+        assert_eq!(
+            detect_by_leading_bytes(b"POST "),
+            Some(DetectedProxyProtocol::Http)
+        );
+        assert_eq!(detect_by_leading_bytes(b"P"), None);
+    }
+
+    #[test]
+    fn tls() {
+        assert_eq!(detect_by_leading_bytes(b"\x16\x03\x01"), None);
+        assert_eq!(
+            detect_by_leading_bytes(b"\x16\x03\x01\x02\x00\x01"),
+            Some(DetectedProxyProtocol::Tls)
+        );
+        assert_eq!(
+            detect_by_leading_bytes(b"\x16\x03\x01\x02\x00\x02"),
+            Some(DetectedProxyProtocol::Unknown)
+        );
+    }
+
+    #[test]
+    fn http2() {
+        assert_eq!(detect_by_leading_bytes(b"PRI * HTTP/2.0"), None);
+        assert_eq!(
+            detect_by_leading_bytes(HTTP2_PREFACE),
+            Some(DetectedProxyProtocol::Http2)
+        );
+    }
+
+    #[test]
+    fn proxy_protocol() {
+        assert_eq!(
+            detect_by_leading_bytes(b"PROXY TCP4 "),
+            Some(DetectedProxyProtocol::ProxyProtocol)
+        );
+        assert_eq!(
+            detect_by_leading_bytes(PROXY_PROTOCOL_V2_SIGNATURE),
+            Some(DetectedProxyProtocol::ProxyProtocol)
+        );
+        assert_eq!(detect_by_leading_bytes(b"\r\n"), None);
+    }
+
+    #[tokio::test]
+    async fn detect_partial_header() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let client = tokio::spawn(async move {
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            stream.write_all(&HTTP2_PREFACE[..3]).await.unwrap();
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            stream.write_all(&HTTP2_PREFACE[3..10]).await.unwrap();
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            stream.write_all(&HTTP2_PREFACE[10..]).await.unwrap();
+            stream
+        });
+        let (stream, _) = listener.accept().await.unwrap();
+        let p = tokio::time::timeout(Duration::from_secs(2), detect_tcp_proxy_protocol(&stream))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(p, DetectedProxyProtocol::Http2);
+        let _ = client.await.unwrap();
+
+        let client = tokio::spawn(async move {
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            stream.write_all(&HTTP2_PREFACE[..3]).await.unwrap();
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            stream.shutdown().await.unwrap();
+            stream
+        });
+        let (stream, _) = listener.accept().await.unwrap();
+        let p = tokio::time::timeout(Duration::from_secs(2), detect_tcp_proxy_protocol(&stream))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(p, DetectedProxyProtocol::Unknown);
+        let _ = client.await.unwrap();
+    }
 }
@@ -18,7 +18,7 @@ use std::net::SocketAddr;
 use std::sync::Arc;
 
 use anyhow::anyhow;
-use arc_swap::ArcSwap;
+use arc_swap::{ArcSwap, ArcSwapOption};
 use async_trait::async_trait;
 #[cfg(feature = "quic")]
 use quinn::Connection;
@@ -47,6 +47,8 @@ pub(crate) struct IntelliProxy {
 
     http_server: ArcSwap<ArcServer>,
     socks_server: ArcSwap<ArcServer>,
+    tls_server: ArcSwapOption<ArcServer>,
+    h2_server: ArcSwapOption<ArcServer>,
     quit_policy: Arc<ServerQuitPolicy>,
     reload_version: usize,
 }
@@ -66,6 +68,8 @@ impl IntelliProxy {
 
         let http_server = Arc::new(crate::serve::get_or_insert_default(&config.http_server));
         let socks_server = Arc::new(crate::serve::get_or_insert_default(&config.socks_server));
+        let tls_server = get_optional_next_server(&config.tls_server);
+        let h2_server = get_optional_next_server(&config.h2_server);
 
         IntelliProxy {
             config,
@@ -74,6 +78,8 @@ impl IntelliProxy {
             reload_sender,
             http_server: ArcSwap::new(http_server),
             socks_server: ArcSwap::new(socks_server),
+            tls_server: ArcSwapOption::new(tls_server),
+            h2_server: ArcSwapOption::new(h2_server),
             quit_policy: Arc::new(ServerQuitPolicy::default()),
             reload_version,
         }
@@ -165,6 +171,24 @@ impl IntelliProxy {
                 let next_server = self.socks_server.load_full();
                 next_server.run_tcp_task(stream, cc_info).await;
             }
+            Ok(Ok(DetectedProxyProtocol::Tls)) => {
+                if let Some(next_server) = self.tls_server.load_full() {
+                    next_server.run_tcp_task(stream, cc_info).await;
+                } else {
+                    self.listen_stats.add_failed();
+                }
+            }
+            Ok(Ok(DetectedProxyProtocol::Http2)) => {
+                if let Some(next_server) = self.h2_server.load_full() {
+                    next_server.run_tcp_task(stream, cc_info).await;
+                } else {
+                    self.listen_stats.add_failed();
+                }
+            }
+            Ok(Ok(DetectedProxyProtocol::ProxyProtocol)) => {
+                // PROXY protocol header not expected here
+                self.listen_stats.add_failed();
+            }
             Ok(Err(_)) => {
                 // io error
                 self.listen_stats.add_failed();
@@ -177,6 +201,14 @@ impl IntelliProxy {
     }
 }
 
+fn get_optional_next_server(name: &MetricsName) -> Option<Arc<ArcServer>> {
+    if name.is_empty() {
+        None
+    } else {
+        Some(Arc::new(crate::serve::get_or_insert_default(name)))
+    }
+}
+
 impl ServerInternal for IntelliProxy {
     fn _clone_config(&self) -> AnyServerConfig {
         AnyServerConfig::IntelliProxy(self.config.clone())
@@ -188,7 +220,10 @@ impl ServerInternal for IntelliProxy {
 
     fn _depend_on_server(&self, name: &MetricsName) -> bool {
         let config = &self.config;
-        config.http_server.eq(name) || config.socks_server.eq(name)
+        config.http_server.eq(name)
+            || config.socks_server.eq(name)
+            || config.tls_server.eq(name)
+            || config.h2_server.eq(name)
     }
 
     fn _reload_config_notify_runtime(&self) {
@@ -201,6 +236,10 @@ impl ServerInternal for IntelliProxy {
         self.http_server.store(Arc::new(http_next_server));
         let socks_next_server = crate::serve::get_or_insert_default(&self.config.socks_server);
         self.socks_server.store(Arc::new(socks_next_server));
+        self.tls_server
+            .store(get_optional_next_server(&self.config.tls_server));
+        self.h2_server
+            .store(get_optional_next_server(&self.config.h2_server));
     }
 
     fn _update_escaper_in_place(&self) {}
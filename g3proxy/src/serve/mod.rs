@@ -59,9 +59,12 @@ mod tcp_stream;
 ))]
 mod tcp_tproxy;
 mod tls_stream;
+#[cfg(target_os = "linux")]
+mod udp_tproxy;
 
 mod error;
 mod task;
+pub(crate) mod task_registry;
 
 pub(crate) use error::{ServerTaskError, ServerTaskForbiddenError, ServerTaskResult};
 pub(crate) use task::{ServerTaskNotes, ServerTaskStage};
@@ -116,6 +119,14 @@ pub(crate) trait Server:
     async fn run_rustls_task(&self, stream: TlsStream<TcpStream>, cc_info: ClientConnectionInfo);
 
     async fn run_openssl_task(&self, stream: SslStream<TcpStream>, cc_info: ClientConnectionInfo);
+
+    #[cfg(target_os = "linux")]
+    async fn run_udp_tproxy_task(
+        &self,
+        _flow: udp_tproxy::UdpTProxyFlow,
+        _cc_info: ClientConnectionInfo,
+    ) {
+    }
 }
 
 pub(crate) type ArcServer = Arc<dyn Server + Send + Sync>;
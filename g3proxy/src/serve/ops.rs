@@ -50,6 +50,8 @@ use super::tcp_stream::TcpStreamServer;
 ))]
 use super::tcp_tproxy::TcpTProxyServer;
 use super::tls_stream::TlsStreamServer;
+#[cfg(target_os = "linux")]
+use super::udp_tproxy::UdpTProxyServer;
 
 static SERVER_OPS_LOCK: Mutex<()> = Mutex::const_new(());
 
@@ -308,6 +310,8 @@ fn spawn_new_unlocked(config: AnyServerConfig) -> anyhow::Result<()> {
             target_os = "openbsd"
         ))]
         AnyServerConfig::TcpTProxy(c) => TcpTProxyServer::prepare_initial(c)?,
+        #[cfg(target_os = "linux")]
+        AnyServerConfig::UdpTProxy(c) => UdpTProxyServer::prepare_initial(c)?,
         AnyServerConfig::TlsStream(c) => TlsStreamServer::prepare_initial(*c)?,
         AnyServerConfig::SniProxy(c) => SniProxyServer::prepare_initial(*c)?,
         AnyServerConfig::SocksProxy(c) => SocksProxyServer::prepare_initial(*c)?,
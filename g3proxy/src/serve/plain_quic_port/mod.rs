@@ -32,7 +32,7 @@ use g3_daemon::server::{BaseServer, ClientConnectionInfo, ServerReloadCommand};
 use g3_openssl::SslStream;
 use g3_types::acl::AclNetworkRule;
 use g3_types::metrics::MetricsName;
-use g3_types::net::{OpensslTicketKey, RollingTicketer, UdpListenConfig};
+use g3_types::net::{OpensslTicketKey, RollingTicketer, RustlsNoSessionTicketer, UdpListenConfig};
 
 use crate::config::server::plain_quic_port::{PlainQuicPortConfig, PlainQuicPortUpdateFlags};
 use crate::config::server::{AnyServerConfig, ServerConfig};
@@ -98,9 +98,10 @@ impl PlainQuicPort {
     ) -> anyhow::Result<Self> {
         let reload_sender = crate::serve::new_reload_notify_channel();
 
-        let quic_server = config
-            .tls_server
-            .build_quic_with_ticketer(tls_rolling_ticketer.clone())?;
+        let quic_server = config.tls_server.build_quic_with_alpn_protocols(
+            config.alpn_protocols.clone(),
+            tls_rolling_ticketer.clone(),
+        )?;
 
         let ingress_net_filter = config
             .ingress_net_filter
@@ -195,7 +196,12 @@ impl ServerInternal for PlainQuicPort {
             };
 
             let quinn_config = if flags.contains(PlainQuicPortUpdateFlags::QUINN) {
-                let quic_config = config.tls_server.build_quic()?;
+                let quic_config = config
+                    .tls_server
+                    .build_quic_with_alpn_protocols::<RustlsNoSessionTicketer>(
+                        config.alpn_protocols.clone(),
+                        None,
+                    )?;
                 Some(quinn::ServerConfig::with_crypto(quic_config.driver))
             } else {
                 None
@@ -32,6 +32,7 @@ use crate::config::server::ServerConfig;
 use crate::inspect::{StreamInspectContext, StreamInspection};
 use crate::log::task::tcp_connect::TaskLogForTcpConnect;
 use crate::module::tcp_connect::{TcpConnectTaskConf, TcpConnectTaskNotes};
+use crate::serve::task_registry;
 use crate::serve::tcp_stream::TcpStreamTaskCltWrapperStats;
 use crate::serve::{ServerTaskError, ServerTaskNotes, ServerTaskResult, ServerTaskStage};
 
@@ -171,7 +172,18 @@ impl TcpStreamTask {
         UW: AsyncWrite + Send + Sync + Unpin + 'static,
     {
         self.task_notes.mark_relaying();
-        self.relay(clt_r, clt_r_buf, clt_w, ups_r, ups_w).await
+        let alive_task = task_registry::register(
+            self.ctx.server_config.name(),
+            "SniProxy",
+            &self.task_notes,
+            &self.upstream,
+            &self.tcp_notes.escaper,
+            &self.task_stats,
+        );
+        tokio::select! {
+            r = self.relay(clt_r, clt_r_buf, clt_w, ups_r, ups_w) => r,
+            _ = alive_task.killed() => Err(ServerTaskError::CanceledAsTaskKilled),
+        }
     }
 
     async fn relay<CR, CW, UR, UW>(
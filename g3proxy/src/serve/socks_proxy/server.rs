@@ -17,7 +17,7 @@
 use std::net::SocketAddr;
 use std::sync::Arc;
 
-use anyhow::anyhow;
+use anyhow::{anyhow, Context};
 use arc_swap::{ArcSwap, ArcSwapOption};
 use async_trait::async_trait;
 #[cfg(feature = "quic")]
@@ -30,10 +30,14 @@ use tokio_rustls::server::TlsStream;
 
 use g3_daemon::listen::{AcceptQuicServer, AcceptTcpServer, ListenStats, ListenTcpRuntime};
 use g3_daemon::server::{BaseServer, ClientConnectionInfo, ServerReloadCommand};
+use g3_geoip_types::IpLocation;
+#[cfg(feature = "gssapi")]
+use g3_gssapi::AcceptorCredential;
 use g3_io_ext::AsyncStream;
+use g3_ip_locate::IpLocationServiceHandle;
 use g3_openssl::SslStream;
 use g3_types::acl::{AclAction, AclNetworkRule};
-use g3_types::acl_set::AclDstHostRuleSet;
+use g3_types::acl_set::{AclClientGeoRuleSet, AclDstHostRuleSet};
 use g3_types::metrics::MetricsName;
 
 use super::task::{CommonTaskContext, SocksProxyNegotiationTask};
@@ -52,12 +56,16 @@ pub(crate) struct SocksProxyServer {
     server_stats: Arc<SocksProxyServerStats>,
     listen_stats: Arc<ListenStats>,
     ingress_net_filter: Option<Arc<AclNetworkRule>>,
+    ingress_geo_filter: Option<AclClientGeoRuleSet>,
+    ip_locate_handle: Option<Arc<IpLocationServiceHandle>>,
     dst_host_filter: Option<Arc<AclDstHostRuleSet>>,
     reload_sender: broadcast::Sender<ServerReloadCommand>,
     task_logger: Logger,
 
     escaper: ArcSwap<ArcEscaper>,
     user_group: ArcSwapOption<UserGroup>,
+    #[cfg(feature = "gssapi")]
+    gssapi_cred: Option<Arc<AcceptorCredential>>,
     audit_handle: ArcSwapOption<AuditHandle>,
     quit_policy: Arc<ServerQuitPolicy>,
     reload_version: usize,
@@ -68,6 +76,7 @@ impl SocksProxyServer {
         config: Arc<SocksProxyServerConfig>,
         server_stats: Arc<SocksProxyServerStats>,
         listen_stats: Arc<ListenStats>,
+        ip_locate_handle: Option<Arc<IpLocationServiceHandle>>,
         version: usize,
     ) -> anyhow::Result<SocksProxyServer> {
         let reload_sender = crate::serve::new_reload_notify_channel();
@@ -76,6 +85,10 @@ impl SocksProxyServer {
             .ingress_net_filter
             .as_ref()
             .map(|builder| Arc::new(builder.build()));
+        let ingress_geo_filter = config
+            .ingress_geo_filter
+            .as_ref()
+            .map(|builder| builder.build());
 
         let dst_host_filter = config
             .dst_host_filter
@@ -90,16 +103,32 @@ impl SocksProxyServer {
         let user_group = config.get_user_group();
         let audit_handle = config.get_audit_handle()?;
 
+        #[cfg(feature = "gssapi")]
+        let gssapi_cred = match &config.gssapi {
+            Some(c) => {
+                let cred = AcceptorCredential::acquire(c.service.as_deref(), c.keytab.as_deref())
+                    .map_err(|e| {
+                    anyhow!("failed to acquire gssapi acceptor credential: {e}")
+                })?;
+                Some(Arc::new(cred))
+            }
+            None => None,
+        };
+
         let server = SocksProxyServer {
             config,
             server_stats,
             listen_stats,
             ingress_net_filter,
+            ingress_geo_filter,
+            ip_locate_handle,
             dst_host_filter,
             reload_sender,
             task_logger,
             escaper: ArcSwap::new(escaper),
             user_group: ArcSwapOption::new(user_group),
+            #[cfg(feature = "gssapi")]
+            gssapi_cred,
             audit_handle: ArcSwapOption::new(audit_handle),
             quit_policy: Arc::new(ServerQuitPolicy::default()),
             reload_version: version,
@@ -113,7 +142,17 @@ impl SocksProxyServer {
         let server_stats = Arc::new(SocksProxyServerStats::new(config.name()));
         let listen_stats = Arc::new(ListenStats::new(config.name()));
 
-        let server = SocksProxyServer::new(config, server_stats, listen_stats, 1)?;
+        let ip_locate_handle = if let Some(c) = &config.ip_locate_service {
+            let handle = c
+                .spawn_ip_locate_agent()
+                .context("failed to spawn ip locate agent")?;
+            Some(Arc::new(handle))
+        } else {
+            None
+        };
+
+        let server =
+            SocksProxyServer::new(config, server_stats, listen_stats, ip_locate_handle, 1)?;
         Ok(Arc::new(server))
     }
 
@@ -123,8 +162,24 @@ impl SocksProxyServer {
             let server_stats = Arc::clone(&self.server_stats);
             let listen_stats = Arc::clone(&self.listen_stats);
 
-            let server =
-                SocksProxyServer::new(config, server_stats, listen_stats, self.reload_version + 1)?;
+            let ip_locate_handle = if self.config.ip_locate_service.eq(&config.ip_locate_service) {
+                self.ip_locate_handle.clone()
+            } else if let Some(c) = &config.ip_locate_service {
+                let handle = c
+                    .spawn_ip_locate_agent()
+                    .context("failed to spawn ip locate agent")?;
+                Some(Arc::new(handle))
+            } else {
+                None
+            };
+
+            let server = SocksProxyServer::new(
+                config,
+                server_stats,
+                listen_stats,
+                ip_locate_handle,
+                self.reload_version + 1,
+            )?;
             Ok(server)
         } else {
             Err(anyhow!(
@@ -152,6 +207,26 @@ impl SocksProxyServer {
         false
     }
 
+    async fn locate_client(&self, client_addr: SocketAddr) -> Option<Arc<IpLocation>> {
+        let handle = self.ip_locate_handle.as_ref()?;
+        handle.fetch(client_addr.ip()).await
+    }
+
+    fn drop_by_location(&self, location: Option<&IpLocation>) -> bool {
+        let Some(filter) = &self.ingress_geo_filter else {
+            return false;
+        };
+        let action = match location {
+            Some(location) => filter.check(location).1,
+            None => filter.missed_action(),
+        };
+        if action.forbid_early() {
+            self.server_stats.forbidden.add_src_geo_blocked();
+            return true;
+        }
+        false
+    }
+
     fn audit_context(&self) -> AuditContext {
         AuditContext::new(self.audit_handle.load_full())
     }
@@ -167,6 +242,10 @@ impl SocksProxyServer {
         if self.drop_early(client_addr) {
             return;
         }
+        let client_location = self.locate_client(client_addr).await;
+        if self.drop_by_location(client_location.as_deref()) {
+            return;
+        }
 
         let ctx = CommonTaskContext {
             server_config: Arc::clone(&self.config),
@@ -176,7 +255,10 @@ impl SocksProxyServer {
             ingress_net_filter: self.ingress_net_filter.clone(),
             dst_host_filter: self.dst_host_filter.clone(),
             cc_info,
+            client_location,
             task_logger: self.task_logger.clone(),
+            #[cfg(feature = "gssapi")]
+            gssapi_cred: self.gssapi_cred.clone(),
         };
         SocksProxyNegotiationTask::new(ctx, self.audit_context(), self.user_group.load_full())
             .into_running(stream)
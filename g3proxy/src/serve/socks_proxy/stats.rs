@@ -39,6 +39,7 @@ pub(crate) struct SocksProxyServerStats {
     pub(crate) forbidden: ServerForbiddenStats,
 
     pub(crate) task_tcp_connect: ServerPerTaskStats,
+    pub(crate) task_tcp_bind: ServerPerTaskStats,
     pub(crate) task_udp_associate: ServerPerTaskStats,
     pub(crate) task_udp_connect: ServerPerTaskStats,
 
@@ -56,6 +57,7 @@ impl SocksProxyServerStats {
             conn_total: AtomicU64::new(0),
             forbidden: Default::default(),
             task_tcp_connect: Default::default(),
+            task_tcp_bind: Default::default(),
             task_udp_associate: Default::default(),
             task_udp_connect: Default::default(),
             io_tcp: TcpIoStats::default(),
@@ -111,12 +113,14 @@ impl ServerStats for SocksProxyServerStats {
 
     fn get_task_total(&self) -> u64 {
         self.task_tcp_connect.get_task_total()
+            + self.task_tcp_bind.get_task_total()
             + self.task_udp_connect.get_task_total()
             + self.task_udp_associate.get_task_total()
     }
 
     fn get_alive_count(&self) -> i32 {
         self.task_tcp_connect.get_alive_count()
+            + self.task_tcp_bind.get_alive_count()
             + self.task_udp_connect.get_alive_count()
             + self.task_udp_associate.get_alive_count()
     }
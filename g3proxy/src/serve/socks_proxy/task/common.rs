@@ -21,6 +21,9 @@ use slog::Logger;
 use tokio::net::UdpSocket;
 
 use g3_daemon::server::ClientConnectionInfo;
+use g3_geoip_types::IpLocation;
+#[cfg(feature = "gssapi")]
+use g3_gssapi::AcceptorCredential;
 use g3_types::acl::{AclAction, AclNetworkRule};
 use g3_types::acl_set::AclDstHostRuleSet;
 use g3_types::net::UpstreamAddr;
@@ -38,7 +41,10 @@ pub(crate) struct CommonTaskContext {
     pub(crate) ingress_net_filter: Option<Arc<AclNetworkRule>>,
     pub(crate) dst_host_filter: Option<Arc<AclDstHostRuleSet>>,
     pub(crate) cc_info: ClientConnectionInfo,
+    pub(crate) client_location: Option<Arc<IpLocation>>,
     pub(crate) task_logger: Logger,
+    #[cfg(feature = "gssapi")]
+    pub(crate) gssapi_cred: Option<Arc<AcceptorCredential>>,
 }
 
 impl CommonTaskContext {
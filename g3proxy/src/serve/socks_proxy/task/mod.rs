@@ -21,6 +21,7 @@ mod common;
 pub(super) use common::CommonTaskContext;
 
 mod negotiation;
+mod tcp_bind;
 mod tcp_connect;
 mod udp_associate;
 mod udp_connect;
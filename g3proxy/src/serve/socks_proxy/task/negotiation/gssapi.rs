@@ -0,0 +1,189 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::sync::Arc;
+
+use log::debug;
+use tokio::io::{AsyncBufRead, AsyncWrite};
+
+use g3_gssapi::{AcceptContext, AcceptorCredential};
+use g3_socks::v5;
+
+use super::SocksProxyNegotiationTask;
+use crate::auth::{UserContext, UserGroup};
+use crate::config::server::ServerConfig;
+use crate::serve::{ServerStats, ServerTaskError, ServerTaskForbiddenError, ServerTaskResult};
+
+impl SocksProxyNegotiationTask {
+    /// Run the GSSAPI subnegotiation as defined in RFC 1961 and map the client principal to a user.
+    ///
+    /// Only the `clear` protection level is supported, so no per-message protection is applied
+    /// to the relayed data, and the `clear` level will always be replied to the client.
+    pub(super) async fn auth_by_gssapi<R, W>(
+        &self,
+        cred: &Arc<AcceptorCredential>,
+        user_group: &Arc<UserGroup>,
+        clt_r: &mut R,
+        clt_w: &mut W,
+    ) -> ServerTaskResult<UserContext>
+    where
+        R: AsyncBufRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        let mut gss_ctx = AcceptContext::new(cred.clone());
+        while !gss_ctx.is_established() {
+            let (mtyp, token) = v5::auth::recv_gssapi_message_from_client(clt_r).await?;
+            match mtyp {
+                v5::auth::GSSAPI_MSG_TYPE_AUTH => {}
+                v5::auth::GSSAPI_MSG_TYPE_ABORT => return Err(ServerTaskError::ClientAuthFailed),
+                _ => {
+                    let _ = v5::auth::send_gssapi_abort_to_client(clt_w).await;
+                    return Err(ServerTaskError::InvalidClientProtocol(
+                        "unexpected gssapi message type",
+                    ));
+                }
+            }
+
+            // the acceptor may do blocking io, such as reading the keytab and the replay cache
+            let (ctx, r) = tokio::task::spawn_blocking(move || {
+                let r = gss_ctx.step(&token);
+                (gss_ctx, r)
+            })
+            .await
+            .map_err(|_| ServerTaskError::InternalServerError("gssapi step task failed"))?;
+            gss_ctx = ctx;
+            match r {
+                Ok(output) => {
+                    if !output.is_empty() {
+                        v5::auth::send_gssapi_message_to_client(
+                            clt_w,
+                            v5::auth::GSSAPI_MSG_TYPE_AUTH,
+                            &output,
+                        )
+                        .await
+                        .map_err(ServerTaskError::ClientTcpWriteFailed)?;
+                    }
+                }
+                Err(_) => {
+                    self.ctx.server_stats.forbidden.add_auth_failed();
+                    let _ = v5::auth::send_gssapi_abort_to_client(clt_w).await;
+                    return Err(ServerTaskError::ClientAuthFailed);
+                }
+            }
+        }
+
+        let principal = match gss_ctx.source_name() {
+            Ok(name) => name,
+            Err(_) => {
+                self.ctx.server_stats.forbidden.add_auth_failed();
+                let _ = v5::auth::send_gssapi_abort_to_client(clt_w).await;
+                return Err(ServerTaskError::ClientAuthFailed);
+            }
+        };
+
+        // protection level subnegotiation
+        let (mtyp, token) = v5::auth::recv_gssapi_message_from_client(clt_r).await?;
+        match mtyp {
+            v5::auth::GSSAPI_MSG_TYPE_PROTECTION => {}
+            v5::auth::GSSAPI_MSG_TYPE_ABORT => return Err(ServerTaskError::ClientAuthFailed),
+            _ => {
+                let _ = v5::auth::send_gssapi_abort_to_client(clt_w).await;
+                return Err(ServerTaskError::InvalidClientProtocol(
+                    "unexpected gssapi message type",
+                ));
+            }
+        }
+        let level = match gss_ctx.unwrap(&token) {
+            Ok(level) if level.len() == 1 => level[0],
+            _ => {
+                let _ = v5::auth::send_gssapi_abort_to_client(clt_w).await;
+                return Err(ServerTaskError::InvalidClientProtocol(
+                    "invalid gssapi protection level message",
+                ));
+            }
+        };
+        let Some(selected) = v5::auth::select_gssapi_protection_level(level) else {
+            let _ = v5::auth::send_gssapi_abort_to_client(clt_w).await;
+            return Err(ServerTaskError::InvalidClientProtocol(
+                "invalid gssapi protection level",
+            ));
+        };
+        if selected != level {
+            debug!(
+                "{} - {} gssapi protection level {level} downgraded to {selected} for {principal}",
+                self.ctx.cc_info.sock_local_addr(),
+                self.ctx.cc_info.sock_peer_addr(),
+            );
+        }
+        let Ok(reply) = gss_ctx.wrap(&[selected], false) else {
+            let _ = v5::auth::send_gssapi_abort_to_client(clt_w).await;
+            return Err(ServerTaskError::ClientAuthFailed);
+        };
+        drop(gss_ctx);
+
+        let username = match &self.ctx.server_config.gssapi {
+            Some(c) if c.strip_realm => principal
+                .split_once('@')
+                .map(|(name, _realm)| name)
+                .unwrap_or(&principal),
+            _ => principal.as_str(),
+        };
+        let Some((user, user_type)) = user_group.get_user(username) else {
+            self.ctx.server_stats.forbidden.add_auth_failed();
+            let _ = v5::auth::send_gssapi_abort_to_client(clt_w).await;
+            return Err(ServerTaskError::ClientAuthFailed);
+        };
+        let user_ctx = UserContext::new(
+            Some(Arc::from(username)),
+            user,
+            user_type,
+            self.ctx.server_config.name(),
+            self.ctx.server_stats.share_extra_tags(),
+        );
+        if user_ctx
+            .check_client_addr(self.ctx.client_addr(), self.ctx.client_location.as_deref())
+            .is_err()
+        {
+            self.ctx.server_stats.forbidden.add_auth_failed();
+            let _ = v5::auth::send_gssapi_abort_to_client(clt_w).await;
+            return Err(ServerTaskError::ClientAuthFailed);
+        }
+        if let Err(e) = user_ctx.check_availability() {
+            return if let Some(duration) = e.blocked_delay() {
+                self.ctx.server_stats.forbidden.add_user_blocked();
+                tokio::time::sleep(duration).await;
+                let _ = v5::auth::send_gssapi_abort_to_client(clt_w).await;
+                Err(ServerTaskError::ForbiddenByRule(
+                    ServerTaskForbiddenError::UserBlocked,
+                ))
+            } else {
+                self.ctx.server_stats.forbidden.add_auth_failed();
+                let _ = v5::auth::send_gssapi_abort_to_client(clt_w).await;
+                Err(ServerTaskError::ClientAuthFailed)
+            };
+        }
+
+        v5::auth::send_gssapi_message_to_client(
+            clt_w,
+            v5::auth::GSSAPI_MSG_TYPE_PROTECTION,
+            &reply,
+        )
+        .await
+        .map_err(ServerTaskError::ClientTcpWriteFailed)?;
+        user_ctx.req_stats().conn_total.add_socks();
+        Ok(user_ctx)
+    }
+}
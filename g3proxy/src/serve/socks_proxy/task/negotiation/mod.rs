@@ -14,10 +14,15 @@
  * limitations under the License.
  */
 
-use super::{tcp_connect, udp_associate, udp_connect, CommonTaskContext, SocksProxyServerStats};
+use super::{
+    tcp_bind, tcp_connect, udp_associate, udp_connect, CommonTaskContext, SocksProxyServerStats,
+};
 
 mod task;
 pub(crate) use task::SocksProxyNegotiationTask;
 
+#[cfg(feature = "gssapi")]
+mod gssapi;
+
 mod stats;
 use stats::SocksProxyCltWrapperStats;
@@ -14,6 +14,7 @@
  * limitations under the License.
  */
 
+use std::collections::BTreeSet;
 use std::sync::Arc;
 
 use log::debug;
@@ -23,6 +24,7 @@ use tokio::time::Instant;
 use g3_io_ext::{AsyncStream, LimitedReader, LimitedWriter};
 use g3_socks::{v4a, v5, SocksAuthMethod, SocksCommand, SocksVersion};
 
+use super::tcp_bind::SocksProxyTcpBindTask;
 use super::tcp_connect::SocksProxyTcpConnectTask;
 use super::udp_associate::SocksProxyUdpAssociateTask;
 use super::udp_connect::SocksProxyUdpConnectTask;
@@ -129,7 +131,7 @@ impl SocksProxyNegotiationTask {
     async fn run_v4<CDR, CDW>(
         self,
         mut clt_r: BufReader<LimitedReader<CDR>>,
-        mut clt_w: LimitedWriter<CDW>,
+        clt_w: LimitedWriter<CDW>,
     ) -> ServerTaskResult<()>
     where
         CDR: AsyncRead + Send + Sync + Unpin + 'static,
@@ -160,10 +162,15 @@ impl SocksProxyNegotiationTask {
                 Ok(())
             }
             SocksCommand::TcpBind => {
-                let _ = v4a::SocksV4Reply::RequestRejectedOrFailed
-                    .send(&mut clt_w)
-                    .await;
-                Err(ServerTaskError::UnimplementedProtocol)
+                let task = SocksProxyTcpBindTask::new(
+                    SocksVersion::V4a,
+                    self.ctx,
+                    task_notes,
+                    req.upstream,
+                    self.audit_ctx,
+                );
+                task.into_running(clt_r.into_inner(), clt_w);
+                Ok(())
             }
             _ => Err(ServerTaskError::InvalidClientProtocol(
                 "invalid socks4 command",
@@ -171,6 +178,27 @@ impl SocksProxyNegotiationTask {
         }
     }
 
+    fn select_auth_method(&self, client_methods: &BTreeSet<SocksAuthMethod>) -> SocksAuthMethod {
+        let Some(user_group) = &self.user_group else {
+            return SocksAuthMethod::None;
+        };
+
+        #[cfg(feature = "gssapi")]
+        if self.ctx.gssapi_cred.is_some() && client_methods.contains(&SocksAuthMethod::GssApi) {
+            return SocksAuthMethod::GssApi;
+        }
+
+        if client_methods.contains(&SocksAuthMethod::User) {
+            SocksAuthMethod::User
+        } else if user_group
+            .allow_anonymous(self.ctx.client_addr(), self.ctx.client_location.as_deref())
+        {
+            SocksAuthMethod::None
+        } else {
+            SocksAuthMethod::User
+        }
+    }
+
     async fn run_v5<CDR, CDW>(
         self,
         mut clt_r: BufReader<LimitedReader<CDR>>,
@@ -181,17 +209,7 @@ impl SocksProxyNegotiationTask {
         CDW: AsyncWrite + Send + Sync + Unpin + 'static,
     {
         let client_methods = v5::auth::recv_methods_from_client(&mut clt_r).await?;
-        let auth_method = if let Some(user_group) = &self.user_group {
-            if client_methods.contains(&SocksAuthMethod::User) {
-                SocksAuthMethod::User
-            } else if user_group.allow_anonymous(self.ctx.client_addr()) {
-                SocksAuthMethod::None
-            } else {
-                SocksAuthMethod::User
-            }
-        } else {
-            SocksAuthMethod::None
-        };
+        let auth_method = self.select_auth_method(&client_methods);
         if !client_methods.contains(&auth_method) {
             let _ =
                 v5::auth::send_method_to_client(&mut clt_w, &SocksAuthMethod::NoAcceptable).await;
@@ -232,7 +250,13 @@ impl SocksProxyNegotiationTask {
                             self.ctx.server_config.name(),
                             self.ctx.server_stats.share_extra_tags(),
                         );
-                        if user_ctx.check_client_addr(self.ctx.client_addr()).is_err() {
+                        if user_ctx
+                            .check_client_addr(
+                                self.ctx.client_addr(),
+                                self.ctx.client_location.as_deref(),
+                            )
+                            .is_err()
+                        {
                             self.ctx.server_stats.forbidden.add_auth_failed();
                             let _ = v5::auth::send_user_auth_failure(&mut clt_w).await;
                             return Err(ServerTaskError::ClientAuthFailed);
@@ -269,6 +293,17 @@ impl SocksProxyNegotiationTask {
                     unreachable!()
                 }
             }
+            #[cfg(feature = "gssapi")]
+            SocksAuthMethod::GssApi => {
+                let (Some(user_group), Some(cred)) = (&self.user_group, &self.ctx.gssapi_cred)
+                else {
+                    unreachable!()
+                };
+                let user_ctx = self
+                    .auth_by_gssapi(cred, user_group, &mut clt_r, &mut clt_w)
+                    .await?;
+                Some(user_ctx)
+            }
             _ => return Err(ServerTaskError::UnimplementedProtocol),
         };
 
@@ -319,8 +354,15 @@ impl SocksProxyNegotiationTask {
                 }
             }
             SocksCommand::TcpBind => {
-                let _ = v5::Socks5Reply::CommandNotSupported.send(&mut clt_w).await;
-                Err(ServerTaskError::UnimplementedProtocol)
+                let task = SocksProxyTcpBindTask::new(
+                    SocksVersion::V5,
+                    self.ctx,
+                    task_notes,
+                    req.upstream,
+                    self.audit_ctx,
+                );
+                task.into_running(clt_r.into_inner(), clt_w);
+                Ok(())
             }
         }
     }
@@ -0,0 +1,543 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+
+use log::debug;
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use g3_daemon::stat::task::TcpStreamTaskStats;
+use g3_io_ext::{LimitedReader, LimitedWriter};
+use g3_socks::{v4a, v5, SocksVersion};
+use g3_types::acl::AclAction;
+use g3_types::net::{ConnectError, Host, ProxyRequestType, UpstreamAddr};
+
+use super::{CommonTaskContext, TcpBindTaskCltWrapperStats};
+use crate::audit::AuditContext;
+use crate::config::server::ServerConfig;
+use crate::inspect::StreamInspectContext;
+use crate::log::task::tcp_bind::TaskLogForTcpBind;
+use crate::module::tcp_bind::{BoxTcpBindListener, TcpBindTaskConf};
+use crate::module::tcp_connect::TcpConnectTaskNotes;
+use crate::serve::{
+    task_registry, ServerStats, ServerTaskError, ServerTaskForbiddenError, ServerTaskNotes,
+    ServerTaskResult, ServerTaskStage,
+};
+
+pub(crate) struct SocksProxyTcpBindTask {
+    socks_version: SocksVersion,
+    ctx: CommonTaskContext,
+    upstream: UpstreamAddr,
+    task_notes: ServerTaskNotes,
+    tcp_notes: TcpConnectTaskNotes,
+    task_stats: Arc<TcpStreamTaskStats>,
+    audit_ctx: AuditContext,
+}
+
+impl SocksProxyTcpBindTask {
+    pub(crate) fn new(
+        socks_version: SocksVersion,
+        ctx: CommonTaskContext,
+        mut task_notes: ServerTaskNotes,
+        upstream: UpstreamAddr,
+        audit_ctx: AuditContext,
+    ) -> Self {
+        if let Some(user_ctx) = task_notes.user_ctx_mut() {
+            user_ctx.check_in_site(
+                ctx.server_config.name(),
+                ctx.server_stats.share_extra_tags(),
+                &upstream,
+            );
+            if let Some(site_req_stats) = user_ctx.site_req_stats() {
+                site_req_stats.conn_total.add_socks();
+            }
+        }
+        SocksProxyTcpBindTask {
+            socks_version,
+            ctx,
+            upstream,
+            task_notes,
+            tcp_notes: TcpConnectTaskNotes::default(),
+            task_stats: Arc::new(TcpStreamTaskStats::default()),
+            audit_ctx,
+        }
+    }
+
+    fn get_log_context(&self) -> TaskLogForTcpBind {
+        TaskLogForTcpBind {
+            upstream: &self.upstream,
+            task_notes: &self.task_notes,
+            tcp_notes: &self.tcp_notes,
+            total_time: self.task_notes.time_elapsed(),
+            client_rd_bytes: self.task_stats.clt.read.get_bytes(),
+            client_wr_bytes: self.task_stats.clt.write.get_bytes(),
+            remote_rd_bytes: self.task_stats.ups.read.get_bytes(),
+            remote_wr_bytes: self.task_stats.ups.write.get_bytes(),
+        }
+    }
+
+    pub(crate) fn into_running<R, W>(mut self, clt_r: LimitedReader<R>, clt_w: LimitedWriter<W>)
+    where
+        R: AsyncRead + Send + Sync + Unpin + 'static,
+        W: AsyncWrite + Send + Sync + Unpin + 'static,
+    {
+        tokio::spawn(async move {
+            self.pre_start();
+            match self.run(clt_r, clt_w).await {
+                Ok(_) => self
+                    .get_log_context()
+                    .log(&self.ctx.task_logger, &ServerTaskError::Finished),
+                Err(e) => self.get_log_context().log(&self.ctx.task_logger, &e),
+            }
+            self.pre_stop();
+        });
+    }
+
+    fn pre_start(&self) {
+        debug!(
+            "Socks/TcpBind: new client from {} to {} server {}, using escaper {}",
+            self.ctx.client_addr(),
+            self.ctx.server_config.server_type(),
+            self.ctx.server_config.name(),
+            self.ctx.server_config.escaper
+        );
+        self.ctx.server_stats.task_tcp_bind.add_task();
+        self.ctx.server_stats.task_tcp_bind.inc_alive_task();
+
+        if let Some(user_ctx) = self.task_notes.user_ctx() {
+            user_ctx.foreach_req_stats(|s| {
+                s.req_total.add_socks_tcp_bind();
+                s.req_alive.add_socks_tcp_bind();
+            });
+        }
+    }
+
+    fn pre_stop(&mut self) {
+        self.ctx.server_stats.task_tcp_bind.dec_alive_task();
+
+        if let Some(user_ctx) = self.task_notes.user_ctx() {
+            user_ctx.foreach_req_stats(|s| s.req_alive.del_socks_tcp_bind());
+
+            if let Some(user_req_alive_permit) = self.task_notes.user_req_alive_permit.take() {
+                drop(user_req_alive_permit);
+            }
+        }
+    }
+
+    async fn reply_forbidden<W>(&self, clt_w: &mut W)
+    where
+        W: AsyncWrite + Unpin,
+    {
+        match self.socks_version {
+            SocksVersion::V4a => {
+                let _ = v4a::SocksV4Reply::RequestRejectedOrFailed.send(clt_w).await;
+            }
+            SocksVersion::V5 => {
+                let _ = v5::Socks5Reply::ForbiddenByRule.send(clt_w).await;
+            }
+            SocksVersion::V6 => {} // TODO socks v6
+        }
+    }
+
+    async fn reply_succeeded<W>(&self, clt_w: &mut W, addr: SocketAddr) -> ServerTaskResult<()>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        match self.socks_version {
+            SocksVersion::V4a => v4a::SocksV4Reply::RequestGranted(addr)
+                .send(clt_w)
+                .await
+                .map_err(ServerTaskError::ClientTcpWriteFailed),
+            SocksVersion::V5 => v5::Socks5Reply::Succeeded(addr)
+                .send(clt_w)
+                .await
+                .map_err(ServerTaskError::ClientTcpWriteFailed),
+            SocksVersion::V6 => Err(ServerTaskError::UnimplementedProtocol),
+        }
+    }
+
+    async fn handle_server_upstream_acl_action<W>(
+        &self,
+        action: AclAction,
+        clt_w: &mut W,
+    ) -> ServerTaskResult<()>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        let forbid = match action {
+            AclAction::Permit => false,
+            AclAction::PermitAndLog => {
+                // TODO log permit
+                false
+            }
+            AclAction::Forbid => true,
+            AclAction::ForbidAndLog => {
+                // TODO log forbid
+                true
+            }
+        };
+        if forbid {
+            self.ctx.server_stats.forbidden.add_dest_denied();
+            if let Some(user_ctx) = self.task_notes.user_ctx() {
+                // also add to user level forbidden stats
+                user_ctx.add_dest_denied();
+            }
+
+            self.reply_forbidden(clt_w).await;
+            Err(ServerTaskError::ForbiddenByRule(
+                ServerTaskForbiddenError::DestDenied,
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    async fn handle_user_acl_action<W>(
+        &self,
+        action: AclAction,
+        clt_w: &mut W,
+        forbidden_error: ServerTaskForbiddenError,
+    ) -> ServerTaskResult<()>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        let forbid = match action {
+            AclAction::Permit => false,
+            AclAction::PermitAndLog => {
+                // TODO log permit
+                false
+            }
+            AclAction::Forbid => true,
+            AclAction::ForbidAndLog => {
+                // TODO log forbid
+                true
+            }
+        };
+        if forbid {
+            self.reply_forbidden(clt_w).await;
+            Err(ServerTaskError::ForbiddenByRule(forbidden_error))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// check the acl rules for both the expected peer address and the real one
+    async fn check_peer<W>(&self, peer: &UpstreamAddr, clt_w: &mut W) -> ServerTaskResult<()>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        if let Some(user_ctx) = self.task_notes.user_ctx() {
+            let action = user_ctx.check_upstream(peer);
+            self.handle_user_acl_action(action, clt_w, ServerTaskForbiddenError::DestDenied)
+                .await?;
+        }
+
+        // server level dst host/port acl rules
+        let action = self.ctx.check_upstream(peer);
+        self.handle_server_upstream_acl_action(action, clt_w).await
+    }
+
+    async fn run<R, W>(
+        &mut self,
+        clt_r: LimitedReader<R>,
+        mut clt_w: LimitedWriter<W>,
+    ) -> ServerTaskResult<()>
+    where
+        R: AsyncRead + Send + Sync + Unpin + 'static,
+        W: AsyncWrite + Send + Sync + Unpin + 'static,
+    {
+        let mut tcp_client_misc_opts = self.ctx.server_config.tcp_misc_opts;
+
+        if let Some(user_ctx) = self.task_notes.user_ctx() {
+            let user_ctx = user_ctx.clone();
+
+            if user_ctx.check_rate_limit().is_err() {
+                self.reply_forbidden(&mut clt_w).await;
+                return Err(ServerTaskError::ForbiddenByRule(
+                    ServerTaskForbiddenError::RateLimited,
+                ));
+            }
+
+            match user_ctx.acquire_request_semaphore() {
+                Ok(permit) => self.task_notes.user_req_alive_permit = Some(permit),
+                Err(_) => {
+                    self.reply_forbidden(&mut clt_w).await;
+                    return Err(ServerTaskError::ForbiddenByRule(
+                        ServerTaskForbiddenError::FullyLoaded,
+                    ));
+                }
+            }
+
+            let action = user_ctx.check_proxy_request(ProxyRequestType::SocksTcpBind);
+            self.handle_user_acl_action(action, &mut clt_w, ServerTaskForbiddenError::ProtoBanned)
+                .await?;
+
+            tcp_client_misc_opts = user_ctx
+                .user_config()
+                .tcp_client_misc_opts(&tcp_client_misc_opts);
+        }
+
+        self.check_peer(&self.upstream, &mut clt_w).await?;
+
+        // set client side socket options
+        self.ctx
+            .cc_info
+            .tcp_sock_set_raw_opts(&tcp_client_misc_opts, true)
+            .map_err(|_| {
+                ServerTaskError::InternalServerError("failed to set client socket options")
+            })?;
+
+        self.task_notes.stage = ServerTaskStage::Connecting;
+
+        let task_conf = TcpBindTaskConf {
+            peer: &self.upstream,
+        };
+        match self
+            .ctx
+            .escaper
+            .tcp_setup_bind(
+                &task_conf,
+                &mut self.tcp_notes,
+                &self.task_notes,
+                self.task_stats.clone(),
+            )
+            .await
+        {
+            Ok(listener) => self.run_listening(clt_r, clt_w, listener).await,
+            Err(e) => {
+                match self.socks_version {
+                    SocksVersion::V4a => {
+                        let _ = v4a::SocksV4Reply::RequestRejectedOrFailed
+                            .send(&mut clt_w)
+                            .await;
+                    }
+                    SocksVersion::V5 => {
+                        let _ = v5::Socks5Reply::from(&e).send(&mut clt_w).await;
+                    }
+                    SocksVersion::V6 => {} // TODO socks v6
+                }
+                Err(e.into())
+            }
+        }
+    }
+
+    async fn run_listening<CR, CW>(
+        &mut self,
+        clt_r: LimitedReader<CR>,
+        mut clt_w: LimitedWriter<CW>,
+        mut listener: BoxTcpBindListener,
+    ) -> ServerTaskResult<()>
+    where
+        CR: AsyncRead + Send + Sync + Unpin + 'static,
+        CW: AsyncWrite + Send + Sync + Unpin + 'static,
+    {
+        let mut listen_addr = listener.listen_addr();
+        if listen_addr.ip().is_unspecified() {
+            listen_addr.set_ip(self.ctx.server_ip());
+        }
+        self.reply_succeeded(&mut clt_w, listen_addr).await?;
+
+        let accept_timeout = self.ctx.server_config.timeout.tcp_bind_accept;
+        let (ups_r, ups_w) = match tokio::time::timeout(
+            accept_timeout,
+            listener.accept(&mut self.tcp_notes, &self.task_notes),
+        )
+        .await
+        {
+            Ok(Ok(c)) => c,
+            Ok(Err(e)) => {
+                match self.socks_version {
+                    SocksVersion::V4a => {
+                        let _ = v4a::SocksV4Reply::RequestRejectedOrFailed
+                            .send(&mut clt_w)
+                            .await;
+                    }
+                    SocksVersion::V5 => {
+                        let _ = v5::Socks5Reply::from(&e).send(&mut clt_w).await;
+                    }
+                    SocksVersion::V6 => {} // TODO socks v6
+                }
+                return Err(e.into());
+            }
+            Err(_) => {
+                match self.socks_version {
+                    SocksVersion::V4a => {
+                        let _ = v4a::SocksV4Reply::RequestRejectedOrFailed
+                            .send(&mut clt_w)
+                            .await;
+                    }
+                    SocksVersion::V5 => {
+                        let _ = v5::Socks5Reply::ConnectionTimedOut.send(&mut clt_w).await;
+                    }
+                    SocksVersion::V6 => {} // TODO socks v6
+                }
+                return Err(ServerTaskError::UpstreamNotConnected(
+                    ConnectError::TimedOut,
+                ));
+            }
+        };
+        drop(listener);
+        self.task_notes.stage = ServerTaskStage::Connected;
+
+        let Some(peer_addr) = self.tcp_notes.next else {
+            return Err(ServerTaskError::InternalServerError(
+                "no peer address set after accept",
+            ));
+        };
+        if let Host::Ip(ip) = self.upstream.host() {
+            if !ip.is_unspecified() && !ip_eq(*ip, peer_addr.ip()) {
+                self.ctx.server_stats.forbidden.add_dest_denied();
+                if let Some(user_ctx) = self.task_notes.user_ctx() {
+                    user_ctx.add_dest_denied();
+                }
+                self.reply_forbidden(&mut clt_w).await;
+                return Err(ServerTaskError::ForbiddenByRule(
+                    ServerTaskForbiddenError::DestDenied,
+                ));
+            }
+        }
+        self.check_peer(&UpstreamAddr::from(peer_addr), &mut clt_w)
+            .await?;
+
+        self.task_notes.stage = ServerTaskStage::Replying;
+        self.reply_succeeded(&mut clt_w, peer_addr).await?;
+
+        self.task_notes.mark_relaying();
+        if let Some(user_ctx) = self.task_notes.user_ctx() {
+            user_ctx.foreach_req_stats(|s| s.req_ready.add_socks_tcp_bind());
+        }
+        let alive_task = task_registry::register(
+            self.ctx.server_config.name(),
+            "Socks/TcpBind",
+            &self.task_notes,
+            &self.upstream,
+            &self.tcp_notes.escaper,
+            &self.task_stats,
+        );
+        tokio::select! {
+            r = self.relay(clt_r, clt_w, ups_r, ups_w) => r,
+            _ = alive_task.killed() => Err(ServerTaskError::CanceledAsTaskKilled),
+        }
+    }
+
+    async fn relay<CR, CW, UR, UW>(
+        &mut self,
+        mut clt_r: LimitedReader<CR>,
+        mut clt_w: LimitedWriter<CW>,
+        ups_r: UR,
+        ups_w: UW,
+    ) -> ServerTaskResult<()>
+    where
+        CR: AsyncRead + Send + Sync + Unpin + 'static,
+        CW: AsyncWrite + Send + Sync + Unpin + 'static,
+        UR: AsyncRead + Send + Sync + Unpin + 'static,
+        UW: AsyncWrite + Send + Sync + Unpin + 'static,
+    {
+        self.update_clt(&mut clt_r, &mut clt_w);
+
+        if let Some(audit_handle) = self.audit_ctx.handle() {
+            let audit_task = self
+                .task_notes
+                .user_ctx()
+                .map(|ctx| {
+                    let user_config = &ctx.user_config().audit;
+                    user_config.enable_protocol_inspection
+                        && user_config
+                            .do_task_audit()
+                            .unwrap_or_else(|| audit_handle.do_task_audit())
+                })
+                .unwrap_or_else(|| audit_handle.do_task_audit());
+
+            if audit_task {
+                let ctx = StreamInspectContext::new(
+                    audit_handle.clone(),
+                    self.ctx.server_config.clone(),
+                    self.ctx.server_stats.clone(),
+                    self.ctx.server_quit_policy.clone(),
+                    &self.task_notes,
+                );
+                return crate::inspect::stream::transit_with_inspection(
+                    clt_r,
+                    clt_w,
+                    ups_r,
+                    ups_w,
+                    ctx,
+                    self.upstream.clone(),
+                    None,
+                )
+                .await;
+            }
+        }
+
+        crate::inspect::stream::transit_transparent(
+            clt_r,
+            clt_w,
+            ups_r,
+            ups_w,
+            &self.ctx.server_config,
+            &self.ctx.server_quit_policy,
+            self.task_notes.user_ctx().map(|ctx| ctx.user()),
+        )
+        .await
+    }
+
+    fn update_clt<CR, CW>(&mut self, clt_r: &mut LimitedReader<CR>, clt_w: &mut LimitedWriter<CW>)
+    where
+        CR: AsyncRead + Unpin,
+        CW: AsyncWrite + Unpin,
+    {
+        let mut wrapper_stats =
+            TcpBindTaskCltWrapperStats::new(&self.ctx.server_stats, &self.task_stats);
+
+        if let Some(user_ctx) = self.task_notes.user_ctx() {
+            wrapper_stats.push_user_io_stats(user_ctx.fetch_traffic_stats(
+                self.ctx.server_config.name(),
+                self.ctx.server_stats.share_extra_tags(),
+            ));
+
+            let user_config = user_ctx.user_config();
+            if !user_config
+                .tcp_sock_speed_limit
+                .eq(&self.ctx.server_config.tcp_sock_speed_limit)
+            {
+                let limit_config = user_config
+                    .tcp_sock_speed_limit
+                    .shrink_as_smaller(&self.ctx.server_config.tcp_sock_speed_limit);
+                clt_r.reset_local_limit(limit_config.shift_millis, limit_config.max_north);
+                clt_w.reset_local_limit(limit_config.shift_millis, limit_config.max_south);
+            }
+
+            let user = user_ctx.user();
+            if let Some(limiter) = user.tcp_all_upload_speed_limit() {
+                clt_r.add_global_limiter(limiter.clone());
+            }
+            if let Some(limiter) = user.tcp_all_download_speed_limit() {
+                clt_w.add_global_limiter(limiter.clone());
+            }
+        }
+        let wrapper_stats = Arc::new(wrapper_stats);
+        clt_r.reset_stats(wrapper_stats.clone());
+        clt_w.reset_stats(wrapper_stats);
+    }
+}
+
+fn ip_eq(expected: IpAddr, peer: IpAddr) -> bool {
+    match (expected, peer) {
+        (IpAddr::V4(e), IpAddr::V6(p)) => p.to_ipv4_mapped().map(|p| p == e).unwrap_or(false),
+        (e, p) => e == p,
+    }
+}
@@ -32,6 +32,7 @@ use crate::config::server::ServerConfig;
 use crate::inspect::StreamInspectContext;
 use crate::log::task::tcp_connect::TaskLogForTcpConnect;
 use crate::module::tcp_connect::{TcpConnectTaskConf, TcpConnectTaskNotes};
+use crate::serve::task_registry;
 use crate::serve::{
     ServerStats, ServerTaskError, ServerTaskForbiddenError, ServerTaskNotes, ServerTaskResult,
     ServerTaskStage,
@@ -358,7 +359,18 @@ impl SocksProxyTcpConnectTask {
         if let Some(user_ctx) = self.task_notes.user_ctx() {
             user_ctx.foreach_req_stats(|s| s.req_ready.add_socks_tcp_connect());
         }
-        self.relay(clt_r, clt_w, ups_r, ups_w).await
+        let alive_task = task_registry::register(
+            self.ctx.server_config.name(),
+            "Socks/TcpConnect",
+            &self.task_notes,
+            &self.upstream,
+            &self.tcp_notes.escaper,
+            &self.task_stats,
+        );
+        tokio::select! {
+            r = self.relay(clt_r, clt_w, ups_r, ups_w) => r,
+            _ = alive_task.killed() => Err(ServerTaskError::CanceledAsTaskKilled),
+        }
     }
 
     async fn relay<CR, CW, UR, UW>(
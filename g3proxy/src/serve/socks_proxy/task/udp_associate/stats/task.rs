@@ -19,6 +19,7 @@ use std::sync::atomic::{AtomicU64, Ordering};
 use g3_daemon::stat::task::UdpConnectHalfConnectionStats;
 
 use crate::module::udp_relay::UdpRelayTaskRemoteStats;
+use crate::serve::task_registry::AliveTaskStats;
 
 #[derive(Default)]
 pub(crate) struct UdpAssociateClientSideStats {
@@ -79,3 +80,21 @@ impl UdpRelayTaskRemoteStats for UdpAssociateTaskStats {
         self.ups.send.add_packets(n);
     }
 }
+
+impl AliveTaskStats for UdpAssociateTaskStats {
+    fn clt_read_bytes(&self) -> u64 {
+        self.clt.recv.get_bytes()
+    }
+
+    fn clt_write_bytes(&self) -> u64 {
+        self.clt.send.get_bytes()
+    }
+
+    fn ups_read_bytes(&self) -> u64 {
+        self.ups.recv.get_bytes()
+    }
+
+    fn ups_write_bytes(&self) -> u64 {
+        self.ups.send.get_bytes()
+    }
+}
@@ -17,6 +17,7 @@
 use g3_daemon::stat::task::UdpConnectConnectionStats;
 
 use crate::module::udp_connect::UdpConnectTaskRemoteStats;
+use crate::serve::task_registry::AliveTaskStats;
 
 #[derive(Default)]
 pub(crate) struct UdpConnectTaskStats {
@@ -41,3 +42,21 @@ impl UdpConnectTaskRemoteStats for UdpConnectTaskStats {
         self.ups.send.add_packets(n);
     }
 }
+
+impl AliveTaskStats for UdpConnectTaskStats {
+    fn clt_read_bytes(&self) -> u64 {
+        self.clt.recv.get_bytes()
+    }
+
+    fn clt_write_bytes(&self) -> u64 {
+        self.clt.send.get_bytes()
+    }
+
+    fn ups_read_bytes(&self) -> u64 {
+        self.ups.recv.get_bytes()
+    }
+
+    fn ups_write_bytes(&self) -> u64 {
+        self.ups.send.get_bytes()
+    }
+}
@@ -41,6 +41,7 @@ use crate::config::server::ServerConfig;
 use crate::log::escape::udp_sendto::EscapeLogForUdpConnectSendTo;
 use crate::log::task::udp_connect::TaskLogForUdpConnect;
 use crate::module::udp_connect::{UdpConnectTaskConf, UdpConnectTaskNotes};
+use crate::serve::task_registry;
 use crate::serve::{
     ServerStats, ServerTaskError, ServerTaskForbiddenError, ServerTaskNotes, ServerTaskResult,
     ServerTaskStage,
@@ -296,15 +297,26 @@ impl SocksProxyUdpConnectTask {
         if let Some(user_ctx) = self.task_notes.user_ctx() {
             user_ctx.foreach_req_stats(|s| s.req_ready.add_socks_udp_connect());
         }
-        self.run_relay(
-            clt_tcp_r,
-            Box::new(clt_r),
-            Box::new(clt_w),
-            ups_r,
-            ups_w,
-            &escape_logger,
-        )
-        .await
+        let upstream = self.upstream.clone().unwrap_or_else(UpstreamAddr::empty);
+        let alive_task = task_registry::register(
+            self.ctx.server_config.name(),
+            "SocksProxy/UdpConnect",
+            &self.task_notes,
+            &upstream,
+            &self.udp_notes.escaper,
+            &self.task_stats,
+        );
+        tokio::select! {
+            r = self.run_relay(
+                clt_tcp_r,
+                Box::new(clt_r),
+                Box::new(clt_w),
+                ups_r,
+                ups_w,
+                &escape_logger,
+            ) => r,
+            _ = alive_task.killed() => Err(ServerTaskError::CanceledAsTaskKilled),
+        }
     }
 
     async fn run_relay<'a, R>(
@@ -22,6 +22,7 @@ use arc_swap::ArcSwapOption;
 use g3_types::metrics::{MetricsName, StaticMetricsTags};
 use g3_types::stats::{StatId, TcpIoSnapshot, UdpIoSnapshot};
 
+use crate::module::http_forward::HttpForwardCacheSnapshot;
 use crate::stat::types::UntrustedTaskStatsSnapshot;
 
 pub(crate) trait ServerStats {
@@ -51,6 +52,10 @@ pub(crate) trait ServerStats {
     fn untrusted_snapshot(&self) -> Option<UntrustedTaskStatsSnapshot> {
         None
     }
+
+    fn http_forward_cache_snapshot(&self) -> Option<HttpForwardCacheSnapshot> {
+        None
+    }
 }
 
 pub(crate) type ArcServerStats = Arc<dyn ServerStats + Send + Sync>;
@@ -60,6 +65,7 @@ pub(crate) struct ServerForbiddenSnapshot {
     pub(crate) auth_failed: u64,
     pub(crate) dest_denied: u64,
     pub(crate) user_blocked: u64,
+    pub(crate) src_geo_blocked: u64,
 }
 
 #[derive(Default)]
@@ -67,6 +73,7 @@ pub(crate) struct ServerForbiddenStats {
     auth_failed: AtomicU64,
     dest_denied: AtomicU64,
     user_blocked: AtomicU64,
+    src_geo_blocked: AtomicU64,
 }
 
 impl ServerForbiddenStats {
@@ -82,11 +89,16 @@ impl ServerForbiddenStats {
         self.user_blocked.fetch_add(1, Ordering::Relaxed);
     }
 
+    pub(crate) fn add_src_geo_blocked(&self) {
+        self.src_geo_blocked.fetch_add(1, Ordering::Relaxed);
+    }
+
     pub(crate) fn snapshot(&self) -> ServerForbiddenSnapshot {
         ServerForbiddenSnapshot {
             auth_failed: self.auth_failed.load(Ordering::Relaxed),
             dest_denied: self.dest_denied.load(Ordering::Relaxed),
             user_blocked: self.user_blocked.load(Ordering::Relaxed),
+            src_geo_blocked: self.src_geo_blocked.load(Ordering::Relaxed),
         }
     }
 }
@@ -0,0 +1,268 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use tokio::sync::Notify;
+use tokio::time::Instant;
+use uuid::Uuid;
+
+use g3_daemon::stat::task::TcpStreamTaskStats;
+use g3_types::metrics::MetricsName;
+use g3_types::net::UpstreamAddr;
+
+use super::ServerTaskNotes;
+
+const REGISTRY_SHARD_COUNT: usize = 16;
+
+type AliveTaskShard = Mutex<HashMap<Uuid, Arc<AliveTask>>>;
+
+/// the registry is split into shards to reduce lock contention between worker threads
+static ALIVE_TASK_REGISTRY: LazyLock<[AliveTaskShard; REGISTRY_SHARD_COUNT]> =
+    LazyLock::new(|| std::array::from_fn(|_| Mutex::new(HashMap::new())));
+
+fn registry_shard(id: &Uuid) -> &'static AliveTaskShard {
+    // the task id is a v1 uuid, use the time low and clock sequence fields
+    let (hi, lo) = id.as_u64_pair();
+    let v = (hi >> 32) ^ (lo >> 48);
+    &ALIVE_TASK_REGISTRY[(v as usize) % REGISTRY_SHARD_COUNT]
+}
+
+/// the traffic stats of an alive task, the write bytes are counted on the proxy side
+pub(crate) trait AliveTaskStats: Send + Sync {
+    fn clt_read_bytes(&self) -> u64;
+    fn clt_write_bytes(&self) -> u64;
+    fn ups_read_bytes(&self) -> u64;
+    fn ups_write_bytes(&self) -> u64;
+}
+
+impl AliveTaskStats for TcpStreamTaskStats {
+    fn clt_read_bytes(&self) -> u64 {
+        self.clt.read.get_bytes()
+    }
+
+    fn clt_write_bytes(&self) -> u64 {
+        self.clt.write.get_bytes()
+    }
+
+    fn ups_read_bytes(&self) -> u64 {
+        self.ups.read.get_bytes()
+    }
+
+    fn ups_write_bytes(&self) -> u64 {
+        self.ups.write.get_bytes()
+    }
+}
+
+/// a relaying task which can be listed and killed through the control interface
+pub(crate) struct AliveTask {
+    pub(crate) id: Uuid,
+    pub(crate) server: MetricsName,
+    pub(crate) task_type: &'static str,
+    pub(crate) client_addr: SocketAddr,
+    pub(crate) server_addr: SocketAddr,
+    pub(crate) user_group: Option<MetricsName>,
+    pub(crate) user: Option<Arc<str>>,
+    pub(crate) upstream: UpstreamAddr,
+    pub(crate) escaper: MetricsName,
+    pub(crate) start_at: DateTime<Utc>,
+    create_ins: Instant,
+    pub(crate) stats: Arc<dyn AliveTaskStats>,
+    kill_notify: Notify,
+}
+
+impl AliveTask {
+    #[inline]
+    pub(crate) fn alive_time(&self) -> Duration {
+        self.create_ins.elapsed()
+    }
+
+    fn belongs_to(&self, user_group: &str, user: &str) -> bool {
+        self.user.as_deref() == Some(user)
+            && self.user_group.as_ref().map(|g| g.as_str()) == Some(user_group)
+    }
+
+    fn kill(&self) {
+        // the permit will be stored if the task is not waiting at this time
+        self.kill_notify.notify_one();
+    }
+}
+
+/// the task will be removed from the registry when this guard is dropped
+pub(crate) struct AliveTaskGuard {
+    task: Arc<AliveTask>,
+}
+
+impl AliveTaskGuard {
+    /// wait until the task is killed through the control interface
+    pub(crate) async fn killed(&self) {
+        self.task.kill_notify.notified().await
+    }
+}
+
+impl Drop for AliveTaskGuard {
+    fn drop(&mut self) {
+        let mut ht = registry_shard(&self.task.id).lock().unwrap();
+        ht.remove(&self.task.id);
+    }
+}
+
+pub(crate) fn register<S>(
+    server: &MetricsName,
+    task_type: &'static str,
+    task_notes: &ServerTaskNotes,
+    upstream: &UpstreamAddr,
+    escaper: &MetricsName,
+    stats: &Arc<S>,
+) -> AliveTaskGuard
+where
+    S: AliveTaskStats + 'static,
+{
+    let user_ctx = task_notes.user_ctx();
+    let task = Arc::new(AliveTask {
+        id: task_notes.id,
+        server: server.clone(),
+        task_type,
+        client_addr: task_notes.client_addr(),
+        server_addr: task_notes.server_addr(),
+        user_group: user_ctx.map(|ctx| ctx.user().group().clone()),
+        user: user_ctx.map(|ctx| ctx.user_name().clone()),
+        upstream: upstream.clone(),
+        escaper: escaper.clone(),
+        start_at: task_notes.start_at,
+        create_ins: task_notes.task_created_instant(),
+        stats: Arc::clone(stats) as Arc<dyn AliveTaskStats>,
+        kill_notify: Notify::new(),
+    });
+    insert(task.clone());
+    AliveTaskGuard { task }
+}
+
+fn insert(task: Arc<AliveTask>) {
+    let mut ht = registry_shard(&task.id).lock().unwrap();
+    ht.insert(task.id, task);
+}
+
+pub(crate) fn foreach<F>(mut f: F)
+where
+    F: FnMut(&Arc<AliveTask>),
+{
+    for shard in ALIVE_TASK_REGISTRY.iter() {
+        let all: Vec<Arc<AliveTask>> = {
+            let ht = shard.lock().unwrap();
+            ht.values().cloned().collect()
+        };
+        for task in &all {
+            f(task);
+        }
+    }
+}
+
+pub(crate) fn kill(id: &Uuid) -> bool {
+    let ht = registry_shard(id).lock().unwrap();
+    if let Some(task) = ht.get(id) {
+        task.kill();
+        true
+    } else {
+        false
+    }
+}
+
+pub(crate) fn kill_user(user_group: &str, user: &str) -> usize {
+    let mut count = 0;
+    for shard in ALIVE_TASK_REGISTRY.iter() {
+        let ht = shard.lock().unwrap();
+        for task in ht.values() {
+            if task.belongs_to(user_group, user) {
+                task.kill();
+                count += 1;
+            }
+        }
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::FutureExt;
+    use std::str::FromStr;
+
+    fn new_alive_task(user_group: &str, user: &str) -> AliveTaskGuard {
+        let task = Arc::new(AliveTask {
+            id: Uuid::new_v4(),
+            server: MetricsName::from_str("test").unwrap(),
+            task_type: "Test",
+            client_addr: SocketAddr::from(([127, 0, 0, 1], 10000)),
+            server_addr: SocketAddr::from(([127, 0, 0, 1], 1080)),
+            user_group: Some(MetricsName::from_str(user_group).unwrap()),
+            user: Some(Arc::from(user)),
+            upstream: UpstreamAddr::empty(),
+            escaper: MetricsName::from_str("default").unwrap(),
+            start_at: Utc::now(),
+            create_ins: Instant::now(),
+            stats: Arc::new(TcpStreamTaskStats::default()),
+            kill_notify: Notify::new(),
+        });
+        insert(task.clone());
+        AliveTaskGuard { task }
+    }
+
+    fn is_registered(id: &Uuid) -> bool {
+        let mut found = false;
+        foreach(|task| {
+            if task.id == *id {
+                found = true;
+            }
+        });
+        found
+    }
+
+    #[test]
+    fn kill_by_id() {
+        let guard = new_alive_task("registry-g1", "registry-u1");
+        let id = guard.task.id;
+        assert!(is_registered(&id));
+        assert!(guard.killed().now_or_never().is_none());
+
+        assert!(kill(&id));
+        assert!(guard.killed().now_or_never().is_some());
+
+        drop(guard);
+        assert!(!is_registered(&id));
+        assert!(!kill(&id));
+    }
+
+    #[test]
+    fn kill_by_user() {
+        let t1 = new_alive_task("registry-g2", "registry-u2");
+        let t2 = new_alive_task("registry-g2", "registry-u2");
+        let t3 = new_alive_task("registry-g3", "registry-u2");
+        let t4 = new_alive_task("registry-g2", "registry-u3");
+
+        assert_eq!(kill_user("registry-g2", "registry-u2"), 2);
+        assert!(t1.killed().now_or_never().is_some());
+        assert!(t2.killed().now_or_never().is_some());
+        assert!(t3.killed().now_or_never().is_none());
+        assert!(t4.killed().now_or_never().is_none());
+
+        assert_eq!(kill_user("registry-g4", "registry-u2"), 0);
+    }
+}
@@ -0,0 +1,413 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use log::{info, warn};
+use tokio::io::Interest;
+use tokio::net::UdpSocket;
+use tokio::runtime::Handle;
+use tokio::sync::{broadcast, mpsc};
+
+use g3_daemon::listen::ListenStats;
+use g3_daemon::server::{ClientConnectionInfo, ServerReloadCommand};
+use g3_socket::util::native_socket_addr;
+use g3_types::net::UdpListenConfig;
+
+use crate::serve::{get_or_insert_default, ArcServer};
+
+const FLOW_CLEAN_INTERVAL: Duration = Duration::from_secs(30);
+const FLOW_FULL_CLEAN_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The client side packets of a single transparent udp flow,
+/// which is identified by the client address and the original destination address.
+pub(crate) struct UdpTProxyFlow {
+    packets: mpsc::Receiver<Vec<u8>>,
+}
+
+impl UdpTProxyFlow {
+    pub(super) fn into_receiver(self) -> mpsc::Receiver<Vec<u8>> {
+        self.packets
+    }
+}
+
+enum FlowDispatch {
+    Queued,
+    /// the flow task is too slow
+    QueueFull,
+    /// max flows reached
+    Refused,
+    New(UdpTProxyFlow),
+}
+
+/// The active flows in a single runtime instance
+struct UdpTProxyFlowTable {
+    flows: HashMap<(SocketAddr, SocketAddr), mpsc::Sender<Vec<u8>>>,
+    queue_size: usize,
+    max_flows: usize,
+    last_full_clean: Option<Instant>,
+}
+
+impl UdpTProxyFlowTable {
+    fn new(queue_size: usize, max_flows: usize) -> Self {
+        UdpTProxyFlowTable {
+            flows: HashMap::new(),
+            queue_size,
+            max_flows,
+            last_full_clean: None,
+        }
+    }
+
+    fn clean(&mut self) {
+        self.flows.retain(|_, sender| !sender.is_closed());
+    }
+
+    fn is_full(&mut self) -> bool {
+        if self.max_flows == 0 || self.flows.len() < self.max_flows {
+            return false;
+        }
+        // clean closed flows, but not too often as this will be triggered by each new packet
+        let now = Instant::now();
+        if self
+            .last_full_clean
+            .map(|t| now.duration_since(t) >= FLOW_FULL_CLEAN_INTERVAL)
+            .unwrap_or(true)
+        {
+            self.last_full_clean = Some(now);
+            self.clean();
+        }
+        self.flows.len() >= self.max_flows
+    }
+
+    fn dispatch(&mut self, key: (SocketAddr, SocketAddr), packet: Vec<u8>) -> FlowDispatch {
+        let packet = match self.flows.get(&key) {
+            Some(sender) => match sender.try_send(packet) {
+                Ok(_) => return FlowDispatch::Queued,
+                Err(mpsc::error::TrySendError::Full(_)) => return FlowDispatch::QueueFull,
+                Err(mpsc::error::TrySendError::Closed(packet)) => {
+                    self.flows.remove(&key);
+                    packet
+                }
+            },
+            None => packet,
+        };
+
+        if self.is_full() {
+            return FlowDispatch::Refused;
+        }
+
+        let (sender, receiver) = mpsc::channel(self.queue_size);
+        let _ = sender.try_send(packet);
+        self.flows.insert(key, sender);
+        FlowDispatch::New(UdpTProxyFlow { packets: receiver })
+    }
+}
+
+#[derive(Clone)]
+pub(super) struct UdpTProxyRuntime {
+    server: ArcServer,
+    server_type: &'static str,
+    server_version: usize,
+    worker_id: Option<usize>,
+    listen_stats: Arc<ListenStats>,
+    instance_id: usize,
+    flow_queue_size: usize,
+    max_flows: usize,
+}
+
+impl UdpTProxyRuntime {
+    pub(super) fn new(server: &ArcServer, flow_queue_size: usize, max_flows: usize) -> Self {
+        UdpTProxyRuntime {
+            server: server.clone(),
+            server_type: server.server_type(),
+            server_version: server.version(),
+            worker_id: None,
+            listen_stats: server.get_listen_stats(),
+            instance_id: 0,
+            flow_queue_size,
+            max_flows,
+        }
+    }
+
+    fn pre_start(&self) {
+        info!(
+            "started {} SRT[{}_v{}#{}]",
+            self.server_type,
+            self.server.name(),
+            self.server_version,
+            self.instance_id,
+        );
+        self.listen_stats.add_running_runtime();
+    }
+
+    fn post_stop(&self) {
+        info!(
+            "stopped {} SRT[{}_v{}#{}]",
+            self.server_type,
+            self.server.name(),
+            self.server_version,
+            self.instance_id,
+        );
+        self.listen_stats.del_running_runtime();
+    }
+
+    async fn run(
+        mut self,
+        socket: UdpSocket,
+        mut server_reload_channel: broadcast::Receiver<ServerReloadCommand>,
+    ) {
+        use broadcast::error::RecvError;
+
+        let mut flows = UdpTProxyFlowTable::new(self.flow_queue_size, self.max_flows);
+        let mut clean_interval = tokio::time::interval(FLOW_CLEAN_INTERVAL);
+        let mut buf = vec![0u8; u16::MAX as usize];
+
+        loop {
+            tokio::select! {
+                biased;
+
+                ev = server_reload_channel.recv() => {
+                    match ev {
+                        Ok(ServerReloadCommand::ReloadVersion(version)) => {
+                            info!("SRT[{}_v{}#{}] received reload request from v{version}",
+                                self.server.name(), self.server_version, self.instance_id);
+                            let new_server = get_or_insert_default(self.server.name());
+                            self.server_version = new_server.version();
+                            self.server = new_server;
+                            continue;
+                        }
+                        Ok(ServerReloadCommand::QuitRuntime) => {},
+                        Err(RecvError::Closed) => {},
+                        Err(RecvError::Lagged(dropped)) => {
+                            warn!("SRT[{}_v{}#{}] server {} reload notify channel overflowed, {dropped} msg dropped",
+                                self.server.name(), self.server_version, self.instance_id, self.server.name());
+                            continue;
+                        },
+                    }
+
+                    info!("SRT[{}_v{}#{}] will go offline",
+                        self.server.name(), self.server_version, self.instance_id);
+                    break;
+                }
+                _ = clean_interval.tick() => {
+                    flows.clean();
+                }
+                r = socket.async_io(Interest::READABLE, || {
+                    g3_socket::udp::recv_with_orig_dst(&socket, &mut buf)
+                        .map(|(nr, peer_addr, orig_dst)| (buf[..nr].to_vec(), peer_addr, orig_dst))
+                }) => {
+                    match r {
+                        Ok((packet, peer_addr, Some(orig_dst))) => {
+                            self.handle_packet(
+                                &mut flows,
+                                packet,
+                                native_socket_addr(peer_addr),
+                                native_socket_addr(orig_dst),
+                            );
+                        }
+                        Ok((_, peer_addr, None)) => {
+                            self.listen_stats.add_dropped();
+                            warn!("SRT[{}_v{}#{}] no original destination address found for packet from {peer_addr}",
+                                self.server.name(), self.server_version, self.instance_id);
+                        }
+                        Err(e) => {
+                            self.listen_stats.add_failed();
+                            warn!("SRT[{}_v{}#{}] recv: {e:?}",
+                                self.server.name(), self.server_version, self.instance_id);
+                        }
+                    }
+                }
+            }
+        }
+        self.post_stop();
+    }
+
+    fn handle_packet(
+        &self,
+        flows: &mut UdpTProxyFlowTable,
+        packet: Vec<u8>,
+        peer_addr: SocketAddr,
+        orig_dst: SocketAddr,
+    ) {
+        match flows.dispatch((peer_addr, orig_dst), packet) {
+            FlowDispatch::Queued | FlowDispatch::QueueFull => {}
+            FlowDispatch::Refused => self.listen_stats.add_dropped(),
+            FlowDispatch::New(flow) => {
+                self.listen_stats.add_accepted();
+                self.run_task(flow, peer_addr, orig_dst);
+            }
+        }
+    }
+
+    fn run_task(&self, flow: UdpTProxyFlow, peer_addr: SocketAddr, orig_dst: SocketAddr) {
+        let server = self.server.clone();
+
+        let mut cc_info = ClientConnectionInfo::new(peer_addr, orig_dst);
+        if let Some(worker_id) = self.worker_id {
+            cc_info.set_worker_id(Some(worker_id));
+            tokio::spawn(async move {
+                server.run_udp_tproxy_task(flow, cc_info).await;
+            });
+        } else if let Some(rt) = g3_daemon::runtime::worker::select_handle() {
+            cc_info.set_worker_id(Some(rt.id));
+            rt.handle.spawn(async move {
+                server.run_udp_tproxy_task(flow, cc_info).await;
+            });
+        } else {
+            tokio::spawn(async move {
+                server.run_udp_tproxy_task(flow, cc_info).await;
+            });
+        }
+    }
+
+    fn get_rt_handle(&mut self, listen_in_worker: bool) -> Handle {
+        if listen_in_worker {
+            if let Some(rt) = g3_daemon::runtime::worker::select_listen_handle() {
+                self.worker_id = Some(rt.id);
+                return rt.handle;
+            }
+        }
+        Handle::current()
+    }
+
+    fn into_running(
+        mut self,
+        socket: std::net::UdpSocket,
+        listen_in_worker: bool,
+        server_reload_channel: broadcast::Receiver<ServerReloadCommand>,
+    ) {
+        let handle = self.get_rt_handle(listen_in_worker);
+        handle.spawn(async move {
+            // make sure the listen socket associated with the correct reactor
+            match UdpSocket::from_std(socket) {
+                Ok(socket) => {
+                    self.pre_start();
+                    self.run(socket, server_reload_channel).await;
+                }
+                Err(e) => {
+                    warn!(
+                        "SRT[{}_v{}#{}] listen async: {e:?}",
+                        self.server.name(),
+                        self.server_version,
+                        self.instance_id
+                    );
+                }
+            }
+        });
+    }
+
+    pub(super) fn run_all_instances(
+        &self,
+        listen_config: &UdpListenConfig,
+        listen_in_worker: bool,
+        server_reload_sender: &broadcast::Sender<ServerReloadCommand>,
+    ) -> anyhow::Result<()> {
+        let mut instance_count = listen_config.instance();
+        if listen_in_worker {
+            let worker_count = g3_daemon::runtime::worker::worker_count();
+            if worker_count > 0 {
+                instance_count = worker_count;
+            }
+        }
+
+        for i in 0..instance_count {
+            let mut runtime = self.clone();
+            runtime.instance_id = i;
+
+            let socket = g3_socket::udp::new_std_bind_listen(listen_config)?;
+            runtime.into_running(socket, listen_in_worker, server_reload_sender.subscribe());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flow_key(port: u16) -> (SocketAddr, SocketAddr) {
+        (
+            SocketAddr::from(([192, 168, 1, 1], port)),
+            SocketAddr::from(([192, 168, 2, 1], 53)),
+        )
+    }
+
+    #[test]
+    fn dispatch() {
+        let mut table = UdpTProxyFlowTable::new(2, 0);
+
+        let FlowDispatch::New(flow) = table.dispatch(flow_key(1000), vec![1]) else {
+            panic!("new flow expected");
+        };
+        assert!(matches!(
+            table.dispatch(flow_key(1000), vec![2]),
+            FlowDispatch::Queued
+        ));
+        assert!(matches!(
+            table.dispatch(flow_key(1000), vec![3]),
+            FlowDispatch::QueueFull
+        ));
+
+        let mut receiver = flow.into_receiver();
+        assert_eq!(receiver.try_recv().unwrap(), vec![1]);
+        assert_eq!(receiver.try_recv().unwrap(), vec![2]);
+        assert!(receiver.try_recv().is_err());
+
+        // a new flow should be created if the old one is closed
+        drop(receiver);
+        let FlowDispatch::New(flow) = table.dispatch(flow_key(1000), vec![4]) else {
+            panic!("new flow expected");
+        };
+        assert_eq!(flow.into_receiver().try_recv().unwrap(), vec![4]);
+    }
+
+    #[test]
+    fn max_flows() {
+        let mut table = UdpTProxyFlowTable::new(2, 2);
+
+        let FlowDispatch::New(flow1) = table.dispatch(flow_key(1001), vec![1]) else {
+            panic!("new flow expected");
+        };
+        let FlowDispatch::New(_flow2) = table.dispatch(flow_key(1002), vec![1]) else {
+            panic!("new flow expected");
+        };
+        assert!(matches!(
+            table.dispatch(flow_key(1003), vec![1]),
+            FlowDispatch::Refused
+        ));
+        // packets to existing flows are still accepted
+        assert!(matches!(
+            table.dispatch(flow_key(1001), vec![2]),
+            FlowDispatch::Queued
+        ));
+
+        // closed flows are cleaned, but not more than once in a short interval
+        drop(flow1);
+        assert!(matches!(
+            table.dispatch(flow_key(1003), vec![1]),
+            FlowDispatch::Refused
+        ));
+        table.last_full_clean = None;
+        assert!(matches!(
+            table.dispatch(flow_key(1003), vec![1]),
+            FlowDispatch::New(_)
+        ));
+        assert_eq!(table.flows.len(), 2);
+    }
+}
@@ -0,0 +1,283 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use arc_swap::ArcSwap;
+use async_trait::async_trait;
+#[cfg(feature = "quic")]
+use quinn::Connection;
+use slog::Logger;
+use tokio::net::TcpStream;
+use tokio::sync::broadcast;
+use tokio_rustls::server::TlsStream;
+
+use g3_daemon::listen::{AcceptQuicServer, AcceptTcpServer, ListenStats};
+use g3_daemon::server::{BaseServer, ClientConnectionInfo, ServerReloadCommand};
+use g3_openssl::SslStream;
+use g3_types::acl::{AclAction, AclNetworkRule};
+use g3_types::metrics::MetricsName;
+
+use super::common::CommonTaskContext;
+use super::runtime::{UdpTProxyFlow, UdpTProxyRuntime};
+use super::stats::UdpTProxyServerStats;
+use super::task::UdpTProxyTask;
+use crate::config::server::udp_tproxy::UdpTProxyServerConfig;
+use crate::config::server::{AnyServerConfig, ServerConfig};
+use crate::escape::ArcEscaper;
+use crate::serve::{
+    ArcServer, ArcServerStats, Server, ServerInternal, ServerQuitPolicy, ServerStats,
+};
+
+pub(crate) struct UdpTProxyServer {
+    config: Arc<UdpTProxyServerConfig>,
+    server_stats: Arc<UdpTProxyServerStats>,
+    listen_stats: Arc<ListenStats>,
+    ingress_net_filter: Option<AclNetworkRule>,
+    reload_sender: broadcast::Sender<ServerReloadCommand>,
+    task_logger: Logger,
+
+    escaper: ArcSwap<ArcEscaper>,
+    quit_policy: Arc<ServerQuitPolicy>,
+    reload_version: usize,
+}
+
+impl UdpTProxyServer {
+    fn new(
+        config: Arc<UdpTProxyServerConfig>,
+        server_stats: Arc<UdpTProxyServerStats>,
+        listen_stats: Arc<ListenStats>,
+        version: usize,
+    ) -> Self {
+        let reload_sender = crate::serve::new_reload_notify_channel();
+
+        let ingress_net_filter = config
+            .ingress_net_filter
+            .as_ref()
+            .map(|builder| builder.build());
+
+        let task_logger = config.get_task_logger();
+
+        server_stats.set_extra_tags(config.extra_metrics_tags.clone());
+
+        let escaper = Arc::new(crate::escape::get_or_insert_default(config.escaper()));
+
+        UdpTProxyServer {
+            config,
+            server_stats,
+            listen_stats,
+            ingress_net_filter,
+            reload_sender,
+            task_logger,
+            escaper: ArcSwap::new(escaper),
+            quit_policy: Arc::new(ServerQuitPolicy::default()),
+            reload_version: version,
+        }
+    }
+
+    pub(crate) fn prepare_initial(config: UdpTProxyServerConfig) -> anyhow::Result<ArcServer> {
+        let config = Arc::new(config);
+        let server_stats = Arc::new(UdpTProxyServerStats::new(config.name()));
+        let listen_stats = Arc::new(ListenStats::new(config.name()));
+
+        let server = UdpTProxyServer::new(config, server_stats, listen_stats, 1);
+        Ok(Arc::new(server))
+    }
+
+    fn prepare_reload(&self, config: AnyServerConfig) -> anyhow::Result<Self> {
+        if let AnyServerConfig::UdpTProxy(config) = config {
+            let config = Arc::new(config);
+            let server_stats = Arc::clone(&self.server_stats);
+            let listen_stats = Arc::clone(&self.listen_stats);
+
+            let server =
+                UdpTProxyServer::new(config, server_stats, listen_stats, self.reload_version + 1);
+            Ok(server)
+        } else {
+            Err(anyhow!(
+                "config type mismatch: expect {}, actual {}",
+                self.config.server_type(),
+                config.server_type()
+            ))
+        }
+    }
+
+    fn drop_early(&self, client_addr: SocketAddr) -> bool {
+        if let Some(ingress_net_filter) = &self.ingress_net_filter {
+            let (_, action) = ingress_net_filter.check(client_addr.ip());
+            match action {
+                AclAction::Permit | AclAction::PermitAndLog => {}
+                AclAction::Forbid | AclAction::ForbidAndLog => {
+                    self.listen_stats.add_dropped();
+                    return true;
+                }
+            }
+        }
+
+        false
+    }
+}
+
+impl ServerInternal for UdpTProxyServer {
+    fn _clone_config(&self) -> AnyServerConfig {
+        AnyServerConfig::UdpTProxy(self.config.as_ref().clone())
+    }
+
+    fn _update_config_in_place(&self, _flags: u64, _config: AnyServerConfig) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn _depend_on_server(&self, _name: &MetricsName) -> bool {
+        false
+    }
+
+    fn _reload_config_notify_runtime(&self) {
+        let cmd = ServerReloadCommand::ReloadVersion(self.reload_version);
+        let _ = self.reload_sender.send(cmd);
+    }
+
+    fn _update_next_servers_in_place(&self) {}
+
+    fn _update_escaper_in_place(&self) {
+        let escaper = crate::escape::get_or_insert_default(self.config.escaper());
+        self.escaper.store(Arc::new(escaper));
+    }
+
+    fn _update_user_group_in_place(&self) {}
+
+    fn _update_audit_handle_in_place(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn _reload_with_old_notifier(&self, config: AnyServerConfig) -> anyhow::Result<ArcServer> {
+        let mut server = self.prepare_reload(config)?;
+        server.reload_sender = self.reload_sender.clone();
+        Ok(Arc::new(server))
+    }
+
+    fn _reload_with_new_notifier(&self, config: AnyServerConfig) -> anyhow::Result<ArcServer> {
+        let server = self.prepare_reload(config)?;
+        Ok(Arc::new(server))
+    }
+
+    fn _start_runtime(&self, server: &ArcServer) -> anyhow::Result<()> {
+        let runtime =
+            UdpTProxyRuntime::new(server, self.config.flow_queue_size, self.config.max_flows);
+        runtime
+            .run_all_instances(
+                &self.config.listen,
+                self.config.listen_in_worker,
+                &self.reload_sender,
+            )
+            .map(|_| self.server_stats.set_online())
+    }
+
+    fn _abort_runtime(&self) {
+        let _ = self.reload_sender.send(ServerReloadCommand::QuitRuntime);
+        self.server_stats.set_offline();
+    }
+}
+
+impl BaseServer for UdpTProxyServer {
+    #[inline]
+    fn name(&self) -> &MetricsName {
+        self.config.name()
+    }
+
+    #[inline]
+    fn server_type(&self) -> &'static str {
+        self.config.server_type()
+    }
+
+    #[inline]
+    fn version(&self) -> usize {
+        self.reload_version
+    }
+}
+
+#[async_trait]
+impl AcceptTcpServer for UdpTProxyServer {
+    async fn run_tcp_task(&self, _stream: TcpStream, _cc_info: ClientConnectionInfo) {}
+}
+
+#[async_trait]
+impl AcceptQuicServer for UdpTProxyServer {
+    #[cfg(feature = "quic")]
+    async fn run_quic_task(&self, _connection: Connection, _cc_info: ClientConnectionInfo) {}
+}
+
+#[async_trait]
+impl Server for UdpTProxyServer {
+    fn escaper(&self) -> &MetricsName {
+        self.config.escaper()
+    }
+
+    fn user_group(&self) -> &MetricsName {
+        Default::default()
+    }
+
+    fn auditor(&self) -> &MetricsName {
+        Default::default()
+    }
+
+    fn get_server_stats(&self) -> Option<ArcServerStats> {
+        Some(self.server_stats.clone())
+    }
+
+    fn get_listen_stats(&self) -> Arc<ListenStats> {
+        Arc::clone(&self.listen_stats)
+    }
+
+    fn alive_count(&self) -> i32 {
+        self.server_stats.get_alive_count()
+    }
+
+    #[inline]
+    fn quit_policy(&self) -> &Arc<ServerQuitPolicy> {
+        &self.quit_policy
+    }
+
+    async fn run_rustls_task(&self, _stream: TlsStream<TcpStream>, _cc_info: ClientConnectionInfo) {
+    }
+
+    async fn run_openssl_task(
+        &self,
+        _stream: SslStream<TcpStream>,
+        _cc_info: ClientConnectionInfo,
+    ) {
+    }
+
+    async fn run_udp_tproxy_task(&self, flow: UdpTProxyFlow, cc_info: ClientConnectionInfo) {
+        let client_addr = cc_info.client_addr();
+        self.server_stats.add_conn(client_addr);
+        if self.drop_early(client_addr) {
+            return;
+        }
+
+        let ctx = CommonTaskContext {
+            server_config: Arc::clone(&self.config),
+            server_stats: Arc::clone(&self.server_stats),
+            server_quit_policy: Arc::clone(&self.quit_policy),
+            escaper: self.escaper.load().as_ref().clone(),
+            cc_info,
+            task_logger: self.task_logger.clone(),
+        };
+
+        UdpTProxyTask::new(ctx).into_running(flow).await;
+    }
+}
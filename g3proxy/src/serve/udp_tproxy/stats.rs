@@ -0,0 +1,220 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicI32, AtomicIsize, AtomicU64, Ordering};
+use std::sync::Arc;
+
+use arc_swap::ArcSwapOption;
+
+use g3_daemon::stat::task::UdpConnectConnectionStats;
+use g3_io_ext::{LimitedRecvStats, LimitedSendStats};
+use g3_types::metrics::{MetricsName, StaticMetricsTags};
+use g3_types::stats::{StatId, UdpIoSnapshot, UdpIoStats};
+
+use crate::module::udp_connect::UdpConnectTaskRemoteStats;
+use crate::serve::task_registry::AliveTaskStats;
+use crate::serve::{ServerForbiddenSnapshot, ServerForbiddenStats, ServerStats};
+
+pub(crate) struct UdpTProxyServerStats {
+    name: MetricsName,
+    id: StatId,
+
+    extra_metrics_tags: Arc<ArcSwapOption<StaticMetricsTags>>,
+
+    online: AtomicIsize,
+    conn_total: AtomicU64,
+
+    task_total: AtomicU64,
+    task_alive_count: AtomicI32,
+
+    udp: UdpIoStats,
+    pub(crate) forbidden: ServerForbiddenStats,
+}
+
+impl UdpTProxyServerStats {
+    pub(crate) fn new(name: &MetricsName) -> Self {
+        UdpTProxyServerStats {
+            name: name.clone(),
+            id: StatId::new(),
+            extra_metrics_tags: Arc::new(ArcSwapOption::new(None)),
+            online: AtomicIsize::new(0),
+            conn_total: AtomicU64::new(0),
+            task_total: AtomicU64::new(0),
+            task_alive_count: AtomicI32::new(0),
+            udp: Default::default(),
+            forbidden: Default::default(),
+        }
+    }
+
+    pub(crate) fn set_online(&self) {
+        self.online.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn set_offline(&self) {
+        self.online.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn set_extra_tags(&self, tags: Option<Arc<StaticMetricsTags>>) {
+        self.extra_metrics_tags.store(tags);
+    }
+
+    pub(crate) fn add_conn(&self, _addr: SocketAddr) {
+        self.conn_total.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_task(&self) {
+        self.task_total.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn inc_alive_task(&self) {
+        self.task_alive_count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn dec_alive_task(&self) {
+        self.task_alive_count.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl ServerStats for UdpTProxyServerStats {
+    #[inline]
+    fn name(&self) -> &MetricsName {
+        &self.name
+    }
+
+    #[inline]
+    fn stat_id(&self) -> StatId {
+        self.id
+    }
+
+    #[inline]
+    fn load_extra_tags(&self) -> Option<Arc<StaticMetricsTags>> {
+        self.extra_metrics_tags.load_full()
+    }
+
+    #[inline]
+    fn share_extra_tags(&self) -> &Arc<ArcSwapOption<StaticMetricsTags>> {
+        &self.extra_metrics_tags
+    }
+
+    fn is_online(&self) -> bool {
+        self.online.load(Ordering::Relaxed) > 0
+    }
+
+    fn get_conn_total(&self) -> u64 {
+        self.conn_total.load(Ordering::Relaxed)
+    }
+
+    fn get_task_total(&self) -> u64 {
+        self.task_total.load(Ordering::Relaxed)
+    }
+
+    fn get_alive_count(&self) -> i32 {
+        self.task_alive_count.load(Ordering::Relaxed)
+    }
+
+    fn udp_io_snapshot(&self) -> Option<UdpIoSnapshot> {
+        Some(self.udp.snapshot())
+    }
+
+    #[inline]
+    fn forbidden_stats(&self) -> ServerForbiddenSnapshot {
+        self.forbidden.snapshot()
+    }
+}
+
+#[derive(Default)]
+pub(super) struct UdpTProxyTaskStats {
+    pub(super) clt: UdpConnectConnectionStats,
+    pub(super) ups: UdpConnectConnectionStats,
+}
+
+impl AliveTaskStats for UdpTProxyTaskStats {
+    fn clt_read_bytes(&self) -> u64 {
+        self.clt.recv.get_bytes()
+    }
+
+    fn clt_write_bytes(&self) -> u64 {
+        self.clt.send.get_bytes()
+    }
+
+    fn ups_read_bytes(&self) -> u64 {
+        self.ups.recv.get_bytes()
+    }
+
+    fn ups_write_bytes(&self) -> u64 {
+        self.ups.send.get_bytes()
+    }
+}
+
+impl UdpConnectTaskRemoteStats for UdpTProxyTaskStats {
+    fn add_recv_bytes(&self, size: u64) {
+        self.ups.recv.add_bytes(size);
+    }
+
+    fn add_recv_packets(&self, n: usize) {
+        self.ups.recv.add_packets(n);
+    }
+
+    fn add_send_bytes(&self, size: u64) {
+        self.ups.send.add_bytes(size);
+    }
+
+    fn add_send_packets(&self, n: usize) {
+        self.ups.send.add_packets(n);
+    }
+}
+
+#[derive(Clone)]
+pub(super) struct UdpTProxyTaskCltWrapperStats {
+    server: Arc<UdpTProxyServerStats>,
+    task: Arc<UdpTProxyTaskStats>,
+}
+
+impl UdpTProxyTaskCltWrapperStats {
+    pub(super) fn new(server: &Arc<UdpTProxyServerStats>, task: &Arc<UdpTProxyTaskStats>) -> Self {
+        UdpTProxyTaskCltWrapperStats {
+            server: Arc::clone(server),
+            task: Arc::clone(task),
+        }
+    }
+}
+
+impl LimitedRecvStats for UdpTProxyTaskCltWrapperStats {
+    fn add_recv_bytes(&self, size: usize) {
+        let size = size as u64;
+        self.server.udp.add_in_bytes(size);
+        self.task.clt.recv.add_bytes(size);
+    }
+
+    fn add_recv_packets(&self, n: usize) {
+        self.server.udp.add_in_packets(n);
+        self.task.clt.recv.add_packets(n);
+    }
+}
+
+impl LimitedSendStats for UdpTProxyTaskCltWrapperStats {
+    fn add_send_bytes(&self, size: usize) {
+        let size = size as u64;
+        self.server.udp.add_out_bytes(size);
+        self.task.clt.send.add_bytes(size);
+    }
+
+    fn add_send_packets(&self, n: usize) {
+        self.server.udp.add_out_packets(n);
+        self.task.clt.send.add_packets(n);
+    }
+}
@@ -0,0 +1,388 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::io::{self, IoSlice};
+use std::sync::Arc;
+use std::task::{ready, Context, Poll};
+use std::time::Duration;
+
+use log::debug;
+use slog::Logger;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+
+use g3_io_ext::{
+    AsyncUdpRecv, AsyncUdpSend, LimitedRecvStats, LimitedUdpRecv, LimitedUdpSend, SendMsgHdr,
+    UdpCopyClientError, UdpCopyClientRecv, UdpCopyClientSend, UdpCopyClientToRemote, UdpCopyError,
+    UdpCopyPacket, UdpCopyPacketMeta, UdpCopyRemoteRecv, UdpCopyRemoteSend, UdpCopyRemoteToClient,
+    UdpRecvHalf, UdpSendHalf,
+};
+use g3_types::net::UpstreamAddr;
+
+use super::common::CommonTaskContext;
+use super::stats::{UdpTProxyTaskCltWrapperStats, UdpTProxyTaskStats};
+use super::UdpTProxyFlow;
+use crate::config::server::ServerConfig;
+use crate::log::escape::udp_sendto::EscapeLogForUdpConnectSendTo;
+use crate::log::task::udp_connect::TaskLogForUdpConnect;
+use crate::module::udp_connect::{UdpConnectTaskConf, UdpConnectTaskNotes};
+use crate::serve::task_registry;
+use crate::serve::{ServerTaskError, ServerTaskNotes, ServerTaskResult, ServerTaskStage};
+
+/// Client side receiver of a transparent udp flow.
+///
+/// Packets may arrive both from the listen socket, through the flow channel, and
+/// from the connected reply socket, as the kernel will prefer it for later packets.
+struct UdpTProxyClientRecv {
+    packets: Option<mpsc::Receiver<Vec<u8>>>,
+    socket: LimitedUdpRecv<UdpRecvHalf>,
+    stats: Arc<UdpTProxyTaskCltWrapperStats>,
+}
+
+impl UdpTProxyClientRecv {
+    fn poll_recv(
+        &mut self,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize, UdpCopyClientError>> {
+        if let Some(packets) = &mut self.packets {
+            match packets.poll_recv(cx) {
+                Poll::Ready(Some(p)) => {
+                    let len = p.len().min(buf.len());
+                    buf[..len].copy_from_slice(&p[..len]);
+                    self.stats.add_recv_bytes(len);
+                    self.stats.add_recv_packet();
+                    return Poll::Ready(Ok(len));
+                }
+                // the listen runtime has gone, only the reply socket is available now
+                Poll::Ready(None) => self.packets = None,
+                Poll::Pending => {}
+            }
+        }
+
+        let nr = ready!(self.socket.poll_recv(cx, buf)).map_err(UdpCopyClientError::RecvFailed)?;
+        Poll::Ready(Ok(nr))
+    }
+}
+
+impl UdpCopyClientRecv for UdpTProxyClientRecv {
+    fn max_hdr_len(&self) -> usize {
+        0
+    }
+
+    fn poll_recv_packet(
+        &mut self,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<(usize, usize), UdpCopyClientError>> {
+        let nr = ready!(self.poll_recv(cx, buf))?;
+        Poll::Ready(Ok((0, nr)))
+    }
+
+    fn poll_recv_packets(
+        &mut self,
+        cx: &mut Context<'_>,
+        packets: &mut [UdpCopyPacket],
+    ) -> Poll<Result<usize, UdpCopyClientError>> {
+        let Some(p) = packets.first_mut() else {
+            return Poll::Ready(Ok(0));
+        };
+        let nr = ready!(self.poll_recv(cx, p.buf_mut()))?;
+        let meta = UdpCopyPacketMeta::new(&io::IoSliceMut::new(p.buf_mut()), 0, nr);
+        meta.set_packet(p);
+        Poll::Ready(Ok(1))
+    }
+}
+
+struct UdpTProxyClientSend {
+    socket: LimitedUdpSend<UdpSendHalf>,
+}
+
+impl UdpCopyClientSend for UdpTProxyClientSend {
+    fn poll_send_packet(
+        &mut self,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, UdpCopyClientError>> {
+        let nw = ready!(self.socket.poll_send(cx, buf)).map_err(UdpCopyClientError::SendFailed)?;
+        if nw == 0 {
+            Poll::Ready(Err(UdpCopyClientError::SendFailed(io::Error::new(
+                io::ErrorKind::WriteZero,
+                "write zero byte into sender",
+            ))))
+        } else {
+            Poll::Ready(Ok(nw))
+        }
+    }
+
+    fn poll_send_packets(
+        &mut self,
+        cx: &mut Context<'_>,
+        packets: &[UdpCopyPacket],
+    ) -> Poll<Result<usize, UdpCopyClientError>> {
+        let mut msgs: Vec<SendMsgHdr<1>> = packets
+            .iter()
+            .map(|p| SendMsgHdr::new([IoSlice::new(p.payload())], None))
+            .collect();
+
+        let count = ready!(self.socket.poll_batch_sendmsg(cx, &mut msgs))
+            .map_err(UdpCopyClientError::SendFailed)?;
+        if count == 0 {
+            Poll::Ready(Err(UdpCopyClientError::SendFailed(io::Error::new(
+                io::ErrorKind::WriteZero,
+                "write zero packet into sender",
+            ))))
+        } else {
+            Poll::Ready(Ok(count))
+        }
+    }
+}
+
+pub(super) struct UdpTProxyTask {
+    ctx: CommonTaskContext,
+    upstream: UpstreamAddr,
+    udp_notes: UdpConnectTaskNotes,
+    task_notes: ServerTaskNotes,
+    task_stats: Arc<UdpTProxyTaskStats>,
+}
+
+impl UdpTProxyTask {
+    pub(super) fn new(ctx: CommonTaskContext) -> Self {
+        let target = ctx.target_addr();
+        let task_notes = ServerTaskNotes::new(ctx.cc_info.clone(), None, Duration::ZERO);
+        UdpTProxyTask {
+            ctx,
+            upstream: UpstreamAddr::from(target),
+            udp_notes: UdpConnectTaskNotes::default(),
+            task_notes,
+            task_stats: Arc::new(UdpTProxyTaskStats::default()),
+        }
+    }
+
+    fn get_log_context(&self) -> TaskLogForUdpConnect<'_> {
+        TaskLogForUdpConnect {
+            task_notes: &self.task_notes,
+            tcp_server_addr: self.ctx.target_addr(),
+            tcp_client_addr: self.ctx.client_addr(),
+            udp_listen_addr: Some(self.ctx.target_addr()),
+            udp_client_addr: Some(self.ctx.client_addr()),
+            upstream: Some(&self.upstream),
+            udp_notes: &self.udp_notes,
+            total_time: self.task_notes.time_elapsed(),
+            client_rd_bytes: self.task_stats.clt.recv.get_bytes(),
+            client_rd_packets: self.task_stats.clt.recv.get_packets(),
+            client_wr_bytes: self.task_stats.clt.send.get_bytes(),
+            client_wr_packets: self.task_stats.clt.send.get_packets(),
+            remote_rd_bytes: self.task_stats.ups.recv.get_bytes(),
+            remote_rd_packets: self.task_stats.ups.recv.get_packets(),
+            remote_wr_bytes: self.task_stats.ups.send.get_bytes(),
+            remote_wr_packets: self.task_stats.ups.send.get_packets(),
+        }
+    }
+
+    pub(super) async fn into_running(mut self, flow: UdpTProxyFlow) {
+        self.pre_start();
+        match self.run(flow).await {
+            Ok(_) => self
+                .get_log_context()
+                .log(&self.ctx.task_logger, &ServerTaskError::Finished),
+            Err(e) => self.get_log_context().log(&self.ctx.task_logger, &e),
+        };
+        self.pre_stop();
+    }
+
+    fn pre_start(&self) {
+        debug!(
+            "UdpTProxy: new transparent flow from {} to {} via server {}, using escaper {}",
+            self.ctx.client_addr(),
+            self.ctx.target_addr(),
+            self.ctx.server_config.name(),
+            self.ctx.server_config.escaper
+        );
+        self.ctx.server_stats.add_task();
+        self.ctx.server_stats.inc_alive_task();
+    }
+
+    fn pre_stop(&self) {
+        self.ctx.server_stats.dec_alive_task();
+    }
+
+    async fn run(&mut self, flow: UdpTProxyFlow) -> ServerTaskResult<()> {
+        self.task_notes.stage = ServerTaskStage::Preparing;
+        let (clt_r, clt_w) = self.setup_client(flow)?;
+
+        self.task_notes.stage = ServerTaskStage::Connecting;
+        let task_conf = UdpConnectTaskConf {
+            upstream: &self.upstream,
+            sock_buf: self.ctx.server_config.udp_socket_buffer,
+        };
+        let (ups_r, ups_w, escape_logger) = self
+            .ctx
+            .escaper
+            .udp_setup_connection(
+                &task_conf,
+                &mut self.udp_notes,
+                &self.task_notes,
+                self.task_stats.clone(),
+            )
+            .await?;
+        self.task_notes.stage = ServerTaskStage::Connected;
+
+        self.task_notes.mark_relaying();
+        let alive_task = task_registry::register(
+            self.ctx.server_config.name(),
+            "UdpTProxy",
+            &self.task_notes,
+            &self.upstream,
+            &self.udp_notes.escaper,
+            &self.task_stats,
+        );
+        tokio::select! {
+            r = self.run_relay(
+                Box::new(clt_r),
+                Box::new(clt_w),
+                ups_r,
+                ups_w,
+                &escape_logger,
+            ) => r,
+            _ = alive_task.killed() => Err(ServerTaskError::CanceledAsTaskKilled),
+        }
+    }
+
+    fn setup_client(
+        &self,
+        flow: UdpTProxyFlow,
+    ) -> ServerTaskResult<(UdpTProxyClientRecv, UdpTProxyClientSend)> {
+        let socket = g3_socket::udp::new_std_transparent_reply(
+            self.ctx.target_addr(),
+            self.ctx.client_addr(),
+            self.ctx.server_config.udp_socket_buffer,
+            self.ctx.server_config.udp_misc_opts,
+        )
+        .map_err(|_| {
+            ServerTaskError::InternalServerError("failed to setup the client side reply socket")
+        })?;
+        let socket = tokio::net::UdpSocket::from_std(socket).map_err(|_| {
+            ServerTaskError::InternalServerError("failed to setup the client side reply socket")
+        })?;
+        let (clt_r, clt_w) = g3_io_ext::split_udp(socket);
+
+        let limit_config = &self.ctx.server_config.udp_sock_speed_limit;
+        let wrapper_stats = Arc::new(UdpTProxyTaskCltWrapperStats::new(
+            &self.ctx.server_stats,
+            &self.task_stats,
+        ));
+
+        let clt_r = LimitedUdpRecv::local_limited(
+            clt_r,
+            limit_config.shift_millis,
+            limit_config.max_north_packets,
+            limit_config.max_north_bytes,
+            wrapper_stats.clone(),
+        );
+        let clt_w = LimitedUdpSend::local_limited(
+            clt_w,
+            limit_config.shift_millis,
+            limit_config.max_south_packets,
+            limit_config.max_south_bytes,
+            wrapper_stats.clone(),
+        );
+
+        Ok((
+            UdpTProxyClientRecv {
+                packets: Some(flow.into_receiver()),
+                socket: clt_r,
+                stats: wrapper_stats,
+            },
+            UdpTProxyClientSend { socket: clt_w },
+        ))
+    }
+
+    async fn run_relay<'a>(
+        &'a mut self,
+        mut clt_r: Box<dyn UdpCopyClientRecv + Unpin + Send>,
+        mut clt_w: Box<dyn UdpCopyClientSend + Unpin + Send>,
+        mut ups_r: Box<dyn UdpCopyRemoteRecv + Unpin + Send>,
+        mut ups_w: Box<dyn UdpCopyRemoteSend + Unpin + Send>,
+        escape_logger: &'a Logger,
+    ) -> ServerTaskResult<()> {
+        let task_id = &self.task_notes.id;
+
+        let mut c_to_r =
+            UdpCopyClientToRemote::new(&mut *clt_r, &mut *ups_w, self.ctx.server_config.udp_relay);
+        let mut r_to_c =
+            UdpCopyRemoteToClient::new(&mut *clt_w, &mut *ups_r, self.ctx.server_config.udp_relay);
+
+        let idle_duration = self.ctx.server_config.task_idle_check_duration;
+        let mut idle_interval =
+            tokio::time::interval_at(Instant::now() + idle_duration, idle_duration);
+        let mut idle_count = 0;
+        loop {
+            tokio::select! {
+                biased;
+
+                r = &mut c_to_r => {
+                    return match r {
+                        Ok(_) => Ok(()),
+                        Err(UdpCopyError::RemoteError(e)) => {
+                            EscapeLogForUdpConnectSendTo {
+                                task_id,
+                                upstream: Some(&self.upstream),
+                                udp_notes: &self.udp_notes,
+                            }
+                            .log(escape_logger, &e);
+                            Err(e.into())
+                        },
+                        Err(UdpCopyError::ClientError(e)) => Err(e.into()),
+                    };
+                }
+                r = &mut r_to_c => {
+                    return match r {
+                        Ok(_) => Ok(()),
+                        Err(UdpCopyError::RemoteError(e)) => {
+                            EscapeLogForUdpConnectSendTo {
+                                task_id,
+                                upstream: Some(&self.upstream),
+                                udp_notes: &self.udp_notes,
+                            }
+                            .log(escape_logger, &e);
+                            Err(e.into())
+                        },
+                        Err(UdpCopyError::ClientError(e)) => Err(e.into()),
+                    };
+                }
+                _ = idle_interval.tick() => {
+                    if c_to_r.is_idle() && r_to_c.is_idle() {
+                        idle_count += 1;
+
+                        if idle_count >= self.ctx.server_config.task_idle_max_count {
+                            return Err(ServerTaskError::Idle(idle_duration, idle_count));
+                        }
+                    } else {
+                        idle_count = 0;
+
+                        c_to_r.reset_active();
+                        r_to_c.reset_active();
+                    }
+
+                    if self.ctx.server_quit_policy.force_quit() {
+                        return Err(ServerTaskError::CanceledAsServerQuit)
+                    }
+                }
+            }
+        }
+    }
+}
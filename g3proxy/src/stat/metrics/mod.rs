@@ -53,8 +53,10 @@ enum MetricUserRequestType {
     HttpConnect,
     FtpOverHttp,
     SocksTcpConnect,
+    SocksTcpBind,
     SocksUdpConnect,
     SocksUdpAssociate,
+    HttpConnectUdp,
 }
 
 impl MetricUserRequestType {
@@ -65,8 +67,10 @@ impl MetricUserRequestType {
             MetricUserRequestType::HttpConnect => "http_connect",
             MetricUserRequestType::FtpOverHttp => "ftp_over_http",
             MetricUserRequestType::SocksTcpConnect => "socks_tcp_connect",
+            MetricUserRequestType::SocksTcpBind => "socks_tcp_bind",
             MetricUserRequestType::SocksUdpConnect => "socks_udp_connect",
             MetricUserRequestType::SocksUdpAssociate => "socks_udp_associate",
+            MetricUserRequestType::HttpConnectUdp => "http_connect_udp",
         }
     }
 }
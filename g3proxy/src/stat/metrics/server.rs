@@ -25,6 +25,7 @@ use g3_daemon::metrics::{
 use g3_statsd_client::{StatsdClient, StatsdTagGroup};
 use g3_types::stats::{StatId, TcpIoSnapshot, UdpIoSnapshot};
 
+use crate::module::http_forward::HttpForwardCacheSnapshot;
 use crate::serve::{ArcServerStats, ServerForbiddenSnapshot};
 use crate::stat::types::UntrustedTaskStatsSnapshot;
 
@@ -34,6 +35,7 @@ const METRIC_NAME_SERVER_TASK_ALIVE: &str = "server.task.alive";
 const METRIC_NAME_SERVER_FORBIDDEN_AUTH_FAILED: &str = "server.forbidden.auth_failed";
 const METRIC_NAME_SERVER_FORBIDDEN_DEST_DENIED: &str = "server.forbidden.dest_denied";
 const METRIC_NAME_SERVER_FORBIDDEN_USER_BLOCKED: &str = "server.forbidden.user_blocked";
+const METRIC_NAME_SERVER_FORBIDDEN_SRC_GEO_BLOCKED: &str = "server.forbidden.src_geo_blocked";
 const METRIC_NAME_SERVER_IO_IN_BYTES: &str = "server.traffic.in.bytes";
 const METRIC_NAME_SERVER_IO_IN_PACKETS: &str = "server.traffic.in.packets";
 const METRIC_NAME_SERVER_IO_OUT_BYTES: &str = "server.traffic.out.bytes";
@@ -41,6 +43,11 @@ const METRIC_NAME_SERVER_IO_OUT_PACKETS: &str = "server.traffic.out.packets";
 const METRIC_NAME_SERVER_UNTRUSTED_TASK_TOTAL: &str = "server.task.untrusted_total";
 const METRIC_NAME_SERVER_UNTRUSTED_TASK_ALIVE: &str = "server.task.untrusted_alive";
 const METRIC_NAME_SERVER_IO_UNTRUSTED_IN_BYTES: &str = "server.traffic.untrusted_in.bytes";
+const METRIC_NAME_SERVER_CACHE_HIT: &str = "server.http_forward_cache.hit";
+const METRIC_NAME_SERVER_CACHE_MISS: &str = "server.http_forward_cache.miss";
+const METRIC_NAME_SERVER_CACHE_REVALIDATED: &str = "server.http_forward_cache.revalidated";
+const METRIC_NAME_SERVER_CACHE_STORED: &str = "server.http_forward_cache.stored";
+const METRIC_NAME_SERVER_CACHE_HIT_BYTES: &str = "server.http_forward_cache.hit.bytes";
 
 type ServerStatsValue = (ArcServerStats, ServerSnapshot);
 type ListenStatsValue = (Arc<ListenStats>, ListenSnapshot);
@@ -58,6 +65,7 @@ struct ServerSnapshot {
     tcp: TcpIoSnapshot,
     udp: UdpIoSnapshot,
     untrusted: UntrustedTaskStatsSnapshot,
+    http_forward_cache: HttpForwardCacheSnapshot,
 }
 
 pub(in crate::stat) fn sync_stats() {
@@ -147,6 +155,15 @@ fn emit_server_stats(client: &mut StatsdClient, stats: &ArcServerStats, snap: &m
     if let Some(untrusted_stats) = stats.untrusted_snapshot() {
         emit_untrusted_stats(client, untrusted_stats, &mut snap.untrusted, &common_tags);
     }
+
+    if let Some(cache_stats) = stats.http_forward_cache_snapshot() {
+        emit_http_forward_cache_stats(
+            client,
+            cache_stats,
+            &mut snap.http_forward_cache,
+            &common_tags,
+        );
+    }
 }
 
 fn emit_forbidden_stats(
@@ -171,6 +188,7 @@ fn emit_forbidden_stats(
     emit_forbid_stats_u64!(auth_failed, METRIC_NAME_SERVER_FORBIDDEN_AUTH_FAILED);
     emit_forbid_stats_u64!(dest_denied, METRIC_NAME_SERVER_FORBIDDEN_DEST_DENIED);
     emit_forbid_stats_u64!(user_blocked, METRIC_NAME_SERVER_FORBIDDEN_USER_BLOCKED);
+    emit_forbid_stats_u64!(src_geo_blocked, METRIC_NAME_SERVER_FORBIDDEN_SRC_GEO_BLOCKED);
 }
 
 fn emit_tcp_io_to_statsd(
@@ -267,3 +285,31 @@ fn emit_untrusted_stats(
         .send();
     snap.in_bytes = new_value;
 }
+
+fn emit_http_forward_cache_stats(
+    client: &mut StatsdClient,
+    stats: HttpForwardCacheSnapshot,
+    snap: &mut HttpForwardCacheSnapshot,
+    common_tags: &StatsdTagGroup,
+) {
+    if stats.hit == 0 && stats.miss == 0 && stats.revalidated == 0 {
+        return;
+    }
+
+    macro_rules! emit_field {
+        ($field:ident, $name:expr) => {
+            let new_value = stats.$field;
+            let diff_value = new_value.wrapping_sub(snap.$field);
+            client
+                .count_with_tags($name, diff_value, common_tags)
+                .send();
+            snap.$field = new_value;
+        };
+    }
+
+    emit_field!(hit, METRIC_NAME_SERVER_CACHE_HIT);
+    emit_field!(miss, METRIC_NAME_SERVER_CACHE_MISS);
+    emit_field!(revalidated, METRIC_NAME_SERVER_CACHE_REVALIDATED);
+    emit_field!(stored, METRIC_NAME_SERVER_CACHE_STORED);
+    emit_field!(hit_bytes, METRIC_NAME_SERVER_CACHE_HIT_BYTES);
+}
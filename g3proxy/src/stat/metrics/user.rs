@@ -49,6 +49,7 @@ const METRIC_NAME_FORBIDDEN_FULLY_LOADED: &str = "user.forbidden.fully_loaded";
 const METRIC_NAME_FORBIDDEN_RATE_LIMITED: &str = "user.forbidden.rate_limited";
 const METRIC_NAME_FORBIDDEN_PROTO_BANNED: &str = "user.forbidden.proto_banned";
 const METRIC_NAME_FORBIDDEN_SRC_BLOCKED: &str = "user.forbidden.src_blocked";
+const METRIC_NAME_FORBIDDEN_SRC_GEO_BLOCKED: &str = "user.forbidden.src_geo_blocked";
 const METRIC_NAME_FORBIDDEN_DEST_DENIED: &str = "user.forbidden.dest_denied";
 const METRIC_NAME_FORBIDDEN_IP_BLOCKED: &str = "user.forbidden.ip_blocked";
 const METRIC_NAME_FORBIDDEN_LOG_SKIPPED: &str = "user.forbidden.log_skipped";
@@ -168,6 +169,10 @@ impl UserMetricExt for StatsdTagGroup {
 pub(in crate::stat) fn sync_stats() {
     let groups = crate::auth::get_all_groups();
 
+    for user_group in groups.iter() {
+        user_group.foreach_user(|_, user: &Arc<User>| user.roll_rate_window());
+    }
+
     let mut fbd_stats_map = USER_FORBIDDEN_STATS_MAP.lock().unwrap();
     for user_group in groups.iter() {
         user_group.foreach_user(|_, user: &Arc<User>| {
@@ -298,6 +303,7 @@ fn emit_user_forbidden_stats(
     emit_forbid_stats_u64!(rate_limited, METRIC_NAME_FORBIDDEN_RATE_LIMITED);
     emit_forbid_stats_u64!(proto_banned, METRIC_NAME_FORBIDDEN_PROTO_BANNED);
     emit_forbid_stats_u64!(src_blocked, METRIC_NAME_FORBIDDEN_SRC_BLOCKED);
+    emit_forbid_stats_u64!(src_geo_blocked, METRIC_NAME_FORBIDDEN_SRC_GEO_BLOCKED);
     emit_forbid_stats_u64!(dest_denied, METRIC_NAME_FORBIDDEN_DEST_DENIED);
     emit_forbid_stats_u64!(ip_blocked, METRIC_NAME_FORBIDDEN_IP_BLOCKED);
     emit_forbid_stats_u64!(ua_blocked, METRIC_NAME_FORBIDDEN_UA_BLOCKED);
@@ -474,11 +480,13 @@ where
     emit_field!(http_connect, MetricUserRequestType::HttpConnect);
     emit_field!(ftp_over_http, MetricUserRequestType::FtpOverHttp);
     emit_field!(socks_tcp_connect, MetricUserRequestType::SocksTcpConnect);
+    emit_field!(socks_tcp_bind, MetricUserRequestType::SocksTcpBind);
     emit_field!(socks_udp_connect, MetricUserRequestType::SocksUdpConnect);
     emit_field!(
         socks_udp_associate,
         MetricUserRequestType::SocksUdpAssociate
     );
+    emit_field!(http_connect_udp, MetricUserRequestType::HttpConnectUdp);
 }
 
 fn find_req_alive_stat<F>(stats: &RequestAliveStats, mut emit: F)
@@ -493,6 +501,7 @@ where
         stats.socks_tcp_connect(),
         MetricUserRequestType::SocksTcpConnect,
     );
+    emit(stats.socks_tcp_bind(), MetricUserRequestType::SocksTcpBind);
     emit(
         stats.socks_udp_connect(),
         MetricUserRequestType::SocksUdpConnect,
@@ -501,6 +510,10 @@ where
         stats.socks_udp_associate(),
         MetricUserRequestType::SocksUdpAssociate,
     );
+    emit(
+        stats.http_connect_udp(),
+        MetricUserRequestType::HttpConnectUdp,
+    );
 }
 
 fn find_keepalive_req_stat<F>(
@@ -550,6 +563,7 @@ fn find_io_stat<'a, F>(
     emit_tcp_field!(http_connect, MetricUserRequestType::HttpConnect);
     emit_tcp_field!(ftp_over_http, MetricUserRequestType::FtpOverHttp);
     emit_tcp_field!(socks_tcp_connect, MetricUserRequestType::SocksTcpConnect);
+    emit_tcp_field!(socks_tcp_bind, MetricUserRequestType::SocksTcpBind);
 
     macro_rules! emit_udp_field {
         ($field:ident, $request:expr) => {
@@ -568,6 +582,7 @@ fn find_io_stat<'a, F>(
         socks_udp_associate,
         MetricUserRequestType::SocksUdpAssociate
     );
+    emit_udp_field!(http_connect_udp, MetricUserRequestType::HttpConnectUdp);
 }
 
 fn find_tcp_io_stat<'a, F>(
@@ -23,8 +23,10 @@ pub(crate) struct RequestStats {
     http_connect: AtomicU64,
     ftp_over_http: AtomicU64,
     socks_tcp_connect: AtomicU64,
+    socks_tcp_bind: AtomicU64,
     socks_udp_connect: AtomicU64,
     socks_udp_associate: AtomicU64,
+    http_connect_udp: AtomicU64,
 }
 
 #[derive(Default)]
@@ -34,11 +36,25 @@ pub(crate) struct RequestSnapshot {
     pub(crate) http_connect: u64,
     pub(crate) ftp_over_http: u64,
     pub(crate) socks_tcp_connect: u64,
+    pub(crate) socks_tcp_bind: u64,
     pub(crate) socks_udp_connect: u64,
     pub(crate) socks_udp_associate: u64,
+    pub(crate) http_connect_udp: u64,
 }
 
 impl RequestStats {
+    pub(crate) fn total(&self) -> u64 {
+        self.http_forward()
+            + self.https_forward()
+            + self.http_connect()
+            + self.ftp_over_http()
+            + self.socks_tcp_connect()
+            + self.socks_tcp_bind()
+            + self.socks_udp_connect()
+            + self.socks_udp_associate()
+            + self.http_connect_udp()
+    }
+
     pub(crate) fn add_http_forward(&self, is_https: bool) {
         if is_https {
             self.https_forward.fetch_add(1, Ordering::Relaxed);
@@ -79,6 +95,14 @@ impl RequestStats {
         self.socks_tcp_connect.load(Ordering::Relaxed)
     }
 
+    pub(crate) fn add_socks_tcp_bind(&self) {
+        self.socks_tcp_bind.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn socks_tcp_bind(&self) -> u64 {
+        self.socks_tcp_bind.load(Ordering::Relaxed)
+    }
+
     pub(crate) fn add_socks_udp_connect(&self) {
         self.socks_udp_connect.fetch_add(1, Ordering::Relaxed);
     }
@@ -94,6 +118,14 @@ impl RequestStats {
     pub(crate) fn socks_udp_associate(&self) -> u64 {
         self.socks_udp_associate.load(Ordering::Relaxed)
     }
+
+    pub(crate) fn add_http_connect_udp(&self) {
+        self.http_connect_udp.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn http_connect_udp(&self) -> u64 {
+        self.http_connect_udp.load(Ordering::Relaxed)
+    }
 }
 
 #[derive(Default)]
@@ -133,11 +165,25 @@ pub(crate) struct RequestAliveStats {
     http_connect: AtomicI32,
     ftp_over_http: AtomicI32,
     socks_tcp_connect: AtomicI32,
+    socks_tcp_bind: AtomicI32,
     socks_udp_connect: AtomicI32,
     socks_udp_associate: AtomicI32,
+    http_connect_udp: AtomicI32,
 }
 
 impl RequestAliveStats {
+    pub(crate) fn total(&self) -> i32 {
+        self.http_forward()
+            + self.https_forward()
+            + self.http_connect()
+            + self.ftp_over_http()
+            + self.socks_tcp_connect()
+            + self.socks_tcp_bind()
+            + self.socks_udp_connect()
+            + self.socks_udp_associate()
+            + self.http_connect_udp()
+    }
+
     pub(crate) fn add_http_forward(&self, is_https: bool) {
         if is_https {
             self.https_forward.fetch_add(1, Ordering::Relaxed);
@@ -198,6 +244,18 @@ impl RequestAliveStats {
         self.socks_tcp_connect.load(Ordering::Relaxed)
     }
 
+    pub(crate) fn add_socks_tcp_bind(&self) {
+        self.socks_tcp_bind.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn del_socks_tcp_bind(&self) {
+        self.socks_tcp_bind.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn socks_tcp_bind(&self) -> i32 {
+        self.socks_tcp_bind.load(Ordering::Relaxed)
+    }
+
     pub(crate) fn add_socks_udp_connect(&self) {
         self.socks_udp_connect.fetch_add(1, Ordering::Relaxed);
     }
@@ -221,4 +279,16 @@ impl RequestAliveStats {
     pub(crate) fn socks_udp_associate(&self) -> i32 {
         self.socks_udp_associate.load(Ordering::Relaxed)
     }
+
+    pub(crate) fn add_http_connect_udp(&self) {
+        self.http_connect_udp.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn del_http_connect_udp(&self) {
+        self.http_connect_udp.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn http_connect_udp(&self) -> i32 {
+        self.http_connect_udp.load(Ordering::Relaxed)
+    }
 }
@@ -23,8 +23,27 @@ pub(crate) struct TrafficStats {
     pub(crate) http_connect: TcpIoStats,
     pub(crate) ftp_over_http: TcpIoStats,
     pub(crate) socks_tcp_connect: TcpIoStats,
+    pub(crate) socks_tcp_bind: TcpIoStats,
     pub(crate) socks_udp_connect: UdpIoStats,
     pub(crate) socks_udp_associate: UdpIoStats,
+    pub(crate) http_connect_udp: UdpIoStats,
+}
+
+impl TrafficStats {
+    pub(crate) fn tcp_total(&self) -> TcpIoSnapshot {
+        self.http_forward.snapshot()
+            + self.https_forward.snapshot()
+            + self.http_connect.snapshot()
+            + self.ftp_over_http.snapshot()
+            + self.socks_tcp_connect.snapshot()
+            + self.socks_tcp_bind.snapshot()
+    }
+
+    pub(crate) fn udp_total(&self) -> UdpIoSnapshot {
+        self.socks_udp_connect.snapshot()
+            + self.socks_udp_associate.snapshot()
+            + self.http_connect_udp.snapshot()
+    }
 }
 
 #[derive(Default)]
@@ -34,8 +53,10 @@ pub(crate) struct TrafficSnapshot {
     pub(crate) http_connect: TcpIoSnapshot,
     pub(crate) ftp_over_http: TcpIoSnapshot,
     pub(crate) socks_tcp_connect: TcpIoSnapshot,
+    pub(crate) socks_tcp_bind: TcpIoSnapshot,
     pub(crate) socks_udp_connect: UdpIoSnapshot,
     pub(crate) socks_udp_associate: UdpIoSnapshot,
+    pub(crate) http_connect_udp: UdpIoSnapshot,
 }
 
 #[derive(Default)]
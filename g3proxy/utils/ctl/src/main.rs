@@ -27,6 +27,7 @@ mod proc;
 mod escaper;
 mod resolver;
 mod server;
+mod task;
 mod user_group;
 
 fn build_cli_args() -> Command {
@@ -47,6 +48,7 @@ fn build_cli_args() -> Command {
         .subcommand(resolver::command())
         .subcommand(escaper::command())
         .subcommand(server::command())
+        .subcommand(task::command())
 }
 
 #[tokio::main(flavor = "current_thread")]
@@ -89,6 +91,7 @@ async fn main() -> anyhow::Result<()> {
                 resolver::COMMAND => resolver::run(&proc_control, args).await,
                 escaper::COMMAND => escaper::run(&proc_control, args).await,
                 server::COMMAND => server::run(&proc_control, args).await,
+                task::COMMAND => task::run(&proc_control, args).await,
                 _ => Err(CommandError::Cli(anyhow!(
                     "unsupported command {subcommand}"
                 ))),
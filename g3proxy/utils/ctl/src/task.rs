@@ -0,0 +1,181 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use clap::{value_parser, Arg, ArgMatches, Command};
+
+use g3_ctl::{CommandError, CommandResult};
+
+use g3proxy_proto::proc_capnp::proc_control;
+
+use crate::common::parse_operation_result;
+
+pub const COMMAND: &str = "task";
+
+const SUBCOMMAND_LIST: &str = "list";
+const SUBCOMMAND_KILL: &str = "kill";
+const SUBCOMMAND_KILL_USER: &str = "kill-user";
+
+const LIST_ARG_SERVER: &str = "server";
+const LIST_ARG_USER_GROUP: &str = "user-group";
+const LIST_ARG_USER: &str = "user";
+const LIST_ARG_ESCAPER: &str = "escaper";
+const LIST_ARG_MIN_ALIVE: &str = "min-alive";
+
+const KILL_ARG_ID: &str = "id";
+const KILL_USER_ARG_USER_GROUP: &str = "user-group";
+const KILL_USER_ARG_USER: &str = "user";
+
+pub fn command() -> Command {
+    Command::new(COMMAND)
+        .about("Show or kill alive relaying tasks")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new(SUBCOMMAND_LIST)
+                .arg(
+                    Arg::new(LIST_ARG_SERVER)
+                        .help("Only show tasks of this server")
+                        .long(LIST_ARG_SERVER)
+                        .num_args(1),
+                )
+                .arg(
+                    Arg::new(LIST_ARG_USER_GROUP)
+                        .help("Only show tasks of this user group")
+                        .long(LIST_ARG_USER_GROUP)
+                        .num_args(1),
+                )
+                .arg(
+                    Arg::new(LIST_ARG_USER)
+                        .help("Only show tasks of this user")
+                        .long(LIST_ARG_USER)
+                        .num_args(1),
+                )
+                .arg(
+                    Arg::new(LIST_ARG_ESCAPER)
+                        .help("Only show tasks using this escaper")
+                        .long(LIST_ARG_ESCAPER)
+                        .num_args(1),
+                )
+                .arg(
+                    Arg::new(LIST_ARG_MIN_ALIVE)
+                        .help("Only show tasks alive for at least this many seconds")
+                        .long(LIST_ARG_MIN_ALIVE)
+                        .num_args(1)
+                        .value_parser(value_parser!(u64)),
+                ),
+        )
+        .subcommand(
+            Command::new(SUBCOMMAND_KILL)
+                .about("Kill the task with the given task id")
+                .arg(Arg::new(KILL_ARG_ID).required(true).num_args(1)),
+        )
+        .subcommand(
+            Command::new(SUBCOMMAND_KILL_USER)
+                .about("Kill all tasks of the given user")
+                .arg(
+                    Arg::new(KILL_USER_ARG_USER_GROUP)
+                        .required(true)
+                        .num_args(1),
+                )
+                .arg(Arg::new(KILL_USER_ARG_USER).required(true).num_args(1)),
+        )
+}
+
+fn to_str<'a>(field: &'static str, text: capnp::text::Reader<'a>) -> CommandResult<&'a str> {
+    text.to_str()
+        .map_err(|e| CommandError::Utf8 { field, reason: e })
+}
+
+async fn list(client: &proc_control::Client, args: &ArgMatches) -> CommandResult<()> {
+    let mut req = client.list_task_request();
+    let mut filter = req.get().init_filter();
+    if let Some(server) = args.get_one::<String>(LIST_ARG_SERVER) {
+        filter.set_server(server);
+    }
+    if let Some(user_group) = args.get_one::<String>(LIST_ARG_USER_GROUP) {
+        filter.set_user_group(user_group);
+    }
+    if let Some(user) = args.get_one::<String>(LIST_ARG_USER) {
+        filter.set_user(user);
+    }
+    if let Some(escaper) = args.get_one::<String>(LIST_ARG_ESCAPER) {
+        filter.set_escaper(escaper);
+    }
+    if let Some(secs) = args.get_one::<u64>(LIST_ARG_MIN_ALIVE) {
+        filter.set_min_alive_seconds(*secs);
+    }
+    let rsp = req.send().promise.await?;
+    for task in rsp.get()?.get_result()?.iter() {
+        println!("{}", to_str("id", task.get_id()?)?);
+        println!("  server: {}", to_str("server", task.get_server()?)?);
+        println!("  type: {}", to_str("task_type", task.get_task_type()?)?);
+        println!(
+            "  client: {}",
+            to_str("client_addr", task.get_client_addr()?)?
+        );
+        println!(
+            "  local: {}",
+            to_str("server_addr", task.get_server_addr()?)?
+        );
+        let user = to_str("user", task.get_user()?)?;
+        if !user.is_empty() {
+            let user_group = to_str("user_group", task.get_user_group()?)?;
+            println!("  user: {user_group}/{user}");
+        }
+        println!("  upstream: {}", to_str("upstream", task.get_upstream()?)?);
+        println!("  escaper: {}", to_str("escaper", task.get_escaper()?)?);
+        println!("  start at: {}", to_str("start_at", task.get_start_at()?)?);
+        println!("  alive: {}s", task.get_alive_seconds());
+        println!(
+            "  client bytes: read {} write {}",
+            task.get_clt_read_bytes(),
+            task.get_clt_write_bytes()
+        );
+        println!(
+            "  upstream bytes: read {} write {}",
+            task.get_ups_read_bytes(),
+            task.get_ups_write_bytes()
+        );
+    }
+    Ok(())
+}
+
+async fn kill(client: &proc_control::Client, args: &ArgMatches) -> CommandResult<()> {
+    let id = args.get_one::<String>(KILL_ARG_ID).unwrap();
+    let mut req = client.kill_task_request();
+    req.get().set_id(id);
+    let rsp = req.send().promise.await?;
+    parse_operation_result(rsp.get()?.get_result()?)
+}
+
+async fn kill_user(client: &proc_control::Client, args: &ArgMatches) -> CommandResult<()> {
+    let user_group = args.get_one::<String>(KILL_USER_ARG_USER_GROUP).unwrap();
+    let user = args.get_one::<String>(KILL_USER_ARG_USER).unwrap();
+    let mut req = client.kill_user_task_request();
+    req.get().set_user_group(user_group);
+    req.get().set_user(user);
+    let rsp = req.send().promise.await?;
+    parse_operation_result(rsp.get()?.get_result()?)
+}
+
+pub async fn run(client: &proc_control::Client, args: &ArgMatches) -> CommandResult<()> {
+    let (subcommand, args) = args.subcommand().unwrap();
+    match subcommand {
+        SUBCOMMAND_LIST => list(client, args).await,
+        SUBCOMMAND_KILL => kill(client, args).await,
+        SUBCOMMAND_KILL_USER => kill_user(client, args).await,
+        _ => unreachable!(),
+    }
+}
@@ -23,18 +23,20 @@ use clap::{value_parser, Arg, ArgMatches, Command, ValueHint};
 use g3_ctl::{CommandError, CommandResult};
 
 use g3proxy_proto::proc_capnp::proc_control;
-use g3proxy_proto::user_group_capnp::user_group_control;
+use g3proxy_proto::user_group_capnp::{rate_limit_quota, user_group_control};
 
-use super::common::parse_operation_result;
+use super::common::{parse_fetch_result, parse_operation_result};
 
 pub const COMMAND: &str = "user-group";
 
 const COMMAND_ARG_NAME: &str = "name";
 const COMMAND_ARG_FILE: &str = "file";
+const COMMAND_ARG_USER: &str = "user";
 
 const SUBCOMMAND_LIST_STATIC_USER: &str = "list-static-user";
 const SUBCOMMAND_LIST_DYNAMIC_USER: &str = "list-dynamic-user";
 const SUBCOMMAND_PUBLISH_USER: &str = "publish-user";
+const SUBCOMMAND_GET_USER: &str = "get-user";
 
 pub fn command() -> Command {
     Command::new(COMMAND)
@@ -54,6 +56,11 @@ pub fn command() -> Command {
                         .value_hint(ValueHint::FilePath),
                 ),
         )
+        .subcommand(
+            Command::new(SUBCOMMAND_GET_USER)
+                .about("Show the current stats of a user")
+                .arg(Arg::new(COMMAND_ARG_USER).required(true).num_args(1)),
+        )
 }
 
 pub async fn run(client: &proc_control::Client, args: &ArgMatches) -> CommandResult<()> {
@@ -66,6 +73,7 @@ pub async fn run(client: &proc_control::Client, args: &ArgMatches) -> CommandRes
         SUBCOMMAND_LIST_STATIC_USER => list_static_user(&user_group).await,
         SUBCOMMAND_LIST_DYNAMIC_USER => list_dynamic_user(&user_group).await,
         SUBCOMMAND_PUBLISH_USER => publish_dynamic_user(&user_group, args).await,
+        SUBCOMMAND_GET_USER => get_user(&user_group, args).await,
         _ => unreachable!(),
     }
 }
@@ -108,3 +116,84 @@ async fn publish_dynamic_user(
     let rsp = req.send().promise.await?;
     parse_operation_result(rsp.get()?.get_result()?)
 }
+
+fn print_rate_limit_quota(name: &str, quota: rate_limit_quota::Reader<'_>) {
+    println!(
+        "{name}: burst {} replenish every {}ms",
+        quota.get_burst(),
+        quota.get_replenish_interval_millis()
+    );
+}
+
+async fn get_user(client: &user_group_control::Client, args: &ArgMatches) -> CommandResult<()> {
+    let user = args.get_one::<String>(COMMAND_ARG_USER).unwrap();
+    let mut req = client.get_user_request();
+    req.get().set_name(user);
+    let rsp = req.send().promise.await?;
+    let stats = parse_fetch_result(rsp.get()?.get_stats()?)?;
+
+    let user_type = stats
+        .get_user_type()?
+        .to_str()
+        .map_err(|e| CommandError::Utf8 {
+            field: "user_type",
+            reason: e,
+        })?;
+    println!("type: {user_type}");
+    println!("blocked: {}", stats.get_blocked());
+    println!("expired: {}", stats.get_expired());
+    println!(
+        "alive requests: {}/{}",
+        stats.get_alive_request_count(),
+        stats.get_alive_request_max()
+    );
+    if stats.has_request_rate_limit() {
+        print_rate_limit_quota("request rate limit", stats.get_request_rate_limit()?);
+    }
+    if stats.has_tcp_conn_rate_limit() {
+        print_rate_limit_quota("tcp conn rate limit", stats.get_tcp_conn_rate_limit()?);
+    }
+    println!("http conn total: {}", stats.get_http_conn_total());
+    println!("socks conn total: {}", stats.get_socks_conn_total());
+    println!("l7 conn alive: {}", stats.get_l7_conn_alive());
+    println!("request total: {}", stats.get_req_total());
+    println!("request alive: {}", stats.get_req_alive());
+    println!("request ready: {}", stats.get_req_ready());
+    println!("conn rate: {:.2}/s", stats.get_conn_rate());
+    println!("request rate: {:.2}/s", stats.get_req_rate());
+    if stats.get_rate_limited() {
+        println!(
+            "rate limited: yes, {} rejected in the last stats interval",
+            stats.get_rate_limited_count()
+        );
+    } else {
+        println!("rate limited: no");
+    }
+    println!(
+        "tcp bytes: in {} out {}",
+        stats.get_tcp_in_bytes(),
+        stats.get_tcp_out_bytes()
+    );
+    println!(
+        "udp bytes: in {} out {}",
+        stats.get_udp_in_bytes(),
+        stats.get_udp_out_bytes()
+    );
+    println!(
+        "upstream bytes: in {} out {}",
+        stats.get_upstream_in_bytes(),
+        stats.get_upstream_out_bytes()
+    );
+    println!("forbidden:");
+    println!("  auth failed: {}", stats.get_forbid_auth_failed());
+    println!("  user expired: {}", stats.get_forbid_user_expired());
+    println!("  user blocked: {}", stats.get_forbid_user_blocked());
+    println!("  fully loaded: {}", stats.get_forbid_fully_loaded());
+    println!("  rate limited: {}", stats.get_forbid_rate_limited());
+    println!("  proto banned: {}", stats.get_forbid_proto_banned());
+    println!("  src blocked: {}", stats.get_forbid_src_blocked());
+    println!("  dest denied: {}", stats.get_forbid_dest_denied());
+    println!("  ip blocked: {}", stats.get_forbid_ip_blocked());
+    println!("  ua blocked: {}", stats.get_forbid_ua_blocked());
+    Ok(())
+}
@@ -87,4 +87,28 @@ impl FtpConnectionProvider<TcpStream, io::Error, ()> for LocalConnectionProvider
             )),
         }
     }
+
+    async fn tls_handshake_control(
+        &mut self,
+        _stream: TcpStream,
+        _upstream: &UpstreamAddr,
+        _user_data: &(),
+    ) -> io::Result<TcpStream> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "tls is not supported",
+        ))
+    }
+
+    async fn tls_handshake_data(
+        &mut self,
+        _stream: TcpStream,
+        _upstream: &UpstreamAddr,
+        _user_data: &(),
+    ) -> io::Result<TcpStream> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "tls is not supported",
+        ))
+    }
 }
@@ -204,6 +204,10 @@ impl BackendConfig for KeylessQuicBackendConfig {
         BACKEND_CONFIG_TYPE
     }
 
+    fn discover(&self) -> Option<&MetricsName> {
+        Some(&self.discover)
+    }
+
     fn diff_action(&self, new: &AnyBackendConfig) -> BackendConfigDiffAction {
         let AnyBackendConfig::KeylessQuic(config) = new else {
             return BackendConfigDiffAction::SpawnNew;
@@ -187,6 +187,10 @@ impl BackendConfig for KeylessTcpBackendConfig {
         BACKEND_CONFIG_TYPE
     }
 
+    fn discover(&self) -> Option<&MetricsName> {
+        Some(&self.discover)
+    }
+
     fn diff_action(&self, new: &AnyBackendConfig) -> BackendConfigDiffAction {
         let AnyBackendConfig::KeylessTcp(config) = new else {
             return BackendConfigDiffAction::SpawnNew;
@@ -46,6 +46,9 @@ pub(crate) trait BackendConfig {
     fn name(&self) -> &MetricsName;
     fn position(&self) -> Option<YamlDocPosition>;
     fn backend_type(&self) -> &'static str;
+    fn discover(&self) -> Option<&MetricsName> {
+        None
+    }
 
     fn diff_action(&self, new: &AnyBackendConfig) -> BackendConfigDiffAction;
 }
@@ -91,6 +94,7 @@ impl AnyBackendConfig {
     impl_transparent0!(name, &MetricsName);
     impl_transparent0!(backend_type, &'static str);
     impl_transparent0!(position, Option<YamlDocPosition>);
+    impl_transparent0!(discover, Option<&MetricsName>);
 
     impl_transparent1!(diff_action, BackendConfigDiffAction, &Self);
 }
@@ -125,6 +125,10 @@ impl BackendConfig for StreamTcpBackendConfig {
         BACKEND_CONFIG_TYPE
     }
 
+    fn discover(&self) -> Option<&MetricsName> {
+        Some(&self.discover)
+    }
+
     fn diff_action(&self, new: &AnyBackendConfig) -> BackendConfigDiffAction {
         let AnyBackendConfig::StreamTcp(new) = new else {
             return BackendConfigDiffAction::SpawnNew;
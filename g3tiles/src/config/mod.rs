@@ -16,9 +16,11 @@
 
 use std::path::Path;
 
-use anyhow::anyhow;
+use anyhow::{anyhow, Context};
 use yaml_rust::{yaml, Yaml};
 
+use g3_daemon::config::{ConfigChecker, ConfigDump, ConfigDumpFormat, ConfigDumpObject};
+
 pub(crate) mod log;
 
 pub(crate) mod backend;
@@ -38,6 +40,73 @@ pub fn load() -> anyhow::Result<&'static Path> {
     Ok(config_file)
 }
 
+/// Check the references between all loaded config objects.
+///
+/// Dependency cycles and missing dependencies between servers are detected when loading,
+/// and all dangling references to backends and discovers will be reported together.
+pub fn check() -> anyhow::Result<()> {
+    let all_server = server::get_all_sorted().context("failed to get all server config")?;
+    let all_backend = backend::get_all();
+    let all_discover = discover::get_all();
+
+    let mut checker = ConfigChecker::default();
+    checker.add_names("backend", all_backend.iter().map(|c| c.name()));
+    checker.add_names("discover", all_discover.iter().map(|c| c.name()));
+
+    for c in &all_backend {
+        if let Some(d) = c.discover() {
+            checker.check_reference("backend", c.name(), c.position(), "discover", d);
+        }
+    }
+
+    for c in &all_server {
+        for b in c.dependent_backend().unwrap_or_default() {
+            checker.check_reference("server", c.name(), c.position(), "backend", &b);
+        }
+    }
+
+    checker.finish()
+}
+
+/// Dump the summary of all loaded server, discover and backend config.
+///
+/// Only names, types, positions and references are included.
+pub fn dump_summary(format: ConfigDumpFormat) -> anyhow::Result<String> {
+    let mut dump = ConfigDump::default();
+
+    for c in server::get_all_sorted()? {
+        let mut o = ConfigDumpObject::new(c.name(), c.server_type(), c.position().as_ref());
+        o.set_names("dependent_server", c.dependent_server().unwrap_or_default());
+        o.set_names(
+            "dependent_backend",
+            c.dependent_backend().unwrap_or_default(),
+        );
+        if let Some(logger) = c.shared_logger() {
+            o.set_str("shared_logger", logger);
+        }
+        dump.add("server", o);
+    }
+
+    let mut all_discover = discover::get_all();
+    all_discover.sort_by(|a, b| a.name().cmp(b.name()));
+    for c in all_discover {
+        let o = ConfigDumpObject::new(c.name(), c.discover_type(), c.position().as_ref());
+        dump.add("discover", o);
+    }
+
+    let mut all_backend = backend::get_all();
+    all_backend.sort_by(|a, b| a.name().cmp(b.name()));
+    for c in all_backend {
+        let mut o = ConfigDumpObject::new(c.name(), c.backend_type(), c.position().as_ref());
+        if let Some(d) = c.discover() {
+            o.set_name("discover", d);
+        }
+        dump.add("backend", o);
+    }
+
+    dump.encode(format)
+}
+
 fn clear_all() {
     server::clear();
     discover::clear();
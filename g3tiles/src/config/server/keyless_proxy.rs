@@ -14,6 +14,7 @@
  * limitations under the License.
  */
 
+use std::collections::BTreeSet;
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -156,4 +157,14 @@ impl ServerConfig for KeylessProxyServerConfig {
 
         ServerConfigDiffAction::ReloadOnlyConfig
     }
+
+    fn dependent_backend(&self) -> Option<BTreeSet<MetricsName>> {
+        if self.backend.is_empty() {
+            None
+        } else {
+            let mut set = BTreeSet::new();
+            set.insert(self.backend.clone());
+            Some(set)
+        }
+    }
 }
@@ -24,7 +24,7 @@ use indexmap::IndexSet;
 use slog::Logger;
 use yaml_rust::{yaml, Yaml};
 
-use g3_daemon::config::sort_nodes_in_dependency_graph;
+use g3_daemon::config::{sort_nodes_in_dependency_graph, ConfigChecker};
 use g3_types::metrics::MetricsName;
 use g3_yaml::{HybridParser, YamlDocPosition};
 
@@ -66,6 +66,9 @@ pub(crate) trait ServerConfig {
     fn dependent_server(&self) -> Option<BTreeSet<MetricsName>> {
         None
     }
+    fn dependent_backend(&self) -> Option<BTreeSet<MetricsName>> {
+        None
+    }
     fn shared_logger(&self) -> Option<&str> {
         None
     }
@@ -126,6 +129,8 @@ impl AnyServerConfig {
     impl_transparent0!(position, Option<YamlDocPosition>);
     impl_transparent0!(server_type, &'static str);
     impl_transparent0!(dependent_server, Option<BTreeSet<MetricsName>>);
+    impl_transparent0!(dependent_backend, Option<BTreeSet<MetricsName>>);
+    impl_transparent0!(shared_logger, Option<&str>);
 
     impl_transparent1!(diff_action, ServerConfigDiffAction, &Self);
 }
@@ -263,6 +268,23 @@ fn check_dependency() -> anyhow::Result<()> {
         all_names.insert(conf.name().clone());
     }
 
+    let mut checker = ConfigChecker::default();
+    checker.add_names("server", all_names.iter());
+    for conf in all_config.iter() {
+        if let Some(names) = conf.dependent_server() {
+            for peer_name in &names {
+                checker.check_reference(
+                    "server",
+                    conf.name(),
+                    conf.position(),
+                    "server",
+                    peer_name,
+                );
+            }
+        }
+    }
+    checker.finish()?;
+
     let edges = get_edges_for_dependency_graph(&all_config, &all_names)?;
 
     if let Err(node_index) = sort_nodes_in_dependency_graph(edges) {
@@ -14,6 +14,7 @@
  * limitations under the License.
  */
 
+use std::collections::BTreeSet;
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -253,4 +254,23 @@ impl ServerConfig for OpensslProxyServerConfig {
 
         ServerConfigDiffAction::ReloadOnlyConfig
     }
+
+    fn dependent_backend(&self) -> Option<BTreeSet<MetricsName>> {
+        let mut set = BTreeSet::new();
+        for host in self.hosts.get_all_values().values() {
+            for protocol in host.backends.protocols() {
+                if let Some(name) = host.backends.get(protocol) {
+                    set.insert(name.clone());
+                }
+            }
+            if let Some(name) = host.backends.get_default() {
+                set.insert(name.clone());
+            }
+        }
+        if set.is_empty() {
+            None
+        } else {
+            Some(set)
+        }
+    }
 }
@@ -15,28 +15,44 @@
  */
 
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+#[cfg(unix)]
+use std::path::PathBuf;
 use std::time::Duration;
 
 use anyhow::{anyhow, Context};
 use tokio::net::UdpSocket;
 
+use g3_io_ext::{MsgQuerySocket, MsgStreamPeer};
 use g3_types::net::SocketBufferConfig;
 
 use super::{CertAgentHandle, QueryRuntime};
 
+const STREAM_MSG_MAX_SIZE: usize = 1 << 20;
+
 #[cfg(feature = "yaml")]
 mod yaml;
 
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum QueryTransport {
+    #[default]
+    Udp,
+    Tcp,
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct CertAgentConfig {
     pub(crate) cache_request_batch_count: usize,
     pub(crate) cache_request_timeout: Duration,
     pub(crate) cache_vanish_wait: Duration,
     pub(crate) query_peer_addr: SocketAddr,
+    pub(crate) query_transport: QueryTransport,
     pub(crate) query_socket_buffer: SocketBufferConfig,
     pub(crate) query_wait_timeout: Duration,
     pub(crate) protective_cache_ttl: u32,
     pub(crate) maximum_cache_ttl: u32,
+    pub(crate) requester: Option<String>,
 }
 
 impl Default for CertAgentConfig {
@@ -46,10 +62,12 @@ impl Default for CertAgentConfig {
             cache_request_timeout: Duration::from_secs(4),
             cache_vanish_wait: Duration::from_secs(300),
             query_peer_addr: SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 2999),
+            query_transport: QueryTransport::default(),
             query_socket_buffer: SocketBufferConfig::default(),
             query_wait_timeout: Duration::from_secs(4),
             protective_cache_ttl: 10,
             maximum_cache_ttl: 300,
+            requester: None,
         }
     }
 }
@@ -71,6 +89,10 @@ impl CertAgentConfig {
         self.query_peer_addr = addr;
     }
 
+    pub fn set_query_transport(&mut self, transport: QueryTransport) {
+        self.query_transport = transport;
+    }
+
     pub fn set_query_socket_buffer(&mut self, config: SocketBufferConfig) {
         self.query_socket_buffer = config;
     }
@@ -87,7 +109,11 @@ impl CertAgentConfig {
         self.maximum_cache_ttl = ttl;
     }
 
-    pub fn spawn_cert_agent(&self) -> anyhow::Result<CertAgentHandle> {
+    pub fn set_requester(&mut self, name: String) {
+        self.requester = Some(name);
+    }
+
+    fn new_udp_socket(&self) -> anyhow::Result<std::net::UdpSocket> {
         let socket = g3_socket::udp::new_std_socket_to(
             self.query_peer_addr,
             &Default::default(),
@@ -101,6 +127,37 @@ impl CertAgentConfig {
                 self.query_peer_addr
             )
         })?;
+        Ok(socket)
+    }
+
+    fn stream_peer(&self) -> Option<MsgStreamPeer> {
+        match &self.query_transport {
+            QueryTransport::Udp => None,
+            QueryTransport::Tcp => Some(MsgStreamPeer::Tcp(self.query_peer_addr)),
+            #[cfg(unix)]
+            QueryTransport::Unix(path) => Some(MsgStreamPeer::Unix(path.clone())),
+        }
+    }
+
+    pub fn spawn_cert_agent(&self) -> anyhow::Result<CertAgentHandle> {
+        let stream_peer = self.stream_peer();
+        let udp_socket = match stream_peer {
+            Some(_) => None,
+            None => Some(self.new_udp_socket()?),
+        };
+        let new_query_socket = move || -> anyhow::Result<MsgQuerySocket> {
+            match udp_socket {
+                Some(socket) => {
+                    let socket =
+                        UdpSocket::from_std(socket).context("failed to setup udp socket")?;
+                    Ok(MsgQuerySocket::new_udp(socket))
+                }
+                None => Ok(MsgQuerySocket::new_stream(
+                    stream_peer.unwrap(),
+                    STREAM_MSG_MAX_SIZE,
+                )),
+            }
+        };
 
         let (cache_runtime, cache_handle, query_handle) =
             g3_io_ext::create_effective_cache(self.cache_request_batch_count);
@@ -108,12 +165,12 @@ impl CertAgentConfig {
         if let Some(rt) = crate::get_cert_generate_rt_handle() {
             let config = self.clone();
             rt.spawn(async move {
-                let socket = UdpSocket::from_std(socket).expect("failed to setup udp socket");
+                let socket = new_query_socket().expect("failed to setup query socket");
                 QueryRuntime::new(&config, socket, query_handle).await
             });
             rt.spawn(cache_runtime);
         } else {
-            let socket = UdpSocket::from_std(socket).context("failed to setup udp socket")?;
+            let socket = new_query_socket()?;
             let query_runtime = QueryRuntime::new(self, socket, query_handle);
             tokio::spawn(query_runtime);
             tokio::spawn(cache_runtime);
@@ -17,7 +17,7 @@
 use anyhow::{anyhow, Context};
 use yaml_rust::Yaml;
 
-use super::CertAgentConfig;
+use super::{CertAgentConfig, QueryTransport};
 
 impl CertAgentConfig {
     fn set_query_peer_addr_by_yaml(&mut self, value: &Yaml) -> anyhow::Result<()> {
@@ -55,6 +55,22 @@ impl CertAgentConfig {
                             .context(format!("invalid sockaddr str value for key {k}"))?;
                         Ok(())
                     }
+                    "query_transport" => {
+                        let transport = g3_yaml::value::as_string(v)?;
+                        match transport.to_lowercase().as_str() {
+                            "udp" => config.set_query_transport(QueryTransport::Udp),
+                            "tcp" => config.set_query_transport(QueryTransport::Tcp),
+                            _ => return Err(anyhow!("unsupported query transport {transport}")),
+                        }
+                        Ok(())
+                    }
+                    #[cfg(unix)]
+                    "query_unix_path" => {
+                        let path = g3_yaml::value::as_absolute_path(v)
+                            .context(format!("invalid absolute path value for key {k}"))?;
+                        config.set_query_transport(QueryTransport::Unix(path));
+                        Ok(())
+                    }
                     "query_socket_buffer" => {
                         let buf_config = g3_yaml::value::as_socket_buffer_config(v)
                             .context(format!("invalid socket buffer config value for key {k}"))?;
@@ -77,6 +93,11 @@ impl CertAgentConfig {
                         config.set_maximum_cache_ttl(ttl);
                         Ok(())
                     }
+                    "requester" => {
+                        let name = g3_yaml::value::as_string(v)?;
+                        config.set_requester(name);
+                        Ok(())
+                    }
                     _ => Err(anyhow!("invalid key {k}")),
                 })?;
 
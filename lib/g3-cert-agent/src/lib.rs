@@ -37,7 +37,7 @@ mod query;
 use query::QueryRuntime;
 
 mod config;
-pub use config::CertAgentConfig;
+pub use config::{CertAgentConfig, QueryTransport};
 
 mod handle;
 pub use handle::CertAgentHandle;
@@ -78,10 +78,10 @@ impl CacheQueryKey {
         self.mimic_cert = Some(cert);
     }
 
-    fn encode(&self) -> Result<Vec<u8>, rmpv::encode::Error> {
+    fn encode(&self, requester: Option<&str>) -> Result<Vec<u8>, rmpv::encode::Error> {
         use rmpv::ValueRef;
 
-        let mut map = Vec::with_capacity(4);
+        let mut map = Vec::with_capacity(5);
         map.push((
             ValueRef::Integer(request_key_id::HOST.into()),
             ValueRef::String(self.host().into()),
@@ -94,6 +94,12 @@ impl CacheQueryKey {
             ValueRef::Integer(request_key_id::USAGE.into()),
             ValueRef::Integer((self.index.usage as u8).into()),
         ));
+        if let Some(requester) = requester {
+            map.push((
+                ValueRef::Integer(request_key_id::REQUESTER.into()),
+                ValueRef::String(requester.into()),
+            ));
+        }
         if let Some(cert) = &self.mimic_cert {
             if let Ok(der) = cert.to_der() {
                 map.push((
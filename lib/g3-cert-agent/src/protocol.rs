@@ -19,6 +19,7 @@ pub mod request_key {
     pub const SERVICE: &str = "service";
     pub const CERT: &str = "cert";
     pub const USAGE: &str = "usage";
+    pub const REQUESTER: &str = "requester";
 }
 
 pub mod request_key_id {
@@ -26,6 +27,7 @@ pub mod request_key_id {
     pub const SERVICE: u64 = 2;
     pub const CERT: u64 = 3;
     pub const USAGE: u64 = 4;
+    pub const REQUESTER: u64 = 5;
 }
 
 pub mod response_key {
@@ -25,14 +25,13 @@ use std::time::Duration;
 use anyhow::anyhow;
 use log::{debug, warn};
 use tokio::io::ReadBuf;
-use tokio::net::UdpSocket;
 
-use g3_io_ext::{EffectiveCacheData, EffectiveQueryHandle};
+use g3_io_ext::{EffectiveCacheData, EffectiveQueryHandle, MsgQuerySocket};
 
 use super::{CacheQueryKey, CertAgentConfig, FakeCertPair, Response};
 
 pub(super) struct QueryRuntime {
-    socket: UdpSocket,
+    socket: MsgQuerySocket,
     query_handle: EffectiveQueryHandle<CacheQueryKey, FakeCertPair>,
     read_buffer: Box<[u8]>,
     write_queue: VecDeque<(Arc<CacheQueryKey>, Vec<u8>)>,
@@ -40,23 +39,26 @@ pub(super) struct QueryRuntime {
     maximum_ttl: u32,
     vanish_wait: Duration,
     query_wait: Duration,
+    requester: Option<String>,
 }
 
 impl QueryRuntime {
     pub(super) fn new(
         config: &CertAgentConfig,
-        socket: UdpSocket,
+        socket: MsgQuerySocket,
         query_handle: EffectiveQueryHandle<CacheQueryKey, FakeCertPair>,
     ) -> Self {
+        let read_buffer = vec![0u8; socket.max_recv_size()].into_boxed_slice();
         QueryRuntime {
             socket,
             query_handle,
-            read_buffer: vec![0u8; 16384].into_boxed_slice(),
+            read_buffer,
             write_queue: VecDeque::new(),
             protective_ttl: config.protective_cache_ttl,
             maximum_ttl: config.maximum_cache_ttl,
             vanish_wait: config.cache_vanish_wait,
             query_wait: config.query_wait_timeout,
+            requester: config.requester.clone(),
         }
     }
 
@@ -70,7 +72,7 @@ impl QueryRuntime {
             .query_handle
             .should_send_raw_query(req.clone(), self.query_wait)
         {
-            match req.encode() {
+            match req.encode(self.requester.as_deref()) {
                 Ok(buf) => self.write_queue.push_back((req, buf)),
                 Err(e) => {
                     warn!("failed to encode cert generate request ro msgpack: {e}");
@@ -137,6 +139,7 @@ impl QueryRuntime {
                     }
                 }
             }
+            self.socket.poll_flush(cx);
 
             // handle timeout
             loop {
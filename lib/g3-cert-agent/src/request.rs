@@ -29,6 +29,7 @@ pub struct Request {
     service: TlsServiceType,
     usage: TlsCertUsage,
     pub(crate) cert: Option<X509>,
+    requester: Option<String>,
 }
 
 impl Default for Request {
@@ -38,6 +39,7 @@ impl Default for Request {
             service: TlsServiceType::Http,
             usage: TlsCertUsage::TlsServer,
             cert: None,
+            requester: None,
         }
     }
 }
@@ -58,6 +60,16 @@ impl Request {
         self.cert.as_ref()
     }
 
+    #[inline]
+    pub fn service(&self) -> TlsServiceType {
+        self.service
+    }
+
+    #[inline]
+    pub fn requester(&self) -> Option<&str> {
+        self.requester.as_deref()
+    }
+
     #[inline]
     pub fn cert_usage(&self) -> TlsCertUsage {
         self.usage
@@ -96,6 +108,12 @@ impl Request {
                         self.cert = Some(cert);
                         Ok(())
                     }
+                    request_key::REQUESTER => {
+                        let requester = g3_msgpack::value::as_string(&v)
+                            .context(format!("invalid string value for key {key}"))?;
+                        self.requester = Some(requester);
+                        Ok(())
+                    }
                     _ => Err(anyhow!("invalid key {key}")),
                 }
             }
@@ -122,6 +140,12 @@ impl Request {
                         self.cert = Some(cert);
                         Ok(())
                     }
+                    request_key_id::REQUESTER => {
+                        let requester = g3_msgpack::value::as_string(&v)
+                            .context(format!("invalid string value for key id {key_id}"))?;
+                        self.requester = Some(requester);
+                        Ok(())
+                    }
                     _ => Err(anyhow!("invalid key id {key_id}")),
                 }
             }
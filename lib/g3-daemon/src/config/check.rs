@@ -0,0 +1,233 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use anyhow::anyhow;
+
+use g3_types::metrics::MetricsName;
+use g3_yaml::{YamlDocPosition, YamlLineLocator};
+
+/// Collect all errors found when checking the loaded config objects, and report them together.
+///
+/// The config objects are identified by their kind, which should be the same as the top level
+/// key in the main config file, and their name. The position will be formatted as `path:line`
+/// if the line can be found.
+pub struct ConfigChecker {
+    main_config: Option<PathBuf>,
+    locators: HashMap<PathBuf, Option<YamlLineLocator>>,
+    names: HashMap<&'static str, HashSet<MetricsName>>,
+    errors: Vec<String>,
+}
+
+impl Default for ConfigChecker {
+    fn default() -> Self {
+        ConfigChecker::new(crate::opts::config_file())
+    }
+}
+
+impl ConfigChecker {
+    pub fn new(main_config: Option<&Path>) -> Self {
+        ConfigChecker {
+            main_config: main_config.map(PathBuf::from),
+            locators: HashMap::new(),
+            names: HashMap::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    fn locator(&mut self, path: &Path) -> Option<&YamlLineLocator> {
+        self.locators
+            .entry(path.to_path_buf())
+            .or_insert_with(|| YamlLineLocator::load(path).ok())
+            .as_ref()
+    }
+
+    /// Get the position string of the config object, which should be used in error messages.
+    pub fn position_str(
+        &mut self,
+        kind: &str,
+        name: &str,
+        position: Option<&YamlDocPosition>,
+    ) -> String {
+        match position {
+            Some(p) => match self.locator(&p.path).and_then(|l| l.doc_line(p.index)) {
+                Some(line) => format!("{}:{line}", p.path.display()),
+                None => p.to_string(),
+            },
+            None => {
+                let Some(main_config) = self.main_config.clone() else {
+                    return "main config file".to_string();
+                };
+                match self
+                    .locator(&main_config)
+                    .and_then(|l| l.named_map_line(kind, name))
+                {
+                    Some(line) => format!("{}:{line}", main_config.display()),
+                    None => main_config.display().to_string(),
+                }
+            }
+        }
+    }
+
+    /// Register all names of the config objects of this kind, so they can be referenced.
+    pub fn add_names<'a, I>(&mut self, kind: &'static str, names: I)
+    where
+        I: IntoIterator<Item = &'a MetricsName>,
+    {
+        self.names
+            .entry(kind)
+            .or_default()
+            .extend(names.into_iter().cloned());
+    }
+
+    pub fn add_error<E: fmt::Display>(
+        &mut self,
+        kind: &str,
+        name: &MetricsName,
+        position: Option<YamlDocPosition>,
+        e: E,
+    ) {
+        let position = self.position_str(kind, name.as_str(), position.as_ref());
+        self.errors
+            .push(format!("{kind} {name} at {position}: {e}"));
+    }
+
+    /// Check that the referenced config object has been registered.
+    ///
+    /// Empty names are treated as not set, and will not be checked.
+    pub fn check_reference(
+        &mut self,
+        kind: &str,
+        name: &MetricsName,
+        position: Option<YamlDocPosition>,
+        ref_kind: &'static str,
+        ref_name: &MetricsName,
+    ) {
+        if ref_name.is_empty() {
+            return;
+        }
+        let existed = self
+            .names
+            .get(ref_kind)
+            .map(|set| set.contains(ref_name))
+            .unwrap_or(false);
+        if !existed {
+            self.add_error(
+                kind,
+                name,
+                position,
+                format_args!("{ref_kind} {ref_name} is not existed"),
+            );
+        }
+    }
+
+    pub fn has_error(&self) -> bool {
+        !self.errors.is_empty()
+    }
+
+    pub fn finish(self) -> anyhow::Result<()> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "{} error(s) found:\n  {}",
+                self.errors.len(),
+                self.errors.join("\n  ")
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::str::FromStr;
+
+    #[test]
+    fn position() {
+        let dir = std::env::temp_dir().join(format!("g3-daemon-check-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let main_conf = dir.join("main.yaml");
+        let mut f = std::fs::File::create(&main_conf).unwrap();
+        f.write_all(b"server:\n  - name: http\n    escaper: default\n")
+            .unwrap();
+        let sub_conf = dir.join("escaper.yaml");
+        let mut f = std::fs::File::create(&sub_conf).unwrap();
+        f.write_all(b"name: a\n---\n\nname: b\n").unwrap();
+
+        let mut checker = ConfigChecker::new(Some(&main_conf));
+        assert_eq!(
+            checker.position_str("server", "http", None),
+            format!("{}:2", main_conf.display())
+        );
+        assert_eq!(
+            checker.position_str("server", "https", None),
+            main_conf.display().to_string()
+        );
+        let p = YamlDocPosition {
+            path: sub_conf.clone(),
+            index: 1,
+        };
+        assert_eq!(
+            checker.position_str("escaper", "b", Some(&p)),
+            format!("{}:4", sub_conf.display())
+        );
+        let p = YamlDocPosition {
+            path: sub_conf.clone(),
+            index: 2,
+        };
+        assert_eq!(
+            checker.position_str("escaper", "c", Some(&p)),
+            format!("{}#2", sub_conf.display())
+        );
+
+        let mut checker = ConfigChecker::new(None);
+        assert_eq!(
+            checker.position_str("server", "http", None),
+            "main config file"
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn reference() {
+        let http = MetricsName::from_str("http").unwrap();
+        let default = MetricsName::from_str("default").unwrap();
+        let other = MetricsName::from_str("other").unwrap();
+        let empty = MetricsName::default();
+
+        let mut checker = ConfigChecker::new(None);
+        checker.add_names("escaper", [&default]);
+        checker.check_reference("server", &http, None, "escaper", &default);
+        checker.check_reference("server", &http, None, "escaper", &empty);
+        checker.check_reference("server", &http, None, "user_group", &default);
+        checker.check_reference("server", &http, None, "escaper", &other);
+        assert!(checker.has_error());
+
+        let e = checker.finish().unwrap_err().to_string();
+        assert_eq!(
+            e,
+            "2 error(s) found:\n  \
+             server http at main config file: user_group default is not existed\n  \
+             server http at main config file: escaper other is not existed"
+        );
+    }
+}
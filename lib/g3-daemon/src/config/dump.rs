@@ -0,0 +1,256 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use serde_json::{Map, Number, Value};
+use yaml_rust::{yaml, Yaml, YamlEmitter};
+
+use g3_types::metrics::MetricsName;
+use g3_yaml::YamlDocPosition;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConfigDumpFormat {
+    Yaml,
+    Json,
+}
+
+impl FromStr for ConfigDumpFormat {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "yaml" | "yml" => Ok(ConfigDumpFormat::Yaml),
+            "json" => Ok(ConfigDumpFormat::Json),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The summary of all loaded config objects.
+///
+/// Objects are grouped by their kind, which should be the same as the top level key
+/// in the main config file.
+#[derive(Default)]
+pub struct ConfigDump {
+    kinds: yaml::Hash,
+}
+
+impl ConfigDump {
+    pub fn add(&mut self, kind: &str, object: ConfigDumpObject) {
+        let entry = self
+            .kinds
+            .entry(Yaml::String(kind.to_string()))
+            .or_insert_with(|| Yaml::Array(Vec::new()));
+        if let Yaml::Array(seq) = entry {
+            seq.push(Yaml::Hash(object.map));
+        }
+    }
+
+    pub fn encode(self, format: ConfigDumpFormat) -> anyhow::Result<String> {
+        let value = Yaml::Hash(self.kinds);
+        match format {
+            ConfigDumpFormat::Yaml => {
+                let mut content = String::with_capacity(4096);
+                YamlEmitter::new(&mut content)
+                    .dump(&value)
+                    .map_err(|e| anyhow!("failed to emit yaml: {e}"))?;
+                Ok(content)
+            }
+            ConfigDumpFormat::Json => {
+                let value = yaml_to_json(&value)?;
+                serde_json::to_string_pretty(&value)
+                    .map_err(|e| anyhow!("failed to emit json: {e}"))
+            }
+        }
+    }
+}
+
+/// The summary of a single config object.
+///
+/// It contains the name, type, position and references of the object, and some selected fields.
+/// Values should be taken from the parsed config, so default values of these fields will also be
+/// included.
+pub struct ConfigDumpObject {
+    map: yaml::Hash,
+}
+
+impl ConfigDumpObject {
+    pub fn new(name: &MetricsName, object_type: &str, position: Option<&YamlDocPosition>) -> Self {
+        let mut object = ConfigDumpObject {
+            map: yaml::Hash::new(),
+        };
+        object.set_str("name", name.as_str());
+        if !object_type.is_empty() {
+            object.set_str("type", object_type);
+        }
+        if let Some(p) = position {
+            object.set_str("position", p.to_string());
+        }
+        object
+    }
+
+    fn set(&mut self, key: &str, value: Yaml) {
+        self.map.insert(Yaml::String(key.to_string()), value);
+    }
+
+    pub fn set_str<T: ToString>(&mut self, key: &str, value: T) {
+        self.set(key, Yaml::String(value.to_string()));
+    }
+
+    pub fn set_bool(&mut self, key: &str, value: bool) {
+        self.set(key, Yaml::Boolean(value));
+    }
+
+    pub fn set_int<T: Into<i64>>(&mut self, key: &str, value: T) {
+        self.set(key, Yaml::Integer(value.into()));
+    }
+
+    pub fn set_usize(&mut self, key: &str, value: usize) {
+        self.set(key, Yaml::Integer(i64::try_from(value).unwrap_or(i64::MAX)));
+    }
+
+    pub fn set_duration(&mut self, key: &str, value: Duration) {
+        self.set_str(key, format!("{value:?}"));
+    }
+
+    /// Set the name of the referenced config object, empty names will be skipped
+    pub fn set_name(&mut self, key: &str, value: &MetricsName) {
+        if !value.is_empty() {
+            self.set_str(key, value);
+        }
+    }
+
+    /// Set the names of the referenced config objects, empty sets will be skipped
+    pub fn set_names<I, T>(&mut self, key: &str, values: I)
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<str>,
+    {
+        let seq: Vec<Yaml> = values
+            .into_iter()
+            .map(|v| Yaml::String(v.as_ref().to_string()))
+            .collect();
+        if !seq.is_empty() {
+            self.set(key, Yaml::Array(seq));
+        }
+    }
+}
+
+fn yaml_key_to_string(key: &Yaml) -> anyhow::Result<String> {
+    match key {
+        Yaml::String(s) | Yaml::Real(s) => Ok(s.clone()),
+        Yaml::Integer(i) => Ok(i.to_string()),
+        Yaml::Boolean(b) => Ok(b.to_string()),
+        Yaml::Null => Ok("null".to_string()),
+        _ => Err(anyhow!("unsupported map key {key:?}")),
+    }
+}
+
+fn yaml_to_json(value: &Yaml) -> anyhow::Result<Value> {
+    match value {
+        Yaml::Real(s) => {
+            let f = f64::from_str(s).map_err(|e| anyhow!("invalid real value {s}: {e}"))?;
+            Ok(Number::from_f64(f)
+                .map(Value::Number)
+                .unwrap_or(Value::Null))
+        }
+        Yaml::Integer(i) => Ok(Value::Number(Number::from(*i))),
+        Yaml::String(s) => Ok(Value::String(s.clone())),
+        Yaml::Boolean(b) => Ok(Value::Bool(*b)),
+        Yaml::Array(seq) => {
+            let mut v = Vec::with_capacity(seq.len());
+            for node in seq {
+                v.push(yaml_to_json(node)?);
+            }
+            Ok(Value::Array(v))
+        }
+        Yaml::Hash(map) => {
+            let mut m = Map::with_capacity(map.len());
+            for (k, v) in map.iter() {
+                m.insert(yaml_key_to_string(k)?, yaml_to_json(v)?);
+            }
+            Ok(Value::Object(m))
+        }
+        Yaml::Null => Ok(Value::Null),
+        Yaml::Alias(_) | Yaml::BadValue => Err(anyhow!("unsupported yaml value {value:?}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yaml_rust::YamlLoader;
+
+    #[test]
+    fn to_json() {
+        let docs = YamlLoader::load_from_str("a: 1\nb: [true, 1.5, x]\nc: ~\n").unwrap();
+        let v = yaml_to_json(&docs[0]).unwrap();
+        assert_eq!(
+            v,
+            serde_json::json!({"a": 1, "b": [true, 1.5, "x"], "c": null})
+        );
+    }
+
+    #[test]
+    fn dump_objects() {
+        let default = MetricsName::from_str("default").unwrap();
+        let http = MetricsName::from_str("http").unwrap();
+        let position = YamlDocPosition {
+            path: "/etc/g3proxy/server.d/http.yaml".into(),
+            index: 1,
+        };
+
+        let mut dump = ConfigDump::default();
+        let mut escaper = ConfigDumpObject::new(&default, "direct_fixed", None);
+        escaper.set_name("resolver", &MetricsName::default());
+        dump.add("escaper", escaper);
+        let mut server = ConfigDumpObject::new(&http, "http_proxy", Some(&position));
+        server.set_name("escaper", &default);
+        server.set_names("dependent_server", Vec::<MetricsName>::new());
+        server.set_duration("task_idle_check_duration", Duration::from_secs(300));
+        server.set_int("task_max_idle_count", 1);
+        server.set_bool("enable_tls", false);
+        dump.add("server", server);
+
+        let json = dump.encode(ConfigDumpFormat::Json).unwrap();
+        let v: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            v,
+            serde_json::json!({
+                "escaper": [{"name": "default", "type": "direct_fixed"}],
+                "server": [{
+                    "name": "http",
+                    "type": "http_proxy",
+                    "position": "/etc/g3proxy/server.d/http.yaml#1",
+                    "escaper": "default",
+                    "task_idle_check_duration": "300s",
+                    "task_max_idle_count": 1,
+                    "enable_tls": false,
+                }],
+            })
+        );
+
+        let mut dump = ConfigDump::default();
+        dump.add("store", ConfigDumpObject::new(&default, "local", None));
+        let yaml = dump.encode(ConfigDumpFormat::Yaml).unwrap();
+        let docs = YamlLoader::load_from_str(&yaml).unwrap();
+        assert_eq!(docs[0]["store"][0]["name"].as_str(), Some("default"));
+        assert_eq!(docs[0]["store"][0]["type"].as_str(), Some("local"));
+    }
+}
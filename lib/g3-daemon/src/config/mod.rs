@@ -14,8 +14,14 @@
  * limitations under the License.
  */
 
+mod check;
+pub use check::ConfigChecker;
+
 mod dependency;
 pub use dependency::sort_nodes_in_dependency_graph;
 
+mod dump;
+pub use dump::{ConfigDump, ConfigDumpFormat, ConfigDumpObject};
+
 mod lookup;
 pub use lookup::get_lookup_dir;
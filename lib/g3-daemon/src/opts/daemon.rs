@@ -15,14 +15,20 @@
  */
 
 use std::path::PathBuf;
+use std::str::FromStr;
 
+use anyhow::anyhow;
 use clap::{value_parser, Arg, ArgAction, ArgMatches, Command, ValueHint};
 
+use crate::config::ConfigDumpFormat;
+
 const ARGS_VERBOSE: &str = "verbose";
 const ARGS_DAEMON: &str = "daemon";
 const ARGS_SYSTEMD: &str = "systemd";
 const ARGS_PID_FILE: &str = "pid-file";
 const ARGS_TEST_CONFIG: &str = "test-config";
+const ARGS_CHECK_CONFIG: &str = "check";
+const ARGS_DUMP_CONFIG_SUMMARY: &str = "dump-config-summary";
 
 pub trait DaemonArgsExt {
     fn append_daemon_args(self) -> Self;
@@ -36,6 +42,8 @@ pub struct DaemonArgs {
     pub process_name: &'static str,
     pub pid_file: Option<PathBuf>,
     pub test_config: bool,
+    pub check_config: bool,
+    pub dump_config_summary: Option<ConfigDumpFormat>,
 }
 
 impl DaemonArgs {
@@ -47,6 +55,8 @@ impl DaemonArgs {
             process_name,
             pid_file: None,
             test_config: false,
+            check_config: false,
+            dump_config_summary: None,
         }
     }
 
@@ -81,6 +91,14 @@ impl DaemonArgs {
         if args.get_flag(ARGS_TEST_CONFIG) {
             self.test_config = true;
         }
+        if args.get_flag(ARGS_CHECK_CONFIG) {
+            self.check_config = true;
+        }
+        if let Some(format) = args.get_one::<String>(ARGS_DUMP_CONFIG_SUMMARY) {
+            let format = ConfigDumpFormat::from_str(format)
+                .map_err(|_| anyhow!("unsupported config dump format {format}"))?;
+            self.dump_config_summary = Some(format);
+        }
         if args.get_flag(ARGS_DAEMON) {
             self.enable_daemon_mode();
         }
@@ -136,5 +154,20 @@ impl DaemonArgsExt for Command {
                 .short('t')
                 .long("test-config"),
         )
+        .arg(
+            Arg::new(ARGS_CHECK_CONFIG)
+                .help("Check the config file, including references between config objects and TLS certificates, and exit")
+                .action(ArgAction::SetTrue)
+                .long("check"),
+        )
+        .arg(
+            Arg::new(ARGS_DUMP_CONFIG_SUMMARY)
+                .help("Dump the summary of all loaded config objects, including names, types, positions and references, and exit")
+                .value_name("FORMAT")
+                .num_args(0..=1)
+                .value_parser(["yaml", "json"])
+                .default_missing_value("yaml")
+                .long("dump-config-summary"),
+        )
     }
 }
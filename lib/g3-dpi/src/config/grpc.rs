@@ -0,0 +1,145 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use g3_types::acl::AclAction;
+
+/// ACL rule for gRPC calls, matched by service or by service/method
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GrpcMethodAcl {
+    services: BTreeMap<String, AclAction>,
+    methods: BTreeMap<String, AclAction>,
+    missed_action: AclAction,
+}
+
+impl Default for GrpcMethodAcl {
+    fn default() -> Self {
+        GrpcMethodAcl::new(AclAction::Permit)
+    }
+}
+
+impl GrpcMethodAcl {
+    pub fn new(missed_action: AclAction) -> Self {
+        GrpcMethodAcl {
+            services: BTreeMap::new(),
+            methods: BTreeMap::new(),
+            missed_action,
+        }
+    }
+
+    #[inline]
+    pub fn missed_action(&self) -> AclAction {
+        self.missed_action
+    }
+
+    #[inline]
+    pub fn set_missed_action(&mut self, action: AclAction) {
+        self.missed_action = action;
+    }
+
+    pub fn add_service(&mut self, service: &str, action: AclAction) {
+        self.services.insert(service.to_string(), action);
+    }
+
+    pub fn add_method(&mut self, service: &str, method: &str, action: AclAction) {
+        self.methods.insert(format!("{service}/{method}"), action);
+    }
+
+    /// Add a rule in `service` or `service/method` form, the leading '/' is optional
+    pub fn add_rule(&mut self, rule: &str, action: AclAction) -> bool {
+        let rule = rule.strip_prefix('/').unwrap_or(rule);
+        match rule.split_once('/') {
+            Some((service, method)) => {
+                if service.is_empty() || method.is_empty() || method.contains('/') {
+                    return false;
+                }
+                self.add_method(service, method, action);
+            }
+            None => {
+                if rule.is_empty() {
+                    return false;
+                }
+                self.add_service(rule, action);
+            }
+        }
+        true
+    }
+
+    pub fn check(&self, service: &str, method: &str) -> (bool, AclAction) {
+        if !self.methods.is_empty() {
+            let full = format!("{service}/{method}");
+            if let Some(action) = self.methods.get(&full) {
+                return (true, *action);
+            }
+        }
+        if let Some(action) = self.services.get(service) {
+            return (true, *action);
+        }
+        (false, self.missed_action)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GrpcInterceptionConfig {
+    pub method_acl: Option<GrpcMethodAcl>,
+    pub icap_forward_messages: bool,
+    pub icap_message_max_size: usize,
+    pub icap_message_max_count: usize,
+    pub icap_audit_timeout: Duration,
+}
+
+impl Default for GrpcInterceptionConfig {
+    fn default() -> Self {
+        GrpcInterceptionConfig {
+            method_acl: None,
+            icap_forward_messages: false,
+            icap_message_max_size: 64 * 1024, // 64KB
+            icap_message_max_count: 16,
+            icap_audit_timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn method_acl() {
+        let mut acl = GrpcMethodAcl::new(AclAction::Permit);
+        assert!(acl.add_rule("admin.Manager", AclAction::Forbid));
+        assert!(acl.add_rule("/admin.Manager/GetStatus", AclAction::Permit));
+        assert!(acl.add_rule("user.Account/Delete", AclAction::ForbidAndLog));
+        assert!(!acl.add_rule("/", AclAction::Forbid));
+        assert!(!acl.add_rule("a/b/c", AclAction::Forbid));
+
+        assert_eq!(
+            acl.check("admin.Manager", "Restart"),
+            (true, AclAction::Forbid)
+        );
+        assert_eq!(
+            acl.check("admin.Manager", "GetStatus"),
+            (true, AclAction::Permit)
+        );
+        assert_eq!(
+            acl.check("user.Account", "Delete"),
+            (true, AclAction::ForbidAndLog)
+        );
+        assert_eq!(acl.check("user.Account", "Get"), (false, AclAction::Permit));
+    }
+}
@@ -37,6 +37,9 @@ pub use smtp::SmtpInterceptionConfig;
 mod imap;
 pub use imap::ImapInterceptionConfig;
 
+mod grpc;
+pub use grpc::{GrpcInterceptionConfig, GrpcMethodAcl};
+
 #[derive(Clone)]
 pub struct ProtocolInspectPolicyBuilder {
     missed_action: ProtocolInspectAction,
@@ -25,9 +25,10 @@ pub use protocol::{
 
 mod config;
 pub use config::{
-    H1InterceptionConfig, H2InterceptionConfig, ImapInterceptionConfig, ProtocolInspectAction,
-    ProtocolInspectPolicy, ProtocolInspectPolicyBuilder, ProtocolInspectionConfig,
-    ProtocolInspectionSizeLimit, SmtpInterceptionConfig,
+    GrpcInterceptionConfig, GrpcMethodAcl, H1InterceptionConfig, H2InterceptionConfig,
+    ImapInterceptionConfig, ProtocolInspectAction, ProtocolInspectPolicy,
+    ProtocolInspectPolicyBuilder, ProtocolInspectionConfig, ProtocolInspectionSizeLimit,
+    SmtpInterceptionConfig,
 };
 
 pub mod parser;
@@ -0,0 +1,276 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use bytes::{Bytes, BytesMut};
+
+const MESSAGE_HEADER_LEN: usize = 5;
+
+/// Check if the Content-Type header value is a gRPC one
+pub fn is_grpc_content_type(value: &str) -> bool {
+    let media_type = value.split(';').next().unwrap_or_default().trim();
+    let Some(sub) = media_type
+        .get(..16)
+        .filter(|s| s.eq_ignore_ascii_case("application/grpc"))
+        .map(|_| &media_type[16..])
+    else {
+        return false;
+    };
+    // application/grpc, application/grpc+proto, application/grpc+json etc.
+    // but not application/grpc-web
+    sub.is_empty() || sub.starts_with('+')
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GrpcCallPath<'a> {
+    pub service: &'a str,
+    pub method: &'a str,
+}
+
+impl<'a> GrpcCallPath<'a> {
+    /// Parse the `/{service}/{method}` style request path
+    pub fn parse(path: &'a str) -> Option<Self> {
+        let path = path.strip_prefix('/')?;
+        let (service, method) = path.split_once('/')?;
+        if service.is_empty() || method.is_empty() || method.contains('/') {
+            return None;
+        }
+        Some(GrpcCallPath { service, method })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GrpcMessage {
+    pub compressed: bool,
+    pub data: Bytes,
+}
+
+/// A parser for the Length-Prefixed-Message framing used in gRPC bodies.
+///
+/// The data is fed in as it is forwarded, and complete messages will be captured
+/// if they are not larger than the capture size limit.
+pub struct GrpcMessageParser {
+    capture_max_size: usize,
+    capture_max_count: usize,
+    header: [u8; MESSAGE_HEADER_LEN],
+    header_len: usize,
+    remaining: usize,
+    compressed: bool,
+    capture_buf: Option<BytesMut>,
+    captured: Vec<GrpcMessage>,
+    message_count: u64,
+    message_bytes: u64,
+    max_message_size: usize,
+    malformed: bool,
+}
+
+impl Default for GrpcMessageParser {
+    fn default() -> Self {
+        GrpcMessageParser::new(0, 0)
+    }
+}
+
+impl GrpcMessageParser {
+    pub fn new(capture_max_size: usize, capture_max_count: usize) -> Self {
+        GrpcMessageParser {
+            capture_max_size,
+            capture_max_count,
+            header: [0u8; MESSAGE_HEADER_LEN],
+            header_len: 0,
+            remaining: 0,
+            compressed: false,
+            capture_buf: None,
+            captured: Vec::new(),
+            message_count: 0,
+            message_bytes: 0,
+            max_message_size: 0,
+            malformed: false,
+        }
+    }
+
+    /// count of the completed messages
+    #[inline]
+    pub fn message_count(&self) -> u64 {
+        self.message_count
+    }
+
+    /// total size of the completed messages, without the prefix header
+    #[inline]
+    pub fn message_bytes(&self) -> u64 {
+        self.message_bytes
+    }
+
+    #[inline]
+    pub fn max_message_size(&self) -> usize {
+        self.max_message_size
+    }
+
+    /// whether any invalid message prefix has been found
+    #[inline]
+    pub fn malformed(&self) -> bool {
+        self.malformed
+    }
+
+    /// whether the data fed in ends at a message boundary
+    pub fn is_complete(&self) -> bool {
+        !self.malformed && self.header_len == 0 && self.remaining == 0
+    }
+
+    pub fn take_captured(&mut self) -> Vec<GrpcMessage> {
+        std::mem::take(&mut self.captured)
+    }
+
+    pub fn feed(&mut self, mut data: &[u8]) {
+        if self.malformed {
+            return;
+        }
+
+        while !data.is_empty() {
+            if self.remaining == 0 && self.header_len < MESSAGE_HEADER_LEN {
+                let to_copy = (MESSAGE_HEADER_LEN - self.header_len).min(data.len());
+                self.header[self.header_len..self.header_len + to_copy]
+                    .copy_from_slice(&data[..to_copy]);
+                self.header_len += to_copy;
+                data = &data[to_copy..];
+                if self.header_len < MESSAGE_HEADER_LEN {
+                    return;
+                }
+
+                if !self.start_message() {
+                    return;
+                }
+                continue;
+            }
+
+            let to_consume = self.remaining.min(data.len());
+            if let Some(buf) = &mut self.capture_buf {
+                buf.extend_from_slice(&data[..to_consume]);
+            }
+            self.remaining -= to_consume;
+            data = &data[to_consume..];
+            if self.remaining == 0 {
+                self.finish_message();
+            }
+        }
+    }
+
+    fn start_message(&mut self) -> bool {
+        self.compressed = match self.header[0] {
+            0 => false,
+            1 => true,
+            _ => {
+                self.malformed = true;
+                return false;
+            }
+        };
+        let size = self.message_size();
+        if self.captured.len() < self.capture_max_count && size <= self.capture_max_size {
+            self.capture_buf = Some(BytesMut::with_capacity(size));
+        }
+        self.remaining = size;
+        if size == 0 {
+            self.finish_message();
+        }
+        true
+    }
+
+    fn finish_message(&mut self) {
+        let size = self.message_size();
+        self.message_count += 1;
+        self.message_bytes += size as u64;
+        self.max_message_size = self.max_message_size.max(size);
+        if let Some(buf) = self.capture_buf.take() {
+            self.captured.push(GrpcMessage {
+                compressed: self.compressed,
+                data: buf.freeze(),
+            });
+        }
+        self.header_len = 0;
+    }
+
+    fn message_size(&self) -> usize {
+        u32::from_be_bytes([
+            self.header[1],
+            self.header[2],
+            self.header[3],
+            self.header[4],
+        ]) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn content_type() {
+        assert!(is_grpc_content_type("application/grpc"));
+        assert!(is_grpc_content_type("application/grpc+proto"));
+        assert!(is_grpc_content_type("Application/gRPC+json; charset=utf-8"));
+        assert!(!is_grpc_content_type("application/grpc-web"));
+        assert!(!is_grpc_content_type("application/json"));
+        assert!(!is_grpc_content_type("application/grp"));
+    }
+
+    #[test]
+    fn call_path() {
+        let path = GrpcCallPath::parse("/helloworld.Greeter/SayHello").unwrap();
+        assert_eq!(path.service, "helloworld.Greeter");
+        assert_eq!(path.method, "SayHello");
+
+        assert!(GrpcCallPath::parse("/helloworld.Greeter").is_none());
+        assert!(GrpcCallPath::parse("/helloworld.Greeter/").is_none());
+        assert!(GrpcCallPath::parse("/a/b/c").is_none());
+        assert!(GrpcCallPath::parse("a/b").is_none());
+    }
+
+    #[test]
+    fn fragmented_messages() {
+        let data: &[u8] = &[
+            0x00, 0x00, 0x00, 0x00, 0x03, b'a', b'b', b'c', // first
+            0x00, 0x00, 0x00, 0x00, 0x00, // empty
+            0x01, 0x00, 0x00, 0x00, 0x02, b'x', b'y', // compressed
+        ];
+
+        let mut parser = GrpcMessageParser::new(16, 2);
+        for chunk in data.chunks(3) {
+            parser.feed(chunk);
+        }
+        assert!(parser.is_complete());
+        assert_eq!(parser.message_count(), 3);
+        assert_eq!(parser.message_bytes(), 5);
+        assert_eq!(parser.max_message_size(), 3);
+
+        let captured = parser.take_captured();
+        assert_eq!(captured.len(), 2);
+        assert!(!captured[0].compressed);
+        assert_eq!(captured[0].data.as_ref(), b"abc");
+        assert!(captured[1].data.is_empty());
+    }
+
+    #[test]
+    fn partial_and_malformed() {
+        let mut parser = GrpcMessageParser::default();
+        parser.feed(&[0x00, 0x00, 0x00, 0x00, 0x04, b'a']);
+        assert!(!parser.is_complete());
+        assert_eq!(parser.message_count(), 0);
+        assert!(parser.take_captured().is_empty());
+
+        let mut parser = GrpcMessageParser::default();
+        parser.feed(&[0x02, 0x00, 0x00, 0x00, 0x01, b'a']);
+        assert!(parser.malformed());
+        assert!(!parser.is_complete());
+    }
+}
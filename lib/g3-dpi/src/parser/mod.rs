@@ -16,5 +16,7 @@
 
 pub mod tls;
 
+pub mod grpc;
+
 #[cfg(feature = "quic")]
 pub mod quic;
@@ -32,7 +32,7 @@ use crate::error::{
 use crate::transfer::{FtpLineDataReceiver, FtpLineDataTransfer, FtpTransferType};
 use crate::{
     log_msg, FtpClientConfig, FtpConnectionProvider, FtpControlChannel, FtpFileFacts,
-    FtpServerFeature,
+    FtpServerFeature, FtpTlsMode,
 };
 
 pub struct FtpClient<CP, S, E, UD>
@@ -47,6 +47,7 @@ where
     control: FtpControlChannel<S>,
     server_feature: FtpServerFeature,
     transfer_type: FtpTransferType,
+    data_protected: bool,
     _phantom_e: PhantomData<E>,
     _phantom_ud: PhantomData<UD>,
 }
@@ -64,11 +65,51 @@ where
 
     pub async fn connect_to(
         server: UpstreamAddr,
+        conn_provider: CP,
+        user_data: &UD,
+        config: &Arc<FtpClientConfig>,
+    ) -> Result<Self, (FtpConnectError<E>, CP)> {
+        Self::connect(server, None, conn_provider, user_data, config).await
+    }
+
+    /// connect to a FTPS server, both the control and the data channels will be protected
+    pub async fn connect_to_tls(
+        server: UpstreamAddr,
+        tls_mode: FtpTlsMode,
+        conn_provider: CP,
+        user_data: &UD,
+        config: &Arc<FtpClientConfig>,
+    ) -> Result<Self, (FtpConnectError<E>, CP)> {
+        Self::connect(server, Some(tls_mode), conn_provider, user_data, config).await
+    }
+
+    async fn tls_handshake_control(
+        server: &UpstreamAddr,
+        stream: S,
+        conn_provider: &mut CP,
+        user_data: &UD,
+        config: &FtpClientConfig,
+    ) -> Result<S, FtpConnectError<E>> {
+        match tokio::time::timeout(
+            config.connect_timeout,
+            conn_provider.tls_handshake_control(stream, server, user_data),
+        )
+        .await
+        {
+            Ok(Ok(stream)) => Ok(stream),
+            Ok(Err(e)) => Err(FtpConnectError::TlsHandshakeFailed(e)),
+            Err(_) => Err(FtpConnectError::TlsHandshakeTimedOut),
+        }
+    }
+
+    async fn connect(
+        server: UpstreamAddr,
+        tls_mode: Option<FtpTlsMode>,
         mut conn_provider: CP,
         user_data: &UD,
         config: &Arc<FtpClientConfig>,
     ) -> Result<Self, (FtpConnectError<E>, CP)> {
-        let control_stream = match tokio::time::timeout(
+        let mut control_stream = match tokio::time::timeout(
             config.connect_timeout,
             conn_provider.new_control_connection(&server, user_data),
         )
@@ -83,6 +124,21 @@ where
             }
         };
 
+        if matches!(tls_mode, Some(FtpTlsMode::Implicit)) {
+            control_stream = match Self::tls_handshake_control(
+                &server,
+                control_stream,
+                &mut conn_provider,
+                user_data,
+                config,
+            )
+            .await
+            {
+                Ok(stream) => stream,
+                Err(e) => return Err((e, conn_provider)),
+            };
+        }
+
         let mut control = FtpControlChannel::new(control_stream, config.control);
         match tokio::time::timeout(config.greeting_timeout, control.wait_greetings()).await {
             Ok(Ok(_)) => {}
@@ -97,6 +153,44 @@ where
             }
         }
 
+        if let Some(tls_mode) = tls_mode {
+            if matches!(tls_mode, FtpTlsMode::Explicit) {
+                match control.request_auth_tls().await {
+                    Ok(_) => {}
+                    Err(FtpCommandError::ServiceNotAvailable) => {
+                        return Err((FtpConnectError::ServiceNotAvailable, conn_provider));
+                    }
+                    Err(e) => {
+                        return Err((FtpConnectError::NegotiationFailed(e), conn_provider));
+                    }
+                }
+
+                let control_stream = match Self::tls_handshake_control(
+                    &server,
+                    control.into_inner(),
+                    &mut conn_provider,
+                    user_data,
+                    config,
+                )
+                .await
+                {
+                    Ok(stream) => stream,
+                    Err(e) => return Err((e, conn_provider)),
+                };
+                control = FtpControlChannel::new(control_stream, config.control);
+            }
+
+            match control.request_private_data_protection().await {
+                Ok(_) => {}
+                Err(FtpCommandError::ServiceNotAvailable) => {
+                    return Err((FtpConnectError::ServiceNotAvailable, conn_provider));
+                }
+                Err(e) => {
+                    return Err((FtpConnectError::NegotiationFailed(e), conn_provider));
+                }
+            }
+        }
+
         let server_feature = match control.check_server_feature().await {
             Ok(feature) => feature,
             Err(FtpCommandError::ServiceNotAvailable) => {
@@ -118,6 +212,7 @@ where
             control,
             server_feature,
             transfer_type: FtpTransferType::Ascii,
+            data_protected: tls_mode.is_some(),
             _phantom_e: Default::default(),
             _phantom_ud: Default::default(),
         })
@@ -322,6 +417,30 @@ where
         Err(FtpTransferSetupError::NeedActiveDataTransfer)
     }
 
+    /// the TLS handshake on the data connection should be done after the transfer command is sent,
+    /// as the server may only start the handshake when it's ready to transfer
+    async fn protect_data_transfer<'a>(
+        &'a mut self,
+        data_stream: S,
+        user_data: &'a UD,
+    ) -> Result<S, FtpTransferSetupError> {
+        if !self.data_protected {
+            return Ok(data_stream);
+        }
+
+        match tokio::time::timeout(
+            self.config.connect_timeout,
+            self.conn_provider
+                .tls_handshake_data(data_stream, &self.server, user_data),
+        )
+        .await
+        {
+            Ok(Ok(stream)) => Ok(stream),
+            Ok(Err(_)) => Err(FtpTransferSetupError::DataTransferTlsHandshakeFailed),
+            Err(_) => Err(FtpTransferSetupError::DataTransferTlsHandshakeTimeout),
+        }
+    }
+
     pub async fn abort_transfer(&mut self) -> Result<(), FtpCommandError> {
         self.control.abort_transfer().await
     }
@@ -345,6 +464,7 @@ where
         let data_stream = self.new_data_transfer(user_data).await?;
 
         self.control.start_list(path).await?;
+        let data_stream = self.protect_data_transfer(data_stream, user_data).await?;
         Ok(data_stream)
     }
 
@@ -425,6 +545,7 @@ where
         }
 
         self.control.start_retrieve(path).await?;
+        let data_stream = self.protect_data_transfer(data_stream, user_data).await?;
         Ok((data_stream, file_transfer_size))
     }
 
@@ -447,6 +568,7 @@ where
         let data_stream = self.new_data_transfer(user_data).await?;
 
         self.control.start_store(path).await?;
+        let data_stream = self.protect_data_transfer(data_stream, user_data).await?;
         Ok(data_stream)
     }
 
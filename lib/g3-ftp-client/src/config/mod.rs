@@ -21,6 +21,14 @@ mod yaml;
 
 const MAXIMUM_LIST_ALL_TIMEOUT: Duration = Duration::from_secs(300);
 
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FtpTlsMode {
+    /// TLS handshake right after the control connection is established
+    Implicit,
+    /// upgrade the control connection by using the AUTH TLS command
+    Explicit,
+}
+
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub struct FtpClientConfig {
     pub control: FtpControlConfig,
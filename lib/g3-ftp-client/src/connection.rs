@@ -33,4 +33,23 @@ pub trait FtpConnectionProvider<T: AsyncRead + AsyncWrite, E: Error, UD> {
         server_addr: &UpstreamAddr,
         user_data: &UD,
     ) -> Result<T, E>;
+
+    /// do TLS handshake on the control connection
+    async fn tls_handshake_control(
+        &mut self,
+        stream: T,
+        upstream: &UpstreamAddr,
+        user_data: &UD,
+    ) -> Result<T, E>;
+
+    /// do TLS handshake on the data connection
+    ///
+    /// The TLS session of the control connection should be reused,
+    /// as it may be required by the server.
+    async fn tls_handshake_data(
+        &mut self,
+        stream: T,
+        upstream: &UpstreamAddr,
+        user_data: &UD,
+    ) -> Result<T, E>;
 }
@@ -54,6 +54,9 @@ ftp_commands! {
     (SPDT, "SPDT");
     (FEAT, "FEAT");
     (OPTS_UTF8_ON, "OPTS UTF8 ON");
+    (AUTH_TLS, "AUTH TLS");
+    (PBSZ_0, "PBSZ 0");
+    (PROT_P, "PROT P");
     (USER, "USER");
     (PASS, "PASS");
     (QUIT, "QUIT");
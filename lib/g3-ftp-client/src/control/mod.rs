@@ -56,6 +56,10 @@ where
         }
     }
 
+    pub(crate) fn into_inner(self) -> T {
+        self.stream.into_inner()
+    }
+
     pub(crate) async fn wait_read_ready(&mut self) -> Result<(), FtpRawResponseError> {
         match self.stream.fill_wait_data().await {
             Ok(true) => Ok(()),
@@ -126,6 +130,51 @@ where
         Ok(feature)
     }
 
+    pub(crate) async fn request_auth_tls(&mut self) -> Result<(), FtpCommandError> {
+        let cmd = FtpCommand::AUTH_TLS;
+        self.send_cmd(cmd)
+            .await
+            .map_err(FtpCommandError::SendFailed)?;
+
+        let reply = self.timed_read_raw_response("request auth tls").await?;
+        match reply.code() {
+            500 | 501 => Err(FtpCommandError::RejectedCommandSyntax(cmd)),
+            502 => Err(FtpCommandError::CommandNotImplemented(cmd)),
+            504 => Err(FtpCommandError::ParameterNotImplemented(cmd)),
+            234 => Ok(()),
+            421 => Err(FtpCommandError::ServiceNotAvailable),
+            n => Err(FtpCommandError::UnexpectedReplyCode(cmd, n)),
+        }
+    }
+
+    async fn send_data_protection_cmd(
+        &mut self,
+        cmd: FtpCommand,
+        stage: &'static str,
+    ) -> Result<(), FtpCommandError> {
+        self.send_cmd(cmd)
+            .await
+            .map_err(FtpCommandError::SendFailed)?;
+
+        let reply = self.timed_read_raw_response(stage).await?;
+        match reply.code() {
+            500 | 501 => Err(FtpCommandError::RejectedCommandSyntax(cmd)),
+            502 => Err(FtpCommandError::CommandNotImplemented(cmd)),
+            503 => Err(FtpCommandError::BadCommandSequence(cmd)),
+            504 | 536 => Err(FtpCommandError::ParameterNotImplemented(cmd)),
+            200 => Ok(()),
+            421 => Err(FtpCommandError::ServiceNotAvailable),
+            n => Err(FtpCommandError::UnexpectedReplyCode(cmd, n)),
+        }
+    }
+
+    pub(crate) async fn request_private_data_protection(&mut self) -> Result<(), FtpCommandError> {
+        self.send_data_protection_cmd(FtpCommand::PBSZ_0, "set protection buffer size")
+            .await?;
+        self.send_data_protection_cmd(FtpCommand::PROT_P, "set data channel protection level")
+            .await
+    }
+
     pub(crate) async fn set_use_utf8(&mut self) -> Result<bool, FtpCommandError> {
         let cmd = FtpCommand::OPTS_UTF8_ON;
         self.send_cmd(cmd)
@@ -24,6 +24,10 @@ pub enum FtpConnectError<E: std::error::Error> {
     ConnectIoError(E),
     #[error("timed out to connect")]
     ConnectTimedOut,
+    #[error("tls handshake failed: {0:?}")]
+    TlsHandshakeFailed(E),
+    #[error("timed out to do tls handshake")]
+    TlsHandshakeTimedOut,
     #[error("timed out to receive greetings")]
     GreetingTimedOut,
     #[error("greeting failed: {0}")]
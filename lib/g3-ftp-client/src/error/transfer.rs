@@ -33,6 +33,10 @@ pub enum FtpTransferSetupError {
     DataTransferNotConnected,
     #[error("data transfer connect timeout")]
     DataTransferConnectTimeout,
+    #[error("data transfer tls handshake failed")]
+    DataTransferTlsHandshakeFailed,
+    #[error("data transfer tls handshake timeout")]
+    DataTransferTlsHandshakeTimeout,
 }
 
 impl FtpTransferSetupError {
@@ -25,7 +25,7 @@ mod feature;
 mod transfer;
 
 pub use client::FtpClient;
-pub use config::{FtpClientConfig, FtpControlConfig, FtpTransferConfig};
+pub use config::{FtpClientConfig, FtpControlConfig, FtpTlsMode, FtpTransferConfig};
 pub use connection::FtpConnectionProvider;
 pub use debug::{FTP_DEBUG_LOG_LEVEL, FTP_DEBUG_LOG_TARGET};
 pub use error::{
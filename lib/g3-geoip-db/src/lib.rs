@@ -15,7 +15,7 @@
  */
 
 mod record;
-pub use record::{GeoIpAsnRecord, GeoIpCountryRecord};
+pub use record::{GeoIpAsnRecord, GeoIpCityRecord, GeoIpCountryRecord};
 
 pub mod store;
 pub mod vendor;
@@ -21,10 +21,28 @@ pub struct GeoIpCountryRecord {
     pub continent: ContinentCode,
 }
 
+pub struct GeoIpCityRecord {
+    pub country: IsoCountryCode,
+    pub continent: ContinentCode,
+    pub(crate) region: Option<String>,
+    pub(crate) city: Option<String>,
+}
+
+impl GeoIpCityRecord {
+    pub fn region(&self) -> Option<&str> {
+        self.region.as_deref()
+    }
+
+    pub fn city(&self) -> Option<&str> {
+        self.city.as_deref()
+    }
+}
+
 pub struct GeoIpAsnRecord {
     pub number: u32,
     pub(crate) name: Option<String>,
     pub(crate) domain: Option<String>,
+    pub(crate) hosting: bool,
 }
 
 impl GeoIpAsnRecord {
@@ -35,4 +53,9 @@ impl GeoIpAsnRecord {
     pub fn isp_domain(&self) -> Option<&str> {
         self.domain.as_deref()
     }
+
+    /// Whether the AS is operated by a hosting or cloud provider
+    pub fn is_hosting(&self) -> bool {
+        self.hosting
+    }
 }
@@ -19,10 +19,12 @@ use std::sync::{Arc, LazyLock};
 use arc_swap::ArcSwapOption;
 use ip_network_table::IpNetworkTable;
 
-use crate::{GeoIpAsnRecord, GeoIpCountryRecord};
+use crate::{GeoIpAsnRecord, GeoIpCityRecord, GeoIpCountryRecord};
 
 static GEO_COUNTRY_DB: LazyLock<ArcSwapOption<IpNetworkTable<GeoIpCountryRecord>>> =
     LazyLock::new(|| ArcSwapOption::new(None));
+static GEO_CITY_DB: LazyLock<ArcSwapOption<IpNetworkTable<GeoIpCityRecord>>> =
+    LazyLock::new(|| ArcSwapOption::new(None));
 static GEO_ASN_DB: LazyLock<ArcSwapOption<IpNetworkTable<GeoIpAsnRecord>>> =
     LazyLock::new(|| ArcSwapOption::new(None));
 
@@ -34,6 +36,14 @@ pub fn store_country(db: Arc<IpNetworkTable<GeoIpCountryRecord>>) {
     GEO_COUNTRY_DB.store(Some(db));
 }
 
+pub fn load_city() -> Option<Arc<IpNetworkTable<GeoIpCityRecord>>> {
+    GEO_CITY_DB.load_full()
+}
+
+pub fn store_city(db: Arc<IpNetworkTable<GeoIpCityRecord>>) {
+    GEO_CITY_DB.store(Some(db));
+}
+
 pub fn load_asn() -> Option<Arc<IpNetworkTable<GeoIpAsnRecord>>> {
     GEO_ASN_DB.load_full()
 }
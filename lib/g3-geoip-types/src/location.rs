@@ -25,9 +25,12 @@ pub struct IpLocationBuilder {
     net: Option<IpNetwork>,
     country: Option<IsoCountryCode>,
     continent: Option<ContinentCode>,
+    region: Option<SmolStr>,
+    city: Option<SmolStr>,
     as_number: Option<u32>,
     isp_name: Option<SmolStr>,
     isp_domain: Option<SmolStr>,
+    hosting: bool,
 }
 
 impl IpLocationBuilder {
@@ -51,6 +54,14 @@ impl IpLocationBuilder {
         self.continent = Some(continent);
     }
 
+    pub fn set_region(&mut self, region: String) {
+        self.region = Some(region.into());
+    }
+
+    pub fn set_city(&mut self, city: String) {
+        self.city = Some(city.into());
+    }
+
     pub fn set_as_number(&mut self, number: u32) {
         self.as_number = Some(number);
     }
@@ -63,6 +74,10 @@ impl IpLocationBuilder {
         self.isp_domain = Some(domain.into());
     }
 
+    pub fn set_hosting(&mut self, hosting: bool) {
+        self.hosting = hosting;
+    }
+
     pub fn build(mut self) -> anyhow::Result<IpLocation> {
         let net = self
             .net
@@ -75,20 +90,27 @@ impl IpLocationBuilder {
             net,
             country: self.country,
             continent,
+            region: self.region,
+            city: self.city,
             as_number: self.as_number,
             isp_name: self.isp_name,
             isp_domain: self.isp_domain,
+            hosting: self.hosting,
         })
     }
 }
 
+#[derive(Clone)]
 pub struct IpLocation {
     net: IpNetwork,
     country: Option<IsoCountryCode>,
     continent: Option<ContinentCode>,
+    region: Option<SmolStr>,
+    city: Option<SmolStr>,
     as_number: Option<u32>,
     isp_name: Option<SmolStr>,
     isp_domain: Option<SmolStr>,
+    hosting: bool,
 }
 
 impl IpLocation {
@@ -107,6 +129,17 @@ impl IpLocation {
         self.continent
     }
 
+    /// the first level subdivision, such as the state or the province
+    #[inline]
+    pub fn region(&self) -> Option<&str> {
+        self.region.as_deref()
+    }
+
+    #[inline]
+    pub fn city(&self) -> Option<&str> {
+        self.city.as_deref()
+    }
+
     #[inline]
     pub fn network_asn(&self) -> Option<u32> {
         self.as_number
@@ -121,4 +154,10 @@ impl IpLocation {
     pub fn isp_domain(&self) -> Option<&str> {
         self.isp_domain.as_deref()
     }
+
+    /// whether the network is operated by a hosting or cloud provider
+    #[inline]
+    pub fn is_hosting(&self) -> bool {
+        self.hosting
+    }
 }
@@ -0,0 +1,326 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::ptr;
+use std::sync::Arc;
+
+use crate::{ffi, AcceptorCredential, GssError};
+
+/// Security context on the acceptor side.
+pub struct AcceptContext {
+    cred: Arc<AcceptorCredential>,
+    ctx: ffi::gss_ctx_id_t,
+    src_name: ffi::gss_name_t,
+    established: bool,
+}
+
+// the context will only be used by one task at a time
+unsafe impl Send for AcceptContext {}
+
+impl AcceptContext {
+    pub fn new(cred: Arc<AcceptorCredential>) -> Self {
+        AcceptContext {
+            cred,
+            ctx: ptr::null_mut(),
+            src_name: ptr::null_mut(),
+            established: false,
+        }
+    }
+
+    #[inline]
+    pub fn is_established(&self) -> bool {
+        self.established
+    }
+
+    /// Process the token received from the initiator.
+    ///
+    /// The returned token, if not empty, should be sent back to the initiator.
+    pub fn step(&mut self, token: &[u8]) -> Result<Vec<u8>, GssError> {
+        if self.established {
+            return Err(GssError::custom(
+                "gss_accept_sec_context",
+                "context already established".to_string(),
+            ));
+        }
+
+        let mut minor: ffi::OM_uint32 = 0;
+        let mut input = ffi::gss_buffer_desc::from_slice(token);
+        let mut output = ffi::gss_buffer_desc::empty();
+        if !self.src_name.is_null() {
+            unsafe { ffi::gss_release_name(&mut minor, &mut self.src_name) };
+        }
+        let major = unsafe {
+            ffi::gss_accept_sec_context(
+                &mut minor,
+                &mut self.ctx,
+                self.cred.cred,
+                &mut input,
+                ptr::null_mut(),
+                &mut self.src_name,
+                ptr::null_mut(),
+                &mut output,
+                ptr::null_mut(),
+                ptr::null_mut(),
+                ptr::null_mut(),
+            )
+        };
+        let output_token = take_buffer(&mut output);
+        if ffi::gss_error(major) {
+            return Err(GssError::new("gss_accept_sec_context", major, minor));
+        }
+        if major & ffi::GSS_S_CONTINUE_NEEDED == 0 {
+            self.established = true;
+        }
+        Ok(output_token)
+    }
+
+    /// Get the principal name of the initiator, only valid after the context is established.
+    pub fn source_name(&self) -> Result<String, GssError> {
+        if !self.established || self.src_name.is_null() {
+            return Err(GssError::custom(
+                "gss_display_name",
+                "context not established".to_string(),
+            ));
+        }
+
+        let mut minor: ffi::OM_uint32 = 0;
+        let mut output = ffi::gss_buffer_desc::empty();
+        let major = unsafe {
+            ffi::gss_display_name(&mut minor, self.src_name, &mut output, ptr::null_mut())
+        };
+        if ffi::gss_error(major) {
+            return Err(GssError::new("gss_display_name", major, minor));
+        }
+        let name = take_buffer(&mut output);
+        String::from_utf8(name)
+            .map_err(|_| GssError::custom("gss_display_name", "invalid utf-8 name".to_string()))
+    }
+
+    pub fn wrap(&self, data: &[u8], encrypt: bool) -> Result<Vec<u8>, GssError> {
+        let mut minor: ffi::OM_uint32 = 0;
+        let mut input = ffi::gss_buffer_desc::from_slice(data);
+        let mut output = ffi::gss_buffer_desc::empty();
+        let major = unsafe {
+            ffi::gss_wrap(
+                &mut minor,
+                self.ctx,
+                encrypt as libc::c_int,
+                ffi::GSS_C_QOP_DEFAULT,
+                &mut input,
+                ptr::null_mut(),
+                &mut output,
+            )
+        };
+        let output = take_buffer(&mut output);
+        if ffi::gss_error(major) {
+            return Err(GssError::new("gss_wrap", major, minor));
+        }
+        Ok(output)
+    }
+
+    pub fn unwrap(&self, data: &[u8]) -> Result<Vec<u8>, GssError> {
+        let mut minor: ffi::OM_uint32 = 0;
+        let mut input = ffi::gss_buffer_desc::from_slice(data);
+        let mut output = ffi::gss_buffer_desc::empty();
+        let major = unsafe {
+            ffi::gss_unwrap(
+                &mut minor,
+                self.ctx,
+                &mut input,
+                &mut output,
+                ptr::null_mut(),
+                ptr::null_mut(),
+            )
+        };
+        let output = take_buffer(&mut output);
+        if ffi::gss_error(major) {
+            return Err(GssError::new("gss_unwrap", major, minor));
+        }
+        Ok(output)
+    }
+}
+
+impl Drop for AcceptContext {
+    fn drop(&mut self) {
+        let mut minor: ffi::OM_uint32 = 0;
+        if !self.src_name.is_null() {
+            unsafe { ffi::gss_release_name(&mut minor, &mut self.src_name) };
+        }
+        if !self.ctx.is_null() {
+            unsafe { ffi::gss_delete_sec_context(&mut minor, &mut self.ctx, ptr::null_mut()) };
+        }
+    }
+}
+
+fn take_buffer(buf: &mut ffi::gss_buffer_desc) -> Vec<u8> {
+    if buf.value.is_null() {
+        return Vec::new();
+    }
+    let data = unsafe { buf.as_slice() }.to_vec();
+    let mut minor: ffi::OM_uint32 = 0;
+    unsafe { ffi::gss_release_buffer(&mut minor, buf) };
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::path::Path;
+
+    /// The initiator side, which use the default credential cache
+    struct InitContext {
+        target: ffi::gss_name_t,
+        ctx: ffi::gss_ctx_id_t,
+        established: bool,
+    }
+
+    impl InitContext {
+        fn new(service: &str) -> Self {
+            let mut minor: ffi::OM_uint32 = 0;
+            let mut buf = ffi::gss_buffer_desc::from_slice(service.as_bytes());
+            let mut target: ffi::gss_name_t = ptr::null_mut();
+            let major = unsafe {
+                ffi::gss_import_name(
+                    &mut minor,
+                    &mut buf,
+                    ffi::GSS_C_NT_HOSTBASED_SERVICE,
+                    &mut target,
+                )
+            };
+            assert!(!ffi::gss_error(major));
+            InitContext {
+                target,
+                ctx: ptr::null_mut(),
+                established: false,
+            }
+        }
+
+        fn step(&mut self, token: &[u8]) -> Result<Vec<u8>, GssError> {
+            let mut minor: ffi::OM_uint32 = 0;
+            let mut input = ffi::gss_buffer_desc::from_slice(token);
+            let mut output = ffi::gss_buffer_desc::empty();
+            let major = unsafe {
+                ffi::gss_init_sec_context(
+                    &mut minor,
+                    ptr::null_mut(),
+                    &mut self.ctx,
+                    self.target,
+                    ptr::null_mut(),
+                    ffi::GSS_C_MUTUAL_FLAG | ffi::GSS_C_INTEG_FLAG | ffi::GSS_C_CONF_FLAG,
+                    0,
+                    ptr::null_mut(),
+                    &mut input,
+                    ptr::null_mut(),
+                    &mut output,
+                    ptr::null_mut(),
+                    ptr::null_mut(),
+                )
+            };
+            let output_token = take_buffer(&mut output);
+            if ffi::gss_error(major) {
+                return Err(GssError::new("gss_init_sec_context", major, minor));
+            }
+            if major & ffi::GSS_S_CONTINUE_NEEDED == 0 {
+                self.established = true;
+            }
+            Ok(output_token)
+        }
+
+        fn wrap(&self, data: &[u8]) -> Vec<u8> {
+            let mut minor: ffi::OM_uint32 = 0;
+            let mut input = ffi::gss_buffer_desc::from_slice(data);
+            let mut output = ffi::gss_buffer_desc::empty();
+            let major = unsafe {
+                ffi::gss_wrap(
+                    &mut minor,
+                    self.ctx,
+                    0,
+                    ffi::GSS_C_QOP_DEFAULT,
+                    &mut input,
+                    ptr::null_mut(),
+                    &mut output,
+                )
+            };
+            assert!(!ffi::gss_error(major));
+            take_buffer(&mut output)
+        }
+
+        fn unwrap(&self, data: &[u8]) -> Vec<u8> {
+            let mut minor: ffi::OM_uint32 = 0;
+            let mut input = ffi::gss_buffer_desc::from_slice(data);
+            let mut output = ffi::gss_buffer_desc::empty();
+            let major = unsafe {
+                ffi::gss_unwrap(
+                    &mut minor,
+                    self.ctx,
+                    &mut input,
+                    &mut output,
+                    ptr::null_mut(),
+                    ptr::null_mut(),
+                )
+            };
+            assert!(!ffi::gss_error(major));
+            take_buffer(&mut output)
+        }
+    }
+
+    impl Drop for InitContext {
+        fn drop(&mut self) {
+            let mut minor: ffi::OM_uint32 = 0;
+            unsafe { ffi::gss_release_name(&mut minor, &mut self.target) };
+            if !self.ctx.is_null() {
+                unsafe { ffi::gss_delete_sec_context(&mut minor, &mut self.ctx, ptr::null_mut()) };
+            }
+        }
+    }
+
+    /// This test requires a MIT KDC, and it will be skipped if the environment is not set.
+    /// Use `scripts/test/gssapi_kdc.sh` to run it with a temporary local KDC.
+    #[test]
+    fn kdc_accept() {
+        let (Ok(service), Ok(keytab), Ok(principal)) = (
+            env::var("G3_GSSAPI_TEST_SERVICE"),
+            env::var("G3_GSSAPI_TEST_KEYTAB"),
+            env::var("G3_GSSAPI_TEST_PRINCIPAL"),
+        ) else {
+            return;
+        };
+
+        let cred = AcceptorCredential::acquire(Some(&service), Some(Path::new(&keytab))).unwrap();
+        let mut acceptor = AcceptContext::new(Arc::new(cred));
+        let mut initiator = InitContext::new(&service);
+
+        let mut token = initiator.step(&[]).unwrap();
+        while !acceptor.is_established() {
+            let output = acceptor.step(&token).unwrap();
+            if !initiator.established {
+                token = initiator.step(&output).unwrap();
+            }
+        }
+        assert!(initiator.established);
+        assert_eq!(acceptor.source_name().unwrap(), principal);
+        assert!(acceptor.step(&token).is_err());
+
+        // the protection level subnegotiation
+        let request = initiator.wrap(&[0x03]);
+        assert_eq!(acceptor.unwrap(&request).unwrap(), [0x03]);
+        let reply = acceptor.wrap(&[0x00], false).unwrap();
+        assert_eq!(initiator.unwrap(&reply), [0x00]);
+
+        assert!(acceptor.unwrap(b"invalid").is_err());
+    }
+}
@@ -0,0 +1,114 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::ffi::CString;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use std::ptr;
+
+use crate::{ffi, GssError};
+
+/// Acceptor credential loaded from a keytab.
+pub struct AcceptorCredential {
+    pub(crate) cred: ffi::gss_cred_id_t,
+}
+
+// the credential is not modified after acquired
+unsafe impl Send for AcceptorCredential {}
+unsafe impl Sync for AcceptorCredential {}
+
+impl AcceptorCredential {
+    /// Acquire the acceptor credential.
+    ///
+    /// If `service` is set, it should be a host based service name like `socks@proxy.example.net`,
+    /// or any service principal in the keytab will be accepted.
+    /// If `keytab` is not set, the default keytab of the kerberos library will be used.
+    pub fn acquire(service: Option<&str>, keytab: Option<&Path>) -> Result<Self, GssError> {
+        let mut minor: ffi::OM_uint32 = 0;
+
+        let mut desired_name: ffi::gss_name_t = ptr::null_mut();
+        if let Some(service) = service {
+            let mut buf = ffi::gss_buffer_desc::from_slice(service.as_bytes());
+            let major = unsafe {
+                ffi::gss_import_name(
+                    &mut minor,
+                    &mut buf,
+                    ffi::GSS_C_NT_HOSTBASED_SERVICE,
+                    &mut desired_name,
+                )
+            };
+            if ffi::gss_error(major) {
+                return Err(GssError::new("gss_import_name", major, minor));
+            }
+        }
+
+        let keytab = match keytab {
+            Some(path) => Some(CString::new(path.as_os_str().as_bytes()).map_err(|_| {
+                GssError::custom(
+                    "gss_acquire_cred_from",
+                    format!("invalid keytab path {}", path.display()),
+                )
+            })?),
+            None => None,
+        };
+        let mut store_element = ffi::gss_key_value_element_desc {
+            key: c"keytab".as_ptr(),
+            value: keytab.as_ref().map(|v| v.as_ptr()).unwrap_or(ptr::null()),
+        };
+        let cred_store = ffi::gss_key_value_set_desc {
+            count: 1,
+            elements: &mut store_element,
+        };
+        let cred_store_ptr = if keytab.is_some() {
+            &cred_store as *const ffi::gss_key_value_set_desc
+        } else {
+            ptr::null()
+        };
+
+        let mut cred: ffi::gss_cred_id_t = ptr::null_mut();
+        let major = unsafe {
+            ffi::gss_acquire_cred_from(
+                &mut minor,
+                desired_name,
+                ffi::GSS_C_INDEFINITE,
+                ptr::null_mut(),
+                ffi::GSS_C_ACCEPT,
+                cred_store_ptr,
+                &mut cred,
+                ptr::null_mut(),
+                ptr::null_mut(),
+            )
+        };
+        if !desired_name.is_null() {
+            let mut release_minor: ffi::OM_uint32 = 0;
+            unsafe { ffi::gss_release_name(&mut release_minor, &mut desired_name) };
+        }
+        if ffi::gss_error(major) {
+            return Err(GssError::new("gss_acquire_cred_from", major, minor));
+        }
+
+        Ok(AcceptorCredential { cred })
+    }
+}
+
+impl Drop for AcceptorCredential {
+    fn drop(&mut self) {
+        if !self.cred.is_null() {
+            let mut minor: ffi::OM_uint32 = 0;
+            unsafe { ffi::gss_release_cred(&mut minor, &mut self.cred) };
+        }
+    }
+}
@@ -0,0 +1,105 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::fmt;
+
+use crate::ffi;
+
+#[derive(Debug)]
+pub struct GssError {
+    func: &'static str,
+    major: u32,
+    minor: u32,
+    message: String,
+}
+
+impl GssError {
+    pub(crate) fn new(func: &'static str, major: u32, minor: u32) -> Self {
+        let mut message = display_status(major, ffi::GSS_C_GSS_CODE);
+        if minor != 0 {
+            let minor_message = display_status(minor, ffi::GSS_C_MECH_CODE);
+            if !minor_message.is_empty() {
+                message.push_str(": ");
+                message.push_str(&minor_message);
+            }
+        }
+        GssError {
+            func,
+            major,
+            minor,
+            message,
+        }
+    }
+
+    pub(crate) fn custom(func: &'static str, message: String) -> Self {
+        GssError {
+            func,
+            major: 0,
+            minor: 0,
+            message,
+        }
+    }
+
+    #[inline]
+    pub fn major_status(&self) -> u32 {
+        self.major
+    }
+
+    #[inline]
+    pub fn minor_status(&self) -> u32 {
+        self.minor
+    }
+}
+
+impl fmt::Display for GssError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} failed: {}", self.func, self.message)
+    }
+}
+
+impl std::error::Error for GssError {}
+
+fn display_status(status: u32, status_type: libc::c_int) -> String {
+    let mut output = String::new();
+    let mut message_context: ffi::OM_uint32 = 0;
+    loop {
+        let mut minor: ffi::OM_uint32 = 0;
+        let mut buf = ffi::gss_buffer_desc::empty();
+        let major = unsafe {
+            ffi::gss_display_status(
+                &mut minor,
+                status,
+                status_type,
+                std::ptr::null_mut(),
+                &mut message_context,
+                &mut buf,
+            )
+        };
+        if ffi::gss_error(major) {
+            break;
+        }
+        let s = unsafe { buf.as_slice() };
+        if !output.is_empty() {
+            output.push_str(", ");
+        }
+        output.push_str(&String::from_utf8_lossy(s));
+        unsafe { ffi::gss_release_buffer(&mut minor, &mut buf) };
+        if message_context == 0 {
+            break;
+        }
+    }
+    output
+}
@@ -0,0 +1,227 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+#![allow(non_camel_case_types)]
+#![allow(unused)]
+
+use libc::{c_char, c_int, c_void};
+
+pub type OM_uint32 = u32;
+
+#[repr(C)]
+pub struct gss_buffer_desc {
+    pub length: usize,
+    pub value: *mut c_void,
+}
+
+pub type gss_buffer_t = *mut gss_buffer_desc;
+
+impl gss_buffer_desc {
+    pub const fn empty() -> Self {
+        gss_buffer_desc {
+            length: 0,
+            value: std::ptr::null_mut(),
+        }
+    }
+
+    pub fn from_slice(data: &[u8]) -> Self {
+        gss_buffer_desc {
+            length: data.len(),
+            value: data.as_ptr() as *mut c_void,
+        }
+    }
+
+    /// # Safety
+    ///
+    /// The buffer should be allocated by the gssapi library and still valid
+    pub unsafe fn as_slice(&self) -> &[u8] {
+        if self.value.is_null() || self.length == 0 {
+            &[]
+        } else {
+            std::slice::from_raw_parts(self.value as *const u8, self.length)
+        }
+    }
+}
+
+#[repr(C)]
+pub struct gss_OID_desc {
+    pub length: OM_uint32,
+    pub elements: *mut c_void,
+}
+
+pub type gss_OID = *mut gss_OID_desc;
+
+#[repr(C)]
+pub struct gss_OID_set_desc {
+    pub count: usize,
+    pub elements: gss_OID,
+}
+
+pub type gss_OID_set = *mut gss_OID_set_desc;
+
+#[repr(C)]
+pub struct gss_key_value_element_desc {
+    pub key: *const c_char,
+    pub value: *const c_char,
+}
+
+#[repr(C)]
+pub struct gss_key_value_set_desc {
+    pub count: OM_uint32,
+    pub elements: *mut gss_key_value_element_desc,
+}
+
+pub enum gss_name_struct {}
+pub type gss_name_t = *mut gss_name_struct;
+
+pub enum gss_cred_id_struct {}
+pub type gss_cred_id_t = *mut gss_cred_id_struct;
+
+pub enum gss_ctx_id_struct {}
+pub type gss_ctx_id_t = *mut gss_ctx_id_struct;
+
+pub enum gss_channel_bindings_struct {}
+pub type gss_channel_bindings_t = *mut gss_channel_bindings_struct;
+
+pub type gss_cred_usage_t = c_int;
+pub type gss_qop_t = OM_uint32;
+
+pub const GSS_C_ACCEPT: gss_cred_usage_t = 2;
+
+pub const GSS_C_MUTUAL_FLAG: OM_uint32 = 2;
+pub const GSS_C_CONF_FLAG: OM_uint32 = 16;
+pub const GSS_C_INTEG_FLAG: OM_uint32 = 32;
+pub const GSS_C_INDEFINITE: OM_uint32 = 0xffffffff;
+pub const GSS_C_QOP_DEFAULT: gss_qop_t = 0;
+
+pub const GSS_C_GSS_CODE: c_int = 1;
+pub const GSS_C_MECH_CODE: c_int = 2;
+
+pub const GSS_S_COMPLETE: OM_uint32 = 0;
+pub const GSS_S_CONTINUE_NEEDED: OM_uint32 = 1;
+
+const GSS_C_CALLING_ERROR_MASK: OM_uint32 = 0xff000000;
+const GSS_C_ROUTINE_ERROR_MASK: OM_uint32 = 0x00ff0000;
+
+#[inline]
+pub const fn gss_error(major: OM_uint32) -> bool {
+    major & (GSS_C_CALLING_ERROR_MASK | GSS_C_ROUTINE_ERROR_MASK) != 0
+}
+
+#[link(name = "gssapi_krb5")]
+extern "C" {
+    pub static GSS_C_NT_HOSTBASED_SERVICE: gss_OID;
+    pub static GSS_C_NT_USER_NAME: gss_OID;
+
+    pub fn gss_import_name(
+        minor_status: *mut OM_uint32,
+        input_name_buffer: gss_buffer_t,
+        input_name_type: gss_OID,
+        output_name: *mut gss_name_t,
+    ) -> OM_uint32;
+
+    pub fn gss_display_name(
+        minor_status: *mut OM_uint32,
+        input_name: gss_name_t,
+        output_name_buffer: gss_buffer_t,
+        output_name_type: *mut gss_OID,
+    ) -> OM_uint32;
+
+    pub fn gss_release_name(minor_status: *mut OM_uint32, name: *mut gss_name_t) -> OM_uint32;
+
+    pub fn gss_release_buffer(minor_status: *mut OM_uint32, buffer: gss_buffer_t) -> OM_uint32;
+
+    pub fn gss_acquire_cred_from(
+        minor_status: *mut OM_uint32,
+        desired_name: gss_name_t,
+        time_req: OM_uint32,
+        desired_mechs: gss_OID_set,
+        cred_usage: gss_cred_usage_t,
+        cred_store: *const gss_key_value_set_desc,
+        output_cred_handle: *mut gss_cred_id_t,
+        actual_mechs: *mut gss_OID_set,
+        time_rec: *mut OM_uint32,
+    ) -> OM_uint32;
+
+    pub fn gss_release_cred(
+        minor_status: *mut OM_uint32,
+        cred_handle: *mut gss_cred_id_t,
+    ) -> OM_uint32;
+
+    pub fn gss_init_sec_context(
+        minor_status: *mut OM_uint32,
+        initiator_cred_handle: gss_cred_id_t,
+        context_handle: *mut gss_ctx_id_t,
+        target_name: gss_name_t,
+        mech_type: gss_OID,
+        req_flags: OM_uint32,
+        time_req: OM_uint32,
+        input_chan_bindings: gss_channel_bindings_t,
+        input_token: gss_buffer_t,
+        actual_mech_type: *mut gss_OID,
+        output_token: gss_buffer_t,
+        ret_flags: *mut OM_uint32,
+        time_rec: *mut OM_uint32,
+    ) -> OM_uint32;
+
+    pub fn gss_accept_sec_context(
+        minor_status: *mut OM_uint32,
+        context_handle: *mut gss_ctx_id_t,
+        acceptor_cred_handle: gss_cred_id_t,
+        input_token_buffer: gss_buffer_t,
+        input_chan_bindings: gss_channel_bindings_t,
+        src_name: *mut gss_name_t,
+        mech_type: *mut gss_OID,
+        output_token: gss_buffer_t,
+        ret_flags: *mut OM_uint32,
+        time_rec: *mut OM_uint32,
+        delegated_cred_handle: *mut gss_cred_id_t,
+    ) -> OM_uint32;
+
+    pub fn gss_delete_sec_context(
+        minor_status: *mut OM_uint32,
+        context_handle: *mut gss_ctx_id_t,
+        output_token: gss_buffer_t,
+    ) -> OM_uint32;
+
+    pub fn gss_wrap(
+        minor_status: *mut OM_uint32,
+        context_handle: gss_ctx_id_t,
+        conf_req_flag: c_int,
+        qop_req: gss_qop_t,
+        input_message_buffer: gss_buffer_t,
+        conf_state: *mut c_int,
+        output_message_buffer: gss_buffer_t,
+    ) -> OM_uint32;
+
+    pub fn gss_unwrap(
+        minor_status: *mut OM_uint32,
+        context_handle: gss_ctx_id_t,
+        input_message_buffer: gss_buffer_t,
+        output_message_buffer: gss_buffer_t,
+        conf_state: *mut c_int,
+        qop_state: *mut gss_qop_t,
+    ) -> OM_uint32;
+
+    pub fn gss_display_status(
+        minor_status: *mut OM_uint32,
+        status_value: OM_uint32,
+        status_type: c_int,
+        mech_type: gss_OID,
+        message_context: *mut OM_uint32,
+        status_string: gss_buffer_t,
+    ) -> OM_uint32;
+}
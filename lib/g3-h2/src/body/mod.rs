@@ -18,7 +18,7 @@ mod error;
 pub use error::H2StreamBodyTransferError;
 
 mod transfer;
-pub use transfer::H2BodyTransfer;
+pub use transfer::{H2BodyInspector, H2BodyTransfer};
 
 mod encoder;
 pub use encoder::{
@@ -20,10 +20,43 @@ use std::task::{ready, Context, Poll};
 
 use bytes::{Buf, Bytes};
 use h2::{FlowControl, RecvStream, SendStream};
+use http::HeaderMap;
 
 use super::H2StreamBodyTransferError;
 
-pub struct H2BodyTransfer {
+/// Observe the data and trailers passed through a [H2BodyTransfer]
+pub trait H2BodyInspector {
+    fn inspect_data(&mut self, _data: &[u8]) {}
+    fn inspect_trailers(&mut self, _trailers: &HeaderMap) {}
+}
+
+impl H2BodyInspector for () {}
+
+impl<T: H2BodyInspector + ?Sized> H2BodyInspector for &mut T {
+    fn inspect_data(&mut self, data: &[u8]) {
+        (**self).inspect_data(data);
+    }
+
+    fn inspect_trailers(&mut self, trailers: &HeaderMap) {
+        (**self).inspect_trailers(trailers);
+    }
+}
+
+impl<T: H2BodyInspector> H2BodyInspector for Option<T> {
+    fn inspect_data(&mut self, data: &[u8]) {
+        if let Some(inspector) = self {
+            inspector.inspect_data(data);
+        }
+    }
+
+    fn inspect_trailers(&mut self, trailers: &HeaderMap) {
+        if let Some(inspector) = self {
+            inspector.inspect_trailers(trailers);
+        }
+    }
+}
+
+pub struct H2BodyTransfer<I = ()> {
     yield_size: usize,
     recv_stream: RecvStream,
     recv_flow_control: FlowControl,
@@ -31,13 +64,21 @@ pub struct H2BodyTransfer {
     send_chunk: Option<Bytes>,
     handle_trailers: bool,
     active: bool,
+    inspector: I,
 }
 
 impl H2BodyTransfer {
-    pub fn new(
+    pub fn new(recv_stream: RecvStream, send_stream: SendStream<Bytes>, yield_size: usize) -> Self {
+        H2BodyTransfer::with_inspector(recv_stream, send_stream, yield_size, ())
+    }
+}
+
+impl<I: H2BodyInspector> H2BodyTransfer<I> {
+    pub fn with_inspector(
         mut recv_stream: RecvStream,
         send_stream: SendStream<Bytes>,
         yield_size: usize,
+        inspector: I,
     ) -> Self {
         let recv_flow_control = recv_stream.flow_control().clone();
         H2BodyTransfer {
@@ -48,6 +89,7 @@ impl H2BodyTransfer {
             send_chunk: None,
             handle_trailers: false,
             active: false,
+            inspector,
         }
     }
 
@@ -70,6 +112,7 @@ impl H2BodyTransfer {
     ) -> Poll<Result<(), H2StreamBodyTransferError>> {
         match ready!(self.recv_stream.poll_trailers(cx)) {
             Ok(Some(trailers)) => {
+                self.inspector.inspect_trailers(&trailers);
                 self.send_stream
                     .send_trailers(trailers)
                     .map_err(H2StreamBodyTransferError::SendTrailersFailed)?;
@@ -137,6 +180,7 @@ impl H2BodyTransfer {
                     Some(Ok(chunk)) => {
                         self.active = true;
                         if chunk.has_remaining() {
+                            self.inspector.inspect_data(chunk.chunk());
                             self.send_stream.reserve_capacity(chunk.len());
                             self.send_chunk = Some(chunk);
                             continue;
@@ -162,7 +206,7 @@ impl H2BodyTransfer {
     }
 }
 
-impl Future for H2BodyTransfer {
+impl<I: H2BodyInspector + Unpin> Future for H2BodyTransfer<I> {
     type Output = Result<(), H2StreamBodyTransferError>;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
@@ -16,7 +16,7 @@
 
 mod body;
 pub use body::{
-    H2BodyEncodeTransfer, H2BodyTransfer, H2StreamBodyEncodeTransferError,
+    H2BodyEncodeTransfer, H2BodyInspector, H2BodyTransfer, H2StreamBodyEncodeTransferError,
     H2StreamBodyTransferError, H2StreamFromChunkedTransfer, H2StreamFromChunkedTransferError,
     H2StreamReader, H2StreamToChunkedTransfer, H2StreamToChunkedTransferError, H2StreamWriter,
     ROwnedH2BodyEncodeTransfer,
@@ -0,0 +1,158 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! HTTP Capsule Protocol, see [RFC 9297](https://datatracker.ietf.org/doc/html/rfc9297).
+
+use bytes::BufMut;
+
+/// the DATAGRAM capsule type
+pub const CAPSULE_TYPE_DATAGRAM: u64 = 0x00;
+
+/// the max value that can be encoded as a variable-length integer
+const VAR_INT_MAX: u64 = (1 << 62) - 1;
+
+/// Try to decode a variable-length integer, return the value and the encoded length
+pub fn decode_var_int(data: &[u8]) -> Option<(u64, usize)> {
+    let first = *data.first()?;
+    let len = 1usize << (first >> 6);
+    if data.len() < len {
+        return None;
+    }
+
+    let mut value = (first & 0b0011_1111) as u64;
+    for b in &data[1..len] {
+        value = (value << 8) | (*b as u64);
+    }
+    Some((value, len))
+}
+
+pub fn var_int_encoded_len(value: u64) -> usize {
+    if value < (1 << 6) {
+        1
+    } else if value < (1 << 14) {
+        2
+    } else if value < (1 << 30) {
+        4
+    } else {
+        8
+    }
+}
+
+/// Encode a variable-length integer, the value should be less than 2^62
+pub fn encode_var_int<B: BufMut>(value: u64, buf: &mut B) {
+    debug_assert!(value <= VAR_INT_MAX);
+    match var_int_encoded_len(value) {
+        1 => buf.put_u8(value as u8),
+        2 => buf.put_u16(0b01 << 14 | value as u16),
+        4 => buf.put_u32(0b10 << 30 | value as u32),
+        _ => buf.put_u64(0b11 << 62 | value),
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CapsuleHeader {
+    pub capsule_type: u64,
+    pub value_length: usize,
+    pub header_length: usize,
+}
+
+impl CapsuleHeader {
+    /// Try to parse the capsule header, return None if more data is needed
+    pub fn try_parse(data: &[u8]) -> Option<Self> {
+        let (capsule_type, type_len) = decode_var_int(data)?;
+        let (value_length, len_len) = decode_var_int(&data[type_len..])?;
+        Some(CapsuleHeader {
+            capsule_type,
+            value_length: usize::try_from(value_length).unwrap_or(usize::MAX),
+            header_length: type_len + len_len,
+        })
+    }
+
+    #[inline]
+    pub fn total_length(&self) -> usize {
+        self.header_length.saturating_add(self.value_length)
+    }
+}
+
+/// The length of the DATAGRAM capsule which contains the UDP payload of `payload_len`.
+///
+/// The context ID for UDP payload in connect-udp is always 0, see
+/// [RFC 9298](https://datatracker.ietf.org/doc/html/rfc9298#section-5).
+pub fn udp_datagram_capsule_len(payload_len: usize) -> usize {
+    let value_len = 1 + payload_len;
+    1 + var_int_encoded_len(value_len as u64) + value_len
+}
+
+/// Encode the UDP payload as a DATAGRAM capsule
+pub fn encode_udp_datagram_capsule<B: BufMut>(payload: &[u8], buf: &mut B) {
+    encode_var_int(CAPSULE_TYPE_DATAGRAM, buf);
+    encode_var_int(1 + payload.len() as u64, buf);
+    encode_var_int(0, buf);
+    buf.put_slice(payload);
+}
+
+/// Get the UDP payload from the value of a DATAGRAM capsule.
+///
+/// None will be returned if the context ID is not 0, which should be dropped silently.
+pub fn udp_payload_in_datagram(value: &[u8]) -> Option<&[u8]> {
+    let (context_id, len) = decode_var_int(value)?;
+    if context_id == 0 {
+        Some(&value[len..])
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn var_int() {
+        for (value, encoded) in [
+            (37u64, &[0x25u8][..]),
+            (15293, &[0x7b, 0xbd][..]),
+            (494878333, &[0x9d, 0x7f, 0x3e, 0x7d][..]),
+            (
+                151288809941952652,
+                &[0xc2, 0x19, 0x7c, 0x5e, 0xff, 0x14, 0xe8, 0x8c][..],
+            ),
+        ] {
+            let mut buf = Vec::new();
+            encode_var_int(value, &mut buf);
+            assert_eq!(buf.as_slice(), encoded);
+            assert_eq!(decode_var_int(encoded), Some((value, encoded.len())));
+        }
+        assert!(decode_var_int(&[0x7b]).is_none());
+    }
+
+    #[test]
+    fn udp_datagram() {
+        let payload = b"hello";
+        let mut buf = Vec::new();
+        encode_udp_datagram_capsule(payload, &mut buf);
+        assert_eq!(buf.len(), udp_datagram_capsule_len(payload.len()));
+
+        let header = CapsuleHeader::try_parse(&buf).unwrap();
+        assert_eq!(header.capsule_type, CAPSULE_TYPE_DATAGRAM);
+        assert_eq!(header.total_length(), buf.len());
+        let value = &buf[header.header_length..header.total_length()];
+        assert_eq!(udp_payload_in_datagram(value), Some(&payload[..]));
+
+        assert!(CapsuleHeader::try_parse(&buf[..1]).is_none());
+        assert!(udp_payload_in_datagram(&[0x01, 0x00]).is_none());
+    }
+}
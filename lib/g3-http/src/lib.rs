@@ -26,6 +26,7 @@ pub use body::{
     TrailerReadError, TrailerReader,
 };
 
+pub mod capsule;
 pub mod client;
 pub mod connect;
 pub mod header;
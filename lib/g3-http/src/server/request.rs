@@ -18,7 +18,7 @@ use std::io::Write;
 use std::str::FromStr;
 
 use bytes::BufMut;
-use http::{header, HeaderName, Method, Uri, Version};
+use http::{header, request, HeaderName, Method, Uri, Version};
 use tokio::io::AsyncBufRead;
 
 use g3_io_ext::LimitedBufReadExt;
@@ -88,6 +88,81 @@ impl HttpProxyClientRequest {
         }
     }
 
+    /// Build a HTTP/1.1 forward request from the head of a HTTP/2 proxy request.
+    ///
+    /// HTTP/2 has no chunked encoding, so if `has_body` is set, the body will be
+    /// sent to the upstream in chunked encoding.
+    pub fn from_h2_parts(
+        parts: &request::Parts,
+        has_body: bool,
+    ) -> Result<Self, HttpRequestParseError> {
+        let mut req =
+            HttpProxyClientRequest::new(parts.method.clone(), parts.uri.clone(), Version::HTTP_11);
+        req.keep_alive = true;
+
+        for (name, value) in &parts.headers {
+            let value_str = value.to_str().map_err(|_| {
+                HttpRequestParseError::InvalidHeaderLine(HttpLineParseError::InvalidHeaderValue)
+            })?;
+            match name.as_str() {
+                "proxy-authorization" => {
+                    req.parse_header_authorization(value_str)?;
+                    continue;
+                }
+                // connection specific headers are not allowed in HTTP/2
+                "connection" | "proxy-connection" | "keep-alive" | "transfer-encoding"
+                | "upgrade" => continue,
+                "te" => {
+                    let value = HttpHeaderValue::from_str(value_str).map_err(|_| {
+                        HttpRequestParseError::InvalidHeaderLine(
+                            HttpLineParseError::InvalidHeaderValue,
+                        )
+                    })?;
+                    req.hop_by_hop_headers.append(name.clone(), value);
+                    continue;
+                }
+                // will be sent in chunked encoding
+                "content-length" if has_body => continue,
+                "host" => {
+                    if req.host.is_some() {
+                        return Err(HttpRequestParseError::InvalidHost);
+                    }
+                    let host = UpstreamAddr::from_str(value_str)
+                        .map_err(|_| HttpRequestParseError::InvalidHost)?;
+                    req.host = Some(host);
+                }
+                _ => {}
+            }
+            let value = HttpHeaderValue::from_str(value_str).map_err(|_| {
+                HttpRequestParseError::InvalidHeaderLine(HttpLineParseError::InvalidHeaderValue)
+            })?;
+            req.end_to_end_headers.append(name.clone(), value);
+        }
+
+        if req.host.is_none() {
+            // the :authority pseudo header will be converted to the Host header
+            if let Some(authority) = parts.uri.authority() {
+                let host = UpstreamAddr::from_str(authority.as_str())
+                    .map_err(|_| HttpRequestParseError::InvalidHost)?;
+                let value = HttpHeaderValue::from_str(authority.as_str())
+                    .map_err(|_| HttpRequestParseError::InvalidHost)?;
+                req.end_to_end_headers.insert(header::HOST, value);
+                req.host = Some(host);
+            }
+        }
+
+        if has_body {
+            req.chunked_transfer = true;
+            req.has_transfer_encoding = true;
+            req.hop_by_hop_headers.insert(
+                header::TRANSFER_ENCODING,
+                HttpHeaderValue::from_static("chunked"),
+            );
+        }
+
+        Ok(req)
+    }
+
     #[inline]
     pub fn origin_header_size(&self) -> usize {
         self.origin_header_size
@@ -530,4 +605,29 @@ mod tests {
                 .unwrap();
         assert!(!request.keep_alive());
     }
+
+    #[test]
+    fn from_h2_parts() {
+        let (parts, _) = http::Request::builder()
+            .method(Method::POST)
+            .uri("http://example.com/upload")
+            .header("proxy-authorization", "Basic dGVzdDpwYXNz")
+            .header("content-length", "4")
+            .header("te", "trailers")
+            .body(())
+            .unwrap()
+            .into_parts();
+        let request = HttpProxyClientRequest::from_h2_parts(&parts, true).unwrap();
+        assert_eq!(request.version, Version::HTTP_11);
+        assert!(request.keep_alive());
+        assert!(request.has_auth_info());
+        assert_eq!(request.body_type(), Some(HttpBodyType::Chunked));
+        assert!(!request.end_to_end_headers.contains_key(header::CONTENT_LENGTH));
+        assert!(request.hop_by_hop_headers.contains_key(header::TE));
+        assert_eq!(
+            request.end_to_end_headers.get(header::HOST).unwrap().to_str(),
+            "example.com"
+        );
+        assert_eq!(request.host.unwrap().port(), 0);
+    }
 }
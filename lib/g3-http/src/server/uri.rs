@@ -84,8 +84,34 @@ impl UriExt for Uri {
             .ok_or(HttpRequestParseError::InvalidRequestTarget)?;
         let port = u16::from_str(port).map_err(|_| HttpRequestParseError::InvalidRequestTarget)?;
 
-        let upstream = UpstreamAddr::from_host_str_and_port(host, port)
-            .map_err(|_| HttpRequestParseError::InvalidRequestTarget)?;
+        // colons in IPv6 addresses should be percent-encoded, see RFC 9298 Section 2
+        let upstream = if host.contains('%') {
+            let host = host.replace("%3A", ":").replace("%3a", ":");
+            UpstreamAddr::from_host_str_and_port(&host, port)
+        } else {
+            UpstreamAddr::from_host_str_and_port(host, port)
+        }
+        .map_err(|_| HttpRequestParseError::InvalidRequestTarget)?;
         Ok(upstream)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn connect_udp_upstream() {
+        let uri =
+            Uri::from_static("https://proxy.example.net/.well-known/masque/udp/192.0.2.6/443/");
+        let upstream = uri.get_connect_udp_upstream().unwrap();
+        assert_eq!(upstream.to_string(), "192.0.2.6:443");
+
+        let uri = Uri::from_static("/.well-known/masque/udp/2001%3Adb8%3A%3A42/53/");
+        let upstream = uri.get_connect_udp_upstream().unwrap();
+        assert_eq!(upstream.to_string(), "[2001:db8::42]:53");
+
+        let uri = Uri::from_static("/.well-known/masque/ip/192.0.2.6/443/");
+        assert!(uri.get_connect_udp_upstream().is_err());
+    }
+}
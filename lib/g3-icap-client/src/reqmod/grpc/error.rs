@@ -0,0 +1,44 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::io;
+
+use thiserror::Error;
+
+use g3_http::client::HttpResponseParseError;
+use g3_http::server::HttpRequestParseError;
+
+use crate::reqmod::IcapReqmodParseError;
+
+#[derive(Debug, Error)]
+pub enum GrpcAdaptationError {
+    #[error("failed to get icap connection: {0:?}")]
+    IcapServerConnectFailed(anyhow::Error),
+    #[error("write to icap server failed: {0:?}")]
+    IcapServerWriteFailed(io::Error),
+    #[error("invalid response from icap server: {0}")]
+    InvalidIcapServerResponse(#[from] IcapReqmodParseError),
+    #[error("read from icap server failed: {0:?}")]
+    IcapServerReadFailed(io::Error),
+    #[error("invalid http request from icap server: {0}")]
+    InvalidIcapServerHttpRequest(#[from] HttpRequestParseError),
+    #[error("adapted message too large: {0}")]
+    AdaptedMessageTooLarge(usize),
+    #[error("invalid http error response from icap server: {0}")]
+    InvalidIcapServerHttpResponse(#[from] HttpResponseParseError),
+    #[error("error response from icap server: {0} {1}")]
+    IcapServerErrorResponse(u16, String),
+}
@@ -0,0 +1,382 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::io::{IoSlice, Write};
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use bytes::{BufMut, Bytes};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+use g3_http::server::HttpAdaptedRequest;
+use g3_http::HttpBodyDecodeReader;
+use g3_io_ext::LimitedWriteExt;
+
+use super::response::ReqmodResponse;
+use super::{IcapReqmodClient, IcapReqmodResponsePayload};
+use crate::{IcapClientConnection, IcapServiceClient};
+
+pub use crate::reqmod::h1::HttpAdapterErrorResponse;
+
+mod error;
+pub use error::GrpcAdaptationError;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GrpcMessageDirection {
+    Request,
+    Response,
+}
+
+impl GrpcMessageDirection {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GrpcMessageDirection::Request => "request",
+            GrpcMessageDirection::Response => "response",
+        }
+    }
+}
+
+pub enum GrpcMessageAuditResult {
+    /// the icap server has no modification on the message
+    Accepted,
+    /// the icap server has modified the message, the new message payload is returned
+    Modified(Bytes),
+    /// the icap server want to reject the message
+    Rejected(HttpAdapterErrorResponse),
+}
+
+impl IcapReqmodClient {
+    pub fn grpc_message_auditor(&self, service: &str, method: &str) -> GrpcMessageAuditor {
+        GrpcMessageAuditor {
+            icap_client: self.inner.clone(),
+            path: format!("/{service}/{method}"),
+            authority: None,
+            message_max_size: usize::MAX,
+            client_addr: None,
+            client_username: None,
+        }
+    }
+}
+
+/// Send decoded gRPC messages to the ICAP server for audit.
+///
+/// Each message will be sent as the body of a HTTP POST request in a standalone
+/// REQMOD request. The caller should forward, replace or reject the message
+/// according to the audit result.
+pub struct GrpcMessageAuditor {
+    icap_client: Arc<IcapServiceClient>,
+    path: String,
+    authority: Option<String>,
+    message_max_size: usize,
+    client_addr: Option<SocketAddr>,
+    client_username: Option<Arc<str>>,
+}
+
+impl GrpcMessageAuditor {
+    pub fn set_authority(&mut self, authority: String) {
+        self.authority = Some(authority);
+    }
+
+    /// Set the max size of the adapted message that will be accepted
+    pub fn set_message_max_size(&mut self, size: usize) {
+        self.message_max_size = size;
+    }
+
+    pub fn set_client_addr(&mut self, addr: SocketAddr) {
+        self.client_addr = Some(addr);
+    }
+
+    pub fn set_client_username(&mut self, user: Arc<str>) {
+        self.client_username = Some(user);
+    }
+
+    fn build_http_header(
+        &self,
+        direction: GrpcMessageDirection,
+        index: usize,
+        compressed: bool,
+        size: usize,
+    ) -> Vec<u8> {
+        let mut header = Vec::with_capacity(256);
+        let _ = write!(&mut header, "POST {} HTTP/1.1\r\n", self.path);
+        if let Some(authority) = &self.authority {
+            let _ = write!(&mut header, "Host: {authority}\r\n");
+        }
+        header.put_slice(b"Content-Type: application/grpc-message\r\n");
+        let _ = write!(&mut header, "Content-Length: {size}\r\n");
+        let _ = write!(&mut header, "X-gRPC-Direction: {}\r\n", direction.as_str());
+        let _ = write!(&mut header, "X-gRPC-Message-Index: {index}\r\n");
+        if compressed {
+            header.put_slice(b"X-gRPC-Message-Compressed: true\r\n");
+        }
+        header.put_slice(b"\r\n");
+        header
+    }
+
+    fn build_icap_header(&self, http_header_len: usize, has_body: bool) -> Vec<u8> {
+        let mut header = Vec::with_capacity(self.icap_client.partial_request_header.len() + 128);
+        header.extend_from_slice(&self.icap_client.partial_request_header);
+        header.put_slice(b"Allow: 204\r\n");
+        header.put_slice(b"X-Transformed-From: gRPC\r\n");
+        if let Some(addr) = self.client_addr {
+            crate::serialize::add_client_addr(&mut header, addr);
+        }
+        if let Some(user) = &self.client_username {
+            crate::serialize::add_client_username(&mut header, user);
+        }
+        if has_body {
+            let _ = write!(
+                header,
+                "Encapsulated: req-hdr=0, req-body={http_header_len}\r\n"
+            );
+        } else {
+            let _ = write!(
+                header,
+                "Encapsulated: req-hdr=0, null-body={http_header_len}\r\n"
+            );
+        }
+        header.put_slice(b"\r\n");
+        header
+    }
+
+    pub async fn audit_message(
+        &self,
+        direction: GrpcMessageDirection,
+        index: usize,
+        compressed: bool,
+        data: &[u8],
+    ) -> Result<GrpcMessageAuditResult, GrpcAdaptationError> {
+        let (mut icap_connection, _icap_options) = self
+            .icap_client
+            .fetch_connection()
+            .await
+            .map_err(GrpcAdaptationError::IcapServerConnectFailed)?;
+
+        let http_header = self.build_http_header(direction, index, compressed, data.len());
+        let icap_header = self.build_icap_header(http_header.len(), !data.is_empty());
+
+        let icap_w = &mut icap_connection.0;
+        if data.is_empty() {
+            icap_w
+                .write_all_vectored([IoSlice::new(&icap_header), IoSlice::new(&http_header)])
+                .await
+                .map_err(GrpcAdaptationError::IcapServerWriteFailed)?;
+        } else {
+            let chunk_header = format!("{:x}\r\n", data.len());
+            icap_w
+                .write_all_vectored([
+                    IoSlice::new(&icap_header),
+                    IoSlice::new(&http_header),
+                    IoSlice::new(chunk_header.as_bytes()),
+                    IoSlice::new(data),
+                    IoSlice::new(b"\r\n0\r\n\r\n"),
+                ])
+                .await
+                .map_err(GrpcAdaptationError::IcapServerWriteFailed)?;
+        }
+        icap_w
+            .flush()
+            .await
+            .map_err(GrpcAdaptationError::IcapServerWriteFailed)?;
+
+        let rsp = ReqmodResponse::parse(
+            &mut icap_connection.1,
+            self.icap_client.config.icap_max_header_size,
+            &self.icap_client.config.respond_shared_names,
+        )
+        .await?;
+
+        match rsp.payload {
+            IcapReqmodResponsePayload::NoPayload => {
+                if rsp.code == 204 {
+                    if rsp.keep_alive {
+                        self.icap_client.save_connection(icap_connection).await;
+                    }
+                    Ok(GrpcMessageAuditResult::Accepted)
+                } else {
+                    Err(GrpcAdaptationError::IcapServerErrorResponse(
+                        rsp.code, rsp.reason,
+                    ))
+                }
+            }
+            IcapReqmodResponsePayload::HttpRequestWithoutBody(header_size) => {
+                let _http_req =
+                    HttpAdaptedRequest::parse(&mut icap_connection.1, header_size, true).await?;
+                if rsp.keep_alive {
+                    self.icap_client.save_connection(icap_connection).await;
+                }
+                Ok(GrpcMessageAuditResult::Modified(Bytes::new()))
+            }
+            IcapReqmodResponsePayload::HttpRequestWithBody(header_size) => {
+                let _http_req =
+                    HttpAdaptedRequest::parse(&mut icap_connection.1, header_size, true).await?;
+                let data = self
+                    .recv_adapted_message(icap_connection, rsp.keep_alive)
+                    .await?;
+                Ok(GrpcMessageAuditResult::Modified(data))
+            }
+            IcapReqmodResponsePayload::HttpResponseWithoutBody(header_size)
+            | IcapReqmodResponsePayload::HttpResponseWithBody(header_size) => {
+                let http_rsp =
+                    HttpAdapterErrorResponse::parse(&mut icap_connection.1, header_size).await?;
+                Ok(GrpcMessageAuditResult::Rejected(http_rsp))
+            }
+        }
+    }
+
+    async fn recv_adapted_message(
+        &self,
+        mut icap_connection: IcapClientConnection,
+        keep_alive: bool,
+    ) -> Result<Bytes, GrpcAdaptationError> {
+        let mut data = Vec::new();
+        let mut body_reader = HttpBodyDecodeReader::new_chunked(&mut icap_connection.1, 256);
+        let limit = self.message_max_size.saturating_add(1) as u64;
+        let nr = (&mut body_reader)
+            .take(limit)
+            .read_to_end(&mut data)
+            .await
+            .map_err(GrpcAdaptationError::IcapServerReadFailed)?;
+        if nr > self.message_max_size {
+            // the connection is dropped as we won't read the remaining payload
+            return Err(GrpcAdaptationError::AdaptedMessageTooLarge(nr));
+        }
+        if keep_alive && body_reader.trailer(128).await.is_ok() {
+            self.icap_client.save_connection(icap_connection).await;
+        }
+        Ok(Bytes::from(data))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{IcapMethod, IcapServiceConfig};
+    use tokio::io::AsyncReadExt;
+    use tokio::net::{TcpListener, TcpStream};
+    use url::Url;
+
+    const OPTIONS_RESPONSE: &[u8] = b"ICAP/1.0 200 OK\r\n\
+        Methods: REQMOD\r\n\
+        ISTag: \"test\"\r\n\
+        Encapsulated: null-body=0\r\n\r\n";
+
+    async fn read_until(stream: &mut TcpStream, end: &[u8]) -> Option<Vec<u8>> {
+        let mut buf = Vec::new();
+        let mut tmp = [0u8; 1];
+        while !buf.ends_with(end) {
+            let nr = stream.read(&mut tmp).await.ok()?;
+            if nr == 0 {
+                return None;
+            }
+            buf.extend_from_slice(&tmp[..nr]);
+        }
+        Some(buf)
+    }
+
+    async fn serve(mut stream: TcpStream, icap_rsp: &[u8]) -> Option<Vec<u8>> {
+        loop {
+            let head = read_until(&mut stream, b"\r\n\r\n").await?;
+            if head.starts_with(b"OPTIONS ") {
+                stream.write_all(OPTIONS_RESPONSE).await.ok()?;
+                continue;
+            }
+            let mut req = head;
+            if !req.ends_with(b"0\r\n\r\n") {
+                req.extend(read_until(&mut stream, b"0\r\n\r\n").await?);
+            }
+            stream.write_all(icap_rsp).await.ok()?;
+            return Some(req);
+        }
+    }
+
+    async fn run_audit(icap_rsp: &'static [u8], data: &[u8]) -> (Vec<u8>, GrpcMessageAuditResult) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (req_sender, req_receiver) = tokio::sync::oneshot::channel();
+        tokio::spawn(async move {
+            let mut req_sender = Some(req_sender);
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                if let Some(req) = serve(stream, icap_rsp).await {
+                    if let Some(sender) = req_sender.take() {
+                        let _ = sender.send(req);
+                    }
+                }
+            }
+        });
+
+        let url = Url::parse(&format!("icap://{addr}/reqmod")).unwrap();
+        let config = IcapServiceConfig::new(IcapMethod::Reqmod, url).unwrap();
+        let client = IcapServiceClient::new(Arc::new(config)).unwrap();
+        let reqmod = IcapReqmodClient::new(Arc::new(client));
+        let mut auditor = reqmod.grpc_message_auditor("test.Echo", "Say");
+        auditor.set_message_max_size(16);
+
+        let r = auditor
+            .audit_message(GrpcMessageDirection::Request, 1, false, data)
+            .await
+            .unwrap();
+        (req_receiver.await.unwrap(), r)
+    }
+
+    #[tokio::test]
+    async fn audit_accepted() {
+        let (req, r) = run_audit(
+            b"ICAP/1.0 204 No Content\r\nISTag: \"test\"\r\nEncapsulated: null-body=0\r\n\r\n",
+            b"hello",
+        )
+        .await;
+        let req = String::from_utf8(req).unwrap();
+        assert!(req.contains("POST /test.Echo/Say HTTP/1.1\r\n"));
+        assert!(req.contains("X-gRPC-Direction: request\r\n"));
+        assert!(req.contains("X-gRPC-Message-Index: 1\r\n"));
+        assert!(req.ends_with("5\r\nhello\r\n0\r\n\r\n"));
+        assert!(matches!(r, GrpcMessageAuditResult::Accepted));
+    }
+
+    #[tokio::test]
+    async fn audit_modified() {
+        let (_, r) = run_audit(
+            b"ICAP/1.0 200 OK\r\nISTag: \"test\"\r\n\
+            Encapsulated: req-hdr=0, req-body=51\r\n\r\n\
+            POST /test.Echo/Say HTTP/1.1\r\n\
+            Content-Length: 3\r\n\r\n\
+            3\r\nnew\r\n0\r\n\r\n",
+            b"hello",
+        )
+        .await;
+        let GrpcMessageAuditResult::Modified(data) = r else {
+            panic!("not modified");
+        };
+        assert_eq!(data.as_ref(), b"new");
+    }
+
+    #[tokio::test]
+    async fn audit_rejected() {
+        let (_, r) = run_audit(
+            b"ICAP/1.0 200 OK\r\nISTag: \"test\"\r\n\
+            Encapsulated: res-hdr=0, null-body=26\r\n\r\n\
+            HTTP/1.1 403 Forbidden\r\n\r\n",
+            b"hello",
+        )
+        .await;
+        let GrpcMessageAuditResult::Rejected(rsp) = r else {
+            panic!("not rejected");
+        };
+        assert_eq!(rsp.status.as_u16(), 403);
+    }
+}
@@ -136,6 +136,15 @@ impl<I: IdleCheck> H2RequestAdapter<I> {
     }
 
     pub async fn xfer_connect(
+        self,
+        state: &mut ReqmodAdaptationRunState,
+        http_request: Request<()>,
+    ) -> Result<ReqmodAdaptationMidState, H2ReqmodAdaptationError> {
+        self.xfer_header_only(state, http_request).await
+    }
+
+    /// Adapt the request header only, the body, if any, should be transferred by the caller
+    pub async fn xfer_header_only(
         mut self,
         state: &mut ReqmodAdaptationRunState,
         http_request: Request<()>,
@@ -184,7 +193,7 @@ impl<I: IdleCheck> H2RequestAdapter<I> {
                     // just drop the icap connection
                     Err(H2ReqmodAdaptationError::InvalidIcapServerResponse(
                         IcapReqmodParseError::UnsupportedBody(
-                            "no body should be set for CONNECT header",
+                            "no body should be set for header only request",
                         ),
                     ))
                 }
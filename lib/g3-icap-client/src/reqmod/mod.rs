@@ -34,6 +34,8 @@ pub mod mail;
 pub mod imap;
 pub mod smtp;
 
+pub mod grpc;
+
 #[derive(Clone)]
 pub struct IcapReqmodClient {
     inner: Arc<IcapServiceClient>,
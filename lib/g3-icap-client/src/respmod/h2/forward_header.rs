@@ -21,14 +21,15 @@ use http::{Request, Response};
 use tokio::io::AsyncWriteExt;
 
 use g3_h2::{RequestExt, ResponseExt};
+use g3_http::client::HttpAdaptedResponse;
 use g3_io_ext::{IdleCheck, LimitedWriteExt};
 
 use super::{
     H2RespmodAdaptationError, H2ResponseAdapter, H2SendResponseToClient, RespmodAdaptationEndState,
-    RespmodAdaptationRunState,
+    RespmodAdaptationMidState, RespmodAdaptationRunState,
 };
 use crate::respmod::response::RespmodResponse;
-use crate::respmod::IcapRespmodResponsePayload;
+use crate::respmod::{IcapRespmodParseError, IcapRespmodResponsePayload};
 
 impl<I: IdleCheck> H2ResponseAdapter<I> {
     fn build_header_only_request(
@@ -131,4 +132,84 @@ impl<I: IdleCheck> H2ResponseAdapter<I> {
             }
         }
     }
+
+    /// Adapt the response header only, the body, if any, should be transferred by the caller
+    pub async fn xfer_header_only(
+        mut self,
+        http_request: &Request<()>,
+        http_response: Response<()>,
+    ) -> Result<RespmodAdaptationMidState, H2RespmodAdaptationError> {
+        let http_req_header = http_request.serialize_for_adapter();
+        let http_rsp_header = http_response.serialize_for_adapter();
+        let icap_header =
+            self.build_header_only_request(http_req_header.len(), http_rsp_header.len());
+
+        let icap_w = &mut self.icap_connection.0;
+        icap_w
+            .write_all_vectored([
+                IoSlice::new(&icap_header),
+                IoSlice::new(&http_req_header),
+                IoSlice::new(&http_rsp_header),
+            ])
+            .await
+            .map_err(H2RespmodAdaptationError::IcapServerWriteFailed)?;
+        icap_w
+            .flush()
+            .await
+            .map_err(H2RespmodAdaptationError::IcapServerWriteFailed)?;
+
+        let rsp = RespmodResponse::parse(
+            &mut self.icap_connection.1,
+            self.icap_client.config.icap_max_header_size,
+        )
+        .await?;
+
+        match rsp.code {
+            204 => {
+                if rsp.keep_alive && rsp.payload == IcapRespmodResponsePayload::NoPayload {
+                    self.icap_client.save_connection(self.icap_connection).await;
+                }
+                Ok(RespmodAdaptationMidState::OriginalResponse(http_response))
+            }
+            n if (200..300).contains(&n) => match rsp.payload {
+                IcapRespmodResponsePayload::NoPayload => {
+                    if rsp.keep_alive {
+                        self.icap_client.save_connection(self.icap_connection).await;
+                    }
+                    // there should be a payload
+                    Err(H2RespmodAdaptationError::IcapServerErrorResponse(
+                        rsp.code, rsp.reason,
+                    ))
+                }
+                IcapRespmodResponsePayload::HttpResponseWithoutBody(header_size) => {
+                    let http_rsp =
+                        HttpAdaptedResponse::parse(&mut self.icap_connection.1, header_size)
+                            .await?;
+                    if rsp.keep_alive {
+                        self.icap_client.save_connection(self.icap_connection).await;
+                    }
+                    let final_rsp = http_response.adapt_to(&http_rsp);
+                    Ok(RespmodAdaptationMidState::AdaptedResponse(
+                        http_rsp, final_rsp,
+                    ))
+                }
+                IcapRespmodResponsePayload::HttpResponseWithBody(_) => {
+                    // just drop the icap connection
+                    Err(H2RespmodAdaptationError::InvalidIcapServerResponse(
+                        IcapRespmodParseError::UnsupportedBody(
+                            "no body should be set for header only response",
+                        ),
+                    ))
+                }
+            },
+            _ => {
+                if rsp.keep_alive && rsp.payload == IcapRespmodResponsePayload::NoPayload {
+                    self.icap_client.save_connection(self.icap_connection).await;
+                }
+                Err(H2RespmodAdaptationError::IcapServerErrorResponse(
+                    rsp.code, rsp.reason,
+                ))
+            }
+        }
+    }
 }
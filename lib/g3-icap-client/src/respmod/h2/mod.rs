@@ -206,3 +206,8 @@ pub enum RespmodAdaptationEndState {
     OriginalTransferred,
     AdaptedTransferred(HttpAdaptedResponse),
 }
+
+pub enum RespmodAdaptationMidState {
+    OriginalResponse(Response<()>),
+    AdaptedResponse(HttpAdaptedResponse, Response<()>),
+}
@@ -40,6 +40,12 @@ pub use line_recv_vec::LineRecvVec;
 mod ext;
 pub use ext::{LimitedBufReadExt, LimitedWriteExt};
 
+mod msg_stream;
+pub use msg_stream::LengthPrefixedMsgStream;
+
+mod msg_socket;
+pub use msg_socket::{MsgQuerySocket, MsgStreamPeer};
+
 mod idle;
 pub use idle::{IdleCheck, IdleForceQuitReason};
 
@@ -0,0 +1,262 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+#[cfg(unix)]
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use log::warn;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::{TcpStream, UdpSocket};
+
+use super::LengthPrefixedMsgStream;
+
+const UDP_RECV_BUF_SIZE: usize = 16384;
+
+trait QueryStream: AsyncRead + AsyncWrite + Send + Unpin {}
+
+impl<T: AsyncRead + AsyncWrite + Send + Unpin> QueryStream for T {}
+
+type BoxQueryStream = Box<dyn QueryStream>;
+type ConnectFuture = Pin<Box<dyn Future<Output = io::Result<BoxQueryStream>> + Send>>;
+
+/// The peer address of a stream based [`MsgQuerySocket`]
+#[derive(Clone)]
+pub enum MsgStreamPeer {
+    Tcp(SocketAddr),
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+impl MsgStreamPeer {
+    fn connect(&self) -> ConnectFuture {
+        match self {
+            MsgStreamPeer::Tcp(addr) => {
+                let addr = *addr;
+                Box::pin(async move {
+                    let stream = TcpStream::connect(addr).await?;
+                    stream.set_nodelay(true)?;
+                    Ok(Box::new(stream) as BoxQueryStream)
+                })
+            }
+            #[cfg(unix)]
+            MsgStreamPeer::Unix(path) => {
+                let path = path.clone();
+                Box::pin(async move {
+                    let stream = tokio::net::UnixStream::connect(path).await?;
+                    Ok(Box::new(stream) as BoxQueryStream)
+                })
+            }
+        }
+    }
+}
+
+enum StreamState {
+    Idle,
+    Connecting(ConnectFuture),
+    Connected(LengthPrefixedMsgStream<BoxQueryStream>),
+}
+
+struct StreamQuerySocket {
+    peer: MsgStreamPeer,
+    max_msg_size: usize,
+    state: StreamState,
+}
+
+impl StreamQuerySocket {
+    fn poll_recv(&mut self, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        let StreamState::Connected(stream) = &mut self.state else {
+            // a new connection will be made when we have new requests to send
+            return Poll::Pending;
+        };
+        match ready!(stream.poll_recv_msg(cx)) {
+            Ok(Some(msg)) => {
+                buf.put_slice(&msg);
+                Poll::Ready(Ok(()))
+            }
+            Ok(None) => {
+                warn!("query stream closed by peer");
+                self.state = StreamState::Idle;
+                Poll::Pending
+            }
+            Err(e) => {
+                warn!("query stream recv error: {e:?}");
+                self.state = StreamState::Idle;
+                Poll::Pending
+            }
+        }
+    }
+
+    fn poll_send(&mut self, cx: &mut Context<'_>, msg: &[u8]) -> Poll<io::Result<()>> {
+        loop {
+            match &mut self.state {
+                StreamState::Idle => self.state = StreamState::Connecting(self.peer.connect()),
+                StreamState::Connecting(fut) => match ready!(fut.as_mut().poll(cx)) {
+                    Ok(stream) => {
+                        self.state = StreamState::Connected(LengthPrefixedMsgStream::new(
+                            stream,
+                            self.max_msg_size,
+                        ));
+                        // make sure the recv side of the new connection will be polled
+                        cx.waker().wake_by_ref();
+                    }
+                    Err(e) => {
+                        warn!("failed to connect to query peer: {e:?}");
+                        self.state = StreamState::Idle;
+                        return Poll::Ready(Err(e));
+                    }
+                },
+                StreamState::Connected(stream) => {
+                    return match stream.poll_send_msg(cx, msg) {
+                        Poll::Ready(Err(e)) => {
+                            warn!("query stream send error: {e:?}");
+                            self.state = StreamState::Idle;
+                            Poll::Ready(Err(e))
+                        }
+                        r => r,
+                    };
+                }
+            }
+        }
+    }
+
+    fn poll_flush(&mut self, cx: &mut Context<'_>) {
+        if let StreamState::Connected(stream) = &mut self.state {
+            if let Poll::Ready(Err(e)) = stream.poll_flush(cx) {
+                warn!("query stream flush error: {e:?}");
+                self.state = StreamState::Idle;
+            }
+        }
+    }
+}
+
+enum QuerySocket {
+    Udp(UdpSocket),
+    Stream(StreamQuerySocket),
+}
+
+/// A message based socket to send queries to and receive responses from the peer.
+///
+/// For stream transport, each message is length prefixed, and the connection will be
+/// re-established when there are new queries to send after it's closed.
+pub struct MsgQuerySocket {
+    inner: QuerySocket,
+}
+
+impl MsgQuerySocket {
+    pub fn new_udp(socket: UdpSocket) -> Self {
+        MsgQuerySocket {
+            inner: QuerySocket::Udp(socket),
+        }
+    }
+
+    pub fn new_stream(peer: MsgStreamPeer, max_msg_size: usize) -> Self {
+        MsgQuerySocket {
+            inner: QuerySocket::Stream(StreamQuerySocket {
+                peer,
+                max_msg_size,
+                state: StreamState::Idle,
+            }),
+        }
+    }
+
+    pub fn max_recv_size(&self) -> usize {
+        match &self.inner {
+            QuerySocket::Udp(_) => UDP_RECV_BUF_SIZE,
+            QuerySocket::Stream(socket) => socket.max_msg_size,
+        }
+    }
+
+    pub fn poll_recv(
+        &mut self,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        match &mut self.inner {
+            QuerySocket::Udp(socket) => socket.poll_recv(cx, buf),
+            QuerySocket::Stream(socket) => socket.poll_recv(cx, buf),
+        }
+    }
+
+    pub fn poll_send(&mut self, cx: &mut Context<'_>, msg: &[u8]) -> Poll<io::Result<()>> {
+        match &mut self.inner {
+            QuerySocket::Udp(socket) => socket.poll_send(cx, msg).map_ok(|_| ()),
+            QuerySocket::Stream(socket) => socket.poll_send(cx, msg),
+        }
+    }
+
+    pub fn poll_flush(&mut self, cx: &mut Context<'_>) {
+        if let QuerySocket::Stream(socket) = &mut self.inner {
+            socket.poll_flush(cx);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::future::poll_fn;
+    use tokio::net::TcpListener;
+
+    async fn query(socket: &mut MsgQuerySocket, msg: &[u8]) -> Vec<u8> {
+        poll_fn(|cx| socket.poll_send(cx, msg)).await.unwrap();
+        poll_fn(|cx| {
+            socket.poll_flush(cx);
+            Poll::Ready(())
+        })
+        .await;
+
+        let mut buf = vec![0u8; socket.max_recv_size()];
+        let mut buf = ReadBuf::new(&mut buf);
+        poll_fn(|cx| socket.poll_recv(cx, &mut buf)).await.unwrap();
+        buf.filled().to_vec()
+    }
+
+    #[tokio::test]
+    async fn stream_reconnect() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            // serve one message per connection
+            for _ in 0..2 {
+                let (stream, _) = listener.accept().await.unwrap();
+                let mut stream = LengthPrefixedMsgStream::new(stream, 1024);
+                let msg = stream.recv_msg().await.unwrap().unwrap();
+                stream.send_msg(&msg).await.unwrap();
+            }
+        });
+
+        let mut socket = MsgQuerySocket::new_stream(MsgStreamPeer::Tcp(addr), 1024);
+        assert_eq!(socket.max_recv_size(), 1024);
+        assert_eq!(query(&mut socket, b"first").await, b"first");
+
+        // the recv side will notice the close and reset the connection
+        let mut buf = [0u8; 16];
+        let mut buf = ReadBuf::new(&mut buf);
+        let r = tokio::time::timeout(
+            std::time::Duration::from_millis(100),
+            poll_fn(|cx| socket.poll_recv(cx, &mut buf)),
+        )
+        .await;
+        assert!(r.is_err());
+
+        assert_eq!(query(&mut socket, b"second").await, b"second");
+        server.await.unwrap();
+    }
+}
@@ -0,0 +1,226 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::future::poll_fn;
+use std::io;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+const MSG_HEADER_SIZE: usize = 4;
+const MIN_READ_BUF_SIZE: usize = 4096;
+const MAX_PENDING_WRITE_SIZE: usize = 16384;
+
+/// A message stream with each message prefixed by its length as a 4 bytes big endian integer
+pub struct LengthPrefixedMsgStream<S> {
+    io: S,
+    max_msg_size: usize,
+    read_buf: Vec<u8>,
+    read_end: usize,
+    write_buf: Vec<u8>,
+    write_offset: usize,
+}
+
+impl<S> LengthPrefixedMsgStream<S> {
+    pub fn new(io: S, max_msg_size: usize) -> Self {
+        LengthPrefixedMsgStream {
+            io,
+            max_msg_size,
+            read_buf: vec![0; MIN_READ_BUF_SIZE],
+            read_end: 0,
+            write_buf: Vec::with_capacity(MIN_READ_BUF_SIZE),
+            write_offset: 0,
+        }
+    }
+
+    pub fn into_inner(self) -> S {
+        self.io
+    }
+
+    fn buffered_msg_size(&self) -> io::Result<Option<usize>> {
+        if self.read_end < MSG_HEADER_SIZE {
+            return Ok(None);
+        }
+        let mut hdr = [0u8; MSG_HEADER_SIZE];
+        hdr.copy_from_slice(&self.read_buf[..MSG_HEADER_SIZE]);
+        let size = u32::from_be_bytes(hdr) as usize;
+        if size > self.max_msg_size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("too large msg size {size}"),
+            ));
+        }
+        Ok(Some(size))
+    }
+
+    fn take_msg(&mut self, size: usize) -> Vec<u8> {
+        let end = MSG_HEADER_SIZE + size;
+        let msg = self.read_buf[MSG_HEADER_SIZE..end].to_vec();
+        self.read_buf.copy_within(end..self.read_end, 0);
+        self.read_end -= end;
+        msg
+    }
+
+    fn push_msg(&mut self, msg: &[u8]) -> io::Result<()> {
+        if msg.len() > self.max_msg_size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("too large msg size {}", msg.len()),
+            ));
+        }
+        self.write_buf
+            .extend_from_slice(&(msg.len() as u32).to_be_bytes());
+        self.write_buf.extend_from_slice(msg);
+        Ok(())
+    }
+}
+
+impl<S> LengthPrefixedMsgStream<S>
+where
+    S: AsyncRead + Unpin,
+{
+    /// Receive the next message, `None` will be returned if the stream is closed cleanly
+    pub fn poll_recv_msg(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<Option<Vec<u8>>>> {
+        loop {
+            let required = match self.buffered_msg_size()? {
+                Some(size) => {
+                    if self.read_end >= MSG_HEADER_SIZE + size {
+                        return Poll::Ready(Ok(Some(self.take_msg(size))));
+                    }
+                    MSG_HEADER_SIZE + size
+                }
+                None => MSG_HEADER_SIZE,
+            };
+            if self.read_buf.len() < required {
+                self.read_buf.resize(required, 0);
+            }
+
+            let mut buf = ReadBuf::new(&mut self.read_buf[self.read_end..]);
+            ready!(Pin::new(&mut self.io).poll_read(cx, &mut buf))?;
+            let nr = buf.filled().len();
+            if nr == 0 {
+                return if self.read_end == 0 {
+                    Poll::Ready(Ok(None))
+                } else {
+                    Poll::Ready(Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "stream closed with incomplete msg",
+                    )))
+                };
+            }
+            self.read_end += nr;
+        }
+    }
+
+    pub async fn recv_msg(&mut self) -> io::Result<Option<Vec<u8>>> {
+        poll_fn(|cx| self.poll_recv_msg(cx)).await
+    }
+}
+
+impl<S> LengthPrefixedMsgStream<S>
+where
+    S: AsyncWrite + Unpin,
+{
+    /// Queue the message for sending, the queued data will be sent out opportunistically,
+    /// but the caller should still call [`Self::poll_flush`] to make sure all of them are sent
+    pub fn poll_send_msg(&mut self, cx: &mut Context<'_>, msg: &[u8]) -> Poll<io::Result<()>> {
+        if self.write_buf.len() - self.write_offset >= MAX_PENDING_WRITE_SIZE {
+            ready!(self.poll_flush(cx))?;
+        }
+        self.push_msg(msg)?;
+        if let Poll::Ready(Err(e)) = self.poll_flush(cx) {
+            return Poll::Ready(Err(e));
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    pub fn poll_flush(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.write_offset < self.write_buf.len() {
+            let nw = ready!(
+                Pin::new(&mut self.io).poll_write(cx, &self.write_buf[self.write_offset..])
+            )?;
+            if nw == 0 {
+                return Poll::Ready(Err(io::Error::new(
+                    io::ErrorKind::WriteZero,
+                    "write zero byte into stream",
+                )));
+            }
+            self.write_offset += nw;
+        }
+        self.write_buf.clear();
+        self.write_offset = 0;
+        Pin::new(&mut self.io).poll_flush(cx)
+    }
+
+    pub async fn send_msg(&mut self, msg: &[u8]) -> io::Result<()> {
+        poll_fn(|cx| self.poll_send_msg(cx, msg)).await?;
+        poll_fn(|cx| self.poll_flush(cx)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn pipelined() {
+        let (client, server) = tokio::io::duplex(8);
+        let mut client = LengthPrefixedMsgStream::new(client, 1024);
+        let mut server = LengthPrefixedMsgStream::new(server, 1024);
+
+        let send = async move {
+            client.send_msg(b"first message").await.unwrap();
+            client.send_msg(b"").await.unwrap();
+            client.send_msg(b"third message").await.unwrap();
+        };
+        let recv = async {
+            let mut msgs = Vec::new();
+            while let Some(msg) = server.recv_msg().await.unwrap() {
+                msgs.push(msg);
+            }
+            msgs
+        };
+        let (_, msgs) = tokio::join!(send, recv);
+        assert_eq!(msgs.len(), 3);
+        assert_eq!(msgs[0], b"first message");
+        assert!(msgs[1].is_empty());
+        assert_eq!(msgs[2], b"third message");
+    }
+
+    #[tokio::test]
+    async fn too_large() {
+        let (client, server) = tokio::io::duplex(1024);
+        let mut client = LengthPrefixedMsgStream::new(client, 1024);
+        let mut server = LengthPrefixedMsgStream::new(server, 8);
+
+        client.send_msg(b"a long message").await.unwrap();
+        let e = server.recv_msg().await.unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[tokio::test]
+    async fn incomplete() {
+        let (mut client, server) = tokio::io::duplex(1024);
+        let mut server = LengthPrefixedMsgStream::new(server, 1024);
+
+        tokio::io::AsyncWriteExt::write_all(&mut client, &[0, 0, 0, 8, 1, 2])
+            .await
+            .unwrap();
+        drop(client);
+        let e = server.recv_msg().await.unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
@@ -15,23 +15,38 @@
  */
 
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+#[cfg(unix)]
+use std::path::PathBuf;
 use std::time::Duration;
 
 use anyhow::anyhow;
 use tokio::net::UdpSocket;
 
+use g3_io_ext::{MsgQuerySocket, MsgStreamPeer};
 use g3_types::net::SocketBufferConfig;
 
 use super::{IpLocationQueryRuntime, IpLocationServiceHandle};
 
+const STREAM_MSG_MAX_SIZE: usize = 16384;
+
 #[cfg(feature = "yaml")]
 mod yaml;
 
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum QueryTransport {
+    #[default]
+    Udp,
+    Tcp,
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct IpLocateServiceConfig {
     pub(crate) cache_request_batch_count: usize,
     pub(crate) cache_request_timeout: Duration,
     pub(crate) query_peer_addr: SocketAddr,
+    pub(crate) query_transport: QueryTransport,
     pub(crate) query_socket_buffer: SocketBufferConfig,
     pub(crate) query_wait_timeout: Duration,
     pub(crate) default_expire_ttl: u32,
@@ -44,6 +59,7 @@ impl Default for IpLocateServiceConfig {
             cache_request_batch_count: 10,
             cache_request_timeout: Duration::from_secs(2),
             query_peer_addr: SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 2888),
+            query_transport: QueryTransport::default(),
             query_socket_buffer: SocketBufferConfig::default(),
             query_wait_timeout: Duration::from_secs(1),
             default_expire_ttl: 10,
@@ -65,6 +81,10 @@ impl IpLocateServiceConfig {
         self.query_peer_addr = addr;
     }
 
+    pub fn set_query_transport(&mut self, transport: QueryTransport) {
+        self.query_transport = transport;
+    }
+
     pub fn set_query_socket_buffer(&mut self, config: SocketBufferConfig) {
         self.query_socket_buffer = config;
     }
@@ -81,7 +101,7 @@ impl IpLocateServiceConfig {
         self.maximum_expire_ttl = ttl;
     }
 
-    pub fn spawn_ip_locate_agent(&self) -> anyhow::Result<IpLocationServiceHandle> {
+    fn new_udp_socket(&self) -> anyhow::Result<std::net::UdpSocket> {
         use anyhow::Context;
 
         let socket = g3_socket::udp::new_std_socket_to(
@@ -97,17 +117,50 @@ impl IpLocateServiceConfig {
                 self.query_peer_addr
             )
         })?;
+        Ok(socket)
+    }
+
+    fn stream_peer(&self) -> Option<MsgStreamPeer> {
+        match &self.query_transport {
+            QueryTransport::Udp => None,
+            QueryTransport::Tcp => Some(MsgStreamPeer::Tcp(self.query_peer_addr)),
+            #[cfg(unix)]
+            QueryTransport::Unix(path) => Some(MsgStreamPeer::Unix(path.clone())),
+        }
+    }
+
+    pub fn spawn_ip_locate_agent(&self) -> anyhow::Result<IpLocationServiceHandle> {
+        use anyhow::Context;
+
+        let stream_peer = self.stream_peer();
+        let udp_socket = match stream_peer {
+            Some(_) => None,
+            None => Some(self.new_udp_socket()?),
+        };
+        let new_query_socket = move || -> anyhow::Result<MsgQuerySocket> {
+            match udp_socket {
+                Some(socket) => {
+                    let socket =
+                        UdpSocket::from_std(socket).context("failed to setup udp socket")?;
+                    Ok(MsgQuerySocket::new_udp(socket))
+                }
+                None => Ok(MsgQuerySocket::new_stream(
+                    stream_peer.unwrap(),
+                    STREAM_MSG_MAX_SIZE,
+                )),
+            }
+        };
 
         let (cache_runtime, cache_handle, query_handle) = super::crate_ip_location_cache(self);
         if let Some(rt) = crate::get_ip_locate_rt_handle() {
             let config = self.clone();
             rt.spawn(async move {
-                let socket = UdpSocket::from_std(socket).expect("failed to setup udp socket");
+                let socket = new_query_socket().expect("failed to setup query socket");
                 IpLocationQueryRuntime::new(&config, socket, query_handle).await
             });
             rt.spawn(cache_runtime);
         } else {
-            let socket = UdpSocket::from_std(socket).context("failed to setup udp socket")?;
+            let socket = new_query_socket()?;
             let query_runtime = IpLocationQueryRuntime::new(self, socket, query_handle);
             tokio::spawn(query_runtime);
             tokio::spawn(cache_runtime);
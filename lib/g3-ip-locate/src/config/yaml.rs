@@ -17,7 +17,7 @@
 use anyhow::{anyhow, Context};
 use yaml_rust::Yaml;
 
-use crate::IpLocateServiceConfig;
+use crate::{IpLocateServiceConfig, QueryTransport};
 
 impl IpLocateServiceConfig {
     fn set_query_peer_addr_by_yaml(&mut self, value: &Yaml) -> anyhow::Result<()> {
@@ -49,6 +49,22 @@ impl IpLocateServiceConfig {
                             .context(format!("invalid sockaddr str value for key {k}"))?;
                         Ok(())
                     }
+                    "query_transport" => {
+                        let transport = g3_yaml::value::as_string(v)?;
+                        match transport.to_lowercase().as_str() {
+                            "udp" => config.set_query_transport(QueryTransport::Udp),
+                            "tcp" => config.set_query_transport(QueryTransport::Tcp),
+                            _ => return Err(anyhow!("unsupported query transport {transport}")),
+                        }
+                        Ok(())
+                    }
+                    #[cfg(unix)]
+                    "query_unix_path" => {
+                        let path = g3_yaml::value::as_absolute_path(v)
+                            .context(format!("invalid absolute path value for key {k}"))?;
+                        config.set_query_transport(QueryTransport::Unix(path));
+                        Ok(())
+                    }
                     "query_socket_buffer" => {
                         let buf_config = g3_yaml::value::as_socket_buffer_config(v)
                             .context(format!("invalid socket buffer config value for key {k}"))?;
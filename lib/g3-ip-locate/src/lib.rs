@@ -24,7 +24,7 @@ use tokio::time::Instant;
 use g3_geoip_types::IpLocation;
 
 mod config;
-pub use config::IpLocateServiceConfig;
+pub use config::{IpLocateServiceConfig, QueryTransport};
 
 mod handle;
 pub use handle::IpLocationServiceHandle;
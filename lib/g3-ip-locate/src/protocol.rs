@@ -31,6 +31,9 @@ pub mod response_key {
     pub const AS_NUMBER: &str = "as_number";
     pub const ISP_NAME: &str = "isp_name";
     pub const ISP_DOMAIN: &str = "isp_domain";
+    pub const REGION: &str = "region";
+    pub const CITY: &str = "city";
+    pub const HOSTING: &str = "hosting";
 }
 
 pub mod response_key_id {
@@ -42,4 +45,7 @@ pub mod response_key_id {
     pub const AS_NUMBER: u64 = 6;
     pub const ISP_NAME: u64 = 7;
     pub const ISP_DOMAIN: u64 = 8;
+    pub const REGION: u64 = 9;
+    pub const CITY: u64 = 10;
+    pub const HOSTING: u64 = 11;
 }
@@ -25,14 +25,15 @@ use std::time::Duration;
 use anyhow::anyhow;
 use log::warn;
 use tokio::io::ReadBuf;
-use tokio::net::UdpSocket;
+
+use g3_io_ext::MsgQuerySocket;
 
 use super::{
     IpLocateServiceConfig, IpLocationCacheResponse, IpLocationQueryHandle, Request, Response,
 };
 
 pub(crate) struct IpLocationQueryRuntime {
-    socket: UdpSocket,
+    socket: MsgQuerySocket,
     query_handle: IpLocationQueryHandle,
     read_buffer: Box<[u8]>,
     write_queue: VecDeque<(IpAddr, Vec<u8>)>,
@@ -44,13 +45,14 @@ pub(crate) struct IpLocationQueryRuntime {
 impl IpLocationQueryRuntime {
     pub(crate) fn new(
         config: &IpLocateServiceConfig,
-        socket: UdpSocket,
+        socket: MsgQuerySocket,
         query_handle: IpLocationQueryHandle,
     ) -> Self {
+        let read_buffer = vec![0u8; socket.max_recv_size()].into_boxed_slice();
         IpLocationQueryRuntime {
             socket,
             query_handle,
-            read_buffer: vec![0u8; 16384].into_boxed_slice(),
+            read_buffer,
             write_queue: VecDeque::new(),
             default_expire_ttl: config.default_expire_ttl,
             maximum_expire_ttl: config.maximum_expire_ttl,
@@ -136,6 +138,7 @@ impl IpLocationQueryRuntime {
                     }
                 }
             }
+            self.socket.poll_flush(cx);
 
             // handle timeout
             loop {
@@ -78,6 +78,21 @@ impl Response {
                             .context(format!("invalid string value for key {key}"))?;
                         self.location_builder.set_isp_domain(domain);
                     }
+                    response_key::REGION => {
+                        let region = g3_msgpack::value::as_string(&v)
+                            .context(format!("invalid string value for key {key}"))?;
+                        self.location_builder.set_region(region);
+                    }
+                    response_key::CITY => {
+                        let city = g3_msgpack::value::as_string(&v)
+                            .context(format!("invalid string value for key {key}"))?;
+                        self.location_builder.set_city(city);
+                    }
+                    response_key::HOSTING => {
+                        let hosting = g3_msgpack::value::as_bool(&v)
+                            .context(format!("invalid bool value for key {key}"))?;
+                        self.location_builder.set_hosting(hosting);
+                    }
                     _ => {} // ignore unknown keys
                 }
             }
@@ -125,6 +140,21 @@ impl Response {
                             .context(format!("invalid string value for key id {key_id}"))?;
                         self.location_builder.set_isp_domain(domain);
                     }
+                    response_key_id::REGION => {
+                        let region = g3_msgpack::value::as_string(&v)
+                            .context(format!("invalid string value for key id {key_id}"))?;
+                        self.location_builder.set_region(region);
+                    }
+                    response_key_id::CITY => {
+                        let city = g3_msgpack::value::as_string(&v)
+                            .context(format!("invalid string value for key id {key_id}"))?;
+                        self.location_builder.set_city(city);
+                    }
+                    response_key_id::HOSTING => {
+                        let hosting = g3_msgpack::value::as_bool(&v)
+                            .context(format!("invalid bool value for key id {key_id}"))?;
+                        self.location_builder.set_hosting(hosting);
+                    }
                     _ => {} // ignore unknown keys
                 }
             }
@@ -179,6 +209,18 @@ impl Response {
                 ValueRef::String(continent.code().into()),
             ));
         }
+        if let Some(region) = location.region() {
+            map.push((
+                ValueRef::Integer(response_key_id::REGION.into()),
+                ValueRef::String(region.into()),
+            ));
+        }
+        if let Some(city) = location.city() {
+            map.push((
+                ValueRef::Integer(response_key_id::CITY.into()),
+                ValueRef::String(city.into()),
+            ));
+        }
         if let Some(number) = location.network_asn() {
             map.push((
                 ValueRef::Integer(response_key_id::AS_NUMBER.into()),
@@ -197,6 +239,12 @@ impl Response {
                 ValueRef::String(domain.into()),
             ));
         }
+        if location.is_hosting() {
+            map.push((
+                ValueRef::Integer(response_key_id::HOSTING.into()),
+                ValueRef::Boolean(true),
+            ));
+        }
         let mut buf = Vec::with_capacity(4096);
         let v = ValueRef::Map(map);
         rmpv::encode::write_value_ref(&mut buf, &v)
@@ -204,3 +252,46 @@ impl Response {
         Ok(buf)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    use g3_geoip_types::IsoCountryCode;
+    use ip_network::IpNetwork;
+
+    fn round_trip(location: IpLocation) -> IpLocation {
+        let ip = IpAddr::from([1, 0, 0, 1]);
+        let buf = Response::encode_new(ip, location, 60).unwrap();
+        let v = rmpv::decode::read_value_ref(&mut buf.as_slice()).unwrap();
+        let (rsp_ip, location, ttl) = Response::parse(v).unwrap().into_parts();
+        assert_eq!(rsp_ip, Some(ip));
+        assert_eq!(ttl, Some(60));
+        location.unwrap()
+    }
+
+    #[test]
+    fn region_city_hosting() {
+        let mut builder = IpLocationBuilder::default();
+        builder.set_network(IpNetwork::from_str("1.0.0.0/24").unwrap());
+        builder.set_country(IsoCountryCode::US);
+        builder.set_region("California".to_string());
+        builder.set_city("Los Angeles".to_string());
+        builder.set_as_number(13335);
+        builder.set_hosting(true);
+        let location = round_trip(builder.build().unwrap());
+        assert_eq!(location.country(), Some(IsoCountryCode::US));
+        assert_eq!(location.region(), Some("California"));
+        assert_eq!(location.city(), Some("Los Angeles"));
+        assert_eq!(location.network_asn(), Some(13335));
+        assert!(location.is_hosting());
+
+        let mut builder = IpLocationBuilder::default();
+        builder.set_network(IpNetwork::from_str("1.0.0.0/24").unwrap());
+        let location = round_trip(builder.build().unwrap());
+        assert!(location.region().is_none());
+        assert!(location.city().is_none());
+        assert!(!location.is_hosting());
+    }
+}
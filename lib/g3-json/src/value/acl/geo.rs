@@ -0,0 +1,101 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context};
+use serde_json::Value;
+
+use g3_geoip_types::{ContinentCode, IsoCountryCode};
+use g3_types::acl::{AclAction, AclAsnRule, AclContinentRule, AclCountryRule};
+
+use super::AclRuleJsonParser;
+
+impl AclRuleJsonParser for AclAsnRule {
+    #[inline]
+    fn get_default_found_action(&self) -> AclAction {
+        AclAction::Permit
+    }
+
+    #[inline]
+    fn set_missed_action(&mut self, action: AclAction) {
+        self.set_missed_action(action);
+    }
+
+    fn add_rule_for_action(&mut self, action: AclAction, value: &Value) -> anyhow::Result<()> {
+        let asn = crate::value::as_u32(value)?;
+        self.add_asn(asn, action);
+        Ok(())
+    }
+}
+
+pub(crate) fn as_asn_rule(value: &Value) -> anyhow::Result<AclAsnRule> {
+    let mut builder = AclAsnRule::new(AclAction::Forbid);
+    builder.parse(value)?;
+    Ok(builder)
+}
+
+impl AclRuleJsonParser for AclCountryRule {
+    #[inline]
+    fn get_default_found_action(&self) -> AclAction {
+        AclAction::Permit
+    }
+
+    #[inline]
+    fn set_missed_action(&mut self, action: AclAction) {
+        self.set_missed_action(action);
+    }
+
+    fn add_rule_for_action(&mut self, action: AclAction, value: &Value) -> anyhow::Result<()> {
+        let s = crate::value::as_string(value).context("invalid iso country code value")?;
+        let country =
+            IsoCountryCode::from_str(&s).map_err(|_| anyhow!("invalid iso country code {s}"))?;
+        self.add_country(country, action);
+        Ok(())
+    }
+}
+
+pub(crate) fn as_country_rule(value: &Value) -> anyhow::Result<AclCountryRule> {
+    let mut builder = AclCountryRule::new(AclAction::Forbid);
+    builder.parse(value)?;
+    Ok(builder)
+}
+
+impl AclRuleJsonParser for AclContinentRule {
+    #[inline]
+    fn get_default_found_action(&self) -> AclAction {
+        AclAction::Permit
+    }
+
+    #[inline]
+    fn set_missed_action(&mut self, action: AclAction) {
+        self.set_missed_action(action);
+    }
+
+    fn add_rule_for_action(&mut self, action: AclAction, value: &Value) -> anyhow::Result<()> {
+        let s = crate::value::as_string(value).context("invalid continent code value")?;
+        let continent =
+            ContinentCode::from_str(&s).map_err(|_| anyhow!("invalid continent code {s}"))?;
+        self.add_continent(continent, action);
+        Ok(())
+    }
+}
+
+pub(crate) fn as_continent_rule(value: &Value) -> anyhow::Result<AclContinentRule> {
+    let mut builder = AclContinentRule::new(AclAction::Forbid);
+    builder.parse(value)?;
+    Ok(builder)
+}
@@ -0,0 +1,87 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use anyhow::{anyhow, Context};
+use serde_json::Value;
+
+use g3_types::acl_set::AclClientGeoRuleSetBuilder;
+
+pub fn as_client_geo_rule_set_builder(value: &Value) -> anyhow::Result<AclClientGeoRuleSetBuilder> {
+    if let Value::Object(map) = value {
+        let mut builder = AclClientGeoRuleSetBuilder::default();
+        for (k, v) in map {
+            match crate::key::normalize(k).as_str() {
+                "asn" | "as_number" | "as_numbers" => {
+                    let rule = crate::value::acl::as_asn_rule(v)
+                        .context(format!("invalid as number acl rule value for key {k}"))?;
+                    builder.asn = Some(rule);
+                }
+                "country" | "countries" => {
+                    let rule = crate::value::acl::as_country_rule(v)
+                        .context(format!("invalid country acl rule value for key {k}"))?;
+                    builder.country = Some(rule);
+                }
+                "continent" | "continents" => {
+                    let rule = crate::value::acl::as_continent_rule(v)
+                        .context(format!("invalid continent acl rule value for key {k}"))?;
+                    builder.continent = Some(rule);
+                }
+                _ => return Err(anyhow!("invalid key {k}")),
+            }
+        }
+        Ok(builder)
+    } else {
+        Err(anyhow!("invalid value type"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use g3_geoip_types::{IpLocationBuilder, IsoCountryCode};
+    use g3_types::acl::AclAction;
+    use serde_json::json;
+    use std::str::FromStr;
+
+    #[test]
+    fn t_client_geo() {
+        let j = json!({
+            "asn": {
+                "allow": 64512
+            },
+            "country": {
+                "forbid": ["KP", "IR"],
+                "default": "allow"
+            }
+        });
+        let builder = as_client_geo_rule_set_builder(&j).unwrap();
+        let rule = builder.build();
+        assert_eq!(rule.missed_action(), AclAction::Forbid);
+
+        let mut location = IpLocationBuilder::default();
+        location.set_network(ip_network::IpNetwork::from_str("192.0.2.0/24").unwrap());
+        location.set_country(IsoCountryCode::KP);
+        location.set_as_number(64512);
+        let location = location.build().unwrap();
+        assert_eq!(rule.check(&location), (true, AclAction::Permit));
+
+        let mut location = IpLocationBuilder::default();
+        location.set_network(ip_network::IpNetwork::from_str("192.0.2.0/24").unwrap());
+        location.set_country(IsoCountryCode::IR);
+        let location = location.build().unwrap();
+        assert_eq!(rule.check(&location), (true, AclAction::Forbid));
+    }
+}
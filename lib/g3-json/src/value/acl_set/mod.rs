@@ -15,5 +15,9 @@
  */
 
 mod dst_host;
+#[cfg(feature = "geoip")]
+mod client_geo;
 
 pub use dst_host::as_dst_host_rule_set_builder;
+#[cfg(feature = "geoip")]
+pub use client_geo::as_client_geo_rule_set_builder;
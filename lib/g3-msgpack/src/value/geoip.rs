@@ -58,6 +58,16 @@ pub fn as_ip_location(value: &ValueRef) -> anyhow::Result<IpLocation> {
                         .context(format!("invalid continent code value for key {k}"))?;
                     builder.set_continent(continent);
                 }
+                "region" => {
+                    let region = crate::value::as_string(v)
+                        .context(format!("invalid string value for key {k}"))?;
+                    builder.set_region(region);
+                }
+                "city" => {
+                    let city = crate::value::as_string(v)
+                        .context(format!("invalid string value for key {k}"))?;
+                    builder.set_city(city);
+                }
                 "as_number" | "asn" => {
                     let asn = crate::value::as_u32(v)
                         .context(format!("invalid u32 value for key {k}"))?;
@@ -73,6 +83,11 @@ pub fn as_ip_location(value: &ValueRef) -> anyhow::Result<IpLocation> {
                         .context(format!("invalid string value for key {k}"))?;
                     builder.set_isp_domain(domain);
                 }
+                "hosting" => {
+                    let hosting = crate::value::as_bool(v)
+                        .context(format!("invalid bool value for key {k}"))?;
+                    builder.set_hosting(hosting);
+                }
                 _ => return Err(anyhow!("invalid key {k}")),
             }
         }
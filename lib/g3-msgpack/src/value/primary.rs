@@ -39,6 +39,27 @@ pub fn as_string(v: &ValueRef) -> anyhow::Result<String> {
     }
 }
 
+pub fn as_bool(v: &ValueRef) -> anyhow::Result<bool> {
+    match v {
+        ValueRef::Boolean(b) => Ok(*b),
+        ValueRef::String(s) => match s.as_str() {
+            Some(s) => match s.to_lowercase().as_str() {
+                "on" | "true" | "yes" | "1" => Ok(true),
+                "off" | "false" | "no" | "0" => Ok(false),
+                _ => Err(anyhow!("invalid string value for 'bool': {s}")),
+            },
+            None => Err(anyhow!("invalid utf-8 string")),
+        },
+        ValueRef::Integer(i) => match i.as_u64() {
+            Some(i) => Ok(i != 0),
+            None => Err(anyhow!("invalid unsigned integer value")),
+        },
+        _ => Err(anyhow!(
+            "msgpack value type for 'bool' should be 'boolean' / 'string' / 'integer'"
+        )),
+    }
+}
+
 pub fn as_u32(v: &ValueRef) -> anyhow::Result<u32> {
     match v {
         ValueRef::String(s) => match s.as_str() {
@@ -142,6 +163,24 @@ mod tests {
         assert!(as_string(&v).is_err());
     }
 
+    #[test]
+    fn t_bool() {
+        let v = ValueRef::Boolean(true);
+        assert!(as_bool(&v).unwrap());
+
+        let v = ValueRef::String(Utf8StringRef::from("off"));
+        assert!(!as_bool(&v).unwrap());
+
+        let v = ValueRef::Integer(Integer::from(1u32));
+        assert!(as_bool(&v).unwrap());
+
+        let v = ValueRef::String(Utf8StringRef::from("maybe"));
+        assert!(as_bool(&v).is_err());
+
+        let v = ValueRef::F32(1.0);
+        assert!(as_bool(&v).is_err());
+    }
+
     #[test]
     fn t_u32() {
         let v = ValueRef::String(Utf8StringRef::from("123"));
@@ -19,7 +19,7 @@ use std::time::Duration;
 
 use anyhow::anyhow;
 use redis::aio::MultiplexedConnection;
-use redis::{AsyncCommands, AsyncConnectionConfig, ProtocolVersion, RedisConnectionInfo};
+use redis::{AsyncConnectionConfig, ProtocolVersion, RedisConnectionInfo};
 use rustls_pki_types::ServerName;
 use tokio::io::{AsyncRead, AsyncWrite};
 use tokio_rustls::TlsConnector;
@@ -151,7 +151,7 @@ impl RedisClientConfig {
         }
     }
 
-    pub async fn connect(&self) -> anyhow::Result<impl AsyncCommands> {
+    pub async fn connect(&self) -> anyhow::Result<MultiplexedConnection> {
         let peer = self.lookup_server().await?;
         let socket = g3_socket::tcp::new_socket_to(
             peer.ip(),
@@ -18,6 +18,8 @@
 mod unix;
 #[cfg(any(target_os = "linux", target_os = "android"))]
 pub(crate) use unix::set_bind_address_no_port;
+#[cfg(target_os = "linux")]
+pub(crate) use unix::set_recv_origin_dst_addr;
 
 #[cfg(windows)]
 mod windows;
@@ -31,6 +31,27 @@ where
     Ok(())
 }
 
+#[cfg(target_os = "linux")]
+pub(crate) fn set_recv_origin_dst_addr<T: AsRawFd>(fd: &T, ipv6: bool) -> io::Result<()> {
+    unsafe {
+        setsockopt(
+            fd.as_raw_fd(),
+            libc::IPPROTO_IP,
+            libc::IP_RECVORIGDSTADDR,
+            1 as c_int,
+        )?;
+        if ipv6 {
+            setsockopt(
+                fd.as_raw_fd(),
+                libc::IPPROTO_IPV6,
+                libc::IPV6_RECVORIGDSTADDR,
+                1 as c_int,
+            )?;
+        }
+        Ok(())
+    }
+}
+
 pub(crate) fn set_bind_address_no_port<T: AsRawFd>(fd: &T, enable: bool) -> io::Result<()> {
     unsafe {
         setsockopt(
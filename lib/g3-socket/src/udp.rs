@@ -116,12 +116,92 @@ pub fn new_std_bind_listen(config: &UdpListenConfig) -> io::Result<UdpSocket> {
     if config.is_ipv6only() {
         socket.set_only_v6(true)?;
     }
+    #[cfg(target_os = "linux")]
+    if config.transparent() {
+        socket.set_ip_transparent(true)?;
+        super::sockopt::set_recv_origin_dst_addr(&socket, addr.is_ipv6())?;
+    }
     let bind_addr = SockAddr::from(addr);
     socket.bind(&bind_addr)?;
     RawSocket::from(&socket).set_udp_misc_opts(config.socket_misc_opts())?;
     Ok(UdpSocket::from(socket))
 }
 
+/// Create a socket which can be used to send reply packets to `peer` using the
+/// non-local source address `local`, which should be the original destination
+/// address of TPROXY redirected packets.
+#[cfg(target_os = "linux")]
+pub fn new_std_transparent_reply(
+    local: SocketAddr,
+    peer: SocketAddr,
+    buf_conf: SocketBufferConfig,
+    misc_opts: UdpMiscSockOpts,
+) -> io::Result<UdpSocket> {
+    let socket = new_udp_socket(AddressFamily::from(&local), buf_conf)?;
+    socket.set_reuse_address(true)?;
+    socket.set_ip_transparent(true)?;
+    RawSocket::from(&socket).set_udp_misc_opts(misc_opts)?;
+    socket.bind(&SockAddr::from(local))?;
+    socket.connect(&SockAddr::from(peer))?;
+    Ok(UdpSocket::from(socket))
+}
+
+/// Receive a packet from a transparent listen socket.
+///
+/// Return `(len, peer_addr, orig_dst_addr)`. The original destination address
+/// will be absent if the socket is not created with transparent listen config.
+#[cfg(target_os = "linux")]
+pub fn recv_with_orig_dst<T: std::os::fd::AsRawFd>(
+    socket: &T,
+    buf: &mut [u8],
+) -> io::Result<(usize, SocketAddr, Option<SocketAddr>)> {
+    use std::mem;
+
+    let mut src: libc::sockaddr_storage = unsafe { mem::zeroed() };
+    let mut iov = libc::iovec {
+        iov_base: buf.as_mut_ptr() as *mut libc::c_void,
+        iov_len: buf.len(),
+    };
+    // use u64 to make sure the control buffer is well aligned
+    let mut control = [0u64; 16];
+    let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+    msg.msg_name = &mut src as *mut _ as *mut libc::c_void;
+    msg.msg_namelen = mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+    msg.msg_controllen = mem::size_of_val(&control) as _;
+
+    let nr = unsafe { libc::recvmsg(socket.as_raw_fd(), &mut msg, 0) };
+    if nr < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let peer_addr = unsafe { SockAddr::new(src, msg.msg_namelen) }
+        .as_socket()
+        .ok_or_else(|| io::Error::other("unsupported peer address family"))?;
+
+    let mut orig_dst = None;
+    let mut cmsg = unsafe { libc::CMSG_FIRSTHDR(&msg) };
+    while !cmsg.is_null() {
+        let hdr = unsafe { &*cmsg };
+        if (hdr.cmsg_level == libc::SOL_IP && hdr.cmsg_type == libc::IP_ORIGDSTADDR)
+            || (hdr.cmsg_level == libc::SOL_IPV6 && hdr.cmsg_type == libc::IPV6_ORIGDSTADDR)
+        {
+            let data = unsafe { libc::CMSG_DATA(cmsg) };
+            let data_len = hdr.cmsg_len as usize - (data as usize - cmsg as usize);
+            let mut storage: libc::sockaddr_storage = unsafe { mem::zeroed() };
+            let copy_len = data_len.min(mem::size_of::<libc::sockaddr_storage>());
+            unsafe {
+                std::ptr::copy_nonoverlapping(data, &mut storage as *mut _ as *mut u8, copy_len);
+            }
+            orig_dst = unsafe { SockAddr::new(storage, copy_len as libc::socklen_t) }.as_socket();
+        }
+        cmsg = unsafe { libc::CMSG_NXTHDR(&msg, cmsg) };
+    }
+
+    Ok((nr as usize, peer_addr, orig_dst))
+}
+
 pub fn new_std_rebind_listen(config: &UdpListenConfig, addr: SocketAddr) -> io::Result<UdpSocket> {
     let socket = new_udp_socket(AddressFamily::from(&addr), config.socket_buffer())?;
     super::listen::set_addr_reuse(&socket, addr)?;
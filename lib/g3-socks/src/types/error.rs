@@ -36,6 +36,8 @@ pub enum SocksNegotiationError {
     InvalidAddrType,
     #[error("invalid user auth message")]
     InvalidUserAuthMsg,
+    #[error("invalid gssapi auth message")]
+    InvalidGssApiAuthMsg,
 }
 
 #[derive(Error, Debug)]
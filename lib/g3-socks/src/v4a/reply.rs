@@ -82,7 +82,7 @@ impl SocksV4Reply {
 
         let ip_bytes: [u8; 4] = buf[4..8].try_into().unwrap();
 
-        let port = ((buf[3] as u16) << 8) + (buf[4] as u16);
+        let port = u16::from_be_bytes([buf[2], buf[3]]);
         let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::from(ip_bytes)), port);
 
         Ok(SocksV4Reply::new(code, addr))
@@ -92,7 +92,11 @@ impl SocksV4Reply {
     where
         W: AsyncWrite + Unpin,
     {
-        let buf: [u8; 8] = [0, self.code(), 0, 0, 0, 0, 0, 0];
+        let mut buf: [u8; 8] = [0, self.code(), 0, 0, 0, 0, 0, 0];
+        if let SocksV4Reply::RequestGranted(SocketAddr::V4(addr)) = self {
+            buf[2..4].copy_from_slice(&addr.port().to_be_bytes());
+            buf[4..8].copy_from_slice(&addr.ip().octets());
+        }
         clt_w.write_all_flush(&buf).await?;
         Ok(())
     }
@@ -101,3 +105,69 @@ impl SocksV4Reply {
         SocksV4Reply::RequestGranted(SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::SocketAddrV4;
+
+    #[tokio::test]
+    async fn encode_granted() {
+        let addr = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(192, 0, 2, 1), 1080));
+        let mut buf = Vec::new();
+        SocksV4Reply::RequestGranted(addr)
+            .send(&mut buf)
+            .await
+            .unwrap();
+        assert_eq!(buf, [0, 90, 0x04, 0x38, 192, 0, 2, 1]);
+
+        let mut buf = Vec::new();
+        SocksV4Reply::request_granted()
+            .send(&mut buf)
+            .await
+            .unwrap();
+        assert_eq!(buf, [0, 90, 0, 0, 0, 0, 0, 0]);
+    }
+
+    #[tokio::test]
+    async fn encode_rejected() {
+        let mut buf = Vec::new();
+        SocksV4Reply::RequestRejectedOrFailed
+            .send(&mut buf)
+            .await
+            .unwrap();
+        assert_eq!(buf, [0, 91, 0, 0, 0, 0, 0, 0]);
+    }
+
+    #[tokio::test]
+    async fn decode() {
+        let data = [0u8, 90, 0x04, 0x38, 192, 0, 2, 1];
+        let reply = SocksV4Reply::recv(&mut data.as_slice()).await.unwrap();
+        let SocksV4Reply::RequestGranted(addr) = reply else {
+            panic!("unexpected reply code");
+        };
+        assert_eq!(addr, SocketAddr::from(([192, 0, 2, 1], 1080)));
+
+        let data = [0u8, 93, 0, 0, 0, 0, 0, 0];
+        let reply = SocksV4Reply::recv(&mut data.as_slice()).await.unwrap();
+        assert!(matches!(reply, SocksV4Reply::UserIdNotMatch));
+
+        let data = [4u8, 90, 0, 0, 0, 0, 0, 0];
+        assert!(SocksV4Reply::recv(&mut data.as_slice()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn round_trip() {
+        let addr = SocketAddr::from(([198, 51, 100, 7], 65534));
+        let mut buf = Vec::new();
+        SocksV4Reply::RequestGranted(addr)
+            .send(&mut buf)
+            .await
+            .unwrap();
+        let reply = SocksV4Reply::recv(&mut buf.as_slice()).await.unwrap();
+        let SocksV4Reply::RequestGranted(decoded) = reply else {
+            panic!("unexpected reply code");
+        };
+        assert_eq!(decoded, addr);
+    }
+}
@@ -143,3 +143,63 @@ impl SocksV4aRequest {
         writer.write_all_flush(buf.as_ref()).await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::SocketAddr;
+
+    use crate::v4a::SocksV4Reply;
+
+    #[tokio::test]
+    async fn bind_flow() {
+        // the version code has already been read
+        let data = [0x02u8, 0x1f, 0x90, 192, 0, 2, 10, b'u', 0x00];
+        let req = SocksV4aRequest::recv(&mut data.as_slice()).await.unwrap();
+        assert!(matches!(req.command, SocksCommand::TcpBind));
+        assert_eq!(req.upstream.to_string(), "192.0.2.10:8080");
+        assert_eq!(req.user_id, "u");
+
+        // the first reply contains the listen address, and the second one the peer address
+        let listen_addr = SocketAddr::from(([198, 51, 100, 1], 40000));
+        let peer_addr = SocketAddr::from(([192, 0, 2, 10], 20));
+        let mut buf = Vec::new();
+        SocksV4Reply::RequestGranted(listen_addr)
+            .send(&mut buf)
+            .await
+            .unwrap();
+        SocksV4Reply::RequestGranted(peer_addr)
+            .send(&mut buf)
+            .await
+            .unwrap();
+
+        let mut reader = buf.as_slice();
+        let SocksV4Reply::RequestGranted(addr) = SocksV4Reply::recv(&mut reader).await.unwrap()
+        else {
+            panic!("unexpected first reply");
+        };
+        assert_eq!(addr, listen_addr);
+        let SocksV4Reply::RequestGranted(addr) = SocksV4Reply::recv(&mut reader).await.unwrap()
+        else {
+            panic!("unexpected second reply");
+        };
+        assert_eq!(addr, peer_addr);
+        assert!(reader.is_empty());
+    }
+
+    #[tokio::test]
+    async fn bind_domain() {
+        let data = [
+            0x02u8, 0x00, 0x15, 0, 0, 0, 1, 0x00, b'f', b't', b'p', b'.', b'n', b'e', b't', 0x00,
+        ];
+        let req = SocksV4aRequest::recv(&mut data.as_slice()).await.unwrap();
+        assert!(matches!(req.command, SocksCommand::TcpBind));
+        assert_eq!(req.upstream.to_string(), "ftp.net:21");
+    }
+
+    #[tokio::test]
+    async fn reject_udp_associate() {
+        let data = [0x03u8, 0x00, 0x35, 192, 0, 2, 10, 0x00];
+        assert!(SocksV4aRequest::recv(&mut data.as_slice()).await.is_err());
+    }
+}
@@ -186,3 +186,136 @@ where
     let buf = [0x01, 0x01];
     clt_w.write_all_flush(&buf).await
 }
+
+pub const GSSAPI_MSG_TYPE_AUTH: u8 = 0x01;
+pub const GSSAPI_MSG_TYPE_PROTECTION: u8 = 0x02;
+pub const GSSAPI_MSG_TYPE_ABORT: u8 = 0xff;
+
+/// The `clear` protection level, which means no per-message protection after authentication.
+/// It is not defined in RFC 1961, but is widely supported by existing implementations.
+pub const GSSAPI_PROTECTION_LEVEL_CLEAR: u8 = 0x00;
+/// Required per-message integrity.
+pub const GSSAPI_PROTECTION_LEVEL_INTEGRITY: u8 = 0x01;
+/// Required per-message integrity and confidentiality.
+pub const GSSAPI_PROTECTION_LEVEL_CONFIDENTIALITY: u8 = 0x02;
+/// Selective per-message integrity or confidentiality based on local configurations.
+pub const GSSAPI_PROTECTION_LEVEL_SELECTIVE: u8 = 0x03;
+
+/// Select the protection level to reply for the one requested by the client.
+///
+/// Per-message integrity and confidentiality protection is not implemented, so the `clear` level,
+/// which is the one really applied, will be replied for all valid requests. The client should
+/// abort the connection if it is not acceptable. `None` will be returned for invalid levels.
+pub fn select_gssapi_protection_level(requested: u8) -> Option<u8> {
+    match requested {
+        GSSAPI_PROTECTION_LEVEL_CLEAR..=GSSAPI_PROTECTION_LEVEL_SELECTIVE => {
+            Some(GSSAPI_PROTECTION_LEVEL_CLEAR)
+        }
+        _ => None,
+    }
+}
+
+/// Receive a GSSAPI subnegotiation message as defined in RFC 1961,
+/// the message type and the token will be returned.
+pub async fn recv_gssapi_message_from_client<R>(
+    clt_r: &mut R,
+) -> Result<(u8, Vec<u8>), SocksRequestParseError>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let ver = clt_r.read_u8().await?;
+    if ver != 0x01 {
+        return Err(SocksNegotiationError::InvalidGssApiAuthMsg.into());
+    }
+
+    let mtyp = clt_r.read_u8().await?;
+    if mtyp == GSSAPI_MSG_TYPE_ABORT {
+        return Ok((mtyp, Vec::new()));
+    }
+
+    let len = clt_r.read_u16().await?;
+    let mut token = vec![0u8; len as usize];
+    clt_r.read_exact(&mut token).await?;
+    Ok((mtyp, token))
+}
+
+pub async fn send_gssapi_message_to_client<W>(
+    clt_w: &mut W,
+    mtyp: u8,
+    token: &[u8],
+) -> io::Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    let Ok(len) = u16::try_from(token.len()) else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "too large gssapi token",
+        ));
+    };
+    let mut buf = BytesMut::with_capacity(4 + token.len());
+    buf.put_u8(0x01);
+    buf.put_u8(mtyp);
+    buf.put_u16(len);
+    buf.put_slice(token);
+    clt_w.write_all_flush(buf.as_ref()).await
+}
+
+pub async fn send_gssapi_abort_to_client<W>(clt_w: &mut W) -> io::Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    let buf = [0x01, GSSAPI_MSG_TYPE_ABORT];
+    clt_w.write_all_flush(&buf).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::BufReader;
+
+    #[test]
+    fn gssapi_protection_level() {
+        assert_eq!(
+            select_gssapi_protection_level(GSSAPI_PROTECTION_LEVEL_CLEAR),
+            Some(GSSAPI_PROTECTION_LEVEL_CLEAR)
+        );
+        assert_eq!(
+            select_gssapi_protection_level(GSSAPI_PROTECTION_LEVEL_SELECTIVE),
+            Some(GSSAPI_PROTECTION_LEVEL_CLEAR)
+        );
+        assert_eq!(
+            select_gssapi_protection_level(GSSAPI_PROTECTION_LEVEL_INTEGRITY),
+            Some(GSSAPI_PROTECTION_LEVEL_CLEAR)
+        );
+        assert_eq!(
+            select_gssapi_protection_level(GSSAPI_PROTECTION_LEVEL_CONFIDENTIALITY),
+            Some(GSSAPI_PROTECTION_LEVEL_CLEAR)
+        );
+        assert_eq!(select_gssapi_protection_level(0x04), None);
+    }
+
+    #[tokio::test]
+    async fn gssapi_message() {
+        let mut buf = Vec::new();
+        send_gssapi_message_to_client(&mut buf, GSSAPI_MSG_TYPE_AUTH, b"token")
+            .await
+            .unwrap();
+        assert_eq!(buf, b"\x01\x01\x00\x05token");
+
+        let mut reader = BufReader::new(buf.as_slice());
+        let (mtyp, token) = recv_gssapi_message_from_client(&mut reader).await.unwrap();
+        assert_eq!(mtyp, GSSAPI_MSG_TYPE_AUTH);
+        assert_eq!(token, b"token");
+
+        let mut buf = Vec::new();
+        send_gssapi_abort_to_client(&mut buf).await.unwrap();
+        let mut reader = BufReader::new(buf.as_slice());
+        let (mtyp, token) = recv_gssapi_message_from_client(&mut reader).await.unwrap();
+        assert_eq!(mtyp, GSSAPI_MSG_TYPE_ABORT);
+        assert!(token.is_empty());
+
+        let mut reader = BufReader::new(&b"\x05\x01\x00\x00"[..]);
+        assert!(recv_gssapi_message_from_client(&mut reader).await.is_err());
+    }
+}
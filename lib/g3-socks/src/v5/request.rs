@@ -125,3 +125,42 @@ impl Socks5Request {
         writer.write_all_flush(buf.as_ref()).await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::v5::Socks5Reply;
+
+    #[tokio::test]
+    async fn bind_flow() {
+        let data = [0x05u8, 0x02, 0x00, 0x01, 192, 0, 2, 10, 0x00, 0x14];
+        let req = Socks5Request::recv(&mut data.as_slice()).await.unwrap();
+        assert!(matches!(req.command, SocksCommand::TcpBind));
+        assert_eq!(req.upstream.to_string(), "192.0.2.10:20");
+
+        // the first reply contains the listen address, and the second one the peer address
+        let listen_addr = SocketAddr::from(([198, 51, 100, 1], 40000));
+        let peer_addr = SocketAddr::from(([0x2001, 0xdb8, 0, 0, 0, 0, 0, 1], 20));
+        let mut buf = Vec::new();
+        Socks5Reply::Succeeded(listen_addr)
+            .send(&mut buf)
+            .await
+            .unwrap();
+        Socks5Reply::Succeeded(peer_addr)
+            .send(&mut buf)
+            .await
+            .unwrap();
+
+        let mut reader = buf.as_slice();
+        let Socks5Reply::Succeeded(addr) = Socks5Reply::recv(&mut reader).await.unwrap() else {
+            panic!("unexpected first reply");
+        };
+        assert_eq!(addr, listen_addr);
+        let Socks5Reply::Succeeded(addr) = Socks5Reply::recv(&mut reader).await.unwrap() else {
+            panic!("unexpected second reply");
+        };
+        assert_eq!(addr, peer_addr);
+        assert!(reader.is_empty());
+    }
+}
@@ -0,0 +1,49 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::{SshKnownHosts, SshPrivateKey};
+
+#[derive(Clone)]
+pub struct SshClientConfig {
+    pub username: String,
+    pub password: Option<String>,
+    pub private_key: Option<Arc<SshPrivateKey>>,
+    pub known_hosts: Arc<SshKnownHosts>,
+    /// send keepalive@openssh.com global request if nothing received in this time
+    pub keepalive_interval: Duration,
+    /// the session will be closed if this count of keepalive requests got no reply
+    pub keepalive_count_max: usize,
+    pub channel_window_size: u32,
+    pub channel_max_packet_size: u32,
+}
+
+impl SshClientConfig {
+    pub fn new(username: String, known_hosts: Arc<SshKnownHosts>) -> Self {
+        SshClientConfig {
+            username,
+            password: None,
+            private_key: None,
+            known_hosts,
+            keepalive_interval: Duration::from_secs(15),
+            keepalive_count_max: 3,
+            channel_window_size: 2 * 1024 * 1024,
+            channel_max_packet_size: 32 * 1024,
+        }
+    }
+}
@@ -0,0 +1,95 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::io;
+
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum SshError {
+    #[error("io failed: {0:?}")]
+    IoFailed(io::Error),
+    #[error("no common {0} algorithm")]
+    NoCommonAlgorithm(String),
+    #[error("invalid host key signature")]
+    InvalidHostKeySignature,
+    #[error("host key {0} is not trusted")]
+    HostKeyNotTrusted(String),
+    #[error("host key {0} has been revoked")]
+    HostKeyRevoked(String),
+    #[error("auth failed")]
+    AuthFailed,
+    #[error("disconnected by peer: {1} ({0})")]
+    Disconnected(u32, String),
+    #[error("keepalive timeout")]
+    KeepaliveTimeout,
+    #[error("protocol error: {0}")]
+    ProtocolError(russh::Error),
+}
+
+impl From<russh::Error> for SshError {
+    fn from(e: russh::Error) -> Self {
+        match e {
+            russh::Error::IO(e) => SshError::IoFailed(e),
+            russh::Error::NoCommonAlgo { kind, .. } => {
+                SshError::NoCommonAlgorithm(format!("{kind:?}").to_lowercase())
+            }
+            russh::Error::WrongServerSig => SshError::InvalidHostKeySignature,
+            russh::Error::KeepaliveTimeout | russh::Error::InactivityTimeout => {
+                SshError::KeepaliveTimeout
+            }
+            e => SshError::ProtocolError(e),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum SshChannelOpenError {
+    #[error("session closed")]
+    SessionClosed,
+    #[error("rejected: {1} ({0})")]
+    Rejected(u32, String),
+}
+
+impl From<russh::Error> for SshChannelOpenError {
+    fn from(e: russh::Error) -> Self {
+        match e {
+            russh::Error::ChannelOpenFailure(reason) => {
+                SshChannelOpenError::Rejected(reason as u32, format!("{reason:?}"))
+            }
+            _ => SshChannelOpenError::SessionClosed,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum SshKeyParseError {
+    #[error("invalid key: {0}")]
+    InvalidKey(russh::keys::Error),
+    #[error("encrypted private key is not supported")]
+    EncryptedKey,
+    #[error("invalid known hosts line {0}: {1}")]
+    InvalidKnownHostsLine(usize, &'static str),
+}
+
+impl From<russh::keys::Error> for SshKeyParseError {
+    fn from(e: russh::keys::Error) -> Self {
+        match e {
+            russh::keys::Error::KeyIsEncrypted => SshKeyParseError::EncryptedKey,
+            e => SshKeyParseError::InvalidKey(e),
+        }
+    }
+}
@@ -0,0 +1,321 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use base64::prelude::*;
+use openssl::hash::MessageDigest;
+use openssl::pkey::PKey;
+use openssl::sign::Signer;
+use russh::keys::{Algorithm, HashAlg, PublicKey};
+
+use super::HOST_KEY_ALGORITHMS;
+use crate::{SshError, SshKeyParseError};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum HostMarker {
+    None,
+    Revoked,
+    CertAuthority,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum HostPatterns {
+    Hashed { salt: Vec<u8>, hash: Vec<u8> },
+    List(Vec<(bool, String)>),
+}
+
+impl HostPatterns {
+    fn parse(s: &str) -> Option<Self> {
+        if let Some(left) = s.strip_prefix("|1|") {
+            let (salt, hash) = left.split_once('|')?;
+            let salt = BASE64_STANDARD.decode(salt).ok()?;
+            let hash = BASE64_STANDARD.decode(hash).ok()?;
+            return Some(HostPatterns::Hashed { salt, hash });
+        }
+
+        let mut patterns = Vec::new();
+        for p in s.split(',') {
+            if let Some(p) = p.strip_prefix('!') {
+                patterns.push((true, p.to_lowercase()));
+            } else {
+                patterns.push((false, p.to_lowercase()));
+            }
+        }
+        Some(HostPatterns::List(patterns))
+    }
+
+    fn matches(&self, host_id: &str) -> bool {
+        match self {
+            HostPatterns::Hashed { salt, hash } => {
+                let Ok(pkey) = PKey::hmac(salt) else {
+                    return false;
+                };
+                let Ok(mut signer) = Signer::new(MessageDigest::sha1(), &pkey) else {
+                    return false;
+                };
+                signer
+                    .sign_oneshot_to_vec(host_id.as_bytes())
+                    .map(|v| v.eq(hash))
+                    .unwrap_or(false)
+            }
+            HostPatterns::List(patterns) => {
+                let host_id = host_id.to_lowercase();
+                let mut matched = false;
+                for (negated, pattern) in patterns {
+                    if wildcard_match(pattern.as_bytes(), host_id.as_bytes()) {
+                        if *negated {
+                            return false;
+                        }
+                        matched = true;
+                    }
+                }
+                matched
+            }
+        }
+    }
+}
+
+fn wildcard_match(pattern: &[u8], s: &[u8]) -> bool {
+    let mut p = 0;
+    let mut i = 0;
+    let mut star: Option<(usize, usize)> = None;
+    while i < s.len() {
+        if p < pattern.len() && (pattern[p] == b'?' || pattern[p] == s[i]) {
+            p += 1;
+            i += 1;
+        } else if p < pattern.len() && pattern[p] == b'*' {
+            star = Some((p, i));
+            p += 1;
+        } else if let Some((sp, si)) = star {
+            p = sp + 1;
+            i = si + 1;
+            star = Some((sp, si + 1));
+        } else {
+            return false;
+        }
+    }
+    while p < pattern.len() && pattern[p] == b'*' {
+        p += 1;
+    }
+    p == pattern.len()
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct KnownHostEntry {
+    marker: HostMarker,
+    hosts: HostPatterns,
+    key: PublicKey,
+}
+
+/// The OpenSSH known_hosts file, see sshd(8) for the format
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SshKnownHosts {
+    entries: Vec<KnownHostEntry>,
+}
+
+impl SshKnownHosts {
+    pub fn parse(content: &str) -> Result<Self, SshKeyParseError> {
+        let mut entries = Vec::new();
+        for (i, line) in content.lines().enumerate() {
+            let line_number = i + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut iter = line.split_whitespace();
+            let mut field = iter.next().unwrap_or_default();
+            let marker = match field {
+                "@revoked" => HostMarker::Revoked,
+                "@cert-authority" => HostMarker::CertAuthority,
+                s if s.starts_with('@') => {
+                    return Err(SshKeyParseError::InvalidKnownHostsLine(
+                        line_number,
+                        "unknown marker",
+                    ))
+                }
+                _ => HostMarker::None,
+            };
+            if marker != HostMarker::None {
+                field = iter.next().ok_or(SshKeyParseError::InvalidKnownHostsLine(
+                    line_number,
+                    "no host patterns found",
+                ))?;
+            }
+            let hosts = HostPatterns::parse(field).ok_or(
+                SshKeyParseError::InvalidKnownHostsLine(line_number, "invalid host patterns"),
+            )?;
+            let key_type = iter.next().ok_or(SshKeyParseError::InvalidKnownHostsLine(
+                line_number,
+                "no key type found",
+            ))?;
+            let key = iter.next().ok_or(SshKeyParseError::InvalidKnownHostsLine(
+                line_number,
+                "no key found",
+            ))?;
+            let key = russh::keys::parse_public_key_base64(key).map_err(|_| {
+                SshKeyParseError::InvalidKnownHostsLine(line_number, "invalid encoded key")
+            })?;
+            if key.algorithm().as_str() != key_type {
+                return Err(SshKeyParseError::InvalidKnownHostsLine(
+                    line_number,
+                    "key type mismatch",
+                ));
+            }
+
+            entries.push(KnownHostEntry { marker, hosts, key });
+        }
+        Ok(SshKnownHosts { entries })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Get the host key algorithms we should send to the server, with the known ones first
+    pub(crate) fn host_key_algorithms(&self, host_id: &str) -> Vec<Algorithm> {
+        let mut algorithms = Vec::with_capacity(HOST_KEY_ALGORITHMS.len());
+        for entry in &self.entries {
+            if entry.marker != HostMarker::None || !entry.hosts.matches(host_id) {
+                continue;
+            }
+            let known = match entry.key.algorithm() {
+                Algorithm::Rsa { .. } => vec![
+                    Algorithm::Rsa {
+                        hash: Some(HashAlg::Sha512),
+                    },
+                    Algorithm::Rsa {
+                        hash: Some(HashAlg::Sha256),
+                    },
+                ],
+                alg if HOST_KEY_ALGORITHMS.contains(&alg) => vec![alg],
+                _ => continue,
+            };
+            for alg in known {
+                if !algorithms.contains(&alg) {
+                    algorithms.push(alg);
+                }
+            }
+        }
+        for alg in HOST_KEY_ALGORITHMS {
+            if !algorithms.contains(alg) {
+                algorithms.push(alg.clone());
+            }
+        }
+        algorithms
+    }
+
+    pub(crate) fn verify(&self, host_id: &str, key: &PublicKey) -> Result<(), SshError> {
+        let mut trusted = false;
+        for entry in &self.entries {
+            if entry.key.key_data() != key.key_data() {
+                continue;
+            }
+            match entry.marker {
+                HostMarker::Revoked => {
+                    return Err(SshError::HostKeyRevoked(super::fingerprint(key)));
+                }
+                HostMarker::CertAuthority => {}
+                HostMarker::None => {
+                    if entry.hosts.matches(host_id) {
+                        trusted = true;
+                    }
+                }
+            }
+        }
+        if trusted {
+            Ok(())
+        } else {
+            Err(SshError::HostKeyNotTrusted(super::fingerprint(key)))
+        }
+    }
+}
+
+/// Get the host name string used in known_hosts file
+pub(crate) fn host_id(host: &str, port: u16) -> String {
+    if port == 22 {
+        host.to_string()
+    } else {
+        format!("[{host}]:{port}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wildcard() {
+        assert!(wildcard_match(b"*", b"example.net"));
+        assert!(wildcard_match(b"*.example.net", b"a.example.net"));
+        assert!(!wildcard_match(b"*.example.net", b"example.net"));
+        assert!(wildcard_match(b"192.168.0.?", b"192.168.0.1"));
+        assert!(!wildcard_match(b"192.168.0.?", b"192.168.0.10"));
+        assert!(wildcard_match(b"a*b*c", b"aXbYbZc"));
+    }
+
+    #[test]
+    fn parse_and_verify() {
+        let key = "AAAAC3NzaC1lZDI1NTE5AAAAILbJZb3i1TjxzOKYWZrV2R+k71chxcOdjo9JZ4+9XyY0";
+        let content = format!(
+            "# comment line\n\
+             bastion.example.net,!bad.example.net,*.example.org ssh-ed25519 {key}\n\
+             [10.0.0.1]:2222 ssh-ed25519 {key} some comment\n"
+        );
+        let known_hosts = SshKnownHosts::parse(&content).unwrap();
+        let key = russh::keys::parse_public_key_base64(key).unwrap();
+        assert_eq!(
+            super::super::fingerprint(&key),
+            "SHA256:G12Ntz39CNGz45oP+ttAYbFZRlFIobJCBK8z7V2220c"
+        );
+
+        assert!(known_hosts
+            .verify(&host_id("bastion.example.net", 22), &key)
+            .is_ok());
+        assert!(known_hosts
+            .verify(&host_id("a.Example.org", 22), &key)
+            .is_ok());
+        assert!(known_hosts
+            .verify(&host_id("bad.example.net", 22), &key)
+            .is_err());
+        assert!(known_hosts.verify(&host_id("10.0.0.1", 2222), &key).is_ok());
+        assert!(known_hosts.verify(&host_id("10.0.0.1", 22), &key).is_err());
+
+        let algorithms = known_hosts.host_key_algorithms("bastion.example.net");
+        assert_eq!(algorithms[0], Algorithm::Ed25519);
+        assert_eq!(algorithms.len(), HOST_KEY_ALGORITHMS.len());
+    }
+
+    #[test]
+    fn revoked() {
+        let key = "AAAAC3NzaC1lZDI1NTE5AAAAILbJZb3i1TjxzOKYWZrV2R+k71chxcOdjo9JZ4+9XyY0";
+        let content = format!("* ssh-ed25519 {key}\n@revoked * ssh-ed25519 {key}\n");
+        let known_hosts = SshKnownHosts::parse(&content).unwrap();
+        let key = russh::keys::parse_public_key_base64(key).unwrap();
+        assert!(matches!(
+            known_hosts.verify("example.net", &key),
+            Err(SshError::HostKeyRevoked(_))
+        ));
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(SshKnownHosts::parse("example.net ssh-rsa").is_err());
+        assert!(SshKnownHosts::parse("@unknown example.net ssh-rsa AAAA").is_err());
+        // key type mismatch
+        let key = "AAAAC3NzaC1lZDI1NTE5AAAAILbJZb3i1TjxzOKYWZrV2R+k71chxcOdjo9JZ4+9XyY0";
+        assert!(SshKnownHosts::parse(&format!("example.net ssh-rsa {key}")).is_err());
+    }
+}
@@ -37,8 +37,24 @@ impl<'a> MimicCertBuilder<'a> {
             .public_key()
             .map_err(|e| anyhow!("failed to get key for the mimic cert: {e}"))?;
         let pkey = match pkey.id() {
-            Id::RSA => super::pkey::new_rsa(2048)?,
-            Id::EC => super::pkey::new_ec256()?,
+            Id::RSA => match pkey.bits() {
+                0..=2048 => super::pkey::new_rsa(2048)?,
+                2049..=3072 => super::pkey::new_rsa(3072)?,
+                _ => super::pkey::new_rsa(4096)?,
+            },
+            Id::EC => {
+                let ec_key = pkey
+                    .ec_key()
+                    .map_err(|e| anyhow!("failed to get ec key for the mimic cert: {e}"))?;
+                match ec_key.group().curve_name() {
+                    Some(Nid::SECP224R1) => super::pkey::new_ec224()?,
+                    Some(Nid::SECP384R1) => super::pkey::new_ec384()?,
+                    Some(Nid::SECP521R1) => super::pkey::new_ec521()?,
+                    #[cfg(not(feature = "no-sm2"))]
+                    Some(Nid::SM2) => super::pkey::new_sm2()?,
+                    _ => super::pkey::new_ec256()?,
+                }
+            }
             #[cfg(not(feature = "no-sm2"))]
             Id::SM2 => super::pkey::new_sm2()?,
             Id::ED448 => super::pkey::new_ed448()?,
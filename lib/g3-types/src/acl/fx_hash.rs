@@ -47,6 +47,11 @@ where
         self.inner.insert(node, action);
     }
 
+    #[inline]
+    pub fn missed_action(&self) -> Action {
+        self.missed_action
+    }
+
     #[inline]
     pub fn set_missed_action(&mut self, action: Action) {
         self.missed_action = action;
@@ -0,0 +1,123 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use g3_geoip_types::{ContinentCode, IsoCountryCode};
+
+use super::{AclAction, AclFxHashRule, ActionContract};
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AclAsnRule<Action = AclAction>(AclFxHashRule<u32, Action>);
+
+impl<Action: ActionContract> AclAsnRule<Action> {
+    #[inline]
+    pub fn new(missed_action: Action) -> Self {
+        AclAsnRule(AclFxHashRule::new(missed_action))
+    }
+
+    #[inline]
+    pub fn add_asn(&mut self, asn: u32, action: Action) {
+        self.0.add_node(asn, action);
+    }
+
+    #[inline]
+    pub fn missed_action(&self) -> Action {
+        self.0.missed_action()
+    }
+
+    #[inline]
+    pub fn set_missed_action(&mut self, action: Action) {
+        self.0.set_missed_action(action);
+    }
+
+    #[inline]
+    pub fn check(&self, asn: u32) -> (bool, Action) {
+        self.0.check(&asn)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AclCountryRule<Action = AclAction>(AclFxHashRule<u16, Action>);
+
+impl<Action: ActionContract> AclCountryRule<Action> {
+    #[inline]
+    pub fn new(missed_action: Action) -> Self {
+        AclCountryRule(AclFxHashRule::new(missed_action))
+    }
+
+    #[inline]
+    pub fn add_country(&mut self, country: IsoCountryCode, action: Action) {
+        self.0.add_node(country as u16, action);
+    }
+
+    #[inline]
+    pub fn missed_action(&self) -> Action {
+        self.0.missed_action()
+    }
+
+    #[inline]
+    pub fn set_missed_action(&mut self, action: Action) {
+        self.0.set_missed_action(action);
+    }
+
+    #[inline]
+    pub fn check(&self, country: IsoCountryCode) -> (bool, Action) {
+        self.0.check(&(country as u16))
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AclContinentRule<Action = AclAction>(AclFxHashRule<u8, Action>);
+
+impl<Action: ActionContract> AclContinentRule<Action> {
+    #[inline]
+    pub fn new(missed_action: Action) -> Self {
+        AclContinentRule(AclFxHashRule::new(missed_action))
+    }
+
+    #[inline]
+    pub fn add_continent(&mut self, continent: ContinentCode, action: Action) {
+        self.0.add_node(continent as u8, action);
+    }
+
+    #[inline]
+    pub fn missed_action(&self) -> Action {
+        self.0.missed_action()
+    }
+
+    #[inline]
+    pub fn set_missed_action(&mut self, action: Action) {
+        self.0.set_missed_action(action);
+    }
+
+    #[inline]
+    pub fn check(&self, continent: ContinentCode) -> (bool, Action) {
+        self.0.check(&(continent as u8))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_country() {
+        let mut rule = AclCountryRule::new(AclAction::Permit);
+        rule.add_country(IsoCountryCode::KP, AclAction::Forbid);
+
+        assert_eq!(rule.check(IsoCountryCode::KP), (true, AclAction::Forbid));
+        assert_eq!(rule.check(IsoCountryCode::US), (false, AclAction::Permit));
+    }
+}
@@ -22,6 +22,8 @@ mod child_domain;
 mod exact_host;
 mod exact_port;
 mod fx_hash;
+#[cfg(feature = "geoip")]
+mod geo;
 mod network;
 mod proxy_request;
 mod radix_trie;
@@ -35,6 +37,8 @@ use fx_hash::AclFxHashRule;
 pub use child_domain::{AclChildDomainRule, AclChildDomainRuleBuilder};
 pub use exact_host::AclExactHostRule;
 pub use exact_port::AclExactPortRule;
+#[cfg(feature = "geoip")]
+pub use geo::{AclAsnRule, AclContinentRule, AclCountryRule};
 pub use network::{AclNetworkRule, AclNetworkRuleBuilder};
 pub use proxy_request::AclProxyRequestRule;
 pub use regex_set::{AclRegexSetRule, AclRegexSetRuleBuilder};
@@ -0,0 +1,177 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use g3_geoip_types::IpLocation;
+
+use crate::acl::{AclAction, AclAsnRule, AclContinentRule, AclCountryRule};
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct AclClientGeoRuleSetBuilder {
+    pub asn: Option<AclAsnRule>,
+    pub country: Option<AclCountryRule>,
+    pub continent: Option<AclContinentRule>,
+}
+
+impl AclClientGeoRuleSetBuilder {
+    pub fn build(&self) -> AclClientGeoRuleSet {
+        let mut missed_action = AclAction::Permit;
+
+        if let Some(rule) = &self.asn {
+            missed_action = missed_action.restrict(rule.missed_action());
+        }
+        if let Some(rule) = &self.country {
+            missed_action = missed_action.restrict(rule.missed_action());
+        }
+        if let Some(rule) = &self.continent {
+            missed_action = missed_action.restrict(rule.missed_action());
+        }
+
+        AclClientGeoRuleSet {
+            asn: self.asn.clone(),
+            country: self.country.clone(),
+            continent: self.continent.clone(),
+            missed_action,
+        }
+    }
+}
+
+pub struct AclClientGeoRuleSet {
+    asn: Option<AclAsnRule>,
+    country: Option<AclCountryRule>,
+    continent: Option<AclContinentRule>,
+    missed_action: AclAction,
+}
+
+impl AclClientGeoRuleSet {
+    /// the action to take if no location info can be found for the client
+    #[inline]
+    pub fn missed_action(&self) -> AclAction {
+        self.missed_action
+    }
+
+    /// Check all of the asn, country and continent rules, the strictest action of the matched
+    /// ones will be used, so a forbid rule will always win over a permit rule.
+    pub fn check(&self, location: &IpLocation) -> (bool, AclAction) {
+        let mut matched: Option<AclAction> = None;
+        let mut add_match = |action: AclAction| {
+            matched = Some(match matched {
+                Some(prev) => prev.restrict(action),
+                None => action,
+            });
+        };
+
+        if let Some(rule) = &self.asn {
+            if let Some(asn) = location.network_asn() {
+                let (found, action) = rule.check(asn);
+                if found {
+                    add_match(action);
+                }
+            }
+        }
+
+        if let Some(rule) = &self.country {
+            if let Some(country) = location.country() {
+                let (found, action) = rule.check(country);
+                if found {
+                    add_match(action);
+                }
+            }
+        }
+
+        if let Some(rule) = &self.continent {
+            if let Some(continent) = location.continent() {
+                let (found, action) = rule.check(continent);
+                if found {
+                    add_match(action);
+                }
+            }
+        }
+
+        match matched {
+            Some(action) => (true, action),
+            None => (false, self.missed_action),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    use g3_geoip_types::{ContinentCode, IpLocationBuilder, IsoCountryCode};
+    use ip_network::IpNetwork;
+
+    fn location(asn: u32, country: IsoCountryCode) -> IpLocation {
+        let mut builder = IpLocationBuilder::default();
+        builder.set_network(IpNetwork::from_str("192.0.2.0/24").unwrap());
+        builder.set_as_number(asn);
+        builder.set_country(country);
+        builder.build().unwrap()
+    }
+
+    #[test]
+    fn deny_wins() {
+        let mut asn = AclAsnRule::new(AclAction::Forbid);
+        asn.add_asn(64500, AclAction::Permit);
+        let mut country = AclCountryRule::new(AclAction::Permit);
+        country.add_country(IsoCountryCode::KP, AclAction::Forbid);
+        let mut continent = AclContinentRule::new(AclAction::Permit);
+        continent.add_continent(ContinentCode::EU, AclAction::ForbidAndLog);
+
+        let builder = AclClientGeoRuleSetBuilder {
+            asn: Some(asn),
+            country: Some(country),
+            continent: Some(continent),
+        };
+        let rule_set = builder.build();
+        assert_eq!(rule_set.missed_action(), AclAction::Forbid);
+
+        // permitted by asn, forbidden by country
+        let loc = location(64500, IsoCountryCode::KP);
+        assert_eq!(rule_set.check(&loc), (true, AclAction::Forbid));
+
+        // permitted by asn, forbidden by continent
+        let loc = location(64500, IsoCountryCode::DE);
+        assert_eq!(rule_set.check(&loc), (true, AclAction::ForbidAndLog));
+
+        // permitted by asn only
+        let loc = location(64500, IsoCountryCode::US);
+        assert_eq!(rule_set.check(&loc), (true, AclAction::Permit));
+
+        // nothing matched
+        let loc = location(64501, IsoCountryCode::US);
+        assert_eq!(rule_set.check(&loc), (false, AclAction::Forbid));
+    }
+
+    #[test]
+    fn permit_and_log() {
+        let mut asn = AclAsnRule::new(AclAction::Forbid);
+        asn.add_asn(64500, AclAction::Permit);
+        let mut country = AclCountryRule::new(AclAction::Forbid);
+        country.add_country(IsoCountryCode::US, AclAction::PermitAndLog);
+
+        let builder = AclClientGeoRuleSetBuilder {
+            asn: Some(asn),
+            country: Some(country),
+            continent: None,
+        };
+        let rule_set = builder.build();
+
+        let loc = location(64500, IsoCountryCode::US);
+        assert_eq!(rule_set.check(&loc), (true, AclAction::PermitAndLog));
+    }
+}
@@ -15,5 +15,9 @@
  */
 
 mod dst_host;
+#[cfg(feature = "geoip")]
+mod client_geo;
 
 pub use dst_host::{AclDstHostRuleSet, AclDstHostRuleSetBuilder};
+#[cfg(feature = "geoip")]
+pub use client_geo::{AclClientGeoRuleSet, AclClientGeoRuleSetBuilder};
@@ -18,7 +18,9 @@ use std::time::Duration;
 
 use anyhow::anyhow;
 use log::warn;
-use openssl::ssl::{Ssl, SslConnector, SslContext, SslContextBuilder, SslMethod, SslVerifyMode};
+use openssl::ssl::{
+    Ssl, SslConnector, SslContext, SslContextBuilder, SslMethod, SslRef, SslVerifyMode,
+};
 use openssl::x509::store::X509StoreBuilder;
 use openssl::x509::X509;
 
@@ -26,6 +28,7 @@ use super::{
     OpensslClientSessionCache, OpensslSessionCacheConfig, DEFAULT_HANDSHAKE_TIMEOUT,
     MINIMAL_HANDSHAKE_TIMEOUT,
 };
+use crate::net::openssl::OpensslKeyLog;
 use crate::net::{TlsAlpn, TlsServerName, TlsVersion, UpstreamAddr};
 
 #[derive(Clone)]
@@ -67,6 +70,7 @@ pub struct OpensslInterceptionClientConfig {
     ssl_context_pair: ContextPair,
     #[cfg(feature = "tongsuo")]
     tlcp_context_pair: ContextPair,
+    key_log: OpensslKeyLog,
     pub insecure: bool,
     pub handshake_timeout: Duration,
 }
@@ -92,6 +96,17 @@ impl OpensslInterceptionClientConfig {
         self.tlcp_context_pair
             .build_ssl(server_name, upstream, alpn_ext)
     }
+
+    /// Start to collect the key log lines for this Ssl
+    #[inline]
+    pub fn enable_key_log(&self, ssl: &mut SslRef) {
+        self.key_log.enable(ssl);
+    }
+
+    #[inline]
+    pub fn take_key_log(&self, ssl: &SslRef) -> Vec<String> {
+        self.key_log.take(ssl)
+    }
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -292,7 +307,7 @@ impl OpensslInterceptionClientConfigBuilder {
     }
 
     #[cfg(any(feature = "aws-lc", feature = "boringssl"))]
-    fn build_ssl_context(&self) -> anyhow::Result<ContextPair> {
+    fn build_ssl_context(&self, key_log: OpensslKeyLog) -> anyhow::Result<ContextPair> {
         let mut ctx_builder = SslConnector::builder(SslMethod::tls_client())
             .map_err(|e| anyhow!("failed to create ssl context builder: {e}"))?;
 
@@ -329,6 +344,8 @@ impl OpensslInterceptionClientConfigBuilder {
 
         let session_cache = self.session_cache.set_for_client(&mut ctx_builder)?;
 
+        key_log.add_to_context(&mut ctx_builder);
+
         Ok(ContextPair {
             ssl_context: ctx_builder.build().into_context(),
             session_cache,
@@ -336,7 +353,7 @@ impl OpensslInterceptionClientConfigBuilder {
     }
 
     #[cfg(not(any(feature = "aws-lc", feature = "boringssl")))]
-    fn build_ssl_context(&self) -> anyhow::Result<ContextPair> {
+    fn build_ssl_context(&self, key_log: OpensslKeyLog) -> anyhow::Result<ContextPair> {
         use openssl::ssl::{SslCtValidationMode, StatusType};
 
         let mut ctx_builder = SslConnector::builder(SslMethod::tls_client())
@@ -372,6 +389,8 @@ impl OpensslInterceptionClientConfigBuilder {
 
         let session_cache = self.session_cache.set_for_client(&mut ctx_builder)?;
 
+        key_log.add_to_context(&mut ctx_builder);
+
         Ok(ContextPair {
             ssl_context: ctx_builder.build().into_context(),
             session_cache,
@@ -379,7 +398,7 @@ impl OpensslInterceptionClientConfigBuilder {
     }
 
     #[cfg(feature = "tongsuo")]
-    fn build_tlcp_context(&self) -> anyhow::Result<ContextPair> {
+    fn build_tlcp_context(&self, key_log: OpensslKeyLog) -> anyhow::Result<ContextPair> {
         use openssl::ssl::{SslCtValidationMode, StatusType};
 
         let mut ctx_builder = SslConnector::builder(SslMethod::ntls_client())
@@ -412,6 +431,8 @@ impl OpensslInterceptionClientConfigBuilder {
 
         let session_cache = self.session_cache.set_for_client(&mut ctx_builder)?;
 
+        key_log.add_to_context(&mut ctx_builder);
+
         Ok(ContextPair {
             ssl_context: ctx_builder.build().into_context(),
             session_cache,
@@ -419,10 +440,12 @@ impl OpensslInterceptionClientConfigBuilder {
     }
 
     pub fn build(&self) -> anyhow::Result<OpensslInterceptionClientConfig> {
+        let key_log = OpensslKeyLog::new()?;
         Ok(OpensslInterceptionClientConfig {
-            ssl_context_pair: self.build_ssl_context()?,
+            ssl_context_pair: self.build_ssl_context(key_log)?,
             #[cfg(feature = "tongsuo")]
-            tlcp_context_pair: self.build_tlcp_context()?,
+            tlcp_context_pair: self.build_tlcp_context(key_log)?,
+            key_log,
             insecure: self.insecure,
             handshake_timeout: self.handshake_timeout,
         })
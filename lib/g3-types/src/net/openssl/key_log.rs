@@ -0,0 +1,63 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::sync::Mutex;
+
+use anyhow::anyhow;
+use openssl::ex_data::Index;
+use openssl::ssl::{Ssl, SslContextBuilder, SslRef};
+
+/// Collect TLS secrets in NSS key log format for each enabled Ssl
+#[derive(Clone, Copy)]
+pub(crate) struct OpensslKeyLog {
+    index: Index<Ssl, Mutex<Vec<String>>>,
+}
+
+impl OpensslKeyLog {
+    pub(crate) fn new() -> anyhow::Result<Self> {
+        let index = Ssl::new_ex_index().map_err(|e| anyhow!("failed to create ex index: {e}"))?;
+        Ok(OpensslKeyLog { index })
+    }
+
+    #[cfg(not(feature = "aws-lc"))]
+    pub(crate) fn add_to_context(&self, builder: &mut SslContextBuilder) {
+        let index = self.index;
+        builder.set_keylog_callback(move |ssl, line| {
+            if let Some(lines) = ssl.ex_data(index) {
+                if let Ok(mut lines) = lines.lock() {
+                    lines.push(line.to_string());
+                }
+            }
+        });
+    }
+
+    #[cfg(feature = "aws-lc")]
+    pub(crate) fn add_to_context(&self, _builder: &mut SslContextBuilder) {}
+
+    pub(crate) fn enable(&self, ssl: &mut SslRef) {
+        ssl.set_ex_data(self.index, Mutex::new(Vec::new()));
+    }
+
+    pub(crate) fn take(&self, ssl: &SslRef) -> Vec<String> {
+        ssl.ex_data(self.index)
+            .and_then(|lines| {
+                lines
+                    .lock()
+                    .ok()
+                    .map(|mut lines| std::mem::take(&mut *lines))
+            })
+            .unwrap_or_default()
+    }
+}
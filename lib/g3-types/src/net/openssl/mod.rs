@@ -37,3 +37,6 @@ pub use tlcp_cert_pair::OpensslTlcpCertificatePair;
 
 mod protocol;
 pub use protocol::OpensslProtocol;
+
+mod key_log;
+use key_log::OpensslKeyLog;
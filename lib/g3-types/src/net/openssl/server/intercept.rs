@@ -24,12 +24,14 @@ use openssl::ssl::{
 };
 
 use super::{OpensslTicketKey, DEFAULT_ACCEPT_TIMEOUT, MINIMAL_ACCEPT_TIMEOUT};
+use crate::net::openssl::OpensslKeyLog;
 use crate::net::{RollingTicketer, TlsAlpn, TlsServerName};
 
 pub struct OpensslInterceptionServerConfig {
     sni_index: Index<Ssl, TlsServerName>,
     alpn_index: Index<Ssl, TlsAlpn>,
     alpn_name_index: Index<Ssl, Vec<u8>>,
+    key_log: OpensslKeyLog,
     pub ssl_context: SslContext,
     #[cfg(feature = "tongsuo")]
     pub tlcp_context: SslContext,
@@ -50,6 +52,17 @@ impl OpensslInterceptionServerConfig {
     pub fn set_selected_alpn(&self, ssl: &mut SslRef, protocol_name: Vec<u8>) {
         ssl.set_ex_data(self.alpn_name_index, protocol_name);
     }
+
+    /// Start to collect the key log lines for this Ssl
+    #[inline]
+    pub fn enable_key_log(&self, ssl: &mut SslRef) {
+        self.key_log.enable(ssl);
+    }
+
+    #[inline]
+    pub fn take_key_log(&self, ssl: &SslRef) -> Vec<String> {
+        self.key_log.take(ssl)
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -96,6 +109,7 @@ impl OpensslInterceptionServerConfigBuilder {
             Ssl::new_ex_index().map_err(|e| anyhow!("failed to create ex index: {e}"))?;
         let ticket_key_index: Index<SslContext, Arc<RollingTicketer<OpensslTicketKey>>> =
             SslContext::new_ex_index().map_err(|e| anyhow!("failed to create ex index: {e}"))?;
+        let key_log = OpensslKeyLog::new()?;
 
         macro_rules! build_ssl_context {
             ($method:expr) => {{
@@ -104,6 +118,7 @@ impl OpensslInterceptionServerConfigBuilder {
                     builder.set_ex_data(ticket_key_index, ticketer.clone());
                     super::set_ticket_key_callback(&mut builder, ticket_key_index)?;
                 }
+                key_log.add_to_context(&mut builder);
                 builder.build().into_context()
             }};
         }
@@ -116,6 +131,7 @@ impl OpensslInterceptionServerConfigBuilder {
             sni_index,
             alpn_index,
             alpn_name_index,
+            key_log,
             ssl_context,
             #[cfg(feature = "tongsuo")]
             tlcp_context,
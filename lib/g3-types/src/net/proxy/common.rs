@@ -22,7 +22,9 @@ pub enum ProxyRequestType {
     HttpsForward,
     FtpOverHttp,
     HttpConnect,
+    HttpConnectUdp,
     SocksTcpConnect,
+    SocksTcpBind,
     SocksUdpAssociate,
 }
 
@@ -35,7 +37,9 @@ impl FromStr for ProxyRequestType {
             "httpsforward" | "https_forward" => Ok(ProxyRequestType::HttpsForward),
             "ftpoverhttp" | "ftp_over_http" => Ok(ProxyRequestType::FtpOverHttp),
             "httpconnect" | "http_connect" => Ok(ProxyRequestType::HttpConnect),
+            "httpconnectudp" | "http_connect_udp" => Ok(ProxyRequestType::HttpConnectUdp),
             "sockstcpconnect" | "socks_tcp_connect" => Ok(ProxyRequestType::SocksTcpConnect),
+            "sockstcpbind" | "socks_tcp_bind" => Ok(ProxyRequestType::SocksTcpBind),
             "socksudpassociate" | "socks_udp_associate" => Ok(ProxyRequestType::SocksUdpAssociate),
             _ => Err(()),
         }
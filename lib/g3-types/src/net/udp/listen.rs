@@ -25,6 +25,8 @@ use crate::net::{SocketBufferConfig, UdpMiscSockOpts};
 pub struct UdpListenConfig {
     address: SocketAddr,
     ipv6only: bool,
+    #[cfg(target_os = "linux")]
+    transparent: bool,
     buf_conf: SocketBufferConfig,
     misc_opts: UdpMiscSockOpts,
     instance: usize,
@@ -36,6 +38,8 @@ impl Default for UdpListenConfig {
         UdpListenConfig {
             address: SocketAddr::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED), 0),
             ipv6only: false,
+            #[cfg(target_os = "linux")]
+            transparent: false,
             buf_conf: SocketBufferConfig::default(),
             misc_opts: UdpMiscSockOpts::default(),
             instance: 1,
@@ -73,6 +77,12 @@ impl UdpListenConfig {
         self.ipv6only
     }
 
+    #[cfg(target_os = "linux")]
+    #[inline]
+    pub fn transparent(&self) -> bool {
+        self.transparent
+    }
+
     #[inline]
     pub fn instance(&self) -> usize {
         self.instance.max(self.scale)
@@ -103,6 +113,12 @@ impl UdpListenConfig {
         self.ipv6only = ipv6only;
     }
 
+    #[cfg(target_os = "linux")]
+    #[inline]
+    pub fn set_transparent(&mut self) {
+        self.transparent = true;
+    }
+
     pub fn set_instance(&mut self, instance: usize) {
         if instance == 0 {
             self.instance = 1;
@@ -86,7 +86,7 @@ impl<T> HostMatch<T> {
 
                 if let Some(trie) = &self.child_domain {
                     let reversed = reverse_idna_domain(domain);
-                    if let Some(v) = trie.get(&reversed) {
+                    if let Some(v) = trie.get_ancestor_value(&reversed) {
                         return Some(v);
                     }
                 }
@@ -252,3 +252,22 @@ where
         dst
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn child_domain() {
+        let mut obj = HostMatch::default();
+        obj.add_child_domain("example.net", 1);
+        obj.add_exact_domain(Arc::from("www.example.net"), 2);
+
+        assert_eq!(obj.get(&Host::from_str("example.net").unwrap()), Some(&1));
+        assert_eq!(obj.get(&Host::from_str("a.example.net").unwrap()), Some(&1));
+        assert_eq!(obj.get(&Host::from_str("www.example.net").unwrap()), Some(&2));
+        assert!(obj.get(&Host::from_str("aexample.net").unwrap()).is_none());
+        assert!(obj.get(&Host::from_str("example.com").unwrap()).is_none());
+    }
+}
@@ -21,3 +21,9 @@ mod stream;
 pub use stream::{
     StreamDumpConfig, StreamDumper, ToClientStreamDumpWriter, ToRemoteStreamDumpWriter,
 };
+
+mod pcap;
+pub use pcap::{
+    FromClientPcapDumpReader, FromRemotePcapDumpReader, PcapDumpConfig, PcapDumpFilter, PcapDumper,
+    ToClientPcapDumpWriter, ToRemotePcapDumpWriter, PCAP_MAX_PACKET_SIZE,
+};
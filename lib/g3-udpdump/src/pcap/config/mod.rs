@@ -0,0 +1,144 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::BTreeSet;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use g3_types::net::Host;
+
+#[cfg(feature = "yaml")]
+mod yaml;
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct PcapDumpFilter {
+    users: BTreeSet<String>,
+    domains: BTreeSet<String>,
+    ips: BTreeSet<IpAddr>,
+}
+
+impl PcapDumpFilter {
+    pub fn add_user(&mut self, user: String) {
+        self.users.insert(user);
+    }
+
+    pub fn add_host(&mut self, host: Host) {
+        match host {
+            Host::Ip(ip) => {
+                self.ips.insert(ip);
+            }
+            Host::Domain(domain) => {
+                self.domains.insert(domain.to_string());
+            }
+        }
+    }
+
+    fn check_user(&self, user: Option<&str>) -> bool {
+        if self.users.is_empty() {
+            return true;
+        }
+        user.map(|u| self.users.contains(u)).unwrap_or(false)
+    }
+
+    fn check_host(&self, host: &Host) -> bool {
+        if self.domains.is_empty() && self.ips.is_empty() {
+            return true;
+        }
+        match host {
+            Host::Ip(ip) => self.ips.contains(ip),
+            Host::Domain(domain) => {
+                let mut name: &str = domain;
+                loop {
+                    if self.domains.contains(name) {
+                        return true;
+                    }
+                    match name.split_once('.') {
+                        Some((_, parent)) => name = parent,
+                        None => return false,
+                    }
+                }
+            }
+        }
+    }
+
+    /// Check if the task with the given username and upstream host should be dumped
+    pub fn check(&self, user: Option<&str>, host: &Host) -> bool {
+        self.check_user(user) && self.check_host(host)
+    }
+}
+
+/// The max packet size, limited by the 16 bit IPv4 total length field
+pub const PCAP_MAX_PACKET_SIZE: usize = u16::MAX as usize;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PcapDumpConfig {
+    pub directory: PathBuf,
+    pub file_prefix: String,
+    pub rotate_size: u64,
+    pub rotate_interval: Duration,
+    pub max_files: usize,
+    pub packet_size: usize,
+    pub queue_size: usize,
+    pub client_side: bool,
+    pub tls_key_log: bool,
+    pub filter: PcapDumpFilter,
+}
+
+impl Default for PcapDumpConfig {
+    fn default() -> Self {
+        PcapDumpConfig {
+            directory: PathBuf::new(),
+            file_prefix: "stream".to_string(),
+            rotate_size: 64 * 1024 * 1024,
+            rotate_interval: Duration::from_secs(3600),
+            max_files: 0,
+            packet_size: 1500,
+            queue_size: 4096,
+            client_side: false,
+            tls_key_log: true,
+            filter: PcapDumpFilter::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn domain(s: &str) -> Host {
+        Host::Domain(Arc::from(s))
+    }
+
+    #[test]
+    fn filter() {
+        let mut filter = PcapDumpFilter::default();
+        assert!(filter.check(None, &domain("www.example.net")));
+
+        filter.add_host(domain("example.net"));
+        filter.add_host(Host::Ip(IpAddr::from([192, 168, 1, 1])));
+        assert!(filter.check(None, &domain("example.net")));
+        assert!(filter.check(None, &domain("www.example.net")));
+        assert!(!filter.check(None, &domain("example.com")));
+        assert!(!filter.check(None, &domain("myexample.net")));
+        assert!(filter.check(None, &Host::Ip(IpAddr::from([192, 168, 1, 1]))));
+
+        filter.add_user("alice".to_string());
+        assert!(!filter.check(None, &domain("example.net")));
+        assert!(!filter.check(Some("bob"), &domain("example.net")));
+        assert!(filter.check(Some("alice"), &domain("example.net")));
+    }
+}
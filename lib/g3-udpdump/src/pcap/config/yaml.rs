@@ -0,0 +1,136 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context};
+use yaml_rust::Yaml;
+
+use super::{PcapDumpConfig, PcapDumpFilter, PCAP_MAX_PACKET_SIZE};
+
+impl PcapDumpFilter {
+    fn parse_yaml(&mut self, value: &Yaml) -> anyhow::Result<()> {
+        if let Yaml::Hash(map) = value {
+            g3_yaml::foreach_kv(map, |k, v| match g3_yaml::key::normalize(k).as_str() {
+                "user" | "users" => {
+                    let users = g3_yaml::value::as_list(v, g3_yaml::value::as_string)
+                        .context(format!("invalid username list value for key {k}"))?;
+                    users.into_iter().for_each(|u| self.add_user(u));
+                    Ok(())
+                }
+                "host" | "hosts" => {
+                    let hosts = g3_yaml::value::as_list(v, g3_yaml::value::as_host)
+                        .context(format!("invalid host list value for key {k}"))?;
+                    hosts.into_iter().for_each(|h| self.add_host(h));
+                    Ok(())
+                }
+                _ => Err(anyhow!("invalid key {k}")),
+            })
+        } else {
+            Err(anyhow!("yaml type for 'pcap dump filter' should be 'map'"))
+        }
+    }
+}
+
+impl PcapDumpConfig {
+    fn parse_directory(value: &Yaml, lookup_dir: Option<&Path>) -> anyhow::Result<PathBuf> {
+        match lookup_dir {
+            Some(dir) => g3_yaml::value::as_dir_path(value, dir, true),
+            None => g3_yaml::value::as_absolute_path(value),
+        }
+    }
+
+    pub fn parse_yaml(value: &Yaml, lookup_dir: Option<&Path>) -> anyhow::Result<Self> {
+        match value {
+            Yaml::Hash(map) => {
+                let mut config = PcapDumpConfig::default();
+
+                g3_yaml::foreach_kv(map, |k, v| match g3_yaml::key::normalize(k).as_str() {
+                    "directory" | "dir" => {
+                        config.directory = PcapDumpConfig::parse_directory(v, lookup_dir)
+                            .context(format!("invalid directory path value for key {k}"))?;
+                        Ok(())
+                    }
+                    "file_prefix" => {
+                        config.file_prefix = g3_yaml::value::as_string(v)?;
+                        Ok(())
+                    }
+                    "rotate_size" => {
+                        config.rotate_size = g3_yaml::humanize::as_u64(v)
+                            .context(format!("invalid humanize u64 value for key {k}"))?;
+                        Ok(())
+                    }
+                    "rotate_interval" => {
+                        config.rotate_interval = g3_yaml::humanize::as_duration(v)
+                            .context(format!("invalid humanize duration value for key {k}"))?;
+                        Ok(())
+                    }
+                    "max_files" => {
+                        config.max_files = g3_yaml::value::as_usize(v)?;
+                        Ok(())
+                    }
+                    "packet_size" => {
+                        let size = g3_yaml::value::as_usize(v)?;
+                        if size > PCAP_MAX_PACKET_SIZE {
+                            return Err(anyhow!(
+                                "packet size should not be larger than {PCAP_MAX_PACKET_SIZE}"
+                            ));
+                        }
+                        config.packet_size = size;
+                        Ok(())
+                    }
+                    "queue_size" => {
+                        let size = g3_yaml::value::as_usize(v)?;
+                        if size == 0 {
+                            return Err(anyhow!("queue size should not be zero"));
+                        }
+                        config.queue_size = size;
+                        Ok(())
+                    }
+                    "client_side" => {
+                        config.client_side = g3_yaml::value::as_bool(v)?;
+                        Ok(())
+                    }
+                    "tls_key_log" => {
+                        config.tls_key_log = g3_yaml::value::as_bool(v)?;
+                        Ok(())
+                    }
+                    "filter" => config
+                        .filter
+                        .parse_yaml(v)
+                        .context(format!("invalid pcap dump filter value for key {k}")),
+                    _ => Err(anyhow!("invalid key {k}")),
+                })?;
+
+                if config.directory.as_os_str().is_empty() {
+                    return Err(anyhow!("no directory set"));
+                }
+                if config.file_prefix.is_empty() {
+                    return Err(anyhow!("empty file prefix"));
+                }
+
+                Ok(config)
+            }
+            Yaml::String(_) => {
+                let config = PcapDumpConfig {
+                    directory: PcapDumpConfig::parse_directory(value, lookup_dir)?,
+                    ..Default::default()
+                };
+                Ok(config)
+            }
+            _ => Err(anyhow!("yaml type for 'pcap dump config' should be 'map'")),
+        }
+    }
+}
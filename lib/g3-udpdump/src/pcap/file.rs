@@ -0,0 +1,326 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use log::warn;
+use tokio::sync::mpsc;
+
+use super::{PcapDumpConfig, PcapRecord};
+
+const PCAPNG_BLOCK_TYPE_SHB: u32 = 0x0A0D0D0A;
+const PCAPNG_BLOCK_TYPE_IDB: u32 = 0x00000001;
+const PCAPNG_BLOCK_TYPE_EPB: u32 = 0x00000006;
+const PCAPNG_BYTE_ORDER_MAGIC: u32 = 0x1A2B3C4D;
+
+const PCAPNG_OPT_END_OF_OPT: u16 = 0;
+const PCAPNG_OPT_COMMENT: u16 = 1;
+
+const LINKTYPE_RAW: u16 = 101;
+
+const WARN_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Limit the rate of warning logs, and count the suppressed ones
+struct WarnLimiter {
+    interval: Duration,
+    last_warn: Option<Instant>,
+    suppressed: u64,
+}
+
+impl WarnLimiter {
+    fn new(interval: Duration) -> Self {
+        WarnLimiter {
+            interval,
+            last_warn: None,
+            suppressed: 0,
+        }
+    }
+
+    /// Return the count of suppressed warnings since last time if we can warn now
+    fn check(&mut self) -> Option<u64> {
+        let now = Instant::now();
+        if let Some(last) = self.last_warn {
+            if now.duration_since(last) < self.interval {
+                self.suppressed += 1;
+                return None;
+            }
+        }
+        self.last_warn = Some(now);
+        Some(std::mem::take(&mut self.suppressed))
+    }
+}
+
+struct OpenedFile {
+    writer: BufWriter<File>,
+    path: PathBuf,
+    size: u64,
+    create_time: Instant,
+}
+
+pub(super) struct PcapFileWriter {
+    directory: PathBuf,
+    file_prefix: String,
+    rotate_size: u64,
+    rotate_interval: Duration,
+    max_files: usize,
+    current: Option<OpenedFile>,
+    history: VecDeque<PathBuf>,
+    file_seq: u64,
+    dropped: Arc<AtomicU64>,
+    reported_dropped: u64,
+    write_warn: WarnLimiter,
+    drop_warn: WarnLimiter,
+}
+
+impl PcapFileWriter {
+    pub(super) fn new(config: &PcapDumpConfig, dropped: Arc<AtomicU64>) -> Self {
+        PcapFileWriter {
+            directory: config.directory.clone(),
+            file_prefix: config.file_prefix.clone(),
+            rotate_size: config.rotate_size,
+            rotate_interval: config.rotate_interval,
+            max_files: config.max_files,
+            current: None,
+            history: VecDeque::new(),
+            file_seq: 0,
+            dropped,
+            reported_dropped: 0,
+            write_warn: WarnLimiter::new(WARN_INTERVAL),
+            drop_warn: WarnLimiter::new(WARN_INTERVAL),
+        }
+    }
+
+    pub(super) fn into_running(mut self, mut receiver: mpsc::Receiver<PcapRecord>) {
+        while let Some(record) = receiver.blocking_recv() {
+            self.write_record(&record);
+            while let Ok(record) = receiver.try_recv() {
+                self.write_record(&record);
+            }
+            self.flush();
+            self.report_dropped();
+        }
+        self.flush();
+    }
+
+    fn report_dropped(&mut self) {
+        let dropped = self.dropped.load(Ordering::Relaxed);
+        if dropped > self.reported_dropped && self.drop_warn.check().is_some() {
+            warn!(
+                "{} pcap records dropped as the write queue is full",
+                dropped - self.reported_dropped
+            );
+            self.reported_dropped = dropped;
+        }
+    }
+
+    fn write_record(&mut self, record: &PcapRecord) {
+        if let Err(e) = self.do_write_record(record) {
+            let file = self.current.take();
+            let Some(suppressed) = self.write_warn.check() else {
+                return;
+            };
+            match file {
+                Some(f) => warn!(
+                    "failed to write pcap file {}: {e}, {suppressed} failures suppressed",
+                    f.path.display()
+                ),
+                None => {
+                    warn!("failed to open new pcap file: {e}, {suppressed} failures suppressed")
+                }
+            }
+        }
+    }
+
+    fn flush(&mut self) {
+        if let Some(f) = &mut self.current {
+            if let Err(e) = f.writer.flush() {
+                warn!("failed to flush pcap file {}: {e}", f.path.display());
+                self.current = None;
+            }
+        }
+    }
+
+    fn do_write_record(&mut self, record: &PcapRecord) -> io::Result<()> {
+        let need_rotate = match &self.current {
+            Some(f) => {
+                f.size >= self.rotate_size || f.create_time.elapsed() >= self.rotate_interval
+            }
+            None => true,
+        };
+        if need_rotate {
+            self.rotate()?;
+        }
+
+        let Some(f) = &mut self.current else {
+            unreachable!()
+        };
+        let block = encode_enhanced_packet_block(record);
+        f.writer.write_all(&block)?;
+        f.size += block.len() as u64;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        if let Some(mut f) = self.current.take() {
+            f.writer.flush()?;
+        }
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        self.file_seq += 1;
+        let path = self.directory.join(format!(
+            "{}-{}-{}.pcapng",
+            self.file_prefix,
+            now.as_secs(),
+            self.file_seq
+        ));
+
+        let file = File::create(&path)?;
+        let mut writer = BufWriter::new(file);
+        let mut hdr = encode_section_header_block();
+        hdr.extend_from_slice(&encode_interface_description_block());
+        writer.write_all(&hdr)?;
+
+        self.history.push_back(path.clone());
+        if self.max_files > 0 {
+            while self.history.len() > self.max_files {
+                let Some(old) = self.history.pop_front() else {
+                    break;
+                };
+                if let Err(e) = std::fs::remove_file(&old) {
+                    warn!("failed to remove old pcap file {}: {e}", old.display());
+                }
+            }
+        }
+
+        self.current = Some(OpenedFile {
+            writer,
+            path,
+            size: hdr.len() as u64,
+            create_time: Instant::now(),
+        });
+        Ok(())
+    }
+}
+
+fn push_block_end(buf: &mut Vec<u8>) {
+    // fill in the total block length both at the start and the end
+    let total_len = (buf.len() + 4) as u32;
+    buf[4..8].copy_from_slice(&total_len.to_le_bytes());
+    buf.extend_from_slice(&total_len.to_le_bytes());
+}
+
+fn push_padding(buf: &mut Vec<u8>) {
+    let pad = (4 - buf.len() % 4) % 4;
+    buf.resize(buf.len() + pad, 0);
+}
+
+fn encode_section_header_block() -> Vec<u8> {
+    let mut buf = Vec::with_capacity(28);
+    buf.extend_from_slice(&PCAPNG_BLOCK_TYPE_SHB.to_le_bytes());
+    buf.extend_from_slice(&[0u8; 4]); // block total length
+    buf.extend_from_slice(&PCAPNG_BYTE_ORDER_MAGIC.to_le_bytes());
+    buf.extend_from_slice(&1u16.to_le_bytes()); // major version
+    buf.extend_from_slice(&0u16.to_le_bytes()); // minor version
+    buf.extend_from_slice(&(-1i64).to_le_bytes()); // section length, not specified
+    push_block_end(&mut buf);
+    buf
+}
+
+fn encode_interface_description_block() -> Vec<u8> {
+    let mut buf = Vec::with_capacity(20);
+    buf.extend_from_slice(&PCAPNG_BLOCK_TYPE_IDB.to_le_bytes());
+    buf.extend_from_slice(&[0u8; 4]); // block total length
+    buf.extend_from_slice(&LINKTYPE_RAW.to_le_bytes());
+    buf.extend_from_slice(&[0u8; 2]); // reserved
+    buf.extend_from_slice(&0u32.to_le_bytes()); // snap length, no limit
+    push_block_end(&mut buf);
+    buf
+}
+
+fn encode_enhanced_packet_block(record: &PcapRecord) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(record.data.len() + 64);
+    buf.extend_from_slice(&PCAPNG_BLOCK_TYPE_EPB.to_le_bytes());
+    buf.extend_from_slice(&[0u8; 4]); // block total length
+    buf.extend_from_slice(&0u32.to_le_bytes()); // interface id
+
+    // the default timestamp resolution is microsecond
+    let ts = record
+        .time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_micros() as u64;
+    buf.extend_from_slice(&((ts >> 32) as u32).to_le_bytes());
+    buf.extend_from_slice(&(ts as u32).to_le_bytes());
+
+    let data_len = record.data.len() as u32;
+    buf.extend_from_slice(&data_len.to_le_bytes()); // captured packet length
+    buf.extend_from_slice(&data_len.to_le_bytes()); // original packet length
+    buf.extend_from_slice(&record.data);
+    push_padding(&mut buf);
+
+    if let Some(comment) = &record.comment {
+        let comment = comment.as_bytes();
+        let len = comment.len().min(u16::MAX as usize);
+        buf.extend_from_slice(&PCAPNG_OPT_COMMENT.to_le_bytes());
+        buf.extend_from_slice(&(len as u16).to_le_bytes());
+        buf.extend_from_slice(&comment[..len]);
+        push_padding(&mut buf);
+        buf.extend_from_slice(&PCAPNG_OPT_END_OF_OPT.to_le_bytes());
+        buf.extend_from_slice(&[0u8; 2]);
+    }
+
+    push_block_end(&mut buf);
+    buf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn warn_limiter() {
+        let mut limiter = WarnLimiter::new(Duration::from_secs(3600));
+        assert_eq!(limiter.check(), Some(0));
+        assert_eq!(limiter.check(), None);
+        assert_eq!(limiter.check(), None);
+
+        limiter.interval = Duration::ZERO;
+        assert_eq!(limiter.check(), Some(2));
+        assert_eq!(limiter.check(), Some(0));
+    }
+
+    #[test]
+    fn write_failure() {
+        let config = PcapDumpConfig {
+            directory: PathBuf::from("/nonexistent/g3-udpdump-test"),
+            ..Default::default()
+        };
+        let mut writer = PcapFileWriter::new(&config, Arc::new(AtomicU64::new(0)));
+        let record = PcapRecord::from(vec![0u8; 40]);
+        for _ in 0..3 {
+            writer.write_record(&record);
+        }
+        assert!(writer.current.is_none());
+        assert_eq!(writer.write_warn.suppressed, 2);
+    }
+}
@@ -0,0 +1,294 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+use super::{PcapRecord, PcapRecordSender};
+use crate::stream::PduHeader;
+
+const IPV4_HEADER_LEN: usize = 20;
+const IPV6_HEADER_LEN: usize = 40;
+const TCP_HEADER_LEN: usize = 20;
+
+const IP_PROTOCOL_TCP: u8 = 6;
+const IP_DEFAULT_TTL: u8 = 64;
+
+const TCP_FLAG_FIN: u8 = 0x01;
+const TCP_FLAG_SYN: u8 = 0x02;
+const TCP_FLAG_PSH: u8 = 0x08;
+const TCP_FLAG_ACK: u8 = 0x10;
+
+pub(super) fn new_pair(
+    client: SocketAddr,
+    remote: SocketAddr,
+    sender: PcapRecordSender,
+    comment: Option<String>,
+) -> (ToClientPcapHeader, ToRemotePcapHeader) {
+    let (client, remote) = unify_address_family(client, remote);
+    let state = Arc::new(TcpConnectionState::new(client, remote, sender));
+    state.send_handshake(comment);
+    let to_client = ToClientPcapHeader {
+        state: state.clone(),
+    };
+    let to_remote = ToRemotePcapHeader { state };
+    (to_client, to_remote)
+}
+
+/// Make sure both sides use the same ip version, as they will be put in the same packet
+fn unify_address_family(client: SocketAddr, remote: SocketAddr) -> (SocketAddr, SocketAddr) {
+    match (client.ip(), remote.ip()) {
+        (IpAddr::V4(_), IpAddr::V4(_)) | (IpAddr::V6(_), IpAddr::V6(_)) => (client, remote),
+        (IpAddr::V4(ip4), IpAddr::V6(_)) => (
+            SocketAddr::new(IpAddr::V6(ip4.to_ipv6_mapped()), client.port()),
+            remote,
+        ),
+        (IpAddr::V6(_), IpAddr::V4(ip4)) => (
+            client,
+            SocketAddr::new(IpAddr::V6(ip4.to_ipv6_mapped()), remote.port()),
+        ),
+    }
+}
+
+struct TcpConnectionState {
+    client: SocketAddr,
+    remote: SocketAddr,
+    sender: PcapRecordSender,
+    write_to_client: AtomicU32,
+    write_to_remote: AtomicU32,
+}
+
+impl TcpConnectionState {
+    fn new(client: SocketAddr, remote: SocketAddr, sender: PcapRecordSender) -> Self {
+        TcpConnectionState {
+            client,
+            remote,
+            sender,
+            write_to_client: AtomicU32::new(1),
+            write_to_remote: AtomicU32::new(1),
+        }
+    }
+
+    fn write_to_client(&self) -> u32 {
+        self.write_to_client.load(Ordering::Relaxed)
+    }
+
+    fn add_write_to_client(&self, size: usize) {
+        self.write_to_client
+            .fetch_add(size as u32, Ordering::Relaxed);
+    }
+
+    fn write_to_remote(&self) -> u32 {
+        self.write_to_remote.load(Ordering::Relaxed)
+    }
+
+    fn add_write_to_remote(&self, size: usize) {
+        self.write_to_remote
+            .fetch_add(size as u32, Ordering::Relaxed);
+    }
+
+    fn send_packet(
+        &self,
+        src: SocketAddr,
+        dst: SocketAddr,
+        seq: u32,
+        ack: u32,
+        flags: u8,
+        comment: Option<String>,
+    ) {
+        let mut pkt = new_header(src, dst, flags, 0);
+        update_header(&mut pkt, src.is_ipv4(), seq, ack, 0);
+        self.sender.send(PcapRecord::with_comment(pkt, comment));
+    }
+
+    /// Emit a synthetic three-way handshake, the initial sequence number on both sides is 0
+    fn send_handshake(&self, comment: Option<String>) {
+        self.send_packet(self.client, self.remote, 0, 0, TCP_FLAG_SYN, comment);
+        self.send_packet(
+            self.remote,
+            self.client,
+            0,
+            1,
+            TCP_FLAG_SYN | TCP_FLAG_ACK,
+            None,
+        );
+        self.send_packet(self.client, self.remote, 1, 1, TCP_FLAG_ACK, None);
+    }
+}
+
+fn new_header(src: SocketAddr, dst: SocketAddr, tcp_flags: u8, pkt_size: usize) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(pkt_size);
+
+    match (src.ip(), dst.ip()) {
+        (IpAddr::V4(src_ip), IpAddr::V4(dst_ip)) => {
+            buf.extend_from_slice(&[0x45, 0x00, 0x00, 0x00]); // version, ihl, tos, total length
+            buf.extend_from_slice(&[0x00, 0x00, 0x40, 0x00]); // id, flags DF, fragment offset
+            buf.extend_from_slice(&[IP_DEFAULT_TTL, IP_PROTOCOL_TCP, 0x00, 0x00]); // ttl, protocol, checksum
+            buf.extend_from_slice(&src_ip.octets());
+            buf.extend_from_slice(&dst_ip.octets());
+        }
+        (IpAddr::V6(src_ip), IpAddr::V6(dst_ip)) => {
+            buf.extend_from_slice(&[0x60, 0x00, 0x00, 0x00]); // version, traffic class, flow label
+            buf.extend_from_slice(&[0x00, 0x00, IP_PROTOCOL_TCP, IP_DEFAULT_TTL]); // payload length, next header, hop limit
+            buf.extend_from_slice(&src_ip.octets());
+            buf.extend_from_slice(&dst_ip.octets());
+        }
+        _ => unreachable!(),
+    }
+
+    buf.extend_from_slice(&src.port().to_be_bytes());
+    buf.extend_from_slice(&dst.port().to_be_bytes());
+    buf.extend_from_slice(&[0x00, 0x00, 0x00, 0x00]); // seq
+    buf.extend_from_slice(&[0x00, 0x00, 0x00, 0x00]); // ack
+    buf.extend_from_slice(&[0x50, tcp_flags, 0xff, 0xff]); // data offset, flags, window
+    buf.extend_from_slice(&[0x00, 0x00, 0x00, 0x00]); // checksum, urgent pointer
+
+    buf
+}
+
+fn update_header(buf: &mut [u8], ipv4: bool, seq: u32, ack: u32, data_len: usize) {
+    let tcp_offset = if ipv4 {
+        // the packet size is checked in config, so this should never overflow
+        let total_len =
+            u16::try_from(IPV4_HEADER_LEN + TCP_HEADER_LEN + data_len).unwrap_or(u16::MAX);
+        buf[2..4].copy_from_slice(&total_len.to_be_bytes());
+        buf[10..12].copy_from_slice(&[0x00, 0x00]);
+        let checksum = ipv4_header_checksum(&buf[0..IPV4_HEADER_LEN]);
+        buf[10..12].copy_from_slice(&checksum.to_be_bytes());
+        IPV4_HEADER_LEN
+    } else {
+        let payload_len = u16::try_from(TCP_HEADER_LEN + data_len).unwrap_or(u16::MAX);
+        buf[4..6].copy_from_slice(&payload_len.to_be_bytes());
+        IPV6_HEADER_LEN
+    };
+
+    buf[tcp_offset + 4..tcp_offset + 8].copy_from_slice(&seq.to_be_bytes());
+    buf[tcp_offset + 8..tcp_offset + 12].copy_from_slice(&ack.to_be_bytes());
+}
+
+fn ipv4_header_checksum(hdr: &[u8]) -> u16 {
+    let mut sum: u32 = hdr
+        .chunks_exact(2)
+        .map(|v| u16::from_be_bytes([v[0], v[1]]) as u32)
+        .sum();
+    while (sum >> 16) != 0 {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+pub struct ToClientPcapHeader {
+    state: Arc<TcpConnectionState>,
+}
+
+impl PduHeader for ToClientPcapHeader {
+    type Sender = PcapRecordSender;
+
+    fn new_header(&mut self, pkt_size: usize) -> Vec<u8> {
+        new_header(
+            self.state.remote,
+            self.state.client,
+            TCP_FLAG_PSH | TCP_FLAG_ACK,
+            pkt_size,
+        )
+    }
+
+    fn update_tcp_dissector_data(&self, hdr: &mut Vec<u8>, data_len: usize) {
+        update_header(
+            hdr,
+            self.state.remote.is_ipv4(),
+            self.state.write_to_client(),
+            self.state.write_to_remote(),
+            data_len,
+        );
+    }
+
+    fn record_written_data(&self, data_len: usize) {
+        self.state.add_write_to_client(data_len);
+    }
+}
+
+impl Drop for ToClientPcapHeader {
+    fn drop(&mut self) {
+        self.state.send_packet(
+            self.state.remote,
+            self.state.client,
+            self.state.write_to_client(),
+            self.state.write_to_remote(),
+            TCP_FLAG_FIN | TCP_FLAG_ACK,
+            None,
+        );
+    }
+}
+
+pub struct ToRemotePcapHeader {
+    state: Arc<TcpConnectionState>,
+}
+
+impl PduHeader for ToRemotePcapHeader {
+    type Sender = PcapRecordSender;
+
+    fn new_header(&mut self, pkt_size: usize) -> Vec<u8> {
+        new_header(
+            self.state.client,
+            self.state.remote,
+            TCP_FLAG_PSH | TCP_FLAG_ACK,
+            pkt_size,
+        )
+    }
+
+    fn update_tcp_dissector_data(&self, hdr: &mut Vec<u8>, data_len: usize) {
+        update_header(
+            hdr,
+            self.state.client.is_ipv4(),
+            self.state.write_to_remote(),
+            self.state.write_to_client(),
+            data_len,
+        );
+    }
+
+    fn record_written_data(&self, data_len: usize) {
+        self.state.add_write_to_remote(data_len);
+    }
+}
+
+impl Drop for ToRemotePcapHeader {
+    fn drop(&mut self) {
+        self.state.send_packet(
+            self.state.client,
+            self.state.remote,
+            self.state.write_to_remote(),
+            self.state.write_to_client(),
+            TCP_FLAG_FIN | TCP_FLAG_ACK,
+            None,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ipv4_checksum() {
+        let src = SocketAddr::from(([192, 168, 0, 1], 1234));
+        let dst = SocketAddr::from(([192, 168, 0, 199], 80));
+        let mut hdr = new_header(src, dst, TCP_FLAG_ACK, 0);
+        update_header(&mut hdr, true, 1, 1, 10);
+        assert_eq!(hdr.len(), IPV4_HEADER_LEN + TCP_HEADER_LEN);
+        assert_eq!(ipv4_header_checksum(&hdr[0..IPV4_HEADER_LEN]), 0);
+    }
+}
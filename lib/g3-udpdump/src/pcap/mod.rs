@@ -0,0 +1,154 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::io;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::mpsc;
+
+use g3_types::net::Host;
+
+use crate::stream::{StreamDumpReader, StreamDumpWriter};
+
+mod config;
+pub use config::{PcapDumpConfig, PcapDumpFilter, PCAP_MAX_PACKET_SIZE};
+
+mod record;
+use record::{PcapRecord, PcapRecordSender};
+
+mod header;
+pub use header::{ToClientPcapHeader, ToRemotePcapHeader};
+
+mod file;
+use file::PcapFileWriter;
+
+pub type ToClientPcapDumpWriter<W> = StreamDumpWriter<W, ToClientPcapHeader>;
+pub type ToRemotePcapDumpWriter<W> = StreamDumpWriter<W, ToRemotePcapHeader>;
+pub type FromClientPcapDumpReader<R> = StreamDumpReader<R, ToRemotePcapHeader>;
+pub type FromRemotePcapDumpReader<R> = StreamDumpReader<R, ToClientPcapHeader>;
+
+/// Dump decrypted streams to local pcapng files, with synthetic TCP/IP headers
+pub struct PcapDumper {
+    config: PcapDumpConfig,
+    sender: PcapRecordSender,
+    dropped: Arc<AtomicU64>,
+}
+
+impl PcapDumper {
+    pub fn new(config: PcapDumpConfig) -> io::Result<Self> {
+        if config.packet_size > PCAP_MAX_PACKET_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("packet size should not be larger than {PCAP_MAX_PACKET_SIZE}"),
+            ));
+        }
+
+        let dropped = Arc::new(AtomicU64::new(0));
+        let writer = PcapFileWriter::new(&config, dropped.clone());
+        let (sender, receiver) = mpsc::channel(config.queue_size.max(1));
+
+        std::thread::Builder::new()
+            .name("pcap-dump".to_string())
+            .spawn(move || writer.into_running(receiver))?;
+
+        let sender = PcapRecordSender::new(sender, dropped.clone());
+        Ok(PcapDumper {
+            config,
+            sender,
+            dropped,
+        })
+    }
+
+    /// Get the number of records dropped as the write queue is full
+    pub fn dropped_records(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    #[inline]
+    pub fn client_side(&self) -> bool {
+        self.config.client_side
+    }
+
+    #[inline]
+    pub fn tls_key_log(&self) -> bool {
+        self.config.tls_key_log
+    }
+
+    #[inline]
+    pub fn check_filter(&self, user: Option<&str>, host: &Host) -> bool {
+        self.config.filter.check(user, host)
+    }
+
+    /// The `comment` will be attached to the first SYN packet
+    pub fn wrap_remote_io<R, W>(
+        &self,
+        client_addr: SocketAddr,
+        remote_addr: SocketAddr,
+        comment: Option<String>,
+        remote_reader: R,
+        remote_writer: W,
+    ) -> (FromRemotePcapDumpReader<R>, ToRemotePcapDumpWriter<W>)
+    where
+        R: AsyncRead,
+        W: AsyncWrite,
+    {
+        let (to_c, to_r) = header::new_pair(client_addr, remote_addr, self.sender.clone(), comment);
+        let r = StreamDumpReader::new(
+            remote_reader,
+            to_c,
+            self.sender.clone(),
+            self.config.packet_size,
+        );
+        let w = StreamDumpWriter::new(
+            remote_writer,
+            to_r,
+            self.sender.clone(),
+            self.config.packet_size,
+        );
+        (r, w)
+    }
+
+    /// The `comment` will be attached to the first SYN packet
+    pub fn wrap_client_io<R, W>(
+        &self,
+        client_addr: SocketAddr,
+        remote_addr: SocketAddr,
+        comment: Option<String>,
+        client_reader: R,
+        client_writer: W,
+    ) -> (FromClientPcapDumpReader<R>, ToClientPcapDumpWriter<W>)
+    where
+        R: AsyncRead,
+        W: AsyncWrite,
+    {
+        let (to_c, to_r) = header::new_pair(client_addr, remote_addr, self.sender.clone(), comment);
+        let r = StreamDumpReader::new(
+            client_reader,
+            to_r,
+            self.sender.clone(),
+            self.config.packet_size,
+        );
+        let w = StreamDumpWriter::new(
+            client_writer,
+            to_c,
+            self.sender.clone(),
+            self.config.packet_size,
+        );
+        (r, w)
+    }
+}
@@ -0,0 +1,97 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use tokio::sync::mpsc;
+
+use crate::stream::PduSender;
+
+pub struct PcapRecord {
+    pub(super) time: SystemTime,
+    pub(super) data: Vec<u8>,
+    pub(super) comment: Option<String>,
+}
+
+impl PcapRecord {
+    pub(super) fn with_comment(data: Vec<u8>, comment: Option<String>) -> Self {
+        PcapRecord {
+            time: SystemTime::now(),
+            data,
+            comment,
+        }
+    }
+}
+
+impl From<Vec<u8>> for PcapRecord {
+    fn from(data: Vec<u8>) -> Self {
+        PcapRecord::with_comment(data, None)
+    }
+}
+
+/// Send records to the file writer thread, and drop them if the queue is full
+#[derive(Clone)]
+pub struct PcapRecordSender {
+    inner: mpsc::Sender<PcapRecord>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl PcapRecordSender {
+    pub(super) fn new(inner: mpsc::Sender<PcapRecord>, dropped: Arc<AtomicU64>) -> Self {
+        PcapRecordSender { inner, dropped }
+    }
+
+    pub(super) fn send(&self, record: PcapRecord) {
+        if let Err(mpsc::error::TrySendError::Full(_)) = self.inner.try_send(record) {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+impl PduSender for PcapRecordSender {
+    fn send_pdu(&self, data: Vec<u8>) {
+        self.send(PcapRecord::from(data));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drop_when_full() {
+        let (sender, mut receiver) = mpsc::channel(2);
+        let dropped = Arc::new(AtomicU64::new(0));
+        let sender = PcapRecordSender::new(sender, dropped.clone());
+
+        for i in 0..5u8 {
+            sender.send_pdu(vec![i]);
+        }
+        assert_eq!(dropped.load(Ordering::Relaxed), 3);
+        assert_eq!(receiver.try_recv().unwrap().data, vec![0]);
+        assert_eq!(receiver.try_recv().unwrap().data, vec![1]);
+        assert!(receiver.try_recv().is_err());
+
+        sender.send_pdu(vec![5]);
+        assert_eq!(dropped.load(Ordering::Relaxed), 3);
+        assert_eq!(receiver.try_recv().unwrap().data, vec![5]);
+
+        drop(receiver);
+        sender.send_pdu(vec![6]);
+        assert_eq!(dropped.load(Ordering::Relaxed), 3);
+    }
+}
@@ -18,6 +18,8 @@ use std::net::{IpAddr, SocketAddr};
 use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::Arc;
 
+use tokio::sync::mpsc;
+
 use crate::ExportedPduDissectorHint;
 
 pub(super) fn new_pair(
@@ -65,7 +67,19 @@ impl TcpDissectorState {
     }
 }
 
+pub trait PduSender {
+    fn send_pdu(&self, data: Vec<u8>);
+}
+
+impl PduSender for mpsc::UnboundedSender<Vec<u8>> {
+    fn send_pdu(&self, data: Vec<u8>) {
+        let _ = self.send(data);
+    }
+}
+
 pub trait PduHeader {
+    type Sender: PduSender + Unpin;
+
     fn new_header(&mut self, pkt_size: usize) -> Vec<u8>;
     fn update_tcp_dissector_data(&self, hdr: &mut Vec<u8>, data_len: usize);
     fn record_written_data(&self, data_len: usize);
@@ -96,6 +110,8 @@ impl ToClientPduHeader {
 }
 
 impl PduHeader for ToClientPduHeader {
+    type Sender = mpsc::UnboundedSender<Vec<u8>>;
+
     fn new_header(&mut self, pkt_size: usize) -> Vec<u8> {
         let mut hdr = new_fixed_header(
             pkt_size,
@@ -155,6 +171,8 @@ impl ToRemotePduHeader {
 }
 
 impl PduHeader for ToRemotePduHeader {
+    type Sender = mpsc::UnboundedSender<Vec<u8>>;
+
     fn new_header(&mut self, pkt_size: usize) -> Vec<u8> {
         let mut hdr = new_fixed_header(
             pkt_size,
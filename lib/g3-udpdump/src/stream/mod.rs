@@ -31,11 +31,11 @@ mod sink;
 use sink::Sinker;
 
 mod header;
-use header::PduHeader;
+pub(crate) use header::{PduHeader, PduSender};
 pub use header::{ToClientPduHeader, ToRemotePduHeader};
 
 mod state;
-use state::StreamDumpState;
+pub(crate) use state::StreamDumpState;
 
 mod write;
 pub use write::{StreamDumpWriter, ToClientStreamDumpWriter, ToRemoteStreamDumpWriter};
@@ -19,25 +19,19 @@ use std::pin::Pin;
 use std::task::{ready, Context, Poll};
 
 use tokio::io::{AsyncRead, ReadBuf};
-use tokio::sync::mpsc;
 
 use super::{PduHeader, StreamDumpState, ToClientPduHeader, ToRemotePduHeader};
 
 pub type FromClientStreamDumpReader<W> = StreamDumpReader<W, ToRemotePduHeader>;
 pub type FromRemoteStreamDumpReader<W> = StreamDumpReader<W, ToClientPduHeader>;
 
-pub struct StreamDumpReader<R, H> {
+pub struct StreamDumpReader<R, H: PduHeader> {
     reader: R,
     state: StreamDumpState<H>,
 }
 
 impl<R: AsyncRead, H: PduHeader> StreamDumpReader<R, H> {
-    pub(super) fn new(
-        reader: R,
-        header: H,
-        sender: mpsc::UnboundedSender<Vec<u8>>,
-        pkt_size: usize,
-    ) -> Self {
+    pub(crate) fn new(reader: R, header: H, sender: H::Sender, pkt_size: usize) -> Self {
         let state = StreamDumpState::new(header, sender, pkt_size);
         StreamDumpReader { reader, state }
     }
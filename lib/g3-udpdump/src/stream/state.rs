@@ -17,24 +17,18 @@
 use std::io::IoSlice;
 use std::mem;
 
-use tokio::sync::mpsc;
+use super::{PduHeader, PduSender};
 
-use super::PduHeader;
-
-pub struct StreamDumpState<H> {
+pub struct StreamDumpState<H: PduHeader> {
     header: H,
-    sender: mpsc::UnboundedSender<Vec<u8>>,
+    sender: H::Sender,
     buf: Vec<u8>,
     pkt_size: usize,
     hdr_len: usize,
 }
 
 impl<H: PduHeader> StreamDumpState<H> {
-    pub(crate) fn new(
-        mut header: H,
-        sender: mpsc::UnboundedSender<Vec<u8>>,
-        mut pkt_size: usize,
-    ) -> Self {
+    pub(crate) fn new(mut header: H, sender: H::Sender, mut pkt_size: usize) -> Self {
         pkt_size = pkt_size.max(1200);
         let buf = header.new_header(pkt_size);
         let hdr_len = buf.len();
@@ -69,7 +63,7 @@ impl<H: PduHeader> StreamDumpState<H> {
         let mut buf = mem::replace(&mut self.buf, new_buf);
         let data_len = buf.len() - self.hdr_len;
         self.header.update_tcp_dissector_data(&mut buf, data_len);
-        let _ = self.sender.send(buf);
+        self.sender.send_pdu(buf);
         self.header.record_written_data(data_len);
     }
 
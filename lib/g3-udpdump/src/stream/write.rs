@@ -19,25 +19,19 @@ use std::pin::Pin;
 use std::task::{ready, Context, Poll};
 
 use tokio::io::AsyncWrite;
-use tokio::sync::mpsc;
 
 use super::{PduHeader, StreamDumpState, ToClientPduHeader, ToRemotePduHeader};
 
 pub type ToClientStreamDumpWriter<W> = StreamDumpWriter<W, ToClientPduHeader>;
 pub type ToRemoteStreamDumpWriter<W> = StreamDumpWriter<W, ToRemotePduHeader>;
 
-pub struct StreamDumpWriter<W, H> {
+pub struct StreamDumpWriter<W, H: PduHeader> {
     writer: W,
     state: StreamDumpState<H>,
 }
 
 impl<W: AsyncWrite, H: PduHeader> StreamDumpWriter<W, H> {
-    pub(super) fn new(
-        writer: W,
-        header: H,
-        sender: mpsc::UnboundedSender<Vec<u8>>,
-        pkt_size: usize,
-    ) -> Self {
+    pub(crate) fn new(writer: W, header: H, sender: H::Sender, pkt_size: usize) -> Self {
         let state = StreamDumpState::new(header, sender, pkt_size);
         StreamDumpWriter { writer, state }
     }
@@ -0,0 +1,194 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use anyhow::anyhow;
+use yaml_rust::parser::{Event, MarkedEventReceiver, Parser};
+use yaml_rust::scanner::Marker;
+
+enum LineNode {
+    Scalar(String, usize),
+    Sequence(Vec<LineNode>, usize),
+    Mapping(Vec<(LineNode, LineNode)>, usize),
+    Alias(usize),
+}
+
+impl LineNode {
+    fn line(&self) -> usize {
+        match self {
+            LineNode::Scalar(_, line)
+            | LineNode::Sequence(_, line)
+            | LineNode::Mapping(_, line)
+            | LineNode::Alias(line) => *line,
+        }
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            LineNode::Scalar(s, _) => Some(s),
+            _ => None,
+        }
+    }
+
+    fn map_value(&self, key: &str) -> Option<&LineNode> {
+        let LineNode::Mapping(map, _) = self else {
+            return None;
+        };
+        map.iter()
+            .find(|(k, _)| {
+                k.as_str()
+                    .map(|s| crate::key::normalize(s) == key)
+                    .unwrap_or(false)
+            })
+            .map(|(_, v)| v)
+    }
+}
+
+enum PendingNode {
+    Sequence(Vec<LineNode>, usize),
+    Mapping(Vec<(LineNode, LineNode)>, Option<LineNode>, usize),
+}
+
+#[derive(Default)]
+struct LineNodeBuilder {
+    docs: Vec<LineNode>,
+    stack: Vec<PendingNode>,
+}
+
+impl LineNodeBuilder {
+    fn push_node(&mut self, node: LineNode) {
+        match self.stack.last_mut() {
+            Some(PendingNode::Sequence(seq, _)) => seq.push(node),
+            Some(PendingNode::Mapping(map, key, _)) => match key.take() {
+                Some(k) => map.push((k, node)),
+                None => *key = Some(node),
+            },
+            None => self.docs.push(node),
+        }
+    }
+}
+
+impl MarkedEventReceiver for LineNodeBuilder {
+    fn on_event(&mut self, ev: Event, mark: Marker) {
+        let line = mark.line();
+        match ev {
+            Event::Scalar(s, _, _, _) => self.push_node(LineNode::Scalar(s, line)),
+            Event::Alias(_) => self.push_node(LineNode::Alias(line)),
+            Event::SequenceStart(_, _) => self.stack.push(PendingNode::Sequence(Vec::new(), line)),
+            Event::MappingStart(_, _) => {
+                self.stack
+                    .push(PendingNode::Mapping(Vec::new(), None, line))
+            }
+            Event::SequenceEnd | Event::MappingEnd => {
+                let node = match self.stack.pop() {
+                    Some(PendingNode::Sequence(seq, line)) => LineNode::Sequence(seq, line),
+                    Some(PendingNode::Mapping(map, _, line)) => LineNode::Mapping(map, line),
+                    None => return,
+                };
+                self.push_node(node);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Find the line numbers of config objects in a yaml file.
+///
+/// All line numbers returned are 1-based, which is the same as most text editors.
+pub struct YamlLineLocator {
+    docs: Vec<LineNode>,
+}
+
+impl YamlLineLocator {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let mut conf = String::new();
+        File::open(path)?.read_to_string(&mut conf)?;
+        Self::parse(&conf).map_err(|e| anyhow!("invalid yaml file {}: {e}", path.display()))
+    }
+
+    pub fn parse(s: &str) -> anyhow::Result<Self> {
+        let mut builder = LineNodeBuilder::default();
+        Parser::new_from_str(s)
+            .load(&mut builder, true)
+            .map_err(|e| anyhow!("{e}"))?;
+        Ok(YamlLineLocator { docs: builder.docs })
+    }
+
+    /// Get the line of the root node of the doc at `index`.
+    pub fn doc_line(&self, index: usize) -> Option<usize> {
+        self.docs.get(index).map(|node| node.line())
+    }
+
+    /// Get the line of the map which has the `name` field set to `name`,
+    /// and is placed inline in the array value of the top level `key` in any doc.
+    ///
+    /// The keys will be normalized before comparison.
+    pub fn named_map_line(&self, key: &str, name: &str) -> Option<usize> {
+        self.docs.iter().find_map(|doc| {
+            let LineNode::Sequence(seq, _) = doc.map_value(key)? else {
+                return None;
+            };
+            seq.iter()
+                .find(|node| node.map_value("name").and_then(|v| v.as_str()) == Some(name))
+                .map(|node| node.line())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONF: &str = r#"
+runtime:
+  thread_number: 2
+
+server:
+  - name: http
+    escaper: default
+  - "server.d"
+
+escaper:
+  -
+    name: default
+    type: direct_fixed
+---
+user-group:
+  - name: default
+    static_users: []
+"#;
+
+    #[test]
+    fn doc_line() {
+        let locator = YamlLineLocator::parse(CONF).unwrap();
+        assert_eq!(locator.doc_line(0), Some(2));
+        assert_eq!(locator.doc_line(1), Some(15));
+        assert_eq!(locator.doc_line(2), None);
+    }
+
+    #[test]
+    fn named_map_line() {
+        let locator = YamlLineLocator::parse(CONF).unwrap();
+        assert_eq!(locator.named_map_line("server", "http"), Some(6));
+        assert_eq!(locator.named_map_line("escaper", "default"), Some(12));
+        assert_eq!(locator.named_map_line("user_group", "default"), Some(16));
+        assert_eq!(locator.named_map_line("server", "https"), None);
+        assert_eq!(locator.named_map_line("runtime", "http"), None);
+    }
+}
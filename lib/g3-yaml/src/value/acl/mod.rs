@@ -24,6 +24,8 @@ use g3_types::acl::AclAction;
 mod child_domain;
 mod exact_host;
 mod exact_port;
+#[cfg(feature = "geoip")]
+mod geo;
 mod network;
 mod proxy_request;
 mod regex_set;
@@ -31,6 +33,8 @@ mod user_agent;
 
 pub(crate) use child_domain::as_child_domain_rule_builder;
 pub(crate) use exact_host::as_exact_host_rule;
+#[cfg(feature = "geoip")]
+pub(crate) use geo::{as_asn_rule, as_continent_rule, as_country_rule};
 pub(crate) use network::as_dst_subnet_rule_builder;
 pub(crate) use regex_set::as_regex_set_rule_builder;
 
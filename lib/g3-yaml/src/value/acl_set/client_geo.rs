@@ -0,0 +1,51 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use anyhow::{anyhow, Context};
+use yaml_rust::Yaml;
+
+use g3_types::acl_set::AclClientGeoRuleSetBuilder;
+
+pub fn as_client_geo_rule_set_builder(value: &Yaml) -> anyhow::Result<AclClientGeoRuleSetBuilder> {
+    if let Yaml::Hash(map) = value {
+        let mut builder = AclClientGeoRuleSetBuilder::default();
+
+        crate::foreach_kv(map, |k, v| match crate::key::normalize(k).as_str() {
+            "asn" | "as_number" | "as_numbers" => {
+                let rule = crate::value::acl::as_asn_rule(v)
+                    .context(format!("invalid as number acl rule value for key {k}"))?;
+                builder.asn = Some(rule);
+                Ok(())
+            }
+            "country" | "countries" => {
+                let rule = crate::value::acl::as_country_rule(v)
+                    .context(format!("invalid country acl rule value for key {k}"))?;
+                builder.country = Some(rule);
+                Ok(())
+            }
+            "continent" | "continents" => {
+                let rule = crate::value::acl::as_continent_rule(v)
+                    .context(format!("invalid continent acl rule value for key {k}"))?;
+                builder.continent = Some(rule);
+                Ok(())
+            }
+            _ => Err(anyhow!("invalid key {k}")),
+        })?;
+        Ok(builder)
+    } else {
+        Err(anyhow!("invalid value type"))
+    }
+}
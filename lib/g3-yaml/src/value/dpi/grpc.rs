@@ -0,0 +1,116 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context};
+use yaml_rust::Yaml;
+
+use g3_dpi::{GrpcInterceptionConfig, GrpcMethodAcl};
+use g3_types::acl::AclAction;
+
+fn add_grpc_method_rule(
+    acl: &mut GrpcMethodAcl,
+    action: AclAction,
+    v: &Yaml,
+) -> anyhow::Result<()> {
+    let rule = crate::value::as_string(v)?;
+    if acl.add_rule(&rule, action) {
+        Ok(())
+    } else {
+        Err(anyhow!("invalid grpc method rule {rule}"))
+    }
+}
+
+fn as_grpc_method_acl(value: &Yaml) -> anyhow::Result<GrpcMethodAcl> {
+    let mut acl = GrpcMethodAcl::default();
+    match value {
+        Yaml::Hash(map) => {
+            crate::foreach_kv(map, |k, v| match crate::key::normalize(k).as_str() {
+                "default" => {
+                    let s = crate::value::as_string(v)?;
+                    let action = AclAction::from_str(&s)
+                        .map_err(|_| anyhow!("invalid AclAction value for key {k}"))?;
+                    acl.set_missed_action(action);
+                    Ok(())
+                }
+                _ => {
+                    let action = AclAction::from_str(k)
+                        .map_err(|_| anyhow!("the key {k} is not a valid AclAction"))?;
+                    if let Yaml::Array(seq) = v {
+                        for (i, v) in seq.iter().enumerate() {
+                            add_grpc_method_rule(&mut acl, action, v)
+                                .context(format!("invalid value for {k}#{i}"))?;
+                        }
+                        Ok(())
+                    } else {
+                        add_grpc_method_rule(&mut acl, action, v)
+                            .context(format!("invalid value for key {k}"))
+                    }
+                }
+            })?;
+        }
+        Yaml::Array(seq) => {
+            for (i, v) in seq.iter().enumerate() {
+                add_grpc_method_rule(&mut acl, AclAction::Forbid, v)
+                    .context(format!("invalid value for element #{i}"))?;
+            }
+        }
+        _ => add_grpc_method_rule(&mut acl, AclAction::Forbid, value)?,
+    }
+    Ok(acl)
+}
+
+pub fn as_grpc_interception_config(value: &Yaml) -> anyhow::Result<GrpcInterceptionConfig> {
+    if let Yaml::Hash(map) = value {
+        let mut config = GrpcInterceptionConfig::default();
+
+        crate::foreach_kv(map, |k, v| match crate::key::normalize(k).as_str() {
+            "method_acl" | "method_filter" => {
+                let acl = as_grpc_method_acl(v)
+                    .context(format!("invalid grpc method acl value for key {k}"))?;
+                config.method_acl = Some(acl);
+                Ok(())
+            }
+            "icap_forward_messages" => {
+                config.icap_forward_messages = crate::value::as_bool(v)?;
+                Ok(())
+            }
+            "icap_message_max_size" => {
+                config.icap_message_max_size = crate::humanize::as_usize(v)
+                    .context(format!("invalid humanize usize value for key {k}"))?;
+                Ok(())
+            }
+            "icap_message_max_count" => {
+                config.icap_message_max_count = crate::value::as_usize(v)
+                    .context(format!("invalid usize value for key {k}"))?;
+                Ok(())
+            }
+            "icap_audit_timeout" => {
+                config.icap_audit_timeout = crate::humanize::as_duration(v)
+                    .context(format!("invalid humanize duration value for key {k}"))?;
+                Ok(())
+            }
+            _ => Err(anyhow!("invalid key {k}")),
+        })?;
+
+        Ok(config)
+    } else {
+        Err(anyhow!(
+            "yaml value type for 'grpc interception config' should be 'map'"
+        ))
+    }
+}
@@ -31,3 +31,6 @@ pub use smtp::as_smtp_interception_config;
 
 mod imap;
 pub use imap::as_imap_interception_config;
+
+mod grpc;
+pub use grpc::as_grpc_interception_config;
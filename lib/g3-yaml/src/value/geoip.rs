@@ -67,6 +67,18 @@ pub fn as_ip_location(value: &Yaml) -> anyhow::Result<IpLocation> {
                 builder.set_continent(continent);
                 Ok(())
             }
+            "region" => {
+                let region = crate::value::as_string(v)
+                    .context(format!("invalid string value for key {k}"))?;
+                builder.set_region(region);
+                Ok(())
+            }
+            "city" => {
+                let city = crate::value::as_string(v)
+                    .context(format!("invalid string value for key {k}"))?;
+                builder.set_city(city);
+                Ok(())
+            }
             "as_number" | "asn" => {
                 let asn =
                     crate::value::as_u32(v).context(format!("invalid u32 value for key {k}"))?;
@@ -85,6 +97,12 @@ pub fn as_ip_location(value: &Yaml) -> anyhow::Result<IpLocation> {
                 builder.set_isp_domain(domain);
                 Ok(())
             }
+            "hosting" => {
+                let hosting =
+                    crate::value::as_bool(v).context(format!("invalid bool value for key {k}"))?;
+                builder.set_hosting(hosting);
+                Ok(())
+            }
             _ => Err(anyhow!("invalid key {k}")),
         })?;
 
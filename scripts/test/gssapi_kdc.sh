@@ -0,0 +1,76 @@
+#!/bin/sh
+
+# Run the g3-gssapi tests with a temporary local MIT KDC.
+#
+# Required tools on Debian: apt install krb5-kdc krb5-admin-server krb5-user
+
+set -e
+
+SCRIPTS_DIR=$(dirname "$0")
+PROJECT_DIR=$(realpath "${SCRIPTS_DIR}/../..")
+
+REALM="G3.TEST"
+KDC_PORT=${KDC_PORT:-18888}
+SERVICE_HOST="localhost"
+USER_NAME="g3user"
+USER_PASS="g3pass"
+
+WORK_DIR=$(mktemp -d)
+KDC_PID=""
+
+cleanup()
+{
+	[ -n "${KDC_PID}" ] && kill "${KDC_PID}" 2>/dev/null || :
+	rm -rf "${WORK_DIR}"
+}
+trap cleanup EXIT
+
+cat > "${WORK_DIR}/krb5.conf" <<EOC
+[libdefaults]
+	default_realm = ${REALM}
+	dns_lookup_kdc = false
+	dns_lookup_realm = false
+	rdns = false
+
+[realms]
+	${REALM} = {
+		kdc = 127.0.0.1:${KDC_PORT}
+	}
+EOC
+
+cat > "${WORK_DIR}/kdc.conf" <<EOC
+[kdcdefaults]
+	kdc_ports = ${KDC_PORT}
+	kdc_tcp_ports = ${KDC_PORT}
+
+[realms]
+	${REALM} = {
+		database_name = ${WORK_DIR}/principal
+		key_stash_file = ${WORK_DIR}/stash
+		acl_file = ${WORK_DIR}/kadm5.acl
+	}
+EOC
+
+touch "${WORK_DIR}/kadm5.acl"
+
+export KRB5_CONFIG="${WORK_DIR}/krb5.conf"
+export KRB5_KDC_PROFILE="${WORK_DIR}/kdc.conf"
+export KRB5CCNAME="FILE:${WORK_DIR}/ccache"
+
+kdb5_util create -s -r "${REALM}" -P "master-pass" >/dev/null
+kadmin.local -q "addprinc -pw ${USER_PASS} ${USER_NAME}" >/dev/null
+kadmin.local -q "addprinc -randkey socks/${SERVICE_HOST}" >/dev/null
+kadmin.local -q "ktadd -k ${WORK_DIR}/socks.keytab socks/${SERVICE_HOST}" >/dev/null
+
+krb5kdc -n &
+KDC_PID=$!
+sleep 1
+
+echo "${USER_PASS}" | kinit "${USER_NAME}@${REALM}" >/dev/null
+
+export G3_GSSAPI_TEST_SERVICE="socks@${SERVICE_HOST}"
+export G3_GSSAPI_TEST_KEYTAB="${WORK_DIR}/socks.keytab"
+export G3_GSSAPI_TEST_PRINCIPAL="${USER_NAME}@${REALM}"
+
+cd "${PROJECT_DIR}"
+cargo test -p g3-gssapi -- --nocapture